@@ -0,0 +1,81 @@
+/*
+ * C interface to the mazegame library.  Build a shared or static library
+ * exporting these functions with the `ffi` feature:
+ *
+ *   cargo rustc --release --lib --features ffi --crate-type cdylib
+ *   cargo rustc --release --lib --features ffi --crate-type staticlib
+ *
+ * Mazes are opaque handles created by maze_new() and released with
+ * maze_free().  Functions return negative MAZE_ERR_* codes on failure and
+ * never let a Rust panic cross into the caller.
+ */
+#ifndef MAZEGAME_H
+#define MAZEGAME_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Wall directions, as used by maze_is_wall() */
+#define MAZE_DIR_NORTH 0
+#define MAZE_DIR_SOUTH 1
+#define MAZE_DIR_EAST  2
+#define MAZE_DIR_WEST  3
+
+/* Orientation flags for the *_oriented() functions; 0 is the convention the
+ * other functions use, with (0, 0) at the top left and y counting down */
+#define MAZE_ORIENT_ORIGIN_RIGHT   1  /* x counts from the east side */
+#define MAZE_ORIENT_ORIGIN_BOTTOM  2  /* y counts from the south side */
+#define MAZE_ORIENT_Y_UP           4  /* MAZE_DIR_NORTH points toward larger y */
+#define MAZE_ORIENT_ORDER_YX       8  /* coordinates are (row, column) */
+
+/* Return codes */
+#define MAZE_OK                     0
+#define MAZE_ERR_NULL              -1  /* a required pointer was null */
+#define MAZE_ERR_OUT_OF_BOUNDS     -2  /* a coordinate was outside of the maze */
+#define MAZE_ERR_UNREACHABLE       -3  /* no path between the requested squares */
+#define MAZE_ERR_BUFFER_TOO_SMALL  -4  /* the caller's buffer can't hold the result */
+#define MAZE_ERR_INVALID_ARGUMENT  -5  /* a zero size, illegal direction, ... */
+#define MAZE_ERR_GENERATION        -6  /* maze generation failed */
+#define MAZE_ERR_PANIC             -7  /* internal error; discard the maze */
+
+typedef struct Maze Maze;
+
+typedef struct MazeCoord {
+    uint32_t x;
+    uint32_t y;
+} MazeCoord;
+
+/* Creates an uncarved maze.  Returns NULL if either dimension is zero. */
+Maze *maze_new(uint32_t width, uint32_t height);
+
+/* Generates a perfect maze from a seed, replacing the maze's contents. */
+int32_t maze_generate_perfect_seeded(Maze *maze, uint64_t seed);
+
+/* Returns 1 if the square at (x, y) has a wall in direction dir, 0 if not,
+ * or a negative error code. */
+int32_t maze_is_wall(const Maze *maze, uint32_t x, uint32_t y, uint32_t dir);
+
+/* Writes the shortest path from (sx, sy) to (gx, gy), both ends included, into
+ * buf (room for buf_len coordinates).  Returns the path length or a negative
+ * error code.  Pass buf = NULL and buf_len = 0 to query the length only. */
+int64_t maze_solve(const Maze *maze, uint32_t sx, uint32_t sy, uint32_t gx, uint32_t gy,
+                   MazeCoord *buf, size_t buf_len);
+
+/* maze_is_wall() and maze_solve() with coordinates and directions in the
+ * convention given by a combination of MAZE_ORIENT_* flags. */
+int32_t maze_is_wall_oriented(const Maze *maze, uint32_t x, uint32_t y, uint32_t dir, uint32_t orientation);
+int64_t maze_solve_oriented(const Maze *maze, uint32_t sx, uint32_t sy, uint32_t gx, uint32_t gy,
+                            MazeCoord *buf, size_t buf_len, uint32_t orientation);
+
+/* Frees a maze created by maze_new().  NULL is ignored. */
+void maze_free(Maze *maze);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MAZEGAME_H */
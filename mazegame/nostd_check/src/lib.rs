@@ -0,0 +1,63 @@
+//! A `#![no_std]` crate that exercises the core of mazegame without the
+//! standard library.  mazegame also builds as a C library (cdylib and
+//! staticlib), and those need std's panic handler and allocator, so a build
+//! without std has to ask for the Rust library alone.  If anything in the
+//! core starts depending on std, this stops building:
+//!
+//! ```text
+//! cargo rustc -p mazegame --lib --no-default-features --crate-type rlib
+//! ```
+//!
+//! The functions here run as part of the workspace tests, where features
+//! unify with mazegame's std-enabled defaults.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+extern crate mazegame;
+
+use alloc::string::String;
+
+use mazegame::constants;
+use mazegame::maze::Coord;
+use mazegame::Maze;
+
+/// Generates a seeded maze, solves it from corner to corner and returns the
+/// solution drawn over the maze, along with the solution's length.
+pub fn generate_and_solve(rows: u32, cols: u32, seed: u64) -> Option<(String, usize)> {
+    let mut maze = Maze::new(rows, cols);
+    let mut rng = mazegame::rng::from_seed(seed);
+    maze.generate_perfect_with_rng(&mut rng).ok()?;
+    maze.set_entrance(0, 0, constants::DIR_NORTH).ok()?;
+    maze.set_exit(cols - 1, rows - 1, constants::DIR_SOUTH).ok()?;
+
+    let path = maze.solve(Coord::new(0, 0), Coord::new(cols - 1, rows - 1))?;
+    Some((maze.render_ascii_with_path(&path), path.len()))
+}
+
+/// Round trips a seeded maze through the text and binary formats.
+pub fn round_trip(rows: u32, cols: u32, seed: u64) -> bool {
+    let mut maze = Maze::new(rows, cols);
+    if maze.generate_perfect_with_rng(&mut mazegame::rng::from_seed(seed)).is_err() {
+        return false;
+    }
+    let from_text = Maze::from_text(&maze.to_text());
+    let from_bytes = Maze::from_bytes(&maze.to_bytes());
+    from_text.as_ref() == Ok(&maze) && from_bytes.as_ref() == Ok(&maze)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_solve() {
+        let (render, len) = generate_and_solve(8, 12, 3).unwrap();
+        assert!(len >= 19);
+        assert_eq!(len, render.matches('.').count() - (len - 1));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert!(round_trip(6, 9, 21));
+    }
+}
@@ -0,0 +1,248 @@
+//! Timings of the main operations on mazes, enabled with the `bench`
+//! feature (and always available to this crate's own tests).
+//!
+//! run_all() times generation with each algorithm, solving, rendering and
+//! the binary format on mazes of the given sizes, repeating each operation
+//! and keeping the fastest, median and slowest times.  The results can be
+//! saved as CSV and read back, so runs from different versions can be
+//! compared:
+//!
+//! ```text
+//! operation,width,height,reps,min_ns,median_ns,max_ns
+//! solve,50,50,5,81211,83470,90112
+//! ```
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::error::MazeError;
+use crate::maze::symmetric::Symmetry;
+use crate::maze::{Coord, Maze};
+
+/// Every operation run_all() times, in the order it reports them.
+pub const OPERATIONS: [&str; 9] = [
+    "generate/growing_tree",
+    "generate/woven",
+    "generate/symmetric",
+    "generate/caves",
+    "generate/max_run",
+    "solve",
+    "render",
+    "serialize",
+    "deserialize",
+];
+
+/// The header line of the CSV format.
+pub const CSV_HEADER: &str = "operation,width,height,reps,min_ns,median_ns,max_ns";
+
+/// The cave settings timed by `generate/caves`.
+const CAVE_FILL_PROB: f32 = 0.45;
+const CAVE_SMOOTHING_PASSES: u32 = 4;
+/// The longest straight run allowed by `generate/max_run`.
+const MAX_RUN_LENGTH: u32 = 3;
+
+/// The timings of one operation on mazes of one size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchResult {
+    /// One of OPERATIONS.
+    pub operation: String,
+    pub width: u32,
+    pub height: u32,
+    /// How many times the operation ran.
+    pub reps: u32,
+    pub min: Duration,
+    /// The middle time, or the mean of the middle two for an even number of
+    /// reps.
+    pub median: Duration,
+    pub max: Duration,
+}
+
+/// Times every operation in OPERATIONS reps times on mazes of each size,
+/// given as (width, height).  Rep n uses seed n, so every run times the
+/// same mazes.  Solving, rendering and the binary format are timed on
+/// perfect mazes, solving from the top left corner to the bottom right.
+///
+/// An operation that fails on a size - a maze too small for an algorithm,
+/// say - gets no result for that size, and nothing is timed if reps is 0.
+///
+/// # Example
+/// ```
+/// let results = mazegame::bench::run_all(&[(8, 6)], 2);
+/// assert_eq!(mazegame::bench::OPERATIONS.len(), results.len());
+/// assert!(results.iter().all(|r| r.min <= r.median && r.median <= r.max));
+/// ```
+pub fn run_all(sizes: &[(u32, u32)], reps: u32) -> Vec<BenchResult> {
+    type Generate = fn(&mut Maze, u64) -> Result<(), String>;
+    let generators: [(&str, Generate); 5] = [
+        (OPERATIONS[0], |maze, seed| maze.generate_perfect_seeded(seed)),
+        (OPERATIONS[1], |maze, seed| maze.generate_perfect_woven_seeded(seed)),
+        (OPERATIONS[2], |maze, seed| maze.generate_symmetric_seeded(Symmetry::MirrorX, seed)),
+        (OPERATIONS[3], |maze, seed| maze.generate_caves(CAVE_FILL_PROB, CAVE_SMOOTHING_PASSES, seed)),
+        (OPERATIONS[4], |maze, seed| maze.generate_perfect_max_run_seeded(MAX_RUN_LENGTH, seed)),
+    ];
+
+    let mut results = Vec::new();
+    for &(width, height) in sizes.iter() {
+        let mut push = |operation: &str, times: Option<(Duration, Duration, Duration)>| {
+            if let Some((min, median, max)) = times {
+                results.push(BenchResult { operation: operation.to_string(), width, height, reps, min, median, max });
+            }
+        };
+
+        for &(operation, generate) in generators.iter() {
+            push(operation, measure(reps, |seed| {
+                let mut maze = Maze::new(height, width);
+                let start = Instant::now();
+                generate(&mut maze, seed).ok()?;
+                Some(start.elapsed())
+            }));
+        }
+
+        let mazes: Option<Vec<Maze>> = (0..reps as u64)
+            .map(|seed| {
+                let mut maze = Maze::new(height, width);
+                maze.generate_perfect_seeded(seed).ok().map(|_| maze)
+            })
+            .collect();
+        let mazes = match mazes {
+            Some(mazes) => mazes,
+            None => continue,
+        };
+        let (from, to) = (Coord::new(0, 0), Coord::new(width - 1, height - 1));
+        push(OPERATIONS[5], measure(reps, |seed| {
+            let start = Instant::now();
+            black_box(mazes[seed as usize].solve(from, to))?;
+            Some(start.elapsed())
+        }));
+        push(OPERATIONS[6], measure(reps, |seed| {
+            let start = Instant::now();
+            black_box(mazes[seed as usize].render_ascii());
+            Some(start.elapsed())
+        }));
+        push(OPERATIONS[7], measure(reps, |seed| {
+            let start = Instant::now();
+            black_box(mazes[seed as usize].to_bytes());
+            Some(start.elapsed())
+        }));
+        let saved: Vec<Vec<u8>> = mazes.iter().map(|maze| maze.to_bytes()).collect();
+        push(OPERATIONS[8], measure(reps, |seed| {
+            let start = Instant::now();
+            black_box(Maze::from_bytes(&saved[seed as usize])).ok()?;
+            Some(start.elapsed())
+        }));
+    }
+    results
+}
+
+/// Writes results in the CSV format, with times in nanoseconds.
+pub fn to_csv(results: &[BenchResult]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for r in results.iter() {
+        out.push_str(&format!("{},{},{},{},{},{},{}\n", r.operation, r.width, r.height, r.reps,
+                              r.min.as_nanos(), r.median.as_nanos(), r.max.as_nanos()));
+    }
+    out
+}
+
+/// Reads results written by to_csv().
+///
+/// # Example
+/// ```
+/// use mazegame::bench;
+///
+/// let results = bench::run_all(&[(4, 4)], 1);
+/// assert_eq!(results, bench::from_csv(&bench::to_csv(&results)).unwrap());
+/// assert!(bench::from_csv("solve,4,4").is_err());
+/// ```
+pub fn from_csv(text: &str) -> Result<Vec<BenchResult>, MazeError> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    if lines.next().map(|line| line.trim()) != Some(CSV_HEADER) {
+        return Err(MazeError::Parse(format!("benchmark results must start with '{}'", CSV_HEADER)));
+    }
+    lines.map(|line| {
+        let bad = || MazeError::Parse(format!("'{}' isn't a line of benchmark results", line));
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        if fields.len() != 7 || fields[0].is_empty() {
+            return Err(bad());
+        }
+        let number = |i: usize| fields[i].parse::<u32>().map_err(|_| bad());
+        let time = |i: usize| fields[i].parse::<u64>().map(Duration::from_nanos).map_err(|_| bad());
+        Ok(BenchResult {
+            operation: fields[0].to_string(),
+            width: number(1)?,
+            height: number(2)?,
+            reps: number(3)?,
+            min: time(4)?,
+            median: time(5)?,
+            max: time(6)?,
+        })
+    }).collect()
+}
+
+//
+// Internal - runs op once for each rep, passing the rep number as the seed,
+// and returns the fastest, median and slowest times.  None if there were no
+// reps or op failed.
+//
+fn measure<F: FnMut(u64) -> Option<Duration>>(reps: u32, mut op: F) -> Option<(Duration, Duration, Duration)> {
+    let mut times = (0..reps as u64).map(&mut op).collect::<Option<Vec<Duration>>>()?;
+    if times.is_empty() {
+        return None;
+    }
+    times.sort();
+    let mid = times.len() / 2;
+    let median = if times.len().is_multiple_of(2) { (times[mid - 1] + times[mid]) / 2 } else { times[mid] };
+    Some((times[0], median, times[times.len() - 1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_operation() {
+        let sizes = [(4, 4), (6, 3)];
+        let results = run_all(&sizes, 1);
+        assert_eq!(OPERATIONS.len() * sizes.len(), results.len());
+        for (i, r) in results.iter().enumerate() {
+            assert_eq!(OPERATIONS[i % OPERATIONS.len()], r.operation);
+            assert_eq!(sizes[i / OPERATIONS.len()], (r.width, r.height));
+            assert_eq!(1, r.reps);
+            assert!(r.min > Duration::from_nanos(0), "{:?}", r);
+            assert!(r.min == r.median && r.median == r.max);
+        }
+
+        let parsed = from_csv(&to_csv(&results)).unwrap();
+        assert_eq!(results, parsed);
+    }
+
+    #[test]
+    fn test_failures() {
+        // Too small for a perfect maze, so there's nothing to solve either
+        let results = run_all(&[(1, 1)], 1);
+        for operation in [OPERATIONS[0], "solve", "render", "serialize", "deserialize"].iter() {
+            assert!(results.iter().all(|r| r.operation != *operation));
+        }
+        assert!(run_all(&[(4, 4)], 0).is_empty());
+
+        assert!(from_csv("").is_err());
+        assert_eq!(Ok(Vec::new()), from_csv(CSV_HEADER));
+        let line = format!("{}\nsolve,4,4,1,10,20,x\n", CSV_HEADER);
+        assert_eq!(Err(MazeError::Parse("'solve,4,4,1,10,20,x' isn't a line of benchmark results".to_string())),
+                   from_csv(&line));
+    }
+
+    #[test]
+    fn test_median() {
+        let times = [5, 1, 3, 9];
+        let mut i = 0;
+        let measured = measure(4, |_| {
+            i += 1;
+            Some(Duration::from_nanos(times[i - 1]))
+        });
+        let ns = Duration::from_nanos;
+        assert_eq!(Some((ns(1), ns(4), ns(9))), measured);
+        assert_eq!(Some((ns(1), ns(3), ns(5))), measure(3, |seed| Some(ns(seed * 2 + 1))));
+        assert_eq!(None, measure(3, |seed| if seed == 1 { None } else { Some(ns(1)) }));
+    }
+}
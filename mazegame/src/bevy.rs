@@ -0,0 +1,288 @@
+//! Bevy integration, enabled with the `bevy` feature.
+//!
+//! Add a MazePlugin to an App and insert a MazeBundle resource.  The plugin
+//! spawns one entity per cell of the maze's WallGrid - either a wall segment
+//! or a floor cell - each carrying a MazeCell and a Transform.  Replacing the
+//! resource sends a MazeChanged event, which despawns the old entities and
+//! spawns a fresh set.
+//!
+//! The plugin also keeps a MessageBox resource, for hints like the
+//! tutorial's: push messages onto it, draw its current() message however
+//! the game likes, and send DismissMessage to move on to the next.
+use std::collections::VecDeque;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_transform::components::Transform;
+
+use crate::maze::{Coord, Maze};
+
+/// What a spawned maze entity represents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CellKind {
+    /// A wall segment, including the posts where walls meet.
+    Wall,
+    /// Floor that isn't part of a room.
+    Corridor,
+    /// Floor inside a room.
+    Room,
+}
+
+/// Attached to every entity spawned for the maze.  `coord` is the cell's
+/// position on the WallGrid, so Square (x, y) is at (2x + 1, 2y + 1).
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MazeCell {
+    pub coord: Coord,
+    pub kind: CellKind,
+}
+
+/// The maze to display.  Inserting or replacing this resource rebuilds the
+/// maze's entities.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct MazeBundle {
+    pub maze: Maze,
+}
+
+/// Layout settings used when placing maze entities.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct MazeSettings {
+    /// The distance between neighboring WallGrid cells.
+    pub cell_size: f32,
+}
+
+/// Sent when the maze's entities need rebuilding.  The plugin sends it
+/// whenever MazeBundle changes; send it yourself after removing the resource
+/// to clear the maze away.
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq)]
+pub struct MazeChanged;
+
+/// Messages waiting to be shown to the player, oldest first.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct MessageBox {
+    pub messages: VecDeque<String>,
+}
+
+impl MessageBox {
+    /// Queues text to be shown after the messages already waiting.
+    pub fn push(&mut self, text: &str) {
+        self.messages.push_back(text.to_string());
+    }
+
+    /// Returns the message to show now, if there is one.
+    pub fn current(&self) -> Option<&str> {
+        self.messages.front().map(|text| text.as_str())
+    }
+}
+
+/// Send to dismiss the MessageBox's current message.
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq)]
+pub struct DismissMessage;
+
+/// Spawns and maintains the entities for the MazeBundle resource.
+///
+/// Cell (x, y) of the WallGrid is placed at (x * cell_size, -y * cell_size),
+/// so the maze's top left corner sits at the origin and rows run down the
+/// screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MazePlugin {
+    pub cell_size: f32,
+}
+
+impl Default for MazePlugin {
+    fn default() -> MazePlugin {
+        MazePlugin { cell_size: 1.0 }
+    }
+}
+
+impl Plugin for MazePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MazeSettings { cell_size: self.cell_size })
+           .init_resource::<MessageBox>()
+           .add_event::<MazeChanged>()
+           .add_event::<DismissMessage>()
+           .add_systems(Update, ((detect_maze_change, respawn_maze).chain(), dismiss_message));
+    }
+}
+
+/// Returns the cells the plugin spawns for a maze, in row-major WallGrid order.
+///
+/// # Example
+/// ```
+/// use mazegame::bevy::{maze_cells, CellKind};
+///
+/// let maze = mazegame::Maze::new(1, 1);
+/// let cells = maze_cells(&maze);
+/// assert_eq!(9, cells.len());
+/// assert_eq!(CellKind::Corridor, cells[4].kind);
+/// ```
+pub fn maze_cells(maze: &Maze) -> Vec<MazeCell> {
+    let grid = maze.to_wall_grid();
+    let mut cells = Vec::with_capacity(grid.walls.len());
+    for gy in 0..grid.height {
+        for gx in 0..grid.width {
+            let kind = if grid.is_wall(gx, gy) { CellKind::Wall } else { floor_kind(maze, gx, gy) };
+            cells.push(MazeCell { coord: Coord::new(gx, gy), kind });
+        }
+    }
+    cells
+}
+
+//
+// Internal - classifies an open WallGrid cell.  It's part of a room only when
+// every Square it touches belongs to the same room.
+//
+fn floor_kind(maze: &Maze, gx: u32, gy: u32) -> CellKind {
+    // Odd grid positions are Squares; even ones sit between two of them
+    let span = |g: u32, len: u32| -> Vec<u32> {
+        if g % 2 == 1 {
+            vec![g / 2]
+        } else {
+            (g.saturating_sub(1) / 2..(g / 2 + 1).min(len)).collect()
+        }
+    };
+    let mut room = None;
+    for y in span(gy, maze.get_rows()) {
+        for x in span(gx, maze.get_cols()) {
+            let sq = match maze.index_of(x, y) {
+                Some(offset) => &maze.sq[offset],
+                None => return CellKind::Corridor,
+            };
+            if !sq.is_part_of_room() || room.is_some_and(|id| id != sq.id) {
+                return CellKind::Corridor;
+            }
+            room = Some(sq.id);
+        }
+    }
+    CellKind::Room
+}
+
+//
+// Internal - sends MazeChanged whenever the MazeBundle resource is inserted
+// or replaced.
+//
+fn detect_maze_change(maze: Option<Res<MazeBundle>>, mut events: EventWriter<MazeChanged>) {
+    if maze.is_some_and(|m| m.is_changed()) {
+        events.write(MazeChanged);
+    }
+}
+
+//
+// Internal - despawns the current maze entities and spawns new ones for the
+// current MazeBundle, if there is one.
+//
+fn respawn_maze(mut commands: Commands, mut events: EventReader<MazeChanged>, maze: Option<Res<MazeBundle>>,
+                settings: Res<MazeSettings>, existing: Query<Entity, With<MazeCell>>) {
+    if events.read().count() == 0 {
+        return;
+    }
+    for entity in &existing {
+        commands.entity(entity).despawn();
+    }
+    if let Some(maze) = maze {
+        for cell in maze_cells(&maze.maze) {
+            let x = cell.coord.x as f32 * settings.cell_size;
+            let y = -(cell.coord.y as f32) * settings.cell_size;
+            commands.spawn((cell, Transform::from_xyz(x, y, 0.0)));
+        }
+    }
+}
+
+//
+// Internal - drops one message from the MessageBox for each DismissMessage.
+//
+fn dismiss_message(mut events: EventReader<DismissMessage>, mut messages: ResMut<MessageBox>) {
+    for _ in events.read() {
+        messages.messages.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_with(maze: Maze) -> App {
+        let mut app = App::new();
+        app.add_plugins(MazePlugin { cell_size: 2.0 });
+        app.insert_resource(MazeBundle { maze });
+        app.update();
+        app
+    }
+
+    fn spawned(app: &mut App) -> Vec<(MazeCell, Transform)> {
+        let mut query = app.world_mut().query::<(&MazeCell, &Transform)>();
+        query.iter(app.world()).map(|(c, t)| (*c, *t)).collect()
+    }
+
+    #[test]
+    fn test_entity_counts_match_grid() {
+        let mut maze = Maze::new(5, 7);
+        maze.generate_perfect_seeded(8).unwrap();
+        let grid = maze.to_wall_grid();
+        let mut app = app_with(maze);
+
+        let cells = spawned(&mut app);
+        let walls = cells.iter().filter(|&&(c, _)| c.kind == CellKind::Wall).count();
+        assert_eq!(grid.walls.len(), cells.len());
+        assert_eq!(grid.walls.iter().filter(|&&w| w).count(), walls);
+
+        // Transforms follow the grid position and cell size
+        for (cell, transform) in cells {
+            assert_eq!(cell.coord.x as f32 * 2.0, transform.translation.x);
+            assert_eq!(cell.coord.y as f32 * -2.0, transform.translation.y);
+        }
+
+        // Nothing is respawned while the maze stays the same
+        app.update();
+        assert_eq!(grid.walls.len(), spawned(&mut app).len());
+    }
+
+    #[test]
+    fn test_rooms_and_corridors() {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_seeded((4, 3, 5, 3, 5), 12).unwrap();
+        let room_squares = maze.sq.iter().filter(|sq| sq.is_part_of_room()).count();
+        assert!(room_squares > 0);
+
+        let mut app = app_with(maze.clone());
+        let cells = spawned(&mut app);
+        let square_kind = |kind: CellKind| {
+            cells.iter().filter(|&&(c, _)| c.kind == kind && c.coord.x % 2 == 1 && c.coord.y % 2 == 1).count()
+        };
+        assert_eq!(room_squares, square_kind(CellKind::Room));
+        assert_eq!(maze.sq.len() - room_squares, square_kind(CellKind::Corridor));
+    }
+
+    #[test]
+    fn test_replacing_maze_respawns() {
+        let mut app = app_with(Maze::new(4, 4));
+        assert_eq!(81, spawned(&mut app).len());
+
+        app.insert_resource(MazeBundle { maze: Maze::new(2, 2) });
+        app.update();
+        assert_eq!(25, spawned(&mut app).len());
+
+        // Removing the maze and announcing it clears the entities
+        app.world_mut().remove_resource::<MazeBundle>();
+        app.world_mut().send_event(MazeChanged);
+        app.update();
+        assert_eq!(0, spawned(&mut app).len());
+    }
+
+    #[test]
+    fn test_message_box() {
+        let mut app = app_with(Maze::new(2, 2));
+        let mut messages = app.world_mut().resource_mut::<MessageBox>();
+        messages.push("first");
+        messages.push("second");
+        app.update();
+        assert_eq!(Some("first"), app.world().resource::<MessageBox>().current());
+
+        app.world_mut().send_event(DismissMessage);
+        app.update();
+        assert_eq!(Some("second"), app.world().resource::<MessageBox>().current());
+        app.world_mut().send_event(DismissMessage);
+        app.world_mut().send_event(DismissMessage);
+        app.update();
+        assert_eq!(None, app.world().resource::<MessageBox>().current());
+    }
+}
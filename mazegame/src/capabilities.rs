@@ -0,0 +1,228 @@
+//! What this build of mazegame can do.  The optional parts of the crate are
+//! cargo features, so two copies of the same version can support different
+//! things; capabilities() reports which were compiled in, and run() uses it
+//! to pick a front end to play in.
+use alloc::fmt;
+use alloc::string::ToString;
+use core::str::FromStr;
+
+use crate::error::MazeError;
+
+/// The optional parts compiled into this build, one for each cargo feature
+/// that changes what the crate can do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The standard library: printing, files and the command line.
+    pub std: bool,
+    /// The SDL bindings.
+    pub sdl: bool,
+    /// An SDL window to play in.  The `sdl` feature only brings in the
+    /// bindings so far, so this stays false until the SDL game loop is part
+    /// of the crate.
+    pub sdl_frontend: bool,
+    /// Masks loaded from image files.
+    pub image: bool,
+    /// Saving and loading as JSON, and maze specs.
+    pub serde: bool,
+    /// The WebAssembly bindings.
+    pub wasm: bool,
+    /// The Bevy plugin.
+    pub bevy: bool,
+    /// The C interface.
+    pub ffi: bool,
+    /// Logging of generation through the `log` crate.
+    pub log: bool,
+    /// quickcheck Arbitrary implementations for mazes.
+    pub quickcheck: bool,
+    /// The golden file helpers in `testing`.
+    pub test_fixtures: bool,
+    /// The fuzzing entry points in `fuzz`.
+    pub fuzz: bool,
+    /// The generator benchmarks in `bench`.
+    pub bench: bool,
+}
+
+/// Where a maze is played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frontend {
+    /// A window drawn with SDL.
+    Sdl,
+    /// Plain text printed to the terminal.
+    Terminal,
+}
+
+/// The front ends run() tries when none is asked for, best first.
+pub const FRONTEND_PREFERENCE: [Frontend; 2] = [Frontend::Sdl, Frontend::Terminal];
+
+/// Returns what this build supports.
+///
+/// # Example
+/// ```
+/// let built = mazegame::capabilities();
+/// assert_eq!(cfg!(feature = "serde"), built.serde);
+/// assert!(built.features().iter().any(|&(name, _)| name == "sdl"));
+/// ```
+pub const fn capabilities() -> Capabilities {
+    Capabilities {
+        std: cfg!(feature = "std"),
+        sdl: cfg!(feature = "sdl"),
+        sdl_frontend: false,
+        image: cfg!(feature = "image"),
+        serde: cfg!(feature = "serde"),
+        wasm: cfg!(feature = "wasm"),
+        bevy: cfg!(feature = "bevy"),
+        ffi: cfg!(feature = "ffi"),
+        log: cfg!(feature = "log"),
+        quickcheck: cfg!(feature = "quickcheck"),
+        test_fixtures: cfg!(feature = "test-fixtures"),
+        fuzz: cfg!(feature = "fuzz"),
+        bench: cfg!(feature = "bench"),
+    }
+}
+
+impl Capabilities {
+    /// Returns each feature's name, as given to cargo, and whether it's
+    /// compiled in.
+    pub fn features(&self) -> [(&'static str, bool); 12] {
+        [("std", self.std), ("sdl", self.sdl), ("image", self.image), ("serde", self.serde),
+         ("wasm", self.wasm), ("bevy", self.bevy), ("ffi", self.ffi), ("log", self.log),
+         ("quickcheck", self.quickcheck), ("test-fixtures", self.test_fixtures), ("fuzz", self.fuzz),
+         ("bench", self.bench)]
+    }
+
+    /// Returns whether frontend can be played in with this build.
+    pub fn has_frontend(&self, frontend: Frontend) -> bool {
+        match frontend {
+            Frontend::Sdl => self.sdl && self.sdl_frontend,
+            Frontend::Terminal => self.std,
+        }
+    }
+
+    /// Picks the front end to play in: requested if there is one, otherwise
+    /// the first in FRONTEND_PREFERENCE this build has.  Returns
+    /// MazeError::FeatureUnavailable naming the missing feature if the
+    /// requested front end isn't built in, or if none is.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::{Capabilities, Frontend, MazeError};
+    ///
+    /// let terminal_only = Capabilities { std: true, ..Capabilities::default() };
+    /// assert_eq!(Ok(Frontend::Terminal), terminal_only.select_frontend(None));
+    /// assert_eq!(Err(MazeError::FeatureUnavailable("sdl".to_string())),
+    ///            terminal_only.select_frontend(Some(Frontend::Sdl)));
+    /// ```
+    pub fn select_frontend(&self, requested: Option<Frontend>) -> Result<Frontend, MazeError> {
+        match requested {
+            Some(frontend) if self.has_frontend(frontend) => Ok(frontend),
+            Some(frontend) => Err(MazeError::FeatureUnavailable(frontend.feature().to_string())),
+            None => FRONTEND_PREFERENCE.iter().copied().find(|&f| self.has_frontend(f))
+                .ok_or_else(|| MazeError::FeatureUnavailable(Frontend::Terminal.feature().to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, built) in self.features().iter() {
+            writeln!(f, "{}: {}", name, if *built { "yes" } else { "no" })?;
+        }
+        Ok(())
+    }
+}
+
+impl Frontend {
+    /// Returns the front end's name, as given to `--frontend`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Frontend::Sdl => "sdl",
+            Frontend::Terminal => "terminal",
+        }
+    }
+
+    /// Returns the cargo feature the front end needs.
+    pub fn feature(&self) -> &'static str {
+        match *self {
+            Frontend::Sdl => "sdl",
+            Frontend::Terminal => "std",
+        }
+    }
+}
+
+impl fmt::Display for Frontend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Frontend {
+    type Err = MazeError;
+
+    fn from_str(s: &str) -> Result<Frontend, MazeError> {
+        FRONTEND_PREFERENCE.iter().copied().find(|f| f.name() == s)
+            .ok_or_else(|| MazeError::InvalidArgument(format!("unknown front end '{}'", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fails to compile if capabilities() stops matching the features this
+    // build was made with
+    const BUILT: Capabilities = capabilities();
+    const _: () = assert!(BUILT.std == cfg!(feature = "std") && BUILT.sdl == cfg!(feature = "sdl")
+                          && BUILT.image == cfg!(feature = "image") && BUILT.serde == cfg!(feature = "serde")
+                          && BUILT.wasm == cfg!(feature = "wasm") && BUILT.bevy == cfg!(feature = "bevy")
+                          && BUILT.ffi == cfg!(feature = "ffi") && BUILT.log == cfg!(feature = "log")
+                          && BUILT.quickcheck == cfg!(feature = "quickcheck")
+                          && BUILT.test_fixtures == cfg!(feature = "test-fixtures")
+                          && BUILT.fuzz == cfg!(feature = "fuzz") && BUILT.bench == cfg!(feature = "bench"));
+
+    #[test]
+    fn test_features_match_manifest() {
+        // Every feature in Cargo.toml but default, in any order
+        let manifest = include_str!("../Cargo.toml");
+        let mut declared: Vec<&str> = manifest.lines()
+            .skip_while(|line| line.trim() != "[features]")
+            .skip(1)
+            .take_while(|line| !line.starts_with('['))
+            .filter_map(|line| line.split('=').next().map(str::trim))
+            .filter(|name| !name.is_empty() && *name != "default")
+            .collect();
+        let mut listed: Vec<&str> = capabilities().features().iter().map(|&(name, _)| name).collect();
+        declared.sort_unstable();
+        listed.sort_unstable();
+        assert_eq!(declared, listed);
+    }
+
+    #[test]
+    fn test_select_frontend() {
+        let everything = Capabilities { std: true, sdl: true, sdl_frontend: true, ..Capabilities::default() };
+        let bindings_only = Capabilities { sdl_frontend: false, ..everything };
+        let terminal_only = Capabilities { std: true, ..Capabilities::default() };
+        let nothing = Capabilities::default();
+
+        assert_eq!(Ok(Frontend::Sdl), everything.select_frontend(None));
+        assert_eq!(Ok(Frontend::Terminal), everything.select_frontend(Some(Frontend::Terminal)));
+        // Without SDL it falls back to the terminal, unless SDL was asked for
+        assert_eq!(Ok(Frontend::Terminal), bindings_only.select_frontend(None));
+        assert_eq!(Ok(Frontend::Terminal), terminal_only.select_frontend(None));
+        let no_sdl = Err(MazeError::FeatureUnavailable("sdl".to_string()));
+        assert_eq!(no_sdl, bindings_only.select_frontend(Some(Frontend::Sdl)));
+        assert_eq!(no_sdl, terminal_only.select_frontend(Some(Frontend::Sdl)));
+        assert_eq!(Err(MazeError::FeatureUnavailable("std".to_string())), nothing.select_frontend(None));
+
+        assert_eq!(Ok(Frontend::Sdl), "sdl".parse());
+        assert_eq!(Ok(Frontend::Terminal), "terminal".parse());
+        assert!("curses".parse::<Frontend>().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let shown = Capabilities { std: true, serde: true, ..Capabilities::default() }.to_string();
+        assert_eq!("std: yes\nsdl: no\nimage: no\nserde: yes\nwasm: no\nbevy: no\nffi: no\nlog: no\n\
+                    quickcheck: no\ntest-fixtures: no\nfuzz: no\nbench: no\n", shown);
+        assert_eq!(BUILT, capabilities());
+    }
+}
@@ -0,0 +1,952 @@
+//! Implementations of the command line subcommands.  Each command takes the
+//! arguments following the subcommand name and returns the text to print, so
+//! they can be exercised without spawning a process.
+use core::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "bench")]
+use crate::bench;
+use crate::capabilities::Capabilities;
+use crate::error::MazeError;
+#[cfg(feature = "serde")]
+use crate::maze::analysis::{self, Summary, SweepParam};
+#[cfg(feature = "serde")]
+use crate::maze::spec::MazeSpec;
+use crate::maze::fit::{fit_text, FitMode, TerminalSize};
+use crate::maze::worksheet::{WorksheetConfig, WorksheetFormat};
+use crate::maze::{Coord, Maze};
+use crate::messages::MessageCatalog;
+use crate::pipeline::{self, RunConfig};
+
+/// The ways a solution can be printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    Ascii,
+    Json,
+}
+
+/// Runs `mazegame solve <file> [--from x,y --to x,y] [--format ascii|json]`.
+///
+/// Loads the maze in `<file>` (the format is picked from the extension),
+/// finds the shortest path between its entrance and nearest exit - or
+/// between the `--from` and `--to` cells, if given - and returns either the
+/// maze drawn with the path overlaid or the path as JSON.
+///
+/// # Example
+/// ```
+/// let args: Vec<String> = vec!["missing.txt".to_string()];
+/// assert!(mazegame::cli::solve_command(&args).is_err());
+/// ```
+pub fn solve_command(args: &[String]) -> Result<String, MazeError> {
+    solve_command_with_messages(args, &MessageCatalog::english())
+}
+
+/// Runs `mazegame solve` like solve_command(), with its text and errors
+/// taken from messages.
+pub fn solve_command_with_messages(args: &[String], messages: &MessageCatalog) -> Result<String, MazeError> {
+    let mut file = None;
+    let mut from = None;
+    let mut to = None;
+    let mut format = OutputFormat::Ascii;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--from" => from = Some(parse_coord(next_value(&mut iter, "--from", messages)?, messages)?),
+            "--to" => to = Some(parse_coord(next_value(&mut iter, "--to", messages)?, messages)?),
+            "--format" => {
+                format = match next_value(&mut iter, "--format", messages)? {
+                    "ascii" => OutputFormat::Ascii,
+                    "json" => OutputFormat::Json,
+                    other => {
+                        return Err(MazeError::InvalidArgument(messages.format("cli.unknown_format", &[other])));
+                    }
+                }
+            }
+            other if other.starts_with("--") => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unknown_option", &[other])));
+            }
+            other => {
+                if file.is_some() {
+                    return Err(MazeError::InvalidArgument(messages.format("cli.unexpected_argument", &[other])));
+                }
+                file = Some(other);
+            }
+        }
+    }
+
+    let file = file.ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.no_maze_file", &[])))?;
+    let maze = Maze::load(file)?;
+
+    let from = match from {
+        Some(c) => c,
+        None => maze.get_entrance().map(|o| o.coord).ok_or_else(|| MazeError::InvalidArgument(
+            messages.format("cli.no_entrance", &[])))?,
+    };
+    let exits: Vec<Coord> = match to {
+        Some(c) => vec![c],
+        None => maze.exits().iter().map(|o| o.coord).collect(),
+    };
+    if exits.is_empty() {
+        return Err(MazeError::InvalidArgument(messages.format("cli.no_exit", &[])));
+    }
+    for c in Some(&from).into_iter().chain(exits.iter()) {
+        if !maze.in_bounds(c.x, c.y) {
+            return Err(MazeError::OutOfBounds(c.x, c.y));
+        }
+    }
+
+    // With several exits, the nearest one
+    let (nearest, path) = maze.solve_to_nearest(from, &exits).ok_or(MazeError::Unreachable(from, exits[0]))?;
+    let to = exits[nearest];
+    match format {
+        OutputFormat::Ascii => {
+            let mut out = maze.render_ascii_with_path(&path);
+            out.push_str(&messages.format("cli.path_length", &[&path.len().to_string()]));
+            out.push('\n');
+            Ok(out)
+        }
+        OutputFormat::Json => {
+            let steps: Vec<String> = path.iter().map(|c| format!("[{},{}]", c.x, c.y)).collect();
+            Ok(format!("{{\"from\":[{},{}],\"to\":[{},{}],\"length\":{},\"path\":[{}]}}\n",
+                       from.x, from.y, to.x, to.y, path.len(), steps.join(",")))
+        }
+    }
+}
+
+/// Runs `mazegame --spec <file> [--output <file>]`.
+///
+/// Generates the maze described by the JSON spec in `<file>` (see
+/// `maze::spec` for the format).  With `--output` the maze is saved to that
+/// file, in the format picked from its extension, and a one line summary is
+/// returned; otherwise the maze is returned drawn as ASCII.
+///
+/// # Example
+/// ```
+/// let args: Vec<String> = vec!["--spec".to_string(), "missing.json".to_string()];
+/// assert!(mazegame::cli::spec_command(&args).is_err());
+/// ```
+#[cfg(feature = "serde")]
+pub fn spec_command(args: &[String]) -> Result<String, MazeError> {
+    spec_command_with_messages(args, &MessageCatalog::english())
+}
+
+/// Runs `mazegame --spec` like spec_command(), with its text and errors
+/// taken from messages.
+#[cfg(feature = "serde")]
+pub fn spec_command_with_messages(args: &[String], messages: &MessageCatalog) -> Result<String, MazeError> {
+    let mut spec = None;
+    let mut output = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--spec" => spec = Some(next_value(&mut iter, "--spec", messages)?),
+            "--output" => output = Some(next_value(&mut iter, "--output", messages)?),
+            other => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unexpected_argument", &[other])));
+            }
+        }
+    }
+
+    let spec = spec.ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.no_spec_file", &[])))?;
+    let maze = Maze::from_spec(&fs::read_to_string(spec)?)?;
+    match output {
+        Some(path) => {
+            maze.save(path)?;
+            let (cols, rows) = (maze.get_cols().to_string(), maze.get_rows().to_string());
+            Ok(messages.format("cli.saved", &[&cols, &rows, path]) + "\n")
+        }
+        None => Ok(maze.render_ascii()),
+    }
+}
+
+/// Runs `mazegame sweep --braid|--rooms|--size <values> [--spec <file>]
+/// [--samples n] [--seed n] [--csv]`.
+///
+/// Generates `--samples` mazes (100 by default) for each value of one
+/// setting and prints the mean, smallest and largest solution length, dead
+/// end, junction and carved Square counts for each, as a table or as CSV.
+/// The other settings come from the spec in `--spec`, or are those of a
+/// plain 20x20 maze.  Values are a comma separated list, or a
+/// `from:to:step` range for `--braid` and `--rooms`; sizes are written
+/// `WxH`.
+///
+/// # Example
+/// ```
+/// let args: Vec<String> = ["--braid", "0,1", "--samples", "2", "--csv"].iter().map(|s| s.to_string()).collect();
+/// let csv = mazegame::cli::sweep_command(&args).unwrap();
+/// assert_eq!(3, csv.lines().count());
+/// assert!(csv.starts_with("braid,samples,"));
+/// ```
+#[cfg(feature = "serde")]
+pub fn sweep_command(args: &[String]) -> Result<String, MazeError> {
+    sweep_command_with_messages(args, &MessageCatalog::english())
+}
+
+/// Runs `mazegame sweep` like sweep_command(), with its text and errors
+/// taken from messages.
+#[cfg(feature = "serde")]
+pub fn sweep_command_with_messages(args: &[String], messages: &MessageCatalog) -> Result<String, MazeError> {
+    let mut param = None;
+    let mut spec = None;
+    let mut samples = 100;
+    let mut seed = 0;
+    let mut csv = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let swept = match arg.as_str() {
+            "--braid" => {
+                let values = next_value(&mut iter, "--braid", messages)?;
+                Some(SweepParam::Braid(parse_range(values, messages, |v| v.parse::<f64>().ok())?))
+            }
+            "--rooms" => {
+                let values = next_value(&mut iter, "--rooms", messages)?;
+                let counts = parse_range(values, messages, |v| v.parse::<u32>().ok().map(|n| n as f64))?;
+                Some(SweepParam::RoomCount(counts.iter().map(|&n| n as u32).collect()))
+            }
+            "--size" => {
+                let values = next_value(&mut iter, "--size", messages)?;
+                Some(SweepParam::Size(values.split(',').map(|v| parse_size(v, messages)).collect::<Result<_, _>>()?))
+            }
+            "--spec" => {
+                spec = Some(MazeSpec::from_json(&fs::read_to_string(next_value(&mut iter, "--spec", messages)?)?)?);
+                None
+            }
+            "--samples" => {
+                samples = parse_u32(next_value(&mut iter, "--samples", messages)?, messages)?;
+                None
+            }
+            "--seed" => {
+                seed = parse_number(next_value(&mut iter, "--seed", messages)?, messages)?;
+                None
+            }
+            "--csv" => {
+                csv = true;
+                None
+            }
+            other if other.starts_with("--") => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unknown_option", &[other])));
+            }
+            other => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unexpected_argument", &[other])));
+            }
+        };
+        if swept.is_some() {
+            if param.is_some() {
+                return Err(MazeError::InvalidArgument(messages.format("cli.sweep_two_settings", &[])));
+            }
+            param = swept;
+        }
+    }
+
+    let param = param.ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.sweep_no_setting", &[])))?;
+    let base = spec.unwrap_or_else(|| MazeSpec::new(20, 20));
+    let rows = analysis::sweep(&base, &param, samples, seed)?;
+
+    let mut out = String::new();
+    if csv {
+        out.push_str(param.name());
+        out.push_str(",samples");
+        for stat in ["solution_length", "dead_ends", "junctions", "carved_squares"].iter() {
+            out.push_str(&format!(",{0}_mean,{0}_min,{0}_max", stat));
+        }
+        out.push('\n');
+        for row in rows.iter() {
+            out.push_str(&format!("{},{}", row.value, row.samples));
+            for s in [row.solution_length, row.dead_ends, row.junctions, row.carved_squares].iter() {
+                out.push_str(&format!(",{:.2},{},{}", s.mean, s.min, s.max));
+            }
+            out.push('\n');
+        }
+        return Ok(out);
+    }
+
+    let summary = |s: &Summary| format!("{:.1} ({}-{})", s.mean, s.min, s.max);
+    let mut table = vec![vec![param.name().to_string(),
+                              messages.format("sweep.samples", &[]),
+                              messages.format("sweep.solution_length", &[]),
+                              messages.format("sweep.dead_ends", &[]),
+                              messages.format("sweep.junctions", &[]),
+                              messages.format("sweep.carved_squares", &[])]];
+    for row in rows.iter() {
+        table.push(vec![row.value.clone(), row.samples.to_string(), summary(&row.solution_length),
+                        summary(&row.dead_ends), summary(&row.junctions), summary(&row.carved_squares)]);
+    }
+    out.push_str(&format_table(&table));
+    Ok(out)
+}
+
+/// Runs `mazegame verify --seed n [--size WxH] [--rooms n]`.
+///
+/// Runs the whole pipeline on a seeded maze (20x20 with no rooms by
+/// default; rooms are 2 to 4 Squares each way) and checks the stages agree,
+/// as pipeline::verify() does.  Returns the maze drawn as ASCII followed by
+/// a report, or an error naming the stage or check that failed.
+///
+/// # Example
+/// ```
+/// let args: Vec<String> = ["--seed", "7", "--size", "8x6"].iter().map(|s| s.to_string()).collect();
+/// let report = mazegame::cli::verify_command(&args).unwrap();
+/// assert!(report.ends_with("Passed: border gaps, moves, dead ends, round trip\n"));
+/// ```
+pub fn verify_command(args: &[String]) -> Result<String, MazeError> {
+    verify_command_with_messages(args, &MessageCatalog::english())
+}
+
+/// Runs `mazegame verify` like verify_command(), with its text and errors
+/// taken from messages.
+pub fn verify_command_with_messages(args: &[String], messages: &MessageCatalog) -> Result<String, MazeError> {
+    let mut seed = None;
+    let mut size = (20, 20);
+    let mut rooms = 0;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(parse_number(next_value(&mut iter, "--seed", messages)?, messages)?),
+            "--size" => size = parse_size(next_value(&mut iter, "--size", messages)?, messages)?,
+            "--rooms" => rooms = parse_u32(next_value(&mut iter, "--rooms", messages)?, messages)?,
+            other if other.starts_with("--") => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unknown_option", &[other])));
+            }
+            other => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unexpected_argument", &[other])));
+            }
+        }
+    }
+
+    let seed = seed.ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.no_seed", &[])))?;
+    let config = RunConfig { rooms: (rooms, 2, 4, 2, 4), ..RunConfig::new(size.0, size.1, seed) };
+    let report = pipeline::verify(&config).map_err(|e| MazeError::ConstraintUnsatisfiable(e.to_string()))?;
+
+    let opening = |o: &crate::maze::Opening| format!("({}, {})", o.coord.x, o.coord.y);
+    let checks: Vec<&str> = report.checks.iter().map(|c| c.name()).collect();
+    let lines = [
+        messages.format(if rooms == 1 { "verify.maze_one_room" } else { "verify.maze" },
+                        &[&seed.to_string(), &size.0.to_string(), &size.1.to_string(), &rooms.to_string()]),
+        messages.format("verify.openings", &[&opening(&report.entrance), &opening(&report.exit)]),
+        messages.format("verify.solution", &[&report.solution_length.to_string()]),
+        messages.format("verify.stats", &[&report.stats.dead_ends.to_string(), &report.stats.junctions.to_string(),
+                                          &report.stats.carved_squares.to_string()]),
+        messages.format("verify.saved", &[&report.saved_bytes.to_string()]),
+        messages.format("verify.passed", &[&checks.join(", ")]),
+    ];
+    let mut out = report.render;
+    for line in lines.iter() {
+        out.push_str(line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Runs `mazegame --window WxH --cell px --seed n [--hud px]`.
+///
+/// Sizes a maze to fill a window of W by H pixels with Squares of `--cell`
+/// pixels, less `--hud` pixels at the top (see RunConfig::from_window()),
+/// and runs it through the pipeline like verify_command().  Returns the maze
+/// drawn as ASCII, cut down to fit the terminal size reports if it's too
+/// big, followed by a line giving its size.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::fit::FixedSize;
+///
+/// let args: Vec<String> = ["--window", "320x200", "--cell", "40", "--seed", "2"].iter()
+///     .map(|s| s.to_string()).collect();
+/// let out = mazegame::cli::window_command(&args, &FixedSize(80, 24)).unwrap();
+/// assert_eq!("Seed 2: 8x5 Squares of 40px fill 320x200 pixels", out.lines().last().unwrap());
+/// ```
+pub fn window_command(args: &[String], size: &dyn TerminalSize) -> Result<String, MazeError> {
+    window_command_with_messages(args, &MessageCatalog::english(), size)
+}
+
+/// Runs `mazegame --window` like window_command(), with its text and errors
+/// taken from messages.
+pub fn window_command_with_messages(args: &[String], messages: &MessageCatalog, size: &dyn TerminalSize)
+                                    -> Result<String, MazeError> {
+    let mut window = None;
+    let mut cell = 24;
+    let mut hud = 0;
+    let mut seed = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--window" => window = Some(parse_size(next_value(&mut iter, "--window", messages)?, messages)?),
+            "--cell" => cell = parse_u32(next_value(&mut iter, "--cell", messages)?, messages)?,
+            "--hud" => hud = parse_u32(next_value(&mut iter, "--hud", messages)?, messages)?,
+            "--seed" => seed = Some(parse_number(next_value(&mut iter, "--seed", messages)?, messages)?),
+            other if other.starts_with("--") => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unknown_option", &[other])));
+            }
+            other => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unexpected_argument", &[other])));
+            }
+        }
+    }
+
+    let (width_px, height_px) =
+        window.ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.no_window", &[])))?;
+    let seed = seed.ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.no_seed", &[])))?;
+    let config = RunConfig { seed, ..RunConfig::from_window_with_hud(width_px, height_px, cell, hud)? };
+    let report = pipeline::verify(&config).map_err(|e| MazeError::ConstraintUnsatisfiable(e.to_string()))?;
+
+    // Like render_fit(), on the drawing the pipeline already made
+    let mut out = match size.terminal_size() {
+        Some((width, height)) => {
+            // Leave a line for the size
+            let height = height.saturating_sub(1);
+            let text_size = (config.width * 2 + 1, config.height * 2 + 1);
+            fit_text(&report.render, width, height, FitMode::pick(text_size, (width, height), None))
+        }
+        None => report.render,
+    };
+    let (used_w, used_h) = config.window_size(cell, hud);
+    let cell = cell.max(pipeline::MIN_CELL_PX);
+    out.push_str(&messages.format("window.maze", &[&seed.to_string(), &config.width.to_string(),
+                                                  &config.height.to_string(), &cell.to_string(),
+                                                  &used_w.to_string(), &used_h.to_string()]));
+    out.push('\n');
+    Ok(out)
+}
+
+/// Runs `mazegame worksheet --seed n --out <dir> [--size WxH] [--title text]
+/// [--cell-size n] [--line n]`.
+///
+/// Generates a perfect maze (20x20 by default), puts its entrance and exit
+/// as far apart as they go, and writes its worksheet pages (see
+/// Maze::render_worksheet()) to `puzzle.svg` and `answer.svg` in `<dir>`,
+/// creating it if need be.  With the `image` feature, `puzzle.png` and
+/// `answer.png` are written too.  Returns a line for each file written.
+///
+/// # Example
+/// ```
+/// let args: Vec<String> = ["--seed", "1"].iter().map(|s| s.to_string()).collect();
+/// assert!(mazegame::cli::worksheet_command(&args).is_err());
+/// ```
+pub fn worksheet_command(args: &[String]) -> Result<String, MazeError> {
+    worksheet_command_with_messages(args, &MessageCatalog::english())
+}
+
+/// Runs `mazegame worksheet` like worksheet_command(), with its text and
+/// errors taken from messages.
+pub fn worksheet_command_with_messages(args: &[String], messages: &MessageCatalog) -> Result<String, MazeError> {
+    let mut seed = None;
+    let mut size = (20, 20);
+    let mut out_dir = None;
+    let mut config = WorksheetConfig::default();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(parse_number(next_value(&mut iter, "--seed", messages)?, messages)?),
+            "--size" => size = parse_size(next_value(&mut iter, "--size", messages)?, messages)?,
+            "--out" => out_dir = Some(next_value(&mut iter, "--out", messages)?),
+            "--title" => config.title = next_value(&mut iter, "--title", messages)?.to_string(),
+            "--cell-size" => {
+                config.cell_size = parse_u32(next_value(&mut iter, "--cell-size", messages)?, messages)?;
+            }
+            "--line" => {
+                config.line_thickness = parse_u32(next_value(&mut iter, "--line", messages)?, messages)?;
+            }
+            other if other.starts_with("--") => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unknown_option", &[other])));
+            }
+            other => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unexpected_argument", &[other])));
+            }
+        }
+    }
+
+    let seed = seed.ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.no_seed", &[])))?;
+    let out_dir = out_dir.ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.no_output_dir", &[])))?;
+    let mut maze = Maze::new(size.1, size.0);
+    maze.generate_perfect_seeded(seed).map_err(MazeError::InvalidArgument)?;
+    maze.place_entrance_exit_longest()?;
+
+    // Everything is drawn before anything is written, so bad options
+    // don't leave a half filled directory
+    #[cfg_attr(not(feature = "image"), allow(unused_mut))]
+    let mut formats = vec![(WorksheetFormat::Svg, "svg")];
+    #[cfg(feature = "image")]
+    formats.push((WorksheetFormat::Png, "png"));
+    let mut files = Vec::new();
+    for (format, extension) in formats {
+        let sheet = maze.render_worksheet(&WorksheetConfig { format, ..config.clone() })?;
+        files.push((format!("puzzle.{}", extension), sheet.puzzle));
+        files.push((format!("answer.{}", extension), sheet.answer));
+    }
+    fs::create_dir_all(out_dir)?;
+    let mut out = String::new();
+    for (name, bytes) in files.iter() {
+        let path = Path::new(out_dir).join(name);
+        fs::write(&path, bytes)?;
+        out.push_str(&messages.format("cli.wrote", &[&path.display().to_string()]));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Runs `mazegame bench [--sizes WxH,...] [--reps n] [--csv]`.
+///
+/// Times the operations in bench::OPERATIONS on mazes of each size (10x10,
+/// 50x50 and 100x100 by default), reps times each (5 by default), and
+/// returns a table of the fastest, median and slowest times - or, with
+/// `--csv`, the results in bench::to_csv()'s format.
+///
+/// # Example
+/// ```
+/// let args: Vec<String> = ["--sizes", "4x4", "--reps", "1", "--csv"].iter().map(|s| s.to_string()).collect();
+/// let csv = mazegame::cli::bench_command(&args).unwrap();
+/// assert_eq!(mazegame::bench::OPERATIONS.len(), mazegame::bench::from_csv(&csv).unwrap().len());
+/// ```
+#[cfg(feature = "bench")]
+pub fn bench_command(args: &[String]) -> Result<String, MazeError> {
+    bench_command_with_messages(args, &MessageCatalog::english())
+}
+
+/// Runs `mazegame bench` like bench_command(), with its text and errors
+/// taken from messages.
+#[cfg(feature = "bench")]
+pub fn bench_command_with_messages(args: &[String], messages: &MessageCatalog) -> Result<String, MazeError> {
+    let mut sizes = vec![(10, 10), (50, 50), (100, 100)];
+    let mut reps = 5;
+    let mut csv = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--sizes" => {
+                let values = next_value(&mut iter, "--sizes", messages)?;
+                sizes = values.split(',').map(|v| parse_size(v, messages)).collect::<Result<_, _>>()?;
+            }
+            "--reps" => reps = parse_u32(next_value(&mut iter, "--reps", messages)?, messages)?,
+            "--csv" => csv = true,
+            other if other.starts_with("--") => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unknown_option", &[other])));
+            }
+            other => {
+                return Err(MazeError::InvalidArgument(messages.format("cli.unexpected_argument", &[other])));
+            }
+        }
+    }
+
+    let results = bench::run_all(&sizes, reps);
+    if csv {
+        return Ok(bench::to_csv(&results));
+    }
+    let time = |d: &std::time::Duration| format!("{:.2?}", d);
+    let mut table = vec![vec![messages.format("bench.operation", &[]),
+                              messages.format("bench.size", &[]),
+                              messages.format("bench.min", &[]),
+                              messages.format("bench.median", &[]),
+                              messages.format("bench.max", &[])]];
+    for r in results.iter() {
+        table.push(vec![r.operation.clone(), format!("{}x{}", r.width, r.height),
+                        time(&r.min), time(&r.median), time(&r.max)]);
+    }
+    Ok(format_table(&table))
+}
+
+/// Runs `mazegame --capabilities`.
+///
+/// Returns a line for each optional feature saying whether capabilities
+/// has it, then the front end run() would play in.
+///
+/// # Example
+/// ```
+/// let out = mazegame::cli::capabilities_command(&[], &mazegame::capabilities()).unwrap();
+/// assert!(out.lines().any(|l| l == "std: yes"));
+/// assert_eq!("Front end: terminal", out.lines().last().unwrap());
+/// ```
+pub fn capabilities_command(args: &[String], capabilities: &Capabilities) -> Result<String, MazeError> {
+    capabilities_command_with_messages(args, &MessageCatalog::english(), capabilities)
+}
+
+/// Runs `mazegame --capabilities` like capabilities_command(), with its text
+/// and errors taken from messages.
+pub fn capabilities_command_with_messages(args: &[String], messages: &MessageCatalog, capabilities: &Capabilities)
+                                          -> Result<String, MazeError> {
+    if let Some(arg) = args.first() {
+        return Err(MazeError::InvalidArgument(messages.format("cli.unexpected_argument", &[arg])));
+    }
+    let mut out = String::new();
+    for &(name, built) in capabilities.features().iter() {
+        let answer = messages.get(if built { "capabilities.yes" } else { "capabilities.no" });
+        out.push_str(&messages.format("capabilities.feature", &[name, answer]));
+        out.push('\n');
+    }
+    let frontend = match capabilities.select_frontend(None) {
+        Ok(frontend) => frontend.name().to_string(),
+        Err(_) => messages.get("capabilities.no_frontend").to_string(),
+    };
+    out.push_str(&messages.format("capabilities.frontend", &[&frontend]));
+    out.push('\n');
+    Ok(out)
+}
+
+//
+// Internal - parses a comma separated list of values, or a `from:to:step`
+// range.  parse reads a single value.
+//
+#[cfg(feature = "serde")]
+fn parse_range<F: Fn(&str) -> Option<f64>>(values: &str, messages: &MessageCatalog, parse: F)
+                                           -> Result<Vec<f64>, MazeError> {
+    let bad = || MazeError::InvalidArgument(messages.format("cli.bad_values", &[values]));
+    let parts: Vec<&str> = values.split(':').collect();
+    if parts.len() == 1 {
+        return values.split(',').map(|v| parse(v.trim()).ok_or_else(bad)).collect();
+    }
+    if parts.len() != 3 {
+        return Err(bad());
+    }
+    let (from, to, step) = match (parse(parts[0].trim()), parse(parts[1].trim()), parse(parts[2].trim())) {
+        (Some(from), Some(to), Some(step)) if step > 0.0 && from <= to => (from, to, step),
+        _ => return Err(bad()),
+    };
+    // A little slack, so 0:1:0.1 gets its last step despite rounding
+    let count = ((to - from) / step + 1e-9).floor() as u64 + 1;
+    Ok((0..count).map(|i| ((from + i as f64 * step) * 1e9).round() / 1e9).collect())
+}
+
+//
+// Internal - parses a `WxH` size.
+//
+fn parse_size(value: &str, messages: &MessageCatalog) -> Result<(u32, u32), MazeError> {
+    let parts: Vec<&str> = value.trim().split('x').collect();
+    if parts.len() == 2 {
+        if let (Ok(w), Ok(h)) = (parts[0].parse(), parts[1].parse()) {
+            return Ok((w, h));
+        }
+    }
+    Err(MazeError::InvalidArgument(messages.format("cli.bad_size", &[value])))
+}
+
+//
+// Internal - lines up the cells of table in columns, two spaces apart.
+//
+#[cfg(any(feature = "serde", feature = "bench"))]
+fn format_table(table: &[Vec<String>]) -> String {
+    let widths: Vec<usize> = (0..table[0].len())
+        .map(|i| table.iter().map(|cells| cells[i].chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut out = String::new();
+    for cells in table.iter() {
+        let padded: Vec<String> = cells.iter().zip(widths.iter())
+            .map(|(cell, &width)| format!("{:<1$}", cell, width))
+            .collect();
+        out.push_str(padded.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+//
+// Internal - parses a whole number.
+//
+fn parse_number(value: &str, messages: &MessageCatalog) -> Result<u64, MazeError> {
+    value.parse().map_err(|_| MazeError::InvalidArgument(messages.format("error.not_a_number", &[value])))
+}
+
+//
+// Internal - parses a whole number that has to fit in a u32.
+//
+fn parse_u32(value: &str, messages: &MessageCatalog) -> Result<u32, MazeError> {
+    u32::try_from(parse_number(value, messages)?)
+        .map_err(|_| MazeError::InvalidArgument(messages.format("error.too_large", &[value])))
+}
+
+//
+// Internal - fetches the value following an option.
+//
+fn next_value<'a, I: Iterator<Item = &'a String>>(iter: &mut I, option: &str, messages: &MessageCatalog)
+                                                   -> Result<&'a str, MazeError> {
+    iter.next()
+        .map(|s| s.as_str())
+        .ok_or_else(|| MazeError::InvalidArgument(messages.format("cli.needs_value", &[option])))
+}
+
+//
+// Internal - parses an `x,y` coordinate.
+//
+fn parse_coord(value: &str, messages: &MessageCatalog) -> Result<Coord, MazeError> {
+    let parts: Vec<&str> = value.split(',').collect();
+    if parts.len() == 2 {
+        if let (Ok(x), Ok(y)) = (parts[0].trim().parse(), parts[1].trim().parse()) {
+            return Ok(Coord::new(x, y));
+        }
+    }
+    Err(MazeError::InvalidArgument(messages.format("cli.bad_coord", &[value])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("mazegame_cli_{}_{}", std::process::id(), name))
+    }
+
+    // A 3x2 maze with a single corridor from the entrance at (0,0) to
+    // (0,1), going around through the right hand column.
+    fn corridor_maze() -> Maze {
+        let mut maze = Maze::new(2, 3);
+        maze.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(2, 0, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(2, 1, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_solve_ascii() {
+        let path = temp_path("ascii.txt");
+        let mut maze = corridor_maze();
+        maze.set_exit(0, 1, constants::DIR_WEST).unwrap();
+        maze.save(&path).unwrap();
+        let out = solve_command(&args(&[path.to_str().unwrap()]));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Ok("XXXXXXX\n \
+                          .....X\n\
+                          XXXXX.X\n \
+                          .....X\n\
+                          XXXXXXX\n\
+                          Path length: 6\n".to_string()), out);
+    }
+
+    #[test]
+    fn test_solve_json_with_coords() {
+        let path = temp_path("coords.bin");
+        corridor_maze().save(&path).unwrap();
+        let out = solve_command(&args(&["--from", "1,0", path.to_str().unwrap(), "--to", "2,1",
+                                        "--format", "json"]));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Ok("{\"from\":[1,0],\"to\":[2,1],\"length\":3,\"path\":[[1,0],[2,0],[2,1]]}\n".to_string()),
+                   out);
+    }
+
+    #[test]
+    fn test_solve_translated() {
+        let path = temp_path("translated.txt");
+        let mut maze = corridor_maze();
+        maze.set_exit(0, 1, constants::DIR_WEST).unwrap();
+        maze.save(&path).unwrap();
+        let messages = MessageCatalog::parse("cli.path_length = Longueur : {0}\n\
+                                              cli.unknown_option = option inconnue « {0} »").unwrap();
+        let out = solve_command_with_messages(&args(&[path.to_str().unwrap()]), &messages).unwrap();
+        assert!(out.ends_with("X\nLongueur : 6\n"));
+        assert_eq!(Err(MazeError::InvalidArgument("option inconnue « --bogus »".to_string())),
+                   solve_command_with_messages(&args(&[path.to_str().unwrap(), "--bogus"]), &messages));
+        // Untranslated messages are still in English
+        assert_eq!(Err(MazeError::InvalidArgument("--to needs a value".to_string())),
+                   solve_command_with_messages(&args(&[path.to_str().unwrap(), "--to"]), &messages));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_solve_nearest_exit() {
+        let path = temp_path("exits.txt");
+        let mut maze = corridor_maze();
+        maze.set_exit(0, 1, constants::DIR_WEST).unwrap();
+        maze.add_exit(2, 0, constants::DIR_NORTH).unwrap();
+        maze.save(&path).unwrap();
+        let out = solve_command(&args(&[path.to_str().unwrap(), "--format", "json"]));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Ok("{\"from\":[0,0],\"to\":[2,0],\"length\":3,\"path\":[[0,0],[1,0],[2,0]]}\n".to_string()), out);
+    }
+
+    #[test]
+    fn test_solve_unreachable() {
+        let path = temp_path("unreachable.txt");
+        let mut maze = Maze::new(2, 2);
+        maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        maze.set_exit(1, 1, constants::DIR_SOUTH).unwrap();
+        maze.save(&path).unwrap();
+        let out = solve_command(&args(&[path.to_str().unwrap()]));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Err(MazeError::Unreachable(Coord::new(0, 0), Coord::new(1, 1))), out);
+    }
+
+    #[test]
+    fn test_solve_bad_arguments() {
+        let path = temp_path("args.txt");
+        corridor_maze().save(&path).unwrap();
+        let file = path.to_str().unwrap();
+
+        // No exit recorded and none given
+        assert!(solve_command(&args(&[file])).is_err());
+        assert!(solve_command(&args(&[file, "--to", "2,1"])).is_ok());
+        // Malformed options
+        assert!(solve_command(&args(&[file, "--to", "2"])).is_err());
+        assert!(solve_command(&args(&[file, "--to"])).is_err());
+        assert!(solve_command(&args(&[file, "--to", "2,1", "--format", "xml"])).is_err());
+        assert!(solve_command(&args(&[file, "--bogus"])).is_err());
+        assert!(solve_command(&args(&[file, file])).is_err());
+        assert_eq!(Err(MazeError::OutOfBounds(9, 9)), solve_command(&args(&[file, "--to", "9,9"])));
+        // No file at all
+        assert!(solve_command(&args(&[])).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_sweep_command() {
+        let table = sweep_command(&args(&["--rooms", "0:2:2", "--samples", "3", "--seed", "447"])).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(3, lines.len());
+        assert!(lines[0].starts_with("rooms  samples  solution length"));
+        assert!(lines[1].starts_with("0      3        "));
+        assert!(lines[2].starts_with("2      3        "));
+
+        let csv = sweep_command(&args(&["--size", "4x4,6x5", "--samples", "2", "--csv"])).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!(14, lines[0].split(',').count());
+        assert!(lines[1].starts_with("4x4,2,"));
+        assert!(lines[2].contains(",30.00,30,30"));
+
+        assert!(sweep_command(&args(&[])).is_err());
+        assert!(sweep_command(&args(&["--braid", "0", "--rooms", "1"])).is_err());
+        assert!(sweep_command(&args(&["--braid", "1:0:0.1"])).is_err());
+        assert!(sweep_command(&args(&["--size", "4by4"])).is_err());
+        assert!(sweep_command(&args(&["--braid", "0", "--samples", "many"])).is_err());
+        assert!(sweep_command(&args(&["--braid", "0", "--samples", "4294967297"])).is_err());
+    }
+
+    #[test]
+    fn test_verify_command() {
+        let out = verify_command(&args(&["--seed", "464", "--size", "9x7", "--rooms", "1"])).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(15 + 6, lines.len());
+        assert_eq!("Seed 464: a 9x7 maze with 1 room", lines[15]);
+        assert_eq!("Passed: border gaps, moves, dead ends, round trip", lines[20]);
+        assert_eq!(out, verify_command(&args(&["--rooms", "1", "--size", "9x7", "--seed", "464"])).unwrap());
+
+        assert!(verify_command(&args(&["--seed", "1"])).is_ok());
+        assert!(verify_command(&args(&[])).is_err());
+        assert!(verify_command(&args(&["--seed", "x"])).is_err());
+        assert!(verify_command(&args(&["--seed", "1", "--size", "1x1"])).is_err());
+        assert!(verify_command(&args(&["--seed", "1", "extra"])).is_err());
+        assert!(verify_command(&args(&["--seed", "1", "--size", "9x5", "--rooms", "1"])).is_err());
+    }
+
+    #[test]
+    fn test_capabilities_command() {
+        let sdl = Capabilities { std: true, sdl: true, sdl_frontend: true, ..Capabilities::default() };
+        let out = capabilities_command(&[], &sdl).unwrap();
+        assert_eq!("std: yes\nsdl: yes\nimage: no\nserde: no\nwasm: no\nbevy: no\nffi: no\nlog: no\n\
+                    quickcheck: no\ntest-fixtures: no\nfuzz: no\nbench: no\nFront end: sdl\n", out);
+        let none = capabilities_command(&[], &Capabilities::default()).unwrap();
+        assert_eq!("Front end: none", none.lines().last().unwrap());
+        assert!(capabilities_command(&args(&["--json"]), &sdl).is_err());
+    }
+
+    #[test]
+    fn test_window_command() {
+        use crate::maze::fit::FixedSize;
+
+        // 1280x720 at 24px is 53x30 Squares, drawn 107 characters across
+        let window = args(&["--window", "1280x720", "--cell", "24", "--seed", "5"]);
+        let full = window_command(&window, &FixedSize(200, 80)).unwrap();
+        let lines: Vec<&str> = full.lines().collect();
+        assert_eq!(61 + 1, lines.len());
+        assert_eq!(107, lines[0].chars().count());
+        assert_eq!("Seed 5: 53x30 Squares of 24px fill 1272x720 pixels", lines[61]);
+
+        // A terminal too small for it gets a scaled down drawing
+        let fitted = window_command(&window, &FixedSize(80, 24)).unwrap();
+        assert!(fitted.lines().count() <= 24);
+        assert!(fitted.lines().all(|l| l.chars().count() <= 80));
+        assert_eq!(lines[61], fitted.lines().last().unwrap());
+
+        let hud = window_command(&args(&["--window", "1280x720", "--cell", "24", "--hud", "48", "--seed", "5"]),
+                                 &FixedSize(200, 80)).unwrap();
+        assert_eq!("Seed 5: 53x28 Squares of 24px fill 1272x720 pixels", hud.lines().last().unwrap());
+
+        assert!(window_command(&args(&["--cell", "24", "--seed", "5"]), &FixedSize(80, 24)).is_err());
+        assert!(window_command(&args(&["--window", "1280x720"]), &FixedSize(80, 24)).is_err());
+        let too_small = args(&["--window", "40x40", "--cell", "24", "--seed", "5"]);
+        assert!(window_command(&too_small, &FixedSize(80, 24)).is_err());
+        let too_big = args(&["--window", "100000x100000", "--seed", "5"]);
+        assert!(window_command(&too_big, &FixedSize(80, 24)).is_err());
+        let huge_cell = args(&["--window", "1280x720", "--cell", "4294967320", "--seed", "5"]);
+        assert!(window_command(&huge_cell, &FixedSize(80, 24)).is_err());
+        let huge_hud = args(&["--window", "1280x720", "--hud", "4294967296", "--seed", "5"]);
+        assert!(window_command(&huge_hud, &FixedSize(80, 24)).is_err());
+    }
+
+    #[test]
+    fn test_worksheet_command() {
+        let dir = temp_path("worksheet");
+        let dir_name = dir.to_str().unwrap();
+        let out = worksheet_command(&args(&["--size", "6x4", "--seed", "3", "--out", dir_name, "--title", "Six",
+                                            "--cell-size", "12"])).unwrap();
+        assert_eq!(format!("Wrote {}", dir.join("puzzle.svg").display()), out.lines().next().unwrap());
+        let puzzle = fs::read_to_string(dir.join("puzzle.svg")).unwrap();
+        let answer = fs::read_to_string(dir.join("answer.svg")).unwrap();
+        #[cfg(feature = "image")]
+        assert!(dir.join("answer.png").exists());
+        fs::remove_dir_all(&dir).unwrap();
+        // Six Squares across at 12 pixels each, with one Square of margin each side
+        assert!(puzzle.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"96\""));
+        assert!(answer.len() > puzzle.len());
+
+        assert!(worksheet_command(&args(&["--seed", "3"])).is_err());
+        assert!(worksheet_command(&args(&["--out", dir_name])).is_err());
+        assert!(worksheet_command(&args(&["--seed", "3", "--out", dir_name, "--line", "11"])).is_err());
+        assert!(worksheet_command(&args(&["--seed", "3", "--out", dir_name, "--line", "3000000000"])).is_err());
+        assert!(worksheet_command(&args(&["--seed", "3", "--out", dir_name, "--cell-size", "4294967296"])).is_err());
+        assert!(!dir.exists());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_range() {
+        let messages = MessageCatalog::english();
+        let braid = parse_range("0:1:0.1", &messages, |v| v.parse().ok()).unwrap();
+        assert_eq!(11, braid.len());
+        assert_eq!((0.3, 1.0), (braid[3], braid[10]));
+        assert_eq!(vec![0.25, 0.5], parse_range("0.25, 0.5", &messages, |v| v.parse().ok()).unwrap());
+        assert!(parse_range("0:1", &messages, |v| v.parse().ok()).is_err());
+        assert!(parse_range("0:1:0", &messages, |v| v.parse().ok()).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_spec_command() {
+        let spec = temp_path("spec.json");
+        let output = temp_path("spec_out.txt");
+        fs::write(&spec, r#"{"width": 6, "height": 3, "seed": 2, "entrance": "auto"}"#).unwrap();
+        let spec_file = spec.to_str().unwrap();
+
+        // Printed as ASCII, 2 * width + 1 characters across
+        let drawn = spec_command(&args(&["--spec", spec_file])).unwrap();
+        assert_eq!(7, drawn.lines().count());
+        assert!(drawn.lines().all(|l| l.len() == 13));
+
+        // Saved, then solvable from entrance to exit
+        spec_command(&args(&["--spec", spec_file, "--output", output.to_str().unwrap()])).unwrap();
+        assert!(solve_command(&args(&[output.to_str().unwrap()])).is_ok());
+        fs::remove_file(&output).unwrap();
+
+        fs::write(&spec, r#"{"width": 6, "height": 3, "braid": 2}"#).unwrap();
+        let err = spec_command(&args(&["--spec", spec_file]));
+        fs::remove_file(&spec).unwrap();
+        assert_eq!(Err(MazeError::Spec("braid".to_string(), "must be between 0.0 and 1.0".to_string())), err);
+        assert!(spec_command(&args(&["--spec"])).is_err());
+        assert!(spec_command(&args(&[])).is_err());
+    }
+}
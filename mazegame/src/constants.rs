@@ -5,7 +5,170 @@ pub const DIR_EAST: u32 = 2;
 pub const DIR_WEST: u32 = 3;
 pub const NUM_DIRECTIONS: u32 = 4;
 
+/// One of the four directions of a square maze, for code that needs to do
+/// math on directions.  Converts to the matching `DIR_*` constant with
+/// `u32::from()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North = DIR_NORTH as isize,
+    South = DIR_SOUTH as isize,
+    East = DIR_EAST as isize,
+    West = DIR_WEST as isize,
+}
+
+impl Direction {
+    /// Every direction, in the order of the `DIR_*` constants.
+    pub const ALL: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+    /// Returns the direction for a `DIR_*` constant, or None if dir isn't one.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::constants::{self, Direction};
+    ///
+    /// assert_eq!(Some(Direction::East), Direction::from_u32(constants::DIR_EAST));
+    /// assert_eq!(None, Direction::from_u32(constants::DIR_UP));
+    /// ```
+    pub fn from_u32(dir: u32) -> Option<Direction> {
+        Direction::ALL.get(dir as usize).cloned()
+    }
+
+    /// Returns the direction pointing the other way.
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// Returns the direction a quarter turn clockwise.
+    pub fn rotate_cw(self) -> Direction {
+        match self {
+            Direction::North => Direction::East,
+            Direction::East => Direction::South,
+            Direction::South => Direction::West,
+            Direction::West => Direction::North,
+        }
+    }
+
+    /// Returns the direction a quarter turn counter-clockwise.
+    pub fn rotate_ccw(self) -> Direction {
+        self.rotate_cw().opposite()
+    }
+
+    /// Returns the (dx, dy) of a step in this direction.  y grows going
+    /// south, like Maze rows.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// assert_eq!((0, -1), Direction::North.delta());
+    /// ```
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+        }
+    }
+
+    /// Returns the direction of a single step of (dx, dy), or None if it
+    /// isn't one.
+    pub fn from_delta(dx: i32, dy: i32) -> Option<Direction> {
+        Direction::ALL.iter().cloned().find(|d| d.delta() == (dx, dy))
+    }
+
+    /// Returns the direction's name, like "north".
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::North => "north",
+            Direction::South => "south",
+            Direction::East => "east",
+            Direction::West => "west",
+        }
+    }
+}
+
+impl From<Direction> for u32 {
+    fn from(dir: Direction) -> u32 {
+        dir as u32
+    }
+}
+
+// Stairs between the levels of a 3D maze, following the flat directions.
+pub const DIR_UP: u32 = 4;
+pub const DIR_DOWN: u32 = 5;
+pub const NUM_DIRECTIONS_3D: u32 = 6;
+
+// Directions in a hex maze, clockwise from north.  The opposite of a
+// direction is (dir + 3) % HEX_NUM_DIRECTIONS.
+pub const HEX_DIR_NORTH: u32 = 0;
+pub const HEX_DIR_NORTH_EAST: u32 = 1;
+pub const HEX_DIR_SOUTH_EAST: u32 = 2;
+pub const HEX_DIR_SOUTH: u32 = 3;
+pub const HEX_DIR_SOUTH_WEST: u32 = 4;
+pub const HEX_DIR_NORTH_WEST: u32 = 5;
+pub const HEX_NUM_DIRECTIONS: u32 = 6;
+
+// Directions in a theta maze.  A cell's outward neighbors are
+// THETA_DIR_OUTWARD, THETA_DIR_OUTWARD + 1 and so on, clockwise.
+pub const THETA_DIR_INWARD: u32 = 0;
+pub const THETA_DIR_CLOCKWISE: u32 = 1;
+pub const THETA_DIR_COUNTER_CLOCKWISE: u32 = 2;
+pub const THETA_DIR_OUTWARD: u32 = 3;
+
 pub const ID_MAZE_PATH: i32 = -1;
 pub const ID_UNCARVED: i32 = 0;
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        for (i, &dir) in Direction::ALL.iter().enumerate() {
+            assert_eq!(i as u32, u32::from(dir));
+            assert_eq!(Some(dir), Direction::from_u32(i as u32));
+        }
+        assert_eq!([DIR_NORTH, DIR_SOUTH, DIR_EAST, DIR_WEST], Direction::ALL.map(u32::from));
+        assert_eq!(None, Direction::from_u32(NUM_DIRECTIONS));
+        assert_eq!(None, Direction::from_u32(u32::MAX));
+    }
+
+    #[test]
+    fn test_turns() {
+        use self::Direction::*;
+        let table = [(North, South, East, West), (South, North, West, East),
+                     (East, West, South, North), (West, East, North, South)];
+        for &(dir, opposite, cw, ccw) in table.iter() {
+            assert_eq!(opposite, dir.opposite());
+            assert_eq!(cw, dir.rotate_cw());
+            assert_eq!(ccw, dir.rotate_ccw());
+            assert_eq!(dir, dir.rotate_cw().rotate_ccw());
+            assert_eq!(dir.opposite(), dir.rotate_cw().rotate_cw());
+        }
+    }
+
+    #[test]
+    fn test_deltas() {
+        use self::Direction::*;
+        let table = [(North, (0, -1)), (South, (0, 1)), (East, (1, 0)), (West, (-1, 0))];
+        for &(dir, delta) in table.iter() {
+            assert_eq!(delta, dir.delta());
+            let (dx, dy) = dir.opposite().delta();
+            assert_eq!(delta, (-dx, -dy));
+        }
+        for dx in -2..=2 {
+            for dy in -2..=2 {
+                let expected = table.iter().find(|&&(_, d)| d == (dx, dy)).map(|&(dir, _)| dir);
+                assert_eq!(expected, Direction::from_delta(dx, dy), "({}, {})", dx, dy);
+            }
+        }
+    }
+}
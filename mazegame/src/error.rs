@@ -0,0 +1,70 @@
+//! The error type returned by maze loading, saving and solving operations.
+use alloc::string::String;
+use alloc::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::maze::Coord;
+
+/// Errors that can occur while working with a Maze outside of basic carving.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MazeError {
+    /// A file could not be read or written.
+    Io(String),
+    /// Maze data could not be parsed.
+    Parse(String),
+    /// A coordinate falls outside the maze.
+    OutOfBounds(u32, u32),
+    /// No path exists between two cells.
+    Unreachable(Coord, Coord),
+    /// An argument (or combination of arguments) was not valid.
+    InvalidArgument(String),
+    /// A maze spec had a missing or invalid field.  Holds the field's name
+    /// (nested fields are dotted, like `rooms.min_w`) and what was wrong.
+    Spec(String, String),
+    /// Generation couldn't meet a requirement, like a minimum solution
+    /// length, within its allowed number of attempts.
+    ConstraintUnsatisfiable(String),
+    /// Generation was stopped through its cancel flag.
+    Cancelled,
+    /// Binary data failed its checksum or held a value that can't be right,
+    /// like a length running past the end of the data.  Holds what was
+    /// being read and the byte offset it was read from.
+    Corrupted { what: String, offset: usize },
+    /// Something was asked for that this build can't provide, like a front
+    /// end whose cargo feature was left out.  Holds the feature's name.
+    FeatureUnavailable(String),
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MazeError::Io(ref msg) => write!(f, "I/O error: {}", msg),
+            MazeError::Parse(ref msg) => write!(f, "parse error: {}", msg),
+            MazeError::OutOfBounds(x, y) => write!(f, "({}, {}) is outside of the maze", x, y),
+            MazeError::Unreachable(ref from, ref to) => {
+                write!(f, "no path from ({}, {}) to ({}, {})", from.x, from.y, to.x, to.y)
+            }
+            MazeError::InvalidArgument(ref msg) => write!(f, "invalid argument: {}", msg),
+            MazeError::Spec(ref field, ref msg) => write!(f, "invalid spec field '{}': {}", field, msg),
+            MazeError::ConstraintUnsatisfiable(ref msg) => write!(f, "can't satisfy constraint: {}", msg),
+            MazeError::Cancelled => write!(f, "generation was cancelled"),
+            MazeError::Corrupted { ref what, offset } => write!(f, "corrupted data: {} at byte {}", what, offset),
+            MazeError::FeatureUnavailable(ref feature) => {
+                write!(f, "'{}' isn't available in this build of mazegame", feature)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for MazeError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for MazeError {
+    fn from(err: io::Error) -> MazeError {
+        MazeError::Io(err.to_string())
+    }
+}
@@ -0,0 +1,371 @@
+//! A C interface to the maze library, enabled with the `ffi` feature.  The
+//! matching declarations live in `include/mazegame.h`.
+//!
+//! Mazes are handed out as opaque pointers from maze_new() and must be
+//! released with maze_free().  Every function reports failure through a
+//! negative error code rather than panicking; a panic inside the library is
+//! caught at the boundary and reported as MAZE_ERR_PANIC.
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::maze::orientation::{Corner, ExportOrientation, XYOrder, YDirection};
+use crate::maze::{Coord, Maze};
+
+/// The call succeeded.
+pub const MAZE_OK: i32 = 0;
+/// A required pointer argument was null.
+pub const MAZE_ERR_NULL: i32 = -1;
+/// A coordinate was outside of the maze.
+pub const MAZE_ERR_OUT_OF_BOUNDS: i32 = -2;
+/// No path exists between the requested Squares.
+pub const MAZE_ERR_UNREACHABLE: i32 = -3;
+/// The caller's buffer can't hold the result.
+pub const MAZE_ERR_BUFFER_TOO_SMALL: i32 = -4;
+/// An argument was out of range (a zero size, an illegal direction, ...).
+pub const MAZE_ERR_INVALID_ARGUMENT: i32 = -5;
+/// Maze generation failed.
+pub const MAZE_ERR_GENERATION: i32 = -6;
+/// The library panicked; the maze should be considered unusable.
+pub const MAZE_ERR_PANIC: i32 = -7;
+
+/// Orientation flag: x counts from the east side of the maze.
+pub const MAZE_ORIENT_ORIGIN_RIGHT: u32 = 1;
+/// Orientation flag: y counts from the south side of the maze.
+pub const MAZE_ORIENT_ORIGIN_BOTTOM: u32 = 2;
+/// Orientation flag: MAZE_DIR_NORTH points toward larger y.
+pub const MAZE_ORIENT_Y_UP: u32 = 4;
+/// Orientation flag: coordinates are (row, column) rather than (column, row).
+pub const MAZE_ORIENT_ORDER_YX: u32 = 8;
+
+/// A Square position, as stored in the buffer filled by maze_solve().
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MazeCoord {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Creates a new, uncarved maze `width` Squares across and `height` Squares
+/// down.  Returns null if either dimension is zero.
+#[no_mangle]
+pub extern "C" fn maze_new(width: u32, height: u32) -> *mut Maze {
+    if width == 0 || height == 0 {
+        return ptr::null_mut();
+    }
+    guard(ptr::null_mut(), || Box::into_raw(Box::new(Maze::new(height, width))))
+}
+
+/// Generates a perfect maze from a seed, replacing the maze's previous contents.
+///
+/// # Safety
+/// `maze` must be null or a pointer returned by maze_new() that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn maze_generate_perfect_seeded(maze: *mut Maze, seed: u64) -> i32 {
+    let maze = match maze.as_mut() {
+        Some(m) => m,
+        None => return MAZE_ERR_NULL,
+    };
+    guard(MAZE_ERR_PANIC, || {
+        let mut fresh = Maze::new(maze.get_rows(), maze.get_cols());
+        match fresh.generate_perfect_seeded(seed) {
+            Ok(()) => {
+                *maze = fresh;
+                MAZE_OK
+            }
+            Err(_) => MAZE_ERR_GENERATION,
+        }
+    })
+}
+
+/// Returns 1 if the Square at (x, y) has a wall in direction `dir` (using the
+/// MAZE_DIR_* values), 0 if it doesn't, or a negative error code.
+///
+/// # Safety
+/// `maze` must be null or a pointer returned by maze_new() that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn maze_is_wall(maze: *const Maze, x: u32, y: u32, dir: u32) -> i32 {
+    let maze = match maze.as_ref() {
+        Some(m) => m,
+        None => return MAZE_ERR_NULL,
+    };
+    guard(MAZE_ERR_PANIC, || {
+        if !maze.in_bounds(x, y) {
+            return MAZE_ERR_OUT_OF_BOUNDS;
+        }
+        if dir >= crate::constants::NUM_DIRECTIONS {
+            return MAZE_ERR_INVALID_ARGUMENT;
+        }
+        match maze.index_of(x, y) {
+            Some(offset) if maze.sq[offset].is_wall_present(dir) => 1,
+            _ => 0,
+        }
+    })
+}
+
+/// Finds the shortest path from (sx, sy) to (gx, gy), writing it (both ends
+/// included) into `buf`, which has room for `buf_len` coordinates.  Returns
+/// the number of coordinates in the path, or a negative error code.
+///
+/// Passing a null `buf` with a `buf_len` of 0 just returns the path length,
+/// so callers can size their buffer first.
+///
+/// # Safety
+/// `maze` must be null or a pointer returned by maze_new() that hasn't been
+/// freed, and `buf` must be null or point to at least `buf_len` MazeCoords.
+#[no_mangle]
+pub unsafe extern "C" fn maze_solve(maze: *const Maze, sx: u32, sy: u32, gx: u32, gy: u32,
+                                    buf: *mut MazeCoord, buf_len: usize) -> i64 {
+    let maze = match maze.as_ref() {
+        Some(m) => m,
+        None => return MAZE_ERR_NULL as i64,
+    };
+    if buf.is_null() && buf_len != 0 {
+        return MAZE_ERR_NULL as i64;
+    }
+    guard(MAZE_ERR_PANIC as i64, || {
+        if !maze.in_bounds(sx, sy) || !maze.in_bounds(gx, gy) {
+            return MAZE_ERR_OUT_OF_BOUNDS as i64;
+        }
+        let path = match maze.solve(Coord::new(sx, sy), Coord::new(gx, gy)) {
+            Some(p) => p,
+            None => return MAZE_ERR_UNREACHABLE as i64,
+        };
+        if buf.is_null() {
+            return path.len() as i64;
+        }
+        if path.len() > buf_len {
+            return MAZE_ERR_BUFFER_TOO_SMALL as i64;
+        }
+        for (i, c) in path.iter().enumerate() {
+            *buf.add(i) = MazeCoord { x: c.x, y: c.y };
+        }
+        path.len() as i64
+    })
+}
+
+/// maze_is_wall() with x, y and dir given in the convention described by
+/// `orientation`, a combination of the MAZE_ORIENT_* flags.  0 is the same
+/// convention maze_is_wall() uses.
+///
+/// # Safety
+/// `maze` must be null or a pointer returned by maze_new() that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn maze_is_wall_oriented(maze: *const Maze, x: u32, y: u32, dir: u32,
+                                               orientation: u32) -> i32 {
+    let o = match export_orientation(orientation) {
+        Some(o) => o,
+        None => return MAZE_ERR_INVALID_ARGUMENT,
+    };
+    let m = match maze.as_ref() {
+        Some(m) => m,
+        None => return MAZE_ERR_NULL,
+    };
+    let (width, height) = o.exported_size(m.get_cols(), m.get_rows());
+    if x >= width || y >= height {
+        return MAZE_ERR_OUT_OF_BOUNDS;
+    }
+    if dir >= crate::constants::NUM_DIRECTIONS {
+        return MAZE_ERR_INVALID_ARGUMENT;
+    }
+    let c = o.import_coord(Coord::new(x, y), m.get_cols(), m.get_rows());
+    maze_is_wall(maze, c.x, c.y, o.import_dir(dir))
+}
+
+/// maze_solve() with the ends given, and the path written, in the
+/// convention described by `orientation`, a combination of the
+/// MAZE_ORIENT_* flags.
+///
+/// # Safety
+/// As for maze_solve().
+#[no_mangle]
+pub unsafe extern "C" fn maze_solve_oriented(maze: *const Maze, sx: u32, sy: u32, gx: u32, gy: u32,
+                                             buf: *mut MazeCoord, buf_len: usize, orientation: u32) -> i64 {
+    let o = match export_orientation(orientation) {
+        Some(o) => o,
+        None => return MAZE_ERR_INVALID_ARGUMENT as i64,
+    };
+    let m = match maze.as_ref() {
+        Some(m) => m,
+        None => return MAZE_ERR_NULL as i64,
+    };
+    let (cols, rows) = (m.get_cols(), m.get_rows());
+    let (width, height) = o.exported_size(cols, rows);
+    if sx >= width || sy >= height || gx >= width || gy >= height {
+        return MAZE_ERR_OUT_OF_BOUNDS as i64;
+    }
+    let (s, g) = (o.import_coord(Coord::new(sx, sy), cols, rows), o.import_coord(Coord::new(gx, gy), cols, rows));
+    let len = maze_solve(maze, s.x, s.y, g.x, g.y, buf, buf_len);
+    if len > 0 && !buf.is_null() {
+        for i in 0..len as usize {
+            let c = *buf.add(i);
+            let e = o.export_coord(Coord::new(c.x, c.y), cols, rows);
+            *buf.add(i) = MazeCoord { x: e.x, y: e.y };
+        }
+    }
+    len
+}
+
+/// Frees a maze created by maze_new().  Passing null does nothing.
+///
+/// # Safety
+/// `maze` must be null or a pointer returned by maze_new() that hasn't
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn maze_free(maze: *mut Maze) {
+    if !maze.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(maze))));
+    }
+}
+
+//
+// Internal - the ExportOrientation described by MAZE_ORIENT_* flags, or
+// None if unknown flags are set.
+//
+fn export_orientation(flags: u32) -> Option<ExportOrientation> {
+    let known = MAZE_ORIENT_ORIGIN_RIGHT | MAZE_ORIENT_ORIGIN_BOTTOM | MAZE_ORIENT_Y_UP | MAZE_ORIENT_ORDER_YX;
+    if flags & !known != 0 {
+        return None;
+    }
+    let origin = match (flags & MAZE_ORIENT_ORIGIN_RIGHT != 0, flags & MAZE_ORIENT_ORIGIN_BOTTOM != 0) {
+        (false, false) => Corner::TopLeft,
+        (true, false) => Corner::TopRight,
+        (false, true) => Corner::BottomLeft,
+        (true, true) => Corner::BottomRight,
+    };
+    let y_axis = if flags & MAZE_ORIENT_Y_UP != 0 { YDirection::Up } else { YDirection::Down };
+    let order = if flags & MAZE_ORIENT_ORDER_YX != 0 { XYOrder::YX } else { XYOrder::XY };
+    Some(ExportOrientation::new(origin, y_axis, order))
+}
+
+//
+// Internal - runs f, turning a panic into the supplied error value so it
+// never unwinds into the caller's C code.
+//
+fn guard<T, F: FnOnce() -> T>(on_panic: T, f: F) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    #[test]
+    fn test_new_and_free() {
+        let maze = maze_new(5, 4);
+        assert!(!maze.is_null());
+        unsafe {
+            assert_eq!(1, maze_is_wall(maze, 4, 3, constants::DIR_EAST));
+            assert_eq!(MAZE_ERR_OUT_OF_BOUNDS, maze_is_wall(maze, 5, 3, constants::DIR_EAST));
+            assert_eq!(MAZE_ERR_INVALID_ARGUMENT, maze_is_wall(maze, 0, 0, 9));
+            maze_free(maze);
+        }
+        assert!(maze_new(0, 4).is_null());
+        assert!(maze_new(4, 0).is_null());
+    }
+
+    #[test]
+    fn test_null_handling() {
+        unsafe {
+            assert_eq!(MAZE_ERR_NULL, maze_generate_perfect_seeded(ptr::null_mut(), 1));
+            assert_eq!(MAZE_ERR_NULL, maze_is_wall(ptr::null(), 0, 0, constants::DIR_NORTH));
+            assert_eq!(MAZE_ERR_NULL as i64, maze_solve(ptr::null(), 0, 0, 1, 1, ptr::null_mut(), 0));
+            maze_free(ptr::null_mut());
+
+            // A null buffer that claims to have room is rejected
+            let maze = maze_new(3, 3);
+            assert_eq!(MAZE_ERR_NULL as i64, maze_solve(maze, 0, 0, 2, 2, ptr::null_mut(), 10));
+            maze_free(maze);
+        }
+    }
+
+    #[test]
+    fn test_generate_and_solve() {
+        unsafe {
+            let maze = maze_new(8, 6);
+            assert_eq!(MAZE_ERR_UNREACHABLE as i64, maze_solve(maze, 0, 0, 7, 5, ptr::null_mut(), 0));
+            assert_eq!(MAZE_OK, maze_generate_perfect_seeded(maze, 42));
+
+            // Size query, then a real solve into a buffer of exactly that size
+            let len = maze_solve(maze, 0, 0, 7, 5, ptr::null_mut(), 0);
+            assert!(len >= 13);
+            let mut buf = vec![MazeCoord::default(); len as usize];
+            assert_eq!(len, maze_solve(maze, 0, 0, 7, 5, buf.as_mut_ptr(), buf.len()));
+            assert_eq!(MazeCoord { x: 0, y: 0 }, buf[0]);
+            assert_eq!(MazeCoord { x: 7, y: 5 }, buf[len as usize - 1]);
+
+            // Each step moves through an open wall
+            let expected = (*maze).solve(Coord::new(0, 0), Coord::new(7, 5)).unwrap();
+            let got: Vec<Coord> = buf.iter().map(|c| Coord::new(c.x, c.y)).collect();
+            assert_eq!(expected, got);
+
+            assert_eq!(MAZE_ERR_OUT_OF_BOUNDS as i64, maze_solve(maze, 0, 0, 8, 5, buf.as_mut_ptr(), buf.len()));
+            maze_free(maze);
+        }
+    }
+
+    #[test]
+    fn test_buffer_too_small() {
+        unsafe {
+            let maze = maze_new(6, 6);
+            maze_generate_perfect_seeded(maze, 7);
+            let len = maze_solve(maze, 0, 0, 5, 5, ptr::null_mut(), 0) as usize;
+
+            // The buffer is left untouched when it's too small
+            let mut buf = vec![MazeCoord { x: 99, y: 99 }; len - 1];
+            assert_eq!(MAZE_ERR_BUFFER_TOO_SMALL as i64, maze_solve(maze, 0, 0, 5, 5, buf.as_mut_ptr(), buf.len()));
+            assert!(buf.iter().all(|c| c.x == 99 && c.y == 99));
+            maze_free(maze);
+        }
+    }
+
+    #[test]
+    fn test_generation_is_seeded() {
+        unsafe {
+            let a = maze_new(10, 7);
+            let b = maze_new(10, 7);
+            maze_generate_perfect_seeded(a, 1234);
+            maze_generate_perfect_seeded(b, 1234);
+            assert_eq!(*a, *b);
+            // Regenerating replaces the old layout rather than adding to it
+            maze_generate_perfect_seeded(a, 1234);
+            assert_eq!(*a, *b);
+            maze_free(a);
+            maze_free(b);
+        }
+    }
+
+    #[test]
+    fn test_oriented_queries() {
+        unsafe {
+            let maze = maze_new(7, 5);
+            maze_generate_perfect_seeded(maze, 488);
+            let m = &*maze;
+            for flags in 0..16 {
+                let o = export_orientation(flags).unwrap();
+                for c in (0..35).map(|i| Coord::new(i % 7, i / 7)) {
+                    let e = o.export_coord(c, 7, 5);
+                    for dir in 0..constants::NUM_DIRECTIONS {
+                        assert_eq!(maze_is_wall(maze, c.x, c.y, dir),
+                                   maze_is_wall_oriented(maze, e.x, e.y, o.export_dir(dir), flags));
+                    }
+                }
+                let (s, g) = (o.export_coord(Coord::new(0, 0), 7, 5), o.export_coord(Coord::new(6, 4), 7, 5));
+                let mut buf = vec![MazeCoord::default(); 64];
+                let len = maze_solve_oriented(maze, s.x, s.y, g.x, g.y, buf.as_mut_ptr(), buf.len(), flags);
+                let path = m.solve(Coord::new(0, 0), Coord::new(6, 4)).unwrap();
+                assert_eq!(path.len() as i64, len);
+                for (c, got) in path.iter().zip(buf.iter()) {
+                    let e = o.export_coord(*c, 7, 5);
+                    assert_eq!(MazeCoord { x: e.x, y: e.y }, *got);
+                }
+            }
+
+            // Rows first, a 7 wide maze is 5 wide
+            assert_eq!(MAZE_ERR_OUT_OF_BOUNDS, maze_is_wall_oriented(maze, 5, 0, 0, MAZE_ORIENT_ORDER_YX));
+            assert_eq!(MAZE_ERR_INVALID_ARGUMENT, maze_is_wall_oriented(maze, 0, 0, 0, 16));
+            assert_eq!(MAZE_ERR_NULL, maze_is_wall_oriented(ptr::null(), 0, 0, 0, 0));
+            maze_free(maze);
+        }
+    }
+}
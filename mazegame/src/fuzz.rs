@@ -0,0 +1,209 @@
+//! Entry points for fuzzing, enabled with the `fuzz` feature (and always
+//! available to this crate's own tests).
+//!
+//! Each entry point takes raw bytes and panics if it finds a bug, so it can
+//! be called straight from a cargo-fuzz target:
+//!
+//! ```text
+//! fuzz_target!(|data: &[u8]| mazegame::fuzz::fuzz_ops(data));
+//! ```
+//!
+//! run_corpus() feeds every file in a directory through one, so the inputs
+//! a fuzzer found can be kept as ordinary tests.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::constants;
+use crate::maze::Maze;
+use crate::maze::transform::Transformable;
+use crate::rng;
+
+/// The largest maze fuzz_ops() starts with, in either direction.
+pub const FUZZ_MAX_DIMENSION: u32 = 16;
+
+/// Reads data as a Maze in the binary format, and if it loads, checks that
+/// saving and loading it again gives the same Maze and the same bytes.
+/// Data that doesn't load is fine; only a panic or a change is a bug.
+///
+/// # Example
+/// ```
+/// let mut maze = mazegame::Maze::new(4, 4);
+/// maze.generate_perfect_seeded(1).unwrap();
+/// mazegame::fuzz::fuzz_roundtrip(&maze.to_bytes());
+/// mazegame::fuzz::fuzz_roundtrip(b"not a maze");
+/// ```
+pub fn fuzz_roundtrip(data: &[u8]) {
+    let maze = match Maze::from_bytes(data) {
+        Ok(maze) => maze,
+        Err(_) => return,
+    };
+    let bytes = maze.to_bytes();
+    let again = Maze::from_bytes(&bytes).unwrap_or_else(|e| panic!("a saved maze didn't load: {}", e));
+    assert_eq!(maze, again, "the maze changed when saved and loaded");
+    assert_eq!(bytes, again.to_bytes(), "the maze saved differently the second time");
+}
+
+/// Builds a maze from data and runs operations on it, panicking if the
+/// Maze fails validate() after any of them.
+///
+/// The first three bytes give the width, height and seed of a perfect maze
+/// to start from.  The top bit of the first byte adds rooms if the maze is
+/// at least 5x5, and the top bit of the second adds an entrance and exit.
+/// After that every four bytes are an operation, picked by the first byte,
+/// with the other three as its arguments:
+///
+/// - `0`: carve from a Square toward a direction
+/// - `1`: build the wall on one side of a Square
+/// - `2`: braid with a chance and seed
+/// - `3`: crop to a block
+/// - `4`: rotate by some quarter turns
+///
+/// Arguments are wrapped into range, so every input runs.  Operations that
+/// return an error are skipped.
+///
+/// # Example
+/// ```
+/// // A 5x4 maze, carved toward the east from (1, 1), then rotated once
+/// mazegame::fuzz::fuzz_ops(&[4, 3, 9, 0, 1, 1, 2, 4, 1, 0, 0]);
+/// ```
+pub fn fuzz_ops(data: &[u8]) {
+    if data.len() < 3 {
+        return;
+    }
+    let cols = data[0] as u32 % FUZZ_MAX_DIMENSION + 1;
+    let rows = data[1] as u32 % FUZZ_MAX_DIMENSION + 1;
+    let mut maze = Maze::new(rows, cols);
+    if data[0] & 0x80 != 0 && cols >= 5 && rows >= 5 {
+        maze.generate_seeded((3, 1, 2, 1, 2), data[2] as u64).unwrap();
+    } else if cols * rows > 1 {
+        maze.generate_perfect_seeded(data[2] as u64).unwrap();
+    }
+    if data[1] & 0x80 != 0 {
+        maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        maze.set_exit(cols - 1, rows - 1, constants::DIR_SOUTH).unwrap();
+    }
+    check(&maze, "generating");
+
+    for op in data[3..].chunks_exact(4) {
+        let (cols, rows) = (maze.get_cols(), maze.get_rows());
+        let (a, b, c) = (op[1] as u32, op[2] as u32, op[3] as u32);
+        let name = match op[0] % 5 {
+            0 => {
+                let _ = maze.carve(a % cols, b % rows, c % constants::NUM_DIRECTIONS, constants::ID_MAZE_PATH, false);
+                "carving"
+            }
+            1 => {
+                let mut edit = maze.begin_edit();
+                if edit.build_wall(a % cols, b % rows, c % constants::NUM_DIRECTIONS).is_ok() {
+                    edit.commit();
+                }
+                "building a wall"
+            }
+            2 => {
+                maze.braid_with_rng(a as f64 / 255.0, &mut rng::from_seed((b << 8 | c) as u64));
+                "braiding"
+            }
+            3 => {
+                let (x, y) = (a % cols, b % rows);
+                let (width, height) = ((c & 0xf) % (cols - x) + 1, (c >> 4) % (rows - y) + 1);
+                if let Ok(cropped) = maze.crop(x, y, width, height) {
+                    maze = cropped;
+                }
+                "cropping"
+            }
+            _ => {
+                for _ in 0..a % 4 {
+                    maze = maze.rotate_cw();
+                }
+                "rotating"
+            }
+        };
+        check(&maze, name);
+    }
+}
+
+/// Calls target with the contents of every file in dir, returning how many
+/// there were.  A panic names the file that caused it.
+pub fn run_corpus<F: Fn(&[u8])>(dir: &Path, target: F) -> io::Result<usize> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    paths.sort();
+    let mut count = 0;
+    for path in paths.iter().filter(|p| p.is_file()) {
+        let data = fs::read(path)?;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| target(&data)));
+        if let Err(cause) = result {
+            eprintln!("fuzz corpus file {} failed", path.display());
+            std::panic::resume_unwind(cause);
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+//
+// Internal - panics with what was just done if the Maze is broken.
+//
+fn check(maze: &Maze, after: &str) {
+    if let Err(e) = maze.validate() {
+        panic!("invalid maze after {}: {}\n{}", after, e, maze.render_ascii());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use rand::Rng;
+    use crate::maze::fingerprint::crc32;
+
+    fn corpus_dir(target: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fuzz_corpus").join(target)
+    }
+
+    #[test]
+    fn test_corpus() {
+        assert!(run_corpus(&corpus_dir("roundtrip"), fuzz_roundtrip).unwrap() > 0);
+        assert!(run_corpus(&corpus_dir("ops"), fuzz_ops).unwrap() > 0);
+    }
+
+    #[test]
+    fn test_random_inputs() {
+        let mut rng = rng::from_seed(460);
+        for _ in 0..5000 {
+            let len = rng.gen_range(0, 60);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            fuzz_ops(&data);
+            fuzz_roundtrip(&data);
+        }
+    }
+
+    #[test]
+    fn test_mutated_saves() {
+        // Random bytes almost never get past the header, so flip bytes in
+        // real saves instead
+        let mut rng = rng::from_seed(461);
+        for _ in 0..500 {
+            let mut maze = Maze::arbitrary_with_rng(&mut rng, 8);
+            if rng.gen() {
+                let (x, y) = (rng.gen_range(0, maze.get_cols()), rng.gen_range(0, maze.get_rows()));
+                let _ = maze.add_exit(x, y, rng.gen_range(0, constants::NUM_DIRECTIONS));
+            }
+            let mut bytes = maze.to_bytes();
+            fuzz_roundtrip(&bytes);
+            for _ in 0..rng.gen_range(1, 4) {
+                let i = rng.gen_range(0, bytes.len() - 4);
+                bytes[i] = rng.gen();
+            }
+            fuzz_roundtrip(&bytes);
+            // Fixing up the CRC-32 gets the damage past it, to the checks
+            // on what the file holds
+            let at = bytes.len() - 4;
+            let crc = crc32(&bytes[..at]);
+            bytes[at..].copy_from_slice(&crc.to_le_bytes());
+            fuzz_roundtrip(&bytes);
+        }
+    }
+}
@@ -1,22 +1,106 @@
 //! A basic game using SDL.  Move a character through a maze, trying to find the
 //! exit as quickly as possible.
+//!
+//! `use mazegame::prelude::*;` brings in Maze and the types used alongside
+//! it.
+//!
+//! The standard library is only required by the default `std` feature.  With
+//! it disabled the core Maze, generators and solvers build for `no_std`
+//! targets that provide an allocator; printing, file access, the command line
+//! front end and the FFI layer need `std`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
 // External crates
-extern crate rand;
+#[macro_use]
+extern crate alloc;
+// Only injected automatically when building for no_std
+#[cfg(any(feature = "std", test))]
+extern crate core;
+pub extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "wasm")]
+extern crate serde_wasm_bindgen;
+#[cfg(all(test, feature = "wasm", target_arch = "wasm32"))]
+extern crate wasm_bindgen_test;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "quickcheck")]
+extern crate quickcheck;
+#[cfg(feature = "bevy")]
+extern crate bevy_app;
+#[cfg(feature = "bevy")]
+extern crate bevy_ecs;
+#[cfg(feature = "bevy")]
+extern crate bevy_transform;
+#[cfg(feature = "image")]
+extern crate image;
 
 // Included modules
+#[macro_use]
+pub mod trace;
+#[cfg(any(test, feature = "bench"))]
+pub mod bench;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+pub mod capabilities;
+#[cfg(feature = "std")]
+pub mod cli;
 pub mod constants;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(any(test, feature = "fuzz"))]
+pub mod fuzz;
 pub mod maze;
+pub mod messages;
+pub mod pipeline;
+pub mod prelude;
+pub mod rng;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod testing;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 // Uses
-pub use maze::Maze;
+pub use crate::capabilities::{capabilities, Capabilities, Frontend};
+pub use crate::error::MazeError;
+pub use crate::maze::Maze;
 
-pub fn run((w, h, rooms, min_w, min_h, max_w, max_h): (u32, u32, u32, u32, u32, u32, u32)) {
+/// Generates a maze of w by h Squares, with rooms if any are asked for, and
+/// plays it in the best front end this build has.  Panics if it can't; see
+/// run_with().
+#[cfg(feature = "std")]
+pub fn run(dimensions: (u32, u32, u32, u32, u32, u32, u32)) {
+    run_with(dimensions, None).unwrap()
+}
+
+/// Generates a maze like run() and plays it in frontend, or the best front
+/// end this build has if frontend is None.  Returns
+/// MazeError::FeatureUnavailable if the front end asked for isn't built in.
+#[cfg(feature = "std")]
+pub fn run_with((w, h, rooms, min_w, min_h, max_w, max_h): (u32, u32, u32, u32, u32, u32, u32),
+                frontend: Option<Frontend>) -> Result<(), MazeError> {
+    let frontend = capabilities().select_frontend(frontend)?;
     let mut m = Maze::new(w, h);
     match (rooms, min_w, min_h, max_w, max_h) {
-        (0, 0, 0, 0, 0) => m.generate_perfect().unwrap(),
-        _ => m.generate((rooms, min_w, min_h, max_w, max_h)).unwrap()
-    }
+        (0, 0, 0, 0, 0) => m.generate_perfect(),
+        _ => m.generate((rooms, min_w, min_h, max_w, max_h))
+    }.map_err(MazeError::InvalidArgument)?;
 
-    m.print();
+    match frontend {
+        Frontend::Terminal => m.print(),
+        // select_frontend() only picks SDL once the crate has a window to
+        // play in
+        Frontend::Sdl => return Err(MazeError::FeatureUnavailable(Frontend::Sdl.feature().to_string())),
+    }
+    Ok(())
 }
@@ -1,25 +1,145 @@
 extern crate mazegame;
 
+use mazegame::messages::MessageCatalog;
 use std::env;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let messages = if args.len() >= 3 && args[1] == "--lang" {
+        let file: Vec<String> = args.drain(1..3).collect();
+        match MessageCatalog::load(&file[1]) {
+            Ok(messages) => messages,
+            Err(e) => {
+                eprintln!("{}", MessageCatalog::english().format("error.general", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+    } else {
+        MessageCatalog::english()
+    };
+
+    if args.len() >= 2 && args[1] == "--capabilities" {
+        match mazegame::cli::capabilities_command_with_messages(&args[2..], &messages, &mazegame::capabilities()) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.general", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.len() >= 2 && args[1] == "solve" {
+        match mazegame::cli::solve_command_with_messages(&args[2..], &messages) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.solve", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.len() >= 2 && args[1] == "verify" {
+        match mazegame::cli::verify_command_with_messages(&args[2..], &messages) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.verify", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    #[cfg(feature = "serde")]
+    if args.len() >= 2 && args[1] == "sweep" {
+        match mazegame::cli::sweep_command_with_messages(&args[2..], &messages) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.sweep", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.len() >= 2 && args[1] == "worksheet" {
+        match mazegame::cli::worksheet_command_with_messages(&args[2..], &messages) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.worksheet", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    #[cfg(feature = "bench")]
+    if args.len() >= 2 && args[1] == "bench" {
+        match mazegame::cli::bench_command_with_messages(&args[2..], &messages) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.bench", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.len() >= 2 && args[1] == "--window" {
+        match mazegame::cli::window_command_with_messages(&args[1..], &messages, &mazegame::maze::fit::SttySize) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.general", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    #[cfg(feature = "serde")]
+    if args.len() >= 2 && args[1] == "--spec" {
+        match mazegame::cli::spec_command_with_messages(&args[1..], &messages) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.general", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let frontend = if args.len() >= 3 && args[1] == "--frontend" {
+        let name: Vec<String> = args.drain(1..3).collect();
+        match name[1].parse::<mazegame::Frontend>() {
+            Ok(frontend) => Some(frontend),
+            Err(e) => {
+                eprintln!("{}", messages.format("error.general", &[&e.to_string()]));
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
     if args.len() != 8 && args.len() != 3 {
-        println!("usage: mazegame <width> <height> <num_rooms> <room_min_w> <room_min_h> <room_max_w> <room_max_h>");
-        println!("       mazegame <width> <height>");
+        for key in ["usage.rooms", "usage.perfect", "usage.spec", "usage.solve", "usage.sweep", "usage.verify",
+                    "usage.window", "usage.bench", "usage.worksheet", "usage.capabilities", "usage.frontend",
+                    "usage.lang"].iter() {
+            if *key == "usage.bench" && !cfg!(feature = "bench") {
+                continue;
+            }
+            println!("{}", messages.get(key));
+        }
         std::process::exit(1);
     }
 
-    if args.len() == 3 {
-        mazegame::run((args[1].parse().unwrap(), args[2].parse().unwrap(), 0, 0, 0, 0, 0));
+    let numbers: Vec<u32> = args[1..].iter()
+        .map(|arg| arg.parse().unwrap_or_else(|_| {
+            eprintln!("{}", messages.format("error.general", &[&messages.format("error.not_a_number", &[arg])]));
+            std::process::exit(1);
+        }))
+        .collect();
+    let dimensions = if numbers.len() == 2 {
+        (numbers[0], numbers[1], 0, 0, 0, 0, 0)
     } else {
-        mazegame::run((args[1].parse().unwrap(), 
-                       args[2].parse().unwrap(),
-                       args[3].parse().unwrap(),
-                       args[4].parse().unwrap(),
-                       args[5].parse().unwrap(),
-                       args[6].parse().unwrap(),
-                       args[7].parse().unwrap()
-                      ));                   
-    }
-}
\ No newline at end of file
+        (numbers[0], numbers[1], numbers[2], numbers[3], numbers[4], numbers[5], numbers[6])
+    };
+    if let Err(e) = mazegame::run_with(dimensions, frontend) {
+        eprintln!("{}", messages.format("error.general", &[&e.to_string()]));
+        std::process::exit(1);
+    }
+}
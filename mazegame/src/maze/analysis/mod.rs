@@ -0,0 +1,885 @@
+//! Caching analyses that are expensive to repeat every frame.  Every Maze
+//! method that changes the layout gives the Maze a new revision, and a
+//! MazeAnalysis throws its results away whenever the revision it saw
+//! changes.  Revisions come from one counter shared by every Maze, so a
+//! cache handed a clone or a different Maze notices too.  Code that edits
+//! `sq` directly should call mark_changed(), which also forgets the
+//! corridor regions.
+//!
+//! enumerate_perfect_mazes() lists every perfect maze of a tiny size, for
+//! checking generators against.
+//!
+//! With the `serde` feature, sweep() runs batches of generation for tuning.
+//! With `std`, compare_algorithms() runs the same batch through every
+//! generator, for choosing between them.
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use alloc::fmt;
+#[cfg(any(feature = "serde", feature = "std"))]
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+use crate::constants;
+use crate::error::MazeError;
+#[cfg(feature = "std")]
+use crate::maze::info::{GenerationAlgorithm, GenerationInfo};
+#[cfg(feature = "std")]
+use crate::maze::symmetric::Symmetry;
+#[cfg(feature = "serde")]
+use crate::maze::spec::{EntranceSpec, MazeSpec, RoomSpec};
+use crate::maze::{find_region, Coord, Maze};
+#[cfg(feature = "serde")]
+use crate::rng;
+
+//
+// Internal - a revision no Maze has had before.
+//
+pub(crate) fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed) as u64
+}
+
+/// The most Squares enumerate_perfect_mazes() will list the mazes of.  A
+/// 3x4 grid has 2,415 perfect mazes; a 4x4 one has 100,352.
+pub const MAX_ENUMERATED_SQUARES: u32 = 12;
+
+// The next revision handed out to any Maze
+static NEXT_REVISION: AtomicUsize = AtomicUsize::new(1);
+
+/// Counts describing a Maze's layout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MazeStats {
+    /// Squares with at least one open wall.
+    pub carved_squares: u32,
+    /// Squares that lead to exactly one other Square.
+    pub dead_ends: u32,
+    /// Squares that lead to three or more other Squares.
+    pub junctions: u32,
+    /// The most Squares in a straight corridor: a line of Squares joined by
+    /// open passages, where the ones in the middle lead nowhere else.  0 if
+    /// nothing is joined.
+    pub longest_straight: u32,
+}
+
+/// Cached analyses of one Maze.  Each is worked out the first time it's
+/// asked for and kept until the Maze changes.
+#[derive(Clone, Debug, Default)]
+pub struct MazeAnalysis {
+    // The (revision, cols, rows) the cached results belong to
+    seen: Option<(u64, u32, u32)>,
+    distances: BTreeMap<Coord, Vec<Option<u32>>>,
+    dead_ends: Option<Vec<Coord>>,
+    stats: Option<MazeStats>,
+    // How many analyses have actually been run, for tests
+    pub(crate) computations: u32,
+}
+
+impl MazeAnalysis {
+    /// Creates an empty cache.
+    pub fn new() -> MazeAnalysis {
+        MazeAnalysis::default()
+    }
+
+    /// Returns the number of steps from `from` to every Square, indexed like
+    /// `sq`, or None for Squares that can't be reached.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::analysis::MazeAnalysis;
+    /// use mazegame::prelude::*;
+    ///
+    /// let maze = Maze::from_compact("4cc8").unwrap();
+    /// let mut analysis = MazeAnalysis::new();
+    /// assert_eq!(Some(3), analysis.distance_map_cached(&maze, Coord::new(0, 0))[3]);
+    /// ```
+    pub fn distance_map_cached(&mut self, maze: &Maze, from: Coord) -> &[Option<u32>] {
+        self.sync(maze);
+        if !self.distances.contains_key(&from) {
+            self.computations += 1;
+            self.distances.insert(from, maze.distances(from));
+        }
+        &self.distances[&from]
+    }
+
+    /// Returns the Maze's dead ends, like Maze::dead_ends().
+    pub fn dead_ends_cached(&mut self, maze: &Maze) -> &[Coord] {
+        self.sync(maze);
+        if self.dead_ends.is_none() {
+            self.computations += 1;
+            self.dead_ends = Some(maze.dead_ends());
+        }
+        self.dead_ends.as_ref().unwrap()
+    }
+
+    /// Returns the Maze's layout stats, like Maze::stats().
+    pub fn stats_cached(&mut self, maze: &Maze) -> MazeStats {
+        self.sync(maze);
+        if self.stats.is_none() {
+            self.computations += 1;
+            self.stats = Some(maze.stats());
+        }
+        self.stats.unwrap()
+    }
+
+    //
+    // Internal - drops everything cached if the Maze has changed since it
+    // was last looked at.
+    //
+    fn sync(&mut self, maze: &Maze) {
+        let key = Some((maze.revision(), maze.cols, maze.rows));
+        if self.seen != key {
+            self.seen = key;
+            self.distances.clear();
+            self.dead_ends = None;
+            self.stats = None;
+        }
+    }
+}
+
+impl Maze {
+    /// Returns a number that changes whenever the Maze's layout does.  No
+    /// two Mazes have the same revision unless one is a clone of the other
+    /// and neither has changed since.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// let before = maze.revision();
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// assert_ne!(before, maze.revision());
+    /// ```
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Gives the Maze a new revision, and forgets the corridor regions from
+    /// partition_corridors(), which may not fit the new layout.  Every
+    /// method that changes the layout does this; call it after changing
+    /// `sq` directly.
+    pub fn mark_changed(&mut self) {
+        self.revision = next_revision();
+        self.corridor_regions.clear();
+    }
+
+    /// Returns the carved Squares that lead to exactly one other Square, in
+    /// row-major order.
+    pub fn dead_ends(&self) -> Vec<Coord> {
+        (0..self.sq.len())
+            .map(|i| self.get_coord(i))
+            .filter(|c| self.open_neighbors(c.x, c.y).len() == 1)
+            .collect()
+    }
+
+    /// Counts the carved Squares, dead ends and junctions, and measures the
+    /// longest straight corridor.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::Maze::from_compact("020\n4f8\n010").unwrap();
+    /// let stats = maze.stats();
+    /// assert_eq!((5, 4, 1), (stats.carved_squares, stats.dead_ends, stats.junctions));
+    /// // The junction in the middle splits both lines of three in two
+    /// assert_eq!(2, stats.longest_straight);
+    /// ```
+    pub fn stats(&self) -> MazeStats {
+        let mut stats = MazeStats::default();
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                if self.sq[self.get_offset(x, y)].is_carved() {
+                    stats.carved_squares += 1;
+                }
+                match self.open_neighbors(x, y).len() {
+                    1 => stats.dead_ends += 1,
+                    n if n >= 3 => stats.junctions += 1,
+                    _ => {}
+                }
+            }
+        }
+        let rows = (0..self.rows).map(|y| (0..self.cols).map(move |x| Coord::new(x, y)).collect());
+        let cols = (0..self.cols).map(|x| (0..self.rows).map(move |y| Coord::new(x, y)).collect());
+        stats.longest_straight = rows.chain(cols).map(|line: Vec<Coord>| self.longest_straight(&line)).max().unwrap_or(0);
+        stats
+    }
+
+    //
+    // Internal - returns the most Squares in a straight corridor along line,
+    // a row or column in order.
+    //
+    fn longest_straight(&self, line: &[Coord]) -> u32 {
+        let mut longest = 0;
+        let mut run = 1;
+        for pair in line.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let open = self.open_neighbors(a.x, a.y);
+            if !open.contains(&b) {
+                run = 1;
+                continue;
+            }
+            // A Square leading off the line ends one corridor and starts the next
+            run = if run > 1 && open.len() == 2 { run + 1 } else { 2 };
+            longest = longest.max(run);
+        }
+        longest
+    }
+}
+
+/// Every perfect maze of one size, from enumerate_perfect_mazes().
+#[derive(Clone, Debug)]
+pub struct PerfectMazes {
+    rows: u32,
+    cols: u32,
+    // Every passage that could be carved, as the Square it's carved from
+    // and DIR_EAST or DIR_SOUTH
+    passages: Vec<(Coord, u32)>,
+    // The passages in the next set to try, in increasing order, or None
+    // once every set has been tried
+    next: Option<Vec<usize>>,
+}
+
+impl PerfectMazes {
+    //
+    // Internal - returns whether the passages chosen join every Square
+    // without a loop.  One fewer passage than Squares and no loop is enough.
+    //
+    fn is_spanning_tree(&self, chosen: &[usize]) -> bool {
+        let mut region: Vec<usize> = (0..(self.rows * self.cols) as usize).collect();
+        for &i in chosen.iter() {
+            let (c, dir) = self.passages[i];
+            let a = (c.y * self.cols + c.x) as usize;
+            let b = if dir == constants::DIR_EAST { a + 1 } else { a + self.cols as usize };
+            let (ra, rb) = (find_region(&mut region, a), find_region(&mut region, b));
+            if ra == rb {
+                return false;
+            }
+            region[ra] = rb;
+        }
+        true
+    }
+
+    //
+    // Internal - moves on to the next set of passages, in lexicographic order.
+    //
+    fn advance(&mut self) {
+        let total = self.passages.len();
+        let next = match self.next.as_mut() {
+            Some(next) => next,
+            None => return,
+        };
+        let k = next.len();
+        match (0..k).rev().find(|&i| next[i] < total - k + i) {
+            Some(i) => {
+                next[i] += 1;
+                for j in i + 1..k {
+                    next[j] = next[j - 1] + 1;
+                }
+            }
+            None => self.next = None,
+        }
+    }
+}
+
+impl Iterator for PerfectMazes {
+    type Item = Maze;
+
+    fn next(&mut self) -> Option<Maze> {
+        loop {
+            let chosen = self.next.clone()?;
+            self.advance();
+            if !self.is_spanning_tree(&chosen) {
+                continue;
+            }
+            let mut maze = Maze::new(self.rows, self.cols);
+            if chosen.is_empty() {
+                maze.sq[0].id = constants::ID_MAZE_PATH;
+            }
+            for &i in chosen.iter() {
+                let (c, dir) = self.passages[i];
+                maze.carve(c.x, c.y, dir, constants::ID_MAZE_PATH, false).unwrap();
+            }
+            return Some(maze);
+        }
+    }
+}
+
+/// Lists every distinct perfect maze with rows rows and cols columns: every
+/// way of carving passages so that each Square can reach each other one by
+/// exactly one path, or in graph terms every spanning tree of the grid.
+/// Each maze comes up once, with no entrance or exit, and the order is the
+/// same every time.  For checking generators, which should only ever make
+/// mazes from this list.
+///
+/// Fails if either side is 0 or there are more than MAX_ENUMERATED_SQUARES
+/// Squares, as the count grows far too quickly to list bigger mazes.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::analysis::enumerate_perfect_mazes;
+///
+/// assert_eq!(192, enumerate_perfect_mazes(3, 3).unwrap().count());
+/// let mut maze = mazegame::Maze::new(3, 3);
+/// maze.generate_perfect_seeded(484).unwrap();
+/// assert!(enumerate_perfect_mazes(3, 3).unwrap().any(|m| m == maze));
+/// assert!(enumerate_perfect_mazes(4, 4).is_err());
+/// ```
+pub fn enumerate_perfect_mazes(rows: u32, cols: u32) -> Result<PerfectMazes, MazeError> {
+    if rows == 0 || cols == 0 {
+        return Err(MazeError::InvalidArgument("a maze needs at least one row and column".to_string()));
+    }
+    if rows.saturating_mul(cols) > MAX_ENUMERATED_SQUARES {
+        return Err(MazeError::InvalidArgument(format!("a {}x{} maze has too many perfect mazes to list; \
+                                                       the most Squares is {}", rows, cols, MAX_ENUMERATED_SQUARES)));
+    }
+    let mut passages = Vec::new();
+    for y in 0..rows {
+        for x in 0..cols {
+            if x + 1 < cols {
+                passages.push((Coord::new(x, y), constants::DIR_EAST));
+            }
+            if y + 1 < rows {
+                passages.push((Coord::new(x, y), constants::DIR_SOUTH));
+            }
+        }
+    }
+    let next = Some((0..(rows * cols - 1) as usize).collect());
+    Ok(PerfectMazes { rows, cols, passages, next })
+}
+
+/// The generation setting a sweep varies, and the values it tries.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum SweepParam {
+    /// Braid factors, each between 0.0 and 1.0.
+    Braid(Vec<f64>),
+    /// Numbers of rooms.  Room sizes come from the base spec, or are 2 to 4
+    /// Squares across if it has none.
+    RoomCount(Vec<u32>),
+    /// (width, height) pairs.
+    Size(Vec<(u32, u32)>),
+}
+
+#[cfg(feature = "serde")]
+impl SweepParam {
+    /// Returns the name of the setting being swept.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SweepParam::Braid(_) => "braid",
+            SweepParam::RoomCount(_) => "rooms",
+            SweepParam::Size(_) => "size",
+        }
+    }
+
+    //
+    // Internal - the specs to sample, labeled with the value they use.
+    //
+    fn specs(&self, base: &MazeSpec) -> Vec<(String, MazeSpec)> {
+        let with = |change: &dyn Fn(&mut MazeSpec)| {
+            let mut spec = base.clone();
+            change(&mut spec);
+            spec
+        };
+        match *self {
+            SweepParam::Braid(ref values) => values.iter()
+                .map(|&b| (format!("{}", b), with(&|s| s.braid = b)))
+                .collect(),
+            SweepParam::RoomCount(ref values) => values.iter()
+                .map(|&count| {
+                    let rooms = base.rooms.unwrap_or(RoomSpec { count, min_w: 2, max_w: 4, min_h: 2, max_h: 4 });
+                    (count.to_string(), with(&|s| s.rooms = Some(RoomSpec { count, ..rooms })))
+                })
+                .collect(),
+            SweepParam::Size(ref values) => values.iter()
+                .map(|&(w, h)| (format!("{}x{}", w, h), with(&|s| {
+                    s.width = w;
+                    s.height = h;
+                })))
+                .collect(),
+        }
+    }
+}
+
+/// The mean, smallest and largest of one number over a batch of samples.
+#[cfg(any(feature = "serde", feature = "std"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub mean: f64,
+    pub min: u32,
+    pub max: u32,
+}
+
+#[cfg(any(feature = "serde", feature = "std"))]
+impl Summary {
+    //
+    // Internal - summarizes values, which must not be empty.
+    //
+    fn of(values: &[u32]) -> Summary {
+        let total: u64 = values.iter().map(|&v| v as u64).sum();
+        Summary {
+            mean: total as f64 / values.len() as f64,
+            min: values.iter().cloned().min().unwrap_or(0),
+            max: values.iter().cloned().max().unwrap_or(0),
+        }
+    }
+}
+
+/// The results of one value of a sweep.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepRow {
+    /// The value tried, written the way the command line takes it.
+    pub value: String,
+    pub samples: u32,
+    pub carved_squares: Summary,
+    pub dead_ends: Summary,
+    pub junctions: Summary,
+    /// Squares on the path between the entrance and exit, both included.
+    pub solution_length: Summary,
+}
+
+/// Generates samples mazes from base for each value of param, and
+/// summarizes their MazeStats and solution lengths.  Every maze gets its
+/// entrance and exit from place_entrance_exit_longest(), whatever base
+/// says.  Sample i of every value uses seed + i, so the values are
+/// compared on the same run of seeds.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::analysis::{sweep, SweepParam};
+/// use mazegame::prelude::*;
+///
+/// let rows = sweep(&MazeSpec::new(10, 10), &SweepParam::Braid(vec![0.0, 1.0]), 5, 1).unwrap();
+/// assert_eq!(2, rows.len());
+/// // Braiding gets rid of dead ends
+/// assert!(rows[1].dead_ends.mean < rows[0].dead_ends.mean);
+/// ```
+#[cfg(feature = "serde")]
+pub fn sweep(base: &MazeSpec, param: &SweepParam, samples: u32, seed: u64) -> Result<Vec<SweepRow>, MazeError> {
+    if samples == 0 {
+        return Err(MazeError::InvalidArgument("a sweep needs at least one sample".to_string()));
+    }
+    let mut rows = Vec::new();
+    for (value, mut spec) in param.specs(base) {
+        spec.entrance = EntranceSpec::None;
+        spec.min_solution_length = 0;
+        spec.validate()?;
+
+        let mut stats = Vec::with_capacity(samples as usize);
+        let mut lengths = Vec::with_capacity(samples as usize);
+        for i in 0..samples as u64 {
+            let mut maze = spec.build_with_rng(&mut rng::from_seed(seed.wrapping_add(i)))?;
+            maze.place_entrance_exit_longest()?;
+            let (entrance, exit) = (maze.entrance.unwrap().coord, maze.exits[0].coord);
+            let path = maze.solve(entrance, exit).ok_or(MazeError::Unreachable(entrance, exit))?;
+            stats.push(maze.stats());
+            lengths.push(path.len() as u32);
+        }
+        let field = |f: fn(&MazeStats) -> u32| Summary::of(&stats.iter().map(f).collect::<Vec<u32>>());
+        rows.push(SweepRow {
+            value,
+            samples,
+            carved_squares: field(|s| s.carved_squares),
+            dead_ends: field(|s| s.dead_ends),
+            junctions: field(|s| s.junctions),
+            solution_length: Summary::of(&lengths),
+        });
+    }
+    Ok(rows)
+}
+
+/// The generators compare_algorithms() tries, with the settings it gives
+/// those that take any.
+#[cfg(feature = "std")]
+pub const COMPARED_ALGORITHMS: [GenerationAlgorithm; 5] = [
+    GenerationAlgorithm::GrowingTree,
+    GenerationAlgorithm::Woven,
+    GenerationAlgorithm::Symmetric(Symmetry::MirrorX),
+    GenerationAlgorithm::Caves { fill_prob: 0.45, smoothing_passes: 4 },
+    GenerationAlgorithm::MaxRun { max_run_length: 3 },
+];
+
+/// The header line of AlgorithmComparison::to_csv().
+#[cfg(feature = "std")]
+pub const COMPARISON_CSV_HEADER: &str = "algorithm,width,height,samples,\
+carved_mean,carved_min,carved_max,dead_ends_mean,dead_ends_min,dead_ends_max,\
+junctions_mean,junctions_min,junctions_max,straight_mean,straight_min,straight_max,\
+solution_mean,solution_min,solution_max,time_mean_ns,time_min_ns,time_max_ns";
+
+/// How one generator's mazes turned out over a batch of samples.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlgorithmProfile {
+    pub algorithm: GenerationAlgorithm,
+    pub samples: u32,
+    pub carved_squares: Summary,
+    pub dead_ends: Summary,
+    pub junctions: Summary,
+    pub longest_straight: Summary,
+    /// Squares on the path between the entrance and exit, both included.
+    pub solution_length: Summary,
+    pub mean_time: Duration,
+    pub min_time: Duration,
+    pub max_time: Duration,
+}
+
+/// The profiles compare_algorithms() made, one per generator in the order
+/// they were tried.  Displays as a table with a row per generator.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlgorithmComparison {
+    /// The (width, height) of every maze.
+    pub size: (u32, u32),
+    pub profiles: Vec<AlgorithmProfile>,
+}
+
+#[cfg(feature = "std")]
+impl AlgorithmComparison {
+    /// Writes the profiles as CSV, a line for each after
+    /// COMPARISON_CSV_HEADER, with times in nanoseconds.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(COMPARISON_CSV_HEADER);
+        out.push('\n');
+        for p in self.profiles.iter() {
+            out.push_str(&format!("{},{},{},{}", p.algorithm, self.size.0, self.size.1, p.samples));
+            for s in [p.carved_squares, p.dead_ends, p.junctions, p.longest_straight, p.solution_length].iter() {
+                out.push_str(&format!(",{},{},{}", s.mean, s.min, s.max));
+            }
+            out.push_str(&format!(",{},{},{}\n", p.mean_time.as_nanos(), p.min_time.as_nanos(),
+                                  p.max_time.as_nanos()));
+        }
+        out
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for AlgorithmComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = self.profiles.iter().map(|p| p.algorithm.to_string()).collect();
+        let width = names.iter().map(|n| n.len()).chain(Some("algorithm".len())).max().unwrap_or(0);
+        writeln!(f, "{:<width$}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}  {:>10}", "algorithm", "carved", "dead ends",
+                 "junctions", "straight", "solution", "time (us)", width = width)?;
+        for (p, name) in self.profiles.iter().zip(names.iter()) {
+            writeln!(f, "{:<width$}  {:>9.1}  {:>9.1}  {:>9.1}  {:>9.1}  {:>9.1}  {:>10.1}", name,
+                     p.carved_squares.mean, p.dead_ends.mean, p.junctions.mean, p.longest_straight.mean,
+                     p.solution_length.mean, p.mean_time.as_nanos() as f64 / 1000.0, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+/// Generates samples mazes of size (width, height) with each generator in
+/// COMPARED_ALGORITHMS, and profiles each generator's mazes as
+/// compare_given_algorithms() does.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::analysis::{compare_algorithms, COMPARED_ALGORITHMS};
+///
+/// let comparison = compare_algorithms((10, 10), 2, 474).unwrap();
+/// assert_eq!(COMPARED_ALGORITHMS.len(), comparison.profiles.len());
+/// println!("{}", comparison);
+/// ```
+#[cfg(feature = "std")]
+pub fn compare_algorithms(size: (u32, u32), samples: u32, seed: u64) -> Result<AlgorithmComparison, MazeError> {
+    compare_given_algorithms(&COMPARED_ALGORITHMS, size, samples, seed)
+}
+
+/// Generates samples mazes of size (width, height) with each of
+/// algorithms, and summarizes their MazeStats, solution lengths and how
+/// long each took to generate.  Sample i of every generator uses seed + i,
+/// so each sees the same run of seeds.  Every maze gets its entrance and
+/// exit from place_entrance_exit_longest().
+///
+/// Everything but the times is the same from one run to the next.  Fails
+/// if samples is 0 or a generator can't make a maze of the size.
+#[cfg(feature = "std")]
+pub fn compare_given_algorithms(algorithms: &[GenerationAlgorithm], size: (u32, u32), samples: u32,
+                                seed: u64) -> Result<AlgorithmComparison, MazeError> {
+    if samples == 0 {
+        return Err(MazeError::InvalidArgument("a comparison needs at least one sample".to_string()));
+    }
+    let mut profiles = Vec::with_capacity(algorithms.len());
+    for &algorithm in algorithms.iter() {
+        let mut stats = Vec::with_capacity(samples as usize);
+        let mut lengths = Vec::with_capacity(samples as usize);
+        let mut times = Vec::with_capacity(samples as usize);
+        for i in 0..samples as u64 {
+            let info = GenerationInfo { seed: seed.wrapping_add(i), algorithm, rooms: None, dimensions: size,
+                                       start: None };
+            let start = Instant::now();
+            let mut maze = info.generate()?;
+            times.push(start.elapsed());
+            maze.place_entrance_exit_longest()?;
+            let (entrance, exit) = (maze.entrance.unwrap().coord, maze.exits[0].coord);
+            let path = maze.solve(entrance, exit).ok_or(MazeError::Unreachable(entrance, exit))?;
+            stats.push(maze.stats());
+            lengths.push(path.len() as u32);
+        }
+        let field = |f: fn(&MazeStats) -> u32| Summary::of(&stats.iter().map(f).collect::<Vec<u32>>());
+        profiles.push(AlgorithmProfile {
+            algorithm,
+            samples,
+            carved_squares: field(|s| s.carved_squares),
+            dead_ends: field(|s| s.dead_ends),
+            junctions: field(|s| s.junctions),
+            longest_straight: field(|s| s.longest_straight),
+            solution_length: Summary::of(&lengths),
+            mean_time: times.iter().sum::<Duration>() / samples,
+            min_time: times.iter().cloned().min().unwrap_or_default(),
+            max_time: times.iter().cloned().max().unwrap_or_default(),
+        });
+    }
+    Ok(AlgorithmComparison { size, profiles })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+    use crate::rng;
+    use rand::Rng;
+
+    // Pearson's chi-squared statistic for how far counts are from all
+    // being equal
+    fn chi_squared(counts: &[u32]) -> f64 {
+        let expected = counts.iter().sum::<u32>() as f64 / counts.len() as f64;
+        counts.iter().map(|&c| (c as f64 - expected).powi(2) / expected).sum()
+    }
+
+    // How often each of the enumerated 3x3 mazes comes out of samples runs of
+    // generate.  Panics on a maze that isn't in the list.
+    fn distribution<F: FnMut(&mut Maze, u64)>(mut generate: F, samples: u64) -> Vec<u32> {
+        let mazes: Vec<Maze> = enumerate_perfect_mazes(3, 3).unwrap().collect();
+        let index: BTreeMap<Vec<u8>, usize> = mazes.iter().enumerate()
+            .map(|(i, m)| (m.sq.iter().map(|sq| sq.open_mask()).collect(), i))
+            .collect();
+        let mut counts = vec![0; mazes.len()];
+        for seed in 0..samples {
+            let mut maze = Maze::new(3, 3);
+            generate(&mut maze, seed);
+            let key: Vec<u8> = maze.sq.iter().map(|sq| sq.open_mask()).collect();
+            match index.get(&key) {
+                Some(&i) => counts[i] += 1,
+                None => panic!("seed {} made a maze that isn't perfect:\n{}", seed, maze.render_ascii()),
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn test_enumerate_perfect_mazes() {
+        // The number of spanning trees of each grid
+        for &(rows, cols, count) in [(1, 1, 1), (1, 5, 1), (2, 2, 4), (2, 3, 15), (3, 3, 192), (3, 4, 2415)].iter() {
+            let mazes: Vec<Maze> = enumerate_perfect_mazes(rows, cols).unwrap().collect();
+            assert_eq!(count, mazes.len(), "{}x{}", rows, cols);
+            for maze in mazes.iter() {
+                // Everything is reached, by one passage fewer than Squares
+                let passages: u32 = maze.sq.iter().map(|sq| sq.open_mask().count_ones()).sum();
+                assert_eq!((rows * cols - 1) * 2, passages);
+                assert_eq!((rows * cols) as usize, maze.distances(Coord::new(0, 0)).iter().flatten().count());
+            }
+            let keys: BTreeMap<Vec<u8>, ()> = mazes.iter()
+                .map(|m| (m.sq.iter().map(|sq| sq.open_mask()).collect(), ()))
+                .collect();
+            assert_eq!(count, keys.len());
+        }
+        assert!(enumerate_perfect_mazes(0, 3).is_err());
+        assert!(enumerate_perfect_mazes(2, 7).is_err());
+        assert!(enumerate_perfect_mazes(u32::MAX, u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_generator_distributions() {
+        const SAMPLES: u64 = 4000;
+        // The chi-squared statistic over 192 mazes falls below this for all
+        // but about 1 in 1000 uniform samplers
+        const UNIFORM_LIMIT: f64 = 260.0;
+
+        // Picking from the list at random is as uniform as it gets, and
+        // checks the test itself
+        let mazes: Vec<Maze> = enumerate_perfect_mazes(3, 3).unwrap().collect();
+        let mut picker = rng::from_seed(484);
+        let uniform = distribution(|maze, _| *maze = mazes[picker.gen_range(0, mazes.len())].clone(), SAMPLES);
+        assert!(chi_squared(&uniform) < UNIFORM_LIMIT, "{}", chi_squared(&uniform));
+
+        // The growing tree makes long winding corridors, so some mazes come
+        // up far more often than others, and some hardly ever
+        let growing = distribution(|maze, seed| maze.generate_perfect_seeded(seed).unwrap(), SAMPLES);
+        assert!(chi_squared(&growing) > UNIFORM_LIMIT * 4.0, "{}", chi_squared(&growing));
+        let max_run = distribution(|maze, seed| maze.generate_perfect_max_run_seeded(2, seed).unwrap(), SAMPLES);
+        assert!(chi_squared(&max_run) > UNIFORM_LIMIT, "{}", chi_squared(&max_run));
+        distribution(|maze, seed| { maze.generate_spanning_tree_seeded(seed).unwrap(); }, SAMPLES);
+    }
+
+    #[test]
+    fn test_cached_until_changed() {
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_seeded(434).unwrap();
+        let mut analysis = MazeAnalysis::new();
+        let origin = Coord::new(0, 0);
+
+        let distances = analysis.distance_map_cached(&maze, origin).to_vec();
+        let dead_ends = analysis.dead_ends_cached(&maze).to_vec();
+        let stats = analysis.stats_cached(&maze);
+        assert_eq!(3, analysis.computations);
+        assert_eq!(maze.distances(origin), distances);
+        assert_eq!(stats.dead_ends as usize, dead_ends.len());
+
+        // Asking again does no work
+        for _ in 0..3 {
+            assert_eq!(&distances[..], analysis.distance_map_cached(&maze, origin));
+            assert_eq!(&dead_ends[..], analysis.dead_ends_cached(&maze));
+            assert_eq!(stats, analysis.stats_cached(&maze));
+        }
+        assert_eq!(3, analysis.computations);
+        // A different source is a separate map
+        analysis.distance_map_cached(&maze, Coord::new(7, 7));
+        assert_eq!(4, analysis.computations);
+
+        // Braiding knocks walls down, so everything is worked out again
+        maze.braid_with_rng(1.0, &mut rng::from_seed(1));
+        assert!(analysis.dead_ends_cached(&maze).len() < dead_ends.len());
+        assert_ne!(stats, analysis.stats_cached(&maze));
+        assert_eq!(6, analysis.computations);
+        analysis.distance_map_cached(&maze, origin);
+        assert_eq!(7, analysis.computations);
+    }
+
+    #[test]
+    fn test_clones_and_other_mazes() {
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_seeded(434).unwrap();
+        let mut analysis = MazeAnalysis::new();
+        let stats = analysis.stats_cached(&maze);
+
+        // An unchanged clone can share the results
+        let mut copy = maze.clone();
+        assert_eq!(stats, analysis.stats_cached(&copy));
+        assert_eq!(1, analysis.computations);
+
+        // Changing each a different way gives them different revisions
+        maze.braid_with_rng(1.0, &mut rng::from_seed(1));
+        copy.braid_with_rng(0.5, &mut rng::from_seed(2));
+        assert_ne!(maze.revision(), copy.revision());
+        assert_eq!(maze.stats(), analysis.stats_cached(&maze));
+        assert_eq!(copy.stats(), analysis.stats_cached(&copy));
+        assert_eq!(3, analysis.computations);
+
+        // So do separately made mazes of the same size
+        let mut other = Maze::new(8, 8);
+        other.generate_perfect_seeded(1).unwrap();
+        assert_eq!(other.stats(), analysis.stats_cached(&other));
+        assert_eq!(4, analysis.computations);
+    }
+
+    #[test]
+    fn test_mutators_bump_revision() {
+        let mut maze = Maze::new(4, 4);
+        let mut last = maze.revision();
+        let mut changed = |maze: &Maze| {
+            let bumped = maze.revision() != last;
+            last = maze.revision();
+            bumped
+        };
+
+        maze.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        assert!(changed(&maze));
+        maze.break_wall_pair_checked(1, 1, constants::DIR_SOUTH, false).unwrap();
+        assert!(changed(&maze));
+        maze.carve_one_way(2, 2, constants::DIR_EAST).unwrap();
+        assert!(changed(&maze));
+        maze.set_entrance(0, 3, constants::DIR_WEST).unwrap();
+        assert!(changed(&maze));
+        let prefab = Maze::from_compact("4a\n01").unwrap();
+        maze.stamp(&prefab, Coord::new(2, 0), crate::maze::prefab::StampMode::Overwrite).unwrap();
+        assert!(changed(&maze));
+        maze.reset();
+        assert!(changed(&maze));
+        maze.generate_perfect_seeded(1).unwrap();
+        assert!(changed(&maze));
+
+        // Reading doesn't
+        maze.stats();
+        maze.solve(Coord::new(0, 0), Coord::new(3, 3));
+        assert!(!changed(&maze));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_tiny_sweep() {
+        let base = MazeSpec::new(12, 10);
+        for param in [SweepParam::Braid(vec![0.0, 0.5]),
+                      SweepParam::RoomCount(vec![0, 3]),
+                      SweepParam::Size(vec![(6, 6), (12, 10)])].iter() {
+            let rows = sweep(&base, param, 3, 447).unwrap();
+            assert_eq!(2, rows.len(), "{}", param.name());
+            for row in rows.iter() {
+                assert_eq!(3, row.samples);
+                for s in [row.carved_squares, row.dead_ends, row.junctions, row.solution_length].iter() {
+                    assert!(s.min as f64 <= s.mean && s.mean <= s.max as f64);
+                }
+                assert!(row.carved_squares.max <= 120);
+                assert!(row.solution_length.min >= 2);
+                assert!(row.solution_length.max <= row.carved_squares.max);
+                assert!(row.dead_ends.max + row.junctions.max <= row.carved_squares.max);
+            }
+            assert_eq!(rows, sweep(&base, param, 3, 447).unwrap());
+        }
+
+        let rows = sweep(&base, &SweepParam::Size(vec![(6, 6), (12, 10)]), 3, 447).unwrap();
+        assert_eq!(("6x6", 36.0), (rows[0].value.as_str(), rows[0].carved_squares.mean));
+        assert_eq!(120, rows[1].carved_squares.min);
+        assert!(sweep(&base, &SweepParam::Braid(vec![1.5]), 3, 1).is_err());
+        assert!(sweep(&base, &SweepParam::Braid(vec![0.5]), 0, 1).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_tiny_comparison() {
+        let algorithms = [GenerationAlgorithm::GrowingTree, GenerationAlgorithm::MaxRun { max_run_length: 2 }];
+        let comparison = compare_given_algorithms(&algorithms, (15, 15), 3, 474).unwrap();
+        assert_eq!(2, comparison.profiles.len());
+        for (p, &algorithm) in comparison.profiles.iter().zip(algorithms.iter()) {
+            assert_eq!((algorithm, 3), (p.algorithm, p.samples));
+            // Perfect mazes carve every Square
+            assert_eq!((225.0, 225, 225), (p.carved_squares.mean, p.carved_squares.min, p.carved_squares.max));
+            for s in [p.dead_ends, p.junctions, p.longest_straight, p.solution_length].iter() {
+                assert!(s.min > 0 && s.min as f64 <= s.mean && s.mean <= s.max as f64, "{:?}", p);
+            }
+            assert!(p.solution_length.max <= 225);
+            assert!(p.min_time > Duration::from_nanos(0));
+            assert!(p.min_time <= p.mean_time && p.mean_time <= p.max_time);
+        }
+
+        // The same seed gives the same mazes, if not the same times
+        let untimed = |c: AlgorithmComparison| c.profiles.into_iter()
+            .map(|p| AlgorithmProfile { mean_time: Duration::from_nanos(0), min_time: Duration::from_nanos(0),
+                                        max_time: Duration::from_nanos(0), ..p })
+            .collect::<Vec<AlgorithmProfile>>();
+        let again = compare_given_algorithms(&algorithms, (15, 15), 3, 474).unwrap();
+        assert_eq!(untimed(comparison.clone()), untimed(again));
+        let other = compare_given_algorithms(&algorithms, (15, 15), 3, 475).unwrap();
+        assert_ne!(untimed(comparison.clone()), untimed(other));
+
+        let table = comparison.to_string();
+        assert_eq!(3, table.lines().count());
+        assert!(table.lines().nth(2).unwrap().starts_with("max_run:2 "), "{}", table);
+        let csv = comparison.to_csv();
+        assert_eq!(COMPARISON_CSV_HEADER, csv.lines().next().unwrap());
+        assert!(csv.lines().nth(1).unwrap().starts_with("growing_tree,15,15,3,225,225,225,"), "{}", csv);
+        assert!(csv.lines().all(|line| line.split(',').count() == 22));
+
+        assert!(compare_given_algorithms(&algorithms, (15, 15), 0, 474).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_every_algorithm() {
+        let comparison = compare_algorithms((12, 12), 2, 474).unwrap();
+        let names: Vec<String> = comparison.profiles.iter().map(|p| p.algorithm.to_string()).collect();
+        assert_eq!(vec!["growing_tree", "woven", "symmetric:mirror_x", "caves:0.45:4", "max_run:3"], names);
+    }
+}
@@ -0,0 +1,222 @@
+//! Anchor points for decorating rooms: the Squares furniture, torches and
+//! the like look right in.  They're worked out from which Squares are in
+//! the room rather than from a bounding box, so rooms of any shape get
+//! sensible anchors.
+//!
+//! A side of a room Square is exterior-facing when the Square on the other
+//! side isn't in the room (or there's no Square there).  A doorway is an
+//! exterior-facing side that's open.
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::maze::room::Room;
+use crate::maze::{Coord, Maze, Opening};
+
+/// The anchor Squares of one room.  Each list is in row-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoomAnchors {
+    /// Squares with two exterior-facing sides that meet, like the four
+    /// corners of a rectangle.  The inside corner of an L has none, so it
+    /// isn't one.
+    pub corners: Vec<Coord>,
+    /// Squares with an exterior-facing side that aren't corners and don't
+    /// hold a doorway, where things can stand against the wall without
+    /// blocking the way in.
+    pub along_walls: Vec<Coord>,
+    /// The room Square nearest the room's middle.  If several are as near,
+    /// the first in row-major order.
+    pub center: Coord,
+    /// Every doorway, as the room Square it's in and the side it's on.
+    pub doors: Vec<Opening>,
+    /// The room Squares either side of each doorway, along the wall it's
+    /// in.  These can be corners or along the walls too.
+    pub door_flanks: Vec<Coord>,
+}
+
+impl RoomAnchors {
+    /// Returns every anchor Square once, in row-major order.
+    pub fn all(&self) -> Vec<Coord> {
+        let mut all: Vec<Coord> = self.corners.iter()
+            .chain(self.along_walls.iter())
+            .chain(core::iter::once(&self.center))
+            .chain(self.doors.iter().map(|o| &o.coord))
+            .chain(self.door_flanks.iter())
+            .cloned()
+            .collect();
+        sort_row_major(&mut all);
+        all
+    }
+}
+
+impl Room {
+    /// Works out the room's anchors in maze, which must be the Maze the
+    /// room came from.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(9, 9);
+    /// maze.generate_seeded((1, 3, 3, 3, 3), 1).unwrap();
+    /// maze.connect_regions();
+    /// let room = maze.room(1).unwrap();
+    /// let anchors = room.anchors(&maze);
+    /// assert_eq!(4, anchors.corners.len());
+    /// let middle = room.cells()[4];
+    /// assert_eq!(middle, anchors.center);
+    /// assert!(!anchors.doors.is_empty());
+    /// ```
+    pub fn anchors(&self, maze: &Maze) -> RoomAnchors {
+        let cells = self.cells();
+        let exterior = |c: Coord, dir: u32| maze.neighbor(c.x, c.y, dir).is_none_or(|n| !self.contains(n));
+
+        let mut anchors = RoomAnchors {
+            corners: Vec::new(),
+            along_walls: Vec::new(),
+            center: cells.first().cloned().unwrap_or(Coord::new(0, 0)),
+            doors: Vec::new(),
+            door_flanks: Vec::new(),
+        };
+        for &c in cells.iter() {
+            let sq = &maze.sq[maze.get_offset(c.x, c.y)];
+            let outside: Vec<Direction> = Direction::ALL.iter().cloned().filter(|&d| exterior(c, d.into())).collect();
+            let doors: Vec<u32> = outside.iter().map(|&d| u32::from(d)).filter(|&d| !sq.is_wall_present(d)).collect();
+
+            let corner = outside.iter().any(|&a| outside.iter().any(|&b| b != a && b != a.opposite()));
+            if corner {
+                anchors.corners.push(c);
+            } else if !outside.is_empty() && doors.is_empty() {
+                anchors.along_walls.push(c);
+            }
+            for dir in doors {
+                anchors.doors.push(Opening { coord: c, dir });
+                for side in sideways(dir).iter() {
+                    if let Some(n) = maze.neighbor(c.x, c.y, *side).filter(|&n| self.contains(n)) {
+                        anchors.door_flanks.push(n);
+                    }
+                }
+            }
+        }
+        sort_row_major(&mut anchors.door_flanks);
+
+        // Nearest the mean position, compared in whole numbers by scaling
+        // everything by the number of cells
+        if !cells.is_empty() {
+            let n = cells.len() as i64;
+            let (sum_x, sum_y) = cells.iter().fold((0, 0), |(sx, sy), c| (sx + c.x as i64, sy + c.y as i64));
+            let off = |c: &Coord| {
+                let (dx, dy) = (c.x as i64 * n - sum_x, c.y as i64 * n - sum_y);
+                dx * dx + dy * dy
+            };
+            anchors.center = *cells.iter().min_by_key(|c| off(c)).unwrap();
+        }
+        anchors
+    }
+}
+
+impl Maze {
+    /// Returns the anchors of room room_id, or None if there's no such
+    /// room.
+    pub fn room_anchors(&self, room_id: i32) -> Option<RoomAnchors> {
+        self.room(room_id).map(|room| room.anchors(self))
+    }
+}
+
+//
+// Internal - the two directions at right angles to dir.
+//
+fn sideways(dir: u32) -> [u32; 2] {
+    match dir {
+        constants::DIR_NORTH | constants::DIR_SOUTH => [constants::DIR_WEST, constants::DIR_EAST],
+        _ => [constants::DIR_NORTH, constants::DIR_SOUTH],
+    }
+}
+
+//
+// Internal - sorts coords into row-major order, dropping repeats.
+//
+fn sort_row_major(coords: &mut Vec<Coord>) {
+    coords.sort_by_key(|c| (c.y, c.x));
+    coords.dedup();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH, DIR_WEST};
+
+    // Carves every pair of neighboring cells into room 1
+    fn room_of(maze: &mut Maze, cells: &[(u32, u32)]) -> Room {
+        for &(x, y) in cells.iter() {
+            for &dir in [DIR_EAST, DIR_SOUTH].iter() {
+                let n = maze.neighbor(x, y, dir).unwrap();
+                if cells.contains(&(n.x, n.y)) {
+                    maze.carve(x, y, dir, 1, false).unwrap();
+                }
+            }
+            let offset = maze.get_offset(x, y);
+            maze.sq[offset].id = 1;
+        }
+        maze.num_rooms = 1;
+        maze.room(1).unwrap()
+    }
+
+    fn coords(cells: &[(u32, u32)]) -> Vec<Coord> {
+        cells.iter().map(|&(x, y)| Coord::new(x, y)).collect()
+    }
+
+    #[test]
+    fn test_rectangle() {
+        // A 5x3 room with doorways on its north and west walls
+        let mut maze = Maze::new(7, 9);
+        let cells: Vec<(u32, u32)> = (2..5).flat_map(|y| (2..7).map(move |x| (x, y))).collect();
+        let room = room_of(&mut maze, &cells);
+        maze.carve(4, 2, DIR_NORTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(2, 3, DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+
+        let anchors = room.anchors(&maze);
+        assert_eq!(coords(&[(2, 2), (6, 2), (2, 4), (6, 4)]), anchors.corners);
+        assert_eq!(Coord::new(4, 3), anchors.center);
+        assert_eq!(vec![Opening { coord: Coord::new(4, 2), dir: DIR_NORTH },
+                        Opening { coord: Coord::new(2, 3), dir: DIR_WEST }], anchors.doors);
+        assert_eq!(coords(&[(2, 2), (3, 2), (5, 2), (2, 4)]), anchors.door_flanks);
+        // The rest of the edge, less the doorways
+        assert_eq!(coords(&[(3, 2), (5, 2), (6, 3), (3, 4), (4, 4), (5, 4)]), anchors.along_walls);
+        assert_eq!(13, anchors.all().len());
+    }
+
+    #[test]
+    fn test_l_shape() {
+        // A 4x4 block with its top right 2x2 missing: five outside corners,
+        // and the inside corner isn't one
+        let mut maze = Maze::new(6, 6);
+        let cells = [(1, 1), (2, 1), (1, 2), (2, 2), (1, 3), (2, 3), (3, 3), (4, 3), (1, 4), (2, 4), (3, 4), (4, 4)];
+        let room = room_of(&mut maze, &cells);
+        let anchors = room.anchors(&maze);
+        assert_eq!(coords(&[(1, 1), (2, 1), (4, 3), (1, 4), (4, 4)]), anchors.corners);
+        assert!(!anchors.corners.contains(&Coord::new(2, 3)));
+        assert!(anchors.doors.is_empty() && anchors.door_flanks.is_empty());
+        assert!(room.contains(anchors.center));
+        assert_eq!(coords(&[(1, 2), (2, 2), (1, 3), (3, 3), (2, 4), (3, 4)]), anchors.along_walls);
+    }
+
+    #[test]
+    fn test_odd_rooms() {
+        // A single Square is its own corner and center
+        let mut maze = Maze::new(3, 3);
+        let room = room_of(&mut maze, &[(1, 1)]);
+        let anchors = room.anchors(&maze);
+        assert_eq!(coords(&[(1, 1)]), anchors.corners);
+        assert_eq!(Coord::new(1, 1), anchors.center);
+
+        // A straight run has corners only at its ends
+        let mut maze = Maze::new(3, 6);
+        let room = room_of(&mut maze, &[(1, 1), (2, 1), (3, 1), (4, 1)]);
+        let anchors = room.anchors(&maze);
+        assert_eq!(coords(&[(1, 1), (4, 1)]), anchors.corners);
+        assert_eq!(coords(&[(2, 1), (3, 1)]), anchors.along_walls);
+        assert_eq!(Coord::new(2, 1), anchors.center);
+        assert_eq!(None, maze.room_anchors(2));
+        assert_eq!(Some(anchors), maze.room_anchors(1));
+    }
+}
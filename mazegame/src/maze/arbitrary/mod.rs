@@ -0,0 +1,293 @@
+//! Random, valid mazes for property testing code built on this crate.
+//!
+//! Maze::arbitrary_with_rng() needs no extra dependencies.  With the
+//! `quickcheck` feature, Maze also implements `quickcheck::Arbitrary`, built
+//! on the same generator and shrinking toward smaller mazes.
+use alloc::vec::Vec;
+use rand::Rng;
+#[cfg(feature = "quickcheck")]
+use quickcheck::{Arbitrary, Gen};
+
+use crate::constants::{self, Direction};
+use crate::maze::{Coord, Maze, Opening};
+#[cfg(feature = "quickcheck")]
+use crate::rng;
+
+/// The largest maze the quickcheck Arbitrary impl will generate, whatever
+/// the Gen's size.
+#[cfg(feature = "quickcheck")]
+pub const MAX_ARBITRARY_DIMENSION: u32 = 24;
+
+impl Maze {
+    /// Creates a random maze no more than `max_dim` Squares in either
+    /// direction.  The maze is one of: a perfect maze, a braided maze, a maze
+    /// with rooms, or a perfect maze with randomly knocked down (and then
+    /// repaired) walls.  Every maze it returns has consistent walls.
+    ///
+    /// # Example
+    /// ```
+    /// let mut rng = mazegame::rng::from_seed(4);
+    /// let maze = mazegame::Maze::arbitrary_with_rng(&mut rng, 10);
+    /// assert!(maze.get_cols() <= 10 && maze.get_rows() <= 10);
+    /// assert!(maze.walls_consistent());
+    /// ```
+    pub fn arbitrary_with_rng<R: Rng>(rng: &mut R, max_dim: u32) -> Maze {
+        let max_dim = max_dim.max(2);
+        let (mut cols, rows) = (rng.gen_range(1, max_dim + 1), rng.gen_range(1, max_dim + 1));
+        // The generators need somewhere to go from the first Square
+        if cols * rows < 2 {
+            cols = 2;
+        }
+        let mut maze = Maze::new(rows, cols);
+
+        match rng.gen_range(0, 4) {
+            0 => {
+                maze.generate_perfect_with_rng(rng).unwrap();
+                maze.braid_with_rng(rng.gen::<f64>(), rng);
+            }
+            1 if cols >= 5 && rows >= 5 => {
+                let (min_w, min_h) = (rng.gen_range(1, 3), rng.gen_range(1, 3));
+                let max_w = rng.gen_range(min_w, cols - 1);
+                let max_h = rng.gen_range(min_h, rows - 1);
+                let count = rng.gen_range(1, 5);
+                maze.generate_with_rng((count, min_w, max_w, min_h, max_h), rng).unwrap();
+            }
+            2 => {
+                maze.generate_perfect_with_rng(rng).unwrap();
+                for _ in 0..rng.gen_range(1, cols * rows + 1) {
+                    // Only one side of the wall is touched; repair_walls() fixes up the other
+                    let offset = rng.gen_range(0, maze.sq.len());
+                    let dir = rng.gen_range(0, constants::NUM_DIRECTIONS);
+                    maze.sq[offset].break_wall(dir);
+                }
+                maze.repair_walls();
+            }
+            _ => maze.generate_perfect_with_rng(rng).unwrap(),
+        }
+
+        if rng.gen() {
+            maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+            maze.set_exit(cols - 1, rows - 1, constants::DIR_SOUTH).unwrap();
+        }
+        maze
+    }
+
+    /// Returns whether every wall is the same when seen from both sides, and
+    /// whether the outer wall is only open at the entrance and exit.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(2, 2);
+    /// assert!(maze.walls_consistent());
+    /// maze.sq[0].break_wall(DIR_EAST);
+    /// assert!(!maze.walls_consistent());
+    /// ```
+    pub fn walls_consistent(&self) -> bool {
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    if self.sq[self.get_offset(x, y)].is_wall_present(dir) != self.expected_wall(x, y, dir) {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Makes the walls consistent: a wall that's open on either side is
+    /// opened on both, and the outer wall is closed everywhere but the
+    /// entrance and exit.  Returns the number of wall sides changed.
+    pub fn repair_walls(&mut self) -> u32 {
+        let mut fixed = 0;
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    let expected = self.expected_wall(x, y, dir);
+                    let offset = self.get_offset(x, y);
+                    if self.sq[offset].is_wall_present(dir) != expected {
+                        if expected {
+                            self.sq[offset].build_wall(dir);
+                        } else {
+                            self.sq[offset].break_wall(dir);
+                        }
+                        fixed += 1;
+                    }
+                }
+            }
+        }
+        if fixed > 0 {
+            self.mark_changed();
+        }
+        fixed
+    }
+
+    /// Returns smaller versions of the maze, for shrinking failed property
+    /// tests: the top left half, quarter and one-Square-smaller corners of
+    /// the maze, each with its walls repaired.  The smallest candidates come
+    /// first.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(6, 6);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let smaller = maze.shrink_candidates();
+    /// assert!(!smaller.is_empty());
+    /// assert!(smaller.iter().all(|m| m.sq.len() < maze.sq.len() && m.walls_consistent()));
+    /// ```
+    pub fn shrink_candidates(&self) -> Vec<Maze> {
+        let (cols, rows) = (self.cols, self.rows);
+        let mut sizes = vec![(cols / 2, rows / 2), (cols / 2, rows), (cols, rows / 2),
+                             (cols - 1, rows), (cols, rows - 1)];
+        sizes.retain(|&(c, r)| c > 0 && r > 0 && c * r < cols * rows);
+        sizes.sort_by_key(|&(c, r)| (c * r, c, r));
+        sizes.dedup();
+        sizes.into_iter().map(|(c, r)| self.crop_corner(c, r)).collect()
+    }
+
+    //
+    // Internal - whether the wall on side dir of (x, y) should be present,
+    // judging by the Square on the other side of it (or, for the outer wall,
+    // by the recorded entrance and exit).  One-way passages are only open on
+    // the side they lead out of.
+    //
+    pub(crate) fn expected_wall(&self, x: u32, y: u32, dir: u32) -> bool {
+        let sq = &self.sq[self.get_offset(x, y)];
+        let here = sq.is_wall_present(dir);
+        match (self.neighbor(x, y, dir), Direction::from_u32(dir)) {
+            (Some(n), Some(side)) => {
+                let back = u32::from(side.opposite());
+                let there = &self.sq[self.get_offset(n.x, n.y)];
+                if sq.is_one_way(dir) {
+                    false
+                } else if there.is_one_way(back) {
+                    true
+                } else {
+                    here && there.is_wall_present(back)
+                }
+            }
+            _ => {
+                let opening = Opening { coord: Coord::new(x, y), dir };
+                Some(opening) != self.entrance && !self.exits.contains(&opening)
+            }
+        }
+    }
+
+    //
+    // Internal - copies the top left cols x rows corner of the maze, keeping
+    // any entrance or exits that are still on the border.
+    //
+    fn crop_corner(&self, cols: u32, rows: u32) -> Maze {
+        let mut maze = Maze::new(rows, cols);
+        for y in 0..rows {
+            for x in 0..cols {
+                maze.sq[(y * cols + x) as usize] = self.sq[self.get_offset(x, y)].clone();
+            }
+        }
+        let mut room_ids: Vec<i32> = maze.sq.iter().filter(|sq| sq.is_part_of_room()).map(|sq| sq.id).collect();
+        room_ids.sort();
+        room_ids.dedup();
+        maze.num_rooms = room_ids.len() as u32;
+
+        if let Some(o) = self.entrance {
+            let _ = maze.set_entrance(o.coord.x, o.coord.y, o.dir);
+        }
+        for o in self.exits.iter() {
+            let _ = maze.add_exit(o.coord.x, o.coord.y, o.dir);
+        }
+        maze.repair_walls();
+        maze
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+impl Arbitrary for Maze {
+    fn arbitrary(g: &mut Gen) -> Maze {
+        let max_dim = (g.size() as u32).clamp(2, MAX_ARBITRARY_DIMENSION);
+        Maze::arbitrary_with_rng(&mut rng::from_seed(u64::arbitrary(g)), max_dim)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Maze>> {
+        Box::new(self.shrink_candidates().into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+
+    // Checks that path is a walk from `from` to `to` through open walls.
+    fn valid_path(maze: &Maze, path: &[Coord], from: Coord, to: Coord) -> bool {
+        path.first() == Some(&from) && path.last() == Some(&to) &&
+            path.windows(2).all(|step| maze.open_neighbors(step[0].x, step[0].y).contains(&step[1]))
+    }
+
+    fn prop_walls_consistent(maze: Maze) -> bool {
+        maze.walls_consistent() && maze.shrink_candidates().iter().all(|m| m.walls_consistent())
+    }
+
+    fn prop_solutions_valid(maze: Maze, a: (u32, u32), b: (u32, u32)) -> bool {
+        let from = Coord::new(a.0 % maze.get_cols(), a.1 % maze.get_rows());
+        let to = Coord::new(b.0 % maze.get_cols(), b.1 % maze.get_rows());
+        match maze.solve(from, to) {
+            Some(path) => valid_path(&maze, &path, from, to),
+            None => true,
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_walls_consistent() {
+        let mut rng = rng::from_seed(406);
+        for _ in 0..300 {
+            assert!(prop_walls_consistent(Maze::arbitrary_with_rng(&mut rng, 16)));
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_solutions_valid() {
+        let mut rng = rng::from_seed(407);
+        for _ in 0..300 {
+            let maze = Maze::arbitrary_with_rng(&mut rng, 16);
+            let (a, b) = ((rng.gen(), rng.gen()), (rng.gen(), rng.gen()));
+            assert!(prop_solutions_valid(maze, a, b));
+        }
+    }
+
+    #[test]
+    fn test_repair_walls() {
+        let mut maze = Maze::new(3, 3);
+        maze.sq[4].break_wall(constants::DIR_EAST);
+        maze.sq[0].break_wall(constants::DIR_NORTH);
+        assert_eq!(2, maze.repair_walls());
+        assert!(maze.walls_consistent());
+        // The one-sided interior opening is completed, the stray border one closed
+        assert!(!maze.sq[5].is_wall_present(constants::DIR_WEST));
+        assert!(maze.sq[0].is_wall_present(constants::DIR_NORTH));
+    }
+
+    #[test]
+    fn test_shrink_candidates() {
+        let mut maze = Maze::new(5, 4);
+        maze.generate_perfect_seeded(2).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        for smaller in maze.shrink_candidates() {
+            assert!(smaller.sq.len() < maze.sq.len());
+            // The entrance is in the top left corner, so it survives cropping
+            assert_eq!(maze.get_entrance(), smaller.get_entrance());
+        }
+        assert!(Maze::new(1, 1).shrink_candidates().is_empty());
+    }
+
+    #[cfg(feature = "quickcheck")]
+    #[test]
+    fn test_quickcheck_properties() {
+        use quickcheck::QuickCheck;
+
+        QuickCheck::new().tests(200).quickcheck(prop_walls_consistent as fn(Maze) -> bool);
+        QuickCheck::new().tests(200)
+                         .quickcheck(prop_solutions_valid as fn(Maze, (u32, u32), (u32, u32)) -> bool);
+    }
+}
@@ -0,0 +1,131 @@
+//! Perfect mazes whose grain flows toward a target Square.  Each time the
+//! generator picks which way to carve, it goes toward the target (any
+//! direction that brings it closer, counted in Manhattan distance) with a
+//! chance of strength, and picks from every direction as usual otherwise.
+//! Passages still reach everywhere, so the maze stays perfect; they just
+//! tend to run toward the target.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::maze::generator::{self, Carvable};
+use crate::maze::info::GenerationAlgorithm;
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+impl Maze {
+    /// Generates a perfect maze from a seed, carving toward target with a
+    /// chance of strength at every step.  strength runs from 0.0, which
+    /// gives the same maze as generate_perfect_seeded(), to 1.0, which goes
+    /// toward target whenever it can.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(16, 16);
+    /// maze.generate_perfect_biased_seeded(Coord::new(15, 15), 0.8, 42).unwrap();
+    /// assert!(maze.solve(Coord::new(0, 0), Coord::new(15, 15)).is_some());
+    /// assert!(maze.generate_perfect_biased_seeded(Coord::new(16, 0), 0.8, 42).is_err());
+    /// ```
+    pub fn generate_perfect_biased_seeded(&mut self, target: Coord, strength: f32, seed: u64) -> Result<(), String> {
+        self.generate_perfect_biased_with_rng(target, strength, &mut rng::from_seed(seed))?;
+        self.record_generation(seed, GenerationAlgorithm::BiasToward { target, strength }, None);
+        Ok(())
+    }
+
+    /// Generates a perfect maze biased toward target using the supplied
+    /// random number generator.
+    pub fn generate_perfect_biased_with_rng<R: Rng>(&mut self, target: Coord, strength: f32, rng: &mut R)
+                                                    -> Result<(), String> {
+        if !self.in_bounds(target.x, target.y) {
+            return Err(format!("Target ({}, {}) is outside the maze", target.x, target.y));
+        }
+        if !(0.0..=1.0).contains(&strength) {
+            return Err("Bias strength must be from 0.0 to 1.0".to_string());
+        }
+        let start = self.get_start();
+        self.measure_generation(|maze| {
+            let stats = generator::growing_tree(&mut Biased { maze, target, strength }, start, rng)?;
+            gen_debug!("growing_tree x={} y={} carved={} backtracks={} bias={},{}:{}", start.x, start.y,
+                       stats.carved, stats.backtracks, target.x, target.y, strength);
+            Ok(stats)
+        })
+    }
+}
+
+//
+// Internal - a Maze being carved with a pull toward a target Square.
+//
+struct Biased<'a> {
+    maze: &'a mut Maze,
+    target: Coord,
+    strength: f32,
+}
+
+impl<'a> Carvable for Biased<'a> {
+    type Cell = Coord;
+
+    fn uncarved_directions(&self, cell: Coord) -> Vec<u32> {
+        self.maze.uncarved_directions(cell)
+    }
+
+    fn carve_toward(&mut self, cell: Coord, dir: u32) -> Result<Coord, String> {
+        self.maze.carve_toward(cell, dir)
+    }
+
+    fn pick_direction<R: Rng>(&self, cell: Coord, rng: &mut R) -> (bool, u32) {
+        let directions = self.uncarved_directions(cell);
+        if directions.is_empty() {
+            return (false, 0);
+        }
+        let distance = |c: Coord| c.x.abs_diff(self.target.x) + c.y.abs_diff(self.target.y);
+        let closer: Vec<u32> = directions.iter().cloned()
+            .filter(|&dir| self.maze.neighbor(cell.x, cell.y, dir).is_some_and(|c| distance(c) < distance(cell)))
+            .collect();
+        // No roll at a strength of 0, so the plain generator's choices are
+        // made with the same random numbers
+        if self.strength > 0.0 && !closer.is_empty() && rng.gen::<f32>() < self.strength {
+            (true, closer[rng.gen_range(0, closer.len())])
+        } else {
+            (true, directions[rng.gen_range(0, directions.len())])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::test_mazes::assert_perfect;
+
+    #[test]
+    fn test_shorter_paths() {
+        let target = Coord::new(19, 19);
+        let (mut biased_total, mut plain_total) = (0, 0);
+        for seed in 0..20 {
+            let mut biased = Maze::new(20, 20);
+            biased.generate_perfect_biased_seeded(target, 0.9, seed).unwrap();
+            assert_perfect(&biased);
+            biased_total += biased.solve(Coord::new(0, 0), target).unwrap().len();
+
+            let mut plain = Maze::new(20, 20);
+            plain.generate_perfect_seeded(seed).unwrap();
+            plain_total += plain.solve(Coord::new(0, 0), target).unwrap().len();
+        }
+        assert!(biased_total < plain_total, "{} vs {}", biased_total, plain_total);
+    }
+
+    #[test]
+    fn test_no_bias() {
+        let mut a = Maze::new(12, 12);
+        let mut b = Maze::new(12, 12);
+        a.generate_perfect_biased_seeded(Coord::new(11, 11), 0.0, 5).unwrap();
+        b.generate_perfect_seeded(5).unwrap();
+        assert_eq!(a.sq, b.sq);
+        assert_eq!(Some(GenerationAlgorithm::BiasToward { target: Coord::new(11, 11), strength: 0.0 }),
+                   a.generation_params().map(|g| g.algorithm));
+
+        assert!(a.generate_perfect_biased_seeded(Coord::new(0, 0), 1.5, 5).is_err());
+        assert!(a.generate_perfect_biased_seeded(Coord::new(0, 0), f32::NAN, 5).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+//! The outer wall: which Squares it runs along, where it's been broken, and
+//! quick entrance and exit placement on it, either on opposite sides or as
+//! far apart as possible.
+//!
+//! A Square is on the border when it has a side with no neighbor, so the
+//! edges of a Torus axis, which wrap around, aren't border.
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze, Opening};
+use crate::rng;
+
+impl Maze {
+    /// Returns whether (x, y) is on the border, with at least one side
+    /// facing out of the maze.  False for positions outside the maze.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::Maze::new(3, 3);
+    /// assert!(maze.is_border_cell(0, 1));
+    /// assert!(!maze.is_border_cell(1, 1));
+    /// assert!(!maze.is_border_cell(3, 1));
+    /// ```
+    pub fn is_border_cell(&self, x: u32, y: u32) -> bool {
+        self.in_bounds(x, y) && (0..constants::NUM_DIRECTIONS).any(|dir| self.neighbor(x, y, dir).is_none())
+    }
+
+    /// Returns every border Square in row-major order, with the directions
+    /// its sides face out of the maze in, in the order of Direction::ALL.
+    /// Corners face out two ways, or three or four in mazes a single
+    /// Square wide or tall.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let maze = Maze::new(2, 3);
+    /// let (corner, outward) = maze.border_cells().next().unwrap();
+    /// assert_eq!(Coord::new(0, 0), corner);
+    /// assert_eq!(vec![Direction::North, Direction::West], outward);
+    /// assert_eq!(6, maze.border_cells().count());
+    /// ```
+    pub fn border_cells(&self) -> impl Iterator<Item = (Coord, Vec<Direction>)> + '_ {
+        (0..self.rows)
+            .flat_map(move |y| (0..self.cols).map(move |x| Coord::new(x, y)))
+            .filter_map(move |c| {
+                let outward: Vec<Direction> = Direction::ALL.iter().cloned()
+                    .filter(|&d| self.neighbor(c.x, c.y, u32::from(d)).is_none())
+                    .collect();
+                if outward.is_empty() { None } else { Some((c, outward)) }
+            })
+    }
+
+    /// Returns every gap in the outer wall, as the Square and the direction
+    /// it opens in, in the order of border_cells().  These are normally just
+    /// the entrance and exits.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// assert!(maze.outer_openings().is_empty());
+    /// maze.set_entrance(2, 1, DIR_EAST).unwrap();
+    /// assert_eq!(vec![(Coord::new(2, 1), Direction::East)], maze.outer_openings());
+    /// ```
+    pub fn outer_openings(&self) -> Vec<(Coord, Direction)> {
+        self.border_cells()
+            .flat_map(|(c, outward)| outward.into_iter().map(move |d| (c, d)))
+            .filter(|&(c, d)| !self.sq[self.get_offset(c.x, c.y)].is_wall_present(u32::from(d)))
+            .collect()
+    }
+
+    /// Puts the entrance on a random Square of one side of the outer wall
+    /// and the exit on a random Square of the opposite side.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// maze.generate_perfect().unwrap();
+    /// maze.place_entrance_exit_opposite().unwrap();
+    /// let (entrance, exit) = (maze.get_entrance().unwrap(), maze.get_exit().unwrap());
+    /// assert!(maze.solve(entrance.coord, exit.coord).is_some());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn place_entrance_exit_opposite(&mut self) -> Result<(), MazeError> {
+        self.place_entrance_exit_opposite_with_rng(&mut thread_rng())
+    }
+
+    /// Places the entrance and exit on opposite sides from a seed.
+    pub fn place_entrance_exit_opposite_seeded(&mut self, seed: u64) -> Result<(), MazeError> {
+        self.place_entrance_exit_opposite_with_rng(&mut rng::from_seed(seed))
+    }
+
+    /// Places the entrance and exit on opposite sides using the supplied
+    /// random number generator.  The pair of sides is picked at random from
+    /// the ones that border the outside (Torus axes don't), and only carved
+    /// Squares are used.  The exit is always reachable from the entrance.
+    /// Any old entrance and exit are walled up.
+    ///
+    /// Fails if a side of the outer wall has no carved Squares, or if no
+    /// side has a Square that can reach a Square on the opposite side.
+    pub fn place_entrance_exit_opposite_with_rng<R: Rng>(&mut self, rng: &mut R) -> Result<(), MazeError> {
+        for &dir in [constants::DIR_NORTH, constants::DIR_SOUTH, constants::DIR_EAST, constants::DIR_WEST].iter() {
+            let side = self.border_squares(dir);
+            if !side.is_empty() && !side.iter().any(|c| self.sq[self.get_offset(c.x, c.y)].is_carved()) {
+                return Err(MazeError::InvalidArgument(
+                    format!("the {} side of the maze has no carved Squares", crate::trace::dir_name(dir))));
+            }
+        }
+
+        let mut sides: Vec<Direction> = Direction::ALL.iter().cloned()
+            .filter(|&side| !self.border_squares(side.into()).is_empty()).collect();
+        rng.shuffle(&mut sides);
+        if sides.is_empty() {
+            return Err(MazeError::InvalidArgument("the maze has no outer wall".to_string()));
+        }
+
+        let carved = |maze: &Maze, dir: u32| -> Vec<Coord> {
+            maze.border_squares(dir).into_iter().filter(|c| maze.sq[maze.get_offset(c.x, c.y)].is_carved()).collect()
+        };
+        // A pair of sides that don't connect falls through to the next
+        for &side in sides.iter() {
+            let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+            let mut entrances = carved(self, dir);
+            let exits = carved(self, back);
+            rng.shuffle(&mut entrances);
+            for entrance in entrances {
+                let dist = self.distances(entrance);
+                let reachable: Vec<Coord> = exits.iter().cloned()
+                    .filter(|&c| c != entrance && dist[self.get_offset(c.x, c.y)].is_some()).collect();
+                if reachable.is_empty() {
+                    continue;
+                }
+                let exit = reachable[rng.gen_range(0, reachable.len())];
+
+                self.close_openings();
+                self.set_entrance(entrance.x, entrance.y, dir)?;
+                self.set_exit(exit.x, exit.y, back)?;
+                return Ok(());
+            }
+        }
+        Err(MazeError::InvalidArgument("no side of the maze connects to the opposite side".to_string()))
+    }
+
+    /// Puts the entrance and exit on the two carved Squares of the outer
+    /// wall that are the most steps apart.  The pair is found by searching
+    /// from the first border Square for the farthest one, then from that
+    /// one for the farthest again, which is exact for perfect mazes.  Ties
+    /// go to the first Square in row-major order, and corners open to the
+    /// north or south before the east or west.  Any old entrance and exit
+    /// are walled up.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::from_compact("020\n4f8\n010").unwrap();
+    /// maze.place_entrance_exit_longest().unwrap();
+    /// let (entrance, exit) = (maze.get_entrance().unwrap(), maze.get_exit().unwrap());
+    /// assert_eq!(3, maze.solve(entrance.coord, exit.coord).unwrap().len());
+    /// ```
+    pub fn place_entrance_exit_longest(&mut self) -> Result<(), MazeError> {
+        let openings: Vec<Opening> = self.border_cells()
+            .filter(|(c, _)| self.sq[self.get_offset(c.x, c.y)].is_carved())
+            .map(|(coord, outward)| Opening { coord, dir: u32::from(outward[0]) })
+            .collect();
+        let first = match openings.first() {
+            Some(&first) => first,
+            None => return Err(MazeError::InvalidArgument("the maze has no carved Squares on its outer wall".to_string())),
+        };
+        let farthest = |maze: &Maze, from: Opening| -> Option<Opening> {
+            let dist = maze.distances(from.coord);
+            let mut best: Option<(u32, Opening)> = None;
+            for o in openings.iter().filter(|o| o.coord != from.coord) {
+                if let Some(d) = dist[maze.get_offset(o.coord.x, o.coord.y)] {
+                    if best.is_none_or(|(most, _)| d > most) {
+                        best = Some((d, *o));
+                    }
+                }
+            }
+            best.map(|(_, o)| o)
+        };
+        let (entrance, exit) = match farthest(self, first).and_then(|e| farthest(self, e).map(|x| (e, x))) {
+            Some(pair) => pair,
+            None => return Err(MazeError::InvalidArgument(
+                "no two Squares on the outer wall are connected".to_string())),
+        };
+
+        self.close_openings();
+        self.set_entrance(entrance.coord.x, entrance.coord.y, entrance.dir)?;
+        self.set_exit(exit.coord.x, exit.coord.y, exit.dir)
+    }
+
+    //
+    // Internal - walls up the entrance and every exit, and forgets them.
+    //
+    fn close_openings(&mut self) {
+        for opening in self.entrance.take().into_iter().chain(core::mem::take(&mut self.exits)) {
+            let offset = self.get_offset(opening.coord.x, opening.coord.y);
+            self.sq[offset].build_wall(opening.dir);
+        }
+    }
+
+    //
+    // Internal - the Squares along side dir of the outer wall, or none if
+    // that side wraps around.
+    //
+    fn border_squares(&self, dir: u32) -> Vec<Coord> {
+        self.border_cells()
+            .filter(|(_, outward)| outward.iter().any(|&d| u32::from(d) == dir))
+            .map(|(c, _)| c)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{Opening, Topology};
+
+    fn check(maze: &Maze) -> (Opening, Opening) {
+        let (entrance, exit) = (maze.get_entrance().unwrap(), maze.get_exit().unwrap());
+        assert_eq!(Direction::from_u32(entrance.dir), Direction::from_u32(exit.dir).map(Direction::opposite));
+        assert_eq!(None, maze.neighbor(entrance.coord.x, entrance.coord.y, entrance.dir));
+        assert_eq!(None, maze.neighbor(exit.coord.x, exit.coord.y, exit.dir));
+        assert!(maze.solve(entrance.coord, exit.coord).is_some());
+        assert!(maze.walls_consistent());
+        (entrance, exit)
+    }
+
+    #[test]
+    fn test_opposite_sides() {
+        let mut maze = Maze::new(12, 15);
+        maze.generate_perfect_seeded(426).unwrap();
+        let mut seen = Vec::new();
+        for seed in 0..20 {
+            maze.place_entrance_exit_opposite_seeded(seed).unwrap();
+            let (entrance, _) = check(&maze);
+            seen.push(entrance.dir);
+        }
+        // Every side gets a turn, and old openings were walled up
+        assert!((0..constants::NUM_DIRECTIONS).all(|dir| seen.contains(&dir)));
+
+        let mut again = maze.clone();
+        maze.place_entrance_exit_opposite_seeded(3).unwrap();
+        again.place_entrance_exit_opposite_seeded(3).unwrap();
+        assert_eq!(maze, again);
+    }
+
+    #[test]
+    fn test_border_cells() {
+        let maze = Maze::new(4, 5);
+        let cells: Vec<(Coord, Vec<Direction>)> = maze.border_cells().collect();
+        // Everything but the 3x2 middle
+        assert_eq!(20 - 6, cells.len());
+        assert_eq!((Coord::new(4, 0), vec![Direction::North, Direction::East]), cells[4]);
+        assert_eq!((Coord::new(0, 3), vec![Direction::South, Direction::West]), cells[9]);
+        assert_eq!((Coord::new(4, 3), vec![Direction::South, Direction::East]), cells[13]);
+        assert_eq!((Coord::new(2, 0), vec![Direction::North]), cells[2]);
+        assert_eq!((Coord::new(0, 1), vec![Direction::West]), cells[5]);
+        assert_eq!((Coord::new(4, 2), vec![Direction::East]), cells[8]);
+        for y in 0..4 {
+            for x in 0..5 {
+                let listed = cells.iter().any(|(c, _)| *c == Coord::new(x, y));
+                assert_eq!(listed, maze.is_border_cell(x, y));
+                assert_eq!(x == 0 || y == 0 || x == 4 || y == 3, listed);
+            }
+        }
+        assert!(!maze.is_border_cell(5, 0));
+
+        // A single Square faces out every way
+        let one = Maze::new(1, 1);
+        assert_eq!(vec![(Coord::new(0, 0), Direction::ALL.to_vec())], one.border_cells().collect::<Vec<_>>());
+
+        // Wrapping edges aren't border
+        let mut torus = Maze::new(4, 5);
+        torus.set_topology(Topology::Torus, Topology::Bounded);
+        assert!(!torus.is_border_cell(0, 1));
+        assert!(torus.is_border_cell(0, 0));
+        assert!(torus.border_cells().all(|(_, outward)| outward.iter().all(|&d| d == Direction::North || d == Direction::South)));
+        torus.set_topology(Topology::Torus, Topology::Torus);
+        assert_eq!(0, torus.border_cells().count());
+    }
+
+    #[test]
+    fn test_outer_openings() {
+        let mut maze = Maze::new(6, 6);
+        maze.generate_perfect_seeded(475).unwrap();
+        maze.place_entrance_exit_longest().unwrap();
+        let recorded: Vec<(Coord, Direction)> = maze.get_entrance().into_iter().chain(maze.get_exit())
+            .map(|o| (o.coord, Direction::from_u32(o.dir).unwrap()))
+            .collect();
+        let openings = maze.outer_openings();
+        assert_eq!(2, openings.len());
+        for o in recorded.iter() {
+            assert!(openings.contains(o));
+        }
+        assert!(maze.validate().is_ok());
+
+        // Outer walls knocked down by hand, one of them on a corner
+        let knocked = [(Coord::new(0, 0), Direction::North), (Coord::new(0, 0), Direction::West),
+                       (Coord::new(3, 5), Direction::South)];
+        for &(c, d) in knocked.iter() {
+            let offset = maze.get_offset(c.x, c.y);
+            maze.sq[offset].break_wall(u32::from(d));
+        }
+        let broken = maze.outer_openings();
+        let new = knocked.iter().filter(|o| !openings.contains(o)).count();
+        assert!(new > 0);
+        assert_eq!(openings.len() + new, broken.len());
+        assert!(knocked.iter().all(|o| broken.contains(o)));
+        assert!(maze.validate().is_err());
+        assert_eq!(' ', maze.render_ascii().lines().last().unwrap().chars().nth(7).unwrap());
+
+        // Moving the entrance and exit walls up only the recorded openings
+        maze.place_entrance_exit_longest().unwrap();
+        assert!(maze.outer_openings().contains(&(Coord::new(3, 5), Direction::South)));
+    }
+
+    #[test]
+    fn test_one_wide() {
+        let mut maze = Maze::new(8, 1);
+        maze.generate_perfect_seeded(1).unwrap();
+        for seed in 0..8 {
+            maze.place_entrance_exit_opposite_seeded(seed).unwrap();
+            check(&maze);
+        }
+    }
+
+    #[test]
+    fn test_torus() {
+        // Only north and south border the outside
+        let mut maze = Maze::new(10, 10);
+        maze.set_topology(Topology::Torus, Topology::Bounded);
+        maze.generate_perfect_seeded(2).unwrap();
+        for seed in 0..6 {
+            maze.place_entrance_exit_opposite_seeded(seed).unwrap();
+            let (entrance, _) = check(&maze);
+            assert!(entrance.dir == constants::DIR_NORTH || entrance.dir == constants::DIR_SOUTH);
+        }
+
+        maze.set_topology(Topology::Torus, Topology::Torus);
+        assert!(maze.place_entrance_exit_opposite_seeded(1).is_err());
+    }
+
+    #[test]
+    fn test_uncarved_side() {
+        let mut maze = Maze::new(5, 5);
+        assert!(maze.place_entrance_exit_opposite_seeded(1).is_err());
+        maze.carve(1, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        assert!(maze.place_entrance_exit_opposite_seeded(1).is_err());
+        assert_eq!(None, maze.get_entrance());
+    }
+
+    #[test]
+    fn test_unconnected_sides() {
+        // North and south don't connect, but the middle row joins west to east
+        let mut maze = Maze::new(3, 3);
+        for &y in [0, 1, 2].iter() {
+            maze.carve(0, y, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        }
+        maze.carve(1, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        for seed in 0..8 {
+            maze.place_entrance_exit_opposite_seeded(seed).unwrap();
+            let (entrance, exit) = check(&maze);
+            assert_eq!(1, entrance.coord.y);
+            assert_eq!(1, exit.coord.y);
+        }
+    }
+
+    #[test]
+    fn test_longest() {
+        let mut maze = Maze::new(9, 11);
+        maze.generate_perfect_seeded(438).unwrap();
+        maze.place_entrance_exit_opposite_seeded(1).unwrap();
+        maze.place_entrance_exit_longest().unwrap();
+        let (entrance, exit) = (maze.get_entrance().unwrap(), maze.get_exit().unwrap());
+        let length = maze.solve(entrance.coord, exit.coord).unwrap().len();
+
+        // Nothing on the outer wall is farther apart
+        let border: Vec<Coord> = (0..9).flat_map(|y| (0..11).map(move |x| Coord::new(x, y)))
+            .filter(|c| c.x == 0 || c.y == 0 || c.x == 10 || c.y == 8).collect();
+        let longest = border.iter()
+            .flat_map(|&a| border.iter().map(move |&b| (a, b)))
+            .map(|(a, b)| maze.solve(a, b).unwrap().len())
+            .max().unwrap();
+        assert_eq!(longest, length);
+        // Only the two new openings are left in the outer wall
+        let holes = border.iter()
+            .flat_map(|&c| (0..constants::NUM_DIRECTIONS).map(move |dir| (c, dir)))
+            .filter(|&(c, dir)| maze.neighbor(c.x, c.y, dir).is_none() && !maze.sq[maze.get_offset(c.x, c.y)].is_wall_present(dir))
+            .count();
+        assert_eq!(2, holes);
+
+        assert!(Maze::new(3, 3).place_entrance_exit_longest().is_err());
+        let mut maze = Maze::new(3, 3);
+        maze.set_topology(Topology::Torus, Topology::Torus);
+        maze.generate_perfect_seeded(1).unwrap();
+        assert!(maze.place_entrance_exit_longest().is_err());
+    }
+}
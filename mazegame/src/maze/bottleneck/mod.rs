@@ -0,0 +1,218 @@
+//! Finding bottlenecks: passages that are the only way between two parts of
+//! a Maze.  Every passage of a perfect maze is one, so they're most useful
+//! on braided mazes, for judging difficulty or deciding where a locked door
+//! can't be walked around.
+use alloc::vec::Vec;
+
+use crate::maze::{Coord, Maze};
+
+// Marks the root of a depth-first search, which wasn't reached by any passage
+const NO_PASSAGE: usize = usize::MAX;
+
+impl Maze {
+    /// Returns every open passage whose removal would split the carved
+    /// Squares it belongs to in two, as the pair of Squares it joins.  The
+    /// first Square of each pair comes first in row-major order, and pairs
+    /// are sorted the same way.  Uses an iterative search, so it works on
+    /// mazes of any size.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// // Two loops joined by a single passage
+    /// let maze = Maze::from_compact("6eea\n5959").unwrap();
+    /// assert_eq!(vec![(Coord::new(1, 0), Coord::new(2, 0))], maze.bottlenecks());
+    /// ```
+    pub fn bottlenecks(&self) -> Vec<(Coord, Coord)> {
+        let graph = self.passage_graph();
+        let count = graph.len();
+        // Discovery times start at 1, so 0 means unvisited
+        let mut disc = vec![0usize; count];
+        let mut low = vec![0usize; count];
+        let mut time = 1;
+        let mut bridges = Vec::new();
+
+        for root in 0..count {
+            if disc[root] != 0 || graph[root].is_empty() {
+                continue;
+            }
+            disc[root] = time;
+            low[root] = time;
+            time += 1;
+            // (Square, passage it was reached by, next neighbor to look at)
+            let mut stack = vec![(root, NO_PASSAGE, 0)];
+            while let Some(&(v, via, next)) = stack.last() {
+                if next < graph[v].len() {
+                    stack.last_mut().unwrap().2 += 1;
+                    let (w, passage) = graph[v][next];
+                    if passage == via {
+                        continue;
+                    }
+                    if disc[w] == 0 {
+                        disc[w] = time;
+                        low[w] = time;
+                        time += 1;
+                        stack.push((w, passage, 0));
+                    } else {
+                        low[v] = low[v].min(disc[w]);
+                    }
+                } else {
+                    stack.pop();
+                    if let Some(&(u, _, _)) = stack.last() {
+                        low[u] = low[u].min(low[v]);
+                        if low[v] > disc[u] {
+                            bridges.push((u.min(v), u.max(v)));
+                        }
+                    }
+                }
+            }
+        }
+
+        bridges.sort();
+        bridges.into_iter().map(|(a, b)| (self.get_coord(a), self.get_coord(b))).collect()
+    }
+
+    /// Returns the bottlenecks a path walks through, in the order it reaches
+    /// them.  Each pair is given in the direction the path crosses it.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let maze = Maze::from_compact("6eea\n5959").unwrap();
+    /// let path = maze.solve(Coord::new(3, 1), Coord::new(0, 1)).unwrap();
+    /// assert_eq!(vec![(Coord::new(2, 0), Coord::new(1, 0))], maze.bottlenecks_on_path(&path));
+    /// ```
+    pub fn bottlenecks_on_path(&self, path: &[Coord]) -> Vec<(Coord, Coord)> {
+        let bridges = self.bottlenecks();
+        path.windows(2)
+            .map(|step| (step[0], step[1]))
+            .filter(|&(a, b)| bridges.binary_search_by(|p| self.pair_key(*p).cmp(&self.pair_key((a, b)))).is_ok())
+            .collect()
+    }
+
+    //
+    // Internal - the passages leading from each Square, as (Square, passage
+    // number) pairs of offsets.  One-way passages count as ordinary ones,
+    // since they still join the Squares, and a pair of Squares joined twice
+    // (across both edges of a narrow Torus) gets two passages.
+    //
+    fn passage_graph(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut graph = vec![Vec::new(); self.sq.len()];
+        let mut passages = 0;
+        for a in 0..self.sq.len() {
+            let here = self.get_coord(a);
+            let out: Vec<usize> = self.open_neighbors(here.x, here.y).iter()
+                .map(|c| self.get_offset(c.x, c.y)).collect();
+            let mut seen: Vec<usize> = Vec::new();
+            for &b in out.iter() {
+                if b == a || seen.contains(&b) {
+                    continue;
+                }
+                seen.push(b);
+                let there = self.get_coord(b);
+                let back = self.open_neighbors(there.x, there.y).iter()
+                    .filter(|c| self.get_offset(c.x, c.y) == a).count();
+                // Passages open both ways are added from the lower Square only
+                if b < a && back > 0 {
+                    continue;
+                }
+                let forward = out.iter().filter(|&&o| o == b).count();
+                for _ in 0..forward.max(back) {
+                    graph[a].push((b, passages));
+                    graph[b].push((a, passages));
+                    passages += 1;
+                }
+            }
+        }
+        graph
+    }
+
+    //
+    // Internal - the offsets of a pair of Squares, lowest first.
+    //
+    fn pair_key(&self, (a, b): (Coord, Coord)) -> (usize, usize) {
+        let (a, b) = (self.get_offset(a.x, a.y), self.get_offset(b.x, b.y));
+        (a.min(b), a.max(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Topology;
+    use crate::rng;
+
+    // Checks a passage is a bottleneck by walling it off and trying to get
+    // from one side to the other
+    fn splits(maze: &Maze, (a, b): (Coord, Coord)) -> bool {
+        let mut cut = maze.clone();
+        for &dir in crate::constants::Direction::ALL.iter() {
+            if cut.neighbor(a.x, a.y, dir.into()) == Some(b) {
+                let (from, to) = (cut.get_offset(a.x, a.y), cut.get_offset(b.x, b.y));
+                cut.sq[from].build_wall(dir.into());
+                cut.sq[to].build_wall(dir.opposite().into());
+            }
+        }
+        cut.solve(a, b).is_none()
+    }
+
+    #[test]
+    fn test_one_bridge() {
+        let maze = Maze::from_compact("6eea\n5959").unwrap();
+        assert_eq!(vec![(Coord::new(1, 0), Coord::new(2, 0))], maze.bottlenecks());
+        assert!(splits(&maze, (Coord::new(1, 0), Coord::new(2, 0))));
+
+        // A path that stays in one loop doesn't cross it
+        let path = maze.solve(Coord::new(0, 0), Coord::new(1, 1)).unwrap();
+        assert!(maze.bottlenecks_on_path(&path).is_empty());
+    }
+
+    #[test]
+    fn test_ring() {
+        assert!(Maze::from_compact("6a\n59").unwrap().bottlenecks().is_empty());
+        assert!(Maze::from_compact("6ca\n5c9").unwrap().bottlenecks().is_empty());
+
+        // A row joined across the seam of a Torus is a ring too
+        let mut row = Maze::new(1, 5);
+        row.set_topology(Topology::Torus, Topology::Bounded);
+        for x in 0..5 {
+            row.carve(x, 0, crate::constants::DIR_EAST, crate::constants::ID_MAZE_PATH, false).unwrap();
+        }
+        assert!(row.bottlenecks().is_empty());
+
+        // Two Squares joined across both edges of a narrow Torus
+        let mut pair = Maze::new(1, 2);
+        pair.set_topology(Topology::Torus, Topology::Bounded);
+        pair.carve(0, 0, crate::constants::DIR_EAST, crate::constants::ID_MAZE_PATH, false).unwrap();
+        assert_eq!(1, pair.bottlenecks().len());
+        pair.carve(0, 0, crate::constants::DIR_WEST, crate::constants::ID_MAZE_PATH, false).unwrap();
+        assert!(pair.bottlenecks().is_empty());
+    }
+
+    #[test]
+    fn test_generated() {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_perfect_seeded(429).unwrap();
+        assert_eq!(399, maze.bottlenecks().len());
+
+        maze.braid_with_rng(0.5, &mut rng::from_seed(429));
+        let bridges = maze.bottlenecks();
+        assert!(!bridges.is_empty() && bridges.len() < 399);
+        for &bridge in bridges.iter() {
+            assert!(splits(&maze, bridge), "{:?}", bridge);
+        }
+        // Every other passage has a way around it
+        for y in 0..20 {
+            for x in 0..20 {
+                let here = Coord::new(x, y);
+                for next in maze.open_neighbors(x, y) {
+                    if here < next && !bridges.contains(&(here, next)) && !bridges.contains(&(next, here)) {
+                        assert!(!splits(&maze, (here, next)), "{:?}", (here, next));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Braiding that can't spoil a level.  Plain braiding knocks down walls at
+//! dead ends wherever it likes, and one of those walls can be a shortcut
+//! that skips most of the intended route.  Braiding here refuses any wall
+//! that would make the way from the entrance to the exit shorter, or give
+//! it a second route of the same length.
+//!
+//! Distances from the entrance and to the exit are worked out once, then
+//! kept up to date after each wall comes down by searching outward only
+//! from the Squares whose distance changed.
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+impl Maze {
+    /// Braids the maze like braid_with_rng(), keeping the shortest path
+    /// from entrance to exit as it is.  Returns how many walls were skipped
+    /// because they would have spoiled it.
+    #[cfg(feature = "std")]
+    pub fn braid_preserving_solution(&mut self, chance: f64, entrance: Coord, exit: Coord) -> Result<u32, MazeError> {
+        self.braid_preserving_solution_with_rng(chance, entrance, exit, &mut thread_rng())
+    }
+
+    /// Removes dead ends like braid_with_rng(), but never knocks down a wall
+    /// that would shorten the shortest path from entrance to exit or make a
+    /// second path of the same length.  A dead end whose walls would all do
+    /// that is left alone.  Returns how many walls were skipped for that
+    /// reason; a wall can be counted once from each side.
+    ///
+    /// Fails if entrance or exit is outside the maze, or if the exit can't
+    /// be reached from the entrance.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(12, 12);
+    /// let mut rng = mazegame::rng::from_seed(467);
+    /// maze.generate_perfect_with_rng(&mut rng).unwrap();
+    /// let (entrance, exit) = (Coord::new(0, 0), Coord::new(11, 11));
+    /// let length = maze.solve(entrance, exit).unwrap().len();
+    /// maze.braid_preserving_solution_with_rng(1.0, entrance, exit, &mut rng).unwrap();
+    /// assert_eq!(length, maze.solve(entrance, exit).unwrap().len());
+    /// ```
+    pub fn braid_preserving_solution_with_rng<R: Rng>(&mut self, chance: f64, entrance: Coord, exit: Coord,
+                                                      rng: &mut R) -> Result<u32, MazeError> {
+        for c in [entrance, exit].iter() {
+            if !self.in_bounds(c.x, c.y) {
+                return Err(MazeError::OutOfBounds(c.x, c.y));
+            }
+        }
+        let mut forward: Vec<Vec<usize>> = (0..self.sq.len())
+            .map(|i| {
+                let c = self.get_coord(i);
+                self.open_neighbors(c.x, c.y).iter().map(|n| self.get_offset(n.x, n.y)).collect()
+            })
+            .collect();
+        let mut backward: Vec<Vec<usize>> = vec![Vec::new(); self.sq.len()];
+        for (i, next) in forward.iter().enumerate() {
+            for &j in next.iter() {
+                backward[j].push(i);
+            }
+        }
+        let (start, goal) = (self.get_offset(entrance.x, entrance.y), self.get_offset(exit.x, exit.y));
+        let mut from_start = search(&forward, start);
+        let mut to_goal = search(&backward, goal);
+        let length = from_start[goal].ok_or(MazeError::Unreachable(entrance, exit))?;
+        // Whether a new passage from a to b would give a route no longer
+        // than the shortest one
+        let spoils = |from_start: &[Option<u32>], to_goal: &[Option<u32>], a: usize, b: usize| {
+            match (from_start[a], to_goal[b]) {
+                (Some(there), Some(back)) => there + 1 + back <= length,
+                _ => false,
+            }
+        };
+
+        let (mut removed, mut skipped) = (0, 0);
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                // Earlier removals may have already opened this one up
+                if self.sq[self.get_offset(x, y)].is_part_of_room() || self.open_neighbors(x, y).len() != 1 {
+                    continue;
+                }
+                if rng.gen::<f64>() >= chance {
+                    continue;
+                }
+
+                let here = self.get_offset(x, y);
+                let mut candidates = Vec::new();
+                let mut dead_end_candidates = Vec::new();
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    let n = match self.neighbor(x, y, dir) {
+                        Some(n) => n,
+                        None => continue,
+                    };
+                    let there = self.get_offset(n.x, n.y);
+                    if !self.sq[here].is_wall_present(dir) || self.sq[there].is_part_of_room() {
+                        continue;
+                    }
+                    // The way back may already be open, if it's one-way
+                    let new_back = !forward[there].contains(&here);
+                    if spoils(&from_start, &to_goal, here, there) ||
+                       (new_back && spoils(&from_start, &to_goal, there, here)) {
+                        skipped += 1;
+                        continue;
+                    }
+                    candidates.push(dir);
+                    if self.open_neighbors(n.x, n.y).len() == 1 {
+                        dead_end_candidates.push(dir);
+                    }
+                }
+
+                // Joining two dead ends removes both at once
+                let pool = if dead_end_candidates.is_empty() { &candidates } else { &dead_end_candidates };
+                if pool.is_empty() {
+                    continue;
+                }
+                let dir = pool[rng.gen_range(0, pool.len())];
+                let n = self.neighbor(x, y, dir).unwrap();
+                let there = self.get_offset(n.x, n.y);
+                self.carve(x, y, dir, constants::ID_MAZE_PATH, false).unwrap();
+                removed += 1;
+                for &(a, b) in [(here, there), (there, here)].iter() {
+                    if !forward[a].contains(&b) {
+                        forward[a].push(b);
+                        backward[b].push(a);
+                        relax(&forward, &mut from_start, a, b);
+                        relax(&backward, &mut to_goal, b, a);
+                    }
+                }
+            }
+        }
+        gen_debug!("braid_preserving chance={} removed={} skipped={}", chance, removed, skipped);
+        Ok(skipped)
+    }
+}
+
+//
+// Internal - the number of steps from start to every cell along the edges
+// in next, or None for cells that can't be reached.
+//
+fn search(next: &[Vec<usize>], start: usize) -> Vec<Option<u32>> {
+    let mut dist = vec![None; next.len()];
+    dist[start] = Some(0);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(cur) = queue.pop_front() {
+        let d = dist[cur].unwrap() + 1;
+        for &n in next[cur].iter() {
+            if dist[n].is_none() {
+                dist[n] = Some(d);
+                queue.push_back(n);
+            }
+        }
+    }
+    dist
+}
+
+//
+// Internal - updates dist after an edge from a to b is added, searching
+// onward only from the cells that got nearer.
+//
+fn relax(next: &[Vec<usize>], dist: &mut [Option<u32>], a: usize, b: usize) {
+    let d = match dist[a] {
+        Some(d) => d + 1,
+        None => return,
+    };
+    if dist[b].is_some_and(|old| old <= d) {
+        return;
+    }
+    dist[b] = Some(d);
+    let mut queue = VecDeque::new();
+    queue.push_back(b);
+    while let Some(cur) = queue.pop_front() {
+        let d = dist[cur].unwrap() + 1;
+        for &n in next[cur].iter() {
+            if dist[n].is_none_or(|old| d < old) {
+                dist[n] = Some(d);
+                queue.push_back(n);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+
+    // The number of shortest paths from one Square to another
+    fn shortest_paths(maze: &Maze, from: Coord, to: Coord) -> u64 {
+        let dist = maze.distances(from);
+        let mut order: Vec<usize> = (0..maze.sq.len()).filter(|&i| dist[i].is_some()).collect();
+        order.sort_by_key(|&i| dist[i]);
+        let mut count = vec![0u64; maze.sq.len()];
+        count[maze.get_offset(from.x, from.y)] = 1;
+        for i in order {
+            let c = maze.get_coord(i);
+            for n in maze.open_neighbors(c.x, c.y) {
+                let j = maze.get_offset(n.x, n.y);
+                if dist[j] == dist[i].map(|d| d + 1) {
+                    count[j] += count[i];
+                }
+            }
+        }
+        count[maze.get_offset(to.x, to.y)]
+    }
+
+    #[test]
+    fn test_solution_kept() {
+        let mut spoiled = 0;
+        for seed in 0..10 {
+            let mut maze = Maze::new(15, 20);
+            maze.generate_perfect_seeded(467 + seed).unwrap();
+            let (entrance, exit) = (Coord::new(0, 7), Coord::new(19, 7));
+            let length = maze.solve(entrance, exit).unwrap().len();
+            let dead_ends = maze.dead_ends().len();
+
+            // Plain braiding from the same seed often finds a shortcut
+            let mut plain = maze.clone();
+            plain.braid_with_rng(1.0, &mut rng::from_seed(seed));
+            if plain.solve(entrance, exit).unwrap().len() < length || shortest_paths(&plain, entrance, exit) > 1 {
+                spoiled += 1;
+            }
+
+            let skipped = maze.braid_preserving_solution_with_rng(1.0, entrance, exit, &mut rng::from_seed(seed))
+                .unwrap();
+            assert!(skipped > 0);
+            assert_eq!(length, maze.solve(entrance, exit).unwrap().len());
+            assert_eq!(1, shortest_paths(&maze, entrance, exit));
+            assert!(maze.dead_ends().len() < dead_ends / 2);
+            assert!(maze.walls_consistent());
+        }
+        assert!(spoiled > 5);
+    }
+
+    #[test]
+    fn test_repeated_with_one_way() {
+        // Braiding bit by bit, around a one-way passage, still keeps the
+        // solution
+        let mut maze = Maze::new(10, 10);
+        let mut rng = rng::from_seed(4670);
+        maze.generate_perfect_with_rng(&mut rng).unwrap();
+        maze.carve_one_way(4, 4, constants::DIR_EAST).unwrap();
+        let (entrance, exit) = (Coord::new(0, 0), Coord::new(9, 9));
+        let length = maze.solve(entrance, exit).unwrap().len();
+        for _ in 0..3 {
+            maze.braid_preserving_solution_with_rng(0.5, entrance, exit, &mut rng).unwrap();
+            assert_eq!(length, maze.solve(entrance, exit).unwrap().len());
+            assert_eq!(1, shortest_paths(&maze, entrance, exit));
+        }
+    }
+
+    #[test]
+    fn test_bad_endpoints() {
+        let mut maze = Maze::new(3, 3);
+        assert_eq!(Err(MazeError::OutOfBounds(3, 0)),
+                   maze.braid_preserving_solution_with_rng(1.0, Coord::new(0, 0), Coord::new(3, 0),
+                                                           &mut rng::from_seed(1)));
+        assert_eq!(Err(MazeError::Unreachable(Coord::new(0, 0), Coord::new(2, 2))),
+                   maze.braid_preserving_solution_with_rng(1.0, Coord::new(0, 0), Coord::new(2, 2),
+                                                           &mut rng::from_seed(1)));
+    }
+}
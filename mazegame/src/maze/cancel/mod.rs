@@ -0,0 +1,123 @@
+//! Generation that can be stopped part way, for very large mazes built on a
+//! background thread.  The caller keeps an AtomicBool (usually in an Arc)
+//! and sets it to cancel.  A cancelled Maze is reset(), so it's never left
+//! half carved.
+use core::sync::atomic::AtomicBool;
+use rand::Rng;
+
+use crate::error::MazeError;
+use crate::maze::generator::{self, GrowthStats};
+use crate::maze::info::GenerationAlgorithm;
+use crate::maze::Maze;
+use crate::rng;
+
+impl Maze {
+    /// Generates a perfect maze from a seed, stopping with
+    /// MazeError::Cancelled if cancel gets set.
+    ///
+    /// # Example
+    /// ```
+    /// use std::sync::atomic::AtomicBool;
+    ///
+    /// let cancel = AtomicBool::new(true);
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// assert_eq!(Err(mazegame::MazeError::Cancelled), maze.generate_perfect_cancellable_seeded(1, &cancel));
+    /// ```
+    pub fn generate_perfect_cancellable_seeded(&mut self, seed: u64, cancel: &AtomicBool) -> Result<(), MazeError> {
+        self.generate_perfect_cancellable_with_rng(&mut rng::from_seed(seed), cancel)?;
+        self.record_generation(seed, GenerationAlgorithm::GrowingTree, None);
+        Ok(())
+    }
+
+    /// Generates a perfect maze using the supplied random number generator,
+    /// stopping with MazeError::Cancelled if cancel gets set.  Until it's
+    /// cancelled it makes the same maze as generate_perfect_with_rng().
+    pub fn generate_perfect_cancellable_with_rng<R: Rng>(&mut self, rng: &mut R, cancel: &AtomicBool)
+                                                         -> Result<(), MazeError> {
+        self.measure_generation(|maze| maze.cancellable_growing_tree(rng, cancel))
+    }
+
+    /// Generates a maze with rooms using the supplied random number
+    /// generator, stopping with MazeError::Cancelled if cancel gets set.
+    pub fn generate_cancellable_with_rng<R: Rng>(&mut self, (rooms, min_x, max_x, min_y, max_y): (u32, u32, u32, u32, u32),
+                                                 rng: &mut R, cancel: &AtomicBool) -> Result<(), MazeError> {
+        self.measure_generation(|maze| {
+            maze.make_rooms(rooms, min_x, max_x, min_y, max_y, rng);
+            maze.cancellable_growing_tree(rng, cancel)
+        })
+    }
+
+    //
+    // Internal - runs the growing tree generator from the start Square,
+    // resetting the Maze if it's cancelled.
+    //
+    fn cancellable_growing_tree<R: Rng>(&mut self, rng: &mut R, cancel: &AtomicBool)
+                                        -> Result<GrowthStats, MazeError> {
+        let start = self.get_start();
+        match generator::growing_tree_cancellable(self, start, rng, cancel) {
+            Ok(stats) => {
+                gen_debug!("growing_tree x={} y={} carved={} backtracks={}", start.x, start.y, stats.carved,
+                           stats.backtracks);
+                Ok(stats)
+            }
+            Err(e) => {
+                if e == MazeError::Cancelled {
+                    gen_debug!("cancelled");
+                    self.reset();
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_cancelled() {
+        let cancel = AtomicBool::new(false);
+        let mut maze = Maze::new(12, 12);
+        maze.generate_perfect_cancellable_seeded(424, &cancel).unwrap();
+        let mut plain = Maze::new(12, 12);
+        plain.generate_perfect_seeded(424).unwrap();
+        assert_eq!(plain, maze);
+
+        let mut rooms = Maze::new(20, 20);
+        rooms.generate_cancellable_with_rng((3, 2, 4, 2, 4), &mut rng::from_seed(5), &cancel).unwrap();
+        let mut plain = Maze::new(20, 20);
+        plain.generate_seeded((3, 2, 4, 2, 4), 5).unwrap();
+        assert_eq!(plain, rooms);
+    }
+
+    #[test]
+    fn test_cancelled_up_front() {
+        let cancel = AtomicBool::new(true);
+        let mut maze = Maze::new(20, 20);
+        let result = maze.generate_cancellable_with_rng((3, 2, 4, 2, 4), &mut rng::from_seed(5), &cancel);
+        assert_eq!(Err(MazeError::Cancelled), result);
+        // The rooms placed before the generator started are gone too
+        assert_eq!(Maze::new(20, 20), maze);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cancel_from_another_thread() {
+        use std::sync::Arc;
+        use std::thread;
+
+        // The flag is set before the worker starts, so the result doesn't
+        // depend on how the threads are scheduled
+        let cancel = Arc::new(AtomicBool::new(true));
+        let flag = cancel.clone();
+        let worker = thread::spawn(move || {
+            let mut maze = Maze::new(100, 100);
+            let result = maze.generate_perfect_cancellable_seeded(1, &flag);
+            (result, maze.sq.iter().all(|sq| !sq.is_carved()))
+        });
+        let (result, untouched) = worker.join().unwrap();
+        assert_eq!(Err(MazeError::Cancelled), result);
+        assert!(untouched);
+    }
+}
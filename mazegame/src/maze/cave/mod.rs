@@ -0,0 +1,185 @@
+//! Cave generation with a cellular automaton.  The Maze starts out as random
+//! rock and open ground, and is smoothed with the 4-5 rule: a cell becomes
+//! rock when at least five of the nine cells in its 3x3 block are rock.  Each
+//! open area left over becomes a cavern, stored like a room: its Squares get
+//! the cavern's id and have no walls between them.  Maze corridors are then
+//! carved through the rock, and connect_regions() ties everything together.
+use alloc::string::String;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants;
+use crate::maze::generator::{self, Carvable};
+use crate::maze::info::GenerationAlgorithm;
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+impl Maze {
+    /// Generates caves from a seed.  `fill_prob` is the chance that a cell
+    /// starts out as rock, and `smoothing_passes` is the number of times the
+    /// 4-5 rule is applied.  Meant for a freshly created Maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(20, 30);
+    /// maze.generate_caves(0.45, 4, 9).unwrap();
+    /// assert!(maze.sq.iter().any(|sq| sq.is_part_of_room()));
+    /// assert!(maze.solve(Coord::new(0, 0), Coord::new(29, 19)).is_some());
+    /// ```
+    pub fn generate_caves(&mut self, fill_prob: f32, smoothing_passes: u32, seed: u64) -> Result<(), String> {
+        self.generate_caves_with_rng(fill_prob, smoothing_passes, &mut rng::from_seed(seed))?;
+        self.record_generation(seed, GenerationAlgorithm::Caves { fill_prob, smoothing_passes }, None);
+        Ok(())
+    }
+
+    /// Generates caves using the supplied random number generator.
+    pub fn generate_caves_with_rng<R: Rng>(&mut self, fill_prob: f32, smoothing_passes: u32,
+                                           rng: &mut R) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&fill_prob) {
+            return Err(format!("Cave fill probability {} isn't between 0 and 1", fill_prob));
+        }
+        let mut rock: Vec<bool> = (0..self.sq.len()).map(|_| rng.gen::<f32>() < fill_prob).collect();
+        for _ in 0..smoothing_passes {
+            rock = self.smooth_caves(&rock);
+        }
+        let caverns = self.carve_caverns(&rock);
+
+        // Every stretch of rock cut off from the others gets its own maze
+        let mut carved = 0;
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let cell = Coord::new(x, y);
+                let sq = &self.sq[self.get_offset(x, y)];
+                if sq.is_carved() || sq.is_part_of_room() || self.uncarved_directions(cell).is_empty() {
+                    continue;
+                }
+                carved += generator::growing_tree(self, cell, rng)?.carved;
+            }
+        }
+        gen_debug!("caves fill={} passes={} caverns={} carved={}", fill_prob, smoothing_passes, caverns, carved);
+
+        self.connect_regions_with_rng(rng);
+        Ok(())
+    }
+
+    //
+    // Internal - applies the 4-5 rule once.  Cells off the edge of the Maze
+    // count as rock.
+    //
+    fn smooth_caves(&self, rock: &[bool]) -> Vec<bool> {
+        let mut next = Vec::with_capacity(rock.len());
+        for y in 0..self.rows as i64 {
+            for x in 0..self.cols as i64 {
+                let mut count = 0;
+                for ny in (y - 1)..(y + 2) {
+                    for nx in (x - 1)..(x + 2) {
+                        if nx < 0 || ny < 0 || nx >= self.cols as i64 || ny >= self.rows as i64 ||
+                           rock[self.get_offset(nx as u32, ny as u32)] {
+                            count += 1;
+                        }
+                    }
+                }
+                next.push(count >= 5);
+            }
+        }
+        next
+    }
+
+    //
+    // Internal - turns each connected patch of open ground into a cavern,
+    // numbered after any rooms already in the Maze.  Returns the number of
+    // caverns.
+    //
+    fn carve_caverns(&mut self, rock: &[bool]) -> u32 {
+        let mut caverns = 0;
+        for start in 0..self.sq.len() {
+            if rock[start] || self.sq[start].is_part_of_room() {
+                continue;
+            }
+            caverns += 1;
+            let id = (self.num_rooms + caverns) as i32;
+            self.sq[start].id = id;
+            self.mark_changed();
+
+            let mut stack = vec![self.get_coord(start)];
+            while let Some(cell) = stack.pop() {
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    let next = match self.neighbor(cell.x, cell.y, dir) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    let offset = self.get_offset(next.x, next.y);
+                    if rock[offset] {
+                        continue;
+                    }
+                    if self.sq[offset].id == id {
+                        if self.sq[self.get_offset(cell.x, cell.y)].is_wall_present(dir) {
+                            self.carve(cell.x, cell.y, dir, id, false).unwrap();
+                        }
+                    } else if !self.sq[offset].is_part_of_room() {
+                        self.carve(cell.x, cell.y, dir, id, false).unwrap();
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        self.num_rooms += caverns;
+        caverns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_component(maze: &Maze) -> bool {
+        let (cols, rows) = (maze.get_cols(), maze.get_rows());
+        (0..rows).all(|y| (0..cols).all(|x| maze.solve(Coord::new(0, 0), Coord::new(x, y)).is_some()))
+    }
+
+    #[test]
+    fn test_caves_seeded() {
+        let mut a = Maze::new(25, 40);
+        let mut b = Maze::new(25, 40);
+        let mut c = Maze::new(25, 40);
+        a.generate_caves(0.45, 4, 414).unwrap();
+        b.generate_caves(0.45, 4, 414).unwrap();
+        c.generate_caves(0.45, 4, 415).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_caves_connected() {
+        for seed in 0..5 {
+            let mut maze = Maze::new(20, 30);
+            maze.generate_caves(0.45, 3, seed).unwrap();
+            assert!(maze.walls_consistent());
+            assert!(single_component(&maze));
+            // Nothing is left uncarved
+            assert!(maze.sq.iter().all(|sq| sq.id != constants::ID_UNCARVED));
+        }
+    }
+
+    #[test]
+    fn test_fill_ratio() {
+        // Without smoothing, the open ground is exactly the cells that
+        // didn't start out as rock
+        for &fill in [0.2f32, 0.5, 0.8].iter() {
+            let mut maze = Maze::new(60, 60);
+            maze.generate_caves(fill, 0, 7).unwrap();
+            let open = maze.sq.iter().filter(|sq| sq.is_part_of_room()).count() as f32 / 3600.0;
+            assert!((open - (1.0 - fill)).abs() < 0.03, "fill {} left {} open", fill, open);
+        }
+
+        // Smoothing keeps the caves from overrunning the Maze or vanishing
+        let mut maze = Maze::new(60, 60);
+        maze.generate_caves(0.45, 4, 7).unwrap();
+        let open = maze.sq.iter().filter(|sq| sq.is_part_of_room()).count() as f32 / 3600.0;
+        assert!(open > 0.3 && open < 0.8, "{} open", open);
+
+        assert!(Maze::new(5, 5).generate_caves(1.5, 4, 7).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Tracking which Squares changed, so a renderer can redraw just those
+//! instead of the whole maze.
+//!
+//! take_changes() hands back every Square whose walls, id or passages differ
+//! from the last time it was called, and starts counting again from there.
+//! A Square shows up once however many times it changed in between, and not
+//! at all if it ended up back the way it was.  The first call reports every
+//! Square, since nothing has been drawn yet.
+use alloc::vec::Vec;
+
+use crate::maze::{Coord, Maze};
+
+/// A Square that changed since the last take_changes().
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CellChanged {
+    pub coord: Coord,
+}
+
+impl Maze {
+    /// Returns the Squares that changed since the last call, in row-major
+    /// order, and forgets them.  The first call returns every Square.
+    ///
+    /// Every change to `sq` is seen, including ones made directly rather
+    /// than through a method.  Each call looks at every Square once, and the
+    /// Maze keeps a copy of the Squares to compare against once it's been
+    /// called.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// assert_eq!(9, maze.take_changes().len());
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// let changed: Vec<Coord> = maze.take_changes().iter().map(|c| c.coord).collect();
+    /// assert_eq!(vec![Coord::new(0, 0), Coord::new(1, 0)], changed);
+    /// assert!(maze.take_changes().is_empty());
+    /// ```
+    pub fn take_changes(&mut self) -> Vec<CellChanged> {
+        let mut base = match self.change_base.take() {
+            Some(base) if base.len() == self.sq.len() => base,
+            _ => {
+                self.change_base = Some(self.sq.clone());
+                return (0..self.sq.len()).map(|i| CellChanged { coord: self.get_coord(i) }).collect();
+            }
+        };
+
+        let mut changes = Vec::new();
+        for (i, (old, new)) in base.iter_mut().zip(self.sq.iter()).enumerate() {
+            if old != new {
+                old.clone_from(new);
+                changes.push(CellChanged { coord: self.get_coord(i) });
+            }
+        }
+        self.change_base = Some(base);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH, ID_MAZE_PATH};
+    use crate::rng;
+
+    // The Squares that differ between two mazes, worked out separately
+    fn modified(before: &Maze, after: &Maze) -> Vec<Coord> {
+        (0..after.sq.len()).filter(|&i| before.sq[i] != after.sq[i]).map(|i| after.get_coord(i)).collect()
+    }
+
+    fn coords(changes: &[CellChanged]) -> Vec<Coord> {
+        changes.iter().map(|c| c.coord).collect()
+    }
+
+    #[test]
+    fn test_carves_and_braids() {
+        let mut maze = Maze::new(12, 12);
+        assert_eq!(144, maze.take_changes().len());
+        maze.generate_perfect_seeded(455).unwrap();
+        assert_eq!(144, maze.take_changes().len());
+
+        let mut rng = rng::from_seed(455);
+        let mut before = maze.clone();
+        maze.braid_with_rng(0.5, &mut rng);
+        let _ = maze.carve(0, 0, DIR_SOUTH, ID_MAZE_PATH, false);
+        let changes = maze.take_changes();
+        assert!(!changes.is_empty());
+        assert_eq!(modified(&before, &maze), coords(&changes));
+
+        // Several carves into the same Squares come back once each
+        before = maze.clone();
+        for y in 0..4 {
+            let _ = maze.carve(5, y, DIR_EAST, 3, false);
+        }
+        let _ = maze.set_entrance(5, 0, DIR_NORTH);
+        let changes = coords(&maze.take_changes());
+        assert_eq!(modified(&before, &maze), changes);
+        let mut unique = changes.clone();
+        unique.dedup();
+        assert_eq!(unique, changes);
+        assert!(maze.take_changes().is_empty());
+    }
+
+    #[test]
+    fn test_direct_and_undone_changes() {
+        let mut maze = Maze::new(4, 4);
+        maze.take_changes();
+
+        // Changes made straight to sq are seen too
+        maze.sq[5].id = 9;
+        assert_eq!(vec![Coord::new(1, 1)], coords(&maze.take_changes()));
+
+        // A Square changed and changed back isn't reported
+        maze.sq[6].id = 9;
+        maze.sq[6].id = 0;
+        maze.sq[7].under = 1;
+        assert_eq!(vec![Coord::new(3, 1)], coords(&maze.take_changes()));
+
+        maze.reset();
+        assert_eq!(vec![Coord::new(1, 1), Coord::new(3, 1)], coords(&maze.take_changes()));
+    }
+}
@@ -0,0 +1,255 @@
+//! Collapsing passages for a hard mode: once the player has been gone from
+//! a passage for a number of moves, its wall is rebuilt, so a player who
+//! dawdles finds the way back closing behind them.
+//!
+//! A passage is never closed if that would cut the player off from every
+//! exit.  Passages that aren't bottlenecks can always close, since there's
+//! another way around; closing a bottleneck (or any passage, in a maze with
+//! one-way passages) is tried first and undone if the player can't reach
+//! an exit afterwards.
+use alloc::collections::BTreeMap;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::constants::Direction;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+/// Something Collapse::player_moved() did to the Maze, for a renderer to
+/// animate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollapseEvent {
+    /// The wall on side dir of coord was rebuilt, on both sides.
+    Collapsed { coord: Coord, dir: u32 },
+    /// The wall on side dir of coord was due to close, but was left open
+    /// because it's the player's only way to an exit.  It's no longer
+    /// counting down, until the player uses it again.
+    Held { coord: Coord, dir: u32 },
+}
+
+/// Tracks when the player last went through each passage, closing them
+/// once they've gone unused for long enough.
+#[derive(Clone, Debug)]
+pub struct Collapse {
+    delay: u32,
+    moves: u64,
+    // The move each passage closes on, keyed by the Square that comes first
+    // in `sq` and the side of it the passage is on
+    due: BTreeMap<(usize, u32), u64>,
+}
+
+impl Collapse {
+    /// Creates a collapse that closes passages delay moves after the player
+    /// last went through them.  The delay must be at least 1.
+    pub fn new(delay: u32) -> Result<Collapse, MazeError> {
+        if delay == 0 {
+            return Err(MazeError::InvalidArgument("the collapse delay must be at least 1 move".to_string()));
+        }
+        Ok(Collapse { delay, moves: 0, due: BTreeMap::new() })
+    }
+
+    /// Returns how many moves the player has made.
+    pub fn moves(&self) -> u64 {
+        self.moves
+    }
+
+    /// Returns how many passages are counting down to closing.
+    pub fn pending(&self) -> usize {
+        self.due.len()
+    }
+
+    /// Records the player moving from one Square to the next through an
+    /// open passage, then closes every passage that's now due, oldest
+    /// first.  Returns what was closed, and what was left open to keep the
+    /// player from being trapped.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::collapse::{Collapse, CollapseEvent};
+    ///
+    /// // A corridor with the exit at the east end
+    /// let mut maze = Maze::from_compact("4cc8").unwrap();
+    /// maze.set_exit(3, 0, DIR_EAST).unwrap();
+    /// let mut collapse = Collapse::new(1).unwrap();
+    /// assert!(collapse.player_moved(&mut maze, Coord::new(0, 0), Coord::new(1, 0)).unwrap().is_empty());
+    /// let events = collapse.player_moved(&mut maze, Coord::new(1, 0), Coord::new(2, 0)).unwrap();
+    /// assert_eq!(vec![CollapseEvent::Collapsed { coord: Coord::new(0, 0), dir: DIR_EAST }], events);
+    /// assert!(maze.sq[0].is_wall_present(DIR_EAST));
+    /// ```
+    pub fn player_moved(&mut self, maze: &mut Maze, from: Coord, to: Coord) -> Result<Vec<CollapseEvent>, MazeError> {
+        let passage = self.passage(maze, from, to)?;
+        self.moves += 1;
+        self.due.insert(passage, self.moves + self.delay as u64);
+
+        let mut expired: Vec<(u64, (usize, u32))> = self.due.iter()
+            .filter(|&(_, &at)| at <= self.moves)
+            .map(|(&key, &at)| (at, key))
+            .collect();
+        expired.sort();
+
+        let mut events = Vec::new();
+        for (_, key) in expired {
+            self.due.remove(&key);
+            let (offset, dir) = key;
+            let coord = maze.get_coord(offset);
+            if close_passage(maze, to, coord, dir) {
+                gen_trace!("collapse x={} y={} dir={}", coord.x, coord.y, crate::trace::dir_name(dir));
+                events.push(CollapseEvent::Collapsed { coord, dir });
+            } else {
+                events.push(CollapseEvent::Held { coord, dir });
+            }
+        }
+        Ok(events)
+    }
+
+    //
+    // Internal - the key of the open passage between two neighboring
+    // Squares.
+    //
+    fn passage(&self, maze: &Maze, from: Coord, to: Coord) -> Result<(usize, u32), MazeError> {
+        if !maze.in_bounds(from.x, from.y) {
+            return Err(MazeError::OutOfBounds(from.x, from.y));
+        }
+        let side = Direction::ALL.iter().cloned()
+            .find(|&side| maze.neighbor(from.x, from.y, side.into()) == Some(to)
+                          && !maze.sq[maze.get_offset(from.x, from.y)].is_wall_present(side.into()))
+            .ok_or_else(|| MazeError::InvalidArgument(format!("there's no passage from ({}, {}) to ({}, {})",
+                                                              from.x, from.y, to.x, to.y)))?;
+        let (here, there) = (maze.get_offset(from.x, from.y), maze.get_offset(to.x, to.y));
+        Ok(if here <= there { (here, side.into()) } else { (there, side.opposite().into()) })
+    }
+}
+
+//
+// Internal - rebuilds the wall on side dir of coord unless that leaves the
+// player with no way to an exit they could reach before.  Returns whether
+// the wall was built; a wall that's already standing counts as built.
+//
+fn close_passage(maze: &mut Maze, player: Coord, coord: Coord, dir: u32) -> bool {
+    let (other, back) = match (maze.neighbor(coord.x, coord.y, dir), Direction::from_u32(dir)) {
+        (Some(other), Some(side)) => (other, u32::from(side.opposite())),
+        _ => return false,
+    };
+    let (here, there) = (maze.get_offset(coord.x, coord.y), maze.get_offset(other.x, other.y));
+    // Bottlenecks are listed with the Square first in `sq` first
+    let pair = if here <= there { (coord, other) } else { (other, coord) };
+    // Bottlenecks ignore which way one-way passages go, so the way around a
+    // passage might only work backwards
+    let risky = maze.sq.iter().any(|sq| sq.one_way != 0) || maze.bottlenecks().contains(&pair);
+    let could_escape = risky && can_escape(maze, player);
+
+    let saved = (maze.sq[here].clone(), maze.sq[there].clone());
+    for (offset, side) in [(here, dir), (there, back)].iter() {
+        maze.sq[*offset].build_wall(*side);
+        maze.sq[*offset].set_one_way(*side, false);
+    }
+    if could_escape && !can_escape(maze, player) {
+        maze.sq[here] = saved.0;
+        maze.sq[there] = saved.1;
+        return false;
+    }
+    maze.mark_changed();
+    true
+}
+
+//
+// Internal - whether the player can get to any exit.
+//
+fn can_escape(maze: &Maze, player: Coord) -> bool {
+    let dist = maze.distances(player);
+    maze.exits().iter().any(|o| dist[maze.get_offset(o.coord.x, o.coord.y)].is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{self, DIR_EAST, DIR_SOUTH, DIR_WEST};
+    use crate::rng;
+    use rand::Rng;
+
+    #[test]
+    fn test_hold_and_collapse() {
+        let mut maze = Maze::from_compact("4cc8").unwrap();
+        maze.set_exit(3, 0, DIR_EAST).unwrap();
+        let mut collapse = Collapse::new(1).unwrap();
+        let c = |x| Coord::new(x, 0);
+
+        assert!(collapse.player_moved(&mut maze, c(2), c(1)).unwrap().is_empty());
+        // Closing the passage behind would shut the player in
+        let events = collapse.player_moved(&mut maze, c(1), c(0)).unwrap();
+        assert_eq!(vec![CollapseEvent::Held { coord: c(1), dir: DIR_EAST }], events);
+        assert!(!maze.sq[1].is_wall_present(DIR_EAST));
+
+        // Heading back out, the dead end closes behind
+        let events = collapse.player_moved(&mut maze, c(0), c(1)).unwrap();
+        assert!(events.is_empty());
+        let events = collapse.player_moved(&mut maze, c(1), c(2)).unwrap();
+        assert_eq!(vec![CollapseEvent::Collapsed { coord: c(0), dir: DIR_EAST }], events);
+        assert!(maze.sq[0].is_wall_present(DIR_EAST) && maze.sq[1].is_wall_present(DIR_WEST));
+        assert_eq!(1, collapse.pending());
+        assert_eq!(4, collapse.moves());
+    }
+
+    #[test]
+    fn test_bad_moves() {
+        let mut maze = Maze::from_compact("4c8\n000").unwrap();
+        let mut collapse = Collapse::new(2).unwrap();
+        assert!(collapse.player_moved(&mut maze, Coord::new(0, 0), Coord::new(0, 1)).is_err());
+        assert!(collapse.player_moved(&mut maze, Coord::new(0, 0), Coord::new(2, 0)).is_err());
+        assert!(collapse.player_moved(&mut maze, Coord::new(5, 0), Coord::new(0, 0)).is_err());
+        assert_eq!(0, collapse.moves());
+        assert!(Collapse::new(0).is_err());
+    }
+
+    #[test]
+    fn test_never_trapped() {
+        // Random walks through braided mazes with passages closing almost
+        // as soon as they're left: an exit must always stay reachable
+        for seed in 0..40 {
+            let mut rng = rng::from_seed(461 + seed);
+            let mut maze = Maze::new(10, 10);
+            maze.generate_perfect_with_rng(&mut rng).unwrap();
+            maze.braid_with_rng(0.5, &mut rng);
+            maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+            maze.set_exit(9, 9, DIR_SOUTH).unwrap();
+            maze.add_exit(9, 0, DIR_EAST).unwrap();
+
+            let mut collapse = Collapse::new(rng.gen_range(1, 4)).unwrap();
+            let mut player = Coord::new(0, 0);
+            let mut closed = 0;
+            for _ in 0..300 {
+                let open = maze.open_neighbors(player.x, player.y);
+                assert!(!open.is_empty(), "seed {}: stuck at {:?}", seed, player);
+                let next = open[rng.gen_range(0, open.len())];
+                let events = collapse.player_moved(&mut maze, player, next).unwrap();
+                closed += events.iter().filter(|e| matches!(e, CollapseEvent::Collapsed { .. })).count();
+                player = next;
+                assert!(maze.exits().iter().any(|o| maze.solve(player, o.coord).is_some()),
+                        "seed {}: trapped at {:?}\n{}", seed, player, maze.render_ascii());
+                assert!(maze.walls_consistent());
+            }
+            assert!(closed > 0);
+        }
+    }
+
+    #[test]
+    fn test_one_way_detour() {
+        // A loop whose west side only goes south: closing the east side
+        // leaves the bottom left with no way to the exit at the top right,
+        // even though it isn't a bottleneck
+        let mut maze = Maze::new(2, 2);
+        maze.carve(0, 0, DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 0, DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_one_way(0, 0, DIR_SOUTH).unwrap();
+        maze.set_exit(1, 0, DIR_EAST).unwrap();
+        assert!(maze.bottlenecks().is_empty());
+
+        let mut collapse = Collapse::new(1).unwrap();
+        collapse.player_moved(&mut maze, Coord::new(1, 0), Coord::new(1, 1)).unwrap();
+        let events = collapse.player_moved(&mut maze, Coord::new(1, 1), Coord::new(0, 1)).unwrap();
+        assert_eq!(vec![CollapseEvent::Held { coord: Coord::new(1, 0), dir: DIR_SOUTH }], events);
+        assert!(maze.solve(Coord::new(0, 1), Coord::new(1, 0)).is_some());
+    }
+}
@@ -0,0 +1,146 @@
+//! A compact text form of a Maze's walls, handy for test fixtures.  Each row
+//! of Squares is a line of hex digits, one per Square, giving the Square's
+//! open mask (see `Square::open_mask()`: 1 is north, 2 south, 4 east and 8
+//! west).  A corridor snaking through a 3x2 maze looks like:
+//!
+//! ```text
+//! 4ca
+//! 4c9
+//! ```
+//!
+//! Openings in the outer wall become the entrance and then the exits, in
+//! row-major order.  Ids, rooms, weave crossings, one-way passages and
+//! topology aren't kept: carved Squares get ID_MAZE_PATH.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze, Opening};
+
+impl Maze {
+    /// Reads a Maze written in the compact form.  Every row must have the
+    /// same number of Squares, and a wall must be open on both sides or
+    /// neither.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let maze = Maze::from_compact("4ca\n4c9").unwrap();
+    /// assert_eq!(6, maze.solve(Coord::new(0, 0), Coord::new(0, 1)).unwrap().len());
+    /// ```
+    pub fn from_compact(text: &str) -> Result<Maze, MazeError> {
+        let lines: Vec<&str> = text.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect();
+        if lines.is_empty() {
+            return Err(MazeError::Parse("compact maze has no rows".to_string()));
+        }
+        let cols = lines[0].chars().count();
+        let mut masks = Vec::with_capacity(cols * lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            if line.chars().count() != cols {
+                return Err(MazeError::Parse(
+                    format!("row {}: expected {} Squares, found {}", y, cols, line.chars().count())));
+            }
+            for (x, c) in line.chars().enumerate() {
+                let mask = c.to_digit(16)
+                    .ok_or_else(|| MazeError::Parse(format!("row {}: bad Square '{}' at column {}", y, c, x)))?;
+                masks.push(mask as u8);
+            }
+        }
+
+        let mut maze = Maze::new(lines.len() as u32, cols as u32);
+        for (sq, &mask) in maze.sq.iter_mut().zip(masks.iter()) {
+            sq.set_open_mask(mask);
+            if mask != 0 {
+                sq.id = constants::ID_MAZE_PATH;
+            }
+        }
+
+        for y in 0..maze.rows {
+            for x in 0..maze.cols {
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    if maze.sq[maze.get_offset(x, y)].is_wall_present(dir) || maze.neighbor(x, y, dir).is_some() {
+                        continue;
+                    }
+                    let opening = Opening { coord: Coord::new(x, y), dir };
+                    if maze.entrance.is_none() {
+                        maze.entrance = Some(opening);
+                    } else {
+                        maze.exits.push(opening);
+                    }
+                }
+            }
+        }
+
+        for y in 0..maze.rows {
+            for x in 0..maze.cols {
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    if maze.sq[maze.get_offset(x, y)].is_wall_present(dir) != maze.expected_wall(x, y, dir) {
+                        return Err(MazeError::Parse(format!("the {} wall of ({}, {}) is only open on one side",
+                                                            crate::trace::dir_name(dir), x, y)));
+                    }
+                }
+            }
+        }
+        Ok(maze)
+    }
+
+    /// Writes the Maze's walls in the compact form, one line per row.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(2, 2);
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// assert_eq!("48\n00\n", maze.to_compact());
+    /// ```
+    pub fn to_compact(&self) -> String {
+        let mut out = String::with_capacity(((self.cols + 1) * self.rows) as usize);
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let mask = self.sq[self.get_offset(x, y)].open_mask();
+                out.push(::core::char::from_digit(mask as u32, 16).unwrap_or('0'));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut maze = Maze::new(9, 13);
+        maze.generate_perfect_seeded(427).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        maze.set_exit(12, 8, constants::DIR_SOUTH).unwrap();
+        let text = maze.to_compact();
+        assert_eq!(9, text.lines().count());
+        let copy = Maze::from_compact(&text).unwrap();
+        assert_eq!(text, copy.to_compact());
+        assert_eq!(maze.get_entrance(), copy.get_entrance());
+        assert_eq!(maze.get_exit(), copy.get_exit());
+        // A perfect maze has no rooms, so nothing else is lost
+        assert_eq!(maze, copy);
+
+        // Indentation and blank lines are ignored, and either case of hex works
+        assert_eq!(Maze::from_compact("4c8").unwrap(), Maze::from_compact("\n   4C8\n\n").unwrap());
+    }
+
+    #[test]
+    fn test_malformed() {
+        for text in ["", "\n  \n", "4c8\n4c", "4g8", "48\n40", "4c\n10", "2\n0"].iter() {
+            assert!(Maze::from_compact(text).is_err(), "{:?}", text);
+        }
+        // Holes after the entrance are all exits
+        assert_eq!(1, Maze::from_compact("5c9").unwrap().exits().len());
+        let maze = Maze::from_compact("5d9").unwrap();
+        assert_eq!(vec![Opening { coord: Coord::new(1, 0), dir: constants::DIR_NORTH },
+                        Opening { coord: Coord::new(2, 0), dir: constants::DIR_NORTH }], maze.exits());
+    }
+}
@@ -0,0 +1,192 @@
+//! Daily challenge mazes.  The seed for a day's maze is worked out from the
+//! date and the spec alone, so every player gets the same maze wherever
+//! they are.  Enabled with the `serde` feature, since it's built on
+//! `maze::spec`.
+//!
+//! The seed is the 64-bit FNV-1a hash of these bytes, in order, with every
+//! number little-endian:
+//!
+//! ```text
+//! "mazegame-daily-v1"        the ASCII bytes, no terminator
+//! year, month, day           i32, u32, u32
+//! width, height              u32, u32
+//! algorithm                  u8; 0 for growing_tree
+//! rooms                      u8 0 for none, or u8 1 followed by count,
+//!                            min_w, max_w, min_h and max_h as u32s
+//! braid                      the f64's bits, as a u64
+//! allow_weave                u8, 0 or 1
+//! corridor_width             u32
+//! ```
+//!
+//! The spec's seed, entrance and min_solution_length aren't hashed, because
+//! a daily maze always uses the derived seed and puts its entrance and exit
+//! as far apart as possible.  Any change to this scheme has to change the
+//! version in the prefix.
+use alloc::vec::Vec;
+
+use crate::error::MazeError;
+use crate::maze::fingerprint::fnv1a;
+use crate::maze::spec::{Algorithm, EntranceSpec, MazeSpec};
+use crate::maze::Maze;
+use crate::rng;
+
+/// The bytes every daily seed's hash starts with.
+pub const DAILY_SCHEME: &str = "mazegame-daily-v1";
+
+/// Works out the seed for the daily maze on date, a (year, month, day)
+/// triple, using the scheme described in the module docs.  Fails if the
+/// date doesn't exist.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::daily::daily_seed;
+/// use mazegame::prelude::*;
+///
+/// let spec = MazeSpec::new(20, 20);
+/// assert_eq!(daily_seed((2026, 10, 16), &spec).unwrap(), daily_seed((2026, 10, 16), &spec).unwrap());
+/// assert_ne!(daily_seed((2026, 10, 16), &spec).unwrap(), daily_seed((2026, 10, 17), &spec).unwrap());
+/// assert!(daily_seed((2026, 2, 29), &spec).is_err());
+/// ```
+pub fn daily_seed(date: (i32, u32, u32), config: &MazeSpec) -> Result<u64, MazeError> {
+    let (year, month, day) = date;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(MazeError::InvalidArgument(format!("{}-{:02}-{:02} isn't a date", year, month, day)));
+    }
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(DAILY_SCHEME.as_bytes());
+    bytes.extend_from_slice(&year.to_le_bytes());
+    bytes.extend_from_slice(&month.to_le_bytes());
+    bytes.extend_from_slice(&day.to_le_bytes());
+    bytes.extend_from_slice(&config.width.to_le_bytes());
+    bytes.extend_from_slice(&config.height.to_le_bytes());
+    bytes.push(match config.algorithm {
+        Algorithm::GrowingTree => 0,
+    });
+    match config.rooms {
+        Some(r) => {
+            bytes.push(1);
+            for v in [r.count, r.min_w, r.max_w, r.min_h, r.max_h].iter() {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        None => bytes.push(0),
+    }
+    bytes.extend_from_slice(&config.braid.to_bits().to_le_bytes());
+    bytes.push(config.allow_weave as u8);
+    bytes.extend_from_slice(&config.corridor_width.to_le_bytes());
+    Ok(fnv1a(&bytes))
+}
+
+impl Maze {
+    /// Builds the daily maze for date, a (year, month, day) triple, from
+    /// config.  The entrance and exit go on the outer wall as far apart as
+    /// possible.  Returns the maze along with the seed it was built from.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let spec = MazeSpec::new(15, 10);
+    /// let (maze, seed) = Maze::daily((2026, 10, 16), &spec).unwrap();
+    /// let (again, _) = Maze::daily((2026, 10, 16), &spec).unwrap();
+    /// assert_eq!(maze, again);
+    /// assert_eq!(mazegame::maze::daily::daily_seed((2026, 10, 16), &spec).unwrap(), seed);
+    /// assert!(maze.get_exit().is_some());
+    /// ```
+    pub fn daily(date: (i32, u32, u32), config: &MazeSpec) -> Result<(Maze, u64), MazeError> {
+        let seed = daily_seed(date, config)?;
+        let mut spec = config.clone();
+        spec.seed = Some(seed);
+        spec.entrance = EntranceSpec::None;
+        spec.min_solution_length = 0;
+        let mut maze = spec.build_with_rng(&mut rng::from_seed(seed))?;
+        maze.place_entrance_exit_longest()?;
+        Ok((maze, seed))
+    }
+}
+
+//
+// Internal - the number of days in month of year, in the Gregorian
+// calendar.
+//
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+    use crate::maze::spec::RoomSpec;
+    use crate::maze::{Coord, Opening};
+
+    #[test]
+    fn test_pinned_daily() {
+        // If this fails, the scheme changed and every player's daily maze
+        // changed with it
+        let spec = MazeSpec::new(12, 8);
+        let (maze, seed) = Maze::daily((2026, 10, 16), &spec).unwrap();
+        assert_eq!(1616000442664388480, seed);
+        assert_eq!(Some(Opening { coord: Coord::new(9, 0), dir: constants::DIR_NORTH }), maze.get_entrance());
+        assert_eq!(Some(Opening { coord: Coord::new(0, 0), dir: constants::DIR_NORTH }), maze.get_exit());
+        assert_eq!("36cca6ca696a\n35ca59697c93\n36c96a5a5c83\n37ca33696ccb\n\
+                    35a33334b6a1\n5a33315a595a\n69137ca5a6cb\n5cc9585cd949\n", maze.to_compact());
+        assert!(maze.sq[maze.get_offset(4, 4)].is_wall_present(constants::DIR_EAST));
+        assert!(!maze.sq[maze.get_offset(4, 4)].is_wall_present(constants::DIR_SOUTH));
+    }
+
+    #[test]
+    fn test_config_changes_seed() {
+        let spec = MazeSpec::new(12, 8);
+        let base = daily_seed((2026, 10, 16), &spec).unwrap();
+
+        // The spec's own seed and entrance don't matter
+        let mut same = spec.clone();
+        same.seed = Some(99);
+        same.entrance = EntranceSpec::Auto;
+        assert_eq!(base, daily_seed((2026, 10, 16), &same).unwrap());
+
+        let mut changed = Vec::new();
+        let mut wide = spec.clone();
+        wide.width = 13;
+        changed.push(wide);
+        let mut braided = spec.clone();
+        braided.braid = 0.25;
+        changed.push(braided);
+        let mut woven = spec.clone();
+        woven.allow_weave = true;
+        changed.push(woven);
+        let mut rooms = spec.clone();
+        rooms.rooms = Some(RoomSpec { count: 1, min_w: 2, max_w: 3, min_h: 2, max_h: 3 });
+        changed.push(rooms);
+        for other in changed.iter() {
+            assert_ne!(base, daily_seed((2026, 10, 16), other).unwrap());
+        }
+        assert_ne!(base, daily_seed((2025, 10, 16), &spec).unwrap());
+        assert_ne!(base, daily_seed((2026, 10, 17), &spec).unwrap());
+    }
+
+    #[test]
+    fn test_dates() {
+        let spec = MazeSpec::new(4, 4);
+        for &date in [(2024, 2, 29), (2000, 2, 29), (2026, 12, 31), (-44, 3, 15)].iter() {
+            assert!(daily_seed(date, &spec).is_ok(), "{:?}", date);
+        }
+        for &date in [(1900, 2, 29), (2026, 2, 29), (2026, 4, 31), (2026, 0, 1), (2026, 13, 1), (2026, 1, 0)].iter() {
+            assert!(daily_seed(date, &spec).is_err(), "{:?}", date);
+        }
+        let (maze, _) = Maze::daily((2024, 2, 29), &spec).unwrap();
+        let (entrance, exit) = (maze.get_entrance().unwrap(), maze.get_exit().unwrap());
+        assert!(maze.solve(entrance.coord, exit.coord).is_some());
+        assert!(maze.sq[maze.get_offset(exit.coord.x, exit.coord.y)].is_carved());
+        assert!(!maze.sq[maze.get_offset(exit.coord.x, exit.coord.y)].is_wall_present(exit.dir));
+        assert_ne!(constants::ID_UNCARVED, maze.sq[0].id);
+        assert!(Maze::daily((2026, 2, 30), &spec).unwrap_err().to_string().contains("2026-02-30"));
+    }
+}
@@ -0,0 +1,426 @@
+//! Per-Square data of the caller's own type, kept alongside a Maze.  Game
+//! state like items or visit counts can be stored here instead of in the
+//! Squares, and it follows the Maze through rotation, mirroring, cropping
+//! and stitching when the two are transformed as a pair.
+//!
+//! Values that refer to a room or a corridor region implement CellIds, so
+//! their ids are renumbered along with the Maze's when a pair is cropped or
+//! stitched.  A value whose room or region is cut out is dropped with it.
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::error::MazeError;
+use crate::maze::transform::{self, Transformable};
+use crate::maze::{Coord, Maze};
+
+/// An optional value for every Square of a grid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MazeData<T> {
+    rows: u32,
+    cols: u32,
+    cells: Vec<Option<T>>,
+}
+
+/// The room or corridor region a value refers to, if any.  The methods do
+/// nothing by default, so types that don't hold ids only need an empty impl.
+pub trait CellIds {
+    /// Returns the id of the room this value refers to.
+    fn room_id(&self) -> Option<i32> {
+        None
+    }
+
+    /// Changes the room this value refers to.
+    fn set_room_id(&mut self, _id: i32) {}
+
+    /// Returns the corridor region this value refers to.
+    fn region_id(&self) -> Option<u32> {
+        None
+    }
+
+    /// Changes the corridor region this value refers to.
+    fn set_region_id(&mut self, _id: u32) {}
+}
+
+/// A reference to a room, kept in step with the Maze's room ids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoomId(pub i32);
+
+impl CellIds for RoomId {
+    fn room_id(&self) -> Option<i32> {
+        Some(self.0)
+    }
+
+    fn set_room_id(&mut self, id: i32) {
+        self.0 = id;
+    }
+}
+
+/// A reference to a corridor region, kept in step with the Maze's regions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionId(pub u32);
+
+impl CellIds for RegionId {
+    fn region_id(&self) -> Option<u32> {
+        Some(self.0)
+    }
+
+    fn set_region_id(&mut self, id: u32) {
+        self.0 = id;
+    }
+}
+
+macro_rules! no_cell_ids {
+    ($($t:ty),*) => { $(impl CellIds for $t {})* };
+}
+
+no_cell_ids!(bool, char, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, String);
+
+impl CellIds for &str {}
+
+impl<T> MazeData<T> {
+    /// Creates empty storage the same size as a Maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::data::MazeData;
+    ///
+    /// let maze = Maze::new(3, 5);
+    /// let mut items = MazeData::new(&maze);
+    /// items.set(Coord::new(4, 0), "key").unwrap();
+    /// let (maze, items) = (maze, items).rotate_cw();
+    /// assert_eq!(Some(&"key"), items.get(Coord::new(2, 4)));
+    /// assert_eq!(3, maze.get_cols());
+    /// ```
+    pub fn new(maze: &Maze) -> MazeData<T> {
+        MazeData::with_size(maze.get_rows(), maze.get_cols())
+    }
+
+    /// Creates empty storage for a grid of the given size.
+    pub fn with_size(rows: u32, cols: u32) -> MazeData<T> {
+        MazeData { rows, cols, cells: (0..rows * cols).map(|_| None).collect() }
+    }
+
+    /// Returns the number of rows.
+    pub fn get_rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Returns the number of columns.
+    pub fn get_cols(&self) -> u32 {
+        self.cols
+    }
+
+    /// Returns the value at coord, if there is one.
+    pub fn get(&self, coord: Coord) -> Option<&T> {
+        self.offset(coord).and_then(|i| self.cells[i].as_ref())
+    }
+
+    /// Returns the value at coord for changing, if there is one.
+    pub fn get_mut(&mut self, coord: Coord) -> Option<&mut T> {
+        match self.offset(coord) {
+            Some(i) => self.cells[i].as_mut(),
+            None => None,
+        }
+    }
+
+    /// Stores a value at coord, returning the one it replaced.
+    pub fn set(&mut self, coord: Coord, value: T) -> Result<Option<T>, MazeError> {
+        match self.offset(coord) {
+            Some(i) => Ok(self.cells[i].replace(value)),
+            None => Err(MazeError::OutOfBounds(coord.x, coord.y)),
+        }
+    }
+
+    /// Removes and returns the value at coord.
+    pub fn remove(&mut self, coord: Coord) -> Option<T> {
+        match self.offset(coord) {
+            Some(i) => self.cells[i].take(),
+            None => None,
+        }
+    }
+
+    /// Returns every stored value with its coordinate, in row-major order.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::data::MazeData;
+    ///
+    /// let mut visits = MazeData::with_size(2, 2);
+    /// visits.set(Coord::new(1, 1), 3).unwrap();
+    /// visits.set(Coord::new(1, 0), 1).unwrap();
+    /// let all: Vec<_> = visits.iter().collect();
+    /// assert_eq!(vec![(Coord::new(1, 0), &1), (Coord::new(1, 1), &3)], all);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (Coord, &T)> + '_ {
+        let cols = self.cols;
+        self.cells.iter().enumerate().filter_map(move |(i, cell)| {
+            cell.as_ref().map(|value| (Coord::new(i as u32 % cols, i as u32 / cols), value))
+        })
+    }
+
+    //
+    // Internal - the index of coord in cells, or None if it's off the grid.
+    //
+    fn offset(&self, coord: Coord) -> Option<usize> {
+        if coord.x < self.cols && coord.y < self.rows {
+            Some((coord.y * self.cols + coord.x) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Clone> Transformable for MazeData<T> {
+    fn rotate_cw(&self) -> MazeData<T> {
+        MazeData { rows: self.cols, cols: self.rows, cells: transform::rotate_cells(&self.cells, self.cols, self.rows) }
+    }
+
+    fn mirror_x(&self) -> MazeData<T> {
+        MazeData { rows: self.rows, cols: self.cols, cells: transform::mirror_x_cells(&self.cells, self.cols) }
+    }
+
+    fn mirror_y(&self) -> MazeData<T> {
+        MazeData { rows: self.rows, cols: self.cols, cells: transform::mirror_y_cells(&self.cells, self.cols) }
+    }
+
+    fn crop(&self, x: u32, y: u32, cols: u32, rows: u32) -> Result<MazeData<T>, MazeError> {
+        let cells = transform::crop_cells(&self.cells, self.cols, self.rows, x, y, cols, rows)?;
+        Ok(MazeData { rows, cols, cells })
+    }
+
+    fn stitch_horizontal(&self, other: &MazeData<T>) -> Result<MazeData<T>, MazeError> {
+        let cells = transform::stitch_cells(&self.cells, self.cols, self.rows,
+                                            &other.cells, other.cols, other.rows, true)?;
+        Ok(MazeData { rows: self.rows, cols: self.cols + other.cols, cells })
+    }
+
+    fn stitch_vertical(&self, other: &MazeData<T>) -> Result<MazeData<T>, MazeError> {
+        let cells = transform::stitch_cells(&self.cells, self.cols, self.rows,
+                                            &other.cells, other.cols, other.rows, false)?;
+        Ok(MazeData { rows: self.rows + other.rows, cols: self.cols, cells })
+    }
+}
+
+/// A Maze and its data, transformed together.  Room and region ids in the
+/// data are renumbered to match the new Maze.
+impl<T: Clone + CellIds> Transformable for (Maze, MazeData<T>) {
+    fn rotate_cw(&self) -> Self {
+        let from = transform::rotate_cells(&sources(&self.0, 0), self.0.get_cols(), self.0.get_rows());
+        let maze = self.0.rotate_cw();
+        let data = renumbered(self.1.rotate_cw(), &maze, &from, &[self]);
+        (maze, data)
+    }
+
+    fn mirror_x(&self) -> Self {
+        let from = transform::mirror_x_cells(&sources(&self.0, 0), self.0.get_cols());
+        let maze = self.0.mirror_x();
+        let data = renumbered(self.1.mirror_x(), &maze, &from, &[self]);
+        (maze, data)
+    }
+
+    fn mirror_y(&self) -> Self {
+        let from = transform::mirror_y_cells(&sources(&self.0, 0), self.0.get_cols());
+        let maze = self.0.mirror_y();
+        let data = renumbered(self.1.mirror_y(), &maze, &from, &[self]);
+        (maze, data)
+    }
+
+    fn crop(&self, x: u32, y: u32, cols: u32, rows: u32) -> Result<Self, MazeError> {
+        let from = transform::crop_cells(&sources(&self.0, 0), self.0.get_cols(), self.0.get_rows(),
+                                         x, y, cols, rows)?;
+        let maze = self.0.crop(x, y, cols, rows)?;
+        let data = renumbered(self.1.crop(x, y, cols, rows)?, &maze, &from, &[self]);
+        Ok((maze, data))
+    }
+
+    fn stitch_horizontal(&self, other: &Self) -> Result<Self, MazeError> {
+        let from = transform::stitch_cells(&sources(&self.0, 0), self.0.get_cols(), self.0.get_rows(),
+                                           &sources(&other.0, 1), other.0.get_cols(), other.0.get_rows(), true)?;
+        let maze = self.0.stitch_horizontal(&other.0)?;
+        let data = renumbered(self.1.stitch_horizontal(&other.1)?, &maze, &from, &[self, other]);
+        Ok((maze, data))
+    }
+
+    fn stitch_vertical(&self, other: &Self) -> Result<Self, MazeError> {
+        let from = transform::stitch_cells(&sources(&self.0, 0), self.0.get_cols(), self.0.get_rows(),
+                                           &sources(&other.0, 1), other.0.get_cols(), other.0.get_rows(), false)?;
+        let maze = self.0.stitch_vertical(&other.0)?;
+        let data = renumbered(self.1.stitch_vertical(&other.1)?, &maze, &from, &[self, other]);
+        Ok((maze, data))
+    }
+}
+
+//
+// Internal - tags every Square of maze with which of the transformed pairs
+// it came from and its index there, so the tags can be moved like data.
+//
+fn sources(maze: &Maze, pair: usize) -> Vec<(usize, usize)> {
+    (0..maze.sq.len()).map(|i| (pair, i)).collect()
+}
+
+//
+// Internal - renumbers the ids in data, which was moved from the pairs in
+// old to line up with maze.  from gives where each Square of maze came
+// from.  Data that isn't the size of its Maze is left alone, and so are
+// region ids if maze hasn't kept any corridor regions.
+//
+fn renumbered<T: CellIds>(mut data: MazeData<T>, maze: &Maze, from: &[(usize, usize)],
+                          old: &[&(Maze, MazeData<T>)]) -> MazeData<T> {
+    if old.iter().any(|(m, d)| d.rows != m.get_rows() || d.cols != m.get_cols()) {
+        return data;
+    }
+    let mut rooms = BTreeMap::new();
+    let mut regions = BTreeMap::new();
+    for (i, &(pair, j)) in from.iter().enumerate() {
+        let before = &old[pair].0;
+        if before.sq[j].is_part_of_room() && maze.sq[i].is_part_of_room() {
+            rooms.insert((pair, before.sq[j].id), maze.sq[i].id);
+        }
+        if let (Some(&Some(b)), Some(&Some(a))) = (before.corridor_regions.get(j), maze.corridor_regions.get(i)) {
+            regions.insert((pair, b), a);
+        }
+    }
+
+    for (cell, &(pair, _)) in data.cells.iter_mut().zip(from.iter()) {
+        let kept = match cell.as_mut() {
+            Some(value) => {
+                let room = value.room_id().map(|id| rooms.get(&(pair, id)).cloned());
+                let region = value.region_id()
+                    .filter(|_| !maze.corridor_regions.is_empty())
+                    .map(|id| regions.get(&(pair, id)).cloned());
+                if let Some(Some(id)) = room {
+                    value.set_room_id(id);
+                }
+                if let Some(Some(id)) = region {
+                    value.set_region_id(id);
+                }
+                room != Some(None) && region != Some(None)
+            }
+            None => true,
+        };
+        if !kept {
+            *cell = None;
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    // A maze with a label on each of its corners
+    fn labeled() -> (Maze, MazeData<char>) {
+        let mut maze = Maze::new(4, 6);
+        maze.generate_perfect_seeded(419).unwrap();
+        let mut data = MazeData::new(&maze);
+        for &(x, y, label) in [(0, 0, 'a'), (5, 0, 'b'), (5, 3, 'c'), (0, 3, 'd')].iter() {
+            data.set(Coord::new(x, y), label).unwrap();
+        }
+        (maze, data)
+    }
+
+    fn find(data: &MazeData<char>, label: char) -> Option<Coord> {
+        data.iter().find(|&(_, &l)| l == label).map(|(c, _)| c)
+    }
+
+    #[test]
+    fn test_get_set() {
+        let (_, mut data) = labeled();
+        assert_eq!(Some(&'b'), data.get(Coord::new(5, 0)));
+        assert_eq!(None, data.get(Coord::new(1, 0)));
+        assert_eq!(None, data.get(Coord::new(6, 0)));
+        assert_eq!(Ok(Some('b')), data.set(Coord::new(5, 0), 'B'));
+        *data.get_mut(Coord::new(5, 0)).unwrap() = 'x';
+        assert_eq!(Some('x'), data.remove(Coord::new(5, 0)));
+        assert_eq!(3, data.iter().count());
+        assert!(data.set(Coord::new(0, 4), 'e').is_err());
+    }
+
+    #[test]
+    fn test_rotate_moves_data() {
+        let pair = labeled();
+        let (maze, data) = pair.rotate_cw();
+        assert_eq!((4, 6), (data.get_cols(), data.get_rows()));
+        // The corners go round clockwise
+        assert_eq!(Some(Coord::new(3, 0)), find(&data, 'a'));
+        assert_eq!(Some(Coord::new(3, 5)), find(&data, 'b'));
+        assert_eq!(Some(Coord::new(0, 5)), find(&data, 'c'));
+        assert_eq!(Some(Coord::new(0, 0)), find(&data, 'd'));
+
+        // Data stays with the Square it was attached to: a's only neighbor
+        // is still the same Square
+        let (old_maze, old_data) = labeled();
+        let mut marked = old_data.clone();
+        let next = old_maze.open_neighbors(0, 0)[0];
+        marked.set(next, 'n').unwrap();
+        let (_, marked) = (old_maze, marked).rotate_cw();
+        let a = find(&data, 'a').unwrap();
+        assert_eq!(vec![find(&marked, 'n').unwrap()], maze.open_neighbors(a.x, a.y));
+    }
+
+    #[test]
+    fn test_mirror_crop_stitch() {
+        let pair = labeled();
+        let (_, data) = pair.mirror_x();
+        assert_eq!(Some(Coord::new(5, 0)), find(&data, 'a'));
+        let (_, data) = pair.mirror_y();
+        assert_eq!(Some(Coord::new(0, 3)), find(&data, 'a'));
+
+        let (maze, data) = pair.crop(3, 0, 3, 4).unwrap();
+        assert_eq!(Some(Coord::new(2, 0)), find(&data, 'b'));
+        assert_eq!(None, find(&data, 'a'));
+        assert!(maze.walls_consistent());
+
+        let (maze, data) = pair.stitch_vertical(&pair.mirror_y()).unwrap();
+        assert_eq!((6, 8), (maze.get_cols(), maze.get_rows()));
+        assert_eq!(Some(Coord::new(5, 7)), data.iter().filter(|&(_, &l)| l == 'b').map(|(c, _)| c).last());
+        assert!(pair.stitch_horizontal(&pair.rotate_cw()).is_err());
+        assert!(pair.crop(0, 0, 7, 1).is_err());
+        assert!(maze.sq[maze.get_offset(0, 3)].is_wall_present(constants::DIR_SOUTH));
+    }
+
+    #[test]
+    fn test_ids_follow_the_maze() {
+        let mut left = Maze::new(12, 12);
+        left.generate_seeded((3, 2, 3, 2, 3), 419).unwrap();
+        left.partition_corridors(2, 419).unwrap();
+        let mut rooms = MazeData::new(&left);
+        let mut regions = MazeData::new(&left);
+        for room in left.rooms() {
+            rooms.set(room.cells()[0], RoomId(room.get_id())).unwrap();
+        }
+        for (i, region) in left.corridor_regions().iter().enumerate() {
+            if let Some(region) = *region {
+                regions.set(Coord::new(i as u32 % 12, i as u32 / 12), RegionId(region)).unwrap();
+            }
+        }
+
+        // The second half's rooms and regions follow the first's
+        let pair = (left.clone(), rooms);
+        let (maze, data) = pair.stitch_horizontal(&pair.mirror_y()).unwrap();
+        assert_eq!(2 * left.get_num_rooms(), maze.get_num_rooms());
+        for (c, &RoomId(id)) in data.iter() {
+            assert_eq!(id, maze.sq[maze.get_offset(c.x, c.y)].id);
+        }
+        let pair = (left.clone(), regions);
+        let (maze, data) = pair.stitch_vertical(&pair.rotate_cw().rotate_cw()).unwrap();
+        assert!(data.iter().any(|(_, &RegionId(id))| id >= 2));
+        for (c, &RegionId(id)) in data.iter() {
+            assert_eq!(Some(id), maze.corridor_region(c));
+        }
+
+        // Ids pointing at rooms that were cut out go with them
+        assert_eq!(2, left.get_num_rooms());
+        let mut far = MazeData::new(&left);
+        far.set(Coord::new(0, 0), RoomId(2)).unwrap();
+        far.set(Coord::new(1, 0), RoomId(1)).unwrap();
+        let (maze, data) = (left, far).crop(0, 0, 6, 12).unwrap();
+        assert_eq!(1, maze.get_num_rooms());
+        assert_eq!(vec![(Coord::new(1, 0), &RoomId(1))], data.iter().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,253 @@
+//! The attract mode shown behind the menu: a maze is generated and solved,
+//! and an automated player walks the solution one Square at a time before
+//! the next maze is generated.  Only the stepping and timing live here, so
+//! the demo can be run headless; whatever front end is showing it draws
+//! each frame and stops when a key is pressed.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::error::MazeError;
+use crate::maze::fit::{fit_text, FitMode, TerminalSize};
+use crate::maze::{Coord, Maze};
+
+/// How the demo's mazes are made and how fast they're walked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DemoConfig {
+    pub rows: u32,
+    pub cols: u32,
+    /// The seed of the first maze.  Each maze after it uses the next seed.
+    pub seed: u64,
+    /// The (count, min_x, max_x, min_y, max_y) of rooms to add, if any.
+    pub rooms: Option<(u32, u32, u32, u32, u32)>,
+    /// How long the player waits on each Square.
+    pub step_interval: Duration,
+}
+
+impl DemoConfig {
+    /// Creates a config for perfect mazes of the given size, walked at ten
+    /// Squares a second.
+    pub fn new(rows: u32, cols: u32, seed: u64) -> DemoConfig {
+        DemoConfig { rows, cols, seed, rooms: None, step_interval: Duration::from_millis(100) }
+    }
+}
+
+/// Something that happened during a step of the demo.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DemoEvent {
+    /// The player moved onto a Square.
+    Moved(Coord),
+    /// The player got to the exit, having taken this many steps.
+    ReachedExit { steps: u32 },
+    /// A new maze was generated, and the player is at its entrance.
+    NewMaze,
+}
+
+/// A running demo.
+#[derive(Clone, Debug)]
+pub struct Demo {
+    config: DemoConfig,
+    maze: Maze,
+    path: Vec<Coord>,
+    // The index in path of the Square the player is on
+    step: usize,
+    // Time passed since the last step
+    waited: Duration,
+    cycles: u64,
+}
+
+impl Demo {
+    /// Starts a demo with the first maze generated and the player at its
+    /// entrance.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::demo::{Demo, DemoConfig, DemoEvent};
+    ///
+    /// let mut demo = Demo::new(DemoConfig::new(8, 8, 1)).unwrap();
+    /// let length = demo.solution().len() as u32 - 1;
+    /// let mut steps = 0;
+    /// while let DemoEvent::Moved(_) = demo.step().unwrap() {
+    ///     steps += 1;
+    /// }
+    /// assert_eq!(length - 1, steps);
+    /// ```
+    pub fn new(config: DemoConfig) -> Result<Demo, MazeError> {
+        let mut demo = Demo {
+            config,
+            maze: Maze::new(config.rows, config.cols),
+            path: Vec::new(),
+            step: 0,
+            waited: Duration::from_secs(0),
+            cycles: 0,
+        };
+        demo.next_maze()?;
+        Ok(demo)
+    }
+
+    /// Returns the maze being walked.
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    /// Returns the solution being walked, from entrance to exit.
+    pub fn solution(&self) -> &[Coord] {
+        &self.path
+    }
+
+    /// Returns the Square the player is on.
+    pub fn player(&self) -> Coord {
+        self.path[self.step]
+    }
+
+    /// Returns the number of mazes that have been walked to the end.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Moves the player one Square along the solution.  Once the player is
+    /// on the exit, the next step generates a new maze instead.
+    pub fn step(&mut self) -> Result<DemoEvent, MazeError> {
+        if self.step + 1 >= self.path.len() {
+            self.cycles += 1;
+            self.next_maze()?;
+            return Ok(DemoEvent::NewMaze);
+        }
+        self.step += 1;
+        if self.step + 1 == self.path.len() {
+            Ok(DemoEvent::ReachedExit { steps: self.step as u32 })
+        } else {
+            Ok(DemoEvent::Moved(self.path[self.step]))
+        }
+    }
+
+    /// Lets elapsed time pass, taking a step for every step_interval of it.
+    /// Returns what happened, oldest first.  Leftover time counts toward the
+    /// next call.
+    pub fn advance(&mut self, elapsed: Duration) -> Result<Vec<DemoEvent>, MazeError> {
+        // A zero interval would never run out, so it steps once a call
+        if self.config.step_interval == Duration::from_secs(0) {
+            return Ok(vec![self.step()?]);
+        }
+        let mut events = Vec::new();
+        self.waited += elapsed;
+        while self.waited >= self.config.step_interval {
+            self.waited -= self.config.step_interval;
+            events.push(self.step()?);
+        }
+        Ok(events)
+    }
+
+    /// Draws the maze like render_ascii_with_path(), with the Squares
+    /// walked so far as the path and the player as an '@'.
+    pub fn render_ascii(&self) -> String {
+        let rendered = self.maze.render_ascii_with_path(&self.path[..=self.step]);
+        let mut lines: Vec<Vec<char>> = rendered.lines().map(|l| l.chars().collect()).collect();
+        let player = self.player();
+        lines[(player.y * 2 + 1) as usize][(player.x * 2 + 1) as usize] = '@';
+        let mut out = String::new();
+        for line in lines {
+            out.extend(line);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Draws the demo like render_ascii(), fitted to the size the terminal
+    /// reports.  A maze too big for it is shown through a window that
+    /// follows the player.
+    pub fn render_for(&self, size: &dyn TerminalSize) -> String {
+        let drawing = self.render_ascii();
+        match size.terminal_size() {
+            Some((width, height)) => {
+                let text_size = (self.maze.get_cols() * 2 + 1, self.maze.get_rows() * 2 + 1);
+                let mode = FitMode::pick(text_size, (width, height), Some(self.player()));
+                fit_text(&drawing, width, height, mode)
+            }
+            None => drawing,
+        }
+    }
+
+    //
+    // Internal - generates the next maze, puts its entrance and exit as far
+    // apart as possible and solves it.
+    //
+    fn next_maze(&mut self) -> Result<(), MazeError> {
+        let seed = self.config.seed.wrapping_add(self.cycles);
+        let mut maze = Maze::new(self.config.rows, self.config.cols);
+        match self.config.rooms {
+            Some(rooms) => maze.generate_seeded(rooms, seed),
+            None => maze.generate_perfect_seeded(seed),
+        }.map_err(MazeError::InvalidArgument)?;
+        maze.place_entrance_exit_longest()?;
+        let (entrance, exit) = match (maze.get_entrance(), maze.get_exit()) {
+            (Some(entrance), Some(exit)) => (entrance.coord, exit.coord),
+            _ => return Err(MazeError::InvalidArgument("the maze has no entrance and exit".to_string())),
+        };
+        self.path = maze.solve(entrance, exit).ok_or(MazeError::Unreachable(entrance, exit))?;
+        self.maze = maze;
+        self.step = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_cycle() {
+        let mut config = DemoConfig::new(10, 14, 439);
+        config.step_interval = Duration::from_millis(50);
+        let mut demo = Demo::new(config).unwrap();
+        let path = demo.solution().to_vec();
+        let first = demo.maze().clone();
+        assert_eq!(demo.maze().get_entrance().unwrap().coord, demo.player());
+        assert_eq!(demo.maze().get_exit().unwrap().coord, *path.last().unwrap());
+
+        // Uneven slices of time still give one step per interval
+        let mut events = Vec::new();
+        for ms in [30, 30, 140, 20, 75].iter().cycle() {
+            events.extend(demo.advance(Duration::from_millis(*ms)).unwrap());
+            if demo.cycles() == 1 {
+                break;
+            }
+        }
+        // The exit is reached in exactly as many steps as the solution has
+        let reached = path.len() - 2;
+        for (i, event) in events[..reached].iter().enumerate() {
+            assert_eq!(DemoEvent::Moved(path[i + 1]), *event);
+        }
+        assert_eq!(DemoEvent::ReachedExit { steps: path.len() as u32 - 1 }, events[reached]);
+        assert_eq!(DemoEvent::NewMaze, events[reached + 1]);
+        assert_ne!(first, *demo.maze());
+        assert_eq!(demo.maze().get_entrance().unwrap().coord, demo.player());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut demo = Demo::new(DemoConfig::new(6, 6, 1)).unwrap();
+        for _ in 0..3 {
+            demo.step().unwrap();
+        }
+        let rendered = demo.render_ascii();
+        assert_eq!(1, rendered.matches('@').count());
+        assert!(rendered.contains('.'));
+        assert_eq!(demo.maze().render_ascii().len(), rendered.len());
+        assert_eq!(rendered, demo.render_for(&crate::maze::fit::FixedSize(13, 13)));
+
+        // A terminal too small for the maze follows the player
+        let mut demo = Demo::new(DemoConfig::new(20, 40, 1)).unwrap();
+        while demo.player().x < 20 {
+            demo.step().unwrap();
+        }
+        let fitted = demo.render_for(&crate::maze::fit::FixedSize(30, 10));
+        assert_eq!(10, fitted.lines().count());
+        assert!(fitted.lines().all(|l| l.chars().count() == 30));
+        assert_eq!(1, fitted.matches('@').count());
+
+        // Nothing happens until a whole interval has passed
+        assert!(demo.advance(Duration::from_millis(99)).unwrap().is_empty());
+        assert_eq!(1, demo.advance(Duration::from_millis(1)).unwrap().len());
+    }
+}
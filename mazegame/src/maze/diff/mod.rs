@@ -0,0 +1,223 @@
+//! Comparing two mazes of the same size, Square by Square.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::square::Square;
+use crate::maze::{Coord, Maze};
+
+/// A Square whose walls differ between two mazes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WallChange {
+    pub coord: Coord,
+    /// The walls in the first maze, indexed by direction.
+    pub before: [bool; constants::NUM_DIRECTIONS as usize],
+    /// The walls in the second maze.
+    pub after: [bool; constants::NUM_DIRECTIONS as usize],
+}
+
+/// A Square whose id differs between two mazes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdChange {
+    pub coord: Coord,
+    pub before: i32,
+    pub after: i32,
+}
+
+/// The kinds of passage a Square can have besides its open walls, as the
+/// bit masks kept in the Square.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Passages {
+    /// Tunnels running under the Square.
+    pub under: u8,
+    /// One-way openings, by direction.
+    pub one_way: u8,
+    /// Secret openings, by direction.
+    pub secret: u8,
+}
+
+/// A Square whose tunnels, one-way or secret openings differ between two
+/// mazes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassageChange {
+    pub coord: Coord,
+    pub before: Passages,
+    pub after: Passages,
+}
+
+/// The differences between two mazes, in row-major order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MazeDiff {
+    pub walls: Vec<WallChange>,
+    pub ids: Vec<IdChange>,
+    pub passages: Vec<PassageChange>,
+}
+
+impl MazeDiff {
+    /// Returns whether the mazes were the same.
+    pub fn is_empty(&self) -> bool {
+        self.walls.is_empty() && self.ids.is_empty() && self.passages.is_empty()
+    }
+
+    /// Returns the number of Squares with any change.
+    pub fn changed_squares(&self) -> u32 {
+        self.changed_coords().len() as u32
+    }
+
+    /// Returns the number of wall sides that changed.  A wall between two
+    /// Squares has two sides, so opening it counts twice.
+    pub fn changed_walls(&self) -> u32 {
+        self.walls.iter()
+            .map(|w| w.before.iter().zip(w.after.iter()).filter(|&(b, a)| b != a).count() as u32)
+            .sum()
+    }
+
+    /// Returns the number of tunnels, one-way and secret markings that were
+    /// added or removed.
+    pub fn changed_passages(&self) -> u32 {
+        self.passages.iter()
+            .map(|p| {
+                let (b, a) = (p.before, p.after);
+                (b.under ^ a.under).count_ones() + (b.one_way ^ a.one_way).count_ones()
+                    + (b.secret ^ a.secret).count_ones()
+            })
+            .sum()
+    }
+
+    //
+    // Internal - every changed Square, sorted and without duplicates.
+    //
+    fn changed_coords(&self) -> Vec<Coord> {
+        let mut coords: Vec<Coord> = self.walls.iter().map(|w| w.coord)
+            .chain(self.ids.iter().map(|i| i.coord))
+            .chain(self.passages.iter().map(|p| p.coord))
+            .collect();
+        coords.sort_by_key(|c| (c.y, c.x));
+        coords.dedup();
+        coords
+    }
+}
+
+impl Maze {
+    /// Lists the Squares whose walls, ids, tunnels, one-way or secret
+    /// openings differ from other's.  Both mazes must be the same size.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let before = Maze::new(3, 3);
+    /// let mut after = before.clone();
+    /// after.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// let diff = before.diff(&after).unwrap();
+    /// assert_eq!(2, diff.changed_squares());
+    /// assert_eq!(2, diff.changed_walls());
+    /// ```
+    pub fn diff(&self, other: &Maze) -> Result<MazeDiff, MazeError> {
+        if (self.cols, self.rows) != (other.cols, other.rows) {
+            return Err(MazeError::InvalidArgument(format!("can't compare a {}x{} maze with a {}x{} one",
+                                                          self.cols, self.rows, other.cols, other.rows)));
+        }
+        let mut diff = MazeDiff::default();
+        for (i, (before, after)) in self.sq.iter().zip(other.sq.iter()).enumerate() {
+            let coord = self.get_coord(i);
+            if before.wall_present != after.wall_present {
+                diff.walls.push(WallChange { coord, before: before.wall_present, after: after.wall_present });
+            }
+            if before.id != after.id {
+                diff.ids.push(IdChange { coord, before: before.id, after: after.id });
+            }
+            let passages = |sq: &Square| Passages { under: sq.under, one_way: sq.one_way, secret: sq.secret };
+            if passages(before) != passages(after) {
+                diff.passages.push(PassageChange { coord, before: passages(before), after: passages(after) });
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Renders the maze like render_ascii(), with the Squares in a diff
+    /// drawn as '*'.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let before = Maze::new(2, 2);
+    /// let mut after = before.clone();
+    /// after.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// let diff = before.diff(&after).unwrap();
+    /// assert_eq!("XXXXX\nX* *X\nXXXXX\nX X X\nXXXXX\n", after.render_diff(&diff));
+    /// ```
+    pub fn render_diff(&self, diff: &MazeDiff) -> String {
+        let mut lines: Vec<Vec<char>> = self.render_ascii().lines().map(|l| l.chars().collect()).collect();
+        for c in diff.changed_coords().iter().filter(|c| self.in_bounds(c.x, c.y)) {
+            lines[(c.y * 2 + 1) as usize][(c.x * 2 + 1) as usize] = '*';
+        }
+        let mut out = String::new();
+        for line in lines {
+            out.extend(line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_changes() {
+        let mut before = Maze::new(8, 8);
+        before.generate_seeded((2, 2, 3, 2, 3), 423).unwrap();
+        let mut after = before.clone();
+        // Build a wall on one side only, and relabel a Square
+        let wall_sq = after.get_offset(3, 5);
+        let open_dir = (0..constants::NUM_DIRECTIONS).find(|&d| !after.sq[wall_sq].is_wall_present(d)).unwrap();
+        after.sq[wall_sq].build_wall(open_dir);
+        let id_sq = after.get_offset(6, 1);
+        after.sq[id_sq].id = 99;
+
+        let diff = before.diff(&after).unwrap();
+        assert_eq!(1, diff.walls.len());
+        assert_eq!(Coord::new(3, 5), diff.walls[0].coord);
+        assert!(!diff.walls[0].before[open_dir as usize]);
+        assert!(diff.walls[0].after[open_dir as usize]);
+        assert_eq!(vec![IdChange { coord: Coord::new(6, 1), before: before.sq[id_sq].id, after: 99 }], diff.ids);
+        assert_eq!(2, diff.changed_squares());
+        assert_eq!(1, diff.changed_walls());
+
+        let rendered = after.render_diff(&diff);
+        assert_eq!(2, rendered.matches('*').count());
+        assert_eq!(Some('*'), rendered.lines().nth(11).unwrap().chars().nth(7));
+
+        assert!(before.diff(&before).unwrap().is_empty());
+        assert_eq!(MazeDiff::default(), after.diff(&after.clone()).unwrap());
+    }
+
+    #[test]
+    fn test_passage_changes() {
+        let mut before = Maze::new(4, 4);
+        before.generate_perfect_seeded(423).unwrap();
+        let mut after = before.clone();
+        let open_dir = (0..constants::NUM_DIRECTIONS).find(|&d| !after.sq[0].is_wall_present(d)).unwrap();
+        after.sq[0].set_one_way(open_dir, true);
+        let tunnel = after.get_offset(2, 2);
+        after.sq[tunnel].add_under_passage(constants::DIR_NORTH);
+
+        let diff = before.diff(&after).unwrap();
+        assert!(diff.walls.is_empty() && diff.ids.is_empty());
+        assert_eq!(vec![Coord::new(0, 0), Coord::new(2, 2)], diff.passages.iter().map(|p| p.coord).collect::<Vec<_>>());
+        assert_eq!(1 << open_dir, diff.passages[0].after.one_way);
+        assert_eq!(0, diff.passages[0].before.one_way);
+        assert_eq!(2, diff.changed_squares());
+        assert_eq!(2, diff.changed_passages());
+        assert_eq!(0, diff.changed_walls());
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        assert!(Maze::new(4, 5).diff(&Maze::new(5, 4)).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Scoring how hard a maze is to solve, and searching seeds for a maze
+//! with a given score.
+//!
+//! The score looks at the solution between the start and exit, where the
+//! start is the Square from set_start() or, if none was set, the entrance.
+//! Half of it is the share of the carved Squares the solution passes
+//! through, since a long solution takes longer to find; the other half is
+//! the share of the solution's Squares that are junctions, since every
+//! junction is a chance to go the wrong way.  Scores run from 0 to 100.
+use alloc::string::ToString;
+
+use crate::error::MazeError;
+use crate::maze::analysis::MazeAnalysis;
+use crate::maze::Maze;
+
+/// How hard a maze is to solve, from 0 to 100.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct DifficultyScore(pub f64);
+
+/// The maze generate_with_difficulty() settled on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DifficultySearch {
+    /// The seed that regenerates the maze with generate_seeded().
+    pub seed: u64,
+    pub score: DifficultyScore,
+    /// Whether the score is within the tolerance.  If it isn't, every
+    /// attempt was used up and this is the closest one.
+    pub hit: bool,
+    /// How many seeds were tried.
+    pub attempts: u32,
+}
+
+impl MazeAnalysis {
+    /// Returns the Maze's difficulty, like Maze::difficulty(), using the
+    /// cached distances from the exit.
+    pub fn difficulty_cached(&mut self, maze: &Maze) -> Option<DifficultyScore> {
+        let (start, exit) = (maze.player_start()?, maze.get_exit()?.coord);
+        let carved = self.stats_cached(maze).carved_squares;
+        let dist = self.distance_map_cached(maze, exit);
+        let mut steps = dist[maze.get_offset(start.x, start.y)]?;
+        let length = steps + 1;
+
+        // Walk down the distances from the start, counting junctions
+        let mut junctions = 0;
+        let mut at = start;
+        while steps > 0 {
+            let open = maze.open_neighbors(at.x, at.y);
+            if open.len() >= 3 {
+                junctions += 1;
+            }
+            at = *open.iter().find(|c| dist[maze.get_offset(c.x, c.y)] == Some(steps - 1))?;
+            steps -= 1;
+        }
+        Some(score(length, junctions, carved.max(length)))
+    }
+}
+
+impl Maze {
+    /// Returns how hard the Maze is to solve from its start to its exit, or
+    /// None if it's missing either or they aren't connected.  The start is
+    /// the Square from set_start(), or the entrance if none was set.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// // A single corridor: every Square is on the path, with no junctions
+    /// let mut maze = Maze::from_compact("4cc8").unwrap();
+    /// maze.set_entrance(0, 0, DIR_WEST).unwrap();
+    /// maze.set_exit(3, 0, DIR_EAST).unwrap();
+    /// assert_eq!(50.0, maze.difficulty().unwrap().0);
+    /// ```
+    pub fn difficulty(&self) -> Option<DifficultyScore> {
+        MazeAnalysis::new().difficulty_cached(self)
+    }
+
+    /// Tries seeds from seed upward, generating a maze with rooms (as in
+    /// generate_seeded()) and placing the entrance and exit with
+    /// place_entrance_exit_longest(), until one scores within tolerance of
+    /// target.  The Maze is left holding that maze.  If none of the
+    /// max_attempts seeds do, it's left holding the closest one instead and
+    /// the result's `hit` is false.  Ties go to the earlier seed, so the same
+    /// arguments always give the same maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::difficulty::DifficultyScore;
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(12, 12);
+    /// let found = maze.generate_with_difficulty(DifficultyScore(25.0), 5.0, (0, 0, 0, 0, 0), 50, 1).unwrap();
+    /// assert!(found.hit);
+    /// assert!((maze.difficulty().unwrap().0 - 25.0).abs() <= 5.0);
+    ///
+    /// let mut again = Maze::new(12, 12);
+    /// again.generate_seeded((0, 0, 0, 0, 0), found.seed).unwrap();
+    /// again.place_entrance_exit_longest().unwrap();
+    /// assert_eq!(maze, again);
+    /// ```
+    pub fn generate_with_difficulty(&mut self, target: DifficultyScore, tolerance: f64,
+                                    rooms: (u32, u32, u32, u32, u32), max_attempts: u32, seed: u64)
+                                    -> Result<DifficultySearch, MazeError> {
+        if !target.0.is_finite() || !tolerance.is_finite() || tolerance < 0.0 {
+            return Err(MazeError::InvalidArgument(
+                "the target and tolerance must be numbers, and the tolerance can't be negative".to_string()));
+        }
+        if max_attempts == 0 {
+            return Err(MazeError::InvalidArgument("max_attempts must be at least 1".to_string()));
+        }
+
+        let mut analysis = MazeAnalysis::new();
+        let mut best: Option<DifficultySearch> = None;
+        for attempt in 0..max_attempts {
+            let candidate = seed.wrapping_add(attempt as u64);
+            self.reset();
+            self.generate_seeded(rooms, candidate).map_err(MazeError::InvalidArgument)?;
+            self.place_entrance_exit_longest()?;
+            let score = match analysis.difficulty_cached(self) {
+                Some(score) => score,
+                None => continue,
+            };
+            gen_debug!("difficulty seed={} score={:.2}", candidate, score.0);
+
+            let off = (score.0 - target.0).abs();
+            if best.is_none_or(|b| off < (b.score.0 - target.0).abs()) {
+                best = Some(DifficultySearch { seed: candidate, score, hit: off <= tolerance, attempts: attempt + 1 });
+            }
+            if off <= tolerance {
+                return Ok(best.unwrap());
+            }
+        }
+
+        let mut best = match best {
+            Some(best) => best,
+            None => return Err(MazeError::ConstraintUnsatisfiable(
+                format!("none of {} mazes had a solvable entrance and exit", max_attempts))),
+        };
+        best.attempts = max_attempts;
+        // The Maze holds the last attempt, so go back to the closest one
+        self.reset();
+        self.generate_seeded(rooms, best.seed).map_err(MazeError::InvalidArgument)?;
+        self.place_entrance_exit_longest()?;
+        Ok(best)
+    }
+}
+
+//
+// Internal - the score for a solution of length Squares with junctions
+// junctions on it, in a maze with carved carved Squares.
+//
+fn score(length: u32, junctions: u32, carved: u32) -> DifficultyScore {
+    let path_share = length as f64 / carved as f64;
+    let junction_share = junctions as f64 / length as f64;
+    DifficultyScore(50.0 * (path_share + junction_share))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_WEST};
+
+    #[test]
+    fn test_difficulty() {
+        let mut maze = Maze::from_compact("4cc8").unwrap();
+        assert_eq!(None, maze.difficulty());
+        maze.set_entrance(0, 0, DIR_WEST).unwrap();
+        maze.set_exit(3, 0, DIR_EAST).unwrap();
+        assert_eq!(Some(DifficultyScore(50.0)), maze.difficulty());
+
+        // A T: three Squares on the path and one off it, with one junction
+        let mut t = Maze::from_compact("4e8\n010").unwrap();
+        t.set_entrance(0, 0, DIR_WEST).unwrap();
+        t.set_exit(2, 0, DIR_EAST).unwrap();
+        let expected = 50.0 * (3.0 / 4.0 + 1.0 / 3.0);
+        assert!((t.difficulty().unwrap().0 - expected).abs() < 1e-9);
+
+        // A start Square takes the entrance's place
+        maze.set_start(1, 0).unwrap();
+        assert_eq!(Some(DifficultyScore(37.5)), maze.difficulty());
+
+        // Cut off: no score
+        let mut apart = Maze::from_compact("48\n48").unwrap();
+        apart.set_entrance(0, 0, DIR_WEST).unwrap();
+        apart.set_exit(1, 1, DIR_EAST).unwrap();
+        assert_eq!(None, apart.difficulty());
+    }
+
+    #[test]
+    fn test_search() {
+        let mut maze = Maze::new(15, 15);
+        let found = maze.generate_with_difficulty(DifficultyScore(30.0), 4.0, (3, 2, 3, 2, 3), 200, 456).unwrap();
+        assert!(found.hit);
+        assert!(found.attempts >= 1 && found.attempts <= 200);
+        assert_eq!(Some(found.score), maze.difficulty());
+        assert!((found.score.0 - 30.0).abs() <= 4.0);
+
+        // The same base seed finds the same maze
+        let mut again = Maze::new(15, 15);
+        assert_eq!(found, again.generate_with_difficulty(DifficultyScore(30.0), 4.0, (3, 2, 3, 2, 3), 200, 456).unwrap());
+        assert_eq!(maze, again);
+    }
+
+    #[test]
+    fn test_closest_when_out_of_attempts() {
+        // Nothing scores 100, so every attempt is used
+        let mut maze = Maze::new(10, 10);
+        let found = maze.generate_with_difficulty(DifficultyScore(100.0), 0.0, (0, 0, 0, 0, 0), 20, 7).unwrap();
+        assert!(!found.hit);
+        assert_eq!(20, found.attempts);
+        assert_eq!(Some(found.score), maze.difficulty());
+
+        let scores: Vec<f64> = (0..20).map(|i| {
+            let mut m = Maze::new(10, 10);
+            m.generate_seeded((0, 0, 0, 0, 0), 7 + i).unwrap();
+            m.place_entrance_exit_longest().unwrap();
+            m.difficulty().unwrap().0
+        }).collect();
+        assert_eq!(scores.iter().cloned().fold(0.0, f64::max), found.score.0);
+
+        assert!(maze.generate_with_difficulty(DifficultyScore(10.0), -1.0, (0, 0, 0, 0, 0), 20, 7).is_err());
+        assert!(maze.generate_with_difficulty(DifficultyScore(10.0), 1.0, (0, 0, 0, 0, 0), 0, 7).is_err());
+    }
+
+    #[test]
+    fn test_reuses_maze() {
+        let mut maze = Maze::new(8, 8);
+        let cells = maze.sq.as_ptr();
+        maze.generate_with_difficulty(DifficultyScore(100.0), 0.0, (0, 0, 0, 0, 0), 5, 1).unwrap();
+        assert_eq!(cells, maze.sq.as_ptr());
+    }
+}
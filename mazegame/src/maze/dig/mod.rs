@@ -0,0 +1,218 @@
+//! Breaking single walls during play, like with a pickaxe power-up, and
+//! putting them back up, like a door slamming shut.  Each changed wall is
+//! reported back, so anything built from the Maze (visibility, distance
+//! maps, the renderer) knows what to update.
+use alloc::string::ToString;
+
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze, Opening};
+use crate::constants::{self, Direction};
+
+/// A wall that was knocked down by break_wall_pair_checked().
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WallBroken {
+    /// The Square the wall was broken from.
+    pub coord: Coord,
+    /// The side of coord the wall was on.
+    pub dir: u32,
+    /// The Square on the other side of the wall, or None for the outer wall.
+    pub other: Option<Coord>,
+}
+
+/// A wall that was put up by build_wall_pair_checked().
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WallBuilt {
+    /// The Square the wall was built from.
+    pub coord: Coord,
+    /// The side of coord the wall is on.
+    pub dir: u32,
+    /// The Square on the other side of the wall.
+    pub other: Coord,
+}
+
+impl Maze {
+    /// Breaks the wall on side dir of (x, y), opening it from both sides.
+    /// Uncarved Squares it opens into become part of the maze path, and any
+    /// one-way passage through it becomes two-way.
+    ///
+    /// The outer wall is only broken when as_entrance is set, in which case
+    /// the hole becomes the Maze's entrance and the old entrance is walled
+    /// up.  Fails without changing anything if the wall is already open or
+    /// can't be broken.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// let change = maze.break_wall_pair_checked(1, 1, DIR_EAST, false).unwrap();
+    /// assert_eq!(Some(Coord::new(2, 1)), change.other);
+    /// assert!(maze.walls_consistent());
+    /// ```
+    pub fn break_wall_pair_checked(&mut self, x: u32, y: u32, dir: u32, as_entrance: bool)
+                                   -> Result<WallBroken, MazeError> {
+        if !self.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        let back = match Direction::from_u32(dir) {
+            Some(side) => u32::from(side.opposite()),
+            None => return Err(MazeError::InvalidArgument(format!("{} isn't a direction", dir))),
+        };
+        let offset = self.get_offset(x, y);
+        if !self.sq[offset].is_wall_present(dir) {
+            return Err(MazeError::InvalidArgument(format!("there's no {} wall at ({}, {}) to break",
+                                                          crate::trace::dir_name(dir), x, y)));
+        }
+
+        let other = self.neighbor(x, y, dir);
+        match other {
+            Some(n) => {
+                let there = self.get_offset(n.x, n.y);
+                self.sq[there].break_wall(back);
+                self.sq[there].set_one_way(back, false);
+                if self.sq[there].id == constants::ID_UNCARVED {
+                    self.sq[there].id = constants::ID_MAZE_PATH;
+                }
+            }
+            None if as_entrance => {
+                if let Some(old) = self.entrance.take() {
+                    let old_offset = self.get_offset(old.coord.x, old.coord.y);
+                    if !self.exits.contains(&old) {
+                        self.sq[old_offset].build_wall(old.dir);
+                    }
+                }
+                self.entrance = Some(Opening { coord: Coord::new(x, y), dir });
+            }
+            None => {
+                return Err(MazeError::InvalidArgument(
+                    "the outer wall can only be broken to make an entrance".to_string()));
+            }
+        }
+        self.sq[offset].break_wall(dir);
+        self.sq[offset].set_one_way(dir, false);
+        if self.sq[offset].id == constants::ID_UNCARVED {
+            self.sq[offset].id = constants::ID_MAZE_PATH;
+        }
+        self.mark_changed();
+        Ok(WallBroken { coord: Coord::new(x, y), dir, other })
+    }
+
+    /// Builds the wall on side dir of (x, y), closing the passage from both
+    /// sides.  Any one-way or secret marking on the passage goes with it.
+    ///
+    /// Only walls between two Squares can be built; the outer wall is left
+    /// to set_entrance() and set_exit().  Fails without changing anything if
+    /// the wall is already there.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// maze.break_wall_pair_checked(1, 1, DIR_EAST, false).unwrap();
+    /// let change = maze.build_wall_pair_checked(2, 1, DIR_WEST).unwrap();
+    /// assert_eq!(Coord::new(1, 1), change.other);
+    /// assert!(maze.sq[4].is_wall_present(DIR_EAST));
+    /// assert!(maze.build_wall_pair_checked(1, 1, DIR_EAST).is_err());
+    /// ```
+    pub fn build_wall_pair_checked(&mut self, x: u32, y: u32, dir: u32) -> Result<WallBuilt, MazeError> {
+        if !self.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        let back = match Direction::from_u32(dir) {
+            Some(side) => u32::from(side.opposite()),
+            None => return Err(MazeError::InvalidArgument(format!("{} isn't a direction", dir))),
+        };
+        let other = match self.neighbor(x, y, dir) {
+            Some(n) => n,
+            None => return Err(MazeError::InvalidArgument("the outer wall can't be built this way".to_string())),
+        };
+        let (here, there) = (self.get_offset(x, y), self.get_offset(other.x, other.y));
+        if self.sq[here].is_wall_present(dir) && self.sq[there].is_wall_present(back) {
+            return Err(MazeError::InvalidArgument(format!("there's already a {} wall at ({}, {})",
+                                                          crate::trace::dir_name(dir), x, y)));
+        }
+        for &(offset, side) in [(here, dir), (there, back)].iter() {
+            self.sq[offset].build_wall(side);
+            self.sq[offset].set_one_way(side, false);
+            self.sq[offset].set_secret(side, false);
+        }
+        self.mark_changed();
+        Ok(WallBuilt { coord: Coord::new(x, y), dir, other })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_break_wall() {
+        let mut maze = Maze::new(6, 6);
+        maze.generate_perfect_seeded(433).unwrap();
+        // Find a wall between two Squares and break it
+        let (x, y, dir) = (0..6).flat_map(|y| (0..5).map(move |x| (x, y)))
+            .map(|(x, y)| (x, y, constants::DIR_EAST))
+            .find(|&(x, y, dir)| maze.sq[maze.get_offset(x, y)].is_wall_present(dir))
+            .unwrap();
+        let change = maze.break_wall_pair_checked(x, y, dir, false).unwrap();
+        assert_eq!(WallBroken { coord: Coord::new(x, y), dir, other: Some(Coord::new(x + 1, y)) }, change);
+        assert!(!maze.sq[maze.get_offset(x, y)].is_wall_present(constants::DIR_EAST));
+        assert!(!maze.sq[maze.get_offset(x + 1, y)].is_wall_present(constants::DIR_WEST));
+        assert!(maze.walls_consistent());
+
+        // It's open now, so there's nothing left to break
+        assert!(maze.break_wall_pair_checked(x, y, dir, false).is_err());
+        assert!(maze.break_wall_pair_checked(x + 1, y, constants::DIR_WEST, false).is_err());
+    }
+
+    #[test]
+    fn test_build_wall() {
+        let mut maze = Maze::new(4, 4);
+        maze.generate_perfect_seeded(489).unwrap();
+        let (x, y, dir) = (0..4).flat_map(|y| (0..3).map(move |x| (x, y, constants::DIR_EAST)))
+            .find(|&(x, y, dir)| !maze.sq[maze.get_offset(x, y)].is_wall_present(dir))
+            .unwrap();
+        let change = maze.build_wall_pair_checked(x, y, dir).unwrap();
+        assert_eq!(WallBuilt { coord: Coord::new(x, y), dir, other: Coord::new(x + 1, y) }, change);
+        assert!(maze.sq[maze.get_offset(x + 1, y)].is_wall_present(constants::DIR_WEST));
+        assert!(maze.walls_consistent());
+        assert!(maze.build_wall_pair_checked(x + 1, y, constants::DIR_WEST).is_err());
+        assert!(maze.build_wall_pair_checked(3, 0, constants::DIR_EAST).is_err());
+
+        // Breaking it again puts the passage back
+        maze.break_wall_pair_checked(x, y, dir, false).unwrap();
+        assert!(maze.build_wall_pair_checked(x + 1, y, constants::DIR_WEST).is_ok());
+    }
+
+    #[test]
+    fn test_solid_rock_and_one_way() {
+        let mut maze = Maze::new(3, 3);
+        maze.break_wall_pair_checked(0, 0, constants::DIR_SOUTH, false).unwrap();
+        assert_eq!(constants::ID_MAZE_PATH, maze.sq[maze.get_offset(0, 1)].id);
+
+        maze.carve_one_way(1, 1, constants::DIR_EAST).unwrap();
+        maze.break_wall_pair_checked(2, 1, constants::DIR_WEST, false).unwrap();
+        assert!(!maze.sq[maze.get_offset(1, 1)].is_one_way(constants::DIR_EAST));
+        assert_eq!(vec![Coord::new(1, 1)], maze.open_neighbors(2, 1));
+        assert!(maze.walls_consistent());
+    }
+
+    #[test]
+    fn test_border() {
+        let mut maze = Maze::new(4, 4);
+        maze.generate_perfect_seeded(1).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        let before = maze.clone();
+        assert!(maze.break_wall_pair_checked(3, 3, constants::DIR_EAST, false).is_err());
+        assert!(maze.break_wall_pair_checked(4, 0, constants::DIR_WEST, false).is_err());
+        assert!(maze.break_wall_pair_checked(0, 0, 9, true).is_err());
+        assert_eq!(before, maze);
+
+        let change = maze.break_wall_pair_checked(3, 3, constants::DIR_EAST, true).unwrap();
+        assert_eq!(None, change.other);
+        assert_eq!(Some(Opening { coord: Coord::new(3, 3), dir: constants::DIR_EAST }), maze.get_entrance());
+        assert!(maze.sq[0].is_wall_present(constants::DIR_NORTH));
+        assert!(maze.walls_consistent());
+    }
+}
@@ -0,0 +1,464 @@
+//! Undoable edits, for level editors.  Changes made through an
+//! EditTransaction remember what the Squares they touched looked like
+//! before, so the whole batch can be rolled back, or committed and undone
+//! later from an UndoStack.  Changing the Maze directly still works, but
+//! can't be undone.
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::dig::WallBroken;
+use crate::maze::prefab::StampMode;
+use crate::maze::{Coord, Maze, Opening, Square};
+
+/// What a batch of edits changed, kept so they can be undone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Edit {
+    // The Squares the edits touched, as they were before, by offset
+    squares: BTreeMap<usize, Square>,
+    // The entrance and exits from before, if any edit moved them
+    openings: Option<(Option<Opening>, Vec<Opening>)>,
+}
+
+impl Edit {
+    /// Returns whether the edit touched nothing.
+    pub fn is_empty(&self) -> bool {
+        self.squares.is_empty() && self.openings.is_none()
+    }
+
+    /// Puts everything the edit touched back the way it was.  Later edits
+    /// to the same Squares are undone too, so edits should be undone
+    /// newest first.
+    pub fn undo(self, maze: &mut Maze) {
+        for (offset, sq) in self.squares {
+            if let Some(slot) = maze.sq.get_mut(offset) {
+                *slot = sq;
+            }
+        }
+        if let Some((entrance, exits)) = self.openings {
+            maze.entrance = entrance;
+            maze.exits = exits;
+        }
+        maze.mark_changed();
+    }
+}
+
+/// One change an editor can make, written down before it's made so it can
+/// be previewed with Maze::preview_edit() and then carried out with
+/// EditTransaction::apply().
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditAction {
+    /// Break the wall on side dir of a Square, like
+    /// EditTransaction::break_wall().
+    OpenWall { at: Coord, dir: u32 },
+    /// Build the wall on side dir of a Square, like
+    /// EditTransaction::build_wall().
+    CloseWall { at: Coord, dir: u32 },
+    /// Carve a room of w by h Squares with its top left corner at `at`,
+    /// marking its Squares with id.
+    Room { at: Coord, w: u32, h: u32, id: i32 },
+}
+
+/// A batch of edits in progress.  Dropping a transaction without
+/// committing it rolls it back.
+#[derive(Debug)]
+pub struct EditTransaction<'a> {
+    maze: &'a mut Maze,
+    edit: Edit,
+    finished: bool,
+}
+
+impl Maze {
+    /// Starts a batch of undoable edits.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(4, 4);
+    /// let before = maze.clone();
+    /// let mut edit = maze.begin_edit();
+    /// edit.carve(0, 0, DIR_EAST, ID_MAZE_PATH).unwrap();
+    /// edit.rollback();
+    /// assert_eq!(before, maze);
+    /// ```
+    pub fn begin_edit(&mut self) -> EditTransaction<'_> {
+        EditTransaction { maze: self, edit: Edit::default(), finished: false }
+    }
+}
+
+impl<'a> EditTransaction<'a> {
+    /// Returns the Maze being edited.
+    pub fn maze(&self) -> &Maze {
+        self.maze
+    }
+
+    /// Carves from (x, y) toward dir like Maze::carve(), marking both
+    /// Squares with id.
+    pub fn carve(&mut self, x: u32, y: u32, dir: u32, id: i32) -> Result<(), MazeError> {
+        self.save_pair(x, y, dir);
+        self.maze.carve(x, y, dir, id, false).map_err(MazeError::InvalidArgument)
+    }
+
+    /// Builds the wall on side dir of (x, y), from both sides.  Walls on the
+    /// outside of the Maze can be built too, which removes an entrance or
+    /// exit through them.
+    pub fn build_wall(&mut self, x: u32, y: u32, dir: u32) -> Result<(), MazeError> {
+        if !self.maze.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        let back = match Direction::from_u32(dir) {
+            Some(side) => u32::from(side.opposite()),
+            None => return Err(MazeError::InvalidArgument(format!("{} isn't a direction", dir))),
+        };
+        self.save_pair(x, y, dir);
+        let offset = self.maze.get_offset(x, y);
+        self.maze.sq[offset].build_wall(dir);
+        self.maze.sq[offset].set_one_way(dir, false);
+        match self.maze.neighbor(x, y, dir) {
+            Some(n) => {
+                let there = self.maze.get_offset(n.x, n.y);
+                self.maze.sq[there].build_wall(back);
+                self.maze.sq[there].set_one_way(back, false);
+            }
+            None => {
+                // The opening through the wall goes with it
+                let closed = Opening { coord: Coord::new(x, y), dir };
+                if self.maze.entrance == Some(closed) || self.maze.exits.contains(&closed) {
+                    self.save_openings(x, y);
+                    if self.maze.entrance == Some(closed) {
+                        self.maze.entrance = None;
+                    }
+                    self.maze.exits.retain(|&o| o != closed);
+                }
+            }
+        }
+        self.maze.mark_changed();
+        Ok(())
+    }
+
+    /// Breaks the wall on side dir of (x, y) from both sides like
+    /// Maze::break_wall_pair_checked().  The outer wall can't be broken;
+    /// use set_entrance() or add_exit() for that.
+    pub fn break_wall(&mut self, x: u32, y: u32, dir: u32) -> Result<WallBroken, MazeError> {
+        self.save_pair(x, y, dir);
+        self.maze.break_wall_pair_checked(x, y, dir, false)
+    }
+
+    /// Stamps prefab into the Maze like Maze::stamp().
+    pub fn stamp(&mut self, prefab: &Maze, at: Coord, mode: StampMode) -> Result<(), MazeError> {
+        // Stamping changes the prefab's Squares and the ones around them
+        let x_end = at.x.saturating_add(prefab.cols).min(self.maze.cols);
+        let y_end = at.y.saturating_add(prefab.rows).min(self.maze.rows);
+        for y in at.y..y_end {
+            for x in at.x..x_end {
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    self.save_pair(x, y, dir);
+                }
+            }
+        }
+        self.maze.stamp(prefab, at, mode)
+    }
+
+    /// Moves the entrance like Maze::set_entrance().
+    pub fn set_entrance(&mut self, x: u32, y: u32, dir: u32) -> Result<(), MazeError> {
+        self.save_openings(x, y);
+        self.maze.set_entrance(x, y, dir)
+    }
+
+    /// Moves the exit like Maze::set_exit().
+    pub fn set_exit(&mut self, x: u32, y: u32, dir: u32) -> Result<(), MazeError> {
+        self.save_openings(x, y);
+        self.maze.set_exit(x, y, dir)
+    }
+
+    /// Adds another exit like Maze::add_exit().
+    pub fn add_exit(&mut self, x: u32, y: u32, dir: u32) -> Result<(), MazeError> {
+        self.save_openings(x, y);
+        self.maze.add_exit(x, y, dir)
+    }
+
+    /// Makes the change action describes.  A room has to fit inside the
+    /// Maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::edit::EditAction;
+    ///
+    /// let mut maze = Maze::new(4, 4);
+    /// let mut edit = maze.begin_edit();
+    /// edit.apply(&EditAction::Room { at: Coord::new(1, 1), w: 2, h: 2, id: 1 }).unwrap();
+    /// assert!(edit.apply(&EditAction::Room { at: Coord::new(3, 3), w: 2, h: 2, id: 2 }).is_err());
+    /// assert_eq!(2, edit.maze().open_neighbors(1, 1).len());
+    /// ```
+    pub fn apply(&mut self, action: &EditAction) -> Result<(), MazeError> {
+        match *action {
+            EditAction::OpenWall { at, dir } => self.break_wall(at.x, at.y, dir).map(|_| ()),
+            EditAction::CloseWall { at, dir } => self.build_wall(at.x, at.y, dir),
+            EditAction::Room { at, w, h, id } => {
+                let (right, bottom) = (at.x.saturating_add(w), at.y.saturating_add(h));
+                if w == 0 || h == 0 || right > self.maze.cols || bottom > self.maze.rows {
+                    return Err(MazeError::OutOfBounds(right.saturating_sub(1), bottom.saturating_sub(1)));
+                }
+                for y in at.y..bottom {
+                    for x in at.x..right {
+                        if x + 1 < right {
+                            self.carve(x, y, constants::DIR_EAST, id)?;
+                        }
+                        if y + 1 < bottom {
+                            self.carve(x, y, constants::DIR_SOUTH, id)?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Keeps the edits, returning what's needed to undo them later.
+    pub fn commit(mut self) -> Edit {
+        self.finished = true;
+        core::mem::take(&mut self.edit)
+    }
+
+    /// Undoes every edit made through the transaction.
+    pub fn rollback(mut self) {
+        self.undo_all();
+    }
+
+    //
+    // Internal - puts the Maze back the way it was before the transaction.
+    //
+    fn undo_all(&mut self) {
+        self.finished = true;
+        let edit = core::mem::take(&mut self.edit);
+        if !edit.is_empty() {
+            edit.undo(self.maze);
+        }
+    }
+
+    //
+    // Internal - remembers the Square at (x, y) and the one on side dir of
+    // it, unless they've already been saved.
+    //
+    fn save_pair(&mut self, x: u32, y: u32, dir: u32) {
+        if !self.maze.in_bounds(x, y) {
+            return;
+        }
+        let mut coords = vec![Coord::new(x, y)];
+        if dir < constants::NUM_DIRECTIONS {
+            coords.extend(self.maze.neighbor(x, y, dir));
+        }
+        for c in coords {
+            let offset = self.maze.get_offset(c.x, c.y);
+            let sq = &self.maze.sq[offset];
+            self.edit.squares.entry(offset).or_insert_with(|| sq.clone());
+        }
+    }
+
+    //
+    // Internal - remembers the entrance and exits, and the Square an
+    // opening is about to be made in.
+    //
+    fn save_openings(&mut self, x: u32, y: u32) {
+        if self.edit.openings.is_none() {
+            self.edit.openings = Some((self.maze.entrance, self.maze.exits.clone()));
+        }
+        self.save_pair(x, y, constants::NUM_DIRECTIONS);
+    }
+}
+
+impl<'a> Drop for EditTransaction<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.undo_all();
+        }
+    }
+}
+
+/// Committed edits, newest last, ready to be undone.  Only the newest
+/// `limit` edits are kept.
+#[derive(Clone, Debug)]
+pub struct UndoStack {
+    edits: VecDeque<Edit>,
+    limit: usize,
+}
+
+impl UndoStack {
+    /// Creates an empty stack that keeps at most limit edits.
+    pub fn new(limit: usize) -> UndoStack {
+        UndoStack { edits: VecDeque::new(), limit }
+    }
+
+    /// Adds a committed edit, forgetting the oldest one if the stack is
+    /// full.  Edits that changed nothing aren't kept.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::edit::UndoStack;
+    ///
+    /// let mut maze = Maze::new(4, 4);
+    /// let before = maze.clone();
+    /// let mut undo = UndoStack::new(10);
+    /// let mut edit = maze.begin_edit();
+    /// edit.carve(1, 1, DIR_SOUTH, ID_MAZE_PATH).unwrap();
+    /// undo.push(edit.commit());
+    ///
+    /// assert!(undo.undo(&mut maze));
+    /// assert_eq!(before, maze);
+    /// assert!(!undo.undo(&mut maze));
+    /// ```
+    pub fn push(&mut self, edit: Edit) {
+        if edit.is_empty() || self.limit == 0 {
+            return;
+        }
+        if self.edits.len() == self.limit {
+            self.edits.pop_front();
+        }
+        self.edits.push_back(edit);
+    }
+
+    /// Undoes the newest edit.  Returns false if there was nothing to undo.
+    pub fn undo(&mut self, maze: &mut Maze) -> bool {
+        match self.edits.pop_back() {
+            Some(edit) => {
+                edit.undo(maze);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the number of edits that can be undone.
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Returns whether there's nothing to undo.
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Forgets every edit.
+    pub fn clear(&mut self) {
+        self.edits.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH, DIR_WEST, ID_MAZE_PATH};
+
+    // A mix of every kind of edit, some of which fail part way
+    fn edit_batch(edit: &mut EditTransaction) {
+        let room = Maze::from_compact("6a\n59").unwrap();
+        edit.carve(0, 0, DIR_EAST, 3).unwrap();
+        edit.build_wall(2, 2, DIR_SOUTH).unwrap();
+        edit.build_wall(0, 4, DIR_WEST).unwrap();
+        edit.stamp(&room, Coord::new(4, 4), StampMode::Overwrite).unwrap();
+        edit.stamp(&room, Coord::new(1, 5), StampMode::Merge).unwrap();
+        assert!(edit.stamp(&room, Coord::new(7, 6), StampMode::Overwrite).is_err());
+        assert!(edit.carve(7, 0, DIR_NORTH, ID_MAZE_PATH).is_err());
+        edit.set_entrance(3, 7, DIR_SOUTH).unwrap();
+        edit.set_exit(7, 3, DIR_EAST).unwrap();
+        edit.carve(5, 5, DIR_WEST, ID_MAZE_PATH).unwrap();
+        // Open or closed, the wall ends up the other way
+        if edit.break_wall(6, 1, DIR_SOUTH).is_err() {
+            edit.build_wall(6, 1, DIR_SOUTH).unwrap();
+        }
+        assert!(edit.break_wall(0, 0, DIR_WEST).is_err());
+    }
+
+    fn generated() -> Maze {
+        let mut maze = Maze::new(8, 8);
+        maze.generate_seeded((2, 2, 3, 2, 3), 437).unwrap();
+        maze.set_entrance(0, 0, DIR_NORTH).unwrap();
+        maze.set_exit(7, 7, DIR_SOUTH).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut maze = generated();
+        let before = maze.clone();
+        let mut edit = maze.begin_edit();
+        edit_batch(&mut edit);
+        assert_ne!(before.to_bytes(), edit.maze().to_bytes());
+        edit.rollback();
+        assert_eq!(before.to_bytes(), maze.to_bytes());
+
+        // Dropping an unfinished transaction does the same
+        {
+            let mut edit = maze.begin_edit();
+            edit_batch(&mut edit);
+        }
+        assert_eq!(before.to_bytes(), maze.to_bytes());
+    }
+
+    #[test]
+    fn test_undo_stack() {
+        let mut maze = generated();
+        let before = maze.clone();
+        let mut undo = UndoStack::new(2);
+
+        let mut edit = maze.begin_edit();
+        edit_batch(&mut edit);
+        undo.push(edit.commit());
+        let middle = maze.clone();
+        assert_ne!(before.to_bytes(), middle.to_bytes());
+
+        let mut edit = maze.begin_edit();
+        edit.carve(6, 0, DIR_SOUTH, 4).unwrap();
+        edit.build_wall(6, 0, DIR_SOUTH).unwrap();
+        undo.push(edit.commit());
+        undo.push(maze.begin_edit().commit());
+        assert_eq!(2, undo.len());
+
+        assert!(undo.undo(&mut maze));
+        assert_eq!(middle.to_bytes(), maze.to_bytes());
+        assert!(undo.undo(&mut maze));
+        assert_eq!(before.to_bytes(), maze.to_bytes());
+        assert!(!undo.undo(&mut maze));
+        assert!(undo.is_empty());
+    }
+
+    #[test]
+    fn test_wall_over_opening() {
+        let mut maze = generated();
+        let before = maze.clone();
+        let mut edit = maze.begin_edit();
+        edit.build_wall(0, 0, DIR_NORTH).unwrap();
+        edit.build_wall(7, 7, DIR_SOUTH).unwrap();
+        assert_eq!(None, edit.maze().get_entrance());
+        assert_eq!(None, edit.maze().get_exit());
+        assert_eq!(Ok(()), edit.maze().validate());
+        edit.commit().undo(&mut maze);
+        assert_eq!(before.to_bytes(), maze.to_bytes());
+    }
+
+    #[test]
+    fn test_stack_limit() {
+        let mut maze = Maze::new(4, 4);
+        let mut undo = UndoStack::new(2);
+        for x in 0..3 {
+            let mut edit = maze.begin_edit();
+            edit.carve(x, 0, DIR_EAST, ID_MAZE_PATH).unwrap();
+            undo.push(edit.commit());
+        }
+        assert_eq!(2, undo.len());
+        while undo.undo(&mut maze) {}
+        // The first carve fell off the bottom of the stack
+        assert_eq!(vec![Coord::new(1, 0)], maze.open_neighbors(0, 0));
+        assert_eq!(Vec::<Coord>::new(), maze.open_neighbors(2, 0));
+
+        let mut none = UndoStack::new(0);
+        let mut edit = maze.begin_edit();
+        edit.carve(0, 1, DIR_EAST, ID_MAZE_PATH).unwrap();
+        none.push(edit.commit());
+        assert!(none.is_empty());
+    }
+}
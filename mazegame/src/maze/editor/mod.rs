@@ -0,0 +1,721 @@
+//! A maze editor that runs in the terminal.  The arrow keys move a cursor,
+//! shift and an arrow opens or closes the wall on that side of the cursor,
+//! `r` carves a room at the cursor, `s` saves, `u` undoes and `q` quits.
+//! `p` turns on preview mode, where walls and rooms aren't changed straight
+//! away: the wall to change is drawn as `?`, Squares the edit would make
+//! reachable as `+` and ones it would cut off as `-`, and enter makes it.
+//!
+//! Everything but the terminal itself is kept apart so it can be tested:
+//! parse_keys() turns the bytes a terminal sends into EditorCommands,
+//! Editor::apply() carries a command out on a Maze, and Editor::render()
+//! draws the screen, or render_for() draws it to fit a terminal.
+//! edit_terminal() just feeds key presses through those and shows the
+//! result.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::maze::edit::{EditAction, UndoStack};
+#[cfg(all(feature = "std", unix))]
+use crate::maze::fit::SttySize;
+use crate::maze::fit::{fit_text, FitMode, TerminalSize};
+use crate::maze::preview::EditPreview;
+use crate::maze::{Coord, Maze};
+
+/// How many Squares across and down a room made with `r` is.
+pub const ROOM_SIZE: u32 = 3;
+
+/// How many edits the editor can undo.
+pub const UNDO_LIMIT: usize = 100;
+
+// Stands in for the cursor's Square while the screen is fitted, so it can
+// be found again afterwards
+const CURSOR_MARK: char = '\u{0}';
+
+// How a previewed edit is drawn: the walls it changes, and the Squares it
+// would make reachable or cut off
+const GHOST_WALL: char = '?';
+const NEWLY_REACHABLE: char = '+';
+const NEWLY_UNREACHABLE: char = '-';
+
+/// Something the user asked the editor to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorCommand {
+    /// Move the cursor one Square.
+    Move(Direction),
+    /// Open the wall on this side of the cursor if it's closed, or close it
+    /// if it's open.
+    ToggleWall(Direction),
+    /// Carve a room of ROOM_SIZE by ROOM_SIZE Squares with the cursor at its
+    /// top left, moved in as far as it takes to fit.
+    StampRoom,
+    /// Turn preview mode on or off.  In preview mode ToggleWall and
+    /// StampRoom only show what they would do.
+    TogglePreview,
+    /// Make the edit being previewed.
+    Confirm,
+    Save,
+    Undo,
+    Quit,
+}
+
+/// What the caller of Editor::apply() needs to do next.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditorOutcome {
+    /// Nothing; carry on.
+    Continue,
+    /// Save this text, the Maze in the text format.
+    Save(String),
+    /// Stop editing.
+    Quit,
+}
+
+/// Reads the key presses in input, as a terminal in raw mode sends them.
+/// Returns the commands along with how many bytes were used.  An escape
+/// sequence cut off at the end of input isn't used, so it can be finished
+/// by the next read.  Bytes that aren't editor keys are skipped.
+///
+/// # Example
+/// ```
+/// use mazegame::constants::Direction;
+/// use mazegame::maze::editor::{parse_keys, EditorCommand};
+///
+/// let (commands, used) = parse_keys(b"\x1b[C\x1b[1;2Bu\x1b[");
+/// assert_eq!(vec![EditorCommand::Move(Direction::East), EditorCommand::ToggleWall(Direction::South),
+///                 EditorCommand::Undo], commands);
+/// assert_eq!(10, used);
+/// ```
+pub fn parse_keys(input: &[u8]) -> (Vec<EditorCommand>, usize) {
+    let arrow = |b: u8| match b {
+        b'A' => Some(Direction::North),
+        b'B' => Some(Direction::South),
+        b'C' => Some(Direction::East),
+        b'D' => Some(Direction::West),
+        _ => None,
+    };
+
+    let mut commands = Vec::new();
+    let mut i = 0;
+    while i < input.len() {
+        let rest = &input[i..];
+        if rest[0] == 0x1b {
+            // Arrows are ESC [ A (or ESC O A), and shifted ones ESC [ 1 ; 2 A
+            match rest.get(1) {
+                None => break,
+                Some(&b'[') | Some(&b'O') => {}
+                Some(_) => {
+                    i += 1;
+                    continue;
+                }
+            }
+            let key = match rest.get(2) {
+                Some(&key) => key,
+                None => break,
+            };
+            if let Some(dir) = arrow(key) {
+                commands.push(EditorCommand::Move(dir));
+                i += 3;
+            } else if key == b'1' {
+                if rest.len() < 6 {
+                    break;
+                }
+                if &rest[3..5] == b";2" {
+                    commands.extend(arrow(rest[5]).map(EditorCommand::ToggleWall));
+                }
+                i += 6;
+            } else {
+                i += 2;
+            }
+            continue;
+        }
+
+        match rest[0] {
+            b'r' => commands.push(EditorCommand::StampRoom),
+            b'p' => commands.push(EditorCommand::TogglePreview),
+            b'\r' | b'\n' => commands.push(EditorCommand::Confirm),
+            b's' => commands.push(EditorCommand::Save),
+            b'u' => commands.push(EditorCommand::Undo),
+            // Ctrl-C, since raw mode keeps it from stopping the program
+            b'q' | 0x03 => commands.push(EditorCommand::Quit),
+            _ => {}
+        }
+        i += 1;
+    }
+    (commands, i)
+}
+
+/// The editor's state between commands: where the cursor is, what can be
+/// undone, the edit being previewed, and the message on the status line.
+#[derive(Clone, Debug)]
+pub struct Editor {
+    cursor: Coord,
+    undo: UndoStack,
+    message: String,
+    previewing: bool,
+    pending: Option<(EditAction, EditPreview)>,
+}
+
+impl Default for Editor {
+    fn default() -> Editor {
+        Editor::new()
+    }
+}
+
+impl Editor {
+    /// Creates an editor with the cursor in the top left corner.
+    pub fn new() -> Editor {
+        Editor {
+            cursor: Coord::new(0, 0),
+            undo: UndoStack::new(UNDO_LIMIT),
+            message: String::new(),
+            previewing: false,
+            pending: None,
+        }
+    }
+
+    /// Returns the Square the cursor is on.
+    pub fn cursor(&self) -> Coord {
+        self.cursor
+    }
+
+    /// Returns the message shown after the cursor's details on the status
+    /// line, like what the last command did.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns whether edits are previewed before they're made.
+    pub fn previewing(&self) -> bool {
+        self.previewing
+    }
+
+    /// Returns the edit waiting for Confirm and what it would do, if
+    /// there is one.
+    pub fn pending(&self) -> Option<&(EditAction, EditPreview)> {
+        self.pending.as_ref()
+    }
+
+    /// Replaces the status line message.
+    pub fn set_message<S: Into<String>>(&mut self, message: S) {
+        self.message = message.into();
+    }
+
+    /// Carries out command on maze.  Every change is made through an
+    /// EditTransaction, so it can be undone; one that fails is rolled back
+    /// and the reason goes on the status line.  In preview mode, walls and
+    /// rooms wait for Confirm, and any other change to the Maze or the
+    /// cursor forgets them.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::editor::{Editor, EditorCommand};
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// let mut editor = Editor::new();
+    /// editor.apply(&mut maze, EditorCommand::ToggleWall(Direction::East));
+    /// assert_eq!(vec![Coord::new(1, 0)], maze.open_neighbors(0, 0));
+    /// editor.apply(&mut maze, EditorCommand::Undo);
+    /// assert_eq!(Maze::new(3, 3), maze);
+    /// ```
+    pub fn apply(&mut self, maze: &mut Maze, command: EditorCommand) -> EditorOutcome {
+        // The Maze may have shrunk since the last command
+        if !maze.in_bounds(self.cursor.x, self.cursor.y) {
+            self.cursor = Coord::new(0, 0);
+        }
+        let Coord { x, y } = self.cursor;
+        // Anything but saving forgets the edit being previewed
+        let pending = self.pending.take();
+        match command {
+            EditorCommand::Move(dir) => match maze.neighbor(x, y, u32::from(dir)) {
+                Some(next) => {
+                    self.cursor = next;
+                    self.message.clear();
+                }
+                None => self.message = format!("can't move {} off the maze", dir_word(dir)),
+            },
+            EditorCommand::ToggleWall(dir) => {
+                let (at, dir) = (self.cursor, u32::from(dir));
+                let action = if maze.sq[maze.get_offset(x, y)].is_wall_present(dir) {
+                    EditAction::OpenWall { at, dir }
+                } else {
+                    EditAction::CloseWall { at, dir }
+                };
+                self.edit(maze, action);
+            }
+            EditorCommand::StampRoom => match self.room_action(maze) {
+                Some(action) => self.edit(maze, action),
+                None => self.message = format!("the maze is too small for a {}x{} room", ROOM_SIZE, ROOM_SIZE),
+            },
+            EditorCommand::TogglePreview => {
+                self.previewing = !self.previewing;
+                self.message = if self.previewing { "preview on" } else { "preview off" }.to_string();
+            }
+            EditorCommand::Confirm => match pending {
+                Some((action, _)) => self.make(maze, action),
+                None => self.message = "nothing to confirm".to_string(),
+            },
+            EditorCommand::Save => {
+                self.pending = pending;
+                self.message = "saved".to_string();
+                return EditorOutcome::Save(maze.to_text());
+            }
+            EditorCommand::Undo => {
+                self.message = if self.undo.undo(maze) { "undone" } else { "nothing to undo" }.to_string();
+            }
+            EditorCommand::Quit => return EditorOutcome::Quit,
+        }
+        EditorOutcome::Continue
+    }
+
+    /// Draws the Maze as ASCII, with the cursor's Square in reverse video,
+    /// followed by a status line with the cursor's position and Square.
+    pub fn render(&self, maze: &Maze) -> String {
+        self.draw(maze, None)
+    }
+
+    /// Draws the screen like render(), fitted to the size the terminal
+    /// reports.  A Maze too big for it is shown through a window that
+    /// follows the cursor, with the status line on the bottom line.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::editor::Editor;
+    /// use mazegame::maze::fit::FixedSize;
+    ///
+    /// let maze = Maze::new(30, 50);
+    /// let screen = Editor::new().render_for(&maze, &FixedSize(80, 24));
+    /// assert_eq!(24, screen.lines().count());
+    /// ```
+    pub fn render_for(&self, maze: &Maze, size: &dyn TerminalSize) -> String {
+        self.draw(maze, size.terminal_size())
+    }
+
+    //
+    // Internal - draws the screen, fitted to size if there is one.
+    //
+    fn draw(&self, maze: &Maze, size: Option<(u32, u32)>) -> String {
+        let (cx, cy) = (self.cursor.x as usize * 2 + 1, self.cursor.y as usize * 2 + 1);
+        let mut grid: Vec<Vec<char>> = maze.render_ascii().lines().map(|l| l.chars().collect()).collect();
+        if let Some((action, ref preview)) = self.pending {
+            let mut marks: Vec<((usize, usize), char)> =
+                ghost_walls(&action).into_iter().map(|at| (at, GHOST_WALL)).collect();
+            let square = |c: &Coord| (c.x as usize * 2 + 1, c.y as usize * 2 + 1);
+            marks.extend(preview.newly_reachable.iter().map(|c| (square(c), NEWLY_REACHABLE)));
+            marks.extend(preview.newly_unreachable.iter().map(|c| (square(c), NEWLY_UNREACHABLE)));
+            for ((x, y), mark) in marks {
+                if let Some(slot) = grid.get_mut(y).and_then(|row| row.get_mut(x)) {
+                    *slot = mark;
+                }
+            }
+        }
+        let under = grid.get(cy).and_then(|row| row.get(cx)).cloned();
+        if under.is_some() {
+            grid[cy][cx] = CURSOR_MARK;
+        }
+        let mut drawing = String::new();
+        for row in grid.iter() {
+            drawing.extend(row.iter());
+            drawing.push('\n');
+        }
+        let mut status = self.status_line(maze);
+        if let Some((width, height)) = size {
+            let height = height.saturating_sub(1);
+            let text_size = (grid.first().map_or(0, |row| row.len()) as u32, grid.len() as u32);
+            let mode = FitMode::pick(text_size, (width, height), Some(self.cursor));
+            drawing = fit_text(&drawing, width, height, mode);
+            status = status.chars().take(width as usize).collect();
+        }
+        let mut out = match under {
+            Some(c) => drawing.replace(CURSOR_MARK, &format!("\x1b[7m{}\x1b[0m", c)),
+            None => drawing,
+        };
+        out.push_str(&status);
+        out.push('\n');
+        out
+    }
+
+    /// Returns the status line: the cursor's position, its Square's id (and
+    /// room, if it's in one), the sides it's open on, and the message.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::editor::Editor;
+    ///
+    /// let maze = Maze::from_compact("4c8").unwrap();
+    /// assert_eq!("(0, 0) id -1 open e", Editor::new().status_line(&maze));
+    /// ```
+    pub fn status_line(&self, maze: &Maze) -> String {
+        let Coord { x, y } = self.cursor;
+        let mut line = format!("({}, {})", x, y);
+        if let Some(sq) = maze.index_of(x, y).map(|i| &maze.sq[i]) {
+            line.push_str(&format!(" id {}", sq.id));
+            if sq.is_part_of_room() {
+                line.push_str(&format!(" room {}", sq.id));
+            }
+            let open: Vec<&str> = (0..constants::NUM_DIRECTIONS)
+                .filter(|&dir| !sq.is_wall_present(dir))
+                .map(crate::trace::dir_name)
+                .collect();
+            if !open.is_empty() {
+                line.push_str(" open ");
+                line.push_str(&open.join(""));
+            }
+        }
+        if !self.message.is_empty() {
+            line.push_str(" - ");
+            line.push_str(&self.message);
+        }
+        line
+    }
+
+    //
+    // Internal - a room at the cursor, moved up and left if it would hang
+    // off the Maze, with the next room id.  None if the Maze is too small.
+    //
+    fn room_action(&self, maze: &Maze) -> Option<EditAction> {
+        if maze.cols < ROOM_SIZE || maze.rows < ROOM_SIZE {
+            return None;
+        }
+        let at = Coord::new(self.cursor.x.min(maze.cols - ROOM_SIZE), self.cursor.y.min(maze.rows - ROOM_SIZE));
+        Some(EditAction::Room { at, w: ROOM_SIZE, h: ROOM_SIZE, id: maze.num_rooms as i32 + 1 })
+    }
+
+    //
+    // Internal - makes action, or in preview mode works out what it would
+    // do and keeps it for Confirm.
+    //
+    fn edit(&mut self, maze: &mut Maze, action: EditAction) {
+        if !self.previewing {
+            self.make(maze, action);
+            return;
+        }
+        match maze.preview_edit(&action) {
+            Ok(preview) => {
+                let length = |l: Option<u32>| l.map_or("none".to_string(), |l| l.to_string());
+                self.message = format!("preview: {} newly reachable, {} cut off{}, solution {} -> {}; enter applies",
+                                       preview.newly_reachable.len(), preview.newly_unreachable.len(),
+                                       if preview.creates_cycle { ", makes a loop" } else { "" },
+                                       length(preview.solution_before), length(preview.solution_after));
+                self.pending = Some((action, preview));
+            }
+            Err(e) => self.message = e.to_string(),
+        }
+    }
+
+    //
+    // Internal - makes action through a transaction, so it can be undone.
+    //
+    fn make(&mut self, maze: &mut Maze, action: EditAction) {
+        let mut edit = maze.begin_edit();
+        if let Err(e) = edit.apply(&action) {
+            self.message = e.to_string();
+            return;
+        }
+        self.undo.push(edit.commit());
+        self.message = match action {
+            EditAction::OpenWall { dir, .. } | EditAction::CloseWall { dir, .. } => {
+                let opened = matches!(action, EditAction::OpenWall { .. });
+                format!("{} the {} wall", if opened { "opened" } else { "closed" },
+                        Direction::from_u32(dir).map_or("", dir_word))
+            }
+            EditAction::Room { id, .. } => {
+                // Undoing the room leaves the count alone, so ids are never reused
+                maze.num_rooms = id as u32;
+                format!("carved room {}", id)
+            }
+        };
+    }
+}
+
+/// Edits maze in the terminal until `q` is pressed, saving to save_path in
+/// the text format when `s` is.  The terminal is put in raw mode with
+/// `stty` while the editor runs, and put back afterwards.
+#[cfg(all(feature = "std", unix))]
+pub fn edit_terminal(maze: &mut Maze, save_path: &std::path::Path) -> Result<(), crate::error::MazeError> {
+    edit_terminal_sized(maze, save_path, &SttySize)
+}
+
+/// Edits maze in the terminal like edit_terminal(), asking size how big the
+/// terminal is before each redraw.
+#[cfg(all(feature = "std", unix))]
+pub fn edit_terminal_sized(maze: &mut Maze, save_path: &std::path::Path, size: &dyn TerminalSize)
+                           -> Result<(), crate::error::MazeError> {
+    use std::fs;
+    use std::io::{self, Read, Write};
+
+    let _raw = RawMode::enable()?;
+    let mut editor = Editor::new();
+    let (mut stdin, mut stdout) = (io::stdin(), io::stdout());
+    let mut pending = Vec::new();
+    let mut buf = [0u8; 64];
+    loop {
+        // Raw mode doesn't turn \n into a new line on its own
+        write!(stdout, "\x1b[2J\x1b[H{}", editor.render_for(maze, size).replace('\n', "\r\n"))?;
+        stdout.flush()?;
+        let read = stdin.read(&mut buf)?;
+        if read == 0 {
+            return Ok(());
+        }
+        pending.extend_from_slice(&buf[..read]);
+        let (commands, used) = parse_keys(&pending);
+        pending.drain(..used);
+        for command in commands {
+            match editor.apply(maze, command) {
+                EditorOutcome::Continue => {}
+                EditorOutcome::Save(text) => match fs::write(save_path, text) {
+                    Ok(()) => editor.set_message(format!("saved to {}", save_path.display())),
+                    Err(e) => editor.set_message(format!("couldn't save: {}", e)),
+                },
+                EditorOutcome::Quit => {
+                    write!(stdout, "\x1b[2J\x1b[H")?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+//
+// Internal - raw mode for the terminal on stdin, turned off again when
+// dropped.
+//
+#[cfg(all(feature = "std", unix))]
+struct RawMode {
+    saved: String,
+}
+
+#[cfg(all(feature = "std", unix))]
+impl RawMode {
+    fn enable() -> std::io::Result<RawMode> {
+        use std::process::{Command, Stdio};
+
+        let saved = Command::new("stty").arg("-g").stdin(Stdio::inherit()).output()?;
+        if !saved.status.success() {
+            return Err(std::io::Error::other("stdin isn't a terminal"));
+        }
+        Command::new("stty").args(["raw", "-echo"]).stdin(Stdio::inherit()).status()?;
+        Ok(RawMode { saved: String::from_utf8_lossy(&saved.stdout).trim().to_string() })
+    }
+}
+
+#[cfg(all(feature = "std", unix))]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = std::process::Command::new("stty").arg(&self.saved).stdin(std::process::Stdio::inherit()).status();
+    }
+}
+
+//
+// Internal - where the walls an edit changes are in the drawing, as
+// (column, row).
+//
+fn ghost_walls(action: &EditAction) -> Vec<(usize, usize)> {
+    match *action {
+        EditAction::OpenWall { at, dir } | EditAction::CloseWall { at, dir } => {
+            let (dx, dy) = Direction::from_u32(dir).map_or((0, 0), |d| d.delta());
+            let (x, y) = (at.x as i64 * 2 + 1 + dx as i64, at.y as i64 * 2 + 1 + dy as i64);
+            vec![(x as usize, y as usize)]
+        }
+        EditAction::Room { at, w, h, .. } => {
+            let mut walls = Vec::new();
+            for y in at.y as usize..(at.y + h) as usize {
+                for x in at.x as usize..(at.x + w) as usize {
+                    if x + 1 < (at.x + w) as usize {
+                        walls.push((x * 2 + 2, y * 2 + 1));
+                    }
+                    if y + 1 < (at.y + h) as usize {
+                        walls.push((x * 2 + 1, y * 2 + 2));
+                    }
+                }
+            }
+            walls
+        }
+    }
+}
+
+//
+// Internal - a direction as it's written on the status line.
+//
+fn dir_word(dir: Direction) -> &'static str {
+    match dir {
+        Direction::North => "north",
+        Direction::South => "south",
+        Direction::East => "east",
+        Direction::West => "west",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_SOUTH};
+
+    // Runs the keys through a fresh editor, returning it and anything it
+    // asked to save
+    fn run(maze: &mut Maze, keys: &[u8]) -> (Editor, Vec<String>) {
+        let mut editor = Editor::new();
+        let mut saved = Vec::new();
+        let (commands, used) = parse_keys(keys);
+        assert_eq!(keys.len(), used);
+        for command in commands {
+            match editor.apply(maze, command) {
+                EditorOutcome::Save(text) => saved.push(text),
+                EditorOutcome::Quit => break,
+                EditorOutcome::Continue => {}
+            }
+        }
+        (editor, saved)
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        use self::EditorCommand::*;
+        let (commands, used) = parse_keys(b"\x1b[A\x1bOB\x1b[1;2Dxrsuq\x03\x1b[5~\x1bZp\r");
+        assert_eq!(vec![Move(Direction::North), Move(Direction::South), ToggleWall(Direction::West),
+                        StampRoom, Save, Undo, Quit, Quit, TogglePreview, Confirm], commands);
+        assert_eq!(26, used);
+
+        // Sequences cut off at the end are left for the next read
+        for partial in [&b"\x1b"[..], b"\x1b[", b"\x1b[1;2"].iter() {
+            assert_eq!((vec![], 0), parse_keys(partial));
+        }
+        assert_eq!((vec![Undo], 1), parse_keys(b"u\x1b[1"));
+    }
+
+    #[test]
+    fn test_carve_corridor() {
+        // Right, open east, right, open south, down: an L of three Squares
+        let mut maze = Maze::new(3, 3);
+        let (editor, _) = run(&mut maze, b"\x1b[1;2C\x1b[C\x1b[1;2C\x1b[C\x1b[1;2B\x1b[B");
+        assert_eq!(Coord::new(2, 1), editor.cursor());
+        let mut expected = Maze::new(3, 3);
+        expected.carve(0, 0, DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        expected.carve(1, 0, DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        expected.carve(2, 0, DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        assert_eq!(expected.to_compact(), maze.to_compact());
+        assert!(maze.walls_consistent());
+
+        // Toggling again closes the wall
+        let (editor, _) = run(&mut maze, b"\x1b[1;2C");
+        assert_eq!("closed the east wall", editor.message());
+        assert_eq!(vec![Coord::new(2, 0)], maze.open_neighbors(1, 0));
+    }
+
+    #[test]
+    fn test_undo_and_errors() {
+        let mut maze = Maze::new(4, 4);
+        maze.generate_perfect_seeded(459).unwrap();
+        let before = maze.clone();
+
+        // The outer wall can't be opened, and moving off the edge does nothing
+        let (editor, _) = run(&mut maze, b"\x1b[1;2A\x1b[D");
+        assert_eq!(Coord::new(0, 0), editor.cursor());
+        assert!(editor.message().contains("west"));
+        assert_eq!(before, maze);
+
+        // Several edits, then undo them all and one more
+        let (editor, _) = run(&mut maze, b"\x1b[C\x1b[B\x1b[1;2C\x1b[1;2Br\x1b[1;2Auuuuu");
+        assert_eq!("nothing to undo", editor.message());
+        assert_eq!(before.to_compact(), maze.to_compact());
+    }
+
+    #[test]
+    fn test_stamp_room_and_save() {
+        let mut maze = Maze::new(5, 5);
+        // Near the bottom right corner, so the room is moved in to fit
+        let (editor, saved) = run(&mut maze, b"\x1b[C\x1b[C\x1b[C\x1b[B\x1b[B\x1b[B\x1b[Brsq\x1b[A");
+        assert_eq!(Coord::new(3, 4), editor.cursor());
+        assert_eq!(1, maze.get_num_rooms());
+        let room = maze.room(1).unwrap();
+        assert_eq!(9, room.area());
+        assert_eq!(Coord::new(2, 2), room.cells()[0]);
+        assert_eq!(vec![maze.to_text()], saved);
+        assert_eq!(Maze::from_text(&saved[0]).unwrap().to_compact(), maze.to_compact());
+
+        // A second room gets the next id, and undo takes it away again
+        let (_, _) = run(&mut maze, b"ru");
+        assert_eq!(2, maze.get_num_rooms());
+        assert_eq!(None, maze.room(2));
+        assert_eq!(1, maze.room(1).unwrap().area() / 9);
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut maze = Maze::new(2, 2);
+        let mut editor = Editor::new();
+        let (commands, _) = parse_keys(b"p\x1b[1;2C");
+        for command in commands {
+            editor.apply(&mut maze, command);
+        }
+        assert!(editor.previewing());
+        assert_eq!(Maze::new(2, 2), maze);
+        let (action, preview) = editor.pending().cloned().unwrap();
+        assert_eq!(EditAction::OpenWall { at: Coord::new(0, 0), dir: DIR_EAST }, action);
+        assert_eq!(vec![Coord::new(1, 0)], preview.newly_reachable);
+        assert_eq!("preview: 1 newly reachable, 0 cut off, solution none -> none; enter applies", editor.message());
+        let screen = editor.render(&maze);
+        let lines: Vec<&str> = screen.lines().collect();
+        assert_eq!("X\x1b[7m \x1b[0m?+X", lines[1]);
+
+        // Saving keeps the preview, and enter makes the edit
+        editor.apply(&mut maze, EditorCommand::Save);
+        editor.apply(&mut maze, EditorCommand::Confirm);
+        assert_eq!(None, editor.pending());
+        assert_eq!("opened the east wall", editor.message());
+        assert_eq!(vec![Coord::new(1, 0)], maze.open_neighbors(0, 0));
+        editor.apply(&mut maze, EditorCommand::Confirm);
+        assert_eq!("nothing to confirm", editor.message());
+
+        // Moving forgets a previewed room
+        let before = maze.clone();
+        editor.apply(&mut maze, EditorCommand::StampRoom);
+        assert!(editor.message().contains("too small"));
+        editor.apply(&mut maze, EditorCommand::ToggleWall(Direction::South));
+        assert!(editor.pending().is_some());
+        editor.apply(&mut maze, EditorCommand::Move(Direction::East));
+        editor.apply(&mut maze, EditorCommand::Confirm);
+        assert_eq!(before, maze);
+
+        editor.apply(&mut maze, EditorCommand::TogglePreview);
+        editor.apply(&mut maze, EditorCommand::ToggleWall(Direction::South));
+        assert_eq!("opened the south wall", editor.message());
+        assert_eq!(None, editor.pending());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut maze = Maze::new(2, 2);
+        let (editor, _) = run(&mut maze, b"\x1b[1;2C\x1b[C");
+        let screen = editor.render(&maze);
+        let lines: Vec<&str> = screen.lines().collect();
+        assert_eq!("XXXXX", lines[0]);
+        assert_eq!("X  \x1b[7m \x1b[0mX", lines[1]);
+        assert_eq!("(1, 0) id -1 open w", lines[5]);
+        assert_eq!(6, lines.len());
+    }
+
+    #[test]
+    fn test_render_for_small_terminal() {
+        use crate::maze::fit::FixedSize;
+
+        // Fits: the same as render()
+        let mut maze = Maze::new(2, 2);
+        let (editor, _) = run(&mut maze, b"\x1b[C");
+        assert_eq!(editor.render(&maze), editor.render_for(&maze, &FixedSize(80, 24)));
+
+        // Too big: a window around the cursor, which stays in view
+        let mut maze = Maze::new(30, 60);
+        let (editor, _) = run(&mut maze, &b"\x1b[C".repeat(40));
+        assert_eq!(Coord::new(40, 0), editor.cursor());
+        let screen = editor.render_for(&maze, &FixedSize(40, 12));
+        let lines: Vec<&str> = screen.lines().collect();
+        assert_eq!(12, lines.len());
+        assert_eq!(1, screen.matches("\x1b[7m").count());
+        assert!(lines[..11].iter().all(|l| l.replace("\x1b[7m", "").replace("\x1b[0m", "").chars().count() == 40));
+        assert_eq!('<', lines[5].chars().next().unwrap());
+        assert_eq!("(40, 0) id 0", lines[11]);
+    }
+}
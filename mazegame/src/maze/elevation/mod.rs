@@ -0,0 +1,304 @@
+//! Heights, for mazes with ledges to drop down but not climb back up.  Each
+//! Square can be given an elevation, and a ledge is a one-way passage from a
+//! higher Square to a lower one, so the solvers and anything else that
+//! follows Maze::open_neighbors() only take it downhill.
+//!
+//! Elevations are kept alongside the Squares, like the corridor regions.
+//! They move with their Squares through the transforms and are saved in
+//! every format, and the ledges themselves are ordinary one-way passages.
+use alloc::collections::VecDeque;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants::Direction;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// The most regions assign_elevations() can give distinct heights.
+pub const MAX_ELEVATION_REGIONS: u32 = 128;
+
+impl Maze {
+    /// Returns the elevation of (x, y), or None if it's outside the Maze.
+    /// Squares that were never given one are at 0.
+    pub fn get_elevation(&self, x: u32, y: u32) -> Option<i8> {
+        self.index_of(x, y).map(|i| self.elevations.get(i).cloned().unwrap_or(0))
+    }
+
+    /// Returns the elevation of every Square, indexed like `sq`, or an empty
+    /// slice if none has been set.
+    pub fn elevations(&self) -> &[i8] {
+        &self.elevations
+    }
+
+    /// Sets the elevation of (x, y).  Passages already carved aren't
+    /// changed; carve_ledge() makes the drops between heights.
+    pub fn set_elevation(&mut self, x: u32, y: u32, elevation: i8) -> Result<(), MazeError> {
+        let offset = self.index_of(x, y).ok_or(MazeError::OutOfBounds(x, y))?;
+        if self.elevations.is_empty() {
+            self.elevations = vec![0; self.sq.len()];
+        }
+        self.elevations[offset] = elevation;
+        Ok(())
+    }
+
+    /// Opens a ledge between (x, y) and its neighbor in direction dir: a
+    /// passage that can only be taken from the higher of the two down to
+    /// the lower, whichever side that is.  Fails if there's no neighbor that
+    /// way or both are at the same height.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(1, 2);
+    /// maze.set_elevation(1, 0, 1).unwrap();
+    /// maze.carve_ledge(0, 0, DIR_EAST).unwrap();
+    /// assert!(maze.solve(Coord::new(1, 0), Coord::new(0, 0)).is_some());
+    /// assert!(maze.solve(Coord::new(0, 0), Coord::new(1, 0)).is_none());
+    /// ```
+    pub fn carve_ledge(&mut self, x: u32, y: u32, dir: u32) -> Result<(), MazeError> {
+        let (dest, back) = match (self.neighbor(x, y, dir), Direction::from_u32(dir)) {
+            (Some(dest), Some(side)) => (dest, u32::from(side.opposite())),
+            _ => return Err(MazeError::InvalidArgument(format!("can't carve a ledge {} from ({}, {})",
+                                                               crate::trace::dir_name(dir), x, y))),
+        };
+        let (here, there) = (self.get_elevation(x, y).unwrap(), self.get_elevation(dest.x, dest.y).unwrap());
+        let (from, dir) = match here.cmp(&there) {
+            core::cmp::Ordering::Greater => (Coord::new(x, y), dir),
+            core::cmp::Ordering::Less => (dest, back),
+            core::cmp::Ordering::Equal => {
+                return Err(MazeError::InvalidArgument(format!("({}, {}) and ({}, {}) are at the same height",
+                                                              x, y, dest.x, dest.y)));
+            }
+        };
+        self.carve_one_way(from.x, from.y, dir).map_err(MazeError::InvalidArgument)?;
+        // A drop can't also be a secret passage, which has to open both ways
+        let offset = self.get_offset(from.x, from.y);
+        self.sq[offset].set_secret(dir, false);
+        Ok(())
+    }
+
+    /// Splits the maze into regions connected areas, each at its own
+    /// height, and turns every passage between two of them into a ledge.
+    /// The region with the entrance (or the top left Square, if there's no
+    /// entrance) is the highest, and each other region is lower than one it
+    /// can be reached from, so everything the entrance could reach before
+    /// can still be reached from it, going only downhill.  Squares the
+    /// entrance can't reach are left at 0.  The same seed always gives the
+    /// same heights.
+    ///
+    /// Meant to run after generation.  Fails if regions is 0, more than
+    /// MAX_ELEVATION_REGIONS or more than the Squares the entrance reaches,
+    /// or if the maze has weave crossings, whose tunnels can't be ledges.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.generate_perfect_seeded(485).unwrap();
+    /// maze.set_entrance(0, 0, DIR_NORTH).unwrap();
+    /// maze.set_exit(9, 9, DIR_SOUTH).unwrap();
+    /// maze.assign_elevations(5, 485).unwrap();
+    /// assert!(maze.solve(Coord::new(0, 0), Coord::new(9, 9)).is_some());
+    /// ```
+    pub fn assign_elevations(&mut self, regions: u32, seed: u64) -> Result<(), MazeError> {
+        if regions == 0 || regions > MAX_ELEVATION_REGIONS {
+            return Err(MazeError::InvalidArgument(format!("can't make {} regions; it must be from 1 to {}",
+                                                          regions, MAX_ELEVATION_REGIONS)));
+        }
+        if self.sq.iter().any(|sq| sq.under != 0) {
+            return Err(MazeError::InvalidArgument("a woven maze can't have ledges".to_string()));
+        }
+        let start = self.player_start().unwrap_or(Coord::new(0, 0));
+        if !self.in_bounds(start.x, start.y) {
+            return Err(MazeError::OutOfBounds(start.x, start.y));
+        }
+
+        // Everything the start reaches, through passages open either way
+        let mut reached = vec![false; self.sq.len()];
+        let mut squares = vec![self.get_offset(start.x, start.y)];
+        reached[squares[0]] = true;
+        let mut next = 0;
+        while next < squares.len() {
+            for (_, b) in self.passages(squares[next]) {
+                if !reached[b] {
+                    reached[b] = true;
+                    squares.push(b);
+                }
+            }
+            next += 1;
+        }
+        if squares.len() < regions as usize {
+            return Err(MazeError::InvalidArgument(format!("can't make {} regions from {} connected Squares",
+                                                          regions, squares.len())));
+        }
+
+        // Grow the regions out from the start and randomly picked Squares
+        // together, so each is connected
+        let mut rng = rng::from_seed(seed);
+        rng.shuffle(&mut squares[1..]);
+        let mut owner = vec![None; self.sq.len()];
+        let mut queue = VecDeque::new();
+        for (region, &i) in squares[..regions as usize].iter().enumerate() {
+            owner[i] = Some(region);
+            queue.push_back(i);
+        }
+        while let Some(a) = queue.pop_front() {
+            for (_, b) in self.passages(a) {
+                if owner[b].is_none() {
+                    owner[b] = owner[a];
+                    queue.push_back(b);
+                }
+            }
+        }
+
+        // Number the regions in the order a search from the start's region
+        // finds them, and give earlier ones greater heights
+        let mut touching = vec![Vec::new(); regions as usize];
+        for &a in squares.iter() {
+            for (_, b) in self.passages(a) {
+                let (ra, rb) = (owner[a].unwrap(), owner[b].unwrap());
+                if ra != rb && !touching[ra].contains(&rb) {
+                    touching[ra].push(rb);
+                }
+            }
+        }
+        let mut order = vec![0];
+        let mut rank = vec![None; regions as usize];
+        rank[0] = Some(0);
+        let mut next = 0;
+        while next < order.len() {
+            let mut around = touching[order[next]].clone();
+            around.sort_unstable();
+            for r in around {
+                if rank[r].is_none() {
+                    rank[r] = Some(order.len());
+                    order.push(r);
+                }
+            }
+            next += 1;
+        }
+
+        self.elevations = vec![0; self.sq.len()];
+        for &i in squares.iter() {
+            self.elevations[i] = (regions as usize - 1 - rank[owner[i].unwrap()].unwrap()) as i8;
+        }
+        let mut ledges = 0;
+        for &a in squares.iter() {
+            for (dir, b) in self.passages(a) {
+                if self.elevations[a] > self.elevations[b] {
+                    let c = self.get_coord(a);
+                    self.carve_ledge(c.x, c.y, dir)?;
+                    ledges += 1;
+                }
+            }
+        }
+        gen_debug!("assign_elevations regions={} seed={} squares={} ledges={}", regions, seed, squares.len(), ledges);
+        Ok(())
+    }
+
+    //
+    // Internal - the Squares joined to the Square at offset by a passage
+    // open from either side, with the direction each is in.
+    //
+    fn passages(&self, offset: usize) -> Vec<(u32, usize)> {
+        let c = self.get_coord(offset);
+        let mut out = Vec::new();
+        for &side in Direction::ALL.iter() {
+            let dir = u32::from(side);
+            if let Some(n) = self.neighbor(c.x, c.y, dir) {
+                let other = self.get_offset(n.x, n.y);
+                if !self.sq[offset].is_wall_present(dir) || !self.sq[other].is_wall_present(side.opposite().into()) {
+                    out.push((dir, other));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{self, DIR_EAST, DIR_NORTH, DIR_SOUTH};
+
+    fn level(seed: u64) -> Maze {
+        let mut maze = Maze::new(15, 15);
+        maze.generate_perfect_seeded(seed).unwrap();
+        maze.set_entrance(0, 0, DIR_NORTH).unwrap();
+        maze.set_exit(14, 14, DIR_SOUTH).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_ledge() {
+        let mut maze = Maze::new(2, 3);
+        assert_eq!(Some(0), maze.get_elevation(2, 1));
+        assert_eq!(None, maze.get_elevation(3, 0));
+        assert!(maze.elevations().is_empty());
+        assert!(maze.carve_ledge(0, 0, DIR_EAST).is_err());
+        assert_eq!(Err(MazeError::OutOfBounds(0, 2)), maze.set_elevation(0, 2, 1));
+
+        maze.set_elevation(0, 0, 2).unwrap();
+        maze.set_elevation(1, 0, 1).unwrap();
+        maze.carve_ledge(1, 0, constants::DIR_WEST).unwrap();
+        maze.carve_ledge(1, 0, DIR_EAST).unwrap();
+        maze.carve(2, 0, DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        assert!(maze.validate().is_ok());
+
+        // Down from the top, but not back up
+        let (top, bottom) = (Coord::new(0, 0), Coord::new(2, 1));
+        assert_eq!(4, maze.solve(top, bottom).unwrap().len());
+        assert!(maze.solve(bottom, top).is_none());
+        assert!(maze.solve(Coord::new(2, 0), Coord::new(1, 0)).is_none());
+        assert!(maze.carve_ledge(2, 0, constants::DIR_WEST).is_ok());
+        assert!(maze.carve_ledge(2, 0, DIR_NORTH).is_err());
+    }
+
+    #[test]
+    fn test_assign_elevations() {
+        for seed in 0..30 {
+            let mut maze = level(seed);
+            maze.assign_elevations(6, seed).unwrap();
+            assert!(maze.validate().is_ok());
+            let (entrance, exit) = (Coord::new(0, 0), Coord::new(14, 14));
+            assert!(maze.solve(entrance, exit).is_some(), "seed {}", seed);
+            // The only route back climbs a ledge, unless there isn't one
+            let climbs = maze.get_elevation(14, 14) != maze.get_elevation(0, 0);
+            assert_eq!(climbs, maze.solve(exit, entrance).is_none(), "seed {}", seed);
+            // Everything is still reached from the entrance
+            assert!(maze.distances(entrance).iter().all(|d| d.is_some()));
+        }
+
+        // Each region has its own height, and the entrance's is the highest
+        let mut maze = level(485);
+        maze.assign_elevations(8, 1).unwrap();
+        let mut heights: Vec<i8> = maze.elevations().to_vec();
+        assert_eq!(Some(7), maze.get_elevation(0, 0));
+        heights.sort_unstable();
+        heights.dedup();
+        assert_eq!((0..8).collect::<Vec<i8>>(), heights);
+        let mut again = level(485);
+        again.assign_elevations(8, 1).unwrap();
+        assert_eq!(maze.elevations(), again.elevations());
+        assert_eq!(maze, again);
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        let mut maze = level(1);
+        assert!(maze.assign_elevations(0, 1).is_err());
+        assert!(maze.assign_elevations(MAX_ELEVATION_REGIONS + 1, 1).is_err());
+        assert!(Maze::new(2, 2).assign_elevations(2, 1).is_err());
+        assert!(maze.assign_elevations(MAX_ELEVATION_REGIONS, 1).is_ok());
+
+        let mut woven = Maze::new(8, 8);
+        woven.generate_perfect_woven_seeded(485).unwrap();
+        if woven.sq.iter().any(|sq| sq.under != 0) {
+            assert!(woven.assign_elevations(2, 1).is_err());
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! An endless maze that grows downward one row at a time with Eller's
+//! algorithm, keeping only a window of recent rows in memory, plus a
+//! headless run of the "outrun the wall of doom" game mode built on it.
+//!
+//! Rows are numbered from 0 at the top and never renumbered, so a Square is
+//! addressed by (x, row) with a u64 row.  Rows older than the window are
+//! dropped; to_maze() copies the kept rows into an ordinary Maze for the
+//! solver and renderers, with row first_row() at y = 0.
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants;
+use crate::maze::{Coord, Maze, Square};
+use crate::rng::{self, MazeRng};
+
+/// A maze that's infinitely long going south.
+#[derive(Clone, Debug)]
+pub struct EndlessMaze {
+    cols: u32,
+    window: u32,
+    // The kept rows, oldest first
+    rows: VecDeque<Vec<Square>>,
+    // The number of the oldest kept row
+    first_row: u64,
+    // The Eller set of each Square in the newest row
+    sets: Vec<u64>,
+    next_set: u64,
+    rng: MazeRng,
+}
+
+impl EndlessMaze {
+    /// Creates an endless maze cols Squares wide that keeps the newest
+    /// window rows.  Nothing is generated until extend_to() is called.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::endless::EndlessMaze;
+    ///
+    /// let mut maze = EndlessMaze::new(8, 20, 1);
+    /// maze.extend_to(100);
+    /// assert_eq!(101, maze.generated_rows());
+    /// assert_eq!(81, maze.first_row());
+    /// ```
+    pub fn new(cols: u32, window: u32, seed: u64) -> EndlessMaze {
+        EndlessMaze {
+            cols: cols.max(1),
+            window: window.max(1),
+            rows: VecDeque::new(),
+            first_row: 0,
+            sets: Vec::new(),
+            next_set: 0,
+            rng: rng::from_seed(seed),
+        }
+    }
+
+    /// Returns the width of the maze.
+    pub fn get_cols(&self) -> u32 {
+        self.cols
+    }
+
+    /// Returns the number of the oldest row still kept.
+    pub fn first_row(&self) -> u64 {
+        self.first_row
+    }
+
+    /// Returns the number of rows generated so far, which is one more than
+    /// the number of the newest row.
+    pub fn generated_rows(&self) -> u64 {
+        self.first_row + self.rows.len() as u64
+    }
+
+    /// Returns the number of rows currently kept in memory, which is never
+    /// more than the window.
+    pub fn stored_rows(&self) -> u32 {
+        self.rows.len() as u32
+    }
+
+    /// Generates rows until row exists, dropping the oldest rows to stay
+    /// within the window.
+    pub fn extend_to(&mut self, row: u64) {
+        while self.generated_rows() <= row {
+            self.push_row();
+            if self.rows.len() > self.window as usize {
+                self.rows.pop_front();
+                self.first_row += 1;
+            }
+        }
+    }
+
+    /// Returns the Square at (x, row), or None if it hasn't been generated
+    /// or has been dropped.  The newest row's south walls aren't final until
+    /// the row after it is generated.
+    pub fn square(&self, x: u32, row: u64) -> Option<&Square> {
+        let index = self.row_index(row)?;
+        self.rows[index].get(x as usize)
+    }
+
+    /// Returns the kept Squares reachable in one step from (x, row).
+    pub fn open_neighbors(&self, x: u32, row: u64) -> Vec<(u32, u64)> {
+        let sq = match self.square(x, row) {
+            Some(sq) => sq,
+            None => return Vec::new(),
+        };
+        let steps = [(constants::DIR_NORTH, x as i64, row as i64 - 1),
+                     (constants::DIR_SOUTH, x as i64, row as i64 + 1),
+                     (constants::DIR_EAST, x as i64 + 1, row as i64),
+                     (constants::DIR_WEST, x as i64 - 1, row as i64)];
+        steps.iter()
+            .filter(|&&(dir, nx, ny)| !sq.is_wall_present(dir) && nx >= 0 && ny >= 0)
+            .map(|&(_, nx, ny)| (nx as u32, ny as u64))
+            .filter(|&(nx, ny)| self.square(nx, ny).is_some())
+            .collect()
+    }
+
+    /// Copies the kept rows into a Maze.  Square (x, row) ends up at
+    /// Coord (x, row - first_row()).
+    pub fn to_maze(&self) -> Maze {
+        let mut maze = Maze::new(self.rows.len() as u32, self.cols);
+        maze.sq = self.rows.iter().flat_map(|r| r.iter().cloned()).collect();
+        maze
+    }
+
+    /// Translates (x, row) to its Coord in to_maze(), or None if the row
+    /// isn't kept.
+    pub fn to_local(&self, x: u32, row: u64) -> Option<Coord> {
+        self.row_index(row).filter(|_| x < self.cols).map(|i| Coord::new(x, i as u32))
+    }
+
+    /// Translates a Coord in to_maze() back to (x, row).
+    pub fn from_local(&self, coord: Coord) -> (u32, u64) {
+        (coord.x, self.first_row + coord.y as u64)
+    }
+
+    //
+    // Internal - the position of row in the ring of kept rows.
+    //
+    fn row_index(&self, row: u64) -> Option<usize> {
+        if row >= self.first_row && row < self.generated_rows() {
+            Some((row - self.first_row) as usize)
+        } else {
+            None
+        }
+    }
+
+    //
+    // Internal - adds one row with Eller's algorithm: every set in the row
+    // above carves down at least once, then neighbors in different sets are
+    // joined at random.
+    //
+    fn push_row(&mut self) {
+        let cols = self.cols as usize;
+        let mut row = vec![Square::new(); cols];
+        let mut sets: Vec<Option<u64>> = vec![None; cols];
+
+        if let Some(above) = self.rows.back_mut() {
+            // Group the row above by set, in order of first appearance
+            let mut groups: Vec<(u64, Vec<usize>)> = Vec::new();
+            for (x, &set) in self.sets.iter().enumerate() {
+                match groups.iter_mut().find(|g| g.0 == set) {
+                    Some(g) => g.1.push(x),
+                    None => groups.push((set, vec![x])),
+                }
+            }
+            for (set, mut members) in groups {
+                self.rng.shuffle(&mut members);
+                let down = 1 + self.rng.gen_range(0, members.len());
+                for &x in members.iter().take(down) {
+                    above[x].break_wall(constants::DIR_SOUTH);
+                    above[x].id = constants::ID_MAZE_PATH;
+                    row[x].break_wall(constants::DIR_NORTH);
+                    row[x].id = constants::ID_MAZE_PATH;
+                    sets[x] = Some(set);
+                }
+            }
+        }
+        let mut sets: Vec<u64> = sets.into_iter().map(|s| s.unwrap_or_else(|| {
+            self.next_set += 1;
+            self.next_set
+        })).collect();
+
+        for x in 0..cols.saturating_sub(1) {
+            if sets[x] != sets[x + 1] && self.rng.gen::<bool>() {
+                row[x].break_wall(constants::DIR_EAST);
+                row[x].id = constants::ID_MAZE_PATH;
+                row[x + 1].break_wall(constants::DIR_WEST);
+                row[x + 1].id = constants::ID_MAZE_PATH;
+                let (keep, merged) = (sets[x], sets[x + 1]);
+                for s in sets.iter_mut().filter(|s| **s == merged) {
+                    *s = keep;
+                }
+            }
+        }
+
+        self.sets = sets;
+        self.rows.push_back(row);
+    }
+}
+
+/// A headless run of the endless mode.  Each tick the player takes one step
+/// toward the deepest Square it can reach within its lookahead, and every
+/// doom_interval ticks the wall of doom sweeps down one more row.  The run
+/// ends when the wall passes the player's row.
+#[derive(Clone, Debug)]
+pub struct EndlessRun {
+    maze: EndlessMaze,
+    player: (u32, u64),
+    doom_row: u64,
+    doom_interval: u32,
+    lookahead: u32,
+    ticks: u64,
+    deepest: u64,
+}
+
+impl EndlessRun {
+    /// Starts a run on a maze cols wide, with the player at the top left.
+    /// The maze keeps window rows, which must be more than lookahead.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::endless::EndlessRun;
+    ///
+    /// let mut run = EndlessRun::new(6, 24, 8, 4, 1);
+    /// while run.tick() && run.ticks() < 100 {}
+    /// assert!(run.score() > 0);
+    /// ```
+    pub fn new(cols: u32, window: u32, lookahead: u32, doom_interval: u32, seed: u64) -> EndlessRun {
+        let lookahead = lookahead.max(1);
+        let mut maze = EndlessMaze::new(cols, window.max(lookahead + 1), seed);
+        maze.extend_to(lookahead as u64);
+        EndlessRun {
+            maze,
+            player: (0, 0),
+            doom_row: 0,
+            doom_interval: doom_interval.max(1),
+            lookahead,
+            ticks: 0,
+            deepest: 0,
+        }
+    }
+
+    /// Advances the run by one tick.  Returns false once the player has
+    /// been caught, after which ticking does nothing.
+    pub fn tick(&mut self) -> bool {
+        if self.is_caught() {
+            return false;
+        }
+        let local = self.maze.to_local(self.player.0, self.player.1);
+        let grid = self.maze.to_maze();
+        if let Some(here) = local {
+            let dist = grid.distances(here);
+            // The deepest reachable Square, nearest first on ties
+            let target = dist.iter().enumerate()
+                .filter_map(|(i, d)| d.map(|d| (i as u32 / grid.get_cols(), d, i)))
+                .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))
+                .map(|(_, _, i)| Coord::new(i as u32 % grid.get_cols(), i as u32 / grid.get_cols()));
+            if let Some(path) = target.and_then(|t| grid.solve(here, t)) {
+                if path.len() > 1 {
+                    self.player = self.maze.from_local(path[1]);
+                }
+            }
+        }
+        self.deepest = self.deepest.max(self.player.1);
+        self.maze.extend_to(self.deepest + self.lookahead as u64);
+
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(self.doom_interval as u64) {
+            self.doom_row += 1;
+        }
+        !self.is_caught()
+    }
+
+    /// Returns whether the wall of doom has caught the player, or the player
+    /// has fallen behind the kept rows.
+    pub fn is_caught(&self) -> bool {
+        self.doom_row > self.player.1 || self.player.1 < self.maze.first_row()
+    }
+
+    /// Returns the deepest row the player has reached.
+    pub fn score(&self) -> u64 {
+        self.deepest
+    }
+
+    /// Returns the player's (x, row).
+    pub fn player(&self) -> (u32, u64) {
+        self.player
+    }
+
+    /// Returns the row the wall of doom has reached.
+    pub fn doom_row(&self) -> u64 {
+        self.doom_row
+    }
+
+    /// Returns the number of ticks run so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Returns the maze being run through.
+    pub fn maze(&self) -> &EndlessMaze {
+        &self.maze
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_translation() {
+        let mut endless = EndlessMaze::new(7, 16, 435);
+        for row in 0..500 {
+            endless.extend_to(row);
+            assert!(endless.stored_rows() <= 16);
+        }
+        assert_eq!(500, endless.generated_rows());
+        assert_eq!(484, endless.first_row());
+
+        // Rows either side of the oldest kept one
+        assert!(endless.square(0, 483).is_none());
+        assert!(endless.square(0, 484).is_some());
+        assert!(endless.square(6, 499).is_some());
+        assert!(endless.square(7, 499).is_none());
+        assert!(endless.square(0, 500).is_none());
+        assert_eq!(Some(Coord::new(3, 0)), endless.to_local(3, 484));
+        assert_eq!(None, endless.to_local(3, 483));
+        assert_eq!((3, 499), endless.from_local(Coord::new(3, 15)));
+
+        let maze = endless.to_maze();
+        assert_eq!((7, 16), (maze.get_cols(), maze.get_rows()));
+        for row in 484..500 {
+            for x in 0..7 {
+                let local = endless.to_local(x, row).unwrap();
+                assert_eq!(endless.square(x, row), Some(&maze.sq[maze.get_offset(local.x, local.y)]));
+                let mut expected: Vec<(u32, u64)> = maze.open_neighbors(local.x, local.y).iter()
+                    .map(|&c| endless.from_local(c)).collect();
+                expected.sort();
+                let mut actual = endless.open_neighbors(x, row);
+                actual.sort();
+                assert_eq!(expected, actual);
+            }
+        }
+    }
+
+    #[test]
+    fn test_eller_rows() {
+        // Kept from the start, the rows form a forest with one tree per set
+        // left in the newest row
+        let mut endless = EndlessMaze::new(9, 60, 2);
+        endless.extend_to(59);
+        let maze = endless.to_maze();
+        let passages: usize = (0..maze.get_rows())
+            .flat_map(|y| (0..9).map(move |x| (x, y)))
+            .map(|(x, y)| maze.open_neighbors(x, y).len())
+            .sum::<usize>() / 2;
+        let mut sets = endless.sets.clone();
+        sets.sort();
+        sets.dedup();
+        assert_eq!(9 * 60 - sets.len(), passages);
+        assert!(maze.walls_consistent());
+
+        // Every Square is carved
+        assert!(maze.sq[..maze.sq.len() - 9].iter().all(|sq| sq.is_carved()));
+        let mut again = EndlessMaze::new(9, 60, 2);
+        again.extend_to(59);
+        assert_eq!(maze, again.to_maze());
+    }
+
+    #[test]
+    fn test_run_500_rows() {
+        // A slow wall never catches up
+        let mut run = EndlessRun::new(8, 40, 12, 1000, 435);
+        while run.score() < 500 {
+            assert!(run.tick(), "caught at row {}", run.player().1);
+            assert!(run.maze().stored_rows() <= 40);
+        }
+        assert!(run.maze().first_row() > 400);
+        assert!(run.maze().to_local(run.player().0, run.player().1).is_some());
+
+        // A fast one does
+        let mut run = EndlessRun::new(8, 40, 12, 1, 435);
+        while run.tick() {}
+        assert!(run.is_caught());
+        assert!(run.score() < 500);
+        assert!(!run.tick());
+    }
+}
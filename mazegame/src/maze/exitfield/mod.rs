@@ -0,0 +1,443 @@
+//! The number of steps from every Square to the exit, kept up to date as
+//! walls open and close, for a HUD that shows the distance to the exit
+//! every frame.
+//!
+//! A wall change only touches the Squares whose way to the exit it could
+//! lengthen or shorten.  When a passage on someone's shortest way closes,
+//! the Squares that depended on it are worked out again from the ones
+//! around them that didn't; when a passage opens, the shorter distances
+//! spread out from it.  If a change reaches more than a quarter of the
+//! maze, it's cheaper to search the whole maze again, and that's done
+//! instead.
+use alloc::collections::{BinaryHeap, VecDeque};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+/// Steps to the exit from every Square of a Maze.
+#[derive(Clone, Debug)]
+pub struct ExitDistanceField {
+    exit: Coord,
+    cols: u32,
+    rows: u32,
+    dist: Vec<Option<u32>>,
+    // The revision update() last brought the field up to
+    seen: u64,
+    // Each Square's open walls and tunnels when last looked at
+    walls: Vec<u8>,
+    // How many times the whole maze has been searched, for tests
+    pub(crate) full_searches: u32,
+}
+
+impl ExitDistanceField {
+    /// Works out the steps from every Square of maze to exit.  Fails if exit
+    /// is outside the maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::exitfield::ExitDistanceField;
+    ///
+    /// // A corridor with the exit at the east end
+    /// let mut maze = Maze::from_compact("4cc8").unwrap();
+    /// let mut field = ExitDistanceField::new(&maze, Coord::new(3, 0)).unwrap();
+    /// assert_eq!(Some(3), field.distance_at(Coord::new(0, 0)));
+    ///
+    /// maze.sq[1].build_wall(DIR_EAST);
+    /// maze.sq[2].build_wall(DIR_WEST);
+    /// maze.mark_changed();
+    /// field.update(&maze);
+    /// assert_eq!(None, field.distance_at(Coord::new(0, 0)));
+    /// assert_eq!(Some(1), field.distance_at(Coord::new(2, 0)));
+    /// ```
+    pub fn new(maze: &Maze, exit: Coord) -> Result<ExitDistanceField, MazeError> {
+        if !maze.in_bounds(exit.x, exit.y) {
+            return Err(MazeError::OutOfBounds(exit.x, exit.y));
+        }
+        let mut field = ExitDistanceField {
+            exit,
+            cols: 0,
+            rows: 0,
+            dist: Vec::new(),
+            seen: 0,
+            walls: Vec::new(),
+            full_searches: 0,
+        };
+        field.search(maze);
+        Ok(field)
+    }
+
+    /// Returns the Square the distances lead to.
+    pub fn exit(&self) -> Coord {
+        self.exit
+    }
+
+    /// Returns the number of steps from coord to the exit, or None if the
+    /// exit can't be reached from there or coord is outside the maze.
+    pub fn distance_at(&self, coord: Coord) -> Option<u32> {
+        if coord.x >= self.cols || coord.y >= self.rows {
+            return None;
+        }
+        self.dist[(coord.y * self.cols + coord.x) as usize]
+    }
+
+    /// Returns the distance from every Square, indexed like `sq`.
+    pub fn distances(&self) -> &[Option<u32>] {
+        &self.dist
+    }
+
+    /// Brings the field up to date with maze, repairing the distances
+    /// around every wall that changed since the last update.  Does nothing
+    /// if the Maze's revision hasn't moved on.  Tunnels that changed, a
+    /// change of size or more changed walls than are worth repairing one by
+    /// one mean searching the whole maze again.
+    pub fn update(&mut self, maze: &Maze) {
+        if maze.revision() == self.seen && maze.get_cols() == self.cols && maze.get_rows() == self.rows {
+            return;
+        }
+        if maze.get_cols() != self.cols || maze.get_rows() != self.rows || !maze.in_bounds(self.exit.x, self.exit.y) {
+            self.search(maze);
+            return;
+        }
+
+        let mut pairs = Vec::new();
+        for (i, sq) in maze.sq.iter().enumerate() {
+            let now = wall_key(maze, i);
+            if now == self.walls[i] {
+                continue;
+            }
+            if (now ^ self.walls[i]) >> 4 != 0 {
+                self.search(maze);
+                return;
+            }
+            let c = maze.get_coord(i);
+            for dir in 0..constants::NUM_DIRECTIONS {
+                if (now ^ self.walls[i]) & (1 << dir) != 0 && !sq.has_under_passage(dir) {
+                    if let Some(n) = maze.neighbor(c.x, c.y, dir) {
+                        pairs.push((i, maze.get_offset(n.x, n.y)));
+                    }
+                }
+            }
+        }
+        if pairs.len() > self.repair_limit() {
+            self.search(maze);
+            return;
+        }
+        self.repair(maze, &pairs);
+        self.seen = maze.revision();
+    }
+
+    /// Repairs the distances after the wall on side dir of (x, y) opened or
+    /// closed, on either side.  maze is the Maze after the change.
+    pub fn wall_changed(&mut self, maze: &Maze, x: u32, y: u32, dir: u32) {
+        if maze.get_cols() != self.cols || maze.get_rows() != self.rows || !maze.in_bounds(x, y) {
+            self.search(maze);
+            return;
+        }
+        let a = maze.get_offset(x, y);
+        if let Some(n) = maze.neighbor(x, y, dir) {
+            self.repair(maze, &[(a, maze.get_offset(n.x, n.y))]);
+        }
+    }
+
+    //
+    // Internal - repairs the distances around the walls between each pair of
+    // Squares, all of which may have changed at once.
+    //
+    fn repair(&mut self, maze: &Maze, pairs: &[(usize, usize)]) {
+        for &(a, b) in pairs.iter() {
+            self.walls[a] = wall_key(maze, a);
+            self.walls[b] = wall_key(maze, b);
+        }
+
+        // Closed passages that were the way on for one side, and everything
+        // whose way on went through them
+        let mut invalid = vec![false; self.dist.len()];
+        let mut lost = Vec::new();
+        let steps = || pairs.iter().flat_map(|&(a, b)| [(a, b), (b, a)]);
+        for (u, v) in steps() {
+            let via_v = self.dist[v].is_some_and(|d| self.dist[u] == Some(d + 1));
+            if via_v && !invalid[u] && !self.leads_to(maze, u, v) &&
+               !self.collect_dependents(maze, u, &mut invalid, &mut lost) {
+                self.search(maze);
+                return;
+            }
+        }
+        self.recover(maze, &invalid, &lost);
+
+        // Anything the recovered Squares or the opened passages are a
+        // shortcut for
+        for &w in lost.iter() {
+            if self.dist[w].is_some() {
+                self.spread(maze, w);
+            }
+        }
+        for (u, v) in steps() {
+            if let Some(d) = self.dist[v] {
+                if self.leads_to(maze, u, v) && self.dist[u].is_none_or(|old| d + 1 < old) {
+                    self.dist[u] = Some(d + 1);
+                    self.spread(maze, u);
+                }
+            }
+        }
+    }
+
+    //
+    // Internal - searches the whole maze from the exit.
+    //
+    fn search(&mut self, maze: &Maze) {
+        self.cols = maze.get_cols();
+        self.rows = maze.get_rows();
+        self.dist = vec![None; maze.sq.len()];
+        self.walls = (0..maze.sq.len()).map(|i| wall_key(maze, i)).collect();
+        self.seen = maze.revision();
+        self.full_searches += 1;
+        if maze.in_bounds(self.exit.x, self.exit.y) {
+            let exit = maze.get_offset(self.exit.x, self.exit.y);
+            self.dist[exit] = Some(0);
+            self.spread(maze, exit);
+        }
+    }
+
+    //
+    // Internal - passes a shorter distance at from on to the Squares that
+    // lead to it, and on from them, as far as it shortens anything.
+    //
+    fn spread(&mut self, maze: &Maze, from: usize) {
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        while let Some(cur) = queue.pop_front() {
+            let d = self.dist[cur].unwrap() + 1;
+            for p in predecessors(maze, cur) {
+                if self.dist[p].is_none_or(|old| d < old) {
+                    self.dist[p] = Some(d);
+                    queue.push_back(p);
+                }
+            }
+        }
+    }
+
+    //
+    // Internal - marks from, and every Square with a shortest way to the
+    // exit through it, as invalid and adds them to lost.  Returns false,
+    // having stopped early, if there are too many to be worth repairing.
+    //
+    fn collect_dependents(&self, maze: &Maze, from: usize, invalid: &mut [bool], lost: &mut Vec<usize>) -> bool {
+        let limit = self.repair_limit();
+        invalid[from] = true;
+        lost.push(from);
+        let mut next = lost.len() - 1;
+        while next < lost.len() {
+            let cur = lost[next];
+            next += 1;
+            let d = self.dist[cur].unwrap() + 1;
+            for p in predecessors(maze, cur) {
+                if !invalid[p] && self.dist[p] == Some(d) {
+                    invalid[p] = true;
+                    lost.push(p);
+                    if lost.len() > limit {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    //
+    // Internal - works out the invalid Squares in lost again, from the
+    // valid Squares they lead to, nearest first.
+    //
+    fn recover(&mut self, maze: &Maze, invalid: &[bool], lost: &[usize]) {
+        let mut heap = BinaryHeap::new();
+        for &w in lost.iter() {
+            self.dist[w] = None;
+        }
+        for &w in lost.iter() {
+            let c = maze.get_coord(w);
+            let best = maze.open_neighbors(c.x, c.y).iter()
+                .map(|n| maze.get_offset(n.x, n.y))
+                .filter(|&n| !invalid[n])
+                .filter_map(|n| self.dist[n])
+                .min();
+            if let Some(d) = best {
+                heap.push(Reverse((d + 1, w)));
+            }
+        }
+        while let Some(Reverse((d, w))) = heap.pop() {
+            if self.dist[w].is_some() {
+                continue;
+            }
+            self.dist[w] = Some(d);
+            for p in predecessors(maze, w) {
+                if invalid[p] && self.dist[p].is_none() {
+                    heap.push(Reverse((d + 1, p)));
+                }
+            }
+        }
+    }
+
+    //
+    // Internal - whether a single step leads from Square u to Square v.
+    //
+    fn leads_to(&self, maze: &Maze, u: usize, v: usize) -> bool {
+        let c = maze.get_coord(u);
+        maze.open_neighbors(c.x, c.y).contains(&maze.get_coord(v))
+    }
+
+    //
+    // Internal - the most Squares a change may touch before a full search
+    // is cheaper.
+    //
+    fn repair_limit(&self) -> usize {
+        (self.dist.len() / 4).max(1)
+    }
+}
+
+//
+// Internal - a Square's open walls, plus its tunnels shifted up four bits.
+//
+fn wall_key(maze: &Maze, i: usize) -> u8 {
+    maze.sq[i].open_mask() | (maze.sq[i].under << 4)
+}
+
+//
+// Internal - the Squares a single step leads to Square i from: its
+// neighbors, and the far ends of tunnels, that have it as an open
+// neighbor.
+//
+fn predecessors(maze: &Maze, i: usize) -> Vec<usize> {
+    let c = maze.get_coord(i);
+    let mut found = Vec::new();
+    for dir in 0..constants::NUM_DIRECTIONS {
+        for p in [maze.neighbor(c.x, c.y, dir), maze.step_through(c.x, c.y, dir)].iter().flatten() {
+            let offset = maze.get_offset(p.x, p.y);
+            if !found.contains(&offset) && maze.open_neighbors(p.x, p.y).contains(&c) {
+                found.push(offset);
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::Direction;
+    use crate::rng;
+    use rand::Rng;
+
+    fn check(field: &ExitDistanceField, maze: &Maze) {
+        let fresh = ExitDistanceField::new(maze, field.exit()).unwrap();
+        assert_eq!(fresh.distances(), field.distances());
+    }
+
+    // Opens or closes a random inside wall on both sides
+    fn toggle_random_wall<R: Rng>(maze: &mut Maze, rng: &mut R) -> (u32, u32, u32) {
+        loop {
+            let (x, y) = (rng.gen_range(0, maze.get_cols()), rng.gen_range(0, maze.get_rows()));
+            let side = Direction::ALL[rng.gen_range(0, Direction::ALL.len())];
+            let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+            let n = match maze.neighbor(x, y, dir) {
+                Some(n) => n,
+                None => continue,
+            };
+            let (a, b) = (maze.get_offset(x, y), maze.get_offset(n.x, n.y));
+            if maze.sq[a].is_wall_present(dir) {
+                maze.sq[a].break_wall(dir);
+                maze.sq[b].break_wall(back);
+            } else {
+                maze.sq[a].build_wall(dir);
+                maze.sq[b].build_wall(back);
+            }
+            maze.mark_changed();
+            return (x, y, dir);
+        }
+    }
+
+    #[test]
+    fn test_single_changes() {
+        let mut maze = Maze::new(16, 16);
+        maze.generate_perfect_seeded(476).unwrap();
+        let mut field = ExitDistanceField::new(&maze, Coord::new(15, 15)).unwrap();
+        let mut rng = rng::from_seed(476);
+        for _ in 0..300 {
+            let (x, y, dir) = toggle_random_wall(&mut maze, &mut rng);
+            field.wall_changed(&maze, x, y, dir);
+            check(&field, &maze);
+        }
+        // Most changes were repaired in place
+        assert!(field.full_searches < 100, "{}", field.full_searches);
+    }
+
+    #[test]
+    fn test_update_after_several() {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_perfect_seeded(4760).unwrap();
+        maze.braid_with_rng(0.5, &mut rng::from_seed(1));
+        let mut field = ExitDistanceField::new(&maze, Coord::new(0, 10)).unwrap();
+        let mut rng = rng::from_seed(4761);
+        for round in 0..50 {
+            for _ in 0..round % 4 + 1 {
+                toggle_random_wall(&mut maze, &mut rng);
+            }
+            field.update(&maze);
+            check(&field, &maze);
+        }
+
+        // Nothing to do without a new revision
+        let searches = field.full_searches;
+        field.update(&maze);
+        assert_eq!(searches, field.full_searches);
+    }
+
+    #[test]
+    fn test_pickaxe_and_collapse() {
+        use crate::maze::collapse::Collapse;
+
+        let mut maze = Maze::new(10, 10);
+        maze.generate_perfect_seeded(476).unwrap();
+        maze.set_exit(9, 9, constants::DIR_EAST).unwrap();
+        let mut field = ExitDistanceField::new(&maze, Coord::new(9, 9)).unwrap();
+
+        // Digging through from a Square next to the exit
+        let before = field.distance_at(Coord::new(8, 9)).unwrap();
+        if maze.sq[maze.get_offset(8, 9)].is_wall_present(constants::DIR_EAST) {
+            maze.break_wall_pair_checked(8, 9, constants::DIR_EAST, false).unwrap();
+            field.update(&maze);
+            assert!(field.distance_at(Coord::new(8, 9)).unwrap() < before);
+        }
+        check(&field, &maze);
+
+        // Walking the way out while the passages collapse behind
+        let mut collapse = Collapse::new(2).unwrap();
+        let path = maze.solve(Coord::new(0, 0), Coord::new(9, 9)).unwrap();
+        for step in path.windows(2) {
+            collapse.player_moved(&mut maze, step[0], step[1]).unwrap();
+            field.update(&maze);
+            check(&field, &maze);
+            assert_eq!(field.distance_at(step[1]).map(|d| d as usize), Some(path.len() - 1 - path.iter().position(|&c| c == step[1]).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_one_way_and_bad_exit() {
+        let mut maze = Maze::new(6, 6);
+        maze.generate_perfect_seeded(47).unwrap();
+        let mut field = ExitDistanceField::new(&maze, Coord::new(3, 3)).unwrap();
+        maze.carve_one_way(0, 0, constants::DIR_EAST).unwrap();
+        field.update(&maze);
+        check(&field, &maze);
+        maze.carve_one_way(4, 3, constants::DIR_WEST).unwrap();
+        field.update(&maze);
+        check(&field, &maze);
+
+        assert_eq!(Err(MazeError::OutOfBounds(6, 0)).map(|_: ExitDistanceField| ()),
+                   ExitDistanceField::new(&maze, Coord::new(6, 0)).map(|_| ()));
+        assert_eq!(None, field.distance_at(Coord::new(6, 0)));
+        assert_eq!(Some(0), field.distance_at(Coord::new(3, 3)));
+    }
+}
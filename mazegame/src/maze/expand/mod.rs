@@ -0,0 +1,167 @@
+//! Widening passages.  A maze generated on a coarse grid is blown up so that
+//! every Square becomes a block of Squares with no walls inside it, and every
+//! open wall becomes an opening as wide as the block.  Rooms grow along with
+//! everything else and keep their ids.
+use alloc::string::ToString;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze, Opening};
+
+impl Maze {
+    /// Returns a copy of the Maze with every passage `width` Squares across.
+    /// The copy is `width` times as wide and as tall, and any start Square
+    /// moves to the top left Square of its block.  Weave crossings can't be
+    /// widened.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(5, 5);
+    /// maze.generate_perfect_seeded(3).unwrap();
+    /// let wide = maze.expand_passages(2).unwrap();
+    /// assert_eq!((10, 10), (wide.get_cols(), wide.get_rows()));
+    /// // The top left Square's block has no walls inside it
+    /// assert_eq!(2, wide.open_neighbors(0, 0).iter().filter(|c| c.x < 2 && c.y < 2).count());
+    /// ```
+    pub fn expand_passages(&self, width: u32) -> Result<Maze, MazeError> {
+        if width == 0 {
+            return Err(MazeError::InvalidArgument("passages must be at least 1 Square wide".to_string()));
+        }
+        if width > 1 && self.sq.iter().any(|sq| sq.under != 0) {
+            return Err(MazeError::InvalidArgument("a weave maze's crossings can't be widened".to_string()));
+        }
+        let (rows, cols) = match (self.rows.checked_mul(width), self.cols.checked_mul(width)) {
+            (Some(rows), Some(cols)) if rows.checked_mul(cols).is_some() => (rows, cols),
+            _ => return Err(MazeError::InvalidArgument(format!("passages {} Squares wide don't fit", width))),
+        };
+
+        let mut wide = Maze::new(rows, cols);
+        wide.num_rooms = self.num_rooms;
+        wide.room_themes = self.room_themes.clone();
+        wide.x_topology = self.x_topology;
+        wide.y_topology = self.y_topology;
+        for y in 0..rows {
+            for x in 0..cols {
+                let (cx, cy) = (x / width, y / width);
+                let from = &self.sq[self.get_offset(cx, cy)];
+                let offset = wide.get_offset(x, y);
+                wide.sq[offset].id = from.id;
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    let inside = match dir {
+                        constants::DIR_NORTH => y % width != 0,
+                        constants::DIR_SOUTH => y % width != width - 1,
+                        constants::DIR_EAST => x % width != width - 1,
+                        _ => x % width != 0,
+                    };
+                    // The outer wall is only opened for the entrance and exit
+                    if inside || (self.neighbor(cx, cy, dir).is_some() && !from.is_wall_present(dir)) {
+                        wide.sq[offset].break_wall(dir);
+                        wide.sq[offset].set_one_way(dir, !inside && from.is_one_way(dir));
+                    }
+                }
+            }
+        }
+
+        if let Some(o) = self.entrance {
+            let o = widen_opening(o, width);
+            wide.set_entrance(o.coord.x, o.coord.y, o.dir)?;
+        }
+        for &o in self.exits.iter() {
+            let o = widen_opening(o, width);
+            wide.add_exit(o.coord.x, o.coord.y, o.dir)?;
+        }
+        wide.start = self.start.map(|c| Coord::new(c.x * width, c.y * width));
+        Ok(wide)
+    }
+}
+
+//
+// Internal - moves an opening to the Square on the outer edge of its widened
+// block.
+//
+fn widen_opening(mut o: Opening, width: u32) -> Opening {
+    o.coord.x *= width;
+    o.coord.y *= width;
+    match o.dir {
+        constants::DIR_SOUTH => o.coord.y += width - 1,
+        constants::DIR_EAST => o.coord.x += width - 1,
+        _ => {}
+    }
+    o
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Topology;
+
+    #[test]
+    fn test_wide_corridors() {
+        let mut maze = Maze::new(8, 10);
+        maze.generate_perfect_seeded(415).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        maze.set_exit(9, 7, constants::DIR_SOUTH).unwrap();
+        maze.set_start(3, 5).unwrap();
+        let wide = maze.expand_passages(2).unwrap();
+        assert_eq!((20, 16), (wide.get_cols(), wide.get_rows()));
+        assert!(wide.walls_consistent());
+
+        // Every Square can move both along and across its corridor
+        for y in 0..16 {
+            for x in 0..20 {
+                let open = wide.open_neighbors(x, y);
+                assert!(open.iter().any(|c| c.y == y), "({}, {}) can't move east or west", x, y);
+                assert!(open.iter().any(|c| c.x == x), "({}, {}) can't move north or south", x, y);
+            }
+        }
+        for y in 0..16 {
+            for x in 0..20 {
+                assert!(wide.solve(Coord::new(0, 0), Coord::new(x, y)).is_some());
+            }
+        }
+        assert_eq!(Coord::new(0, 0), wide.get_entrance().unwrap().coord);
+        assert_eq!(Coord::new(18, 15), wide.get_exit().unwrap().coord);
+        assert_eq!(Coord::new(6, 10), wide.get_start());
+
+        assert_eq!(maze, maze.expand_passages(1).unwrap());
+    }
+
+    #[test]
+    fn test_rooms_keep_ids() {
+        let mut maze = Maze::new(12, 12);
+        maze.generate_seeded((3, 2, 3, 2, 3), 6).unwrap();
+        let wide = maze.expand_passages(3).unwrap();
+        for y in 0..36 {
+            for x in 0..36 {
+                assert_eq!(maze.sq[maze.get_offset(x / 3, y / 3)].id, wide.sq[wide.get_offset(x, y)].id);
+            }
+        }
+        assert!(wide.walls_consistent());
+    }
+
+    #[test]
+    fn test_expand_topology_and_one_way() {
+        let mut maze = Maze::new(1, 3);
+        maze.set_topology(Topology::Torus, Topology::Bounded);
+        maze.carve(2, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_one_way(0, 0, constants::DIR_EAST).unwrap();
+        let wide = maze.expand_passages(2).unwrap();
+        assert_eq!((Topology::Torus, Topology::Bounded), wide.get_topology());
+        assert_eq!(Ok(()), wide.validate());
+        // Wrapping from the east edge, then through the one-way door
+        assert_eq!(4, wide.solve(Coord::new(5, 1), Coord::new(2, 1)).unwrap().len());
+        assert_eq!(None, wide.solve(Coord::new(2, 1), Coord::new(1, 1)));
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let maze = Maze::new(4, 4);
+        assert!(maze.expand_passages(0).is_err());
+        assert!(maze.expand_passages(u32::MAX).is_err());
+
+        let mut woven = Maze::new(12, 12);
+        woven.generate_perfect_woven_seeded(411).unwrap();
+        assert!(woven.expand_passages(2).is_err());
+        assert!(woven.expand_passages(1).is_ok());
+    }
+}
@@ -0,0 +1,254 @@
+//! How much of a maze the player explored on the way to the exit, for the
+//! summary screen after a level.  The Squares the player stood on are
+//! compared with the solution, and each step with the distances to the
+//! goal, to count the ones that didn't get the player any closer.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::maze::exitfield::ExitDistanceField;
+use crate::maze::ghost::GhostRun;
+use crate::maze::{Coord, Maze};
+
+/// The characters render_exploration() draws Unexplored, Explored and
+/// OnSolution Squares with.
+pub const DISCOVERY_GLYPHS: [char; 3] = ['.', '+', '@'];
+
+/// The Squares the player stood on, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trail {
+    squares: Vec<Coord>,
+}
+
+impl Trail {
+    /// Creates an empty trail.
+    pub fn new() -> Trail {
+        Trail::default()
+    }
+
+    /// Records the player standing on coord.  Standing still doesn't add
+    /// to the trail.
+    pub fn record(&mut self, coord: Coord) {
+        if self.squares.last() != Some(&coord) {
+            self.squares.push(coord);
+        }
+    }
+
+    /// Returns the Squares in the order they were stood on.
+    pub fn squares(&self) -> &[Coord] {
+        &self.squares
+    }
+}
+
+impl<'a> From<&'a GhostRun> for Trail {
+    fn from(run: &'a GhostRun) -> Trail {
+        let mut trail = Trail::new();
+        for m in run.moves() {
+            trail.record(m.coord);
+        }
+        trail
+    }
+}
+
+/// How a Square figures in a finished level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Discovery {
+    /// On the solution, whether the player stood on it or not.
+    OnSolution,
+    /// Stood on by the player, but off the solution.
+    Explored,
+    /// Never stood on, and off the solution.
+    Unexplored,
+}
+
+/// The summary of a finished level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExplorationReport {
+    /// The share of the carved Squares the player stood on, from 0 to 100.
+    pub explored_percent: f64,
+    /// How many times the player moved from one Square to another.
+    pub steps: u32,
+    /// The steps that didn't bring the player one step closer to the goal,
+    /// so weren't on any shortest path from where the player was.
+    pub wasted_steps: u32,
+    /// Each Square's Discovery, indexed like `sq`.
+    pub cells: Vec<Discovery>,
+}
+
+/// What the player did in a finished level.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameResult {
+    pub trail: Trail,
+}
+
+impl GameResult {
+    /// Compares trail with solution, the shortest path from the start to
+    /// the goal.  The goal is the last Square of solution, or the Maze's
+    /// exit if solution is empty; with neither, no step counts as wasted.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::exploration::{GameResult, Trail};
+    /// use mazegame::prelude::*;
+    ///
+    /// // A corridor with a side passage at (1, 0), explored on the way
+    /// let maze = Maze::from_compact("6a\n11").unwrap();
+    /// let mut trail = Trail::new();
+    /// for &(x, y) in [(0, 0), (1, 0), (1, 1), (1, 0), (0, 0), (0, 1)].iter() {
+    ///     trail.record(Coord::new(x, y));
+    /// }
+    /// let solution = maze.solve(Coord::new(0, 0), Coord::new(0, 1)).unwrap();
+    /// let report = GameResult::exploration_report(&maze, &trail, &solution);
+    /// assert_eq!(100.0, report.explored_percent);
+    /// assert_eq!((5, 2), (report.steps, report.wasted_steps));
+    /// ```
+    pub fn exploration_report(maze: &Maze, trail: &Trail, solution: &[Coord]) -> ExplorationReport {
+        let mut cells = vec![Discovery::Unexplored; maze.sq.len()];
+        for c in trail.squares().iter().filter(|c| maze.in_bounds(c.x, c.y)) {
+            cells[maze.get_offset(c.x, c.y)] = Discovery::Explored;
+        }
+        let carved = maze.sq.iter().filter(|sq| sq.is_carved()).count();
+        let explored = (0..maze.sq.len()).filter(|&i| maze.sq[i].is_carved() && cells[i] != Discovery::Unexplored)
+                                          .count();
+        for c in solution.iter().filter(|c| maze.in_bounds(c.x, c.y)) {
+            cells[maze.get_offset(c.x, c.y)] = Discovery::OnSolution;
+        }
+
+        let goal = solution.last().cloned().or_else(|| maze.get_exit().map(|o| o.coord));
+        let field = goal.and_then(|goal| ExitDistanceField::new(maze, goal).ok());
+        let mut wasted_steps = 0;
+        for pair in trail.squares().windows(2) {
+            let closer = match field {
+                Some(ref field) => match (field.distance_at(pair[0]), field.distance_at(pair[1])) {
+                    (Some(from), Some(to)) => to + 1 == from,
+                    _ => false,
+                },
+                None => true,
+            };
+            if !closer {
+                wasted_steps += 1;
+            }
+        }
+
+        ExplorationReport {
+            explored_percent: if carved == 0 { 0.0 } else { explored as f64 * 100.0 / carved as f64 },
+            steps: trail.squares().len().saturating_sub(1) as u32,
+            wasted_steps,
+            cells,
+        }
+    }
+
+    /// Works out exploration_report() for this result's trail.
+    pub fn report(&self, maze: &Maze, solution: &[Coord]) -> ExplorationReport {
+        GameResult::exploration_report(maze, &self.trail, solution)
+    }
+}
+
+impl Maze {
+    /// Renders the maze like render_ascii(), with each carved Square drawn
+    /// as its Discovery in report, using DISCOVERY_GLYPHS.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::exploration::{Discovery, ExplorationReport};
+    ///
+    /// let maze = mazegame::Maze::from_compact("4c8").unwrap();
+    /// let report = ExplorationReport {
+    ///     explored_percent: 0.0, steps: 0, wasted_steps: 0,
+    ///     cells: vec![Discovery::OnSolution, Discovery::Explored, Discovery::Unexplored],
+    /// };
+    /// assert_eq!("XXXXXXX\nX@ + .X\nXXXXXXX\n", maze.render_exploration(&report));
+    /// ```
+    pub fn render_exploration(&self, report: &ExplorationReport) -> String {
+        let mut lines: Vec<Vec<char>> = self.render_ascii().lines().map(|l| l.chars().collect()).collect();
+        for (i, &cell) in report.cells.iter().enumerate().take(self.sq.len()) {
+            if !self.sq[i].is_carved() {
+                continue;
+            }
+            let glyph = match cell {
+                Discovery::Unexplored => DISCOVERY_GLYPHS[0],
+                Discovery::Explored => DISCOVERY_GLYPHS[1],
+                Discovery::OnSolution => DISCOVERY_GLYPHS[2],
+            };
+            let Coord { x, y } = self.get_coord(i);
+            lines[(y * 2 + 1) as usize][(x * 2 + 1) as usize] = glyph;
+        }
+        let mut out = String::new();
+        for line in lines {
+            out.extend(line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::time::Duration;
+
+    fn trail(squares: &[(u32, u32)]) -> Trail {
+        let mut trail = Trail::new();
+        for &(x, y) in squares {
+            trail.record(Coord::new(x, y));
+        }
+        trail
+    }
+
+    #[test]
+    fn test_playthrough() {
+        // A corridor along the top with three passages down; the exit is
+        // at the bottom of the third, and the fourth column is never
+        // visited
+        let maze = Maze::from_compact("6eea\n1111").unwrap();
+        let solution = maze.solve(Coord::new(0, 0), Coord::new(2, 1)).unwrap();
+        // Down the first dead end and back, then down the second and back
+        let played = trail(&[(0, 0), (0, 1), (0, 0), (1, 0), (1, 0), (1, 1), (1, 0), (2, 0), (2, 1)]);
+        let report = GameResult { trail: played }.report(&maze, &solution);
+        assert_eq!(75.0, report.explored_percent);
+        assert_eq!((7, 2), (report.steps, report.wasted_steps));
+
+        use self::Discovery::*;
+        assert_eq!(vec![OnSolution, OnSolution, OnSolution, Unexplored,
+                        Explored, Explored, OnSolution, Unexplored], report.cells);
+        let drawn = maze.render_exploration(&report);
+        let lines: Vec<&str> = drawn.lines().collect();
+        assert_eq!(maze.render_ascii().lines().count(), lines.len());
+        assert_eq!(vec!['@', '@', '@', '.'], lines[1].chars().skip(1).step_by(2).take(4).collect::<Vec<char>>());
+        assert_eq!(vec!['+', '+', '@', '.'], lines[3].chars().skip(1).step_by(2).take(4).collect::<Vec<char>>());
+
+        // Straight there wastes nothing
+        let report = GameResult::exploration_report(&maze, &trail(&[(0, 0), (1, 0), (2, 0), (2, 1)]), &solution);
+        assert_eq!((50.0, 3, 0), (report.explored_percent, report.steps, report.wasted_steps));
+
+        // Overshooting down the last passage and coming back
+        let report = GameResult::exploration_report(&maze, &trail(&[(0, 0), (1, 0), (2, 0), (3, 0), (3, 1), (3, 0),
+                                                                    (2, 0), (2, 1)]), &solution);
+        assert_eq!((75.0, 7, 2), (report.explored_percent, report.steps, report.wasted_steps));
+    }
+
+    #[test]
+    fn test_goal_from_exit() {
+        let mut maze = Maze::from_compact("4cc8").unwrap();
+        let walk = trail(&[(1, 0), (0, 0), (1, 0), (2, 0), (3, 0)]);
+        // Without a solution or an exit there's nothing to waste steps on
+        let report = GameResult::exploration_report(&maze, &walk, &[]);
+        assert_eq!((100.0, 4, 0), (report.explored_percent, report.steps, report.wasted_steps));
+        assert!(report.cells.iter().all(|&c| c == Discovery::Explored));
+
+        maze.set_exit(3, 0, crate::constants::DIR_EAST).unwrap();
+        let report = GameResult::exploration_report(&maze, &walk, &[]);
+        assert_eq!(1, report.wasted_steps);
+
+        let empty = GameResult::exploration_report(&maze, &Trail::new(), &[]);
+        assert_eq!((0.0, 0, 0), (empty.explored_percent, empty.steps, empty.wasted_steps));
+    }
+
+    #[test]
+    fn test_trail_from_ghost_run() {
+        let mut run = GhostRun::new();
+        for (i, &(x, y)) in [(0, 0), (0, 0), (1, 0), (1, 1)].iter().enumerate() {
+            run.record(Duration::from_millis(i as u64 * 100), Coord::new(x, y)).unwrap();
+        }
+        assert_eq!(trail(&[(0, 0), (1, 0), (1, 1)]), Trail::from(&run));
+    }
+}
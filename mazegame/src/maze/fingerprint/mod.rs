@@ -0,0 +1,253 @@
+//! Fingerprints: short hashes that identify a maze by its layout alone, so
+//! the same maze gets the same fingerprint however it was made, on any
+//! platform and in any version that keeps the scheme.
+//!
+//! A fingerprint is the FNV-1a hash (64-bit, or 128-bit for the hex form)
+//! of these bytes, in order, with every number little-endian:
+//!
+//! ```text
+//! "mazegame-fingerprint-v2"  the ASCII bytes, no terminator
+//! cols, rows                 u32, u32
+//! each Square, row-major     u8 with bit n set when the wall in direction n
+//!                            is present and bit n + 4 when that opening is
+//!                            one-way, then the Square's `under` bits as a
+//!                            u8, then its `secret` bits as a u8
+//! ```
+//!
+//! Ids, rooms, the entrance and exits, topology and generation parameters
+//! aren't hashed.  Any change to this scheme has to change the version in
+//! the prefix.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::maze::transform::Transformable;
+use crate::maze::Maze;
+
+/// The bytes every fingerprint's hash starts with.
+pub const FINGERPRINT_SCHEME: &str = "mazegame-fingerprint-v2";
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+const FNV_OFFSET_BASIS_128: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV_PRIME_128: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+// The CRC-32 lookup table, for the reflected polynomial 0xedb88320
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+impl Maze {
+    /// Returns the 64-bit fingerprint of the Maze, as described in the
+    /// module docs.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut a = Maze::new(8, 8);
+    /// a.generate_perfect_seeded(1).unwrap();
+    /// let mut b = a.clone();
+    /// b.place_entrance_exit_longest().unwrap();
+    /// // Placing the entrance and exit opens the outer wall
+    /// assert_ne!(a.fingerprint(), b.fingerprint());
+    /// a.place_entrance_exit_longest().unwrap();
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        fnv1a(&self.fingerprint_bytes())
+    }
+
+    /// Returns the 128-bit fingerprint of the Maze as 32 lowercase hex
+    /// digits, for when collisions between many mazes matter.
+    pub fn fingerprint_hex(&self) -> String {
+        format!("{:032x}", fnv1a_128(&self.fingerprint_bytes()))
+    }
+
+    /// Returns the 64-bit fingerprint of whichever of the Maze's eight
+    /// rotations and mirror images has the lexicographically smallest
+    /// fingerprint bytes.  A Maze and any turned or flipped copy of it
+    /// share a canonical fingerprint.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(6, 9);
+    /// maze.generate_perfect_seeded(3).unwrap();
+    /// let turned = maze.rotate_cw().mirror_y();
+    /// assert_ne!(maze.fingerprint(), turned.fingerprint());
+    /// assert_eq!(maze.fingerprint_canonical(), turned.fingerprint_canonical());
+    /// ```
+    pub fn fingerprint_canonical(&self) -> u64 {
+        let mut turned = self.clone();
+        let mut smallest = self.fingerprint_bytes();
+        for _ in 0..4 {
+            for variant in [turned.mirror_x().fingerprint_bytes(), turned.fingerprint_bytes()] {
+                if variant < smallest {
+                    smallest = variant;
+                }
+            }
+            turned = turned.rotate_cw();
+        }
+        fnv1a(&smallest)
+    }
+
+    //
+    // Internal - the bytes a fingerprint is the hash of.
+    //
+    fn fingerprint_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(FINGERPRINT_SCHEME.len() + 8 + self.sq.len() * 3);
+        bytes.extend_from_slice(FINGERPRINT_SCHEME.as_bytes());
+        bytes.extend_from_slice(&self.cols.to_le_bytes());
+        bytes.extend_from_slice(&self.rows.to_le_bytes());
+        for sq in self.sq.iter() {
+            let walls = (0..constants::NUM_DIRECTIONS)
+                .filter(|&dir| sq.is_wall_present(dir))
+                .fold(0u8, |bits, dir| bits | 1 << dir);
+            bytes.push(walls | (sq.one_way & 0x0f) << 4);
+            bytes.push(sq.under);
+            bytes.push(sq.secret);
+        }
+        bytes
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of bytes.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &b| (hash ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Returns the CRC-32 (as used by zip and PNG) of bytes.
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &b| CRC32_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+//
+// Internal - builds CRC32_TABLE.
+//
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+//
+// Internal - the 128-bit FNV-1a hash of bytes.
+//
+fn fnv1a_128(bytes: &[u8]) -> u128 {
+    bytes.iter().fold(FNV_OFFSET_BASIS_128, |hash, &b| (hash ^ b as u128).wrapping_mul(FNV_PRIME_128))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        // Published test vectors
+        assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(b"a"));
+        assert_eq!(0x8594_4171_f739_67e8, fnv1a(b"foobar"));
+        assert_eq!(0x6c62_272e_07bb_0142_62b8_2175_6295_c58d, fnv1a_128(b""));
+        assert_eq!(0xd228_cb69_6f1a_8caf_7891_2b70_4e4a_8964, fnv1a_128(b"a"));
+    }
+
+    #[test]
+    fn test_crc32() {
+        // The standard check value, and a couple of others
+        assert_eq!(0, crc32(b""));
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0x414f_a339, crc32(b"The quick brown fox jumps over the lazy dog"));
+    }
+
+    #[test]
+    fn test_pinned() {
+        // If this fails, the scheme changed and every stored fingerprint
+        // changed with it
+        let solid = Maze::new(1, 2);
+        assert_eq!(0x9dde_8632_e0e8_c965, solid.fingerprint());
+        assert_eq!("a5f0da3463e0e83bae2d6049c5eef905", solid.fingerprint_hex());
+
+        let maze = Maze::from_compact("4ca\n4c9").unwrap();
+        assert_eq!(0xf4a8_073b_2631_ee4a, maze.fingerprint());
+        assert_eq!("4f139b8a4596efba028511b37112f0f2", maze.fingerprint_hex());
+        assert_eq!(0x7242_228e_cd5a_b3ab, maze.fingerprint_canonical());
+
+        let mut maze = Maze::new(10, 12);
+        maze.generate_perfect_seeded(469).unwrap();
+        assert_eq!(0xed51_1bf4_b191_c7b1, maze.fingerprint());
+        assert_eq!(0x729c_5d13_cc3b_147a, maze.fingerprint_canonical());
+    }
+
+    #[test]
+    fn test_ignores_metadata() {
+        let mut maze = Maze::new(7, 7);
+        maze.generate_seeded((1, 3, 3, 3, 3), 469).unwrap();
+        let mut plain = maze.clone();
+        for sq in plain.sq.iter_mut() {
+            sq.id = constants::ID_MAZE_PATH;
+        }
+        plain.num_rooms = 0;
+        assert_eq!(maze.fingerprint(), plain.fingerprint());
+        assert_eq!(maze.fingerprint_hex(), plain.fingerprint_hex());
+
+        // A one-way opening isn't the same as a two-way one
+        let mut one_way = Maze::new(2, 2);
+        one_way.carve_one_way(0, 0, constants::DIR_EAST).unwrap();
+        let mut two_way = Maze::new(2, 2);
+        two_way.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        assert_ne!(one_way.fingerprint(), two_way.fingerprint());
+
+        // Nor is a secret passage
+        let mut secret = Maze::new(2, 2);
+        secret.carve_secret(0, 0, constants::DIR_EAST).unwrap();
+        assert_ne!(secret.fingerprint(), two_way.fingerprint());
+        assert_ne!(secret.fingerprint_hex(), two_way.fingerprint_hex());
+    }
+
+    #[test]
+    fn test_canonical() {
+        let mut maze = Maze::new(9, 14);
+        maze.generate_perfect_seeded(46).unwrap();
+        let canonical = maze.fingerprint_canonical();
+        let mut fingerprints = Vec::new();
+        let mut turned = maze.clone();
+        for _ in 0..4 {
+            for variant in [turned.clone(), turned.mirror_x()] {
+                assert_eq!(canonical, variant.fingerprint_canonical());
+                fingerprints.push(variant.fingerprint());
+            }
+            turned = turned.rotate_cw();
+        }
+        // A random maze has no symmetry, so its variants all differ
+        fingerprints.sort();
+        fingerprints.dedup();
+        assert_eq!(8, fingerprints.len());
+        assert!(fingerprints.contains(&canonical));
+    }
+
+    #[test]
+    fn test_seeds_differ() {
+        let fingerprints: Vec<u64> = (0..20)
+            .map(|seed| {
+                let mut maze = Maze::new(10, 10);
+                maze.generate_perfect_seeded(seed).unwrap();
+                maze.fingerprint()
+            })
+            .collect();
+        for (i, a) in fingerprints.iter().enumerate() {
+            assert!(fingerprints[i + 1..].iter().all(|b| a != b));
+        }
+        assert_ne!(Maze::new(3, 4).fingerprint(), Maze::new(4, 3).fingerprint());
+        assert_ne!(Maze::new(3, 4).fingerprint_canonical(), Maze::new(2, 6).fingerprint_canonical());
+    }
+}
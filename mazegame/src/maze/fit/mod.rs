@@ -0,0 +1,360 @@
+//! Drawing mazes in terminals too small for them.  A drawing that's wider
+//! than the terminal wraps and can't be read, so render_fit() cuts it down
+//! to size one of three ways: a window onto part of it, a scaled down
+//! overview in block characters, or pages to be shown one at a time.
+//!
+//! The terminal front ends ask a TerminalSize how big the terminal is and
+//! pick a FitMode with FitMode::pick(), so tests can hand them a FixedSize
+//! instead of a real terminal.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::maze::{Coord, Maze};
+
+/// What separates the pages of a drawing in FitMode::Paged.
+pub const PAGE_BREAK: char = '\x0c';
+
+/// The characters a scaled down drawing is made of, from a block with no
+/// walls in it to a block that's solid wall.
+pub const SCALE_SHADES: [char; 5] = [' ', '\u{2591}', '\u{2592}', '\u{2593}', '\u{2588}'];
+
+/// How a drawing too big for its space is cut down.  Drawings that fit are
+/// never changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FitMode {
+    /// Show the part of the drawing with the Square at origin in its top
+    /// left corner, moved in if that would run off the bottom or right.
+    /// Each edge beyond which the drawing goes on becomes a scroll
+    /// indicator: a blank line or column with an arrow in its middle.
+    Clip { origin: Coord },
+    /// Shrink the whole drawing by the same whole number across and down,
+    /// drawing each block of characters as one of SCALE_SHADES by how much
+    /// of it is wall.
+    Scale,
+    /// Cut the drawing into pages that each fit, row by row, separated by
+    /// PAGE_BREAK.
+    Paged,
+}
+
+impl FitMode {
+    /// Picks the mode for showing a drawing of text_size (width, height)
+    /// characters in a space of max_size.  With a focus, like a cursor or a
+    /// player, it's a window centred on that Square as far as it can be;
+    /// without one, the whole maze is scaled down to fit.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::fit::FitMode;
+    /// use mazegame::prelude::*;
+    ///
+    /// assert_eq!(FitMode::Scale, FitMode::pick((121, 41), (80, 24), None));
+    /// assert_eq!(FitMode::Clip { origin: Coord::new(30, 14) },
+    ///            FitMode::pick((121, 41), (80, 24), Some(Coord::new(50, 20))));
+    /// ```
+    pub fn pick(text_size: (u32, u32), max_size: (u32, u32), focus: Option<Coord>) -> FitMode {
+        let (width, height) = max_size;
+        match focus {
+            _ if text_size.0 <= width && text_size.1 <= height => FitMode::Clip { origin: Coord::new(0, 0) },
+            // A Square is two characters across and down
+            Some(c) => FitMode::Clip {
+                origin: Coord::new(c.x.saturating_sub(width / 4), c.y.saturating_sub(height / 4)),
+            },
+            None => FitMode::Scale,
+        }
+    }
+}
+
+/// Reports how big the terminal is.
+pub trait TerminalSize {
+    /// Returns the terminal's (width, height) in characters, or None if it
+    /// can't be told.
+    fn terminal_size(&self) -> Option<(u32, u32)>;
+}
+
+/// A terminal size that never changes, for tests and for output that isn't
+/// going to a terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedSize(pub u32, pub u32);
+
+impl TerminalSize for FixedSize {
+    fn terminal_size(&self) -> Option<(u32, u32)> {
+        Some((self.0, self.1))
+    }
+}
+
+/// The size of the terminal on stdin, asked of `stty`.  There's no `stty`
+/// to ask off unix, so there the size is never known and callers have to be
+/// given one.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SttySize;
+
+#[cfg(all(feature = "std", unix))]
+impl TerminalSize for SttySize {
+    fn terminal_size(&self) -> Option<(u32, u32)> {
+        use std::process::{Command, Stdio};
+
+        let output = Command::new("stty").arg("size").stdin(Stdio::inherit()).output().ok()?;
+        let text = String::from_utf8(output.stdout).ok()?;
+        let mut fields = text.split_whitespace().map(|f| f.parse::<u32>().ok());
+        match (fields.next()??, fields.next()??) {
+            (0, _) | (_, 0) => None,
+            (rows, cols) => Some((cols, rows)),
+        }
+    }
+}
+
+#[cfg(all(feature = "std", not(unix)))]
+impl TerminalSize for SttySize {
+    fn terminal_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+}
+
+impl Maze {
+    /// Renders the maze like render_ascii(), cut down by mode to at most
+    /// max_width characters across and max_height lines.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::fit::FitMode;
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(20, 60);
+    /// maze.generate_perfect_seeded(479).unwrap();
+    /// let fitted = maze.render_fit(80, 24, FitMode::Scale);
+    /// assert_eq!(21, fitted.lines().count());
+    /// assert!(fitted.lines().all(|l| l.chars().count() == 61));
+    /// ```
+    pub fn render_fit(&self, max_width: u32, max_height: u32, mode: FitMode) -> String {
+        fit_text(&self.render_ascii(), max_width, max_height, mode)
+    }
+}
+
+/// Cuts text, a drawing like render_ascii()'s with one character per cell
+/// and a Square at every odd row and column, down to at most max_width
+/// characters across and max_height lines using mode.
+pub fn fit_text(text: &str, max_width: u32, max_height: u32, mode: FitMode) -> String {
+    let grid = to_grid(text);
+    let (width, height) = grid_size(&grid);
+    if width <= max_width as usize && height <= max_height as usize {
+        return text.into();
+    }
+    let (max_width, max_height) = (max_width.max(1) as usize, max_height.max(1) as usize);
+    match mode {
+        FitMode::Clip { origin } => clip(&grid, max_width, max_height, origin),
+        FitMode::Scale => scale(&grid, max_width, max_height),
+        FitMode::Paged => {
+            let mut out = String::new();
+            for (i, page) in pages(&grid, max_width, max_height).iter().enumerate() {
+                if i > 0 {
+                    out.push(PAGE_BREAK);
+                }
+                out.push_str(page);
+            }
+            out
+        }
+    }
+}
+
+//
+// Internal - text as rows of characters, with short rows padded with
+// spaces.
+//
+fn to_grid(text: &str) -> Vec<Vec<char>> {
+    let mut grid: Vec<Vec<char>> = text.lines().map(|l| l.chars().collect()).collect();
+    let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+    for row in grid.iter_mut() {
+        row.resize(width, ' ');
+    }
+    grid
+}
+
+//
+// Internal - the (width, height) of a grid from to_grid().
+//
+fn grid_size(grid: &[Vec<char>]) -> (usize, usize) {
+    (grid.first().map_or(0, |row| row.len()), grid.len())
+}
+
+//
+// Internal - a window onto grid, with scroll indicators on the edges it
+// hides more of the drawing beyond.
+//
+fn clip(grid: &[Vec<char>], max_width: usize, max_height: usize, origin: Coord) -> String {
+    let (width, height) = grid_size(grid);
+    let (w, h) = (width.min(max_width), height.min(max_height));
+    let left = (origin.x as usize * 2).min(width - w);
+    let top = (origin.y as usize * 2).min(height - h);
+    let (more_west, more_east) = (left > 0, left + w < width);
+    let (more_north, more_south) = (top > 0, top + h < height);
+
+    let mut out = String::new();
+    for y in 0..h {
+        for x in 0..w {
+            let edge_x = (x == 0 && more_west) || (x + 1 == w && more_east);
+            let edge_y = (y == 0 && more_north) || (y + 1 == h && more_south);
+            out.push(match (edge_x, edge_y) {
+                (true, true) => ' ',
+                (true, false) if y == h / 2 => if x == 0 { '<' } else { '>' },
+                (false, true) if x == w / 2 => if y == 0 { '^' } else { 'v' },
+                (false, false) => grid[top + y][left + x],
+                _ => ' ',
+            });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+//
+// Internal - grid shrunk by the smallest whole number that makes it fit,
+// each block drawn by its share of wall.
+//
+fn scale(grid: &[Vec<char>], max_width: usize, max_height: usize) -> String {
+    let (width, height) = grid_size(grid);
+    let factor = width.div_ceil(max_width).max(height.div_ceil(max_height));
+    let mut out = String::new();
+    for by in (0..height).step_by(factor) {
+        for bx in (0..width).step_by(factor) {
+            let block: Vec<char> = grid[by..(by + factor).min(height)].iter()
+                .flat_map(|row| row[bx..(bx + factor).min(width)].iter().cloned())
+                .collect();
+            out.push(minimap_char(block.iter().filter(|&&c| c == 'X').count(), block.len()));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+//
+// Internal - the shade of SCALE_SHADES for a block with walls wall
+// characters out of total.  Only a block that's all wall or has none gets
+// the ends of the scale.
+//
+fn minimap_char(walls: usize, total: usize) -> char {
+    match walls {
+        0 => SCALE_SHADES[0],
+        w if w == total => SCALE_SHADES[4],
+        w => SCALE_SHADES[1 + (w * 3 / total).min(2)],
+    }
+}
+
+//
+// Internal - grid cut into pages that fit, left to right and then top to
+// bottom.
+//
+fn pages(grid: &[Vec<char>], max_width: usize, max_height: usize) -> Vec<String> {
+    let (width, height) = grid_size(grid);
+    let mut pages = Vec::new();
+    for top in (0..height).step_by(max_height) {
+        for left in (0..width).step_by(max_width) {
+            let mut page = String::new();
+            for row in grid[top..(top + max_height).min(height)].iter() {
+                page.extend(row[left..(left + max_width).min(width)].iter());
+                page.push('\n');
+            }
+            pages.push(page);
+        }
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wide_maze() -> Maze {
+        let mut maze = Maze::new(15, 60);
+        maze.generate_perfect_seeded(479).unwrap();
+        maze
+    }
+
+    fn size(text: &str) -> (usize, usize) {
+        let widths: Vec<usize> = text.lines().map(|l| l.chars().count()).collect();
+        assert!(widths.iter().all(|&w| w == widths[0]), "{:?}", widths);
+        (widths[0], widths.len())
+    }
+
+    #[test]
+    fn test_fits_already() {
+        let maze = Maze::new(3, 4);
+        for &mode in [FitMode::Clip { origin: Coord::new(2, 1) }, FitMode::Scale, FitMode::Paged].iter() {
+            assert_eq!(maze.render_ascii(), maze.render_fit(9, 7, mode));
+        }
+    }
+
+    #[test]
+    fn test_clip() {
+        let maze = wide_maze();
+        let full = to_grid(&maze.render_ascii());
+        assert_eq!((121, 31), grid_size(&full));
+
+        // At the origin, only the east and south edges have more beyond
+        let view = maze.render_fit(80, 24, FitMode::Clip { origin: Coord::new(0, 0) });
+        assert_eq!((80, 24), size(&view));
+        let lines: Vec<Vec<char>> = view.lines().map(|l| l.chars().collect()).collect();
+        assert_eq!(full[0][..79], lines[0][..79]);
+        assert_eq!('>', lines[12][79]);
+        assert_eq!('v', lines[23][40]);
+        assert_eq!(1, lines.iter().filter(|l| l[79] != ' ').count());
+
+        // Away from it, all four; and past the far corner, the window stops
+        // at the edge
+        let view = maze.render_fit(40, 12, FitMode::Clip { origin: Coord::new(20, 5) });
+        assert_eq!((40, 12), size(&view));
+        let lines: Vec<Vec<char>> = view.lines().map(|l| l.chars().collect()).collect();
+        assert_eq!(('<', '>'), (lines[6][0], lines[6][39]));
+        assert_eq!(('^', 'v'), (lines[0][20], lines[11][20]));
+        assert_eq!(full[11][41..79], lines[1][1..39]);
+        for &(x, y) in [(0, 0), (39, 0), (0, 11), (39, 11)].iter() {
+            assert_eq!(' ', lines[y][x]);
+        }
+        let view = maze.render_fit(40, 12, FitMode::Clip { origin: Coord::new(99, 99) });
+        let lines: Vec<Vec<char>> = view.lines().map(|l| l.chars().collect()).collect();
+        assert_eq!(full[30][82..], lines[11][1..]);
+        assert_eq!('<', lines[6][0]);
+        assert_eq!('^', lines[0][20]);
+    }
+
+    #[test]
+    fn test_scale() {
+        let maze = wide_maze();
+        let small = maze.render_fit(80, 24, FitMode::Scale);
+        assert_eq!((61, 16), size(&small));
+        assert!(small.chars().all(|c| c == '\n' || SCALE_SHADES.contains(&c)));
+        // The outer wall is solid wherever there's no opening
+        assert!(small.lines().next().unwrap().chars().all(|c| c != ' '));
+        let tiny = maze.render_fit(10, 10, FitMode::Scale);
+        assert_eq!((10, 3), size(&tiny));
+
+        assert_eq!(' ', minimap_char(0, 4));
+        assert_eq!('\u{2591}', minimap_char(1, 4));
+        assert_eq!('\u{2593}', minimap_char(3, 4));
+        assert_eq!('\u{2588}', minimap_char(4, 4));
+    }
+
+    #[test]
+    fn test_paged() {
+        let maze = wide_maze();
+        let paged = maze.render_fit(50, 20, FitMode::Paged);
+        let pages: Vec<&str> = paged.split(PAGE_BREAK).collect();
+        // Three pages across and two down
+        assert_eq!(6, pages.len());
+        assert_eq!((50, 20), size(pages[0]));
+        assert_eq!((21, 20), size(pages[2]));
+        assert_eq!((21, 11), size(pages[5]));
+        let rejoined: String = pages[0].lines().zip(pages[1].lines()).zip(pages[2].lines())
+            .map(|((a, b), c)| format!("{}{}{}\n", a, b, c))
+            .collect();
+        assert!(maze.render_ascii().starts_with(&rejoined));
+    }
+
+    #[test]
+    fn test_pick() {
+        assert_eq!(FitMode::Clip { origin: Coord::new(0, 0) }, FitMode::pick((41, 21), (80, 24), None));
+        assert_eq!(FitMode::Clip { origin: Coord::new(0, 0) },
+                   FitMode::pick((121, 41), (80, 24), Some(Coord::new(3, 2))));
+        assert_eq!(Some((80, 24)), FixedSize(80, 24).terminal_size());
+    }
+}
@@ -0,0 +1,485 @@
+//! A rising flood for escape finales.  Water starts on one Square, usually
+//! the entrance, and each tick spreads one Square further along every open
+//! passage, so the player has to reach the exit before the water reaches
+//! them.
+//!
+//! Water only flows the ways a player could walk.  A locked door holds it
+//! back until the door is unlocked, and a secret passage only lets it
+//! through once the passage has been discovered; the flood keeps trying
+//! both every tick, so it pours through as soon as the way opens.
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::render::{Tile, TileStyle};
+use crate::maze::secret::Discoveries;
+use crate::maze::{Coord, Maze};
+
+/// The water spreading through a Maze.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Flood {
+    cols: u32,
+    ticks: u32,
+    flooded: Vec<bool>,
+    // The Squares that flooded on the last tick
+    frontier: Vec<usize>,
+    // Older flooded Squares held back by a locked door or an undiscovered
+    // secret passage
+    held: Vec<usize>,
+    // The tick each Square is expected to flood on, and how many Squares
+    // are expected to flood on each tick
+    arrival: Vec<Option<u32>>,
+    due: BTreeMap<u32, usize>,
+}
+
+impl Flood {
+    /// Starts a flood at maze's entrance.  Fails if it has none.
+    pub fn new(maze: &Maze) -> Result<Flood, MazeError> {
+        let entrance = maze.get_entrance().ok_or_else(|| {
+            MazeError::InvalidArgument("a flood starts at the entrance, and there isn't one".to_string())
+        })?;
+        Flood::from_source(maze, entrance.coord)
+    }
+
+    /// Starts a flood at source, which is flooded straight away, on tick 0.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::flood::Flood;
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(1, 4);
+    /// for x in 0..3 {
+    ///     maze.carve(x, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// }
+    /// let mut flood = Flood::from_source(&maze, Coord::new(1, 0)).unwrap();
+    /// assert_eq!(Some(2), flood.ticks_until(Coord::new(3, 0)));
+    /// assert_eq!(vec![Coord::new(0, 0), Coord::new(2, 0)], flood.advance(&maze));
+    /// assert!(flood.is_flooded(Coord::new(2, 0)));
+    /// assert_eq!(Some(1), flood.ticks_until(Coord::new(3, 0)));
+    /// ```
+    pub fn from_source(maze: &Maze, source: Coord) -> Result<Flood, MazeError> {
+        let start = maze.index_of(source.x, source.y).ok_or(MazeError::OutOfBounds(source.x, source.y))?;
+        let mut flooded = vec![false; maze.sq.len()];
+        flooded[start] = true;
+        let mut flood = Flood {
+            cols: maze.cols,
+            ticks: 0,
+            flooded,
+            frontier: vec![start],
+            held: Vec::new(),
+            arrival: Vec::new(),
+            due: BTreeMap::new(),
+        };
+        flood.predict(maze, &Discoveries::new());
+        Ok(flood)
+    }
+
+    /// Spreads the water one Square further, treating every secret passage
+    /// as undiscovered, and returns the Squares that flooded.
+    pub fn advance(&mut self, maze: &Maze) -> Vec<Coord> {
+        self.advance_with_discoveries(maze, &Discoveries::new())
+    }
+
+    /// Spreads the water one Square further, letting it through the secret
+    /// passages in discoveries, and returns the Squares that flooded, in
+    /// row-major order.  maze has to be the Maze the flood started in,
+    /// though its doors may have been locked or unlocked since.
+    pub fn advance_with_discoveries(&mut self, maze: &Maze, discoveries: &Discoveries) -> Vec<Coord> {
+        self.ticks += 1;
+        let mut sources = core::mem::take(&mut self.frontier);
+        sources.append(&mut self.held);
+        let mut fresh = Vec::new();
+        for a in sources {
+            let (outs, blocked) = outflow(maze, discoveries, &self.flooded, a);
+            for b in outs {
+                if !self.flooded[b] {
+                    self.flooded[b] = true;
+                    fresh.push(b);
+                }
+            }
+            if blocked {
+                self.held.push(a);
+            }
+        }
+        fresh.sort_unstable();
+
+        // A door opening, a passage being found or a door being locked makes
+        // the flood go somewhere other than expected
+        let expected = self.due.get(&self.ticks).cloned().unwrap_or(0);
+        let surprised = expected != fresh.len() || fresh.iter().any(|&b| self.arrival[b] != Some(self.ticks));
+        self.frontier = fresh.clone();
+        if surprised {
+            self.predict(maze, discoveries);
+        }
+        fresh.into_iter().map(|i| self.coord(i)).collect()
+    }
+
+    /// Returns whether coord is under water.
+    pub fn is_flooded(&self, coord: Coord) -> bool {
+        self.index(coord).is_some_and(|i| self.flooded[i])
+    }
+
+    /// Returns how many more ticks until coord floods, 0 if it already has,
+    /// or None if the water can't reach it.  This is worked out ahead for
+    /// the doors and secret passages as the last tick found them, and is
+    /// worked out again when the flood finds they've changed.
+    pub fn ticks_until(&self, coord: Coord) -> Option<u32> {
+        let i = self.index(coord)?;
+        self.arrival[i].map(|tick| tick.saturating_sub(self.ticks))
+    }
+
+    /// Returns the number of times the flood has advanced.
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// Returns every flooded Square, in row-major order.
+    pub fn flooded(&self) -> Vec<Coord> {
+        (0..self.flooded.len()).filter(|&i| self.flooded[i]).map(|i| self.coord(i)).collect()
+    }
+
+    //
+    // Internal - works out when every Square will flood if nothing changes,
+    // spreading out from everything flooded so far.
+    //
+    fn predict(&mut self, maze: &Maze, discoveries: &Discoveries) {
+        let mut arrival: Vec<Option<u32>> = self.flooded.iter().map(|&f| Some(self.ticks).filter(|_| f)).collect();
+        let mut queue: VecDeque<usize> = self.frontier.iter().chain(self.held.iter()).cloned().collect();
+        let mut due = BTreeMap::new();
+        while let Some(a) = queue.pop_front() {
+            let tick = arrival[a].unwrap() + 1;
+            for b in outflow(maze, discoveries, &self.flooded, a).0 {
+                if arrival[b].is_none() {
+                    arrival[b] = Some(tick);
+                    *due.entry(tick).or_insert(0) += 1;
+                    queue.push_back(b);
+                }
+            }
+        }
+        self.arrival = arrival;
+        self.due = due;
+    }
+
+    //
+    // Internal - converts between coordinates and offsets into flooded.
+    //
+    fn index(&self, coord: Coord) -> Option<usize> {
+        let i = coord.y as usize * self.cols as usize + coord.x as usize;
+        if coord.x < self.cols && i < self.flooded.len() { Some(i) } else { None }
+    }
+
+    fn coord(&self, i: usize) -> Coord {
+        Coord::new(i as u32 % self.cols, i as u32 / self.cols)
+    }
+}
+
+impl Maze {
+    /// Renders the maze like render_ascii(), with a '~' on every flooded
+    /// Square.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::flood::Flood;
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(1, 2);
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// let flood = Flood::from_source(&maze, Coord::new(0, 0)).unwrap();
+    /// assert_eq!("XXXXX\nX~  X\nXXXXX\n", maze.render_ascii_with_flood(&flood));
+    /// ```
+    pub fn render_ascii_with_flood(&self, flood: &Flood) -> String {
+        self.render_ascii_marked(&flood.flooded(), '~', &Discoveries::new())
+    }
+
+    /// Renders the maze like render_svg(), with every flooded Square filled
+    /// in under the walls.
+    pub fn render_svg_with_flood(&self, cell_size: u32, flood: &Flood) -> String {
+        let out = self.render_svg(cell_size);
+        // The water goes between the background and the walls
+        let (before, walls) = out.split_at(out.find("<g ").unwrap_or(out.len()));
+        let margin = cell_size / 2;
+        let water: String = flood.flooded().iter()
+            .map(|c| format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"steelblue\" \
+                              fill-opacity=\"0.5\"/>\n", margin + c.x * cell_size, margin + c.y * cell_size,
+                             cell_size, cell_size))
+            .collect();
+        format!("{}{}{}", before, water, walls)
+    }
+
+    /// Exports the Maze as a tile map like to_tilemap(), with every flooded
+    /// Square as Tile::Water.  In the Thick style, so is the gap between
+    /// two flooded Squares that lead into each other.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::flood::Flood;
+    /// use mazegame::maze::render::{Tile, TileStyle};
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(1, 3);
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// maze.carve(1, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// let mut flood = Flood::from_source(&maze, Coord::new(0, 0)).unwrap();
+    /// flood.advance(&maze);
+    /// let thin = maze.to_tilemap_with_flood(TileStyle::Thin, &flood);
+    /// assert_eq!(vec![Tile::Water, Tile::Water, Tile::Floor], thin[0]);
+    /// let thick = maze.to_tilemap_with_flood(TileStyle::Thick, &flood);
+    /// assert_eq!(&[Tile::Water, Tile::Water, Tile::Water, Tile::Floor, Tile::Floor], &thick[1][1..6]);
+    /// ```
+    pub fn to_tilemap_with_flood(&self, style: TileStyle, flood: &Flood) -> Vec<Vec<Tile>> {
+        let mut tiles = self.to_tilemap(style);
+        for c in flood.flooded() {
+            match style {
+                TileStyle::Thin => tiles[c.y as usize][c.x as usize] = Tile::Water,
+                TileStyle::Thick => {
+                    let (tx, ty) = (c.x as usize * 2 + 1, c.y as usize * 2 + 1);
+                    tiles[ty][tx] = Tile::Water;
+                    // Each Square fills the gaps to its east and south
+                    let gaps = [(constants::DIR_EAST, tx + 1, ty), (constants::DIR_SOUTH, tx, ty + 1)];
+                    for &(dir, gx, gy) in gaps.iter() {
+                        let wet = self.neighbor(c.x, c.y, dir).is_some_and(|n| flood.is_flooded(n));
+                        if wet && tiles[gy][gx] != Tile::Wall {
+                            tiles[gy][gx] = Tile::Water;
+                        }
+                    }
+                }
+            }
+        }
+        tiles
+    }
+}
+
+//
+// Internal - the Squares water on the Square at offset a flows into next,
+// and whether a locked door or undiscovered secret passage held it back
+// from one that isn't flooded yet.
+//
+fn outflow(maze: &Maze, discoveries: &Discoveries, flooded: &[bool], a: usize) -> (Vec<usize>, bool) {
+    let c = maze.get_coord(a);
+    let sq = &maze.sq[a];
+    let mut outs = Vec::new();
+    let mut blocked = false;
+    for &side in Direction::ALL.iter() {
+        let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+        // Water takes the same steps a player could, secret passages aside
+        let b = match maze.passable_step(c.x, c.y, dir, true) {
+            Some(n) => maze.get_offset(n.x, n.y),
+            None => continue,
+        };
+        let next = maze.neighbor(c.x, c.y, dir);
+        let locked = maze.doors().iter().any(|d| {
+            d.locked && ((d.coord, d.dir) == (c, dir) || Some(d.coord) == next && d.dir == back)
+        });
+        if locked || (sq.is_secret(dir) && !discoveries.contains(c, dir)) {
+            blocked |= !flooded[b];
+        } else {
+            outs.push(b);
+        }
+    }
+    (outs, blocked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH, DIR_WEST};
+    use crate::maze::room::Door;
+
+    // Walks the shortest path from the entrance to the exit, one Square
+    // every few ticks
+    struct OptimalAgent {
+        path: Vec<Coord>,
+        step: usize,
+        ticks: u32,
+        ticks_per_move: u32,
+    }
+
+    impl OptimalAgent {
+        fn new(maze: &Maze, ticks_per_move: u32) -> OptimalAgent {
+            let path = maze.solve(maze.get_entrance().unwrap().coord, maze.exits()[0].coord).unwrap();
+            OptimalAgent { path, step: 0, ticks: 0, ticks_per_move }
+        }
+
+        fn position(&self) -> Coord {
+            self.path[self.step]
+        }
+
+        fn is_finished(&self) -> bool {
+            self.step + 1 == self.path.len()
+        }
+
+        fn tick(&mut self) {
+            self.ticks += 1;
+            if !self.is_finished() && self.ticks.is_multiple_of(self.ticks_per_move) {
+                self.step += 1;
+            }
+        }
+    }
+
+    // Runs the escape: the water rises from the entrance head_start ticks
+    // after the agent sets off.  Each tick the agent moves, then the water.
+    // Returns whether the agent got out.
+    fn escape(maze: &Maze, ticks_per_move: u32, head_start: u32) -> bool {
+        let mut agent = OptimalAgent::new(maze, ticks_per_move);
+        let mut flood: Option<Flood> = None;
+        for tick in 0.. {
+            if tick > 0 {
+                agent.tick();
+            }
+            if agent.is_finished() {
+                return true;
+            }
+            match flood.as_mut() {
+                Some(flood) => {
+                    flood.advance(maze);
+                }
+                None if tick == head_start => flood = Some(Flood::new(maze).unwrap()),
+                None => {}
+            }
+            if flood.as_ref().is_some_and(|f| f.is_flooded(agent.position())) {
+                return false;
+            }
+        }
+        unreachable!()
+    }
+
+    fn level(seed: u64) -> Maze {
+        let mut maze = Maze::new(12, 12);
+        maze.generate_perfect_seeded(seed).unwrap();
+        maze.set_entrance(0, 0, DIR_NORTH).unwrap();
+        maze.set_exit(11, 11, DIR_SOUTH).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_spreads_by_distance() {
+        let maze = level(487);
+        let mut flood = Flood::new(&maze).unwrap();
+        let distances = maze.distances(Coord::new(0, 0));
+        for (i, d) in distances.iter().enumerate() {
+            assert_eq!(*d, flood.ticks_until(maze.get_coord(i)));
+        }
+        let mut tick = 0;
+        loop {
+            let fresh = flood.advance(&maze);
+            tick += 1;
+            if fresh.is_empty() {
+                break;
+            }
+            for c in fresh {
+                assert_eq!(Some(tick), distances[maze.get_offset(c.x, c.y)]);
+                assert_eq!(Some(0), flood.ticks_until(c));
+            }
+        }
+        assert_eq!(maze.sq.len(), flood.flooded().len());
+        assert!(maze.render_ascii_with_flood(&flood).matches('~').count() >= maze.sq.len());
+        assert!(Flood::new(&Maze::new(2, 2)).is_err());
+        assert_eq!(Some(MazeError::OutOfBounds(12, 0)), Flood::from_source(&maze, Coord::new(12, 0)).err());
+    }
+
+    #[test]
+    fn test_doors_and_secrets() {
+        // A corridor along the top, through a locked door into a room and
+        // on through a secret passage
+        let mut maze = Maze::new(2, 6);
+        maze.carve(0, 0, DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 0, DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(3, 0, DIR_WEST, 1, true).unwrap();
+        maze.carve(3, 0, DIR_SOUTH, 1, false).unwrap();
+        maze.num_rooms = 1;
+        maze.add_door(Door { coord: Coord::new(3, 0), dir: DIR_WEST, room_id: 1, locked: true }).unwrap();
+        maze.carve_secret(3, 1, DIR_EAST).unwrap();
+        maze.carve(4, 1, DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+
+        let mut flood = Flood::from_source(&maze, Coord::new(0, 0)).unwrap();
+        let beyond = Coord::new(3, 1);
+        assert_eq!(None, flood.ticks_until(beyond));
+        for _ in 0..5 {
+            flood.advance(&maze);
+        }
+        assert_eq!(3, flood.flooded().len());
+        assert!(!flood.is_flooded(Coord::new(3, 0)));
+
+        // Unlocked, the water pours through on the next tick
+        maze.set_door_locked(Coord::new(3, 0), DIR_WEST, false).unwrap();
+        assert_eq!(vec![Coord::new(3, 0)], flood.advance(&maze));
+        assert_eq!(Some(1), flood.ticks_until(beyond));
+        assert_eq!(vec![beyond], flood.advance(&maze));
+        assert_eq!(None, flood.ticks_until(Coord::new(4, 1)));
+        for _ in 0..3 {
+            assert!(flood.advance(&maze).is_empty());
+        }
+
+        // The secret passage only lets the water through once it's found
+        let mut found = Discoveries::new();
+        maze.discover_secret(&mut found, 4, 1, DIR_WEST).unwrap();
+        assert_eq!(vec![Coord::new(4, 1)], flood.advance_with_discoveries(&maze, &found));
+        assert_eq!(Some(1), flood.ticks_until(Coord::new(5, 1)));
+        assert_eq!(vec![Coord::new(5, 1)], flood.advance_with_discoveries(&maze, &found));
+    }
+
+    #[test]
+    fn test_crossings() {
+        // A corridor down the middle column, crossed by a tunnel along the
+        // middle row: water in the corridor stays out of the tunnel
+        let mut maze = Maze::new(3, 3);
+        maze.carve(1, 0, DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_under(0, 1, DIR_EAST).unwrap();
+        let mut flood = Flood::from_source(&maze, Coord::new(1, 0)).unwrap();
+        assert_eq!(None, flood.ticks_until(Coord::new(0, 1)));
+        assert_eq!(vec![Coord::new(1, 1)], flood.advance(&maze));
+        assert_eq!(vec![Coord::new(1, 2)], flood.advance(&maze));
+        assert!(flood.advance(&maze).is_empty());
+
+        // And water in the tunnel passes under the corridor
+        let mut flood = Flood::from_source(&maze, Coord::new(0, 1)).unwrap();
+        assert_eq!(vec![Coord::new(2, 1)], flood.advance(&maze));
+        assert!(flood.advance(&maze).is_empty());
+    }
+
+    #[test]
+    fn test_drawing() {
+        let maze = level(487);
+        let mut flood = Flood::new(&maze).unwrap();
+        for _ in 0..4 {
+            flood.advance(&maze);
+        }
+        let wet = flood.flooded();
+        let svg = maze.render_svg_with_flood(10, &flood);
+        assert_eq!(wet.len(), svg.matches("fill=\"steelblue\"").count());
+        assert!(svg.find("steelblue").unwrap() < svg.find("<line").unwrap());
+        assert_eq!(maze.render_svg(10).matches("<line").count(), svg.matches("<line").count());
+
+        let thin = maze.to_tilemap_with_flood(TileStyle::Thin, &flood);
+        let thick = maze.to_tilemap_with_flood(TileStyle::Thick, &flood);
+        for c in wet.iter() {
+            assert_eq!(Tile::Water, thin[c.y as usize][c.x as usize]);
+            assert_eq!(Tile::Water, thick[c.y as usize * 2 + 1][c.x as usize * 2 + 1]);
+        }
+        let count = |tiles: &Vec<Vec<Tile>>| tiles.iter().flatten().filter(|&&t| t == Tile::Water).count();
+        assert_eq!(wet.len(), count(&thin));
+        // A perfect maze floods along a tree, with a gap for each Square
+        // but the source
+        assert_eq!(wet.len() * 2 - 1, count(&thick));
+        assert_eq!(Tile::Wall, thick[0][0]);
+    }
+
+    #[test]
+    fn test_escape() {
+        // An agent that moves every third tick loses two Squares to the
+        // water each move, so it gets out when the head start is more than
+        // twice the distance from the entrance to the exit
+        for seed in 0..5 {
+            let maze = level(seed);
+            let distance = OptimalAgent::new(&maze, 3).path.len() as u32 - 1;
+            for head_start in [0, distance, 2 * distance - 1, 2 * distance, 2 * distance + 1, 3 * distance].iter() {
+                assert_eq!(2 * distance < *head_start, escape(&maze, 3, *head_start),
+                           "seed {} distance {} head start {}", seed, distance, head_start);
+            }
+        }
+    }
+}
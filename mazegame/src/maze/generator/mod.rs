@@ -0,0 +1,217 @@
+//! Generation algorithms shared by every maze shape.  A maze shape only has
+//! to describe its neighbors by implementing Carvable; the algorithms here
+//! take care of the rest.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use rand::Rng;
+
+use crate::error::MazeError;
+
+/// How many cells a cancellable generator carves between checks of its
+/// cancel flag.
+pub const CANCEL_CHECK_INTERVAL: u32 = 1024;
+
+/// A maze made of cells joined by walls that can be knocked down.
+/// Directions are shape-specific u32 values (`constants::DIR_*` for square
+/// mazes, `constants::HEX_DIR_*` for hex mazes).
+pub trait Carvable {
+    /// A position in the maze.
+    type Cell: Copy;
+
+    /// Returns the directions leading from `cell` to neighbors that haven't
+    /// been carved into yet, in a fixed order.
+    fn uncarved_directions(&self, cell: Self::Cell) -> Vec<u32>;
+
+    /// Knocks down the wall on side `dir` of `cell`, returning the cell on
+    /// the other side.
+    fn carve_toward(&mut self, cell: Self::Cell, dir: u32) -> Result<Self::Cell, String>;
+
+    /// Picks a random direction to tunnel from `cell`.  Returns (false, 0)
+    /// if every neighbor has already been carved.
+    fn pick_direction<R: Rng>(&self, cell: Self::Cell, rng: &mut R) -> (bool, u32) {
+        let directions = self.uncarved_directions(cell);
+        if directions.is_empty() {
+            (false, 0)
+        } else {
+            (true, directions[rng.gen_range(0, directions.len())])
+        }
+    }
+
+    /// Picks a random direction to tunnel from `cell` like pick_direction(),
+    /// but never `avoid`.  Returns (false, 0) if there's no other direction.
+    fn pick_direction_avoiding<R: Rng>(&self, cell: Self::Cell, rng: &mut R, avoid: Option<u32>) -> (bool, u32) {
+        let avoid = match avoid {
+            Some(avoid) => avoid,
+            None => return self.pick_direction(cell, rng),
+        };
+        let mut directions = self.uncarved_directions(cell);
+        directions.retain(|&dir| dir != avoid);
+        if directions.is_empty() {
+            (false, 0)
+        } else {
+            (true, directions[rng.gen_range(0, directions.len())])
+        }
+    }
+}
+
+/// Counts of what a generator did, for tracing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GrowthStats {
+    /// Cells added to the maze, including the starting cell.
+    pub carved: u32,
+    /// Times the generator hit a dead end and returned to an earlier cell.
+    pub backtracks: u32,
+    /// The most cells the generator had waiting to return to at once.
+    pub max_depth: u32,
+}
+
+/// The (from, to) pairs of cells a generator carved between.
+pub type CarvedEdges<C> = Vec<(C, C)>;
+
+/// Carves a perfect maze using the growing tree algorithm, always growing
+/// from the newest cell (which makes it a recursive backtracker).  Cells that
+/// were carved before it started, like rooms, are left alone.
+pub fn growing_tree<G: Carvable, R: Rng>(maze: &mut G, start: G::Cell, rng: &mut R) -> Result<GrowthStats, String> {
+    grow(maze, start, rng, None, 0, |_, _| {}).map_err(Stop::into_message)
+}
+
+/// Carves a perfect maze the same way as growing_tree(), but after
+/// max_run_length cells carved in a row in the same direction, turns
+/// instead of going straight on.  A cell that can only go straight on is
+/// set aside, in case its neighbor gets carved from another side, and only
+/// carves straight on if the neighbor's still uncarved once everything else
+/// is.  A max_run_length of 0 doesn't limit runs.
+pub fn growing_tree_max_run<G: Carvable, R: Rng>(maze: &mut G, start: G::Cell, rng: &mut R, max_run_length: u32)
+                                                 -> Result<GrowthStats, String> {
+    grow(maze, start, rng, None, max_run_length, |_, _| {}).map_err(Stop::into_message)
+}
+
+/// Carves a perfect maze the same way as growing_tree(), stopping with
+/// MazeError::Cancelled if cancel is set while it runs.  The flag is checked
+/// every CANCEL_CHECK_INTERVAL cells, and a cancelled maze is left half
+/// carved.
+pub fn growing_tree_cancellable<G: Carvable, R: Rng>(maze: &mut G, start: G::Cell, rng: &mut R, cancel: &AtomicBool)
+                                                     -> Result<GrowthStats, MazeError> {
+    grow(maze, start, rng, Some(cancel), 0, |_, _| {}).map_err(|stop| match stop {
+        Stop::Cancelled => MazeError::Cancelled,
+        Stop::Failed(msg) => MazeError::InvalidArgument(msg),
+    })
+}
+
+/// Carves a perfect maze the same way as growing_tree(), also returning
+/// every (from, to) pair of cells it carved between, in the order they were
+/// carved.  Each cell but the start appears as `to` exactly once, so these
+/// are the edges of the maze's spanning tree.
+pub fn growing_tree_with_edges<G: Carvable, R: Rng>(maze: &mut G, start: G::Cell, rng: &mut R)
+                                                    -> Result<(GrowthStats, CarvedEdges<G::Cell>), String> {
+    let mut edges = Vec::new();
+    let stats = grow(maze, start, rng, None, 0, |from, to| edges.push((from, to))).map_err(Stop::into_message)?;
+    Ok((stats, edges))
+}
+
+//
+// Internal - why grow() stopped early.
+//
+enum Stop {
+    Cancelled,
+    Failed(String),
+}
+
+impl Stop {
+    fn into_message(self) -> String {
+        match self {
+            Stop::Cancelled => "Generation was cancelled".to_string(),
+            Stop::Failed(msg) => msg,
+        }
+    }
+}
+
+impl From<String> for Stop {
+    fn from(msg: String) -> Stop {
+        Stop::Failed(msg)
+    }
+}
+
+//
+// Internal - the growing tree algorithm, calling carved(from, to) for each
+// new cell and giving up if cancel gets set.  Each cell waiting on the
+// stack keeps the direction it was carved into from and how many cells in a
+// row were carved that way, so runs longer than max_run (if it isn't 0) can
+// be turned away from.
+//
+fn grow<G: Carvable, R: Rng, F: FnMut(G::Cell, G::Cell)>(maze: &mut G, start: G::Cell, rng: &mut R,
+                                                          cancel: Option<&AtomicBool>, max_run: u32, mut carved: F)
+                                                          -> Result<GrowthStats, Stop> {
+    let mut visited: Vec<(G::Cell, Option<u32>, u32)> = Vec::new();
+    // Cells that stopped rather than run on too far
+    let mut held_back: Vec<(G::Cell, Option<u32>, u32)> = Vec::new();
+    let mut stats = GrowthStats { carved: 1, backtracks: 0, max_depth: 0 };
+    let cancelled = || cancel.is_some_and(|c| c.load(Ordering::Relaxed));
+    if cancelled() {
+        return Err(Stop::Cancelled);
+    }
+    let avoid = |incoming: Option<u32>, run: u32| incoming.filter(|_| max_run > 0 && run >= max_run);
+
+    // Handle the initial cell
+    let (result, dir) = maze.pick_direction(start, rng);
+    if !result {
+        return Err(Stop::Failed("Unable to pick initial direction in generator!".to_string()));
+    }
+    let (mut cur, mut incoming, mut run) = (start, None, 0);
+    let mut next_dir = Some(dir);
+
+    loop {
+        // Handle all subsequent cells
+        while let Some(dir) = next_dir {
+            visited.push((cur, incoming, run));
+            stats.max_depth = stats.max_depth.max(visited.len() as u32);
+            let next = maze.carve_toward(cur, dir)?;
+            carved(cur, next);
+            run = if incoming == Some(dir) { run + 1 } else { 1 };
+            incoming = Some(dir);
+            cur = next;
+            stats.carved += 1;
+            if stats.carved.is_multiple_of(CANCEL_CHECK_INTERVAL) && cancelled() {
+                return Err(Stop::Cancelled);
+            }
+
+            next_dir = None;
+            while !visited.is_empty() {
+                let avoiding = avoid(incoming, run);
+                let (result, dir) = maze.pick_direction_avoiding(cur, rng, avoiding);
+                if result {
+                    next_dir = Some(dir);
+                    break;
+                }
+                // No directions available.  Pull a cell from the stack.
+                if avoiding.is_some() {
+                    held_back.push((cur, incoming, run));
+                }
+                stats.backtracks += 1;
+                match visited.pop() {
+                    Some(entry) => (cur, incoming, run) = entry,
+                    None => {
+                        return Err(Stop::Failed("Pop failed in generate_growing_tree!".to_string()));
+                    }
+                }
+            }
+        }
+
+        // Everything else is done, so let a held back cell go straight on if
+        // its neighbor is still waiting
+        loop {
+            match held_back.pop() {
+                Some(entry) => {
+                    let (result, dir) = maze.pick_direction(entry.0, rng);
+                    if result {
+                        (cur, incoming, run) = entry;
+                        next_dir = Some(dir);
+                        break;
+                    }
+                }
+                None => return Ok(stats),
+            }
+        }
+    }
+}
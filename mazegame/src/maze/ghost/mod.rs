@@ -0,0 +1,275 @@
+//! Time-trial ghosts: a recorded run played back alongside the player so
+//! they can race their best time.  A ghost only has a position; it never
+//! touches the Maze and nothing collides with it, so drawing it is all a
+//! front end has to do.
+//!
+//! Playback runs on a fixed timestep.  Frame times are added up and the
+//! ghost's clock only moves in whole steps, so a ghost watched at 30 frames
+//! a second is always in the same place as one watched at 144 after the
+//! same number of steps.
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::error::MazeError;
+use crate::maze::Coord;
+
+/// One move of a recorded run: the player arrived at coord this long after
+/// the run started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GhostMove {
+    pub at: Duration,
+    pub coord: Coord,
+}
+
+/// A recorded run, with its moves in the order they were made.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GhostRun {
+    moves: Vec<GhostMove>,
+}
+
+impl GhostRun {
+    /// Creates an empty run.
+    pub fn new() -> GhostRun {
+        GhostRun::default()
+    }
+
+    /// Records the player arriving at coord at time at.  Fails if at is
+    /// earlier than the last move recorded.
+    pub fn record(&mut self, at: Duration, coord: Coord) -> Result<(), MazeError> {
+        if self.moves.last().is_some_and(|m| at < m.at) {
+            return Err(MazeError::InvalidArgument("ghost moves have to be recorded in order".to_string()));
+        }
+        self.moves.push(GhostMove { at, coord });
+        Ok(())
+    }
+
+    /// Returns the recorded moves.
+    pub fn moves(&self) -> &[GhostMove] {
+        &self.moves
+    }
+
+    /// Returns the time of the last move, which is the run's finishing
+    /// time.
+    pub fn duration(&self) -> Duration {
+        self.moves.last().map_or(Duration::from_secs(0), |m| m.at)
+    }
+}
+
+/// Returns the index of the move a ghost is showing elapsed into its run:
+/// the last one made at or before elapsed.  Returns None before the first
+/// move.  Stretches with no moves, like the player stopping to think, just
+/// keep the ghost where it is.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use mazegame::maze::ghost::{replay_index, GhostMove};
+/// use mazegame::prelude::*;
+///
+/// let ms = Duration::from_millis;
+/// let moves = [GhostMove { at: ms(0), coord: Coord::new(0, 0) },
+///              GhostMove { at: ms(100), coord: Coord::new(1, 0) },
+///              GhostMove { at: ms(900), coord: Coord::new(2, 0) }];
+/// assert_eq!(Some(0), replay_index(&moves, ms(99)));
+/// assert_eq!(Some(1), replay_index(&moves, ms(100)));
+/// assert_eq!(Some(1), replay_index(&moves, ms(899)));
+/// assert_eq!(Some(2), replay_index(&moves, ms(5000)));
+/// ```
+pub fn replay_index(moves: &[GhostMove], elapsed: Duration) -> Option<usize> {
+    moves.partition_point(|m| m.at <= elapsed).checked_sub(1)
+}
+
+/// A run being played back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ghost {
+    run: GhostRun,
+    timestep: Duration,
+    // Time played back so far, always a whole number of timesteps
+    elapsed: Duration,
+    // Frame time not yet making up a whole step
+    leftover: Duration,
+}
+
+impl Ghost {
+    /// Starts playing run back, with its clock moving in steps of
+    /// timestep.  Fails if timestep is zero.
+    pub fn new(run: GhostRun, timestep: Duration) -> Result<Ghost, MazeError> {
+        if timestep == Duration::from_secs(0) {
+            return Err(MazeError::InvalidArgument("the timestep can't be zero".to_string()));
+        }
+        Ok(Ghost { run, timestep, elapsed: Duration::from_secs(0), leftover: Duration::from_secs(0) })
+    }
+
+    /// Lets a frame's worth of time pass, and returns how many whole steps
+    /// the ghost's clock moved.  While the game is paused, just don't call
+    /// this.
+    pub fn advance(&mut self, frame: Duration) -> u32 {
+        self.leftover += frame;
+        let mut steps = 0;
+        while self.leftover >= self.timestep {
+            self.leftover -= self.timestep;
+            self.elapsed += self.timestep;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Moves the ghost's clock exactly one step, for game loops that run
+    /// their own fixed timestep.
+    pub fn tick(&mut self) {
+        self.elapsed += self.timestep;
+    }
+
+    /// Returns how far into the run the ghost is.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns where the ghost is, or None if the run hasn't made its first
+    /// move yet.
+    pub fn position(&self) -> Option<Coord> {
+        replay_index(&self.run.moves, self.elapsed).map(|i| self.run.moves[i].coord)
+    }
+
+    /// Returns whether the ghost has made its last move.
+    pub fn is_finished(&self) -> bool {
+        !self.run.moves.is_empty() && self.elapsed >= self.run.duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Maze;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    // Walks the shortest path one Square every few steps
+    struct OptimalAgent {
+        path: Vec<Coord>,
+        step: usize,
+        ticks: u32,
+        ticks_per_move: u32,
+    }
+
+    impl OptimalAgent {
+        fn new(maze: &Maze, ticks_per_move: u32) -> OptimalAgent {
+            let path = maze.solve(Coord::new(0, 0), Coord::new(maze.cols - 1, maze.rows - 1)).unwrap();
+            OptimalAgent { path, step: 0, ticks: 0, ticks_per_move }
+        }
+
+        fn position(&self) -> Coord {
+            self.path[self.step]
+        }
+
+        fn is_finished(&self) -> bool {
+            self.step + 1 == self.path.len()
+        }
+
+        // Returns whether the agent moved
+        fn tick(&mut self) -> bool {
+            self.ticks += 1;
+            if !self.is_finished() && self.ticks.is_multiple_of(self.ticks_per_move) {
+                self.step += 1;
+                return true;
+            }
+            false
+        }
+    }
+
+    #[test]
+    fn test_replay_index_with_pauses() {
+        let moves: Vec<GhostMove> = [(250, 0), (300, 1), (350, 2), (2350, 3), (2400, 4), (2400, 5)].iter()
+            .map(|&(at, x)| GhostMove { at: ms(at), coord: Coord::new(x, 0) })
+            .collect();
+        assert_eq!(None, replay_index(&moves, ms(0)));
+        assert_eq!(None, replay_index(&moves, ms(249)));
+        assert_eq!(Some(0), replay_index(&moves, ms(250)));
+        // A two second pause in the middle holds the ghost still
+        for t in (350..2350).step_by(100) {
+            assert_eq!(Some(2), replay_index(&moves, ms(t)));
+        }
+        assert_eq!(Some(3), replay_index(&moves, ms(2350)));
+        // Two moves at the same moment show the later one
+        assert_eq!(Some(5), replay_index(&moves, ms(2400)));
+        assert_eq!(Some(5), replay_index(&moves, ms(60_000)));
+        assert_eq!(None, replay_index(&[], ms(10)));
+    }
+
+    #[test]
+    fn test_frame_rates_agree() {
+        let mut run = GhostRun::new();
+        for i in 0..20 {
+            run.record(ms(i * 45), Coord::new(i as u32, 0)).unwrap();
+        }
+        assert!(run.record(ms(0), Coord::new(0, 0)).is_err());
+
+        let step = Duration::from_micros(16_667);
+        let mut slow = Ghost::new(run.clone(), step).unwrap();
+        let mut fast = Ghost::new(run.clone(), step).unwrap();
+        let mut jittery = Ghost::new(run, step).unwrap();
+        let jitter = [ms(3), ms(40), ms(11), ms(0), ms(90)];
+        let mut i = 0;
+        while !slow.is_finished() {
+            slow.advance(ms(33));
+            while fast.elapsed() < slow.elapsed() {
+                fast.advance(Duration::from_micros(6_944));
+            }
+            while jittery.elapsed() < slow.elapsed() {
+                jittery.advance(jitter[i % jitter.len()]);
+                i += 1;
+            }
+            // Whoever overshot is ahead by whole steps, never part of one
+            for ghost in [&fast, &jittery].iter() {
+                assert_eq!(0, (ghost.elapsed().as_nanos() % step.as_nanos()));
+            }
+            if fast.elapsed() == slow.elapsed() {
+                assert_eq!(slow.position(), fast.position());
+            }
+        }
+        assert!(Ghost::new(GhostRun::new(), Duration::from_secs(0)).is_err());
+        assert!(!Ghost::new(GhostRun::new(), step).unwrap().is_finished());
+    }
+
+    #[test]
+    fn test_race_own_ghost() {
+        let mut maze = Maze::new(12, 12);
+        maze.generate_perfect_seeded(445).unwrap();
+        let timestep = Duration::from_micros(16_667);
+
+        // Record a run, one move every five steps
+        let mut agent = OptimalAgent::new(&maze, 5);
+        let mut run = GhostRun::new();
+        let mut clock = Duration::from_secs(0);
+        run.record(clock, agent.position()).unwrap();
+        while !agent.is_finished() {
+            clock += timestep;
+            if agent.tick() {
+                run.record(clock, agent.position()).unwrap();
+            }
+        }
+
+        // Race it, with the frame rate wandering between about 20 and 200
+        let mut agent = OptimalAgent::new(&maze, 5);
+        let mut ghost = Ghost::new(run, timestep).unwrap();
+        let frames = [ms(5), ms(48), ms(16), ms(17), ms(33), ms(9)];
+        let mut leftover = Duration::from_secs(0);
+        let mut frame = 0;
+        while !agent.is_finished() {
+            leftover += frames[frame % frames.len()];
+            frame += 1;
+            while leftover >= timestep && !agent.is_finished() {
+                leftover -= timestep;
+                agent.tick();
+                ghost.tick();
+                assert_eq!(Some(agent.position()), ghost.position());
+                // The ghost never finishes before the agent does
+                assert_eq!(agent.is_finished(), ghost.is_finished());
+            }
+        }
+        assert!(ghost.is_finished());
+    }
+}
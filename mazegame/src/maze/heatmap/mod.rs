@@ -0,0 +1,134 @@
+//! Heat maps of how often simulated walkers pass through each Square, for
+//! finding the busy corridors worth putting traps in.
+use alloc::string::String;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// The characters heat is drawn with, from coldest to hottest.  Squares no
+/// walker visited are left blank.
+pub const HEAT_RAMP: &[char] = &['.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+/// How the walkers in a heat map move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WalkPolicy {
+    /// Each trial starts on a random carved Square and takes this many
+    /// steps, each to a random open neighbor.
+    RandomWalk { steps: u32 },
+    /// Each trial walks the shortest path between two random carved Squares.
+    SolvePairs,
+}
+
+impl Maze {
+    /// Runs trials simulated walks and counts how many times each Square is
+    /// visited, indexed like `sq`.  The same seed always gives the same
+    /// counts.  Mazes with nothing carved get all zeroes.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::heatmap::WalkPolicy;
+    ///
+    /// let maze = mazegame::Maze::from_compact("4cc8").unwrap();
+    /// let heat = maze.traversal_heatmap(100, 1, WalkPolicy::SolvePairs);
+    /// assert!(heat[1] > heat[0]);
+    /// ```
+    pub fn traversal_heatmap(&self, trials: u32, seed: u64, policy: WalkPolicy) -> Vec<u32> {
+        let mut heat = vec![0; self.sq.len()];
+        let carved: Vec<Coord> = (0..self.sq.len())
+            .filter(|&i| self.sq[i].is_carved())
+            .map(|i| self.get_coord(i))
+            .collect();
+        if carved.is_empty() {
+            return heat;
+        }
+
+        let mut rng = rng::from_seed(seed);
+        for _ in 0..trials {
+            let start = carved[rng.gen_range(0, carved.len())];
+            match policy {
+                WalkPolicy::RandomWalk { steps } => {
+                    let mut cur = start;
+                    heat[self.get_offset(cur.x, cur.y)] += 1;
+                    for _ in 0..steps {
+                        let next = self.open_neighbors(cur.x, cur.y);
+                        if next.is_empty() {
+                            break;
+                        }
+                        cur = next[rng.gen_range(0, next.len())];
+                        heat[self.get_offset(cur.x, cur.y)] += 1;
+                    }
+                }
+                WalkPolicy::SolvePairs => {
+                    let end = carved[rng.gen_range(0, carved.len())];
+                    for c in self.solve(start, end).unwrap_or_default() {
+                        heat[self.get_offset(c.x, c.y)] += 1;
+                    }
+                }
+            }
+        }
+        heat
+    }
+
+    /// Renders the maze like render_ascii(), with each visited Square drawn
+    /// using HEAT_RAMP scaled so the hottest Square gets the last character.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::Maze::from_compact("4cc8").unwrap();
+    /// assert_eq!("XXXXXXXXX\nX: = @  X\nXXXXXXXXX\n", maze.render_heatmap(&[1, 3, 8, 0]));
+    /// ```
+    pub fn render_heatmap(&self, heat: &[u32]) -> String {
+        let mut lines: Vec<Vec<char>> = self.render_ascii().lines().map(|l| l.chars().collect()).collect();
+        let hottest = heat.iter().cloned().max().unwrap_or(0);
+        for (i, &count) in heat.iter().enumerate().take(self.sq.len()).filter(|&(_, &count)| count > 0) {
+            let level = (count as u64 * (HEAT_RAMP.len() as u64 - 1) / hottest as u64) as usize;
+            let Coord { x, y } = self.get_coord(i);
+            lines[(y * 2 + 1) as usize][(x * 2 + 1) as usize] = HEAT_RAMP[level];
+        }
+        let mut out = String::new();
+        for line in lines {
+            out.extend(line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corridor_middle_is_hottest() {
+        let maze = Maze::from_compact("4ccccccccccccccccccccccccc8").unwrap();
+        let heat = maze.traversal_heatmap(2000, 431, WalkPolicy::SolvePairs);
+        // The three middle Squares are within noise of each other
+        let middle = heat[12..15].iter().cloned().max().unwrap();
+        assert_eq!(middle, *heat.iter().max().unwrap());
+        // Heat falls off toward both ends
+        assert!(heat[0] < heat[6] && heat[6] < middle);
+        assert!(heat[26] < heat[20] && heat[20] < middle);
+
+        assert_eq!(heat, maze.traversal_heatmap(2000, 431, WalkPolicy::SolvePairs));
+    }
+
+    #[test]
+    fn test_random_walk() {
+        let mut maze = Maze::new(10, 10);
+        maze.generate_perfect_seeded(431).unwrap();
+        let policy = WalkPolicy::RandomWalk { steps: 50 };
+        let heat = maze.traversal_heatmap(40, 7, policy);
+        // Every trial visits its start plus one Square per step
+        assert_eq!(40 * 51, heat.iter().sum::<u32>());
+        assert_eq!(heat, maze.traversal_heatmap(40, 7, policy));
+        assert_ne!(heat, maze.traversal_heatmap(40, 8, policy));
+
+        let rendered = maze.render_heatmap(&heat);
+        assert!(rendered.contains('@'));
+        assert_eq!(maze.render_ascii().len(), rendered.len());
+
+        assert_eq!(vec![0; 4], Maze::new(2, 2).traversal_heatmap(10, 1, policy));
+    }
+}
@@ -0,0 +1,415 @@
+//! Hexagonal mazes.  Cells are flat-topped hexagons with six walls, addressed
+//! by axial coordinates: q counts columns to the east and r counts cells
+//! down each column, so (q, r) and (q + 1, r) meet at an angle.  A HexMaze is
+//! itself a hexagon, made up of every cell within `radius` steps of (0, 0).
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::generator::{self, Carvable};
+use crate::rng;
+
+/// An axial (q, r) position in a HexMaze.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HexCoord {
+    pub q: i32,
+    pub r: i32,
+}
+
+impl HexCoord {
+    /// Creates a new HexCoord.
+    pub fn new(q: i32, r: i32) -> HexCoord {
+        HexCoord { q, r }
+    }
+
+    /// Returns the position one step away in direction `dir` (one of the
+    /// `constants::HEX_DIR_*` values).
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::constants;
+    /// use mazegame::maze::hex::HexCoord;
+    ///
+    /// let c = HexCoord::new(0, 0);
+    /// assert_eq!(HexCoord::new(1, -1), c.neighbor(constants::HEX_DIR_NORTH_EAST));
+    /// assert_eq!(c, c.neighbor(constants::HEX_DIR_SOUTH).neighbor(constants::HEX_DIR_NORTH));
+    /// ```
+    pub fn neighbor(&self, dir: u32) -> HexCoord {
+        let (dq, dr) = match dir {
+            constants::HEX_DIR_NORTH => (0, -1),
+            constants::HEX_DIR_NORTH_EAST => (1, -1),
+            constants::HEX_DIR_SOUTH_EAST => (1, 0),
+            constants::HEX_DIR_SOUTH => (0, 1),
+            constants::HEX_DIR_SOUTH_WEST => (-1, 1),
+            _ => (-1, 0),
+        };
+        HexCoord::new(self.q + dq, self.r + dr)
+    }
+
+    /// Returns how many steps the position is from (0, 0).
+    pub fn distance_from_center(&self) -> u32 {
+        let s = -self.q - self.r;
+        self.q.unsigned_abs().max(self.r.unsigned_abs()).max(s.unsigned_abs())
+    }
+}
+
+/// A single cell in a HexMaze, with a wall on each of its six sides.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HexSquare {
+    pub wall_present: [bool; constants::HEX_NUM_DIRECTIONS as usize],
+    pub id: i32,
+}
+
+impl Default for HexSquare {
+    fn default() -> HexSquare {
+        HexSquare::new()
+    }
+}
+
+impl HexSquare {
+    /// Creates a new HexSquare with all six walls standing.
+    pub fn new() -> HexSquare {
+        HexSquare {
+            wall_present: [true; constants::HEX_NUM_DIRECTIONS as usize],
+            id: constants::ID_UNCARVED,
+        }
+    }
+
+    /// Removes the wall in the specified direction.
+    pub fn break_wall(&mut self, dir: u32) {
+        self.wall_present[dir as usize] = false;
+    }
+
+    /// Builds the wall in the specified direction.
+    pub fn build_wall(&mut self, dir: u32) {
+        self.wall_present[dir as usize] = true;
+    }
+
+    /// Returns whether the wall in the specified direction is standing.
+    pub fn is_wall_present(&self, dir: u32) -> bool {
+        self.wall_present[dir as usize]
+    }
+
+    /// Returns whether at least one wall has been removed.
+    pub fn is_carved(&self) -> bool {
+        self.wall_present.iter().any(|&w| !w)
+    }
+}
+
+/// A hexagon-shaped maze of hexagonal cells.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HexMaze {
+    radius: u32,
+    // A (2 * radius + 1) square of cells, indexed by (q, r); the corners
+    // outside of the hexagon are never used.
+    cells: Vec<HexSquare>,
+}
+
+impl HexMaze {
+    /// Creates a new, uncarved hex maze of every cell within `radius` steps
+    /// of the center.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::maze::hex::HexMaze::new(2);
+    /// assert_eq!(19, maze.cell_count());
+    /// ```
+    pub fn new(radius: u32) -> HexMaze {
+        let side = (radius * 2 + 1) as usize;
+        HexMaze {
+            radius,
+            cells: vec![HexSquare::new(); side * side],
+        }
+    }
+
+    /// Returns the radius of the maze.
+    pub fn get_radius(&self) -> u32 {
+        self.radius
+    }
+
+    /// Returns the number of cells in the maze.
+    pub fn cell_count(&self) -> usize {
+        (3 * self.radius * (self.radius + 1) + 1) as usize
+    }
+
+    /// Returns whether `c` is one of the maze's cells.
+    pub fn contains(&self, c: HexCoord) -> bool {
+        c.distance_from_center() <= self.radius
+    }
+
+    /// Returns every cell position in the maze, row by row.
+    pub fn coords(&self) -> Vec<HexCoord> {
+        let radius = self.radius as i32;
+        let mut coords = Vec::with_capacity(self.cell_count());
+        for r in -radius..=radius {
+            for q in -radius..=radius {
+                let c = HexCoord::new(q, r);
+                if self.contains(c) {
+                    coords.push(c);
+                }
+            }
+        }
+        coords
+    }
+
+    /// Returns the cell at `c`, or None if it's outside of the maze.
+    pub fn get(&self, c: HexCoord) -> Option<&HexSquare> {
+        if self.contains(c) {
+            Some(&self.cells[self.get_offset(c)])
+        } else {
+            None
+        }
+    }
+
+    // Internal: Calculates the offset of a cell in the cell vector.
+    fn get_offset(&self, c: HexCoord) -> usize {
+        let radius = self.radius as i32;
+        ((c.r + radius) * (radius * 2 + 1) + c.q + radius) as usize
+    }
+
+    /// Knocks down the wall on side `dir` of the cell at `c`, along with the
+    /// matching wall of the cell on the other side.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::constants;
+    /// use mazegame::maze::hex::{HexCoord, HexMaze};
+    ///
+    /// let mut maze = HexMaze::new(1);
+    /// maze.carve(HexCoord::new(0, 0), constants::HEX_DIR_SOUTH_WEST).unwrap();
+    /// assert!(!maze.get(HexCoord::new(-1, 1)).unwrap().is_wall_present(constants::HEX_DIR_NORTH_EAST));
+    /// assert!(maze.carve(HexCoord::new(0, -1), constants::HEX_DIR_NORTH).is_err());
+    /// ```
+    pub fn carve(&mut self, c: HexCoord, dir: u32) -> Result<(), MazeError> {
+        if !self.contains(c) {
+            return Err(MazeError::InvalidArgument(format!("({}, {}) is outside of the hex maze", c.q, c.r)));
+        }
+        if dir >= constants::HEX_NUM_DIRECTIONS {
+            return Err(MazeError::InvalidArgument(format!("illegal hex direction {}", dir)));
+        }
+        let dest = c.neighbor(dir);
+        if !self.contains(dest) {
+            return Err(MazeError::InvalidArgument(
+                format!("can't carve through the outer wall at ({}, {})", c.q, c.r)));
+        }
+
+        let offset = self.get_offset(c);
+        self.cells[offset].break_wall(dir);
+        self.cells[offset].id = constants::ID_MAZE_PATH;
+        let offset = self.get_offset(dest);
+        self.cells[offset].break_wall((dir + 3) % constants::HEX_NUM_DIRECTIONS);
+        self.cells[offset].id = constants::ID_MAZE_PATH;
+        Ok(())
+    }
+
+    /// Generates a perfect hex maze from a seed.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::maze::hex::HexMaze::new(3);
+    /// maze.generate_perfect_seeded(8).unwrap();
+    /// ```
+    pub fn generate_perfect_seeded(&mut self, seed: u64) -> Result<(), String> {
+        self.generate_perfect_with_rng(&mut rng::from_seed(seed))
+    }
+
+    /// Generates a perfect hex maze using the supplied random number
+    /// generator, growing out from the center.
+    pub fn generate_perfect_with_rng<R: Rng>(&mut self, rng: &mut R) -> Result<(), String> {
+        let stats = generator::growing_tree(self, HexCoord::new(0, 0), rng)?;
+        gen_debug!("hex_growing_tree q=0 r=0 carved={} backtracks={}", stats.carved, stats.backtracks);
+        Ok(())
+    }
+
+    /// Returns the cells that can be reached in a single step from `c`.
+    pub fn open_neighbors(&self, c: HexCoord) -> Vec<HexCoord> {
+        let mut neighbors = Vec::new();
+        if let Some(cell) = self.get(c) {
+            for dir in 0..constants::HEX_NUM_DIRECTIONS {
+                let next = c.neighbor(dir);
+                if !cell.is_wall_present(dir) && self.contains(next) {
+                    neighbors.push(next);
+                }
+            }
+        }
+        neighbors
+    }
+
+    /// Finds the shortest path between two cells using a breadth-first
+    /// search.  The path includes both endpoints.  Returns None if either
+    /// cell is outside of the maze or if there's no path between them.
+    pub fn solve(&self, from: HexCoord, to: HexCoord) -> Option<Vec<HexCoord>> {
+        if !self.contains(from) || !self.contains(to) {
+            return None;
+        }
+
+        let mut came_from: Vec<Option<HexCoord>> = vec![None; self.cells.len()];
+        let mut seen = vec![false; self.cells.len()];
+        let mut queue = VecDeque::new();
+
+        seen[self.get_offset(from)] = true;
+        queue.push_back(from);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                let mut path = vec![cur];
+                let mut step = cur;
+                while let Some(prev) = came_from[self.get_offset(step)] {
+                    path.push(prev);
+                    step = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.open_neighbors(cur) {
+                let offset = self.get_offset(next);
+                if !seen[offset] {
+                    seen[offset] = true;
+                    came_from[offset] = Some(cur);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Draws the maze with slashes and underscores, each cell looking like
+    ///
+    /// ```text
+    ///  __
+    /// /  \
+    /// \__/
+    /// ```
+    ///
+    /// with neighboring cells sharing their walls.
+    pub fn render_ascii(&self) -> String {
+        let radius = self.radius as i32;
+        let width = (6 * radius + 4) as usize;
+        let height = (4 * radius + 3) as usize;
+        let mut grid = vec![vec![' '; width]; height];
+
+        for c in self.coords() {
+            let cell = &self.cells[self.get_offset(c)];
+            // Each column of cells sits half a cell lower than the one to its west
+            let x = (3 * (c.q + radius)) as usize;
+            let y = (2 * c.r + c.q + 2 * radius) as usize;
+            let walls = [(constants::HEX_DIR_NORTH, [(x + 1, y), (x + 2, y)], '_'),
+                         (constants::HEX_DIR_SOUTH, [(x + 1, y + 2), (x + 2, y + 2)], '_'),
+                         (constants::HEX_DIR_NORTH_EAST, [(x + 3, y + 1); 2], '\\'),
+                         (constants::HEX_DIR_SOUTH_EAST, [(x + 3, y + 2); 2], '/'),
+                         (constants::HEX_DIR_SOUTH_WEST, [(x, y + 2); 2], '\\'),
+                         (constants::HEX_DIR_NORTH_WEST, [(x, y + 1); 2], '/')];
+            for &(dir, points, ch) in &walls {
+                if cell.is_wall_present(dir) {
+                    for &(px, py) in &points {
+                        grid[py][px] = ch;
+                    }
+                }
+            }
+        }
+
+        let mut out = String::new();
+        for row in grid {
+            let line: String = row.into_iter().collect();
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Carvable for HexMaze {
+    type Cell = HexCoord;
+
+    fn uncarved_directions(&self, c: HexCoord) -> Vec<u32> {
+        (0..constants::HEX_NUM_DIRECTIONS).filter(|&dir| {
+            self.get(c.neighbor(dir)).is_some_and(|cell| !cell.is_carved())
+        }).collect()
+    }
+
+    fn carve_toward(&mut self, c: HexCoord, dir: u32) -> Result<HexCoord, String> {
+        self.carve(c, dir).map_err(|e| e.to_string())?;
+        Ok(c.neighbor(dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::assert_matches_fixture;
+
+    #[test]
+    fn test_new_hex_maze() {
+        let maze = HexMaze::new(2);
+        assert_eq!(19, maze.cell_count());
+        assert_eq!(19, maze.coords().len());
+        assert!(maze.contains(HexCoord::new(2, -2)));
+        assert!(maze.contains(HexCoord::new(-2, 0)));
+        assert!(!maze.contains(HexCoord::new(2, 1)));
+        assert!(!maze.contains(HexCoord::new(-2, -1)));
+        assert!(maze.coords().iter().all(|&c| !maze.get(c).unwrap().is_carved()));
+        assert_eq!(1, HexMaze::new(0).cell_count());
+    }
+
+    #[test]
+    fn test_carve() {
+        let mut maze = HexMaze::new(2);
+        let center = HexCoord::new(0, 0);
+        for dir in 0..constants::HEX_NUM_DIRECTIONS {
+            maze.carve(center, dir).unwrap();
+            let other = maze.get(center.neighbor(dir)).unwrap();
+            assert!(!other.is_wall_present((dir + 3) % constants::HEX_NUM_DIRECTIONS));
+            assert_eq!(1, other.wall_present.iter().filter(|&&w| !w).count());
+        }
+        assert_eq!(6, maze.open_neighbors(center).len());
+
+        // The outer wall and anything outside the maze can't be carved
+        assert!(maze.carve(HexCoord::new(2, 0), constants::HEX_DIR_SOUTH_EAST).is_err());
+        assert!(maze.carve(HexCoord::new(3, 0), constants::HEX_DIR_NORTH_WEST).is_err());
+        assert!(maze.carve(center, constants::HEX_NUM_DIRECTIONS).is_err());
+    }
+
+    #[test]
+    fn test_full_connectivity() {
+        let mut maze = HexMaze::new(5);
+        maze.generate_perfect_seeded(12).unwrap();
+        let center = HexCoord::new(0, 0);
+        for c in maze.coords() {
+            let path = maze.solve(center, c).unwrap();
+            assert_eq!(c, path[path.len() - 1]);
+            assert!(path.windows(2).all(|step| maze.open_neighbors(step[0]).contains(&step[1])));
+        }
+
+        // A perfect maze has exactly one fewer passage than it has cells
+        let passages: usize = maze.coords().iter().map(|&c| maze.open_neighbors(c).len()).sum();
+        assert_eq!(maze.cell_count() - 1, passages / 2);
+    }
+
+    #[test]
+    fn test_wall_symmetry() {
+        let mut maze = HexMaze::new(4);
+        maze.generate_perfect_seeded(3).unwrap();
+        for c in maze.coords() {
+            let cell = maze.get(c).unwrap();
+            for dir in 0..constants::HEX_NUM_DIRECTIONS {
+                match maze.get(c.neighbor(dir)) {
+                    Some(other) => assert_eq!(cell.is_wall_present(dir),
+                                              other.is_wall_present((dir + 3) % constants::HEX_NUM_DIRECTIONS)),
+                    None => assert!(cell.is_wall_present(dir)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_golden_render() {
+        let mut maze = HexMaze::new(2);
+        maze.generate_perfect_seeded(1).unwrap();
+        assert_matches_fixture("hex_2_seed1.txt", &maze.render_ascii());
+    }
+}
@@ -0,0 +1,333 @@
+//! How a Maze was generated.  The seeded generators (and the std ones, which
+//! pick a random seed) record their seed and parameters on the Maze, so a
+//! maze can be reported and rebuilt later.  Mazes built by hand, loaded from
+//! a file or generated with a caller's random number generator have none.
+//! Neither do masked mazes, since the mask can't be recorded.
+//!
+//! The recorded info is written on a single line, like
+//! `seed=42 algorithm=growing_tree rooms=5,2,4,2,4 size=20x20 start=3,7`,
+//! which can be parsed back with `str::parse()`.  The rooms and start are
+//! only there when the Maze had them.
+use alloc::fmt;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use crate::error::MazeError;
+use crate::maze::symmetric::Symmetry;
+use crate::maze::{Coord, Maze};
+
+/// The generator that made a Maze, with any parameters it took.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GenerationAlgorithm {
+    /// generate_perfect() and generate(), which adds rooms.
+    GrowingTree,
+    /// generate_perfect_woven().
+    Woven,
+    /// generate_symmetric().
+    Symmetric(Symmetry),
+    /// generate_caves().
+    Caves { fill_prob: f32, smoothing_passes: u32 },
+    /// generate_perfect_max_run_seeded().
+    MaxRun { max_run_length: u32 },
+    /// generate_perfect_biased_seeded().
+    BiasToward { target: Coord, strength: f32 },
+}
+
+/// The seed and parameters a Maze was generated with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerationInfo {
+    pub seed: u64,
+    pub algorithm: GenerationAlgorithm,
+    /// The (count, min_x, max_x, min_y, max_y) rooms were asked for with.
+    pub rooms: Option<(u32, u32, u32, u32, u32)>,
+    /// The Maze's (cols, rows).
+    pub dimensions: (u32, u32),
+    /// The start Square chosen with Maze::set_start(), if there was one.
+    pub start: Option<Coord>,
+}
+
+impl GenerationInfo {
+    /// Generates the Maze this info describes again.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(12, 16);
+    /// maze.generate_seeded((3, 2, 3, 2, 3), 99).unwrap();
+    /// let line = maze.generation_params().unwrap().to_string();
+    ///
+    /// let info: mazegame::maze::info::GenerationInfo = line.parse().unwrap();
+    /// assert_eq!(maze, info.generate().unwrap());
+    /// ```
+    pub fn generate(&self) -> Result<Maze, MazeError> {
+        let (cols, rows) = self.dimensions;
+        let mut maze = Maze::new(rows, cols);
+        if let Some(start) = self.start {
+            maze.set_start(start.x, start.y)?;
+        }
+        let result = match (self.algorithm, self.rooms) {
+            (GenerationAlgorithm::GrowingTree, Some(rooms)) => maze.generate_seeded(rooms, self.seed),
+            (GenerationAlgorithm::GrowingTree, None) => maze.generate_perfect_seeded(self.seed),
+            (GenerationAlgorithm::Woven, _) => maze.generate_perfect_woven_seeded(self.seed),
+            (GenerationAlgorithm::Symmetric(symmetry), _) => maze.generate_symmetric_seeded(symmetry, self.seed),
+            (GenerationAlgorithm::Caves { fill_prob, smoothing_passes }, _) => {
+                maze.generate_caves(fill_prob, smoothing_passes, self.seed)
+            }
+            (GenerationAlgorithm::MaxRun { max_run_length }, _) => {
+                maze.generate_perfect_max_run_seeded(max_run_length, self.seed)
+            }
+            (GenerationAlgorithm::BiasToward { target, strength }, _) => {
+                maze.generate_perfect_biased_seeded(target, strength, self.seed)
+            }
+        };
+        result.map_err(MazeError::InvalidArgument)?;
+        Ok(maze)
+    }
+}
+
+/// Writes the algorithm the way the `algorithm=` field of the info line
+/// does, like `growing_tree` or `caves:0.45:4`.
+impl fmt::Display for GenerationAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GenerationAlgorithm::GrowingTree => write!(f, "growing_tree"),
+            GenerationAlgorithm::Woven => write!(f, "woven"),
+            GenerationAlgorithm::Symmetric(symmetry) => write!(f, "symmetric:{}", symmetry_name(symmetry)),
+            GenerationAlgorithm::Caves { fill_prob, smoothing_passes } => {
+                write!(f, "caves:{}:{}", fill_prob, smoothing_passes)
+            }
+            GenerationAlgorithm::MaxRun { max_run_length } => write!(f, "max_run:{}", max_run_length),
+            GenerationAlgorithm::BiasToward { target, strength } => {
+                write!(f, "bias_toward:{}:{}:{}", target.x, target.y, strength)
+            }
+        }
+    }
+}
+
+impl fmt::Display for GenerationInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "seed={} algorithm={}", self.seed, self.algorithm)?;
+        if let Some((count, min_x, max_x, min_y, max_y)) = self.rooms {
+            write!(f, " rooms={},{},{},{},{}", count, min_x, max_x, min_y, max_y)?;
+        }
+        write!(f, " size={}x{}", self.dimensions.0, self.dimensions.1)?;
+        if let Some(start) = self.start {
+            write!(f, " start={},{}", start.x, start.y)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GenerationInfo {
+    type Err = MazeError;
+
+    fn from_str(line: &str) -> Result<GenerationInfo, MazeError> {
+        let bad = |what: &str| MazeError::Parse(format!("bad {} in generation info '{}'", what, line));
+        let mut seed = None;
+        let mut algorithm = None;
+        let mut rooms = None;
+        let mut dimensions = None;
+        let mut start = None;
+        for field in line.split_whitespace() {
+            let (key, value) = match field.find('=') {
+                Some(i) => (&field[..i], &field[i + 1..]),
+                None => return Err(bad("field")),
+            };
+            match key {
+                "seed" => seed = Some(value.parse().map_err(|_| bad("seed"))?),
+                "algorithm" => algorithm = Some(parse_algorithm(value).ok_or_else(|| bad("algorithm"))?),
+                "rooms" => {
+                    let n: Vec<u32> = value.split(',').map(|v| v.parse()).collect::<Result<_, _>>()
+                                           .map_err(|_| bad("rooms"))?;
+                    if n.len() != 5 {
+                        return Err(bad("rooms"));
+                    }
+                    rooms = Some((n[0], n[1], n[2], n[3], n[4]));
+                }
+                "size" => {
+                    let n: Vec<u32> = value.split('x').map(|v| v.parse()).collect::<Result<_, _>>()
+                                           .map_err(|_| bad("size"))?;
+                    if n.len() != 2 {
+                        return Err(bad("size"));
+                    }
+                    dimensions = Some((n[0], n[1]));
+                }
+                "start" => {
+                    let n: Vec<u32> = value.split(',').map(|v| v.parse()).collect::<Result<_, _>>()
+                                           .map_err(|_| bad("start"))?;
+                    if n.len() != 2 {
+                        return Err(bad("start"));
+                    }
+                    start = Some(Coord::new(n[0], n[1]));
+                }
+                _ => return Err(bad("field")),
+            }
+        }
+        match (seed, algorithm, dimensions) {
+            (Some(seed), Some(algorithm), Some(dimensions)) => {
+                Ok(GenerationInfo { seed, algorithm, rooms, dimensions, start })
+            }
+            _ => Err(MazeError::Parse(format!("generation info '{}' needs a seed, algorithm and size", line))),
+        }
+    }
+}
+
+impl Maze {
+    /// Returns the seed the Maze was generated from, if it was recorded.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// assert_eq!(None, maze.seed());
+    /// maze.generate_perfect_seeded(42).unwrap();
+    /// assert_eq!(Some(42), maze.seed());
+    /// ```
+    pub fn seed(&self) -> Option<u64> {
+        self.generation.as_ref().map(|g| g.seed)
+    }
+
+    /// Returns how the Maze was generated, if it was recorded.
+    pub fn generation_params(&self) -> Option<&GenerationInfo> {
+        self.generation.as_ref()
+    }
+
+    //
+    // Internal - records a successful seeded generation.
+    //
+    pub(crate) fn record_generation(&mut self, seed: u64, algorithm: GenerationAlgorithm,
+                                    rooms: Option<(u32, u32, u32, u32, u32)>) {
+        self.generation = Some(GenerationInfo { seed, algorithm, rooms, dimensions: (self.cols, self.rows),
+                                                start: self.start });
+    }
+}
+
+//
+// Internal - the name a symmetry is written with.
+//
+fn symmetry_name(symmetry: Symmetry) -> &'static str {
+    match symmetry {
+        Symmetry::MirrorX => "mirror_x",
+        Symmetry::MirrorY => "mirror_y",
+        Symmetry::Rotate180 => "rotate_180",
+        Symmetry::Rotate90 => "rotate_90",
+    }
+}
+
+//
+// Internal - parses the value of an algorithm field.
+//
+fn parse_algorithm(value: &str) -> Option<GenerationAlgorithm> {
+    let parts: Vec<&str> = value.split(':').collect();
+    match parts[..] {
+        ["growing_tree"] => Some(GenerationAlgorithm::GrowingTree),
+        ["woven"] => Some(GenerationAlgorithm::Woven),
+        ["symmetric", name] => {
+            [Symmetry::MirrorX, Symmetry::MirrorY, Symmetry::Rotate180, Symmetry::Rotate90].iter()
+                .find(|&&s| symmetry_name(s) == name)
+                .map(|&s| GenerationAlgorithm::Symmetric(s))
+        }
+        ["caves", fill_prob, smoothing_passes] => Some(GenerationAlgorithm::Caves {
+            fill_prob: fill_prob.parse().ok()?,
+            smoothing_passes: smoothing_passes.parse().ok()?,
+        }),
+        ["max_run", max_run_length] => Some(GenerationAlgorithm::MaxRun { max_run_length: max_run_length.parse().ok()? }),
+        ["bias_toward", x, y, strength] => Some(GenerationAlgorithm::BiasToward {
+            target: Coord::new(x.parse().ok()?, y.parse().ok()?),
+            strength: strength.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorded() {
+        let mut maze = Maze::new(10, 14);
+        assert_eq!(None, maze.generation_params());
+        maze.generate_seeded((2, 2, 3, 2, 3), 425).unwrap();
+        let info = maze.generation_params().unwrap().clone();
+        assert_eq!(GenerationInfo { seed: 425, algorithm: GenerationAlgorithm::GrowingTree,
+                                    rooms: Some((2, 2, 3, 2, 3)), dimensions: (14, 10), start: None }, info);
+        assert_eq!("seed=425 algorithm=growing_tree rooms=2,2,3,2,3 size=14x10", info.to_string());
+        assert_eq!(maze, info.generate().unwrap());
+
+        maze.reset();
+        assert_eq!(None, maze.seed());
+
+        // Generating with a caller's rng can't be recorded
+        let mut other = Maze::new(10, 14);
+        other.generate_perfect_with_rng(&mut crate::rng::from_seed(1)).unwrap();
+        assert_eq!(None, other.generation_params());
+    }
+
+    #[test]
+    fn test_round_trip_every_algorithm() {
+        let mut mazes = Vec::new();
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_woven_seeded(3).unwrap();
+        mazes.push(maze);
+        let mut maze = Maze::new(8, 8);
+        maze.generate_symmetric_seeded(Symmetry::Rotate90, 4).unwrap();
+        mazes.push(maze);
+        let mut maze = Maze::new(12, 16);
+        maze.generate_caves(0.45, 3, 5).unwrap();
+        mazes.push(maze);
+        let mut maze = Maze::new(8, 8);
+        maze.generate_spanning_tree_seeded(6).unwrap();
+        mazes.push(maze);
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_max_run_seeded(2, 7).unwrap();
+        mazes.push(maze);
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_biased_seeded(Coord::new(7, 3), 0.65, 8).unwrap();
+        mazes.push(maze);
+        #[cfg(feature = "std")]
+        {
+            let mut maze = Maze::new(8, 8);
+            maze.generate_perfect().unwrap();
+            mazes.push(maze);
+        }
+
+        for maze in mazes.iter() {
+            let line = maze.generation_params().unwrap().to_string();
+            let info: GenerationInfo = line.parse().unwrap();
+            assert_eq!(maze.generation_params(), Some(&info), "{}", line);
+            assert_eq!(*maze, info.generate().unwrap(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_start_is_replayed() {
+        let mut maze = Maze::new(9, 11);
+        maze.set_start(8, 5).unwrap();
+        maze.generate_perfect_seeded(490).unwrap();
+        let line = maze.generation_params().unwrap().to_string();
+        assert_eq!("seed=490 algorithm=growing_tree size=11x9 start=8,5", line);
+        let info: GenerationInfo = line.parse().unwrap();
+        assert_eq!(Some(Coord::new(8, 5)), info.start);
+        let again = info.generate().unwrap();
+        assert_eq!(maze, again);
+        assert_eq!(Coord::new(8, 5), again.get_start());
+
+        // The start changes the maze, so dropping it doesn't give the same one
+        let mut plain = info.clone();
+        plain.start = None;
+        assert_ne!(maze, plain.generate().unwrap());
+        plain.start = Some(Coord::new(11, 0));
+        assert!(plain.generate().is_err());
+    }
+
+    #[test]
+    fn test_bad_lines() {
+        for line in ["", "seed=1 size=4x4", "seed=x algorithm=woven size=4x4", "seed=1 algorithm=prim size=4x4",
+                     "seed=1 algorithm=woven size=4", "seed=1 algorithm=woven rooms=1,2 size=4x4",
+                     "seed=1 algorithm=symmetric:sideways size=4x4", "seed=1 algorithm=max_run:x size=4x4",
+                     "seed=1 algorithm=bias_toward:3:3 size=4x4",
+                     "seed=1 algorithm=woven size=4x4 colour", "seed=1 algorithm=woven size=4x4 start=1",
+                     "seed=1 algorithm=woven size=4x4 start=1,x"].iter() {
+            assert!(line.parse::<GenerationInfo>().is_err(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Exporting per-Square analysis as CSV, for loading into spreadsheets or
+//! notebooks.  Each row is one Square, in row-major order, with its x and y
+//! followed by a column for each layer asked for:
+//!
+//! ```text
+//! x,y,walls,distance
+//! 0,0,10,0
+//! 1,0,7,1
+//! ```
+use std::io::Write;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::heatmap::WalkPolicy;
+use crate::maze::Maze;
+
+/// A column of export_layers_csv().
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    /// The walls still standing, with bit n set for a wall in direction n.
+    Walls,
+    /// The Square's id, as carved.
+    Id,
+    /// Steps from the entrance, or empty for Squares it can't reach.  Needs
+    /// an entrance.
+    Distance,
+    /// 1 for a dead end, a Square that leads to exactly one other, else 0.
+    DeadEnd,
+    /// Visits from traversal_heatmap() with these settings.  Needs at least
+    /// one trial.
+    Heat { trials: u32, seed: u64, policy: WalkPolicy },
+    /// The id of the room the Square is in, or 0 if it isn't in one.
+    Room,
+}
+
+impl Layer {
+    /// Returns the layer's column header.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Layer::Walls => "walls",
+            Layer::Id => "id",
+            Layer::Distance => "distance",
+            Layer::DeadEnd => "dead_end",
+            Layer::Heat { .. } => "heat",
+            Layer::Room => "room",
+        }
+    }
+}
+
+impl Maze {
+    /// Writes a CSV table to w with a row for each Square: its x and y, then
+    /// a column for each of layers, in order.  The first row holds the
+    /// column names.  Fails without writing anything if a layer's
+    /// prerequisite is missing, like an entrance for Layer::Distance.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::layers::Layer;
+    ///
+    /// let maze = mazegame::Maze::from_compact("4cc8").unwrap();
+    /// let mut out = Vec::new();
+    /// maze.export_layers_csv(&mut out, &[Layer::DeadEnd]).unwrap();
+    /// assert_eq!("x,y,dead_end\n0,0,1\n1,0,0\n2,0,0\n3,0,1\n", String::from_utf8(out).unwrap());
+    /// ```
+    pub fn export_layers_csv<W: Write>(&self, w: &mut W, layers: &[Layer]) -> Result<(), MazeError> {
+        // Work out every column first, so a bad layer writes nothing
+        let mut columns: Vec<Vec<String>> = Vec::with_capacity(layers.len());
+        for layer in layers.iter() {
+            columns.push(self.layer_column(layer)?);
+        }
+
+        let mut header = String::from("x,y");
+        for layer in layers.iter() {
+            header.push(',');
+            header.push_str(layer.name());
+        }
+        writeln!(w, "{}", header)?;
+        for i in 0..self.sq.len() {
+            let c = self.get_coord(i);
+            let mut row = format!("{},{}", c.x, c.y);
+            for column in columns.iter() {
+                row.push(',');
+                row.push_str(&column[i]);
+            }
+            writeln!(w, "{}", row)?;
+        }
+        Ok(())
+    }
+
+    //
+    // Internal - the values of one layer, indexed like `sq`.
+    //
+    fn layer_column(&self, layer: &Layer) -> Result<Vec<String>, MazeError> {
+        Ok(match *layer {
+            Layer::Walls => self.sq.iter()
+                .map(|sq| {
+                    let walls = (0..constants::NUM_DIRECTIONS)
+                        .filter(|&dir| sq.is_wall_present(dir))
+                        .fold(0, |mask, dir| mask | 1 << dir);
+                    walls.to_string()
+                })
+                .collect(),
+            Layer::Id => self.sq.iter().map(|sq| sq.id.to_string()).collect(),
+            Layer::Distance => {
+                let entrance = self.get_entrance().ok_or_else(|| {
+                    MazeError::InvalidArgument("the distance layer needs the maze to have an entrance".to_string())
+                })?;
+                self.distances(entrance.coord).iter()
+                    .map(|d| d.map(|d| d.to_string()).unwrap_or_default())
+                    .collect()
+            }
+            Layer::DeadEnd => (0..self.sq.len())
+                .map(|i| {
+                    let c = self.get_coord(i);
+                    if self.open_neighbors(c.x, c.y).len() == 1 { "1" } else { "0" }.to_string()
+                })
+                .collect(),
+            Layer::Heat { trials, seed, policy } => {
+                if trials == 0 {
+                    return Err(MazeError::InvalidArgument("the heat layer needs at least one trial".to_string()));
+                }
+                self.traversal_heatmap(trials, seed, policy).iter().map(|h| h.to_string()).collect()
+            }
+            Layer::Room => self.sq.iter()
+                .map(|sq| if sq.is_part_of_room() { sq.id } else { 0 }.to_string())
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::DIR_NORTH;
+
+    #[test]
+    fn test_export() {
+        // A 2x2 maze: a U open at the top left
+        let mut maze = Maze::from_compact("22\n59").unwrap();
+        maze.set_entrance(0, 0, DIR_NORTH).unwrap();
+        let mut out = Vec::new();
+        maze.export_layers_csv(&mut out, &[Layer::Walls, Layer::Distance]).unwrap();
+        assert_eq!("x,y,walls,distance\n\
+                    0,0,12,0\n\
+                    1,0,13,3\n\
+                    0,1,10,1\n\
+                    1,1,6,2\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_rooms_and_heat() {
+        let mut maze = Maze::new(2, 2);
+        maze.carve(0, 0, crate::constants::DIR_EAST, 2, false).unwrap();
+        maze.num_rooms = 2;
+        let mut out = Vec::new();
+        let heat = Layer::Heat { trials: 10, seed: 1, policy: WalkPolicy::SolvePairs };
+        maze.export_layers_csv(&mut out, &[Layer::Id, Layer::Room, heat]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!("x,y,id,room,heat", lines[0]);
+        assert!(lines[1].starts_with("0,0,2,2,"));
+        assert_eq!("0,1,0,0,0", lines[3]);
+        assert_eq!(5, lines.len());
+    }
+
+    #[test]
+    fn test_missing_prerequisites() {
+        let maze = Maze::from_compact("4cc8").unwrap();
+        let mut out = Vec::new();
+        let err = maze.export_layers_csv(&mut out, &[Layer::Walls, Layer::Distance]).unwrap_err();
+        assert!(err.to_string().contains("distance layer"), "{}", err);
+        assert!(out.is_empty());
+
+        let heat = Layer::Heat { trials: 0, seed: 1, policy: WalkPolicy::SolvePairs };
+        let err = maze.export_layers_csv(&mut out, &[heat]).unwrap_err();
+        assert!(err.to_string().contains("heat layer"), "{}", err);
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+//! Everything a level puts into a maze - items, enemies, traps, locked
+//! doors and pressure plates - worked out from a single level seed, so one
+//! number reproduces the whole populated level.
+//!
+//! Each placement system gets its own seed, split off the level seed, so
+//! changing how many of one thing there are doesn't move any of the
+//! others.  A system's seed is the 64-bit FNV-1a hash of these bytes, in
+//! order:
+//!
+//! ```text
+//! "mazegame-layout-v1"       the ASCII bytes, no terminator
+//! level seed                 u64, little-endian
+//! system                     the ASCII bytes of its name in LAYOUT_SYSTEMS
+//! ```
+//!
+//! The systems run in the order of LAYOUT_SYSTEMS, and when two want the
+//! same Square, the one that ran first keeps it.  Any change to the scheme
+//! or the order has to change the version in the prefix.
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::maze::fingerprint::fnv1a;
+use crate::maze::plates::PlateSetup;
+use crate::maze::room::Door;
+use crate::maze::spawn::SpawnConstraints;
+use crate::maze::traps::Traps;
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// The bytes every placement system's seed's hash starts with.
+pub const LAYOUT_SCHEME: &str = "mazegame-layout-v1";
+
+/// The placement systems, in the order they run.
+pub const LAYOUT_SYSTEMS: [&str; 5] = ["locks", "items", "enemies", "traps", "plates"];
+
+/// What to put into a level.  The defaults put nothing in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayoutConfig {
+    /// How many of the Maze's doors to lock.
+    pub locked_doors: u32,
+    /// How many items to place, and the rules for where.
+    pub items: u32,
+    pub item_constraints: SpawnConstraints,
+    /// How many enemies to place, and the rules for where.
+    pub enemies: u32,
+    pub enemy_constraints: SpawnConstraints,
+    /// How many traps to place.  Traps go on corridor Squares, never on the
+    /// entrance, the exit, an item or an enemy.
+    pub traps: u32,
+    /// How many pressure plates to place, each with the door it opens and
+    /// a block to hold it down.  Plates need the Maze to have an entrance
+    /// and an exit, and are placed so the exit can still be reached.
+    pub plates: u32,
+}
+
+/// A populated level, for the game loop to set up from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelLayout {
+    /// The level seed it was worked out from.
+    pub seed: u64,
+    /// The doors to lock, in the order the Maze stores them.
+    pub locked_doors: Vec<Door>,
+    /// The items' Squares, in the order they were picked.
+    pub items: Vec<Coord>,
+    /// The enemies' Squares, in the order they were picked.
+    pub enemies: Vec<Coord>,
+    pub traps: Traps,
+    pub plates: PlateSetup,
+    /// Whether everything the LayoutConfig asked for fit.
+    pub complete: bool,
+}
+
+/// Returns the seed the placement system named system gets from level seed
+/// seed, using the scheme described in the module docs.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::layout::layout_seed;
+///
+/// assert_eq!(layout_seed(7, "items"), layout_seed(7, "items"));
+/// assert_ne!(layout_seed(7, "items"), layout_seed(7, "enemies"));
+/// assert_ne!(layout_seed(7, "items"), layout_seed(8, "items"));
+/// ```
+pub fn layout_seed(seed: u64, system: &str) -> u64 {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(LAYOUT_SCHEME.as_bytes());
+    bytes.extend_from_slice(&seed.to_le_bytes());
+    bytes.extend_from_slice(system.as_bytes());
+    fnv1a(&bytes)
+}
+
+impl LevelLayout {
+    /// Works out the layout of maze from seed.  The same Maze, seed and
+    /// config always give the same layout.
+    ///
+    /// Enemies that would share a Square with an item are skipped, and the
+    /// next ones picked take their places, so a clash never costs an enemy
+    /// while there's room for it elsewhere.  The Maze isn't changed; lock
+    /// the doors with set_door_locked() if they should be saved with it.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::layout::{LayoutConfig, LevelLayout};
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(16, 16);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let config = LayoutConfig { items: 5, enemies: 3, traps: 4, ..Default::default() };
+    /// let layout = LevelLayout::from_seed(&maze, 99, &config);
+    /// assert!(layout.complete);
+    /// assert_eq!(layout, LevelLayout::from_seed(&maze, 99, &config));
+    /// ```
+    pub fn from_seed(maze: &Maze, seed: u64, config: &LayoutConfig) -> LevelLayout {
+        let mut complete = true;
+
+        let mut doors: Vec<usize> = (0..maze.doors().len()).collect();
+        rng::from_seed(layout_seed(seed, LAYOUT_SYSTEMS[0])).shuffle(&mut doors);
+        doors.truncate(config.locked_doors as usize);
+        doors.sort_unstable();
+        let locked_doors: Vec<Door> = doors.iter().map(|&i| Door { locked: true, ..maze.doors()[i] }).collect();
+        complete &= locked_doors.len() as u32 == config.locked_doors;
+
+        let (items, all_items) = maze.spawn_points(config.items, &config.item_constraints,
+                                                   layout_seed(seed, LAYOUT_SYSTEMS[1]));
+        complete &= all_items;
+
+        // Picking more than needed gives the same first picks, so the extras
+        // stand in for any that land on an item
+        let (picked, _) = maze.spawn_points(config.enemies + items.len() as u32, &config.enemy_constraints,
+                                            layout_seed(seed, LAYOUT_SYSTEMS[2]));
+        let enemies: Vec<Coord> = picked.into_iter()
+            .filter(|c| !items.contains(c))
+            .take(config.enemies as usize)
+            .collect();
+        complete &= enemies.len() as u32 == config.enemies;
+
+        let mut avoid: Vec<Coord> = items.iter().chain(enemies.iter()).cloned().collect();
+        avoid.extend(maze.get_entrance().iter().chain(maze.exits().iter()).map(|o| o.coord));
+        let traps = Traps::place(maze, config.traps, layout_seed(seed, LAYOUT_SYSTEMS[3]), &avoid);
+        complete &= traps.count() == config.traps;
+
+        let plates = match (maze.get_entrance(), maze.get_exit()) {
+            (Some(entrance), Some(exit)) if config.plates > 0 => {
+                avoid.extend(traps.coords());
+                let (plates, all_plates) = PlateSetup::place(maze, config.plates, entrance.coord, exit.coord,
+                                                             layout_seed(seed, LAYOUT_SYSTEMS[4]), &avoid);
+                complete &= all_plates;
+                plates
+            }
+            _ => {
+                complete &= config.plates == 0;
+                PlateSetup::default()
+            }
+        };
+
+        LevelLayout { seed, locked_doors, items, enemies, traps, plates, complete }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+    use crate::maze::spawn::SpawnArea;
+    use crate::maze::test_mazes::dungeon;
+
+    fn config() -> LayoutConfig {
+        LayoutConfig {
+            locked_doors: 2,
+            items: 8,
+            item_constraints: SpawnConstraints { area: SpawnArea::RoomsOnly, ..Default::default() },
+            enemies: 10,
+            enemy_constraints: SpawnConstraints { min_entrance_distance: 10, ..Default::default() },
+            traps: 12,
+            plates: 0,
+        }
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let maze = dungeon(6, 477);
+        assert!(maze.doors().len() > 2);
+        let layout = LevelLayout::from_seed(&maze, 477, &config());
+        assert!(layout.complete);
+        assert_eq!(layout, LevelLayout::from_seed(&maze, 477, &config()));
+        assert_eq!(2, layout.locked_doors.len());
+        assert!(layout.locked_doors.iter().all(|d| d.locked && maze.doors().iter().any(|m| m.coord == d.coord)));
+
+        let other = LevelLayout::from_seed(&maze, 478, &config());
+        assert_ne!(layout, other);
+        assert_ne!(layout.items, other.items);
+        assert_ne!(layout.enemies, other.enemies);
+        assert_ne!(layout.traps, other.traps);
+    }
+
+    #[test]
+    fn test_no_clashes() {
+        let maze = dungeon(6, 477);
+        for seed in 0..20 {
+            let layout = LevelLayout::from_seed(&maze, seed, &config());
+            assert_eq!(config().enemies as usize, layout.enemies.len());
+            assert!(layout.enemies.iter().all(|c| !layout.items.contains(c)), "seed {}", seed);
+            for c in layout.items.iter().chain(layout.enemies.iter()) {
+                assert!(!layout.traps.is_trapped(*c));
+            }
+            assert!(!layout.traps.is_trapped(Coord::new(0, 0)));
+            assert!(!layout.traps.is_trapped(Coord::new(29, 29)));
+        }
+    }
+
+    #[test]
+    fn test_systems_independent() {
+        // More enemies don't move the items or the locked doors
+        let maze = dungeon(6, 477);
+        let layout = LevelLayout::from_seed(&maze, 5, &config());
+        let crowded = LevelLayout::from_seed(&maze, 5, &LayoutConfig { enemies: 20, ..config() });
+        assert_eq!(layout.items, crowded.items);
+        assert_eq!(layout.locked_doors, crowded.locked_doors);
+        assert_eq!(layout.enemies[..], crowded.enemies[..10]);
+
+        // Asking for more than fits
+        let layout = LevelLayout::from_seed(&maze, 5, &LayoutConfig { locked_doors: 1000, ..config() });
+        assert!(!layout.complete);
+        assert_eq!(maze.doors().len(), layout.locked_doors.len());
+    }
+
+    #[test]
+    fn test_plates() {
+        let mut maze = Maze::new(16, 16);
+        maze.generate_perfect_seeded(489).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        maze.set_exit(15, 15, constants::DIR_SOUTH).unwrap();
+        let config = LayoutConfig { items: 4, traps: 4, plates: 1, ..Default::default() };
+        let layout = LevelLayout::from_seed(&maze, 489, &config);
+        assert!(layout.complete);
+        assert_eq!(1, layout.plates.links.len());
+        let plain = LevelLayout::from_seed(&maze, 489, &LayoutConfig { plates: 0, ..config.clone() });
+        assert_eq!(layout.items, plain.items);
+        assert_eq!(layout.traps, plain.traps);
+
+        // Nothing shares a Square with a block or plate
+        let plate = layout.plates.links[0].plate;
+        for c in layout.plates.blocks.iter().chain(Some(&plate)) {
+            assert!(!layout.items.contains(c) && !layout.traps.is_trapped(*c));
+        }
+        let moves = maze.solve_with_plates(&layout.plates, Coord::new(0, 0), Coord::new(15, 15)).unwrap();
+        assert!(moves.is_some());
+
+        // Without an exit there's nowhere for them to lead
+        let mut open = Maze::new(16, 16);
+        open.generate_perfect_seeded(489).unwrap();
+        open.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        assert!(!LevelLayout::from_seed(&open, 489, &config).complete);
+    }
+}
@@ -0,0 +1,327 @@
+//! Masks, for mazes that fill a shape instead of the whole grid.  Squares
+//! outside the mask are never carved and keep all four walls.  Each
+//! separate patch of the mask gets a maze of its own.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use rand::Rng;
+#[cfg(feature = "image")]
+use std::path::Path;
+
+use crate::error::MazeError;
+use crate::maze::generator::{self, Carvable};
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// Patches of a mask smaller than this many Squares are dropped when a mask
+/// is built from text or an image, so stray pixels don't turn into tiny
+/// mazes of their own.
+pub const MIN_MASK_REGION: u32 = 2;
+
+/// Which Squares of a grid are inside a shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    rows: u32,
+    cols: u32,
+    inside: Vec<bool>,
+}
+
+impl Mask {
+    /// Creates a mask with every Square inside.
+    pub fn new(rows: u32, cols: u32) -> Mask {
+        Mask { rows, cols, inside: vec![true; (rows * cols) as usize] }
+    }
+
+    /// Builds a mask from lines of `#` (inside) and `.` (outside).  Every
+    /// line must be the same length.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::mask::Mask;
+    ///
+    /// let mask = Mask::from_ascii("##.\n.##\n").unwrap();
+    /// assert_eq!((3, 2), (mask.get_cols(), mask.get_rows()));
+    /// assert!(mask.is_inside(0, 0));
+    /// assert!(!mask.is_inside(2, 0));
+    /// ```
+    pub fn from_ascii(text: &str) -> Result<Mask, MazeError> {
+        let lines: Vec<&str> = text.lines().map(|l| l.trim_end()).filter(|l| !l.is_empty()).collect();
+        let cols = match lines.first() {
+            Some(line) => line.chars().count() as u32,
+            None => return Err(MazeError::Parse("a mask needs at least one line".to_string())),
+        };
+        let mut inside = Vec::with_capacity(lines.len() * cols as usize);
+        for (y, line) in lines.iter().enumerate() {
+            if line.chars().count() as u32 != cols {
+                return Err(MazeError::Parse(format!("mask line {} isn't {} characters long", y + 1, cols)));
+            }
+            for c in line.chars() {
+                inside.push(match c {
+                    '#' => true,
+                    '.' => false,
+                    _ => return Err(MazeError::Parse(format!("unexpected '{}' in mask line {}", c, y + 1))),
+                });
+            }
+        }
+        let mut mask = Mask { rows: lines.len() as u32, cols, inside };
+        mask.drop_small_regions(MIN_MASK_REGION);
+        Ok(mask)
+    }
+
+    /// Builds a mask from an image file, scaled to `cols` x `rows` Squares.
+    /// Squares whose grayscale brightness (0 to 255) is below `threshold`
+    /// are inside, so the shape is drawn dark on a light background.
+    #[cfg(feature = "image")]
+    pub fn from_image<P: AsRef<Path>>(path: P, cols: u32, rows: u32, threshold: u8) -> Result<Mask, MazeError> {
+        if cols == 0 || rows == 0 {
+            return Err(MazeError::InvalidArgument(format!("can't scale an image to {}x{}", cols, rows)));
+        }
+        let image = ::image::open(path).map_err(|e| match e {
+            ::image::ImageError::IoError(e) => MazeError::from(e),
+            e => MazeError::Parse(e.to_string()),
+        })?;
+        let gray = ::image::imageops::resize(&image.to_luma8(), cols, rows, ::image::imageops::FilterType::Triangle);
+        let inside = gray.pixels().map(|p| p.0[0] < threshold).collect();
+        let mut mask = Mask { rows, cols, inside };
+        mask.drop_small_regions(MIN_MASK_REGION);
+        Ok(mask)
+    }
+
+    /// Returns the number of rows in the mask.
+    pub fn get_rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Returns the number of columns in the mask.
+    pub fn get_cols(&self) -> u32 {
+        self.cols
+    }
+
+    /// Returns whether (x, y) is inside the mask.  Squares off the edge of
+    /// the mask are outside.
+    pub fn is_inside(&self, x: u32, y: u32) -> bool {
+        x < self.cols && y < self.rows && self.inside[(y * self.cols + x) as usize]
+    }
+
+    /// Puts (x, y) inside or outside the mask.
+    pub fn set_inside(&mut self, x: u32, y: u32, inside: bool) -> Result<(), MazeError> {
+        if x >= self.cols || y >= self.rows {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        self.inside[(y * self.cols + x) as usize] = inside;
+        Ok(())
+    }
+
+    /// Returns the number of Squares inside the mask.
+    pub fn count(&self) -> u32 {
+        self.inside.iter().filter(|&&i| i).count() as u32
+    }
+
+    /// Moves every patch of the mask smaller than `min_size` Squares outside
+    /// it.  Returns the number of Squares removed.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::mask::Mask;
+    ///
+    /// let mut mask = Mask::from_ascii("###.#\n###..\n").unwrap();
+    /// assert_eq!(6, mask.count());
+    /// assert_eq!(6, mask.drop_small_regions(7));
+    /// ```
+    pub fn drop_small_regions(&mut self, min_size: u32) -> u32 {
+        let mut dropped = 0;
+        for region in self.regions() {
+            if (region.len() as u32) < min_size {
+                for c in region.iter() {
+                    self.inside[(c.y * self.cols + c.x) as usize] = false;
+                }
+                dropped += region.len() as u32;
+            }
+        }
+        dropped
+    }
+
+    //
+    // Internal - returns each patch of Squares inside the mask, joined
+    // north, south, east and west.
+    //
+    fn regions(&self) -> Vec<Vec<Coord>> {
+        let mut seen = vec![false; self.inside.len()];
+        let mut regions = Vec::new();
+        for start in 0..self.inside.len() {
+            if !self.inside[start] || seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut region = Vec::new();
+            let mut stack = vec![Coord::new(start as u32 % self.cols, start as u32 / self.cols)];
+            while let Some(c) = stack.pop() {
+                region.push(c);
+                let steps = [(c.x, c.y.wrapping_sub(1)), (c.x, c.y + 1), (c.x + 1, c.y), (c.x.wrapping_sub(1), c.y)];
+                for &(x, y) in steps.iter() {
+                    if self.is_inside(x, y) && !seen[(y * self.cols + x) as usize] {
+                        seen[(y * self.cols + x) as usize] = true;
+                        stack.push(Coord::new(x, y));
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        regions
+    }
+}
+
+impl Maze {
+    /// Generates a perfect maze inside each patch of a mask from a seed.
+    /// The mask must be the same size as the Maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::mask::Mask;
+    ///
+    /// let mask = Mask::from_ascii("####\n##..\n##..\n").unwrap();
+    /// let mut maze = mazegame::Maze::new(3, 4);
+    /// maze.generate_masked_seeded(&mask, 1).unwrap();
+    /// assert!(!maze.sq[10].is_carved());
+    /// ```
+    pub fn generate_masked_seeded(&mut self, mask: &Mask, seed: u64) -> Result<(), String> {
+        self.generate_masked_with_rng(mask, &mut rng::from_seed(seed))
+    }
+
+    /// Generates a perfect maze inside each patch of a mask using the
+    /// supplied random number generator.
+    pub fn generate_masked_with_rng<R: Rng>(&mut self, mask: &Mask, rng: &mut R) -> Result<(), String> {
+        if (mask.cols, mask.rows) != (self.cols, self.rows) {
+            return Err(format!("A {}x{} mask doesn't fit a {}x{} maze", mask.cols, mask.rows, self.cols, self.rows));
+        }
+        let regions = mask.regions();
+        let mut carved = 0;
+        let mut masked = Masked { maze: self, mask };
+        for region in regions.iter() {
+            let start = region[0];
+            if !masked.uncarved_directions(start).is_empty() {
+                carved += generator::growing_tree(&mut masked, start, rng)?.carved;
+            }
+        }
+        gen_debug!("masked regions={} carved={}", regions.len(), carved);
+        Ok(())
+    }
+}
+
+//
+// Internal - a Maze being carved only inside a mask.
+//
+struct Masked<'a> {
+    maze: &'a mut Maze,
+    mask: &'a Mask,
+}
+
+impl<'a> Carvable for Masked<'a> {
+    type Cell = Coord;
+
+    fn uncarved_directions(&self, cell: Coord) -> Vec<u32> {
+        let mut directions = self.maze.uncarved_directions(cell);
+        directions.retain(|&dir| match self.maze.neighbor(cell.x, cell.y, dir) {
+            Some(c) => self.mask.is_inside(c.x, c.y),
+            None => false,
+        });
+        directions
+    }
+
+    fn carve_toward(&mut self, cell: Coord, dir: u32) -> Result<Coord, String> {
+        self.maze.carve_toward(cell, dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    const RING: &str = "..######..\n\
+                        .########.\n\
+                        ###....###\n\
+                        ##..#...##\n\
+                        ##......##\n\
+                        ###....###\n\
+                        .########.\n\
+                        ..######..\n";
+
+    // Whether every Square inside the mask can reach every other one in its
+    // patch, and nothing outside it was touched
+    fn check_masked(maze: &Maze, mask: &Mask) {
+        for y in 0..mask.get_rows() {
+            for x in 0..mask.get_cols() {
+                let sq = &maze.sq[maze.get_offset(x, y)];
+                if !mask.is_inside(x, y) {
+                    assert!(!sq.is_carved(), "({}, {}) is outside the mask but carved", x, y);
+                    assert_eq!(constants::ID_UNCARVED, sq.id);
+                }
+            }
+        }
+        for region in mask.regions() {
+            for &c in region.iter() {
+                assert!(maze.solve(region[0], c).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_ring() {
+        let mask = Mask::from_ascii(RING).unwrap();
+        // The lone Square in the middle is dropped
+        assert!(!mask.is_inside(4, 3));
+        assert_eq!(1, mask.regions().len());
+
+        let mut maze = Maze::new(8, 10);
+        maze.generate_masked_seeded(&mask, 418).unwrap();
+        check_masked(&maze, &mask);
+        assert!(maze.walls_consistent());
+
+        // One fewer passage than Squares: a perfect maze around the ring
+        let passages: usize = mask.regions()[0].iter().map(|c| maze.open_neighbors(c.x, c.y).len()).sum();
+        assert_eq!(mask.count() as usize - 1, passages / 2);
+    }
+
+    #[test]
+    fn test_separate_regions() {
+        let mut mask = Mask::from_ascii(RING).unwrap();
+        for &(x, y) in [(4, 3), (5, 3), (4, 4), (5, 4)].iter() {
+            mask.set_inside(x, y, true).unwrap();
+        }
+        assert_eq!(2, mask.regions().len());
+
+        let mut maze = Maze::new(8, 10);
+        maze.generate_masked_seeded(&mask, 3).unwrap();
+        check_masked(&maze, &mask);
+        assert_eq!(None, maze.solve(Coord::new(4, 3), Coord::new(4, 0)));
+
+        assert!(Maze::new(8, 9).generate_masked_seeded(&mask, 3).is_err());
+    }
+
+    #[test]
+    fn test_bad_ascii() {
+        assert!(Mask::from_ascii("").is_err());
+        assert!(Mask::from_ascii("##\n#\n").is_err());
+        assert!(Mask::from_ascii("#x\n").is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_from_image() {
+        // A dark disc on a white background, at twice the maze's size
+        let image = ::image::GrayImage::from_fn(40, 40, |x, y| {
+            let (dx, dy) = (x as i32 - 20, y as i32 - 20);
+            ::image::Luma([if dx * dx + dy * dy < 15 * 15 { 0 } else { 255 }])
+        });
+        let path = ::std::env::temp_dir().join("mazegame_mask_test.png");
+        image.save(&path).unwrap();
+        let mask = Mask::from_image(&path, 20, 20, 128).unwrap();
+        ::std::fs::remove_file(&path).unwrap();
+
+        assert!(mask.is_inside(10, 10));
+        assert!(!mask.is_inside(0, 0));
+        assert!(!mask.is_inside(19, 19));
+        assert_eq!(1, mask.regions().len());
+        assert!(matches!(Mask::from_image("/no/such/mask.png", 20, 20, 128), Err(MazeError::Io(_))));
+    }
+}
@@ -0,0 +1,343 @@
+//! Multi-level mazes.  A Maze3d is a stack of equally sized Mazes, with
+//! stairwells joining Squares on neighboring levels.  Stairs extend the
+//! usual four directions with `constants::DIR_UP` and `constants::DIR_DOWN`.
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::Maze;
+use crate::rng;
+
+/// An (x, y, z) position in a Maze3d.  z is the level, counting up from 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Coord3d {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+impl Coord3d {
+    /// Creates a new Coord3d.
+    pub fn new(x: u32, y: u32, z: u32) -> Coord3d {
+        Coord3d { x, y, z }
+    }
+}
+
+/// A maze made of `depth` levels joined by stairs.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Maze3d {
+    levels: Vec<Maze>,
+    // Whether each Square has stairs up and down, indexed by level and then
+    // by the Square's offset within the level.
+    stairs: Vec<[bool; 2]>,
+}
+
+impl Maze3d {
+    /// Creates a new, uncarved maze of `depth` levels, each `rows` by `cols`
+    /// Squares.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::maze::maze3d::Maze3d::new(3, 4, 5);
+    /// assert_eq!(3, maze.get_depth());
+    /// assert_eq!(5, maze.level(0).get_cols());
+    /// ```
+    pub fn new(depth: u32, rows: u32, cols: u32) -> Maze3d {
+        Maze3d {
+            levels: (0..depth).map(|_| Maze::new(rows, cols)).collect(),
+            stairs: vec![[false; 2]; (depth * rows * cols) as usize],
+        }
+    }
+
+    /// Returns the number of levels in the maze.
+    pub fn get_depth(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    /// Returns one level of the maze.  Panics if there's no level `z`.
+    pub fn level(&self, z: u32) -> &Maze {
+        &self.levels[z as usize]
+    }
+
+    /// Returns whether (x, y, z) lies inside the maze.
+    pub fn in_bounds(&self, c: Coord3d) -> bool {
+        c.z < self.get_depth() && self.levels[c.z as usize].in_bounds(c.x, c.y)
+    }
+
+    // Internal: Calculates the offset of a Square in the stair vector.
+    fn get_offset(&self, c: Coord3d) -> usize {
+        let level = &self.levels[c.z as usize];
+        (c.z * level.get_rows() * level.get_cols()) as usize + level.get_offset(c.x, c.y)
+    }
+
+    /// Returns whether the Square at `c` has stairs leading in direction
+    /// `dir` (`constants::DIR_UP` or `constants::DIR_DOWN`).
+    pub fn has_stairs(&self, c: Coord3d, dir: u32) -> bool {
+        match dir {
+            constants::DIR_UP | constants::DIR_DOWN if self.in_bounds(c) => {
+                self.stairs[self.get_offset(c)][(dir - constants::DIR_UP) as usize]
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the Square next to `c` in direction dir, or None if the step
+    /// leaves the maze.
+    pub fn neighbor(&self, c: Coord3d, dir: u32) -> Option<Coord3d> {
+        if !self.in_bounds(c) {
+            return None;
+        }
+        match dir {
+            constants::DIR_UP if c.z + 1 < self.get_depth() => Some(Coord3d::new(c.x, c.y, c.z + 1)),
+            constants::DIR_DOWN if c.z > 0 => Some(Coord3d::new(c.x, c.y, c.z - 1)),
+            constants::DIR_UP | constants::DIR_DOWN => None,
+            _ => self.levels[c.z as usize].neighbor(c.x, c.y, dir).map(|n| Coord3d::new(n.x, n.y, c.z)),
+        }
+    }
+
+    /// Opens a passage from `c` in direction dir.  The four flat directions
+    /// carve within the level; DIR_UP and DIR_DOWN add a stairwell, with
+    /// stairs on both of the levels it joins.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::constants;
+    /// use mazegame::maze::maze3d::{Coord3d, Maze3d};
+    ///
+    /// let mut maze = Maze3d::new(2, 3, 3);
+    /// maze.carve(Coord3d::new(1, 1, 0), constants::DIR_UP).unwrap();
+    /// assert!(maze.has_stairs(Coord3d::new(1, 1, 1), constants::DIR_DOWN));
+    /// assert!(maze.carve(Coord3d::new(1, 1, 1), constants::DIR_UP).is_err());
+    /// ```
+    pub fn carve(&mut self, c: Coord3d, dir: u32) -> Result<(), MazeError> {
+        if !self.in_bounds(c) {
+            return Err(MazeError::InvalidArgument(format!("({}, {}, {}) is outside of the maze", c.x, c.y, c.z)));
+        }
+        match dir {
+            constants::DIR_UP | constants::DIR_DOWN => {
+                let dest = self.neighbor(c, dir).ok_or_else(|| {
+                    MazeError::InvalidArgument(format!("no level for stairs from ({}, {}, {})", c.x, c.y, c.z))
+                })?;
+                let (from, to) = if dir == constants::DIR_UP { (c, dest) } else { (dest, c) };
+                let (from, to) = (self.get_offset(from), self.get_offset(to));
+                self.stairs[from][0] = true;
+                self.stairs[to][1] = true;
+                Ok(())
+            }
+            _ => self.levels[c.z as usize].carve(c.x, c.y, dir, constants::ID_MAZE_PATH, false)
+                                          .map_err(MazeError::InvalidArgument),
+        }
+    }
+
+    /// Generates a perfect maze on each level, then joins each pair of
+    /// neighboring levels with `stairwells` stairs.  The same seed always
+    /// produces the same maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::maze3d::{Coord3d, Maze3d};
+    ///
+    /// let mut maze = Maze3d::new(3, 5, 5);
+    /// maze.generate_seeded(2, 7).unwrap();
+    /// assert!(maze.solve(Coord3d::new(0, 0, 0), Coord3d::new(4, 4, 2)).is_some());
+    /// ```
+    pub fn generate_seeded(&mut self, stairwells: u32, seed: u64) -> Result<(), MazeError> {
+        self.generate_with_rng(stairwells, &mut rng::from_seed(seed))
+    }
+
+    /// Generates the maze using the supplied random number generator.  Each
+    /// stairwell is placed on a different Square; asking for none (when
+    /// there's more than one level) or for more than a level has Squares is
+    /// an error.
+    pub fn generate_with_rng<R: Rng>(&mut self, stairwells: u32, rng: &mut R) -> Result<(), MazeError> {
+        if self.levels.is_empty() {
+            return Ok(());
+        }
+        let (rows, cols) = (self.levels[0].get_rows(), self.levels[0].get_cols());
+        if self.get_depth() > 1 && (stairwells == 0 || stairwells > rows * cols) {
+            return Err(MazeError::InvalidArgument(
+                format!("can't place {} stairwells on {}x{} levels", stairwells, cols, rows)));
+        }
+
+        for level in self.levels.iter_mut() {
+            level.generate_perfect_with_rng(rng).map_err(MazeError::InvalidArgument)?;
+        }
+        for z in 0..self.get_depth() - 1 {
+            let mut placed = 0;
+            while placed < stairwells {
+                let c = Coord3d::new(rng.gen_range(0, cols), rng.gen_range(0, rows), z);
+                if !self.has_stairs(c, constants::DIR_UP) {
+                    self.carve(c, constants::DIR_UP)?;
+                    placed += 1;
+                }
+            }
+            gen_debug!("stairs z={} placed={}", z, placed);
+        }
+        Ok(())
+    }
+
+    /// Returns the Squares that can be reached in a single step from `c`,
+    /// including by stairs.
+    pub fn open_neighbors(&self, c: Coord3d) -> Vec<Coord3d> {
+        if !self.in_bounds(c) {
+            return Vec::new();
+        }
+        let mut neighbors: Vec<Coord3d> = self.levels[c.z as usize].open_neighbors(c.x, c.y).iter()
+                                              .map(|n| Coord3d::new(n.x, n.y, c.z)).collect();
+        for &dir in &[constants::DIR_UP, constants::DIR_DOWN] {
+            if self.has_stairs(c, dir) {
+                neighbors.extend(self.neighbor(c, dir));
+            }
+        }
+        neighbors
+    }
+
+    /// Finds the shortest path between two Squares using a breadth-first
+    /// search, taking stairs between levels.  The path includes both
+    /// endpoints.  Returns None if either point is outside of the maze or if
+    /// there's no path between them.
+    pub fn solve(&self, from: Coord3d, to: Coord3d) -> Option<Vec<Coord3d>> {
+        if !self.in_bounds(from) || !self.in_bounds(to) {
+            return None;
+        }
+
+        let mut came_from: Vec<Option<Coord3d>> = vec![None; self.stairs.len()];
+        let mut seen = vec![false; self.stairs.len()];
+        let mut queue = VecDeque::new();
+
+        seen[self.get_offset(from)] = true;
+        queue.push_back(from);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                let mut path = vec![cur];
+                let mut step = cur;
+                while let Some(prev) = came_from[self.get_offset(step)] {
+                    path.push(prev);
+                    step = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.open_neighbors(cur) {
+                let offset = self.get_offset(next);
+                if !seen[offset] {
+                    seen[offset] = true;
+                    came_from[offset] = Some(cur);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Renders each level in the same format as Maze::render_ascii(), from
+    /// the bottom level up.  Squares with stairs are marked 'U' (up), 'D'
+    /// (down) or 'B' (both).
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::constants;
+    /// use mazegame::maze::maze3d::{Coord3d, Maze3d};
+    ///
+    /// let mut maze = Maze3d::new(2, 1, 2);
+    /// maze.carve(Coord3d::new(1, 0, 0), constants::DIR_UP).unwrap();
+    /// assert_eq!("level 0\nXXXXX\nX XUX\nXXXXX\nlevel 1\nXXXXX\nX XDX\nXXXXX\n", maze.render_ascii());
+    /// ```
+    pub fn render_ascii(&self) -> String {
+        let mut out = String::new();
+        for (z, level) in self.levels.iter().enumerate() {
+            out.push_str(&format!("level {}\n", z));
+            for (row, line) in level.render_ascii().lines().enumerate() {
+                let mut line: Vec<char> = line.chars().collect();
+                if row % 2 == 1 {
+                    for x in 0..level.get_cols() {
+                        let c = Coord3d::new(x, row as u32 / 2, z as u32);
+                        let marker = match (self.has_stairs(c, constants::DIR_UP),
+                                            self.has_stairs(c, constants::DIR_DOWN)) {
+                            (true, true) => 'B',
+                            (true, false) => 'U',
+                            (false, true) => 'D',
+                            _ => continue,
+                        };
+                        line[(x * 2 + 1) as usize] = marker;
+                    }
+                }
+                out.extend(line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_coords(maze: &Maze3d) -> Vec<Coord3d> {
+        let level = maze.level(0);
+        let mut coords = Vec::new();
+        for z in 0..maze.get_depth() {
+            for y in 0..level.get_rows() {
+                for x in 0..level.get_cols() {
+                    coords.push(Coord3d::new(x, y, z));
+                }
+            }
+        }
+        coords
+    }
+
+    #[test]
+    fn test_connected() {
+        let mut maze = Maze3d::new(4, 6, 5);
+        maze.generate_seeded(1, 410).unwrap();
+        let start = Coord3d::new(0, 0, 0);
+        for c in all_coords(&maze) {
+            assert!(maze.solve(start, c).is_some(), "({}, {}, {}) is unreachable", c.x, c.y, c.z);
+        }
+    }
+
+    #[test]
+    fn test_stairs_paired() {
+        let mut maze = Maze3d::new(3, 4, 4);
+        maze.generate_seeded(5, 3).unwrap();
+        let mut ups = 0;
+        for c in all_coords(&maze) {
+            if maze.has_stairs(c, constants::DIR_UP) {
+                ups += 1;
+                assert!(maze.has_stairs(Coord3d::new(c.x, c.y, c.z + 1), constants::DIR_DOWN));
+            }
+            if maze.has_stairs(c, constants::DIR_DOWN) {
+                assert!(maze.has_stairs(Coord3d::new(c.x, c.y, c.z - 1), constants::DIR_UP));
+            }
+        }
+        assert_eq!(10, ups);
+
+        // There's nowhere for stairs to go from the top or bottom level
+        assert!(maze.carve(Coord3d::new(0, 0, 2), constants::DIR_UP).is_err());
+        assert!(maze.carve(Coord3d::new(0, 0, 0), constants::DIR_DOWN).is_err());
+        assert!(Maze3d::new(2, 2, 2).generate_seeded(0, 1).is_err());
+    }
+
+    #[test]
+    fn test_solve_uses_stairs() {
+        // Two levels with a wall down the middle of each; the only way across
+        // is up the stairs on one side and down the stairs on the other.
+        let mut maze = Maze3d::new(2, 1, 4);
+        maze.carve(Coord3d::new(0, 0, 0), constants::DIR_EAST).unwrap();
+        maze.carve(Coord3d::new(2, 0, 0), constants::DIR_EAST).unwrap();
+        maze.carve(Coord3d::new(1, 0, 1), constants::DIR_EAST).unwrap();
+        maze.carve(Coord3d::new(1, 0, 0), constants::DIR_UP).unwrap();
+        maze.carve(Coord3d::new(2, 0, 1), constants::DIR_DOWN).unwrap();
+
+        let path = maze.solve(Coord3d::new(0, 0, 0), Coord3d::new(3, 0, 0)).unwrap();
+        assert_eq!(vec![Coord3d::new(0, 0, 0), Coord3d::new(1, 0, 0), Coord3d::new(1, 0, 1),
+                        Coord3d::new(2, 0, 1), Coord3d::new(2, 0, 0), Coord3d::new(3, 0, 0)], path);
+        assert!(maze.level(0).solve(crate::maze::Coord::new(0, 0), crate::maze::Coord::new(3, 0)).is_none());
+    }
+}
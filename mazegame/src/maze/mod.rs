@@ -1,24 +1,241 @@
 //! Representation of a standard 4-walled maze, including methods to generate
 //! both perfect mazes and mazes with rooms.
+pub mod analysis;
+pub mod anchors;
+pub mod arbitrary;
+pub mod bias;
+pub mod border;
+pub mod bottleneck;
+pub mod braid;
+pub mod cancel;
+pub mod cave;
+pub mod changes;
+pub mod collapse;
+pub mod compact;
+#[cfg(feature = "serde")]
+pub mod daily;
+pub mod data;
+pub mod demo;
+pub mod diff;
+pub mod difficulty;
+pub mod dig;
+pub mod edit;
+pub mod editor;
+pub mod elevation;
+pub mod endless;
+pub mod exitfield;
+pub mod expand;
+pub mod exploration;
+pub mod fingerprint;
+pub mod fit;
+pub mod flood;
+pub mod generator;
+pub mod ghost;
+pub mod heatmap;
+pub mod hex;
+pub mod info;
+#[cfg(feature = "std")]
+pub mod layers;
+pub mod layout;
+pub mod mask;
+pub mod maze3d;
+pub mod morph;
+pub mod oneway;
+pub mod openness;
+pub mod orientation;
+pub mod partition;
+pub mod plates;
+pub mod prefab;
+pub mod preview;
+pub mod render;
+pub mod room;
+pub mod runs;
+pub mod save;
+pub mod secret;
+pub mod shaping;
+#[cfg(feature = "std")]
+pub mod shared;
+pub mod silhouette;
+pub mod solver;
+pub mod spawn;
+#[cfg(feature = "serde")]
+pub mod spec;
 pub mod square;
+pub mod stats;
+pub mod symmetric;
+#[cfg(test)]
+mod test_mazes;
+pub mod theta;
+pub mod thread;
+pub mod transform;
+pub mod traps;
+pub mod tree;
+pub mod tutorial;
+#[cfg(feature = "std")]
+pub mod tv;
+pub mod variant;
+pub mod view;
+pub mod weave;
+pub mod worksheet;
 
-pub use constants;
+// Re-exported so the older `mazegame::maze::constants` path keeps working
+pub use crate::constants;
+use crate::constants::Direction;
 pub use self::square::Square;
 
-use rand::{Rng, thread_rng};
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
 
-#[derive(Clone, Debug)]
+use crate::error::MazeError;
+use self::generator::{Carvable, GrowthStats};
+use self::info::{GenerationAlgorithm, GenerationInfo};
+use self::room::Door;
+use self::secret::Discoveries;
+use self::stats::GenerationStats;
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use crate::rng;
+
+/// An (x, y) position in the Maze.  x is the column and y is the row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Coord {
-    x: u32,
-    y: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Coord {
+    /// Creates a new Coord.
+    pub fn new(x: u32, y: u32) -> Coord {
+        Coord { x, y }
+    }
+}
+
+/// A gap in the outer wall of the Maze, used for entrances and exits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Opening {
+    pub coord: Coord,
+    pub dir: u32,
+}
+
+/// How one axis of the Maze treats its edges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Topology {
+    /// The edges are part of the outer wall.
+    #[default]
+    Bounded,
+    /// The edges wrap around, so leaving one side enters on the opposite one.
+    Torus,
+}
+
+/// The shape the two axes' Topology settings make together, for code that
+/// would rather name the whole surface than set each axis.  A cylinder has
+/// one seam, where its wrapping axis joins up, and an outer wall along the
+/// two edges of the other axis.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Surface {
+    /// Neither axis wraps.
+    #[default]
+    Plane,
+    /// The x axis wraps, so the east and west edges are a seam and the
+    /// north and south edges are outer wall.
+    CylinderX,
+    /// The y axis wraps, so the north and south edges are a seam and the
+    /// east and west edges are outer wall.
+    CylinderY,
+    /// Both axes wrap.
+    Torus,
+}
+
+impl Surface {
+    /// Returns the Topology of the x and y axes, as set_topology() takes
+    /// them.
+    pub fn axes(self) -> (Topology, Topology) {
+        match self {
+            Surface::Plane => (Topology::Bounded, Topology::Bounded),
+            Surface::CylinderX => (Topology::Torus, Topology::Bounded),
+            Surface::CylinderY => (Topology::Bounded, Topology::Torus),
+            Surface::Torus => (Topology::Torus, Topology::Torus),
+        }
+    }
+
+    /// Returns the surface the x and y axes' Topology settings make.
+    pub fn from_axes(x: Topology, y: Topology) -> Surface {
+        match (x, y) {
+            (Topology::Bounded, Topology::Bounded) => Surface::Plane,
+            (Topology::Torus, Topology::Bounded) => Surface::CylinderX,
+            (Topology::Bounded, Topology::Torus) => Surface::CylinderY,
+            (Topology::Torus, Topology::Torus) => Surface::Torus,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Maze {
     rows: u32,
     cols: u32,
     num_rooms: u32,
+    entrance: Option<Opening>,
+    // The first exit is the one get_exit() returns
+    #[cfg_attr(feature = "serde", serde(default, alias = "exit", deserialize_with = "save::deserialize_exits"))]
+    exits: Vec<Opening>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    x_topology: Topology,
+    #[cfg_attr(feature = "serde", serde(default))]
+    y_topology: Topology,
     pub sq: Vec<Square>,
+    // The theme of each room, indexed by room id - 1
+    #[cfg_attr(feature = "serde", serde(default))]
+    room_themes: Vec<String>,
+    // The corridor region of each Square from partition_corridors(), or
+    // empty if the corridors haven't been partitioned
+    #[cfg_attr(feature = "serde", serde(default))]
+    corridor_regions: Vec<Option<u32>>,
+    // The height of each Square from set_elevation() or
+    // assign_elevations(), or empty if every Square is at 0
+    #[cfg_attr(feature = "serde", serde(default))]
+    elevations: Vec<i8>,
+    // The doorways into rooms, from connect_regions() or add_door()
+    #[cfg_attr(feature = "serde", serde(default))]
+    doors: Vec<Door>,
+    // The Square generators start carving from and the player spawns on,
+    // from set_start(), or None for the default of (0, 0)
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    start: Option<Coord>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    generation: Option<GenerationInfo>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    last_stats: Option<GenerationStats>,
+    // Calls to carve() over the Maze's life, for working out carve_calls
+    #[cfg_attr(feature = "serde", serde(skip))]
+    carve_count: u32,
+    #[cfg_attr(feature = "serde", serde(skip, default = "analysis::next_revision"))]
+    revision: u64,
+    // The Squares as they were at the last take_changes()
+    #[cfg_attr(feature = "serde", serde(skip))]
+    change_base: Option<Vec<Square>>,
+}
+
+// Mazes are equal when they're laid out the same, however they were made.  One
+// without elevations is level, like one with every Square at 0, and missing
+// room themes and corridor regions are the same as empty ones
+impl PartialEq for Maze {
+    fn eq(&self, other: &Maze) -> bool {
+        self.rows == other.rows && self.cols == other.cols && self.num_rooms == other.num_rooms &&
+            self.entrance == other.entrance && self.exits == other.exits &&
+            self.x_topology == other.x_topology && self.y_topology == other.y_topology && self.sq == other.sq &&
+            self.doors == other.doors && self.get_start() == other.get_start() &&
+            (0..self.sq.len()).all(|i| self.elevations.get(i).unwrap_or(&0) == other.elevations.get(i).unwrap_or(&0)) &&
+            (1..=self.num_rooms as i32).all(|id| self.room_theme(id) == other.room_theme(id)) &&
+            (0..self.sq.len()).all(|i| {
+                self.corridor_regions.get(i).cloned().flatten() == other.corridor_regions.get(i).cloned().flatten()
+            })
+    }
 }
 
 impl Maze {
@@ -30,11 +247,52 @@ impl Maze {
     /// ``` 
     pub fn new(rows: u32, cols: u32) -> Maze {
         Maze {
-            rows: rows as u32,
-            cols: cols as u32,
+            rows,
+            cols,
             num_rooms: 0,
+            entrance: None,
+            exits: Vec::new(),
+            x_topology: Topology::Bounded,
+            y_topology: Topology::Bounded,
             sq: vec![Square::new(); (rows * cols) as usize],
+            room_themes: Vec::new(),
+            corridor_regions: Vec::new(),
+            elevations: Vec::new(),
+            doors: Vec::new(),
+            start: None,
+            generation: None,
+            last_stats: None,
+            carve_count: 0,
+            revision: analysis::next_revision(),
+            change_base: None,
+        }
+    }
+
+    /// Puts every Square back to its uncarved state and removes the rooms,
+    /// corridor regions, doors, entrance, exits, generation info and generation
+    /// stats.  The size, topology and start Square are kept.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(5, 5);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// maze.reset();
+    /// assert_eq!(mazegame::Maze::new(5, 5), maze);
+    /// ```
+    pub fn reset(&mut self) {
+        for sq in self.sq.iter_mut() {
+            *sq = Square::new();
         }
+        self.num_rooms = 0;
+        self.room_themes.clear();
+        self.corridor_regions.clear();
+        self.elevations.clear();
+        self.doors.clear();
+        self.entrance = None;
+        self.exits.clear();
+        self.generation = None;
+        self.last_stats = None;
+        self.mark_changed();
     }
 
     /// Returns the number of rows in the Maze.
@@ -59,11 +317,282 @@ impl Maze {
         self.cols
     }
 
+    /// Returns the index in `sq` of the Square at (x, y), or None if it's
+    /// outside the Maze.  Squares are stored in row-major order with x
+    /// varying fastest, so (x, y) is at `y * cols + x`; saved files, the FFI
+    /// layer and the WallGrid all rely on that order, and it won't change.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::Maze::new(3, 4);
+    /// assert_eq!(Some(0), maze.index_of(0, 0));
+    /// assert_eq!(Some(6), maze.index_of(2, 1));
+    /// assert_eq!(None, maze.index_of(4, 0));
+    /// ```
+    pub fn index_of(&self, x: u32, y: u32) -> Option<usize> {
+        if self.in_bounds(x, y) { Some(self.get_offset(x, y)) } else { None }
+    }
+
+    /// Returns the Coord of the Square at index in `sq`, or None if there's
+    /// no Square there.  The reverse of index_of().
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let maze = Maze::new(3, 4);
+    /// assert_eq!(Some(Coord::new(2, 1)), maze.coord_of(6));
+    /// assert_eq!(None, maze.coord_of(12));
+    /// ```
+    pub fn coord_of(&self, index: usize) -> Option<Coord> {
+        if index < self.sq.len() { Some(self.get_coord(index)) } else { None }
+    }
+
     // Internal: Calculates the offset into the Maze's Square vector
-    // based on x and y position.
+    // based on x and y position.  index_of() without the bounds check.
     fn get_offset(&self, x: u32, y: u32) -> usize {
-        let u = (y * self.cols + x) as usize;
-        u
+        (y * self.cols + x) as usize
+    }
+
+    // Internal: The position of the Square at an offset into the Maze's
+    // Square vector.  coord_of() without the bounds check.
+    fn get_coord(&self, offset: usize) -> Coord {
+        Coord::new(offset as u32 % self.cols, offset as u32 / self.cols)
+    }
+
+    /// Returns whether (x, y) lies inside the Maze.
+    /// 
+    /// # Example
+    /// ```
+    /// let maze = mazegame::Maze::new(10, 10);
+    /// assert_eq!(true, maze.in_bounds(9, 9));
+    /// assert_eq!(false, maze.in_bounds(10, 0));
+    /// ```
+    pub fn in_bounds(&self, x: u32, y: u32) -> bool {
+        x < self.cols && y < self.rows
+    }
+
+    /// Returns how the x (east-west) and y (north-south) axes treat their edges.
+    pub fn get_topology(&self) -> (Topology, Topology) {
+        (self.x_topology, self.y_topology)
+    }
+
+    /// Sets how the x (east-west) and y (north-south) axes treat their edges.
+    /// On a Torus axis, carving across the edge joins the Squares on opposite
+    /// sides of the Maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// maze.set_topology(Topology::Torus, Topology::Bounded);
+    /// assert_eq!(Ok(()), maze.carve(2, 0, DIR_EAST, ID_MAZE_PATH, false));
+    /// assert_eq!(false, maze.sq[0].is_wall_present(DIR_WEST));
+    /// ```
+    pub fn set_topology(&mut self, x: Topology, y: Topology) {
+        self.x_topology = x;
+        self.y_topology = y;
+        self.mark_changed();
+    }
+
+    /// Returns the surface the Maze's axes make.
+    pub fn surface(&self) -> Surface {
+        Surface::from_axes(self.x_topology, self.y_topology)
+    }
+
+    /// Sets both axes' Topology to make surface.  Walls already standing
+    /// are left alone.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 4);
+    /// maze.set_surface(Surface::CylinderX);
+    /// assert_eq!((Topology::Torus, Topology::Bounded), maze.get_topology());
+    /// assert_eq!(Some(Coord::new(3, 1)), maze.neighbor(0, 1, DIR_WEST));
+    /// assert_eq!(None, maze.neighbor(0, 0, DIR_NORTH));
+    /// ```
+    pub fn set_surface(&mut self, surface: Surface) {
+        let (x, y) = surface.axes();
+        self.set_topology(x, y);
+    }
+
+    /// Returns the Square next to (x, y) in direction dir, wrapping around
+    /// the edges of Torus axes.  Returns None if the step leaves the Maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// assert_eq!(None, maze.neighbor(0, 1, DIR_WEST));
+    /// maze.set_topology(Topology::Torus, Topology::Bounded);
+    /// assert_eq!(Some(Coord::new(2, 1)), maze.neighbor(0, 1, DIR_WEST));
+    /// ```
+    pub fn neighbor(&self, x: u32, y: u32, dir: u32) -> Option<Coord> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        // A one Square wide axis would wrap onto itself, so it never wraps
+        let wrap_x = self.x_topology == Topology::Torus && self.cols > 1;
+        let wrap_y = self.y_topology == Topology::Torus && self.rows > 1;
+        let (dx, dy) = Direction::from_u32(dir)?.delta();
+        Some(Coord::new(step_axis(x, dx, self.cols, wrap_x)?, step_axis(y, dy, self.rows, wrap_y)?))
+    }
+
+    /// Returns the recorded entrance of the Maze, if there is one.
+    pub fn get_entrance(&self) -> Option<Opening> {
+        self.entrance
+    }
+
+    /// Returns the Square generators start carving from and the player
+    /// spawns on.  This is (0, 0) unless set_start() has chosen another.
+    pub fn get_start(&self) -> Coord {
+        self.start.unwrap_or(Coord::new(0, 0))
+    }
+
+    /// Returns true if set_start() has chosen the start Square, rather than
+    /// the Maze using the default of (0, 0).
+    pub fn has_start(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Chooses the Square generators start carving from and the player
+    /// spawns on.  The Maze's contents aren't changed, so it takes effect
+    /// on the next generation.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.set_start(4, 7).unwrap();
+    /// assert_eq!(Coord::new(4, 7), maze.get_start());
+    /// assert!(maze.set_start(10, 0).is_err());
+    /// ```
+    pub fn set_start(&mut self, x: u32, y: u32) -> Result<(), MazeError> {
+        if !self.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        self.start = Some(Coord::new(x, y));
+        Ok(())
+    }
+
+    /// Goes back to starting at the default Square of (0, 0).
+    pub fn clear_start(&mut self) {
+        self.start = None;
+    }
+
+    //
+    // Internal - where a player begins: the chosen start Square, or
+    // failing that the entrance.  None if the Maze has neither.
+    //
+    pub(crate) fn player_start(&self) -> Option<Coord> {
+        self.start.or_else(|| self.entrance.map(|o| o.coord))
+    }
+
+    /// Returns the recorded exit of the Maze, if there is one.  A Maze with
+    /// several exits returns the first.
+    pub fn get_exit(&self) -> Option<Opening> {
+        self.exits.first().copied()
+    }
+
+    /// Returns every recorded exit of the Maze, in the order they were added.
+    pub fn exits(&self) -> &[Opening] {
+        &self.exits
+    }
+
+    /// Returns whether the Square at coord holds one of the Maze's exits, so
+    /// a player standing there has won.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.set_exit(9, 9, DIR_SOUTH).unwrap();
+    /// maze.add_exit(9, 0, DIR_EAST).unwrap();
+    /// assert!(maze.is_exit(Coord::new(9, 0)));
+    /// assert!(maze.is_exit(Coord::new(9, 9)));
+    /// assert!(!maze.is_exit(Coord::new(0, 0)));
+    /// ```
+    pub fn is_exit(&self, coord: Coord) -> bool {
+        self.exits.iter().any(|o| o.coord == coord)
+    }
+
+    /// Opens the outer wall of a border Square and records it as the entrance.
+    /// 
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// 
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.set_entrance(0, 0, DIR_NORTH).unwrap();
+    /// assert_eq!(false, maze.sq[0].is_wall_present(DIR_NORTH));
+    /// ```
+    pub fn set_entrance(&mut self, x: u32, y: u32, dir: u32) -> Result<(), MazeError> {
+        let opening = self.open_border(x, y, dir)?;
+        self.entrance = Some(opening);
+        Ok(())
+    }
+
+    /// Opens the outer wall of a border Square and records it as the exit,
+    /// in place of any exits the Maze already had.  Their walls are left open.
+    /// 
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// 
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.set_exit(9, 9, DIR_SOUTH).unwrap();
+    /// assert_eq!(9, maze.get_exit().unwrap().coord.x);
+    /// ```
+    pub fn set_exit(&mut self, x: u32, y: u32, dir: u32) -> Result<(), MazeError> {
+        let opening = self.open_border(x, y, dir)?;
+        self.exits = vec![opening];
+        Ok(())
+    }
+
+    /// Opens the outer wall of a border Square and records it as another
+    /// exit, after any the Maze already has.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.set_exit(9, 9, DIR_SOUTH).unwrap();
+    /// maze.add_exit(0, 9, DIR_WEST).unwrap();
+    /// assert_eq!(2, maze.exits().len());
+    /// assert_eq!(9, maze.get_exit().unwrap().coord.x);
+    /// ```
+    pub fn add_exit(&mut self, x: u32, y: u32, dir: u32) -> Result<(), MazeError> {
+        let opening = self.open_border(x, y, dir)?;
+        if !self.exits.contains(&opening) {
+            self.exits.push(opening);
+        }
+        Ok(())
+    }
+
+    //
+    // Internal - breaks the outer wall of the Square at (x, y) in direction dir,
+    // making sure that the wall really is part of the Maze's border.
+    //
+    fn open_border(&mut self, x: u32, y: u32, dir: u32) -> Result<Opening, MazeError> {
+        if !self.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        // Edges of Torus axes lead back into the Maze, so they aren't borders
+        if dir >= constants::NUM_DIRECTIONS || self.neighbor(x, y, dir).is_some() {
+            return Err(MazeError::InvalidArgument(
+                format!("direction {} at ({}, {}) doesn't face the outside of the maze", dir, x, y)));
+        }
+        let offset = self.get_offset(x, y);
+        self.sq[offset].break_wall(dir);
+        self.mark_changed();
+        Ok(Opening { coord: Coord::new(x, y), dir })
     }
 
     /// Carves a Square in the Maze in a given direction.
@@ -77,10 +606,10 @@ impl Maze {
     /// 
     /// # Example
     /// ``` 
-    /// use mazegame::constants;
+    /// use mazegame::prelude::*;
     /// 
-    /// let mut maze = mazegame::Maze::new(10, 10);
-    /// let result = maze.carve(5, 5, constants::DIR_NORTH, constants::ID_MAZE_PATH, false);
+    /// let mut maze = Maze::new(10, 10);
+    /// let result = maze.carve(5, 5, DIR_NORTH, ID_MAZE_PATH, false);
     /// assert_eq!(Ok(()), result);
     /// ```
     pub fn carve(&mut self, x: u32, y: u32, dir: u32, id: i32, carve_out: bool) -> Result<(), String> {
@@ -92,65 +621,50 @@ impl Maze {
             return Err(format!("Can't carve outside of maze at ({}, {})", x, y)); 
         }
 
-        // The position of the square on the other side of the wall
-        let mut dest_x = x;        
-        let mut dest_y = y;
-        let dest_dir;
-
         // Prevent breaking of walls that would lead out of the maze
-        match dir {
-            constants::DIR_NORTH => {
-                if y == 0 { 
-                    return Err(format!("Can't build north wall at ({}, {})", x, y)); 
-                }
-                dest_y = dest_y - 1;
-                dest_dir = constants::DIR_SOUTH;
-            }
-            constants::DIR_SOUTH => {
-                if y == self.rows - 1 { 
-                    return Err(format!("Can't build south wall at ({}, {})", x, y)); 
-                }
-                dest_y = dest_y + 1;
-                dest_dir = constants::DIR_NORTH;
-            }
-            constants::DIR_EAST => {
-                if x == self.cols -1 { 
-                    return Err(format!("Can't build east wall at ({}, {})", x, y)); 
-                }
-                dest_x = dest_x + 1;
-                dest_dir = constants::DIR_WEST;
-            }
-            constants::DIR_WEST => {
-                if x == 0 { 
-                    return Err(format!("Can't build west wall at ({}, {})", x, y)); 
-                }
-                dest_x = dest_x - 1;
-                dest_dir = constants::DIR_EAST;
-            }
-            _ => { 
+        let (dest_dir, name) = match Direction::from_u32(dir) {
+            Some(d) => (u32::from(d.opposite()), d.name()),
+            None => {
                 return Err(format!("Can't build wall in illegal direction {}", dir)); 
             }
-        }
+        };
+
+        // The position of the square on the other side of the wall
+        let dest = match self.neighbor(x, y, dir) {
+            Some(c) => c,
+            None => return Err(format!("Can't build {} wall at ({}, {})", name, x, y)),
+        };
 
         // Need multiple mutable references, but not at the same time.
         // Stick em in their own scopes.
+        // Rooms knock their walls down from both sides; only trace the first
+        let was_wall = self.sq[self.get_offset(x, y)].is_wall_present(dir);
         {
             let offset = self.get_offset(x, y);
             let square = &mut self.sq[offset];
             square.break_wall(dir);
+            square.set_one_way(dir, false);
+            square.set_secret(dir, false);
             square.id = id;
         }
 
         {
-            let offset = self.get_offset(dest_x, dest_y);
+            let offset = self.get_offset(dest.x, dest.y);
             let square = &mut self.sq[offset];
             square.break_wall(dest_dir);
-            if carve_out == false {
+            square.set_one_way(dest_dir, false);
+            square.set_secret(dest_dir, false);
+            if !carve_out {
                 square.id = id;
             }
         }
 
-        return Ok(());
+        self.carve_count = self.carve_count.wrapping_add(1);
+        self.mark_changed();
+        if was_wall {
+            gen_trace!("carve x={} y={} dir={} id={}", x, y, crate::trace::dir_name(dir), id);
+        }
+        Ok(())
     }
 
     /// Generates a perfect maze.
@@ -160,9 +674,39 @@ impl Maze {
     /// let mut maze = mazegame::Maze::new(10, 10);
     /// maze.generate_perfect();
     /// ```
+    #[cfg(feature = "std")]
     pub fn generate_perfect(&mut self) -> Result<(), String> {
-        let result = self.generator_growing_tree(0, 0);
-        result
+        self.generate_perfect_seeded(thread_rng().gen())
+    }
+
+    /// Generates a perfect maze from a seed.  The same seed always produces
+    /// the same maze.
+    /// 
+    /// # Example
+    /// ```
+    /// let mut a = mazegame::Maze::new(10, 10);
+    /// let mut b = mazegame::Maze::new(10, 10);
+    /// a.generate_perfect_seeded(42).unwrap();
+    /// b.generate_perfect_seeded(42).unwrap();
+    /// assert_eq!(a, b);
+    /// ```
+    pub fn generate_perfect_seeded(&mut self, seed: u64) -> Result<(), String> {
+        self.generate_perfect_with_rng(&mut rng::from_seed(seed))?;
+        self.record_generation(seed, GenerationAlgorithm::GrowingTree, None);
+        Ok(())
+    }
+
+    /// Generates a perfect maze using the supplied random number generator.
+    /// 
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// let mut rng = mazegame::rng::from_seed(7);
+    /// maze.generate_perfect_with_rng(&mut rng).unwrap();
+    /// ```
+    pub fn generate_perfect_with_rng<R: Rng>(&mut self, rng: &mut R) -> Result<(), String> {
+        let start = self.get_start();
+        self.measure_generation(|maze| maze.generator_growing_tree(start.x, start.y, rng))
     }
 
     /// Generates a maze with rooms and with removed extraneous passages.
@@ -172,120 +716,187 @@ impl Maze {
     /// let mut maze = mazegame::Maze::new(10, 10);
     /// maze.generate((20, 2, 3, 2, 3));
     /// ```
-    pub fn generate(&mut self, (rooms, min_x, max_x, min_y, max_y): (u32, u32, u32, u32, u32)) -> Result<(), String> {
-        let _rooms = self.make_rooms(rooms, min_x, max_x, min_y, max_y);
-        let result = self.generator_growing_tree(0, 0);
-        if result != Ok(()) { 
-            return result; 
-        }
-        // Perform additional opening and pruning tasks
-  
-        return Ok(());
+    #[cfg(feature = "std")]
+    pub fn generate(&mut self, params: (u32, u32, u32, u32, u32)) -> Result<(), String> {
+        self.generate_seeded(params, thread_rng().gen())
     }
 
-    //
-    // Internal - generates a perfect maze using the growing tree algorithm.
-    //
-    fn generator_growing_tree(&mut self, start_x: u32, start_y: u32) -> Result<(), String> {
-        let mut visited: Vec<Coord> = Vec::new();
-        let mut cur_coord = Coord { x: start_x, y: start_y };
-
-        // Handle the initial square
-        let (result, dir) = self.pick_direction(cur_coord.x, cur_coord.y);
-        if result == false { 
-            return Err(format!("Unable to pick initial direction in generator!")); 
-        } else {
-            self.carve(cur_coord.x, cur_coord.y, dir, constants::ID_MAZE_PATH, false).unwrap();
-            visited.push(cur_coord.clone());
-            match dir {
-                constants::DIR_NORTH => cur_coord.y = cur_coord.y - 1,
-                constants::DIR_SOUTH => cur_coord.y = cur_coord.y + 1,
-                constants::DIR_EAST => cur_coord.x = cur_coord.x + 1,
-                constants::DIR_WEST => cur_coord.x = cur_coord.x - 1,
-                _ => {
-                    return Err(format!("Illegal direction in generate_growing_tree!"));
+    /// Generates a maze with rooms from a seed.  The same seed and parameters
+    /// always produce the same maze.
+    /// 
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(20, 20);
+    /// maze.generate_seeded((5, 2, 4, 2, 4), 42).unwrap();
+    /// ```
+    pub fn generate_seeded(&mut self, params: (u32, u32, u32, u32, u32), seed: u64) -> Result<(), String> {
+        self.generate_with_rng(params, &mut rng::from_seed(seed))?;
+        self.record_generation(seed, GenerationAlgorithm::GrowingTree, Some(params));
+        Ok(())
+    }
+
+    /// Generates a maze with rooms using the supplied random number generator.
+    pub fn generate_with_rng<R: Rng>(&mut self, (rooms, min_x, max_x, min_y, max_y): (u32, u32, u32, u32, u32),
+                                     rng: &mut R) -> Result<(), String> {
+        self.measure_generation(|maze| {
+            let _rooms = maze.make_rooms(rooms, min_x, max_x, min_y, max_y, rng);
+            let start = maze.get_start();
+            let stats = maze.generator_growing_tree(start.x, start.y, rng)?;
+            // Perform additional opening and pruning tasks
+
+            Ok(stats)
+        })
+    }
+
+    /// Removes dead ends by knocking down a wall at each one with probability
+    /// `chance` (0.0 leaves the maze alone, 1.0 removes every dead end it can).
+    /// Walls into rooms are left standing.  Returns the number of walls removed.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// let mut rng = mazegame::rng::from_seed(3);
+    /// maze.generate_perfect_with_rng(&mut rng).unwrap();
+    /// assert!(maze.braid_with_rng(1.0, &mut rng) > 0);
+    /// ```
+    pub fn braid_with_rng<R: Rng>(&mut self, chance: f64, rng: &mut R) -> u32 {
+        let mut removed = 0;
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                // Earlier removals may have already opened this one up
+                if self.sq[self.get_offset(x, y)].is_part_of_room() || self.open_neighbors(x, y).len() != 1 {
+                    continue;
+                }
+                if rng.gen::<f64>() >= chance {
+                    continue;
                 }
-            }
-        }
 
-        // Handle all subsequent squares
-        while visited.len() > 0 {
-            let (result, dir) = self.pick_direction(cur_coord.x, cur_coord.y);
-            // No directions available.  Pull a square from the stack.
-            if result == false {
-                let item = visited.pop();
-                match item {
-                    Some(i) => cur_coord = i,
-                    None => {
-                        return Err(format!("Pop failed in generate_growing_tree!"));
+                let mut candidates = Vec::new();
+                let mut dead_end_candidates = Vec::new();
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    let (nx, ny) = match self.neighbor(x, y, dir) {
+                        Some(c) => (c.x, c.y),
+                        None => continue,
+                    };
+                    if !self.sq[self.get_offset(x, y)].is_wall_present(dir) ||
+                       self.sq[self.get_offset(nx, ny)].is_part_of_room() {
+                        continue;
                     }
-                }
-            } else {
-                self.carve(cur_coord.x, cur_coord.y, dir, constants::ID_MAZE_PATH, false).unwrap();
-                visited.push(cur_coord.clone());
-                match dir {
-                    constants::DIR_NORTH => cur_coord.y = cur_coord.y - 1,
-                    constants::DIR_SOUTH => cur_coord.y = cur_coord.y + 1,
-                    constants::DIR_EAST => cur_coord.x = cur_coord.x + 1,
-                    constants::DIR_WEST => cur_coord.x = cur_coord.x - 1,
-                    _ => {
-                        return Err(format!("Illegal direction in generate_growing_tree!"));
+                    candidates.push(dir);
+                    if self.open_neighbors(nx, ny).len() == 1 {
+                        dead_end_candidates.push(dir);
                     }
                 }
+
+                // Joining two dead ends removes both at once
+                let pool = if dead_end_candidates.is_empty() { &candidates } else { &dead_end_candidates };
+                if !pool.is_empty() {
+                    let dir = pool[rng.gen_range(0, pool.len())];
+                    self.carve(x, y, dir, constants::ID_MAZE_PATH, false).unwrap();
+                    removed += 1;
+                }
             }
         }
+        gen_debug!("braid chance={} removed={}", chance, removed);
+        removed
+    }
 
-        Ok(())
+    /// Joins every region of the Maze that can't be reached from the others
+    /// (rooms, caverns, separate stretches of maze) by knocking down one wall
+    /// between each pair of regions that get merged.  Squares keep their ids.
+    /// Returns the number of walls removed.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(3, 3);
+    /// assert_eq!(8, maze.connect_regions());
+    /// assert!(maze.solve(Coord::new(0, 0), Coord::new(2, 2)).is_some());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn connect_regions(&mut self) -> u32 {
+        self.connect_regions_with_rng(&mut thread_rng())
     }
 
-    //
-    // Internal - picks a random direction to tunnel a new maze square
-    //
-    fn pick_direction(&self, x: u32, y: u32) -> (bool, u32) {
-        let mut directions: Vec<u32> = Vec::new();
-        let mut rng = thread_rng();
-
-        if y > 0 {
-            let sq = &self.sq[self.get_offset(x, y-1)];            
-            if sq.is_carved() == false {
-                directions.push(constants::DIR_NORTH);
+    /// Joins every region of the Maze, choosing which walls to knock down
+    /// with the supplied random number generator.
+    pub fn connect_regions_with_rng<R: Rng>(&mut self, rng: &mut R) -> u32 {
+        let mut region: Vec<usize> = (0..self.sq.len()).collect();
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let a = self.get_offset(x, y);
+                for c in self.open_neighbors(x, y) {
+                    let b = self.get_offset(c.x, c.y);
+                    let (ra, rb) = (find_region(&mut region, a), find_region(&mut region, b));
+                    region[ra] = rb;
+                }
             }
         }
-        if y < self.rows - 1 {
-            let sq = &self.sq[self.get_offset(x, y+1)];
-            if sq.is_carved() == false {
-                directions.push(constants::DIR_SOUTH);
+        let regions = (0..region.len()).filter(|&i| find_region(&mut region, i) == i).count();
+
+        // Every wall between two different regions, visited in random order
+        let mut walls = Vec::new();
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                for &side in [Direction::South, Direction::East].iter() {
+                    let dir = u32::from(side);
+                    if let Some(c) = self.neighbor(x, y, dir) {
+                        if self.sq[self.get_offset(x, y)].is_wall_present(dir) &&
+                           self.sq[self.get_offset(c.x, c.y)].is_wall_present(side.opposite().into()) {
+                            walls.push((x, y, side, c));
+                        }
+                    }
+                }
             }
         }
-        if x < self.cols - 1 {
-            let sq = &self.sq[self.get_offset(x+1, y)];
-            if sq.is_carved() == false {
-                directions.push(constants::DIR_EAST);
+        rng.shuffle(&mut walls);
+
+        let mut joined = 0;
+        for (x, y, side, c) in walls {
+            let dir = u32::from(side);
+            let (a, b) = (self.get_offset(x, y), self.get_offset(c.x, c.y));
+            let (ra, rb) = (find_region(&mut region, a), find_region(&mut region, b));
+            if ra == rb {
+                continue;
             }
-        }
-        if x > 0 {
-            let sq = &self.sq[self.get_offset(x-1, y)];
-            if sq.is_carved() == false {
-                directions.push(constants::DIR_WEST);
+            region[ra] = rb;
+            let id = if self.sq[a].id == constants::ID_UNCARVED { constants::ID_MAZE_PATH } else { self.sq[a].id };
+            self.carve(x, y, dir, id, true).unwrap();
+            if self.sq[b].id == constants::ID_UNCARVED {
+                self.sq[b].id = constants::ID_MAZE_PATH;
             }
+            // A wall knocked down into a room is a doorway; one between two
+            // rooms is recorded once, for the room on the north or west
+            if self.sq[a].is_part_of_room() && self.sq[b].id != self.sq[a].id {
+                self.doors.push(Door { coord: Coord::new(x, y), dir, room_id: self.sq[a].id, locked: false });
+            } else if self.sq[b].is_part_of_room() && self.sq[b].id != self.sq[a].id {
+                self.doors.push(Door { coord: c, dir: side.opposite().into(), room_id: self.sq[b].id, locked: false });
+            }
+            joined += 1;
         }
-        
-        // If no directions are found...
-        if directions.len() == 0 {
-            (false, 0)
-        } else {
-            let dir = rng.gen_range(0, directions.len());
-            (true, directions[dir])
-        }
+        self.sort_doors();
+        gen_debug!("connect_regions regions={} joined={}", regions, joined);
+        joined
+    }
+
+    //
+    // Internal - generates a perfect maze using the growing tree algorithm.
+    //
+    fn generator_growing_tree<R: Rng>(&mut self, start_x: u32, start_y: u32, rng: &mut R)
+                                      -> Result<GrowthStats, String> {
+        let stats = generator::growing_tree(self, Coord::new(start_x, start_y), rng)?;
+        gen_debug!("growing_tree x={} y={} carved={} backtracks={}", start_x, start_y, stats.carved, stats.backtracks);
+        Ok(stats)
     }
 
     // 
     // Internal - creates the specified number of non-overlapping rooms, each with
     // a minimum and maximum size.
     //
-    fn make_rooms(&mut self, count: u32, min_x: u32, max_x: u32, min_y: u32, max_y: u32) -> u32 {
+    fn make_rooms<R: Rng>(&mut self, count: u32, min_x: u32, max_x: u32, min_y: u32, max_y: u32,
+                          rng: &mut R) -> u32 {
         let mut id = 1;
-        let mut rng = thread_rng();
 
         for _i in 0..count {
             let x_size = rng.gen_range(min_x, max_x+1); 
@@ -293,13 +904,20 @@ impl Maze {
             let x_pos = rng.gen_range(1, self.cols - x_size);
             let y_pos = rng.gen_range(1, self.rows - y_size);
 
-            if self.rooms_overlap(x_pos, y_pos, x_size, y_size) == false {
+            if !self.rooms_overlap(x_pos, y_pos, x_size, y_size) {
+                gen_trace!("room id={} x={} y={} w={} h={}", id, x_pos, y_pos, x_size, y_size);
                 self.carve_room(x_pos, y_pos, x_size, y_size, id);
-                id = id + 1;
+                id += 1;
+            } else {
+                gen_debug!("room_rejected x={} y={} w={} h={} reason=overlap", x_pos, y_pos, x_size, y_size);
             }
         }
 
         self.num_rooms =  (id - 1) as u32;
+        gen_debug!("rooms requested={} placed={} rejected={}", count, self.num_rooms, count - self.num_rooms);
+        if self.num_rooms < count {
+            gen_warn!("rooms_exhausted requested={} placed={}", count, self.num_rooms);
+        }
         self.num_rooms
     }
 
@@ -314,7 +932,7 @@ impl Maze {
         for x in (x_pos-1)..(end_x+1) {
             for y in (y_pos-1)..(end_y+1) {
                 let sq = &self.sq[self.get_offset(x, y)];
-                if sq.is_part_of_room() == true {
+                if sq.is_part_of_room() {
                     return true;
                 }
             }
@@ -356,48 +974,205 @@ impl Maze {
     /// m.generate_perfect();
     /// m.print();
     /// ```
+    #[cfg(feature = "std")]
     pub fn print(&self) {
+        print!("{}", self.render_ascii());
+    }
+
+    /// Renders the maze to a String, in the same format used by print().
+    /// Openings in the border that wrap around a Torus axis are drawn as
+    /// '^', 'v', '<' and '>'.  Weave crossings are drawn as '|' when the
+    /// north-south passage is on top and '-' when the east-west one is, and
+    /// one-way passages as an arrow pointing the way they can be taken.
+    /// Secret passages are drawn as walls, the way a player who hasn't found
+    /// them sees the maze.
+    /// 
+    /// # Example:
+    /// ```
+    /// let m = mazegame::Maze::new(2, 2);
+    /// assert_eq!("XXXXX\nX X X\nXXXXX\nX X X\nXXXXX\n", m.render_ascii());
+    /// ```
+    pub fn render_ascii(&self) -> String {
+        self.render_ascii_with_path(&[])
+    }
+
+    /// Renders the maze to a String with a path drawn over it.  Squares on
+    /// the path (and the gaps between consecutive path Squares) are drawn
+    /// with a '.'.
+    /// 
+    /// # Example:
+    /// ```
+    /// use mazegame::prelude::*;
+    /// 
+    /// let mut m = Maze::new(2, 2);
+    /// m.generate_perfect().unwrap();
+    /// let path = m.solve(Coord::new(0, 0), Coord::new(1, 1)).unwrap();
+    /// let rendered = m.render_ascii_with_path(&path);
+    /// assert!(rendered.contains("."));
+    /// ```
+    pub fn render_ascii_with_path(&self, path: &[Coord]) -> String {
+        self.render_ascii_marked(path, '.', &Discoveries::new())
+    }
+
+    //
+    // Internal - renders the maze with the Squares of path (and the gaps
+    // between consecutive ones) drawn as mark.  Secret passages are walls
+    // unless they're in discoveries.
+    //
+    pub(crate) fn render_ascii_marked(&self, path: &[Coord], mark: char, discoveries: &Discoveries) -> String {
+        let mut path_mask = vec![false; self.sq.len()];
+        for c in path.iter().filter(|c| self.in_bounds(c.x, c.y)) {
+            path_mask[self.get_offset(c.x, c.y)] = true;
+        }
+        let on_path = |x: u32, y: u32| self.in_bounds(x, y) && path_mask[self.get_offset(x, y)];
+        let walled = |x: u32, y: u32, dir: u32| {
+            let sq = &self.sq[self.get_offset(x, y)];
+            sq.is_wall_present(dir) || (sq.is_secret(dir) && !discoveries.contains(Coord::new(x, y), dir))
+        };
+        let mut out = String::new();
+
+        // Openings in the outer wall are gaps, and those along the edge of a
+        // Torus axis are drawn as wrapped, since they lead round to the
+        // other side
+        let openings: BTreeSet<(Coord, u32)> = self.outer_openings().into_iter()
+            .map(|(c, d)| (c, u32::from(d)))
+            .collect();
+        let border = |x: u32, y: u32, dir: u32, wrapped: char| {
+            if walled(x, y, dir) {
+                'X'
+            } else if openings.contains(&(Coord::new(x, y), dir)) {
+                ' '
+            } else {
+                wrapped
+            }
+        };
+
         // Print the first row
-        print!("X");
-        for _i in 0..(self.cols * 2) {
-            print!("X");
+        out.push('X');
+        for x in 0..self.cols {
+            out.push(border(x, 0, constants::DIR_NORTH, '^'));
+            out.push('X');
         }
-        println!("");
+        out.push('\n');
         for y in 0..self.rows {
-            print!("X");
+            out.push(border(0, y, constants::DIR_WEST, '<'));
             for x in 0..self.cols {
-                let sq = &self.sq[(y * self.cols + x) as usize];
-                if sq.is_wall_present(constants::DIR_EAST) == true {
-                    print!(" X");
+                let sq = &self.sq[self.get_offset(x, y)];
+                let here = on_path(x, y);
+                out.push(if here {
+                    mark
+                } else if sq.has_under_passage(constants::DIR_EAST) {
+                    '|'
+                } else if sq.has_under_passage(constants::DIR_NORTH) {
+                    '-'
                 } else {
-                    print!("  ");
+                    ' '
+                });
+                if x + 1 < self.cols && sq.is_one_way(constants::DIR_EAST) {
+                    out.push('>');
+                } else if x + 1 < self.cols && self.sq[self.get_offset(x + 1, y)].is_one_way(constants::DIR_WEST) {
+                    out.push('<');
+                } else if walled(x, y, constants::DIR_EAST) {
+                    out.push('X');
+                } else if x == self.cols - 1 {
+                    out.push(border(x, y, constants::DIR_EAST, '>'));
+                } else if here && on_path(x + 1, y) {
+                    out.push(mark);
+                } else {
+                    out.push(' ');
                 }
             }
-            println!("");
-            print!("X");
+            out.push('\n');
+            out.push('X');
             for x in 0..self.cols {
-                let sq = &self.sq[(y * self.cols + x) as usize];
-                if sq.is_wall_present(constants::DIR_SOUTH) == true {
-                    print!("XX");
+                let sq = &self.sq[self.get_offset(x, y)];
+                if y + 1 < self.rows && sq.is_one_way(constants::DIR_SOUTH) {
+                    out.push_str("vX");
+                } else if y + 1 < self.rows && self.sq[self.get_offset(x, y + 1)].is_one_way(constants::DIR_NORTH) {
+                    out.push_str("^X");
+                } else if walled(x, y, constants::DIR_SOUTH) {
+                    out.push_str("XX");
                 } else {
+                    if y == self.rows - 1 {
+                        out.push(border(x, y, constants::DIR_SOUTH, 'v'));
+                    } else if on_path(x, y) && on_path(x, y + 1) {
+                        out.push(mark);
+                    } else {
+                        out.push(' ');
+                    }
                     // Room 'pillar' removing code.  Omit the bottom right
                     // X of the square if the following is true:
                     // - The square is in a room (ID > 0)
                     // - The square to the east is also in a room (ID > 0)
-                    let sq2 = &self.sq[(y * self.cols + x + 1) as usize];
-                    if sq.is_part_of_room() == true && sq2.is_part_of_room() == true {
-                        print!("  ");
-                    } else {
-                        print!(" X");
-                    }
+                    // - None of the walls meeting at the corner are present
+                    //   (caverns aren't rectangles)
+                    let in_room = x + 1 < self.cols && y + 1 < self.rows &&
+                                  sq.is_part_of_room() && 
+                                  self.sq[self.get_offset(x + 1, y)].is_part_of_room() &&
+                                  !walled(x, y, constants::DIR_EAST) &&
+                                  !walled(x + 1, y, constants::DIR_SOUTH) &&
+                                  !walled(x, y + 1, constants::DIR_EAST);
+                    out.push(if in_room { ' ' } else { 'X' });
                 }
             }
-            println!("");            
+            out.push('\n');
         }
+        out
+    }
+}
+
+//
+// Internal - moves pos by delta along an axis len Squares long, wrapping
+// around if wrap is set.  Returns None if the step leaves the axis.
+//
+fn step_axis(pos: u32, delta: i32, len: u32, wrap: bool) -> Option<u32> {
+    let next = i64::from(pos) + i64::from(delta);
+    if (0..i64::from(len)).contains(&next) {
+        Some(next as u32)
+    } else if wrap {
+        Some(next.rem_euclid(i64::from(len)) as u32)
+    } else {
+        None
+    }
+}
+
+//
+// Internal - finds the region a Square's offset belongs to, flattening the
+// chain of regions on the way.
+//
+pub(crate) fn find_region(region: &mut [usize], mut i: usize) -> usize {
+    while region[i] != i {
+        region[i] = region[region[i]];
+        i = region[i];
+    }
+    i
+}
+
+impl Carvable for Maze {
+    type Cell = Coord;
+
+    fn uncarved_directions(&self, cell: Coord) -> Vec<u32> {
+        // A one Square room or cavern has no open walls, but still isn't
+        // part of the maze
+        (0..constants::NUM_DIRECTIONS).filter(|&dir| {
+            match self.neighbor(cell.x, cell.y, dir) {
+                Some(c) => {
+                    let sq = &self.sq[self.get_offset(c.x, c.y)];
+                    !sq.is_carved() && !sq.is_part_of_room()
+                }
+                None => false,
+            }
+        }).collect()
+    }
+
+    fn carve_toward(&mut self, cell: Coord, dir: u32) -> Result<Coord, String> {
+        self.carve(cell.x, cell.y, dir, constants::ID_MAZE_PATH, false)?;
+        self.neighbor(cell.x, cell.y, dir).ok_or_else(|| format!("No Square {} of ({}, {})", dir, cell.x, cell.y))
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -414,6 +1189,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_index_round_trip() {
+        // 7 columns by 4 rows, so swapped axes would show up
+        let mut maze = Maze::new(4, 7);
+        for (i, sq) in maze.sq.iter_mut().enumerate() {
+            sq.id = i as i32 + 1;
+        }
+        let mut expected = 0;
+        for y in 0..4 {
+            for x in 0..7 {
+                let index = maze.index_of(x, y).unwrap();
+                assert_eq!(expected, index);
+                assert_eq!(Some(Coord::new(x, y)), maze.coord_of(index));
+                assert_eq!(index as i32 + 1, maze.sq[index].id);
+                expected += 1;
+            }
+        }
+        assert_eq!(maze.sq.len(), expected);
+        assert_eq!(None, maze.index_of(7, 0));
+        assert_eq!(None, maze.index_of(0, 4));
+        assert_eq!(None, maze.coord_of(28));
+
+        // Carving through one agrees with looking through the other
+        maze.carve(6, 2, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        let below = maze.index_of(6, 3).unwrap();
+        assert!(!maze.sq[below].is_wall_present(constants::DIR_NORTH));
+        assert_eq!(Some(Coord::new(6, 3)), maze.coord_of(below));
+    }
+
     #[test] 
     fn test_carver() {
         let mut maze = Maze::new(10, 10);
@@ -495,15 +1299,163 @@ mod tests {
         assert_ne!(Ok(()), result);
     }
 
+    #[test]
+    fn test_carve_rejections() {
+        // Every way carve() can refuse, with the Maze left alone
+        let mut maze = Maze::new(3, 4);
+        let cases = [(0, 0, constants::DIR_NORTH, "Can't build north wall at (0, 0)"),
+                     (0, 2, constants::DIR_SOUTH, "Can't build south wall at (0, 2)"),
+                     (3, 1, constants::DIR_EAST, "Can't build east wall at (3, 1)"),
+                     (0, 1, constants::DIR_WEST, "Can't build west wall at (0, 1)"),
+                     (1, 1, constants::DIR_UP, "Can't build wall in illegal direction 4"),
+                     (4, 0, constants::DIR_WEST, "Can't carve outside of maze at (4, 0)"),
+                     (0, 3, constants::DIR_NORTH, "Can't carve outside of maze at (0, 3)")];
+        for &(x, y, dir, message) in cases.iter() {
+            assert_eq!(Err(message.to_string()), maze.carve(x, y, dir, constants::ID_MAZE_PATH, false));
+        }
+        assert_eq!(Maze::new(3, 4), maze);
+
+        // Every in-bounds direction still lands on the right Square
+        for &dir in Direction::ALL.iter() {
+            let (dx, dy) = dir.delta();
+            let mut maze = Maze::new(3, 3);
+            maze.carve(1, 1, dir.into(), constants::ID_MAZE_PATH, false).unwrap();
+            let there = Coord::new((1 + dx) as u32, (1 + dy) as u32);
+            assert_eq!(vec![there], maze.open_neighbors(1, 1));
+            assert!(!maze.sq[maze.get_offset(there.x, there.y)].is_wall_present(dir.opposite().into()));
+        }
+    }
+
+    #[test]
+    fn test_torus_carve() {
+        let mut maze = Maze::new(4, 5);
+        maze.set_topology(Topology::Torus, Topology::Torus);
+
+        // Carving off the east edge opens the west wall of the same row
+        assert_eq!(Ok(()), maze.carve(4, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false));
+        assert_eq!([true, true, false, true], maze.sq[maze.get_offset(4, 1)].wall_present);
+        assert_eq!([true, true, true, false], maze.sq[maze.get_offset(0, 1)].wall_present);
+
+        // And off the north edge opens the south wall of the same column
+        assert_eq!(Ok(()), maze.carve(2, 0, constants::DIR_NORTH, constants::ID_MAZE_PATH, false));
+        assert_eq!([false, true, true, true], maze.sq[maze.get_offset(2, 0)].wall_present);
+        assert_eq!([true, false, true, true], maze.sq[maze.get_offset(2, 3)].wall_present);
+
+        // Seams aren't part of the outer wall, so they can't be entrances
+        assert!(maze.set_entrance(0, 0, constants::DIR_WEST).is_err());
+        let rendered = maze.render_ascii();
+        assert!(rendered.lines().nth(3).unwrap().starts_with('<'));
+        assert!(rendered.lines().nth(3).unwrap().ends_with('>'));
+        assert_eq!("XXXXX^XXXXX", rendered.lines().next().unwrap());
+
+        // A perfect torus maze still has exactly one fewer passage than Squares
+        let mut maze = Maze::new(6, 7);
+        maze.set_topology(Topology::Torus, Topology::Bounded);
+        maze.generate_perfect_seeded(409).unwrap();
+        let passages: usize = (0..7).flat_map(|x| (0..6).map(move |y| (x, y)))
+                                    .map(|(x, y)| maze.open_neighbors(x, y).len()).sum();
+        assert_eq!(6 * 7 - 1, passages / 2);
+        assert!(maze.walls_consistent());
+    }
+
+    #[test]
+    fn test_cylinder() {
+        // Row 2 runs from (1, 2) west across the seam to (6, 2); the only
+        // other way round is up and along the top row
+        let mut maze = Maze::new(5, 8);
+        maze.set_surface(Surface::CylinderX);
+        assert_eq!(Surface::CylinderX, maze.surface());
+        maze.carve(1, 2, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(0, 2, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(7, 2, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 2, constants::DIR_NORTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, constants::DIR_NORTH, constants::ID_MAZE_PATH, false).unwrap();
+        for x in 1..6 {
+            maze.carve(x, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        }
+        maze.carve(6, 0, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(6, 1, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+
+        let path = maze.solve(Coord::new(1, 2), Coord::new(6, 2)).unwrap();
+        assert_eq!(vec![Coord::new(1, 2), Coord::new(0, 2), Coord::new(7, 2), Coord::new(6, 2)], path);
+        assert_eq!(Ok(()), maze.validate());
+
+        // Arrows only on the seam; the top and bottom are outer wall
+        let rendered = maze.render_ascii();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[5].starts_with('<') && lines[5].ends_with('>'));
+        assert!(!lines[0].contains('^') && !lines[10].contains('v'));
+
+        // The bounded axis still won't carve off the edge, and openings
+        // only go in the outer wall
+        assert!(maze.carve(3, 0, constants::DIR_NORTH, constants::ID_MAZE_PATH, false).is_err());
+        assert!(maze.carve(3, 4, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).is_err());
+        assert!(maze.set_entrance(0, 3, constants::DIR_WEST).is_err());
+        assert_eq!(Ok(()), maze.set_entrance(3, 0, constants::DIR_NORTH));
+
+        // A seam wall broken on one side only is caught
+        let offset = maze.get_offset(0, 3);
+        maze.sq[offset].break_wall(constants::DIR_WEST);
+        assert!(maze.validate().unwrap_err().to_string().contains("wall e of (7, 3)"));
+
+        // And the other way round
+        let mut maze = Maze::new(4, 3);
+        maze.set_surface(Surface::CylinderY);
+        assert_eq!((Topology::Bounded, Topology::Torus), maze.get_topology());
+        assert_eq!(Some(Coord::new(1, 3)), maze.neighbor(1, 0, constants::DIR_NORTH));
+        assert_eq!(None, maze.neighbor(0, 1, constants::DIR_WEST));
+        maze.set_surface(Surface::Plane);
+        assert_eq!(Surface::Plane, maze.surface());
+    }
+
+    #[test]
+    fn test_bounded_unchanged() {
+        let mut maze = Maze::new(3, 3);
+        assert_eq!((Topology::Bounded, Topology::Bounded), maze.get_topology());
+        assert!(maze.carve(2, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).is_err());
+        assert_eq!(None, maze.neighbor(0, 0, constants::DIR_NORTH));
+        assert_eq!(vec![constants::DIR_SOUTH, constants::DIR_EAST], maze.uncarved_directions(Coord::new(0, 0)));
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        assert!(maze.render_ascii().lines().nth(1).unwrap().starts_with(' '));
+    }
+
+    #[test]
+    fn test_start() {
+        let mut maze = Maze::new(8, 10);
+        assert_eq!(Coord::new(0, 0), maze.get_start());
+        assert!(!maze.has_start());
+        assert_eq!(Err(MazeError::OutOfBounds(10, 2)), maze.set_start(10, 2));
+        maze.set_start(6, 5).unwrap();
+        assert_eq!(Coord::new(6, 5), maze.get_start());
+
+        // The spanning tree is rooted where generation began
+        let tree = maze.generate_spanning_tree_seeded(3).unwrap();
+        assert_eq!(Coord::new(6, 5), tree.root);
+        let mut copy = maze.clone();
+        copy.reset();
+        assert_eq!(Coord::new(6, 5), copy.get_start());
+        copy.generate_perfect_seeded(3).unwrap();
+        assert_eq!(maze.sq, copy.sq);
+        assert!(copy.distances(Coord::new(6, 5)).iter().all(|d| d.is_some()));
+
+        // The same seed from the default start makes a different maze
+        let mut plain = Maze::new(8, 10);
+        plain.generate_perfect_seeded(3).unwrap();
+        assert_ne!(plain, copy);
+        copy.clear_start();
+        assert_eq!(Coord::new(0, 0), copy.get_start());
+    }
+
     #[test]
     fn test_direction_picker() {
         let mut maze = Maze::new(10, 10);
+        let mut rng = rng::from_seed(17);
 
         // Use the top left corner.  Since the maze is completely uncarved, 
         // if I request random directions multiple times, I should always get one 
         // of (DIR_SOUTH, DIR_EAST) - the other two directions are off the maze
         for _i in 0..10 {
-            let (result, value) = maze.pick_direction(0, 0);
+            let (result, value) = maze.pick_direction(Coord::new(0, 0), &mut rng);
             assert_ne!(false, result);
             if value == constants::DIR_WEST || value == constants::DIR_NORTH {
                 panic!("Received invalid direction at (0,0)");
@@ -513,7 +1465,7 @@ mod tests {
         // Use the top right corner.  Since the maze is completely uncarved,
         // the returned directions should always be DIR_WEST or DIR_SOUTH.
         for _i in 0..10 {
-            let (result, value) = maze.pick_direction(9, 0);
+            let (result, value) = maze.pick_direction(Coord::new(9, 0), &mut rng);
             assert_ne!(false, result);
             if value == constants::DIR_EAST || value == constants::DIR_NORTH {
                 panic!("Received invalid direction at (9,0)");
@@ -523,7 +1475,7 @@ mod tests {
         // Use the bottom left corner.  Since the maze is completely uncarved,
         // the returned directions should always be DIR_EAST or DIR_NORTH.
         for _i in 0..10 {
-            let (result, value) = maze.pick_direction(0, 9);
+            let (result, value) = maze.pick_direction(Coord::new(0, 9), &mut rng);
             assert_ne!(false, result);
             if value == constants::DIR_WEST || value == constants::DIR_SOUTH {
                 panic!("Received invalid direction at (0,9)");
@@ -533,7 +1485,7 @@ mod tests {
         // Use the bottom right corner.  Since the maze is completely uncarved,
         // the returned directions should always be DIR_WEST or DIR_NORTH.
         for _i in 0..10 {
-            let (result, value) = maze.pick_direction(9, 9);
+            let (result, value) = maze.pick_direction(Coord::new(9, 9), &mut rng);
             assert_ne!(false, result);
             if value == constants::DIR_EAST || value == constants::DIR_SOUTH {
                 panic!("Received invalid direction at (9,9)")
@@ -543,7 +1495,7 @@ mod tests {
         // Pick a center location.  Since the maze is completely uncarved,
         // any of the four directions should be returned
         for _i in 1..20 {
-            let (result, _) = maze.pick_direction(3, 3);
+            let (result, _) = maze.pick_direction(Coord::new(3, 3), &mut rng);
             assert_ne!(false, result);
         }
 
@@ -553,7 +1505,7 @@ mod tests {
         assert_eq!([false, true, true, true], maze.sq[maze.get_offset(3, 2)].wall_present);
         assert_eq!(Ok(()), result);
         for _i in 1..20 {
-            let (result, value) = maze.pick_direction(3, 3);
+            let (result, value) = maze.pick_direction(Coord::new(3, 3), &mut rng);
             assert_eq!(true, result);
             assert_ne!(constants::DIR_NORTH, value);
         }
@@ -564,7 +1516,7 @@ mod tests {
         assert_eq!([true, true, true, false], maze.sq[maze.get_offset(2, 3)].wall_present);
         assert_eq!(Ok(()), result);        
         for _i in 1..20 {
-            let (result, value) = maze.pick_direction(3, 3);
+            let (result, value) = maze.pick_direction(Coord::new(3, 3), &mut rng);
             assert_eq!(true, result);
             assert_ne!(constants::DIR_NORTH, value);
             assert_ne!(constants::DIR_WEST, value);
@@ -576,7 +1528,7 @@ mod tests {
         assert_eq!([true, false, true, true], maze.sq[maze.get_offset(4, 3)].wall_present);
         assert_eq!(Ok(()), result);
         for _i in 1..20 {
-            let (result, value) = maze.pick_direction(3, 3);
+            let (result, value) = maze.pick_direction(Coord::new(3, 3), &mut rng);
             assert_eq!(true, result);
             assert_ne!(constants::DIR_NORTH, value);
             assert_ne!(constants::DIR_WEST, value);
@@ -589,7 +1541,7 @@ mod tests {
         assert_eq!([true, true, true, false], maze.sq[maze.get_offset(3, 4)].wall_present);
         assert_eq!(Ok(()), result);
         for _i in 1..20 {
-            let (result, _value) = maze.pick_direction(3, 3);            
+            let (result, _value) = maze.pick_direction(Coord::new(3, 3), &mut rng);            
             assert_eq!(false, result);
         }              
     }
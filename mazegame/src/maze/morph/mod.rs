@@ -0,0 +1,237 @@
+//! Turning one maze into another of the same size one wall at a time, for
+//! animated transitions between levels.  The steps are ordered so the maze
+//! stays connected the whole way through: each wall opened makes a loop,
+//! and a wall on that loop that has to close anyway is closed right after
+//! it, so a perfect maze stays perfect at every other step.
+use alloc::collections::VecDeque;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+/// One wall opening or closing during a morph.  Walls between two Squares
+/// change on both sides at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MorphStep {
+    pub coord: Coord,
+    pub dir: u32,
+    /// Whether the wall opens, rather than closes.
+    pub open: bool,
+}
+
+impl Maze {
+    /// Lists the wall changes that turn this Maze's walls into target's, in
+    /// the order to make them.  Walls are opened before the walls they
+    /// replace are closed, so a connected maze never gets cut in two on the
+    /// way.  Only walls change; Squares opened up for the first time become
+    /// part of the maze path.  Both mazes must be the same size and shape.
+    ///
+    /// # Example
+    /// ```
+    /// let mut from = mazegame::Maze::new(6, 6);
+    /// from.generate_perfect_seeded(1).unwrap();
+    /// let mut to = mazegame::Maze::new(6, 6);
+    /// to.generate_perfect_seeded(2).unwrap();
+    ///
+    /// for step in from.morph_steps(&to).unwrap() {
+    ///     from.apply_morph_step(&step).unwrap();
+    /// }
+    /// assert_eq!(to, from);
+    /// ```
+    pub fn morph_steps(&self, target: &Maze) -> Result<Vec<MorphStep>, MazeError> {
+        if (self.cols, self.rows) != (target.cols, target.rows) {
+            return Err(MazeError::InvalidArgument(format!("can't morph a {}x{} maze into a {}x{} one",
+                                                          self.cols, self.rows, target.cols, target.rows)));
+        }
+        if self.get_topology() != target.get_topology() {
+            return Err(MazeError::InvalidArgument("can't morph between mazes that wrap differently".to_string()));
+        }
+
+        // Every wall is the east or south wall of exactly one Square, except
+        // for the outer wall's north and west sides
+        let mut opens = Vec::new();
+        let mut closes = Vec::new();
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let offset = self.get_offset(x, y);
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    let shared = self.neighbor(x, y, dir).is_some();
+                    if shared && (dir == constants::DIR_NORTH || dir == constants::DIR_WEST) {
+                        continue;
+                    }
+                    let was_open = !self.sq[offset].is_wall_present(dir);
+                    let open = !target.sq[offset].is_wall_present(dir);
+                    let step = MorphStep { coord: Coord::new(x, y), dir, open };
+                    match (was_open, open) {
+                        (false, true) => opens.push(step),
+                        (true, false) => closes.push(step),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let mut maze = self.clone();
+        let mut steps = Vec::with_capacity(opens.len() + closes.len());
+        for step in opens {
+            maze.apply_morph_step(&step)?;
+            steps.push(step);
+            // The loop the new opening made, if any wall on it is due to close
+            let other = match maze.neighbor(step.coord.x, step.coord.y, step.dir) {
+                Some(other) => other,
+                None => continue,
+            };
+            let detour = match maze.path_without(step.coord, other) {
+                Some(detour) => detour,
+                None => continue,
+            };
+            let on_loop = closes.iter().position(|c| {
+                let far = maze.neighbor(c.coord.x, c.coord.y, c.dir);
+                detour.windows(2).any(|w| (w[0] == c.coord && Some(w[1]) == far) ||
+                                          (w[1] == c.coord && Some(w[0]) == far))
+            });
+            if let Some(i) = on_loop {
+                let close = closes.remove(i);
+                maze.apply_morph_step(&close)?;
+                steps.push(close);
+            }
+        }
+        // Everything left to close is a wall the target doesn't need
+        steps.extend(closes);
+        Ok(steps)
+    }
+
+    /// Makes one morph step.  Squares it opens into that weren't carved
+    /// become part of the maze path.
+    pub fn apply_morph_step(&mut self, step: &MorphStep) -> Result<(), MazeError> {
+        let (x, y) = (step.coord.x, step.coord.y);
+        if !self.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        let back = match Direction::from_u32(step.dir) {
+            Some(side) => u32::from(side.opposite()),
+            None => return Err(MazeError::InvalidArgument(format!("{} isn't a direction", step.dir))),
+        };
+        let mut sides = vec![(self.get_offset(x, y), step.dir)];
+        if let Some(n) = self.neighbor(x, y, step.dir) {
+            sides.push((self.get_offset(n.x, n.y), back));
+        }
+        for (offset, dir) in sides {
+            let sq = &mut self.sq[offset];
+            if step.open {
+                sq.break_wall(dir);
+                if sq.id == constants::ID_UNCARVED {
+                    sq.id = constants::ID_MAZE_PATH;
+                }
+            } else {
+                sq.build_wall(dir);
+            }
+            sq.set_one_way(dir, false);
+        }
+        self.mark_changed();
+        Ok(())
+    }
+
+    //
+    // Internal - the shortest path from `from` to its neighbor `to` that
+    // doesn't go straight between them.
+    //
+    fn path_without(&self, from: Coord, to: Coord) -> Option<Vec<Coord>> {
+        let blocked = |a: Coord, b: Coord| (a == from && b == to) || (a == to && b == from);
+        let mut prev: Vec<Option<Coord>> = vec![None; self.sq.len()];
+        let mut seen = vec![false; self.sq.len()];
+        let mut queue = VecDeque::new();
+        seen[self.get_offset(from.x, from.y)] = true;
+        queue.push_back(from);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                let mut path = vec![to];
+                let mut at = to;
+                while let Some(p) = prev[self.get_offset(at.x, at.y)] {
+                    path.push(p);
+                    at = p;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.open_neighbors(cur.x, cur.y) {
+                let offset = self.get_offset(next.x, next.y);
+                if !seen[offset] && !blocked(cur, next) {
+                    seen[offset] = true;
+                    prev[offset] = Some(cur);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::test_mazes::connected;
+
+    #[test]
+    fn test_morph_perfect_mazes() {
+        let mut from = Maze::new(12, 10);
+        from.generate_perfect_seeded(441).unwrap();
+        from.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        let mut to = Maze::new(12, 10);
+        to.generate_perfect_seeded(442).unwrap();
+        to.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+
+        let steps = from.morph_steps(&to).unwrap();
+        let diff = from.diff(&to).unwrap();
+        // Each step changes one wall, which is two sides of it
+        assert_eq!(diff.changed_walls() as usize, steps.len() * 2);
+
+        let mut maze = from.clone();
+        let passages = |maze: &Maze| maze.sq.iter().map(|sq| sq.open_mask().count_ones()).sum::<u32>() / 2;
+        let tree = passages(&from);
+        for (i, step) in steps.iter().enumerate() {
+            maze.apply_morph_step(step).unwrap();
+            assert!(connected(&maze), "cut in two after step {}", i);
+            assert!(maze.walls_consistent());
+            // Never more than one loop at a time
+            assert!(passages(&maze) <= tree + 1);
+        }
+        assert_eq!(to, maze);
+        assert_eq!(Vec::<MorphStep>::new(), maze.morph_steps(&to).unwrap());
+    }
+
+    #[test]
+    fn test_morph_to_braided() {
+        let mut from = Maze::new(8, 8);
+        from.generate_perfect_seeded(1).unwrap();
+        let mut to = Maze::new(8, 8);
+        to.generate_perfect_seeded(2).unwrap();
+        to.braid_with_rng(1.0, &mut crate::rng::from_seed(3));
+
+        let mut maze = from.clone();
+        for step in from.morph_steps(&to).unwrap() {
+            maze.apply_morph_step(&step).unwrap();
+            assert!(connected(&maze));
+        }
+        assert_eq!(to, maze);
+        // And back again, starting from an empty grid
+        let blank = Maze::new(8, 8);
+        let mut maze = blank.clone();
+        for step in blank.morph_steps(&from).unwrap() {
+            maze.apply_morph_step(&step).unwrap();
+        }
+        assert_eq!(from, maze);
+    }
+
+    #[test]
+    fn test_bad_morphs() {
+        assert!(Maze::new(3, 4).morph_steps(&Maze::new(4, 3)).is_err());
+        let mut torus = Maze::new(3, 3);
+        torus.set_topology(crate::maze::Topology::Torus, crate::maze::Topology::Bounded);
+        assert!(Maze::new(3, 3).morph_steps(&torus).is_err());
+        let step = MorphStep { coord: Coord::new(3, 0), dir: constants::DIR_EAST, open: true };
+        assert_eq!(Err(MazeError::OutOfBounds(3, 0)), Maze::new(3, 3).apply_morph_step(&step));
+    }
+}
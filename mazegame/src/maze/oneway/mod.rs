@@ -0,0 +1,220 @@
+//! One-way passages, for puzzle mazes with doors that only open one way.  A
+//! one-way passage is open on the side it leads out of and walled on the
+//! other; `Square::one_way` records which openings are meant to be that way,
+//! so they can be told apart from walls that were broken by mistake.
+//!
+//! Since a Square's own walls decide where you can go from it,
+//! Maze::open_neighbors() and the solvers already treat one-way passages as
+//! directed edges.
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+impl Maze {
+    /// Opens a passage from (x, y) in direction dir that can't be taken the
+    /// other way.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(1, 2);
+    /// maze.carve_one_way(0, 0, DIR_EAST).unwrap();
+    /// assert_eq!(vec![Coord::new(1, 0)], maze.open_neighbors(0, 0));
+    /// assert!(maze.open_neighbors(1, 0).is_empty());
+    /// ```
+    pub fn carve_one_way(&mut self, x: u32, y: u32, dir: u32) -> Result<(), String> {
+        let (dest, back) = match (self.neighbor(x, y, dir), Direction::from_u32(dir)) {
+            (Some(c), Some(side)) => (c, u32::from(side.opposite())),
+            _ => return Err(format!("Can't carve one-way {} from ({}, {})", crate::trace::dir_name(dir), x, y)),
+        };
+
+        let offset = self.get_offset(x, y);
+        self.sq[offset].break_wall(dir);
+        self.sq[offset].set_one_way(dir, true);
+        self.sq[offset].id = constants::ID_MAZE_PATH;
+        let offset = self.get_offset(dest.x, dest.y);
+        self.sq[offset].build_wall(back);
+        self.sq[offset].set_one_way(back, false);
+        self.sq[offset].id = constants::ID_MAZE_PATH;
+        self.mark_changed();
+
+        gen_trace!("carve_one_way x={} y={} dir={}", x, y, crate::trace::dir_name(dir));
+        Ok(())
+    }
+
+    /// Checks that every wall matches the wall on its other side, apart from
+    /// deliberate one-way passages, and that every one-way passage really is
+    /// open one way only.  Secret passages must be open both ways and marked
+    /// as secret on both sides.  The rest of the Maze is checked too: there
+    /// must be a Square for every position, the entrance and exits must be
+    /// on the outer wall, room ids must run from 1 to the number of rooms,
+    /// and the corridor regions (if any) must cover the Maze.  The error
+    /// describes the first problem found.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(2, 2);
+    /// maze.carve_one_way(0, 0, DIR_EAST).unwrap();
+    /// assert!(maze.validate().is_ok());
+    /// maze.sq[0].break_wall(DIR_SOUTH);
+    /// assert!(maze.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), MazeError> {
+        let invalid = |msg: String| Err(MazeError::InvalidArgument(msg));
+        if self.sq.len() != (self.rows as usize) * (self.cols as usize) {
+            return invalid(format!("{} Squares in a {}x{} maze", self.sq.len(), self.cols, self.rows));
+        }
+        for o in self.entrance.iter().chain(self.exits.iter()) {
+            if !self.in_bounds(o.coord.x, o.coord.y) || o.dir >= constants::NUM_DIRECTIONS
+               || self.neighbor(o.coord.x, o.coord.y, o.dir).is_some() {
+                return invalid(format!("the opening at ({}, {}) facing {} isn't on the outer wall",
+                                       o.coord.x, o.coord.y, crate::trace::dir_name(o.dir)));
+            }
+        }
+        if let Some(sq) = self.sq.iter().find(|sq| sq.is_part_of_room() && (sq.id < 1 || sq.id as u32 > self.num_rooms)) {
+            return invalid(format!("room id {} in a maze with {} rooms", sq.id, self.num_rooms));
+        }
+        if !self.corridor_regions.is_empty() && self.corridor_regions.len() != self.sq.len() {
+            return invalid(format!("{} corridor regions for {} Squares", self.corridor_regions.len(), self.sq.len()));
+        }
+        if !self.elevations.is_empty() && self.elevations.len() != self.sq.len() {
+            return invalid(format!("{} elevations for {} Squares", self.elevations.len(), self.sq.len()));
+        }
+        let recorded: BTreeSet<(Coord, u32)> = self.entrance.iter().chain(self.exits.iter())
+            .map(|o| (o.coord, o.dir))
+            .collect();
+        for (c, d) in self.outer_openings() {
+            if !recorded.contains(&(c, u32::from(d))) {
+                return invalid(format!("the outer wall of ({}, {}) facing {} is open, but isn't an entrance or exit",
+                                       c.x, c.y, crate::trace::dir_name(u32::from(d))));
+            }
+        }
+        for d in self.doors.iter() {
+            let in_room = self.in_bounds(d.coord.x, d.coord.y) && d.dir < constants::NUM_DIRECTIONS &&
+                self.sq[self.get_offset(d.coord.x, d.coord.y)].id == d.room_id;
+            if !in_room {
+                return invalid(format!("the door at ({}, {}) facing {} isn't in room {}",
+                                       d.coord.x, d.coord.y, crate::trace::dir_name(d.dir), d.room_id));
+            }
+        }
+
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let sq = &self.sq[self.get_offset(x, y)];
+                for &side in Direction::ALL.iter() {
+                    let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+                    let secret = self.neighbor(x, y, dir)
+                        .is_some_and(|n| self.sq[self.get_offset(n.x, n.y)].is_secret(back));
+                    let problem = if sq.is_secret(dir) || secret {
+                        if sq.is_wall_present(dir) || sq.is_one_way(dir) {
+                            Some("is a secret passage that isn't open both ways")
+                        } else if !secret || !sq.is_secret(dir) {
+                            Some("is a secret passage on one side only")
+                        } else {
+                            None
+                        }
+                    } else if sq.is_one_way(dir) {
+                        match self.neighbor(x, y, dir) {
+                            None => Some("is a one-way passage out of the maze"),
+                            Some(_) if sq.is_wall_present(dir) => Some("is a walled up one-way passage"),
+                            Some(n) if !self.sq[self.get_offset(n.x, n.y)].is_wall_present(back) => {
+                                Some("is a one-way passage that's open both ways")
+                            }
+                            Some(_) => None,
+                        }
+                    } else if sq.is_wall_present(dir) != self.expected_wall(x, y, dir) {
+                        Some("doesn't match the other side")
+                    } else {
+                        None
+                    };
+                    if let Some(problem) = problem {
+                        return Err(MazeError::InvalidArgument(
+                            format!("wall {} of ({}, {}) {}", crate::trace::dir_name(dir), x, y, problem)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::assert_matches_fixture;
+    use crate::maze::Coord;
+
+    // A corridor along a single row, with a one-way door in the middle
+    fn corridor() -> Maze {
+        let mut maze = Maze::new(1, 4);
+        maze.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_one_way(1, 0, constants::DIR_EAST).unwrap();
+        maze.carve(2, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_solve_one_way() {
+        let maze = corridor();
+        assert_eq!(4, maze.solve(Coord::new(0, 0), Coord::new(3, 0)).unwrap().len());
+        assert_eq!(None, maze.solve(Coord::new(3, 0), Coord::new(0, 0)));
+        // The far side of the door is walled off
+        assert_eq!(vec![Coord::new(3, 0)], maze.open_neighbors(2, 0));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut maze = corridor();
+        assert_eq!(Ok(()), maze.validate());
+        assert!(maze.walls_consistent());
+
+        // Carving the door normally turns it back into a two-way passage
+        let mut two_way = maze.clone();
+        two_way.carve(2, 0, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        assert!(!two_way.sq[1].is_one_way(constants::DIR_EAST));
+        assert_eq!(Ok(()), two_way.validate());
+
+        // The same walls without the flag are just broken
+        maze.sq[1].set_one_way(constants::DIR_EAST, false);
+        assert!(maze.validate().is_err());
+        assert!(!maze.walls_consistent());
+
+        // A flag on a passage that's open both ways is a mistake too
+        let mut maze = corridor();
+        maze.sq[2].break_wall(constants::DIR_WEST);
+        assert!(maze.validate().is_err());
+
+        // So are room ids past the number of rooms, and openings inside
+        let mut maze = corridor();
+        maze.sq[0].id = 1;
+        assert!(maze.validate().unwrap_err().to_string().contains("room id 1"));
+        maze.num_rooms = 1;
+        assert_eq!(Ok(()), maze.validate());
+        maze.entrance = Some(crate::maze::Opening { coord: Coord::new(1, 0), dir: constants::DIR_EAST });
+        assert!(maze.validate().is_err());
+    }
+
+    #[test]
+    fn test_save_one_way() {
+        let maze = corridor();
+        assert!(maze.to_text().contains(" c:-1:4 "));
+        assert_eq!(maze, Maze::from_text(&maze.to_text()).unwrap());
+        assert_eq!(maze, Maze::from_bytes(&maze.to_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_render_arrows() {
+        let mut maze = Maze::new(2, 2);
+        maze.carve_one_way(0, 0, constants::DIR_EAST).unwrap();
+        maze.carve_one_way(1, 1, constants::DIR_WEST).unwrap();
+        maze.carve_one_way(1, 0, constants::DIR_SOUTH).unwrap();
+        maze.carve_one_way(0, 1, constants::DIR_NORTH).unwrap();
+        assert_matches_fixture("oneway_arrows.txt", &maze.render_ascii());
+    }
+}
@@ -0,0 +1,153 @@
+//! Keeping a maze open enough for sprites bigger than a wall is thick.  A
+//! 2x2 block of Squares walled off from each other on all four inside walls
+//! leaves a post standing alone in the middle, which a large sprite can't
+//! be drawn around without clipping.
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::maze::{find_region, Maze};
+
+impl Maze {
+    /// Opens one inside wall of every 2x2 block whose four inside walls are
+    /// all present, and returns how many walls were opened.  Where it can,
+    /// the wall opened joins two parts of the maze that weren't connected,
+    /// so no loop is made; in a connected maze that isn't possible and the
+    /// block gets a loop.  Blocks are handled in row-major order, and the
+    /// first suitable wall is opened in the order north, west, east, south,
+    /// so the result is always the same.
+    ///
+    /// Blocks with an uncarved Square or a room Square are left alone.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(30, 30);
+    /// maze.generate_perfect_seeded(471).unwrap();
+    /// assert!(maze.ensure_local_openness() > 0);
+    /// assert_eq!(0, maze.ensure_local_openness());
+    /// ```
+    pub fn ensure_local_openness(&mut self) -> u32 {
+        // The connected parts of the maze, as a spanning forest
+        let mut region: Vec<usize> = (0..self.sq.len()).collect();
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let a = self.get_offset(x, y);
+                for c in self.open_neighbors(x, y) {
+                    let b = self.get_offset(c.x, c.y);
+                    let (ra, rb) = (find_region(&mut region, a), find_region(&mut region, b));
+                    region[ra] = rb;
+                }
+            }
+        }
+
+        let (mut opened, mut loops) = (0, 0);
+        for y in 0..self.rows.saturating_sub(1) {
+            for x in 0..self.cols.saturating_sub(1) {
+                let block = [self.get_offset(x, y), self.get_offset(x + 1, y),
+                             self.get_offset(x, y + 1), self.get_offset(x + 1, y + 1)];
+                if block.iter().any(|&i| self.sq[i].id == constants::ID_UNCARVED || self.sq[i].is_part_of_room()) {
+                    continue;
+                }
+                // Each inside wall, as the Square it's carved from and the
+                // direction to carve in
+                let walls = [
+                    (x, y, Direction::East),
+                    (x, y, Direction::South),
+                    (x + 1, y, Direction::South),
+                    (x, y + 1, Direction::East),
+                ];
+                let closed = walls.iter().all(|&(wx, wy, dir)| {
+                    let n = self.neighbor(wx, wy, dir.into()).unwrap();
+                    self.sq[self.get_offset(wx, wy)].is_wall_present(dir.into()) &&
+                        self.sq[self.get_offset(n.x, n.y)].is_wall_present(dir.opposite().into())
+                });
+                if !closed {
+                    continue;
+                }
+
+                let mut joins = |&(wx, wy, dir): &(u32, u32, Direction)| {
+                    let n = self.neighbor(wx, wy, dir.into()).unwrap();
+                    let (a, b) = (self.get_offset(wx, wy), self.get_offset(n.x, n.y));
+                    find_region(&mut region, a) != find_region(&mut region, b)
+                };
+                let (wx, wy, dir) = match walls.iter().find(|w| joins(w)) {
+                    Some(&wall) => wall,
+                    None => {
+                        loops += 1;
+                        walls[0]
+                    }
+                };
+                let n = self.neighbor(wx, wy, dir.into()).unwrap();
+                let (a, b) = (self.get_offset(wx, wy), self.get_offset(n.x, n.y));
+                self.carve(wx, wy, dir.into(), self.sq[a].id, true).unwrap();
+                let (ra, rb) = (find_region(&mut region, a), find_region(&mut region, b));
+                region[ra] = rb;
+                opened += 1;
+            }
+        }
+        gen_debug!("ensure_local_openness opened={} loops={}", opened, loops);
+        opened
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Coord;
+    use crate::maze::test_mazes::cycles;
+
+    fn fenced_blocks(maze: &Maze) -> Vec<Coord> {
+        let walled = |x: u32, y: u32, dir: u32| maze.sq[maze.get_offset(x, y)].is_wall_present(dir);
+        (0..maze.get_rows() - 1)
+            .flat_map(|y| (0..maze.get_cols() - 1).map(move |x| Coord::new(x, y)))
+            .filter(|c| walled(c.x, c.y, constants::DIR_EAST) && walled(c.x, c.y, constants::DIR_SOUTH) &&
+                        walled(c.x + 1, c.y, constants::DIR_SOUTH) && walled(c.x, c.y + 1, constants::DIR_EAST))
+            .collect()
+    }
+
+    #[test]
+    fn test_perfect_maze() {
+        // Every block in a connected maze needs a loop
+        let mut maze = Maze::new(30, 30);
+        maze.generate_perfect_seeded(471).unwrap();
+        assert_eq!(0, cycles(&maze));
+        let before = fenced_blocks(&maze).len();
+        assert!(before > 3);
+        let opened = maze.ensure_local_openness();
+        assert!(opened as usize <= before);
+        assert!(fenced_blocks(&maze).is_empty());
+        assert_eq!(opened as i64, cycles(&maze));
+        assert!(maze.walls_consistent());
+    }
+
+    #[test]
+    fn test_separate_parts() {
+        // Squares that are carved but cut off from each other: every block
+        // is fenced, and every wall opened can join two parts instead of
+        // making a loop
+        let mut maze = Maze::from_compact("000000\n000000\n000000\n000000\n000000").unwrap();
+        for sq in maze.sq.iter_mut() {
+            sq.id = constants::ID_MAZE_PATH;
+        }
+        assert_eq!(20, fenced_blocks(&maze).len());
+        let opened = maze.ensure_local_openness();
+        assert!(opened > 0);
+        assert!(fenced_blocks(&maze).is_empty());
+        assert_eq!(0, cycles(&maze));
+    }
+
+    #[test]
+    fn test_skips_rock_and_rooms() {
+        let mut maze = Maze::new(4, 4);
+        assert_eq!(0, maze.ensure_local_openness());
+        maze.generate_seeded((1, 2, 2, 2, 2), 471).unwrap();
+        let before = maze.clone();
+        maze.ensure_local_openness();
+        for (old, new) in before.sq.iter().zip(maze.sq.iter()) {
+            if old.is_part_of_room() {
+                assert_eq!(old, new);
+            }
+        }
+    }
+}
@@ -0,0 +1,475 @@
+//! Coordinate and direction conventions for exported mazes.  The crate puts
+//! Square (0, 0) in the top left corner, counts y down the screen and
+//! writes coordinates as (x, y), but engines and importers differ: one
+//! counts y up from the bottom left, another wants (row, column) pairs.  An
+//! ExportOrientation describes the convention on the other side, and the
+//! `_oriented` exporters lay the Maze out in it, so nothing has to be
+//! flipped by hand.  The default orientation is the crate's own.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::render::{Tile, TileStyle, WallGrid};
+use crate::maze::transform::{mirror_dir, Transformable};
+use crate::maze::{Coord, Maze, Opening};
+
+/// The corner of the Maze that exported coordinates count from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Corner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Which way "north" points along the exported y axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum YDirection {
+    /// North is toward smaller y, as on a screen.
+    #[default]
+    Down,
+    /// North is toward larger y, as in a y-up engine.
+    Up,
+}
+
+/// The order of the two numbers in an exported coordinate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum XYOrder {
+    /// (column, row).
+    #[default]
+    XY,
+    /// (row, column).  Exported grids are transposed to match, so they're
+    /// still indexed by the second number, then the first.
+    YX,
+}
+
+/// The coordinate and direction conventions of an exported Maze.
+///
+/// The exported Maze is the original mirrored so origin is its top left
+/// corner, then transposed for XYOrder::YX, so the exported coordinates of
+/// a Square are its coordinates in the result.  Directions turn with it:
+/// east always points toward larger exported x.  y_axis then only names
+/// the two y directions; with YDirection::Up, what the crate calls south
+/// is exported as north.  Flipping the origin to the bottom without
+/// setting YDirection::Up swaps the maze's north and south walls.
+///
+/// # Example
+/// ```
+/// use mazegame::prelude::*;
+/// use mazegame::maze::orientation::{Corner, ExportOrientation, XYOrder, YDirection};
+///
+/// // A y-up engine counting from the bottom left
+/// let godot = ExportOrientation::new(Corner::BottomLeft, YDirection::Up, XYOrder::XY);
+/// assert_eq!(Coord::new(1, 3), godot.export_coord(Coord::new(1, 0), 5, 4));
+/// assert_eq!(DIR_NORTH, godot.export_dir(DIR_NORTH));
+/// assert_eq!(Coord::new(1, 0), godot.import_coord(Coord::new(1, 3), 5, 4));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExportOrientation {
+    pub origin: Corner,
+    pub y_axis: YDirection,
+    pub order: XYOrder,
+}
+
+impl ExportOrientation {
+    pub fn new(origin: Corner, y_axis: YDirection, order: XYOrder) -> ExportOrientation {
+        ExportOrientation { origin, y_axis, order }
+    }
+
+    /// The (width, height) of a cols x rows Maze once exported.
+    pub fn exported_size(&self, cols: u32, rows: u32) -> (u32, u32) {
+        match self.order {
+            XYOrder::XY => (cols, rows),
+            XYOrder::YX => (rows, cols),
+        }
+    }
+
+    /// Where Square c of a cols x rows Maze is in exported coordinates.
+    pub fn export_coord(&self, c: Coord, cols: u32, rows: u32) -> Coord {
+        let x = if self.flips_x() { cols - 1 - c.x } else { c.x };
+        let y = if self.flips_y() { rows - 1 - c.y } else { c.y };
+        match self.order {
+            XYOrder::XY => Coord::new(x, y),
+            XYOrder::YX => Coord::new(y, x),
+        }
+    }
+
+    /// The Square of a cols x rows Maze at exported coordinates c.  The
+    /// reverse of export_coord().
+    pub fn import_coord(&self, c: Coord, cols: u32, rows: u32) -> Coord {
+        let (x, y) = match self.order {
+            XYOrder::XY => (c.x, c.y),
+            XYOrder::YX => (c.y, c.x),
+        };
+        Coord::new(if self.flips_x() { cols - 1 - x } else { x },
+                   if self.flips_y() { rows - 1 - y } else { y })
+    }
+
+    /// The exported name of direction dir.
+    pub fn export_dir(&self, dir: u32) -> u32 {
+        let mut dir = dir;
+        if self.flips_x() {
+            dir = mirror_dir(dir, constants::DIR_EAST);
+        }
+        if self.flips_y() {
+            dir = mirror_dir(dir, constants::DIR_NORTH);
+        }
+        if self.order == XYOrder::YX {
+            dir = transpose_dir(dir);
+        }
+        if self.y_axis == YDirection::Up {
+            dir = mirror_dir(dir, constants::DIR_NORTH);
+        }
+        dir
+    }
+
+    /// The direction an exported direction names.  The reverse of
+    /// export_dir().
+    pub fn import_dir(&self, dir: u32) -> u32 {
+        // Each step is its own reverse, so they're undone in reverse order
+        let mut dir = dir;
+        if self.y_axis == YDirection::Up {
+            dir = mirror_dir(dir, constants::DIR_NORTH);
+        }
+        if self.order == XYOrder::YX {
+            dir = transpose_dir(dir);
+        }
+        if self.flips_y() {
+            dir = mirror_dir(dir, constants::DIR_NORTH);
+        }
+        if self.flips_x() {
+            dir = mirror_dir(dir, constants::DIR_EAST);
+        }
+        dir
+    }
+
+    /// Lays out the per-Square values of a cols x rows Maze, like a
+    /// MazeData's, in exported order.
+    pub fn export_cells<T: Clone>(&self, cells: &[T], cols: u32, rows: u32) -> Vec<T> {
+        let (width, height) = self.exported_size(cols, rows);
+        let mut out = Vec::with_capacity(cells.len());
+        for y in 0..height {
+            for x in 0..width {
+                let c = self.import_coord(Coord::new(x, y), cols, rows);
+                out.push(cells[(c.y * cols + c.x) as usize].clone());
+            }
+        }
+        out
+    }
+
+    /// Puts per-Square values laid out by export_cells() back in the
+    /// order of the cols x rows Maze they came from.
+    pub fn import_cells<T: Clone>(&self, cells: &[T], cols: u32, rows: u32) -> Vec<T> {
+        let (width, _) = self.exported_size(cols, rows);
+        let mut out = Vec::with_capacity(cells.len());
+        for y in 0..rows {
+            for x in 0..cols {
+                let c = self.export_coord(Coord::new(x, y), cols, rows);
+                out.push(cells[(c.y * width + c.x) as usize].clone());
+            }
+        }
+        out
+    }
+
+    //
+    // Internal - whether exported x counts from the east side.
+    //
+    fn flips_x(&self) -> bool {
+        self.origin == Corner::TopRight || self.origin == Corner::BottomRight
+    }
+
+    //
+    // Internal - whether exported y counts from the south side.
+    //
+    fn flips_y(&self) -> bool {
+        self.origin == Corner::BottomLeft || self.origin == Corner::BottomRight
+    }
+}
+
+impl Maze {
+    /// Returns the Maze laid out in orientation: mirrored and transposed so
+    /// each Square sits at its exported coordinates, with its walls turned
+    /// to match.  The result is an ordinary Maze; y_axis only changes what
+    /// the directions are called, so it's ignored here.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::orientation::{Corner, ExportOrientation, XYOrder, YDirection};
+    ///
+    /// let mut maze = Maze::new(3, 5);
+    /// maze.generate_perfect_seeded(488).unwrap();
+    /// let rows_first = ExportOrientation::new(Corner::TopLeft, YDirection::Down, XYOrder::YX);
+    /// let turned = maze.to_orientation(&rows_first);
+    /// assert_eq!((3, 5), (turned.get_cols(), turned.get_rows()));
+    /// assert!(turned.walls_consistent());
+    /// assert_eq!(maze, turned.from_orientation(&rows_first));
+    /// ```
+    pub fn to_orientation(&self, orientation: &ExportOrientation) -> Maze {
+        let mut maze = self.clone();
+        if orientation.flips_x() {
+            maze = maze.mirror_x();
+        }
+        if orientation.flips_y() {
+            maze = maze.mirror_y();
+        }
+        if orientation.order == XYOrder::YX {
+            maze = maze.rotate_cw().mirror_x();
+        }
+        if !self.elevations.is_empty() {
+            maze.elevations = orientation.export_cells(&self.elevations, self.cols, self.rows);
+        }
+        if !self.corridor_regions.is_empty() {
+            maze.corridor_regions = orientation.export_cells(&self.corridor_regions, self.cols, self.rows);
+        }
+        maze
+    }
+
+    /// Undoes to_orientation(), returning the Maze that was laid out in
+    /// orientation.
+    pub fn from_orientation(&self, orientation: &ExportOrientation) -> Maze {
+        let mut maze = self.clone();
+        if orientation.order == XYOrder::YX {
+            maze = maze.rotate_cw().mirror_x();
+        }
+        if orientation.flips_y() {
+            maze = maze.mirror_y();
+        }
+        if orientation.flips_x() {
+            maze = maze.mirror_x();
+        }
+        if !self.elevations.is_empty() {
+            maze.elevations = orientation.import_cells(&self.elevations, maze.cols, maze.rows);
+        }
+        if !self.corridor_regions.is_empty() {
+            maze.corridor_regions = orientation.import_cells(&self.corridor_regions, maze.cols, maze.rows);
+        }
+        maze
+    }
+
+    /// Expands the Maze into a WallGrid laid out in orientation.  The grid
+    /// is the default one mirrored or transposed to match.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::orientation::{Corner, ExportOrientation, XYOrder, YDirection};
+    ///
+    /// let mut maze = mazegame::Maze::new(4, 6);
+    /// maze.generate_perfect_seeded(488).unwrap();
+    /// let (top, bottom) = (maze.to_wall_grid(), maze.to_wall_grid_oriented(
+    ///     &ExportOrientation::new(Corner::BottomLeft, YDirection::Up, XYOrder::XY)));
+    /// assert_eq!(top.is_wall(3, 0), bottom.is_wall(3, top.height - 1));
+    /// ```
+    pub fn to_wall_grid_oriented(&self, orientation: &ExportOrientation) -> WallGrid {
+        self.to_orientation(orientation).to_wall_grid()
+    }
+
+    /// Exports the Maze as a tile map in style, laid out in orientation and
+    /// indexed by the second exported coordinate, then the first.
+    pub fn to_tilemap_oriented(&self, style: TileStyle, orientation: &ExportOrientation) -> Vec<Vec<Tile>> {
+        self.to_orientation(orientation).to_tilemap(style)
+    }
+
+    /// Writes the Maze as JSON laid out in orientation.  With
+    /// YDirection::Up the north and south walls, and the directions of the
+    /// entrance, exits and doors, are written under each other's names, so
+    /// the JSON only reads back through from_json_oriented() with the same
+    /// orientation.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::orientation::{Corner, ExportOrientation, XYOrder, YDirection};
+    ///
+    /// let mut maze = mazegame::Maze::new(6, 4);
+    /// maze.generate_perfect_seeded(488).unwrap();
+    /// let unity = ExportOrientation::new(Corner::BottomRight, YDirection::Up, XYOrder::YX);
+    /// let json = maze.to_json_oriented(&unity).unwrap();
+    /// assert_eq!(maze, mazegame::Maze::from_json_oriented(&json, &unity).unwrap());
+    /// ```
+    pub fn to_json_oriented(&self, orientation: &ExportOrientation) -> Result<String, MazeError> {
+        let mut maze = self.to_orientation(orientation);
+        if orientation.y_axis == YDirection::Up {
+            maze.swap_north_south_names();
+        }
+        maze.to_json()
+    }
+
+    /// Reads a Maze written by to_json_oriented() in orientation.
+    #[cfg(feature = "serde")]
+    pub fn from_json_oriented(text: &str, orientation: &ExportOrientation) -> Result<Maze, MazeError> {
+        let mut maze = Maze::from_json(text)?;
+        if orientation.y_axis == YDirection::Up {
+            maze.swap_north_south_names();
+        }
+        Ok(maze.from_orientation(orientation))
+    }
+
+    //
+    // Internal - renames every north wall, passage and opening south and
+    // every south one north, without moving anything.
+    //
+    fn swap_north_south_names(&mut self) {
+        let (north, south) = (constants::DIR_NORTH as usize, constants::DIR_SOUTH as usize);
+        let swap_bits = |bits: u8| {
+            let (n, s) = ((bits >> north) & 1, (bits >> south) & 1);
+            bits & !(1 << north | 1 << south) | n << south | s << north
+        };
+        for sq in self.sq.iter_mut() {
+            sq.wall_present.swap(north, south);
+            sq.one_way = swap_bits(sq.one_way);
+            sq.secret = swap_bits(sq.secret);
+        }
+        let rename = |o: &mut Opening| o.dir = mirror_dir(o.dir, constants::DIR_NORTH);
+        self.entrance.iter_mut().for_each(rename);
+        self.exits.iter_mut().for_each(rename);
+        for door in self.doors.iter_mut() {
+            door.dir = mirror_dir(door.dir, constants::DIR_NORTH);
+        }
+    }
+}
+
+//
+// Internal - the direction dir becomes when x and y swap.
+//
+fn transpose_dir(dir: u32) -> u32 {
+    match dir {
+        constants::DIR_NORTH => constants::DIR_WEST,
+        constants::DIR_WEST => constants::DIR_NORTH,
+        constants::DIR_SOUTH => constants::DIR_EAST,
+        _ => constants::DIR_SOUTH,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_orientations() -> Vec<ExportOrientation> {
+        let mut out = Vec::new();
+        for &origin in [Corner::TopLeft, Corner::TopRight, Corner::BottomLeft, Corner::BottomRight].iter() {
+            for &y_axis in [YDirection::Down, YDirection::Up].iter() {
+                for &order in [XYOrder::XY, XYOrder::YX].iter() {
+                    out.push(ExportOrientation::new(origin, y_axis, order));
+                }
+            }
+        }
+        out
+    }
+
+    fn dungeon() -> Maze {
+        let mut maze = Maze::new(9, 13);
+        maze.generate_seeded((3, 2, 3, 2, 3), 488).unwrap();
+        maze.connect_regions_with_rng(&mut crate::rng::from_seed(488));
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        maze.set_exit(12, 8, constants::DIR_SOUTH).unwrap();
+        maze
+    }
+
+    // A dungeon with one-way ledges between its levels
+    fn ledges() -> Maze {
+        let mut maze = dungeon();
+        maze.assign_elevations(3, 488).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_default_is_unchanged() {
+        let maze = ledges();
+        let o = ExportOrientation::default();
+        assert_eq!(maze, maze.to_orientation(&o));
+        assert_eq!(maze.to_wall_grid(), maze.to_wall_grid_oriented(&o));
+        assert_eq!(maze.to_tilemap(TileStyle::Thick), maze.to_tilemap_oriented(TileStyle::Thick, &o));
+        #[cfg(feature = "serde")]
+        assert_eq!(maze.to_json().unwrap(), maze.to_json_oriented(&o).unwrap());
+        for dir in 0..constants::NUM_DIRECTIONS {
+            assert_eq!(dir, o.export_dir(dir));
+        }
+    }
+
+    #[test]
+    fn test_coords_and_dirs() {
+        let maze = ledges();
+        for o in all_orientations() {
+            let turned = maze.to_orientation(&o);
+            assert_eq!(o.exported_size(13, 9), (turned.get_cols(), turned.get_rows()));
+            for i in 0..maze.sq.len() {
+                let c = maze.get_coord(i);
+                let e = o.export_coord(c, 13, 9);
+                assert_eq!(c, o.import_coord(e, 13, 9));
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    assert_eq!(dir, o.import_dir(o.export_dir(dir)));
+                    // The exported direction is the wall in the laid out Maze,
+                    // once y_axis has renamed it
+                    let laid_out = mirror_dir(o.export_dir(dir), constants::DIR_NORTH);
+                    let laid_out = if o.y_axis == YDirection::Up { laid_out } else { o.export_dir(dir) };
+                    assert_eq!(maze.sq[i].is_wall_present(dir),
+                               turned.sq[turned.get_offset(e.x, e.y)].is_wall_present(laid_out), "{:?}", o);
+                }
+            }
+        }
+
+        // Counting from the bottom without turning y up swaps north and south
+        let flipped = ExportOrientation::new(Corner::BottomLeft, YDirection::Down, XYOrder::XY);
+        assert_eq!(constants::DIR_SOUTH, flipped.export_dir(constants::DIR_NORTH));
+        let godot = ExportOrientation::new(Corner::BottomLeft, YDirection::Up, XYOrder::XY);
+        assert_eq!(constants::DIR_NORTH, godot.export_dir(constants::DIR_NORTH));
+        assert_eq!(constants::DIR_EAST, godot.export_dir(constants::DIR_EAST));
+    }
+
+    #[test]
+    fn test_grids_flip_and_transpose() {
+        // Without ledges, since a one-way passage's grid cell depends on
+        // which of its Squares is drawn last
+        let maze = dungeon();
+        let grid = maze.to_wall_grid();
+        let (w, h) = (grid.width, grid.height);
+        for o in all_orientations() {
+            let other = maze.to_wall_grid_oriented(&o);
+            let thin = maze.to_tilemap(TileStyle::Thin);
+            let other_thin = maze.to_tilemap_oriented(TileStyle::Thin, &o);
+            for y in 0..h {
+                for x in 0..w {
+                    let fx = if o.flips_x() { w - 1 - x } else { x };
+                    let fy = if o.flips_y() { h - 1 - y } else { y };
+                    let (ex, ey) = if o.order == XYOrder::YX { (fy, fx) } else { (fx, fy) };
+                    assert_eq!(grid.is_wall(x, y), other.is_wall(ex, ey), "{:?} ({}, {})", o, x, y);
+                }
+            }
+            for c in (0..maze.sq.len()).map(|i| maze.get_coord(i)) {
+                let e = o.export_coord(c, 13, 9);
+                assert_eq!(thin[c.y as usize][c.x as usize], other_thin[e.y as usize][e.x as usize]);
+            }
+        }
+
+        // Two orientations differing only in order are transposes
+        let xy = maze.to_tilemap_oriented(TileStyle::Thick, &ExportOrientation::default());
+        let yx = maze.to_tilemap_oriented(TileStyle::Thick,
+                                          &ExportOrientation::new(Corner::TopLeft, YDirection::Down, XYOrder::YX));
+        assert_eq!(xy.len(), yx[0].len());
+        for (y, row) in xy.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                assert_eq!(*tile, yx[x][y]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let maze = ledges();
+        for o in all_orientations() {
+            assert_eq!(maze, maze.to_orientation(&o).from_orientation(&o), "{:?}", o);
+            assert_eq!(maze.elevations(), maze.to_orientation(&o).from_orientation(&o).elevations());
+            #[cfg(feature = "serde")]
+            {
+                let json = maze.to_json_oriented(&o).unwrap();
+                assert_eq!(maze, Maze::from_json_oriented(&json, &o).unwrap(), "{:?}", o);
+            }
+        }
+    }
+}
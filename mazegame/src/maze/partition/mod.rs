@@ -0,0 +1,316 @@
+//! Splitting a Maze into territories around a set of sources, like spawn
+//! points, so each gets the Squares it's closest to, and splitting the
+//! corridors into regions of about the same size, so a renderer can give
+//! each its own look.  The corridor regions are forgotten whenever the
+//! layout changes, since they may no longer be joined up.
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// How far partition_corridors() lets region sizes drift apart: unless a
+/// region is hemmed in by the others, none ends up bigger than this many
+/// times the mean size.
+pub const CORRIDOR_BALANCE: u32 = 2;
+
+impl Maze {
+    /// Assigns every Square to the index of its nearest source, counted in
+    /// steps through the maze.  A Square that's as close to several sources
+    /// goes to the one with the lowest index.  Squares no source can reach
+    /// get None, and sources outside the Maze are ignored.
+    ///
+    /// Returns the owner of each Square in row-major order, along with the
+    /// number of Squares each source got.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let maze = Maze::from_compact("4cccccc8").unwrap();
+    /// let (owners, sizes) = maze.partition_by_distance(&[Coord::new(0, 0), Coord::new(7, 0)]);
+    /// assert_eq!(Some(0), owners[3]);
+    /// assert_eq!(Some(1), owners[4]);
+    /// assert_eq!(vec![4, 4], sizes);
+    /// ```
+    pub fn partition_by_distance(&self, sources: &[Coord]) -> (Vec<Option<usize>>, Vec<u32>) {
+        let mut owners = vec![None; self.sq.len()];
+        let mut sizes = vec![0; sources.len()];
+        let mut queue = VecDeque::new();
+        // Starting the queue in source order keeps every step of the search
+        // in source order too, so ties go to the lowest index
+        for (i, source) in sources.iter().enumerate() {
+            if !self.in_bounds(source.x, source.y) {
+                continue;
+            }
+            let offset = self.get_offset(source.x, source.y);
+            if owners[offset].is_none() {
+                owners[offset] = Some(i);
+                sizes[i] += 1;
+                queue.push_back((*source, i));
+            }
+        }
+
+        while let Some((cur, owner)) = queue.pop_front() {
+            for next in self.open_neighbors(cur.x, cur.y) {
+                let offset = self.get_offset(next.x, next.y);
+                if owners[offset].is_none() {
+                    owners[offset] = Some(owner);
+                    sizes[owner] += 1;
+                    queue.push_back((next, owner));
+                }
+            }
+        }
+        (owners, sizes)
+    }
+}
+
+impl Maze {
+    /// Splits the corridors (the carved Squares outside of rooms) into
+    /// `regions` areas that are each joined up through their own Squares,
+    /// and remembers each Square's region for corridor_region().  Returns
+    /// the number of Squares in each region.  The same seed always gives the
+    /// same regions.
+    ///
+    /// The regions grow out together from centers spread over the
+    /// corridors, with the smallest region growing next, so they come out
+    /// about the same size; see CORRIDOR_BALANCE.  Stretches of corridor cut
+    /// off from each other by rooms get a center each before any gets a
+    /// second, so it's an error to ask for fewer regions than there are
+    /// stretches, or more than there are corridor Squares.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::from_compact("4cccccc8").unwrap();
+    /// assert_eq!(vec![4, 4], maze.partition_corridors(2, 1).unwrap());
+    /// assert_eq!(maze.corridor_region(Coord::new(0, 0)), maze.corridor_region(Coord::new(3, 0)));
+    /// assert_ne!(maze.corridor_region(Coord::new(0, 0)), maze.corridor_region(Coord::new(7, 0)));
+    /// ```
+    pub fn partition_corridors(&mut self, regions: u32, seed: u64) -> Result<Vec<u32>, MazeError> {
+        self.partition_corridors_with_rng(regions, &mut rng::from_seed(seed))
+    }
+
+    /// Splits the corridors into regions like partition_corridors(), using
+    /// rng.
+    pub fn partition_corridors_with_rng<R: Rng>(&mut self, regions: u32, rng: &mut R) -> Result<Vec<u32>, MazeError> {
+        let corridor: Vec<bool> = self.sq.iter().map(|sq| sq.is_carved() && !sq.is_part_of_room()).collect();
+        let count = corridor.iter().filter(|&&c| c).count();
+        if regions == 0 || regions as usize > count {
+            return Err(MazeError::InvalidArgument(
+                format!("can't split {} corridor Squares into {} regions", count, regions)));
+        }
+
+        // Farthest point sampling: each center is the corridor Square the
+        // most steps from all the others, with Squares no center can reach
+        // counting as farthest of all
+        let first = corridor.iter().enumerate().filter(|&(_, &c)| c).nth(rng.gen_range(0, count)).unwrap().0;
+        let mut centers = vec![first];
+        let mut nearest = self.corridor_distances(&corridor, first);
+        while centers.len() < regions as usize {
+            let next = (0..self.sq.len())
+                .filter(|&i| corridor[i] && !centers.contains(&i))
+                .max_by_key(|&i| (nearest[i].map_or(u32::MAX, |d| d), core::cmp::Reverse(i)))
+                .unwrap();
+            for (near, d) in nearest.iter_mut().zip(self.corridor_distances(&corridor, next)) {
+                if let Some(d) = d {
+                    *near = Some(near.map_or(d, |n| n.min(d)));
+                }
+            }
+            centers.push(next);
+        }
+        if let Some(cut_off) = (0..self.sq.len()).find(|&i| corridor[i] && nearest[i].is_none()) {
+            let c = self.get_coord(cut_off);
+            return Err(MazeError::InvalidArgument(
+                format!("{} regions aren't enough to reach every corridor; ({}, {}) is cut off", regions, c.x, c.y)));
+        }
+
+        // Grow the regions together, always the smallest one next
+        let mut owners: Vec<Option<u32>> = vec![None; self.sq.len()];
+        let mut sizes = vec![1; centers.len()];
+        let mut frontiers: Vec<VecDeque<usize>> = centers.iter().map(|&c| VecDeque::from(vec![c])).collect();
+        for (region, &c) in centers.iter().enumerate() {
+            owners[c] = Some(region as u32);
+        }
+        while let Some(region) = (0..frontiers.len()).filter(|&r| !frontiers[r].is_empty()).min_by_key(|&r| sizes[r]) {
+            let cur = self.get_coord(frontiers[region].pop_front().unwrap());
+            for next in self.open_neighbors(cur.x, cur.y) {
+                let offset = self.get_offset(next.x, next.y);
+                if corridor[offset] && owners[offset].is_none() {
+                    owners[offset] = Some(region as u32);
+                    sizes[region] += 1;
+                    frontiers[region].push_back(offset);
+                }
+            }
+        }
+
+        gen_debug!("partition_corridors regions={} sizes={:?}", regions, sizes);
+        self.corridor_regions = owners;
+        Ok(sizes)
+    }
+
+    /// Returns the corridor region partition_corridors() put coord in, or
+    /// None if it isn't a corridor or the corridors haven't been split since
+    /// the layout last changed.
+    pub fn corridor_region(&self, coord: Coord) -> Option<u32> {
+        self.index_of(coord.x, coord.y).and_then(|i| self.corridor_regions.get(i).cloned().flatten())
+    }
+
+    /// Returns the corridor region of every Square, indexed like `sq`, or an
+    /// empty slice if the corridors haven't been split.
+    pub fn corridor_regions(&self) -> &[Option<u32>] {
+        &self.corridor_regions
+    }
+
+    //
+    // Internal - steps from start to every Square, moving only through
+    // corridor Squares.
+    //
+    fn corridor_distances(&self, corridor: &[bool], start: usize) -> Vec<Option<u32>> {
+        let mut dist = vec![None; self.sq.len()];
+        dist[start] = Some(0);
+        let mut queue = VecDeque::from(vec![start]);
+        while let Some(cur) = queue.pop_front() {
+            let (c, d) = (self.get_coord(cur), dist[cur].unwrap());
+            for next in self.open_neighbors(c.x, c.y) {
+                let offset = self.get_offset(next.x, next.y);
+                if corridor[offset] && dist[offset].is_none() {
+                    dist[offset] = Some(d + 1);
+                    queue.push_back(offset);
+                }
+            }
+        }
+        dist
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corridor_split() {
+        // Nine Squares in a row, so the middle one is a tie
+        let maze = Maze::from_compact("4ccccccc8").unwrap();
+        let (owners, sizes) = maze.partition_by_distance(&[Coord::new(0, 0), Coord::new(8, 0)]);
+        assert_eq!(vec![Some(0), Some(0), Some(0), Some(0), Some(0), Some(1), Some(1), Some(1), Some(1)], owners);
+        assert_eq!(vec![5, 4], sizes);
+
+        let (owners, sizes) = maze.partition_by_distance(&[Coord::new(8, 0), Coord::new(0, 0)]);
+        assert_eq!(Some(0), owners[4]);
+        assert_eq!(vec![5, 4], sizes);
+    }
+
+    #[test]
+    fn test_three_way_tie() {
+        // A plus sign: the center and the bottom arm are as close to all
+        // three sources
+        let maze = Maze::from_compact("020\n4f8\n010").unwrap();
+        let sources = [Coord::new(2, 1), Coord::new(0, 1), Coord::new(1, 0)];
+        let (owners, sizes) = maze.partition_by_distance(&sources);
+        assert_eq!(vec![None, Some(2), None,
+                        Some(1), Some(0), Some(0),
+                        None, Some(0), None], owners);
+        assert_eq!(vec![3, 1, 1], sizes);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let mut maze = Maze::from_compact("4800\n0000").unwrap();
+        maze.carve(2, 1, crate::constants::DIR_EAST, crate::constants::ID_MAZE_PATH, false).unwrap();
+        let (owners, sizes) = maze.partition_by_distance(&[Coord::new(1, 0), Coord::new(9, 9), Coord::new(1, 0)]);
+        assert_eq!(vec![Some(0), Some(0), None, None, None, None, None, None], owners);
+        assert_eq!(vec![2, 0, 0], sizes);
+    }
+
+    // Checks that the regions cover exactly the corridors, that each is
+    // joined up and that sizes match, and returns the sizes
+    fn assert_partition(maze: &Maze, sizes: &[u32]) {
+        let regions = maze.corridor_regions();
+        for (i, sq) in maze.sq.iter().enumerate() {
+            assert_eq!(sq.is_carved() && !sq.is_part_of_room(), regions[i].is_some(), "{:?}", maze.get_coord(i));
+        }
+        for (region, &size) in sizes.iter().enumerate() {
+            let region = Some(region as u32);
+            let start = (0..regions.len()).find(|&i| regions[i] == region).unwrap();
+            let mut seen = vec![false; regions.len()];
+            seen[start] = true;
+            let mut stack = vec![start];
+            let mut reached = 0;
+            while let Some(cur) = stack.pop() {
+                reached += 1;
+                let c = maze.get_coord(cur);
+                for n in maze.open_neighbors(c.x, c.y) {
+                    let offset = maze.get_offset(n.x, n.y);
+                    if regions[offset] == region && !seen[offset] {
+                        seen[offset] = true;
+                        stack.push(offset);
+                    }
+                }
+            }
+            assert_eq!(size, reached);
+            assert_eq!(size as usize, regions.iter().filter(|&&r| r == region).count());
+        }
+    }
+
+    #[test]
+    fn test_partition_corridors() {
+        for seed in 0..8 {
+            let mut maze = Maze::new(30, 30);
+            maze.generate_seeded((4, 3, 5, 3, 5), 458 + seed).unwrap();
+            let sizes = maze.partition_corridors(6, seed).unwrap();
+            assert_eq!(6, sizes.len());
+            assert_partition(&maze, &sizes);
+            let mean = sizes.iter().sum::<u32>() / 6;
+            assert!(sizes.iter().all(|&s| s <= mean * CORRIDOR_BALANCE), "{:?}", sizes);
+        }
+        for seed in 0..5 {
+            let mut maze = Maze::new(60, 60);
+            maze.generate_perfect_seeded(seed).unwrap();
+            let sizes = maze.partition_corridors(12, seed).unwrap();
+            assert_partition(&maze, &sizes);
+            assert!(sizes.iter().all(|&s| s <= 300 * CORRIDOR_BALANCE), "{:?}", sizes);
+        }
+    }
+
+    #[test]
+    fn test_partition_corridors_same_seed() {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_seeded((3, 2, 4, 2, 4), 1).unwrap();
+        let sizes = maze.partition_corridors(4, 9).unwrap();
+        let regions = maze.corridor_regions().to_vec();
+        assert_eq!(sizes, maze.partition_corridors(4, 9).unwrap());
+        assert_eq!(regions, maze.corridor_regions());
+        assert_ne!(regions, { maze.partition_corridors(4, 10).unwrap(); maze.corridor_regions() });
+
+        // Changing the layout forgets them
+        let mut carved = maze.clone();
+        carved.carve(0, 0, crate::constants::DIR_EAST, crate::constants::ID_MAZE_PATH, false).unwrap();
+        assert!(carved.corridor_regions().is_empty());
+        maze.reset();
+        assert!(maze.corridor_regions().is_empty());
+        assert_eq!(None, maze.corridor_region(Coord::new(0, 0)));
+    }
+
+    #[test]
+    fn test_partition_corridors_errors() {
+        let mut maze = Maze::from_compact("4cc8").unwrap();
+        assert!(maze.partition_corridors(0, 1).is_err());
+        assert!(maze.partition_corridors(5, 1).is_err());
+        assert_eq!(vec![1, 1, 1, 1], maze.partition_corridors(4, 1).unwrap());
+
+        // Two corridors a room keeps apart need a region each
+        let mut split = Maze::from_compact("4cc8").unwrap();
+        split.sq[1].id = 1;
+        split.sq[2].id = 1;
+        split.num_rooms = 1;
+        assert!(split.partition_corridors(1, 1).unwrap_err().to_string().contains("cut off"));
+        assert_eq!(vec![1, 1], split.partition_corridors(2, 1).unwrap());
+        assert!(split.corridor_region(Coord::new(0, 0)).is_some());
+        assert_ne!(split.corridor_region(Coord::new(0, 0)), split.corridor_region(Coord::new(3, 0)));
+        assert_eq!(None, split.corridor_region(Coord::new(1, 0)));
+    }
+}
@@ -0,0 +1,687 @@
+//! Pressure plates and the remote doors they hold open.  A door is a wall
+//! between two Squares that stands open while something rests on one of
+//! its plates and shuts again as soon as nothing does.  The player opens a
+//! door by standing on a plate, but can't be in two places at once, so
+//! getting through usually means pushing a block onto the plate and
+//! leaving it there.
+//!
+//! A PlateSetup is the puzzle's data - the links and where the blocks
+//! start - and can be placed on a Maze so it's always solvable.
+//! PlatePuzzle plays one, opening and closing the doors through
+//! break_wall_pair_checked() and build_wall_pair_checked() so take_changes()
+//! and the returned events report every door that moves.
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::dig::{WallBroken, WallBuilt};
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// The most states solve_with_plates() looks at before giving up.  Each
+/// state is a place for the player and every block, so this bounds the
+/// size of maze and number of blocks a puzzle can have.
+pub const MAX_PLATE_STATES: usize = 1 << 20;
+
+// How many candidate plates, doors and blocks PlateSetup::place() tries for
+// each link before giving up on it
+const PLACE_ATTEMPTS: u32 = 64;
+
+/// A pressure plate and the door it opens: the wall on side dir of door.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PlateLink {
+    pub plate: Coord,
+    pub door: Coord,
+    pub dir: u32,
+}
+
+/// The plates and doors of a puzzle, and the Squares its blocks start on.
+/// A door can have several plates, and opens when any of them is pressed.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PlateSetup {
+    pub links: Vec<PlateLink>,
+    pub blocks: Vec<Coord>,
+}
+
+/// Something that happened during a PlatePuzzle move.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlateEvent {
+    /// The player moved onto this Square.
+    Moved(Coord),
+    /// A block was pushed from one Square to the next.
+    BlockPushed { from: Coord, to: Coord },
+    /// A door opened as its plate was pressed.
+    DoorOpened(WallBroken),
+    /// A door shut as its last plate was let go.
+    DoorClosed(WallBuilt),
+    /// The player reached an exit.
+    ReachedExit,
+}
+
+impl PlateSetup {
+    /// Checks that every plate, door and block is in maze, that every door
+    /// is between two Squares, and that no two blocks share a Square.
+    pub fn validate(&self, maze: &Maze) -> Result<(), MazeError> {
+        for link in self.links.iter() {
+            for c in [link.plate, link.door].iter() {
+                if !maze.in_bounds(c.x, c.y) {
+                    return Err(MazeError::OutOfBounds(c.x, c.y));
+                }
+            }
+            if link.dir >= constants::NUM_DIRECTIONS || maze.neighbor(link.door.x, link.door.y, link.dir).is_none() {
+                return Err(MazeError::InvalidArgument(format!("({}, {}) has no door on side {}",
+                                                              link.door.x, link.door.y, link.dir)));
+            }
+        }
+        for (i, b) in self.blocks.iter().enumerate() {
+            if !maze.in_bounds(b.x, b.y) {
+                return Err(MazeError::OutOfBounds(b.x, b.y));
+            }
+            if self.blocks[..i].contains(b) {
+                return Err(MazeError::InvalidArgument(format!("two blocks start on ({}, {})", b.x, b.y)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds a setup for maze of count plate and door pairs, each with one
+    /// block, that still lets the player get from start to goal.  Each
+    /// door closes a passage on the way from start to goal, so in a perfect
+    /// maze every block is needed; in one with loops there may be a way
+    /// around.  Plates and blocks avoid start, goal and every Square in
+    /// avoid.  Returns the setup, and whether all count pairs fit.  The same
+    /// seed always gives the same setup.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::plates::PlateSetup;
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(8, 8);
+    /// maze.generate_perfect_seeded(489).unwrap();
+    /// let (setup, all_placed) = PlateSetup::place(&maze, 1, Coord::new(0, 0), Coord::new(7, 7), 3, &[]);
+    /// assert!(all_placed);
+    /// assert!(maze.solve(Coord::new(0, 0), Coord::new(7, 7)).is_some());
+    /// assert!(maze.solve_with_plates(&setup, Coord::new(0, 0), Coord::new(7, 7)).unwrap().is_some());
+    /// ```
+    pub fn place(maze: &Maze, count: u32, start: Coord, goal: Coord, seed: u64, avoid: &[Coord])
+                 -> (PlateSetup, bool) {
+        PlateSetup::place_with_rng(maze, count, start, goal, avoid, &mut rng::from_seed(seed))
+    }
+
+    /// Places plates like place(), using rng.
+    pub fn place_with_rng<R: Rng>(maze: &Maze, count: u32, start: Coord, goal: Coord, avoid: &[Coord],
+                                  rng: &mut R) -> (PlateSetup, bool) {
+        let mut setup = PlateSetup::default();
+        for placed in 0..count {
+            let mut added = false;
+            // The route only changes once a pair is added
+            if let Ok(Some(route)) = maze.solve_with_plates(&setup, start, goal) {
+                for _ in 0..PLACE_ATTEMPTS {
+                    if let Some(candidate) = setup.with_candidate(maze, start, goal, avoid, &route, rng) {
+                        if let Ok(Some(_)) = maze.solve_with_plates(&candidate, start, goal) {
+                            setup = candidate;
+                            added = true;
+                            break;
+                        }
+                    }
+                }
+            }
+            if !added {
+                gen_debug!("place_plates count={} placed={}", count, placed);
+                return (setup, false);
+            }
+        }
+        gen_debug!("place_plates count={} placed={}", count, count);
+        (setup, true)
+    }
+
+    //
+    // Internal - the setup with one more randomly picked link and block:
+    // the door across a passage of route, and a block one push away from
+    // its plate, both on the near side of the door with room for the
+    // player behind the block.
+    //
+    fn with_candidate<R: Rng>(&self, maze: &Maze, start: Coord, goal: Coord, avoid: &[Coord],
+                              route: &[u32], rng: &mut R) -> Option<PlateSetup> {
+        let mut at = start;
+        let mut passages = Vec::new();
+        for &dir in route.iter() {
+            let next = maze.step_through(at.x, at.y, dir)?;
+            passages.push((at, dir));
+            at = next;
+        }
+        if passages.is_empty() {
+            return None;
+        }
+        let (door, dir) = passages[rng.gen_range(0, passages.len())];
+        let other = maze.neighbor(door.x, door.y, dir)?;
+        if Some(other) != maze.step_through(door.x, door.y, dir) ||
+            self.links.iter().any(|l| is_same_door(maze, l, door, dir)) {
+            return None;
+        }
+
+        // The Squares the player can walk to with every door shut, the new
+        // one included
+        let shut = |c: Coord, side: u32| {
+            is_same_door_at(maze, door, dir, c, side) || self.links.iter().any(|l| is_same_door(maze, l, c, side))
+        };
+        let near = walkable(maze, start, &shut, None);
+
+        // A plate beside its door would let the player walk through alone
+        let free = |c: Coord| {
+            near[maze.get_offset(c.x, c.y)] && c != start && c != goal && c != door && c != other &&
+                !avoid.contains(&c) && !self.blocks.contains(&c) && !self.links.iter().any(|l| l.plate == c)
+        };
+        let mut pushes = Vec::new();
+        for i in 0..maze.sq.len() {
+            let block = maze.get_coord(i);
+            for &push in Direction::ALL.iter() {
+                let ahead = open_step(maze, block, push.into());
+                let behind = open_step(maze, block, push.opposite().into());
+                if let (Some(plate), Some(behind)) = (ahead, behind) {
+                    if free(block) && free(plate) {
+                        pushes.push((block, plate, behind));
+                    }
+                }
+            }
+        }
+        // A block parked in a dead end stays out of the way, so those plates
+        // are picked when there are any
+        let dead_ends: Vec<(Coord, Coord, Coord)> = pushes.iter().cloned()
+            .filter(|&(_, plate, _)| maze.open_neighbors(plate.x, plate.y).len() == 1)
+            .collect();
+        let mut pushes = if dead_ends.is_empty() { pushes } else { dead_ends };
+        rng.shuffle(&mut pushes);
+        // The player has to get behind the block without going through it,
+        // and on to the door once it's parked, through any earlier doors
+        // their own blocks hold open
+        let door_at = maze.get_offset(door.x, door.y);
+        let only_new = |c: Coord, side: u32| is_same_door_at(maze, door, dir, c, side);
+        let (block, plate, _) = pushes.into_iter().find(|&(block, plate, behind)| {
+            walkable(maze, start, &shut, Some(block))[maze.get_offset(behind.x, behind.y)] &&
+                walkable(maze, block, &only_new, Some(plate))[door_at]
+        })?;
+
+        let mut setup = self.clone();
+        setup.links.push(PlateLink { plate, door, dir });
+        setup.blocks.push(block);
+        Some(setup)
+    }
+}
+
+impl Maze {
+    /// Finds the fewest moves that get the player from start to goal in the
+    /// plate puzzle described by setup, pushing blocks onto plates as
+    /// needed.  Every door starts shut, whatever the Maze says, and opens
+    /// as PlatePuzzle opens it.  Returns the directions to move in, or None
+    /// if goal can't be reached however the blocks are used.
+    ///
+    /// Fails if setup isn't valid for the Maze, a block starts on start, or
+    /// the search would look at more than MAX_PLATE_STATES states.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::plates::{PlateLink, PlateSetup};
+    /// use mazegame::prelude::*;
+    ///
+    /// // A corridor whose door is held open by a plate next to it
+    /// let mut maze = Maze::new(1, 3);
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// maze.carve(1, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// let door = PlateLink { plate: Coord::new(1, 0), door: Coord::new(1, 0), dir: DIR_EAST };
+    /// let setup = PlateSetup { links: vec![door], blocks: vec![] };
+    /// let moves = maze.solve_with_plates(&setup, Coord::new(0, 0), Coord::new(2, 0)).unwrap();
+    /// assert_eq!(Some(vec![DIR_EAST, DIR_EAST]), moves);
+    /// ```
+    pub fn solve_with_plates(&self, setup: &PlateSetup, start: Coord, goal: Coord)
+                             -> Result<Option<Vec<u32>>, MazeError> {
+        setup.validate(self)?;
+        for c in [start, goal].iter() {
+            if !self.in_bounds(c.x, c.y) {
+                return Err(MazeError::OutOfBounds(c.x, c.y));
+            }
+        }
+        if setup.blocks.contains(&start) {
+            return Err(MazeError::InvalidArgument("the player can't start on a block".to_string()));
+        }
+        let mut maze = self.clone();
+        maze.shut_doors(&setup.links);
+
+        // Breadth-first over the player's Square and the blocks' Squares,
+        // sorted since blocks are interchangeable
+        let mut blocks = setup.blocks.clone();
+        blocks.sort();
+        let mut states = vec![(start, blocks)];
+        let mut came_from: Vec<(usize, u32)> = vec![(0, 0)];
+        let mut seen = BTreeMap::new();
+        seen.insert(states[0].clone(), 0);
+        let mut next = 0;
+        while next < states.len() {
+            let (player, blocks) = states[next].clone();
+            if player == goal {
+                let mut moves = Vec::new();
+                let mut at = next;
+                while at != 0 {
+                    moves.push(came_from[at].1);
+                    at = came_from[at].0;
+                }
+                moves.reverse();
+                gen_debug!("solve_with_plates states={} moves={}", states.len(), moves.len());
+                return Ok(Some(moves));
+            }
+            let open = pressed_doors(&setup.links, player, &blocks);
+            let go = |c: Coord, dir: u32| {
+                let door_open = open.iter().any(|&(d, side)| is_same_door_at(&maze, d, side, c, dir));
+                if door_open { maze.step_through(c.x, c.y, dir) } else { open_step(&maze, c, dir) }
+            };
+            for dir in 0..constants::NUM_DIRECTIONS {
+                if let Some((to, pushed)) = step(&go, player, &blocks, dir) {
+                    let mut moved = blocks.clone();
+                    if let Some((i, c)) = pushed {
+                        moved[i] = c;
+                        moved.sort();
+                    }
+                    let state = (to, moved);
+                    if !seen.contains_key(&state) {
+                        if states.len() >= MAX_PLATE_STATES {
+                            return Err(MazeError::InvalidArgument(
+                                format!("the puzzle has more than {} states", MAX_PLATE_STATES)));
+                        }
+                        seen.insert(state.clone(), states.len());
+                        states.push(state);
+                        came_from.push((next, dir));
+                    }
+                }
+            }
+            next += 1;
+        }
+        gen_debug!("solve_with_plates states={} unsolvable", states.len());
+        Ok(None)
+    }
+
+    //
+    // Internal - builds the wall of every linked door that's open.
+    //
+    fn shut_doors(&mut self, links: &[PlateLink]) {
+        for link in links.iter() {
+            let _ = self.build_wall_pair_checked(link.door.x, link.door.y, link.dir);
+        }
+    }
+}
+
+/// A plate puzzle being played: a Maze whose doors open and shut as the
+/// player and blocks move on and off their plates.
+#[derive(Clone, Debug)]
+pub struct PlatePuzzle {
+    maze: Maze,
+    links: Vec<PlateLink>,
+    blocks: Vec<Coord>,
+    player: Coord,
+}
+
+impl PlatePuzzle {
+    /// Sets up setup on maze with the player at player.  Every door is
+    /// shut, then the ones with something on a plate are opened.  Fails if
+    /// setup isn't valid for the Maze or a block is on the player's Square.
+    pub fn new(maze: Maze, setup: &PlateSetup, player: Coord) -> Result<PlatePuzzle, MazeError> {
+        setup.validate(&maze)?;
+        if !maze.in_bounds(player.x, player.y) {
+            return Err(MazeError::OutOfBounds(player.x, player.y));
+        }
+        if setup.blocks.contains(&player) {
+            return Err(MazeError::InvalidArgument("the player can't start on a block".to_string()));
+        }
+        let mut puzzle = PlatePuzzle { maze, links: setup.links.clone(), blocks: setup.blocks.clone(), player };
+        puzzle.maze.shut_doors(&puzzle.links);
+        puzzle.update_doors();
+        Ok(puzzle)
+    }
+
+    /// Returns the Maze, with the doors as they stand.
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    /// Returns the Square the player is on.
+    pub fn player(&self) -> Coord {
+        self.player
+    }
+
+    /// Returns the Squares the blocks are on.
+    pub fn blocks(&self) -> &[Coord] {
+        &self.blocks
+    }
+
+    /// Returns whether the player or a block is on the plate at coord.
+    pub fn is_pressed(&self, coord: Coord) -> bool {
+        self.links.iter().any(|l| l.plate == coord) && (self.player == coord || self.blocks.contains(&coord))
+    }
+
+    /// Moves the player one Square in direction dir, pushing any block
+    /// there one Square further, and returns what happened.  Fails if
+    /// there's a wall in the way, or the block can't move.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::plates::{PlateEvent, PlateLink, PlatePuzzle, PlateSetup};
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(2, 3);
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// maze.carve(1, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// maze.carve(2, 0, DIR_SOUTH, ID_MAZE_PATH, false).unwrap();
+    /// let link = PlateLink { plate: Coord::new(2, 0), door: Coord::new(2, 0), dir: DIR_SOUTH };
+    /// let setup = PlateSetup { links: vec![link], blocks: vec![Coord::new(1, 0)] };
+    /// let mut puzzle = PlatePuzzle::new(maze, &setup, Coord::new(0, 0)).unwrap();
+    /// let events = puzzle.move_player(DIR_EAST).unwrap();
+    /// assert!(events.contains(&PlateEvent::BlockPushed { from: Coord::new(1, 0), to: Coord::new(2, 0) }));
+    /// assert!(puzzle.is_pressed(Coord::new(2, 0)));
+    /// assert!(!puzzle.maze().sq[2].is_wall_present(DIR_SOUTH));
+    /// ```
+    pub fn move_player(&mut self, dir: u32) -> Result<Vec<PlateEvent>, MazeError> {
+        let maze = &self.maze;
+        let go = |c: Coord, dir: u32| open_step(maze, c, dir);
+        let (to, pushed) = match step(&go, self.player, &self.blocks, dir) {
+            Some(moved) => moved,
+            None => return Err(MazeError::InvalidArgument(format!("can't move {} from ({}, {})",
+                                                                  crate::trace::dir_name(dir),
+                                                                  self.player.x, self.player.y))),
+        };
+        let mut events = vec![PlateEvent::Moved(to)];
+        if let Some((i, c)) = pushed {
+            events.push(PlateEvent::BlockPushed { from: self.blocks[i], to: c });
+            self.blocks[i] = c;
+        }
+        self.player = to;
+        events.extend(self.update_doors());
+        if self.maze.is_exit(to) {
+            events.push(PlateEvent::ReachedExit);
+        }
+        Ok(events)
+    }
+
+    /// Draws the maze with the player as an '@', blocks as 'o' and plates
+    /// with nothing on them as '_'.
+    pub fn render_ascii(&self) -> String {
+        let rendered = self.maze.render_ascii();
+        let mut lines: Vec<Vec<char>> = rendered.lines().map(|l| l.chars().collect()).collect();
+        let marks = self.links.iter().map(|l| (l.plate, '_'))
+            .chain(self.blocks.iter().map(|&b| (b, 'o')))
+            .chain(Some((self.player, '@')));
+        for (c, mark) in marks {
+            lines[(c.y * 2 + 1) as usize][(c.x * 2 + 1) as usize] = mark;
+        }
+        let mut out = String::new();
+        for line in lines {
+            out.extend(line);
+            out.push('\n');
+        }
+        out
+    }
+
+    //
+    // Internal - opens the doors with something on a plate and shuts the
+    // rest, returning the ones that changed.
+    //
+    fn update_doors(&mut self) -> Vec<PlateEvent> {
+        let open = pressed_doors(&self.links, self.player, &self.blocks);
+        let mut events = Vec::new();
+        for (i, link) in self.links.iter().enumerate() {
+            // Each door once, however many plates it has
+            if self.links[..i].iter().any(|l| is_same_door(&self.maze, l, link.door, link.dir)) {
+                continue;
+            }
+            let (door, dir) = (link.door, link.dir);
+            let should_open = open.iter().any(|&(d, side)| is_same_door_at(&self.maze, d, side, door, dir));
+            let is_open = !self.maze.sq[self.maze.get_offset(door.x, door.y)].is_wall_present(dir);
+            if should_open && !is_open {
+                if let Ok(change) = self.maze.break_wall_pair_checked(door.x, door.y, dir, false) {
+                    events.push(PlateEvent::DoorOpened(change));
+                }
+            } else if !should_open && is_open {
+                if let Ok(change) = self.maze.build_wall_pair_checked(door.x, door.y, dir) {
+                    events.push(PlateEvent::DoorClosed(change));
+                }
+            }
+        }
+        events
+    }
+}
+
+//
+// Internal - where the player ends up moving from player in direction dir,
+// and the index and new Square of any block pushed, with go giving where a
+// step through an open wall leads.  None if the player or block is walled
+// in, or the block would run into another.
+//
+fn step<F: Fn(Coord, u32) -> Option<Coord>>(go: &F, player: Coord, blocks: &[Coord], dir: u32)
+                                             -> Option<(Coord, Option<(usize, Coord)>)> {
+    let to = go(player, dir)?;
+    match blocks.iter().position(|&b| b == to) {
+        None => Some((to, None)),
+        Some(i) => {
+            let beyond = go(to, dir)?;
+            if beyond == player || blocks.contains(&beyond) {
+                None
+            } else {
+                Some((to, Some((i, beyond))))
+            }
+        }
+    }
+}
+
+//
+// Internal - the Squares that can be walked to from start without passing
+// a wall that shut says is closed, or going through blocked.
+//
+fn walkable<F: Fn(Coord, u32) -> bool>(maze: &Maze, start: Coord, shut: &F, blocked: Option<Coord>) -> Vec<bool> {
+    let mut seen = vec![false; maze.sq.len()];
+    seen[maze.get_offset(start.x, start.y)] = true;
+    let mut queue = vec![start];
+    while let Some(c) = queue.pop() {
+        for side in 0..constants::NUM_DIRECTIONS {
+            match open_step(maze, c, side) {
+                Some(n) if !shut(c, side) && Some(n) != blocked && !seen[maze.get_offset(n.x, n.y)] => {
+                    seen[maze.get_offset(n.x, n.y)] = true;
+                    queue.push(n);
+                }
+                _ => {}
+            }
+        }
+    }
+    seen
+}
+
+//
+// Internal - the Square a step from c in direction dir leads to, if the
+// wall there is open.
+//
+fn open_step(maze: &Maze, c: Coord, dir: u32) -> Option<Coord> {
+    if maze.sq[maze.get_offset(c.x, c.y)].is_wall_present(dir) {
+        None
+    } else {
+        maze.step_through(c.x, c.y, dir)
+    }
+}
+
+//
+// Internal - the doors with the player or a block on one of their plates.
+//
+fn pressed_doors(links: &[PlateLink], player: Coord, blocks: &[Coord]) -> Vec<(Coord, u32)> {
+    links.iter()
+        .filter(|l| l.plate == player || blocks.contains(&l.plate))
+        .map(|l| (l.door, l.dir))
+        .collect()
+}
+
+//
+// Internal - whether link's door is the wall on side dir of c, seen from
+// either side.
+//
+fn is_same_door(maze: &Maze, link: &PlateLink, c: Coord, dir: u32) -> bool {
+    is_same_door_at(maze, link.door, link.dir, c, dir)
+}
+
+//
+// Internal - whether the wall on side dir of door is the wall on side
+// side of c, seen from either side.
+//
+fn is_same_door_at(maze: &Maze, door: Coord, dir: u32, c: Coord, side: u32) -> bool {
+    (door == c && dir == side) ||
+        (Direction::from_u32(dir).is_some_and(|d| u32::from(d.opposite()) == side) &&
+            maze.neighbor(door.x, door.y, dir) == Some(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH, DIR_WEST, ID_MAZE_PATH};
+
+    // Two corridors, one above the other, joined by a door under (1, 0).
+    // The block at (1, 0) has to be pushed onto the plate at (2, 0) and
+    // left there; pushed one Square too far it's stuck in the dead end.
+    //
+    //   start  block  plate  dead end
+    //          door
+    //          (1, 1) ----------------- exit
+    fn fixture() -> (Maze, PlateSetup) {
+        let mut maze = Maze::new(2, 5);
+        for x in 0..3 {
+            maze.carve(x, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+            maze.carve(x + 1, 1, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+        }
+        maze.carve(1, 0, DIR_SOUTH, ID_MAZE_PATH, false).unwrap();
+        maze.set_exit(4, 1, DIR_EAST).unwrap();
+        let setup = PlateSetup {
+            links: vec![PlateLink { plate: Coord::new(2, 0), door: Coord::new(1, 0), dir: DIR_SOUTH }],
+            blocks: vec![Coord::new(1, 0)],
+        };
+        (maze, setup)
+    }
+
+    #[test]
+    fn test_fixture_needs_block() {
+        let (maze, setup) = fixture();
+        let (start, goal) = (Coord::new(0, 0), Coord::new(4, 1));
+        let moves = maze.solve_with_plates(&setup, start, goal).unwrap();
+        assert_eq!(Some(vec![DIR_EAST, DIR_SOUTH, DIR_EAST, DIR_EAST, DIR_EAST]), moves);
+
+        // Without the block, or with it already stuck past the plate, the
+        // door can't be held open
+        let empty = PlateSetup { blocks: vec![], ..setup.clone() };
+        assert_eq!(None, maze.solve_with_plates(&empty, start, goal).unwrap());
+        let stuck = PlateSetup { blocks: vec![Coord::new(3, 0)], ..setup.clone() };
+        assert_eq!(None, maze.solve_with_plates(&stuck, start, goal).unwrap());
+
+        assert!(maze.solve_with_plates(&setup, Coord::new(1, 0), goal).is_err());
+        let outer = PlateLink { plate: Coord::new(0, 0), door: Coord::new(4, 1), dir: DIR_EAST };
+        let bad = PlateSetup { links: vec![outer], blocks: vec![] };
+        assert!(maze.solve_with_plates(&bad, start, goal).is_err());
+    }
+
+    #[test]
+    fn test_playthrough() {
+        let (maze, setup) = fixture();
+        let mut puzzle = PlatePuzzle::new(maze, &setup, Coord::new(0, 0)).unwrap();
+        puzzle.maze.take_changes();
+        assert!(puzzle.move_player(DIR_SOUTH).is_err());
+
+        // Standing on the plate opens the door, and stepping off shuts it
+        let mut stand = PlatePuzzle::new(puzzle.maze.clone(), &PlateSetup { blocks: vec![], ..setup.clone() },
+                                         Coord::new(1, 0)).unwrap();
+        let events = stand.move_player(DIR_EAST).unwrap();
+        assert!(matches!(events[1], PlateEvent::DoorOpened(WallBroken { coord, dir: DIR_SOUTH, .. })
+                         if coord == Coord::new(1, 0)));
+        let events = stand.move_player(DIR_WEST).unwrap();
+        assert_eq!(PlateEvent::DoorClosed(WallBuilt { coord: Coord::new(1, 0), dir: DIR_SOUTH,
+                                                      other: Coord::new(1, 1) }), events[1]);
+        assert!(stand.move_player(DIR_SOUTH).is_err());
+
+        // The scripted route: park the block, go through, reach the exit
+        let script = [DIR_EAST, DIR_SOUTH, DIR_EAST, DIR_EAST, DIR_EAST];
+        let mut all = Vec::new();
+        for &dir in script.iter() {
+            all.extend(puzzle.move_player(dir).unwrap());
+        }
+        assert_eq!(Coord::new(4, 1), puzzle.player());
+        assert_eq!(&[Coord::new(2, 0)], puzzle.blocks());
+        assert_eq!(Some(&PlateEvent::ReachedExit), all.last());
+        assert!(all.contains(&PlateEvent::BlockPushed { from: Coord::new(1, 0), to: Coord::new(2, 0) }));
+        assert!(puzzle.maze().walls_consistent());
+
+        // The opened door shows up as a change for the renderer
+        let changed: Vec<Coord> = puzzle.maze.take_changes().iter().map(|c| c.coord).collect();
+        assert_eq!(vec![Coord::new(1, 0), Coord::new(1, 1)], changed);
+
+        let drawn = puzzle.render_ascii();
+        let lines: Vec<&str> = drawn.lines().collect();
+        assert_eq!(Some('o'), lines[1].chars().nth(5));
+        assert_eq!(Some('@'), lines[3].chars().nth(9));
+    }
+
+    #[test]
+    fn test_pushing() {
+        let (maze, setup) = fixture();
+        // Two blocks in a row can't be pushed
+        let two = PlateSetup { blocks: vec![Coord::new(1, 0), Coord::new(2, 0)], ..setup.clone() };
+        let mut puzzle = PlatePuzzle::new(maze.clone(), &two, Coord::new(0, 0)).unwrap();
+        assert!(puzzle.is_pressed(Coord::new(2, 0)));
+        assert!(!puzzle.maze().sq[puzzle.maze().get_offset(1, 0)].is_wall_present(DIR_SOUTH));
+        assert!(puzzle.move_player(DIR_EAST).is_err());
+
+        // A block against a wall doesn't move
+        let mut puzzle = PlatePuzzle::new(maze, &setup, Coord::new(0, 0)).unwrap();
+        puzzle.move_player(DIR_EAST).unwrap();
+        puzzle.move_player(DIR_EAST).unwrap();
+        assert_eq!(&[Coord::new(3, 0)], puzzle.blocks());
+        assert!(puzzle.move_player(DIR_EAST).is_err());
+        assert!(puzzle.move_player(DIR_NORTH).is_err());
+
+        // The player is on the plate now, so the door is open until they
+        // step off
+        assert!(puzzle.is_pressed(Coord::new(2, 0)));
+        puzzle.move_player(DIR_WEST).unwrap();
+        assert!(puzzle.maze().sq[puzzle.maze().get_offset(1, 0)].is_wall_present(DIR_SOUTH));
+    }
+
+    #[test]
+    fn test_place() {
+        let mut complete = 0;
+        for seed in 0..10 {
+            let mut maze = Maze::new(12, 12);
+            maze.generate_perfect_seeded(seed).unwrap();
+            let (start, goal) = (Coord::new(0, 0), Coord::new(11, 11));
+            let (setup, all_placed) = PlateSetup::place(&maze, 2, start, goal, seed, &[Coord::new(3, 3)]);
+            assert_eq!(setup, PlateSetup::place(&maze, 2, start, goal, seed, &[Coord::new(3, 3)]).0);
+            if all_placed {
+                complete += 1;
+                assert_eq!(2, setup.links.len());
+            }
+            assert!(setup.blocks.iter().all(|&b| b != start && b != goal && b != Coord::new(3, 3)));
+
+            // Solvable, but only with the blocks
+            let moves = maze.solve_with_plates(&setup, start, goal).unwrap().unwrap();
+            if !setup.blocks.is_empty() {
+                let empty = PlateSetup { blocks: vec![], ..setup.clone() };
+                assert!(maze.solve_with_plates(&empty, start, goal).unwrap().is_none(), "seed {}", seed);
+            }
+
+            // Playing the solved moves reaches the exit
+            maze.set_exit(11, 11, DIR_EAST).unwrap();
+            let mut puzzle = PlatePuzzle::new(maze, &setup, start).unwrap();
+            let mut events = Vec::new();
+            for dir in moves {
+                events = puzzle.move_player(dir).unwrap();
+            }
+            assert_eq!(Some(&PlateEvent::ReachedExit), events.last());
+        }
+        // A maze this size usually has room for two
+        assert!(complete >= 7, "{}", complete);
+    }
+}
@@ -0,0 +1,231 @@
+//! Hand-authored pieces of mazes: carving a whole path in one go, and
+//! stamping a small prefab Maze into a bigger one.
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+/// How a prefab's walls combine with the walls already in a Maze.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StampMode {
+    /// The prefab replaces everything under it, walls and all.
+    Overwrite,
+    /// A wall is open if it's open in either the Maze or the prefab.
+    Merge,
+}
+
+impl Maze {
+    /// Carves a path from start, one move at a time, and returns the Square
+    /// it ends on.  The whole path is checked before anything is carved, so
+    /// a path that runs off the Maze changes nothing.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(4, 4);
+    /// let end = maze.carve_path(Coord::new(0, 0), &[DIR_EAST, DIR_EAST, DIR_SOUTH], ID_MAZE_PATH).unwrap();
+    /// assert_eq!(Coord::new(2, 1), end);
+    /// assert!(maze.carve_path(end, &[DIR_EAST, DIR_EAST], ID_MAZE_PATH).is_err());
+    /// assert!(!maze.sq[7].is_carved());
+    /// ```
+    pub fn carve_path(&mut self, start: Coord, moves: &[u32], id: i32) -> Result<Coord, MazeError> {
+        if !self.in_bounds(start.x, start.y) {
+            return Err(MazeError::OutOfBounds(start.x, start.y));
+        }
+        let mut steps = Vec::with_capacity(moves.len());
+        let mut cur = start;
+        for &dir in moves.iter() {
+            let side = Direction::from_u32(dir)
+                .ok_or_else(|| MazeError::InvalidArgument(format!("{} isn't a direction", dir)))?;
+            let next = match self.neighbor(cur.x, cur.y, dir) {
+                Some(c) => c,
+                None => {
+                    let (dx, dy) = side.delta();
+                    return Err(MazeError::OutOfBounds(cur.x.wrapping_add(dx as u32), cur.y.wrapping_add(dy as u32)));
+                }
+            };
+            steps.push((cur, dir));
+            cur = next;
+        }
+        for &(c, dir) in steps.iter() {
+            self.carve(c.x, c.y, dir, id, false).map_err(MazeError::InvalidArgument)?;
+        }
+        Ok(cur)
+    }
+
+    /// Copies a prefab's walls into the Maze with its top left corner at
+    /// `at`.  Walls between the prefab and the rest of the Maze follow the
+    /// prefab's outer wall, so its entrance and exit become doorways, but
+    /// the Maze's own outer wall is never changed.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::prefab::StampMode;
+    ///
+    /// let mut room = Maze::new(2, 2);
+    /// room.carve_path(Coord::new(0, 0), &[DIR_EAST, DIR_SOUTH, DIR_WEST],
+    ///                 ID_MAZE_PATH).unwrap();
+    /// room.set_entrance(0, 1, DIR_WEST).unwrap();
+    ///
+    /// let mut maze = Maze::new(6, 6);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// maze.stamp(&room, Coord::new(3, 2), StampMode::Overwrite).unwrap();
+    /// assert_eq!(vec![Coord::new(4, 3), Coord::new(2, 3)], maze.open_neighbors(3, 3));
+    /// ```
+    pub fn stamp(&mut self, prefab: &Maze, at: Coord, mode: StampMode) -> Result<(), MazeError> {
+        match (at.x.checked_add(prefab.cols), at.y.checked_add(prefab.rows)) {
+            (Some(end_x), Some(end_y)) if end_x <= self.cols && end_y <= self.rows => {}
+            _ => {
+                return Err(MazeError::OutOfBounds(at.x.saturating_add(prefab.cols).saturating_sub(1),
+                                                  at.y.saturating_add(prefab.rows).saturating_sub(1)))
+            }
+        }
+
+        for py in 0..prefab.rows {
+            for px in 0..prefab.cols {
+                let (x, y) = (at.x + px, at.y + py);
+                let offset = self.get_offset(x, y);
+                let from = &prefab.sq[prefab.get_offset(px, py)];
+                if mode == StampMode::Overwrite {
+                    self.sq[offset].under = from.under;
+                    self.sq[offset].id = if from.is_carved() { constants::ID_MAZE_PATH } else { constants::ID_UNCARVED };
+                } else if from.is_carved() && !self.sq[offset].is_carved() {
+                    self.sq[offset].id = constants::ID_MAZE_PATH;
+                }
+
+                for &side in Direction::ALL.iter() {
+                    let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+                    let n = match self.neighbor(x, y, dir) {
+                        Some(n) => n,
+                        None => continue,
+                    };
+                    let inside = prefab.neighbor(px, py, dir).is_some();
+                    let open = !from.is_wall_present(dir);
+                    let one_way = from.is_one_way(dir);
+                    let n = self.get_offset(n.x, n.y);
+                    match mode {
+                        StampMode::Overwrite => {
+                            if open {
+                                self.sq[offset].break_wall(dir);
+                            } else {
+                                self.sq[offset].build_wall(dir);
+                            }
+                            self.sq[offset].set_one_way(dir, one_way);
+                            // Squares inside the prefab set their own side
+                            if !inside {
+                                if open && !one_way {
+                                    self.sq[n].break_wall(back);
+                                } else {
+                                    self.sq[n].build_wall(back);
+                                }
+                                self.sq[n].set_one_way(back, false);
+                            }
+                        }
+                        StampMode::Merge if open && !one_way => {
+                            self.sq[offset].break_wall(dir);
+                            self.sq[offset].set_one_way(dir, false);
+                            self.sq[n].break_wall(back);
+                            self.sq[n].set_one_way(back, false);
+                        }
+                        StampMode::Merge => {}
+                    }
+                }
+            }
+        }
+        self.mark_changed();
+        gen_debug!("stamp x={} y={} w={} h={} mode={:?}", at.x, at.y, prefab.cols, prefab.rows, mode);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH, DIR_WEST, ID_MAZE_PATH};
+
+    // A 3x3 room with no inner walls and a doorway on its west side
+    fn open_room() -> Maze {
+        let mut room = Maze::new(3, 3);
+        let moves = [DIR_EAST, DIR_EAST, DIR_SOUTH, DIR_WEST, DIR_WEST, DIR_SOUTH, DIR_EAST, DIR_EAST];
+        room.carve_path(Coord::new(0, 0), &moves, ID_MAZE_PATH).unwrap();
+        for &(x, y) in [(0, 0), (1, 0), (1, 1), (2, 1)].iter() {
+            room.carve(x, y, DIR_SOUTH, ID_MAZE_PATH, false).unwrap();
+        }
+        room.set_entrance(0, 1, DIR_WEST).unwrap();
+        room
+    }
+
+    #[test]
+    fn test_carve_path_is_atomic() {
+        let mut maze = Maze::new(5, 5);
+        let before = maze.clone();
+        let moves = [DIR_SOUTH, DIR_SOUTH, DIR_EAST, DIR_NORTH, DIR_NORTH, DIR_NORTH, DIR_NORTH];
+        assert_eq!(Err(MazeError::OutOfBounds(3, u32::MAX)), maze.carve_path(Coord::new(2, 1), &moves, 1));
+        assert_eq!(before, maze);
+        assert!(maze.carve_path(Coord::new(2, 1), &[DIR_SOUTH, 7], 1).is_err());
+        assert!(maze.carve_path(Coord::new(5, 0), &[DIR_SOUTH], 1).is_err());
+        assert_eq!(before, maze);
+
+        let end = maze.carve_path(Coord::new(2, 1), &moves[..6], 1).unwrap();
+        assert_eq!(Coord::new(3, 0), end);
+        assert!(maze.walls_consistent());
+        assert_eq!(7, maze.solve(Coord::new(2, 1), end).unwrap().len());
+        assert_eq!(Ok(Coord::new(2, 1)), maze.carve_path(Coord::new(2, 1), &[], 1));
+    }
+
+    #[test]
+    fn test_stamp_overwrite() {
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_seeded(420).unwrap();
+        maze.stamp(&open_room(), Coord::new(4, 2), StampMode::Overwrite).unwrap();
+        assert!(maze.walls_consistent());
+        for y in 2..5 {
+            for x in 4..7 {
+                let expected = 4 - (x == 4 || x == 6) as usize - (y == 2 || y == 4) as usize;
+                let doorway = (x, y) == (4, 3);
+                assert_eq!(expected + doorway as usize, maze.open_neighbors(x, y).len(), "({}, {})", x, y);
+            }
+        }
+        // The only way in is the doorway
+        let path = maze.solve(Coord::new(0, 0), Coord::new(5, 3)).unwrap();
+        assert_eq!(Coord::new(3, 3), path[path.len() - 3]);
+    }
+
+    #[test]
+    fn test_stamp_merge() {
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_seeded(420).unwrap();
+        let before = maze.clone();
+        maze.stamp(&open_room(), Coord::new(4, 2), StampMode::Merge).unwrap();
+        assert!(maze.walls_consistent());
+        for y in 2..5 {
+            for x in 4..7 {
+                let offset = maze.get_offset(x, y);
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    // Nothing that was open gets closed
+                    if !before.sq[offset].is_wall_present(dir) {
+                        assert!(!maze.sq[offset].is_wall_present(dir));
+                    }
+                }
+            }
+        }
+        assert!(!maze.sq[maze.get_offset(4, 3)].is_wall_present(DIR_WEST));
+        assert!(!maze.sq[maze.get_offset(5, 3)].is_wall_present(DIR_NORTH));
+    }
+
+    #[test]
+    fn test_stamp_bounds() {
+        let mut maze = Maze::new(4, 4);
+        let before = maze.clone();
+        assert_eq!(Err(MazeError::OutOfBounds(4, 3)), maze.stamp(&open_room(), Coord::new(2, 1), StampMode::Merge));
+        assert!(maze.stamp(&open_room(), Coord::new(u32::MAX, 0), StampMode::Merge).is_err());
+        assert_eq!(before, maze);
+
+        // A doorway on the Maze's edge doesn't break the outer wall
+        maze.stamp(&open_room(), Coord::new(0, 0), StampMode::Overwrite).unwrap();
+        assert!(maze.walls_consistent());
+    }
+}
@@ -0,0 +1,229 @@
+//! Seeing what an edit would do before making it.  Maze::preview_edit()
+//! makes the edit through an EditTransaction, which copies only the
+//! Squares it touches, measures the result and rolls it back, so the rest
+//! of the Maze is never copied.  Rolling back is a change like any other,
+//! so a preview that touched anything gives the Maze a new revision.
+use alloc::vec::Vec;
+
+use crate::error::MazeError;
+use crate::maze::edit::EditAction;
+use crate::maze::{Coord, Maze};
+
+/// What an edit would change about getting around a Maze.  Reachability is
+/// counted from the start, or the entrance if no start was set, or the top
+/// left corner if there's neither.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditPreview {
+    /// The Squares that can't be reached now but could be after the edit,
+    /// in row-major order.
+    pub newly_reachable: Vec<Coord>,
+    /// The Squares that can be reached now but couldn't be after the edit,
+    /// in row-major order.
+    pub newly_unreachable: Vec<Coord>,
+    /// Whether the edit adds a loop, by joining Squares that were already
+    /// connected.
+    pub creates_cycle: bool,
+    /// The number of Squares on the shortest path from the start to the
+    /// exit now, or None if there's no path.
+    pub solution_before: Option<u32>,
+    /// The same after the edit.
+    pub solution_after: Option<u32>,
+}
+
+impl Maze {
+    /// Works out what action would change without keeping it.  The Maze's
+    /// layout is left exactly as it was, even when the action fails, in
+    /// which case the error says why it can't be made.  Its revision still
+    /// moves on if the action touched anything.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::edit::EditAction;
+    ///
+    /// // Two corridors side by side, joined at the bottom
+    /// let mut maze = Maze::from_compact("22\n59").unwrap();
+    /// maze.set_entrance(0, 0, DIR_NORTH).unwrap();
+    /// maze.set_exit(1, 0, DIR_NORTH).unwrap();
+    /// let before = maze.clone();
+    ///
+    /// let preview = maze.preview_edit(&EditAction::OpenWall { at: Coord::new(0, 0), dir: DIR_EAST }).unwrap();
+    /// assert!(preview.creates_cycle);
+    /// assert_eq!((Some(4), Some(2)), (preview.solution_before, preview.solution_after));
+    /// assert_eq!(before, maze);
+    /// ```
+    pub fn preview_edit(&mut self, action: &EditAction) -> Result<EditPreview, MazeError> {
+        let before = Reach::measure(self);
+        let mut edit = self.begin_edit();
+        let result = edit.apply(action).map(|_| Reach::measure(edit.maze()));
+        edit.rollback();
+        let after = result?;
+
+        let coords = |dist: &[Option<u32>], other: &[Option<u32>]| -> Vec<Coord> {
+            (0..dist.len()).filter(|&i| dist[i].is_some() && other[i].is_none()).map(|i| self.get_coord(i)).collect()
+        };
+        Ok(EditPreview {
+            newly_reachable: coords(&after.dist, &before.dist),
+            newly_unreachable: coords(&before.dist, &after.dist),
+            creates_cycle: after.loops > before.loops,
+            solution_before: before.solution,
+            solution_after: after.solution,
+        })
+    }
+}
+
+//
+// Internal - the measurements compared before and after an edit.
+//
+struct Reach {
+    dist: Vec<Option<u32>>,
+    solution: Option<u32>,
+    loops: i64,
+}
+
+impl Reach {
+    fn measure(maze: &Maze) -> Reach {
+        let from = maze.player_start().unwrap_or_else(|| maze.get_start());
+        let dist = maze.distances(from);
+        let solution = maze.get_exit()
+            .and_then(|exit| dist[maze.get_offset(exit.coord.x, exit.coord.y)])
+            .map(|d| d + 1);
+
+        // Independent loops: passages, less Squares, plus connected parts
+        let region = maze.connected_regions();
+        let passages: usize = (0..maze.sq.len())
+            .map(|i| {
+                let c = maze.get_coord(i);
+                maze.open_neighbors(c.x, c.y).iter().filter(|n| maze.get_offset(n.x, n.y) > i).count()
+            })
+            .sum();
+        let parts = (0..region.len()).filter(|&i| region[i] == i).count();
+        Reach { dist, solution, loops: passages as i64 - maze.sq.len() as i64 + parts as i64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH, DIR_WEST};
+
+    fn generated() -> Maze {
+        let mut maze = Maze::new(10, 10);
+        maze.generate_perfect_seeded(494).unwrap();
+        maze.set_entrance(0, 0, DIR_NORTH).unwrap();
+        maze.set_exit(9, 9, DIR_SOUTH).unwrap();
+        maze
+    }
+
+    // Independent loops, with the connected parts found by flooding out
+    // from each Square not reached yet
+    fn loops(maze: &Maze) -> i64 {
+        let mut seen = vec![false; maze.sq.len()];
+        let mut parts = 0;
+        for i in 0..maze.sq.len() {
+            if !seen[i] {
+                parts += 1;
+                let c = maze.get_coord(i);
+                for (j, d) in maze.distances(c).iter().enumerate() {
+                    seen[j] |= d.is_some();
+                }
+            }
+        }
+        let passages: usize = (0..maze.sq.len())
+            .map(|i| {
+                let c = maze.get_coord(i);
+                maze.open_neighbors(c.x, c.y).iter().filter(|n| maze.get_offset(n.x, n.y) > i).count()
+            })
+            .sum();
+        passages as i64 - maze.sq.len() as i64 + parts
+    }
+
+    // Makes the edit on a clone, and works out the preview from that
+    fn applied(maze: &Maze, action: &EditAction) -> EditPreview {
+        let mut copy = maze.clone();
+        let mut edit = copy.begin_edit();
+        edit.apply(action).unwrap();
+        edit.commit();
+        let from = |m: &Maze| m.player_start().unwrap_or_else(|| m.get_start());
+        let solution = |m: &Maze| m.get_exit().and_then(|e| m.solve(from(m), e.coord)).map(|p| p.len() as u32);
+        let (before, after) = (maze.distances(from(maze)), copy.distances(from(&copy)));
+        let coords = |a: &[Option<u32>], b: &[Option<u32>]| -> Vec<Coord> {
+            (0..a.len()).filter(|&i| a[i].is_some() && b[i].is_none()).map(|i| maze.get_coord(i)).collect()
+        };
+        EditPreview {
+            newly_reachable: coords(&after, &before),
+            newly_unreachable: coords(&before, &after),
+            creates_cycle: loops(&copy) > loops(maze),
+            solution_before: solution(maze),
+            solution_after: solution(&copy),
+        }
+    }
+
+    fn check(maze: &mut Maze, action: EditAction) -> EditPreview {
+        let before = maze.clone();
+        let preview = maze.preview_edit(&action).unwrap();
+        assert_eq!(before.to_bytes(), maze.to_bytes());
+        // The revision never goes backwards
+        assert!(maze.revision() > before.revision());
+        assert_eq!(applied(maze, &action), preview, "{:?}", action);
+        preview
+    }
+
+    #[test]
+    fn test_matches_applying() {
+        let mut maze = generated();
+        // Closing a wall on a perfect maze cuts part of it off
+        let wall = (0..maze.sq.len()).map(|i| maze.get_coord(i))
+            .find(|c| c.x < 9 && !maze.sq[maze.get_offset(c.x, c.y)].is_wall_present(DIR_EAST))
+            .unwrap();
+        let closed = check(&mut maze, EditAction::CloseWall { at: wall, dir: DIR_EAST });
+        assert!(closed.newly_reachable.is_empty());
+        assert!(!closed.newly_unreachable.is_empty());
+        assert!(!closed.creates_cycle);
+
+        // Opening a wall in one makes a loop, and can only make the
+        // solution shorter
+        let wall = (0..maze.sq.len()).map(|i| maze.get_coord(i))
+            .find(|c| c.x < 9 && maze.sq[maze.get_offset(c.x, c.y)].is_wall_present(DIR_EAST))
+            .unwrap();
+        let opened = check(&mut maze, EditAction::OpenWall { at: wall, dir: DIR_EAST });
+        assert!(opened.creates_cycle);
+        assert!(opened.newly_reachable.is_empty() && opened.newly_unreachable.is_empty());
+        assert!(opened.solution_after <= opened.solution_before);
+
+        let room = check(&mut maze, EditAction::Room { at: Coord::new(3, 3), w: 4, h: 3, id: 1 });
+        assert!(room.creates_cycle);
+        assert!(room.solution_after <= room.solution_before);
+
+        // Closing the exit's wall takes the solution away
+        let exit = check(&mut maze, EditAction::CloseWall { at: Coord::new(9, 9), dir: DIR_SOUTH });
+        assert_eq!(None, exit.solution_after);
+    }
+
+    #[test]
+    fn test_reconnecting() {
+        // A wall shut off part of the maze; opening it again brings that
+        // part back
+        let mut maze = generated();
+        let wall = (0..maze.sq.len()).map(|i| maze.get_coord(i))
+            .find(|c| c.x > 0 && !maze.sq[maze.get_offset(c.x, c.y)].is_wall_present(DIR_WEST))
+            .unwrap();
+        let mut edit = maze.begin_edit();
+        edit.build_wall(wall.x, wall.y, DIR_WEST).unwrap();
+        edit.commit();
+        let preview = check(&mut maze, EditAction::OpenWall { at: wall, dir: DIR_WEST });
+        assert!(!preview.newly_reachable.is_empty());
+        assert!(!preview.creates_cycle);
+        assert!(preview.solution_after.is_some());
+    }
+
+    #[test]
+    fn test_failed_preview() {
+        let mut maze = generated();
+        let before = maze.clone();
+        assert!(maze.preview_edit(&EditAction::OpenWall { at: Coord::new(0, 0), dir: DIR_WEST }).is_err());
+        assert!(maze.preview_edit(&EditAction::Room { at: Coord::new(8, 8), w: 3, h: 3, id: 1 }).is_err());
+        assert_eq!(before.to_bytes(), maze.to_bytes());
+        assert!(maze.revision() >= before.revision());
+    }
+}
@@ -0,0 +1,939 @@
+//! Renderers and exporters that turn a Maze into something other than the
+//! console ASCII drawing.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::fingerprint::crc32;
+use crate::maze::{Coord, Maze, Opening, Topology};
+
+/// The most cells WallGrid::from_rle() will decode, so a damaged record
+/// can't make it allocate gigabytes.  A 4096x4096 maze's grid fits.
+pub const MAX_RLE_CELLS: u32 = 1 << 28;
+
+/// A Maze expanded onto a grid where walls and Squares each take up one cell,
+/// the same layout used by the ASCII renderer.  The grid is `2 * cols + 1`
+/// cells wide and `2 * rows + 1` cells tall; Square (x, y) sits at grid cell
+/// (2x + 1, 2y + 1), with its walls on the grid cells next to it.  Secret
+/// passages are walls, as render_ascii() draws them.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WallGrid {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major; true where there's a wall.
+    pub walls: Vec<bool>,
+}
+
+impl WallGrid {
+    /// Returns whether the grid cell at (x, y) is a wall.  Cells outside the
+    /// grid are treated as walls.
+    pub fn is_wall(&self, x: u32, y: u32) -> bool {
+        if x >= self.width || y >= self.height {
+            return true;
+        }
+        self.walls[(y * self.width + x) as usize]
+    }
+
+    /// Encodes the grid as one run-length encoded record, which can be
+    /// written back to back with others in a stream.  All numbers are
+    /// little endian:
+    ///
+    /// ```text
+    /// length  u32      the number of bytes in the rest of the record
+    /// width   u32
+    /// height  u32
+    /// first   u8       1 if the first cell is a wall, 0 if it's open
+    /// runs    varints  the lengths of the runs of cells, row-major, which
+    ///                  alternate between wall and open starting with first
+    /// crc     u32      the CRC-32 of width through runs
+    /// ```
+    ///
+    /// A varint holds 7 bits of the number per byte, lowest first, with the
+    /// top bit set on every byte but the last; runs under 128 take one
+    /// byte.  Every run is at least 1 long, and they add up to width *
+    /// height.
+    ///
+    /// # Example
+    /// ```
+    /// // A 3x1 maze's 7x3 grid: wall all round, with three open Squares
+    /// let rle = mazegame::Maze::new(1, 3).to_wall_grid().to_rle();
+    /// assert_eq!(vec![20, 0, 0, 0, 7, 0, 0, 0, 3, 0, 0, 0, 1, 8, 1, 1, 1, 1, 1, 8, 29, 97, 27, 193], rle);
+    /// ```
+    pub fn to_rle(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&self.width.to_le_bytes());
+        body.extend_from_slice(&self.height.to_le_bytes());
+        body.push(self.walls.first().map_or(1, |&wall| wall as u8));
+        let mut cells = self.walls.iter().peekable();
+        while let Some(&wall) = cells.next() {
+            let mut run = 1u64;
+            while cells.next_if(|&&next| next == wall).is_some() {
+                run += 1;
+            }
+            push_varint(&mut body, run);
+        }
+
+        let mut out = Vec::with_capacity(body.len() + 8);
+        out.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        out.extend_from_slice(&body);
+        out.extend_from_slice(&crc32(&body).to_le_bytes());
+        out
+    }
+
+    /// Decodes the record at the start of data, written by to_rle().
+    /// Returns the grid and how many bytes the record took, so a stream of
+    /// records can be read by calling it again on what's left.  Fails with
+    /// MazeError::Corrupted if the record is cut short, fails its CRC-32,
+    /// has runs that don't add up or more than MAX_RLE_CELLS cells, and
+    /// with MazeError::Parse if the grid couldn't have
+    /// come from a Maze: its sides must be odd, every Square's cell open,
+    /// posts on the outer edge walls, and other posts walls exactly when a
+    /// wall meets them.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::render::WallGrid;
+    ///
+    /// let mut maze = mazegame::Maze::new(4, 6);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let mut stream = maze.to_wall_grid_rle();
+    /// stream.extend(mazegame::Maze::new(2, 2).to_wall_grid_rle());
+    ///
+    /// let (first, used) = WallGrid::from_rle(&stream).unwrap();
+    /// assert_eq!(maze.to_wall_grid(), first);
+    /// let (second, rest) = WallGrid::from_rle(&stream[used..]).unwrap();
+    /// assert_eq!((5, 5), (second.width, second.height));
+    /// assert_eq!(stream.len(), used + rest);
+    /// ```
+    pub fn from_rle(data: &[u8]) -> Result<(WallGrid, usize), MazeError> {
+        let bad = |msg: &str| MazeError::Parse(format!("wall grid record: {}", msg));
+        let corrupted = |what: &str, offset: usize| MazeError::Corrupted { what: format!("wall grid {}", what), offset };
+        let u32_at = |at: usize| data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+        let length = u32_at(0).ok_or_else(|| corrupted("length cut short", 0))? as usize;
+        if length < 13 || length > data.len() - 4 {
+            return Err(corrupted(&format!("length {}", length), 0));
+        }
+        let (body, crc) = data[4..4 + length].split_at(length - 4);
+        if crc32(body).to_le_bytes() != crc {
+            return Err(corrupted("CRC-32 mismatch", length));
+        }
+        let (width, height) = (u32_at(4).unwrap(), u32_at(8).unwrap());
+        let cells = match width.checked_mul(height) {
+            Some(cells) if cells <= MAX_RLE_CELLS => cells as usize,
+            _ => return Err(corrupted(&format!("size {}x{}", width, height), 4)),
+        };
+        let mut wall = match body[8] {
+            0 => false,
+            1 => true,
+            other => return Err(corrupted(&format!("first cell {}", other), 12)),
+        };
+
+        let mut walls = Vec::new();
+        let mut pos = 9;
+        while pos < body.len() {
+            let (run, used) = read_varint(&body[pos..]).ok_or_else(|| corrupted("run length", 4 + pos))?;
+            if run == 0 || run > (cells - walls.len()) as u64 {
+                return Err(corrupted(&format!("run of {}", run), 4 + pos));
+            }
+            pos += used;
+            walls.resize(walls.len() + run as usize, wall);
+            wall = !wall;
+        }
+        if walls.len() != cells {
+            return Err(corrupted(&format!("runs covering {} of {} cells", walls.len(), cells), 4 + pos));
+        }
+
+        let grid = WallGrid { width, height, walls };
+        grid.check_layout().map_err(|msg| bad(&msg))?;
+        Ok((grid, 4 + length))
+    }
+
+    //
+    // Internal - checks the grid is laid out like one from to_wall_grid().
+    //
+    fn check_layout(&self) -> Result<(), String> {
+        if self.width.is_multiple_of(2) || self.height.is_multiple_of(2) {
+            return Err(format!("a {}x{} grid doesn't have odd sides", self.width, self.height));
+        }
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let expected = match (x % 2, y % 2) {
+                    (1, 1) => Some(false),
+                    (0, 0) if x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1 => Some(true),
+                    (0, 0) => Some(self.is_wall(x - 1, y) || self.is_wall(x + 1, y) ||
+                                   self.is_wall(x, y - 1) || self.is_wall(x, y + 1)),
+                    _ => None,
+                };
+                if expected.is_some_and(|wall| wall != self.is_wall(x, y)) {
+                    return Err(format!("cell ({}, {}) can't be {}", x, y,
+                                       if self.is_wall(x, y) { "a wall" } else { "open" }));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a tile of an exported tile map holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Tile {
+    Floor,
+    Wall,
+    /// Floor inside a room.
+    RoomFloor,
+    /// The way between a room and a corridor, or two different rooms.
+    Door,
+    Entrance,
+    Exit,
+    /// A flooded Square, from Maze::to_tilemap_with_flood().
+    Water,
+}
+
+/// Where a tile map puts a Maze's walls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TileStyle {
+    /// One tile per Square, `cols` wide and `rows` tall.  Walls are on the
+    /// edges between tiles, so they're read from the Maze itself; a tile
+    /// only says what its Square is.  Room Squares with a way out to a
+    /// corridor are Doors, as are the Squares of recorded doors, and the
+    /// Squares holding the entrance and exit are Entrance and Exit.
+    Thin,
+    /// Walls take up whole tiles, laid out like a WallGrid: `2 * cols + 1`
+    /// wide and `2 * rows + 1` tall, with Square (x, y) at tile
+    /// (2x + 1, 2y + 1).  Uncarved Squares are Wall.  The gap between a
+    /// room and a corridor is a Door, as is the gap of each recorded door,
+    /// and the gaps in the outer wall are Entrance and Exit.
+    Thick,
+}
+
+impl Maze {
+    /// Returns the (width, height) of the tile map to_tilemap() makes in
+    /// style.
+    pub fn tilemap_size(&self, style: TileStyle) -> (u32, u32) {
+        match style {
+            TileStyle::Thin => (self.cols, self.rows),
+            TileStyle::Thick => (self.cols * 2 + 1, self.rows * 2 + 1),
+        }
+    }
+
+    /// Exports the Maze as a tile map in style, indexed `[y][x]`.  Its size
+    /// is given by tilemap_size().
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::render::{Tile, TileStyle};
+    ///
+    /// // A corridor of two Squares with the entrance on its west end
+    /// let maze = mazegame::Maze::from_compact("c8").unwrap();
+    /// assert_eq!(vec![vec![Tile::Entrance, Tile::Floor]], maze.to_tilemap(TileStyle::Thin));
+    /// let thick = maze.to_tilemap(TileStyle::Thick);
+    /// assert_eq!((5, 3), maze.tilemap_size(TileStyle::Thick));
+    /// assert_eq!(vec![Tile::Entrance, Tile::Floor, Tile::Floor, Tile::Floor, Tile::Wall], thick[1]);
+    /// ```
+    pub fn to_tilemap(&self, style: TileStyle) -> Vec<Vec<Tile>> {
+        match style {
+            TileStyle::Thin => self.thin_tilemap(),
+            TileStyle::Thick => self.thick_tilemap(),
+        }
+    }
+
+    /// Returns the corridor region of each tile of to_tilemap(style), for
+    /// picking a palette per region after partition_corridors().  Tiles
+    /// that aren't corridor floor, or that join two different regions, get
+    /// None, and so does every tile if the corridors haven't been split.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::render::TileStyle;
+    ///
+    /// // Eight Squares in a row, split into two regions of four
+    /// let mut maze = mazegame::Maze::from_compact("4cccccc8").unwrap();
+    /// maze.partition_corridors(2, 1).unwrap();
+    /// let regions = maze.tilemap_regions(TileStyle::Thick);
+    /// assert_eq!(regions[1][1], regions[1][7]);
+    /// assert_eq!(None, regions[1][8]);
+    /// assert_ne!(regions[1][1], regions[1][15]);
+    /// assert_eq!(None, regions[0][1]);
+    /// ```
+    pub fn tilemap_regions(&self, style: TileStyle) -> Vec<Vec<Option<u32>>> {
+        let (width, height) = self.tilemap_size(style);
+        let region = |x: u32, y: u32| self.corridor_region(Coord::new(x, y));
+        (0..height).map(|ty| (0..width).map(|tx| match style {
+            TileStyle::Thin => region(tx, ty),
+            TileStyle::Thick => {
+                let (x, y) = (tx / 2, ty / 2);
+                match (tx % 2, ty % 2) {
+                    (1, 1) => region(x, y),
+                    // A gap between two Squares takes their region if it's
+                    // open and they share one
+                    (0, 1) if tx > 0 && !self.sq[self.get_offset(x - 1, y)].is_wall_drawn(constants::DIR_EAST) =>
+                        region(x - 1, y).filter(|&r| Some(r) == region(x, y)),
+                    (1, 0) if ty > 0 && !self.sq[self.get_offset(x, y - 1)].is_wall_drawn(constants::DIR_SOUTH) =>
+                        region(x, y - 1).filter(|&r| Some(r) == region(x, y)),
+                    _ => None,
+                }
+            }
+        }).collect()).collect()
+    }
+
+    //
+    // Internal - one tile per Square.
+    //
+    fn thin_tilemap(&self) -> Vec<Vec<Tile>> {
+        let mut tiles = Vec::with_capacity(self.rows as usize);
+        for y in 0..self.rows {
+            let mut row = Vec::with_capacity(self.cols as usize);
+            for x in 0..self.cols {
+                let sq = &self.sq[self.get_offset(x, y)];
+                let door = sq.is_part_of_room() && (0..constants::NUM_DIRECTIONS).any(|dir| {
+                    self.neighbor(x, y, dir).is_some_and(|n| {
+                        let other = &self.sq[self.get_offset(n.x, n.y)];
+                        !sq.is_wall_drawn(dir) && other.is_carved() && other.id != sq.id
+                    })
+                });
+                row.push(if !sq.is_carved() {
+                    Tile::Wall
+                } else if door {
+                    Tile::Door
+                } else if sq.is_part_of_room() {
+                    Tile::RoomFloor
+                } else {
+                    Tile::Floor
+                });
+            }
+            tiles.push(row);
+        }
+        for door in self.doors.iter() {
+            tiles[door.coord.y as usize][door.coord.x as usize] = Tile::Door;
+        }
+        for (Opening { coord, .. }, tile) in self.opening_tiles() {
+            tiles[coord.y as usize][coord.x as usize] = tile;
+        }
+        tiles
+    }
+
+    //
+    // Internal - walls as whole tiles, on the WallGrid layout.  Open tiles
+    // are room floor when every Square they touch is in the same room, and
+    // a door when they join a room to anywhere else.
+    //
+    fn thick_tilemap(&self) -> Vec<Vec<Tile>> {
+        let grid = self.to_wall_grid();
+        // The Squares a grid position along one axis touches: odd ones are
+        // Squares, even ones sit between two of them
+        let span = |g: u32, len: u32| -> Vec<u32> {
+            if g % 2 == 1 {
+                vec![g / 2]
+            } else {
+                (g.saturating_sub(1) / 2..(g / 2 + 1).min(len)).collect()
+            }
+        };
+
+        let mut tiles = Vec::with_capacity(grid.height as usize);
+        for gy in 0..grid.height {
+            let mut row = Vec::with_capacity(grid.width as usize);
+            for gx in 0..grid.width {
+                if grid.is_wall(gx, gy) {
+                    row.push(Tile::Wall);
+                    continue;
+                }
+                let touched: Vec<&crate::maze::square::Square> = span(gy, self.rows).iter()
+                    .flat_map(|&y| span(gx, self.cols).into_iter().map(move |x| (x, y)))
+                    .map(|(x, y)| &self.sq[self.get_offset(x, y)])
+                    .collect();
+                let rooms = touched.iter().filter(|sq| sq.is_part_of_room()).count();
+                let same_room = touched.iter().all(|sq| sq.id == touched[0].id);
+                row.push(if !touched.iter().any(|sq| sq.is_carved()) {
+                    Tile::Wall
+                } else if rooms == touched.len() && same_room {
+                    Tile::RoomFloor
+                } else if rooms > 0 && touched.len() == 2 {
+                    Tile::Door
+                } else {
+                    Tile::Floor
+                });
+            }
+            tiles.push(row);
+        }
+        let doors = self.doors.iter().map(|d| (Opening { coord: d.coord, dir: d.dir }, Tile::Door));
+        for (Opening { coord: Coord { x, y }, dir }, tile) in doors.chain(self.opening_tiles()) {
+            let (gx, gy) = match dir {
+                constants::DIR_NORTH => (x * 2 + 1, y * 2),
+                constants::DIR_SOUTH => (x * 2 + 1, y * 2 + 2),
+                constants::DIR_EAST => (x * 2 + 2, y * 2 + 1),
+                _ => (x * 2, y * 2 + 1),
+            };
+            tiles[gy as usize][gx as usize] = tile;
+        }
+        tiles
+    }
+
+    //
+    // Internal - the entrance and every exit, with the Tile each is drawn
+    // as.
+    //
+    fn opening_tiles(&self) -> Vec<(Opening, Tile)> {
+        self.entrance.iter().map(|&o| (o, Tile::Entrance))
+            .chain(self.exits.iter().map(|&o| (o, Tile::Exit)))
+            .collect()
+    }
+
+    /// Expands the Maze into a WallGrid.  Corner posts are only left open
+    /// when none of the four walls that meet at them are present, and
+    /// secret passages are walls.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::Maze::new(2, 3);
+    /// let grid = maze.to_wall_grid();
+    /// assert_eq!((7, 5), (grid.width, grid.height));
+    /// assert_eq!(false, grid.is_wall(1, 1));
+    /// assert_eq!(true, grid.is_wall(2, 1));
+    /// ```
+    pub fn to_wall_grid(&self) -> WallGrid {
+        let width = self.cols * 2 + 1;
+        let height = self.rows * 2 + 1;
+        let mut walls = vec![true; (width * height) as usize];
+
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let sq = &self.sq[self.get_offset(x, y)];
+                let (gx, gy) = (x * 2 + 1, y * 2 + 1);
+                walls[(gy * width + gx) as usize] = false;
+                walls[((gy - 1) * width + gx) as usize] = sq.is_wall_drawn(constants::DIR_NORTH);
+                walls[((gy + 1) * width + gx) as usize] = sq.is_wall_drawn(constants::DIR_SOUTH);
+                walls[(gy * width + gx + 1) as usize] = sq.is_wall_drawn(constants::DIR_EAST);
+                walls[(gy * width + gx - 1) as usize] = sq.is_wall_drawn(constants::DIR_WEST);
+            }
+        }
+
+        // Interior posts are open when every wall meeting at them is open
+        for gy in (2..height - 1).step_by(2) {
+            for gx in (2..width - 1).step_by(2) {
+                let any_wall = walls[((gy - 1) * width + gx) as usize] ||
+                               walls[((gy + 1) * width + gx) as usize] ||
+                               walls[(gy * width + gx - 1) as usize] ||
+                               walls[(gy * width + gx + 1) as usize];
+                walls[(gy * width + gx) as usize] = any_wall;
+            }
+        }
+
+        WallGrid { width, height, walls }
+    }
+
+    /// Expands the Maze into a WallGrid and run-length encodes it, as
+    /// WallGrid::to_rle() describes.  Long runs of wall or open floor, like
+    /// rooms, take a byte or two however long they are.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::render::WallGrid;
+    ///
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let (grid, _) = WallGrid::from_rle(&maze.to_wall_grid_rle()).unwrap();
+    /// assert_eq!(maze.to_wall_grid(), grid);
+    /// ```
+    pub fn to_wall_grid_rle(&self) -> Vec<u8> {
+        self.to_wall_grid().to_rle()
+    }
+
+    /// Reads the walls back out of a drawing made by render_ascii(), so
+    /// what was drawn can be checked against the Maze it came from.  Every
+    /// wall that isn't an 'X' is open, and the arrows render_ascii() draws
+    /// between Squares become one-way passages.  The drawing doesn't say
+    /// which gap in the border is the entrance, so every gap is recorded as
+    /// an exit, in row-major order.  Room ids, weave crossings and path
+    /// marks aren't recovered.
+    ///
+    /// Arrows in the border are open seams, so an axis with one wraps
+    /// around.  An axis whose seams are all walled up draws just like a
+    /// bounded one, and is read as one.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(4, 6);
+    /// maze.generate_perfect_seeded(2).unwrap();
+    /// let drawn = mazegame::Maze::from_ascii(&maze.render_ascii()).unwrap();
+    /// assert_eq!(maze.to_compact(), drawn.to_compact());
+    /// ```
+    pub fn from_ascii(text: &str) -> Result<Maze, MazeError> {
+        let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+        let width = lines.first().map_or(0, |line| line.len());
+        if lines.len() < 3 || lines.len().is_multiple_of(2) || width < 3 || width.is_multiple_of(2) {
+            return Err(MazeError::Parse(format!("a {}x{} drawing isn't 2n+1 characters each way", width, lines.len())));
+        }
+        if let Some(y) = lines.iter().position(|line| line.len() != width) {
+            return Err(MazeError::Parse(format!("line {} is {} characters long, not {}", y + 1, lines[y].len(), width)));
+        }
+
+        let (cols, rows) = ((width as u32 - 1) / 2, (lines.len() as u32 - 1) / 2);
+        let (last_x, last_y) = (width - 1, lines.len() - 1);
+        let wraps_x = lines.iter().any(|line| line[0] == '<' || line[last_x] == '>');
+        let wraps_y = lines[0].contains(&'^') || lines[last_y].contains(&'v');
+        let mut maze = Maze::new(rows, cols);
+        let topology = |wraps: bool| if wraps { Topology::Torus } else { Topology::Bounded };
+        maze.set_topology(topology(wraps_x), topology(wraps_y));
+
+        for y in 0..rows {
+            for x in 0..cols {
+                let (gx, gy) = (x as usize * 2 + 1, y as usize * 2 + 1);
+                let sides = [(constants::DIR_NORTH, gx, gy - 1, 'v', '^'),
+                             (constants::DIR_SOUTH, gx, gy + 1, '^', 'v'),
+                             (constants::DIR_EAST, gx + 1, gy, '<', '>'),
+                             (constants::DIR_WEST, gx - 1, gy, '>', '<')];
+                for &(dir, wx, wy, inward, outward) in sides.iter() {
+                    let c = lines[wy][wx];
+                    let on_edge = wx == 0 || wy == 0 || wx == last_x || wy == last_y;
+                    match maze.neighbor(x, y, dir) {
+                        _ if c == 'X' => {}
+                        None if c == inward || c == outward => {
+                            return Err(MazeError::Parse(format!("the border at ({}, {}) can't wrap around", wx, wy)));
+                        }
+                        None => {
+                            maze.add_exit(x, y, dir)?;
+                            let offset = maze.get_offset(x, y);
+                            maze.sq[offset].id = constants::ID_MAZE_PATH;
+                        }
+                        // Seams are drawn the same whichever way they go
+                        Some(_) if on_edge => {
+                            maze.carve(x, y, dir, constants::ID_MAZE_PATH, false).map_err(MazeError::Parse)?;
+                        }
+                        // Each passage is read from the Square it leads out of
+                        Some(_) if c == inward => {}
+                        Some(_) if c == outward => {
+                            maze.carve_one_way(x, y, dir).map_err(MazeError::Parse)?;
+                        }
+                        Some(_) => {
+                            maze.carve(x, y, dir, constants::ID_MAZE_PATH, false).map_err(MazeError::Parse)?;
+                        }
+                    }
+                }
+            }
+        }
+        maze.mark_changed();
+        Ok(maze)
+    }
+
+    /// Renders the Maze as an SVG image, with each Square `cell_size` pixels
+    /// across.  Walls, and secret passages, are drawn as black lines on a
+    /// white background.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(5, 5);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let svg = maze.render_svg(10);
+    /// assert!(svg.starts_with("<svg"));
+    /// ```
+    pub fn render_svg(&self, cell_size: u32) -> String {
+        let margin = cell_size / 2;
+        let width = self.cols * cell_size + margin * 2;
+        let height = self.rows * cell_size + margin * 2;
+        let mut out = String::new();
+
+        out.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                               viewBox=\"0 0 {} {}\">\n", width, height, width, height));
+        out.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", width, height));
+        out.push_str("<g stroke=\"black\" stroke-width=\"2\" stroke-linecap=\"square\">\n");
+
+        let line = |out: &mut String, x1: u32, y1: u32, x2: u32, y2: u32| {
+            out.push_str(&format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>\n",
+                                  margin + x1 * cell_size, margin + y1 * cell_size,
+                                  margin + x2 * cell_size, margin + y2 * cell_size));
+        };
+
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let sq = &self.sq[self.get_offset(x, y)];
+                // Each Square draws its north and west walls; the last row and
+                // column also draw the south and east borders.
+                if sq.is_wall_drawn(constants::DIR_NORTH) {
+                    line(&mut out, x, y, x + 1, y);
+                }
+                if sq.is_wall_drawn(constants::DIR_WEST) {
+                    line(&mut out, x, y, x, y + 1);
+                }
+                if y == self.rows - 1 && sq.is_wall_drawn(constants::DIR_SOUTH) {
+                    line(&mut out, x, y + 1, x + 1, y + 1);
+                }
+                if x == self.cols - 1 && sq.is_wall_drawn(constants::DIR_EAST) {
+                    line(&mut out, x + 1, y, x + 1, y + 1);
+                }
+            }
+        }
+
+        out.push_str("</g>\n</svg>\n");
+        out
+    }
+}
+
+//
+// Internal - appends value as a varint: 7 bits a byte, lowest first, with
+// the top bit set on every byte but the last.
+//
+fn push_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+//
+// Internal - reads a varint from the start of data, returning it and how
+// many bytes it took, or None if it's cut off or too big for a u64.
+//
+fn read_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        let bits = (byte & 0x7f) as u64;
+        if i == 9 && bits > 1 {
+            return None;
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::assert_matches_fixture;
+
+    #[test]
+    fn test_wall_grid_matches_ascii() {
+        // Outside of rooms, the grid should line up exactly with the ASCII
+        // drawing's X characters.
+        let mut maze = Maze::new(7, 9);
+        maze.generate_perfect_seeded(3).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        maze.set_exit(8, 6, constants::DIR_EAST).unwrap();
+        let grid = maze.to_wall_grid();
+        let ascii = maze.render_ascii();
+        for (gy, line) in ascii.lines().enumerate() {
+            for (gx, c) in line.chars().enumerate() {
+                assert_eq!(c == 'X', grid.is_wall(gx as u32, gy as u32), "mismatch at ({}, {})", gx, gy);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wall_grid_open_posts() {
+        // Opening all four walls around the center post of a 2x2 maze opens the post too
+        let maze = Maze::from_compact("6a\n11").unwrap();
+        assert!(maze.to_wall_grid().is_wall(2, 2));
+        let maze = Maze::from_compact("6a\n59").unwrap();
+        assert!(!maze.to_wall_grid().is_wall(2, 2));
+        // Outer posts never open
+        assert!(maze.to_wall_grid().is_wall(0, 0));
+    }
+
+    #[test]
+    fn test_rle_round_trip() {
+        let mut rooms = Maze::new(30, 40);
+        rooms.generate_seeded((6, 4, 8, 4, 8), 462).unwrap();
+        rooms.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        rooms.set_exit(39, 29, constants::DIR_EAST).unwrap();
+        let mut perfect = Maze::new(25, 17);
+        perfect.generate_perfect_seeded(462).unwrap();
+
+        // Back to back in one stream, split again by the length prefixes
+        let mut stream = Vec::new();
+        for maze in [&rooms, &perfect, &Maze::new(1, 1)].iter() {
+            stream.extend(maze.to_wall_grid_rle());
+        }
+        let mut rest = &stream[..];
+        for maze in [&rooms, &perfect, &Maze::new(1, 1)].iter() {
+            let (grid, used) = WallGrid::from_rle(rest).unwrap();
+            assert_eq!(maze.to_wall_grid(), grid);
+            rest = &rest[used..];
+        }
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_rle_size() {
+        // One big room: nearly every run is a whole row of open floor
+        let mut open = Maze::new(100, 100);
+        for y in 0..100 {
+            for x in 0..100 {
+                if x < 99 {
+                    open.carve(x, y, constants::DIR_EAST, 1, false).unwrap();
+                }
+                if y < 99 {
+                    open.carve(x, y, constants::DIR_SOUTH, 1, false).unwrap();
+                }
+            }
+        }
+        let grid = open.to_wall_grid();
+        let rle = open.to_wall_grid_rle();
+        assert!(rle.len() * 20 < grid.walls.len(), "{} bytes for {} cells", rle.len(), grid.walls.len());
+
+        // A perfect maze has short runs, but still no more than a byte each
+        let mut perfect = Maze::new(100, 100);
+        perfect.generate_perfect_seeded(462).unwrap();
+        assert!(perfect.to_wall_grid_rle().len() < perfect.to_wall_grid().walls.len());
+    }
+
+    #[test]
+    fn test_rle_rejects_bad_records() {
+        let mut maze = Maze::new(3, 3);
+        maze.generate_perfect_seeded(4).unwrap();
+        let good = maze.to_wall_grid_rle();
+        for cut in 0..good.len() {
+            assert!(WallGrid::from_rle(&good[..cut]).is_err(), "cut at {}", cut);
+        }
+
+        // Runs that don't add up, or a first cell that isn't 0 or 1
+        let record = |first: u8, runs: &[u8]| {
+            let mut body = vec![3, 0, 0, 0, 3, 0, 0, 0, first];
+            body.extend_from_slice(runs);
+            let mut out = (body.len() as u32 + 4).to_le_bytes().to_vec();
+            out.extend_from_slice(&body);
+            out.extend_from_slice(&crc32(&body).to_le_bytes());
+            WallGrid::from_rle(&out)
+        };
+        assert!(record(1, &[4, 1, 4]).is_ok());
+        assert!(record(1, &[4, 1, 3]).is_err());
+        assert!(record(1, &[4, 0, 1, 4]).is_err());
+        assert!(record(1, &[4, 1, 5]).is_err());
+        assert!(record(2, &[4, 1, 4]).is_err());
+        // Laid out wrong: the Square's cell is a wall
+        assert!(record(1, &[9]).is_err());
+        // A run length that never ends
+        assert!(record(1, &[0x84, 0x80]).is_err());
+    }
+
+    #[test]
+    fn test_rle_corruption() {
+        let mut maze = Maze::new(6, 9);
+        maze.generate_perfect_seeded(480).unwrap();
+        let good = maze.to_wall_grid_rle();
+        for i in 0..good.len() {
+            let mut bad = good.clone();
+            bad[i] ^= 0x04;
+            assert!(matches!(WallGrid::from_rle(&bad), Err(MazeError::Corrupted { .. })), "byte {}", i);
+        }
+
+        // A size far too big to allocate, behind a good CRC-32
+        let mut body = vec![0xff, 0xff, 0, 0, 0xff, 0xff, 0, 0, 1];
+        push_varint(&mut body, 0xffff * 0xffff);
+        let mut record = (body.len() as u32 + 4).to_le_bytes().to_vec();
+        record.extend_from_slice(&body);
+        record.extend_from_slice(&crc32(&body).to_le_bytes());
+        assert_eq!(Err(MazeError::Corrupted { what: "wall grid size 65535x65535".to_string(), offset: 4 }),
+                   WallGrid::from_rle(&record));
+        record[0..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(WallGrid::from_rle(&record), Err(MazeError::Corrupted { offset: 0, .. })));
+    }
+
+    // A 3x2 maze whose right two columns are a room, with the entrance on
+    // the west and the exit on the east
+    fn room_maze() -> Maze {
+        let mut maze = Maze::from_compact("cea\n4dd").unwrap();
+        for &(x, y) in [(1, 0), (2, 0), (1, 1), (2, 1)].iter() {
+            let offset = maze.get_offset(x, y);
+            maze.sq[offset].id = 1;
+        }
+        maze
+    }
+
+    fn tiles(rows: &[&str]) -> Vec<Vec<Tile>> {
+        rows.iter()
+            .map(|row| row.chars().map(|c| match c {
+                '#' => Tile::Wall,
+                '.' => Tile::Floor,
+                'r' => Tile::RoomFloor,
+                'D' => Tile::Door,
+                'E' => Tile::Entrance,
+                'X' => Tile::Exit,
+                _ => panic!("bad tile {}", c),
+            }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_from_ascii() {
+        // Rooms, border gaps and one-way passages all read back
+        let mut maze = Maze::new(9, 9);
+        maze.generate_seeded((2, 2, 3, 2, 3), 464).unwrap();
+        maze.connect_regions_with_rng(&mut crate::rng::from_seed(464));
+        maze.carve_one_way(0, 0, constants::DIR_EAST).unwrap();
+        maze.carve_one_way(0, 1, constants::DIR_NORTH).unwrap();
+        maze.set_entrance(0, 4, constants::DIR_WEST).unwrap();
+        maze.set_exit(8, 8, constants::DIR_SOUTH).unwrap();
+        let drawn = Maze::from_ascii(&maze.render_ascii()).unwrap();
+        for (a, b) in maze.sq.iter().zip(drawn.sq.iter()) {
+            assert_eq!((a.wall_present, a.one_way), (b.wall_present, b.one_way));
+        }
+        assert_eq!(Ok(()), drawn.validate());
+        assert_eq!(None, drawn.get_entrance());
+        let gaps: Vec<Opening> = maze.get_entrance().into_iter().chain(maze.get_exit()).collect();
+        assert_eq!(&gaps[..], drawn.exits());
+
+        assert!(Maze::from_ascii("XXXX\nX  X\nXXXX\n").is_err());
+        assert!(Maze::from_ascii("XXX\nX X\nXX\n").is_err());
+        assert!(Maze::from_ascii("").is_err());
+        // A gap in the bounded part of a cylinder's border can't be a seam
+        assert!(Maze::from_ascii("X<X\nX X\nXXX\n").is_ok());
+        assert!(Maze::from_ascii("X^X\n< >\nXXX\n").is_err());
+    }
+
+    #[test]
+    fn test_from_ascii_seams() {
+        let mut cylinder = Maze::new(4, 5);
+        cylinder.set_topology(Topology::Torus, Topology::Bounded);
+        cylinder.generate_perfect_seeded(465).unwrap();
+        cylinder.carve(4, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        cylinder.set_entrance(2, 0, constants::DIR_NORTH).unwrap();
+        let drawn = Maze::from_ascii(&cylinder.render_ascii()).unwrap();
+        assert_eq!((Topology::Torus, Topology::Bounded), drawn.get_topology());
+        assert_eq!(cylinder.render_ascii(), drawn.render_ascii());
+        assert_eq!(Ok(()), drawn.validate());
+
+        let mut torus = Maze::new(3, 3);
+        torus.set_topology(Topology::Torus, Topology::Torus);
+        torus.generate_perfect_seeded(465).unwrap();
+        torus.carve(0, 0, constants::DIR_NORTH, constants::ID_MAZE_PATH, false).unwrap();
+        torus.carve(0, 0, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        assert_eq!(torus.render_ascii(), Maze::from_ascii(&torus.render_ascii()).unwrap().render_ascii());
+    }
+
+    #[test]
+    fn test_thin_tilemap() {
+        let maze = room_maze();
+        assert_eq!((3, 2), maze.tilemap_size(TileStyle::Thin));
+        assert_eq!(tiles(&["EDr",
+                           ".DX"]), maze.to_tilemap(TileStyle::Thin));
+        assert_eq!(tiles(&["##"]), Maze::new(1, 2).to_tilemap(TileStyle::Thin));
+    }
+
+    #[test]
+    fn test_tilemap_regions() {
+        let mut maze = Maze::new(10, 10);
+        maze.generate_seeded((2, 2, 3, 2, 3), 458).unwrap();
+        assert!(maze.tilemap_regions(TileStyle::Thin).iter().flatten().all(|r| r.is_none()));
+        maze.partition_corridors(3, 458).unwrap();
+
+        let thin = maze.tilemap_regions(TileStyle::Thin);
+        let thick = maze.tilemap_regions(TileStyle::Thick);
+        let tiles = maze.to_tilemap(TileStyle::Thick);
+        for y in 0..10 {
+            for x in 0..10 {
+                let region = maze.corridor_region(Coord::new(x, y));
+                assert_eq!(region, thin[y as usize][x as usize]);
+                assert_eq!(region, thick[y as usize * 2 + 1][x as usize * 2 + 1]);
+            }
+        }
+        // Only open tiles ever get a region
+        for (row, tile_row) in thick.iter().zip(tiles.iter()) {
+            for (region, tile) in row.iter().zip(tile_row.iter()) {
+                assert!(region.is_none() || *tile != Tile::Wall);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tilemap_several_exits() {
+        let mut maze = Maze::from_compact("4ca\n4c9").unwrap();
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        maze.set_exit(0, 1, constants::DIR_WEST).unwrap();
+        maze.add_exit(2, 0, constants::DIR_NORTH).unwrap();
+        assert_eq!(tiles(&["E.X",
+                           "X.."]), maze.to_tilemap(TileStyle::Thin));
+        assert_eq!(tiles(&["#####X#",
+                           "E.....#",
+                           "#####.#",
+                           "X.....#",
+                           "#######"]), maze.to_tilemap(TileStyle::Thick));
+        assert_eq!("XXXXX X\n      X\nXXXXX X\n      X\nXXXXXXX\n", maze.render_ascii());
+    }
+
+    #[test]
+    fn test_thick_tilemap() {
+        let maze = room_maze();
+        assert_eq!((7, 5), maze.tilemap_size(TileStyle::Thick));
+        assert_eq!(tiles(&["#######",
+                           "E.Drrr#",
+                           "###rrr#",
+                           "#.DrrrX",
+                           "#######"]), maze.to_tilemap(TileStyle::Thick));
+        // Uncarved Squares are solid
+        assert_eq!(tiles(&["#####"; 3]), Maze::new(1, 2).to_tilemap(TileStyle::Thick));
+
+        // Without the room it's all plain floor
+        let plain = Maze::from_compact("cea\n4dd").unwrap();
+        assert_eq!(tiles(&["#######",
+                           "E.....#",
+                           "###...#",
+                           "#.....X",
+                           "#######"]), plain.to_tilemap(TileStyle::Thick));
+    }
+
+    #[test]
+    fn test_tilemap_doors() {
+        use crate::maze::transform::Transformable;
+
+        let mut maze = Maze::new(12, 12);
+        maze.generate_seeded((3, 2, 3, 2, 3), 470).unwrap();
+        maze.connect_regions_with_rng(&mut crate::rng::from_seed(470));
+        for maze in [maze.clone(), maze.rotate_cw(), maze.mirror_y()].iter() {
+            assert!(!maze.doors().is_empty());
+            let thin = maze.to_tilemap(TileStyle::Thin);
+            let thick = maze.to_tilemap(TileStyle::Thick);
+            for door in maze.doors() {
+                let Coord { x, y } = door.coord;
+                assert_eq!(Tile::Door, thin[y as usize][x as usize]);
+                let n = maze.neighbor(x, y, door.dir).unwrap();
+                assert_eq!(Tile::Door, thick[(y + n.y + 1) as usize][(x + n.x + 1) as usize]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_svg() {
+        // An uncarved 1x2 maze has 7 wall segments: top and bottom of both
+        // squares, two outer sides and the wall between them.
+        let maze = Maze::new(1, 2);
+        let svg = maze.render_svg(10);
+        assert_eq!(7, svg.matches("<line").count());
+        assert!(svg.contains("width=\"30\" height=\"20\""));
+
+        let mut maze = Maze::new(1, 2);
+        maze.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        assert_eq!(6, maze.render_svg(10).matches("<line").count());
+    }
+
+    #[test]
+    fn test_secrets_drawn_as_walls() {
+        // A one Square room joined to a corridor only by a secret passage
+        let mut maze = Maze::new(1, 2);
+        maze.carve_secret(0, 0, constants::DIR_EAST).unwrap();
+        maze.sq[0].id = 1;
+        let walled = Maze::new(1, 2);
+        assert_eq!(walled.to_wall_grid(), maze.to_wall_grid());
+        assert_eq!(walled.render_svg(10), maze.render_svg(10));
+        assert_eq!(tiles(&["r."]), maze.to_tilemap(TileStyle::Thin));
+        assert_eq!(tiles(&["#####", "#r#.#", "#####"]), maze.to_tilemap(TileStyle::Thick));
+    }
+
+    #[test]
+    fn test_golden_renders() {
+        let mut maze = Maze::new(6, 4);
+        maze.generate_perfect_seeded(450).unwrap();
+        maze.place_entrance_exit_longest().unwrap();
+        let (entrance, exit) = (maze.get_entrance().unwrap(), maze.get_exit().unwrap());
+        let path = maze.solve(entrance.coord, exit.coord).unwrap();
+        assert_matches_fixture("perfect_6x4_seed450.txt", &maze.render_ascii_with_path(&path));
+        assert_matches_fixture("perfect_6x4_seed450.svg", &maze.render_svg(10));
+    }
+}
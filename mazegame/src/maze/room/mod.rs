@@ -0,0 +1,695 @@
+//! Looking up the Squares that make up a room.  Rooms are found by the ids
+//! their Squares were carved with rather than by a bounding box, so rooms of
+//! any shape work, like caverns or rooms made of several rectangles.
+//!
+//! Rooms can also be given themes, like "library" or "crypt", picked from a
+//! weighted list.  A theme is just a name; what it means is up to whatever
+//! draws or furnishes the room.
+//!
+//! The doorways connect_regions() knocks through a room's walls are
+//! recorded as Doors, which are saved with the Maze and follow it through
+//! transforms, so a game can put a door sprite (or a lock) in each.
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::{analysis, Coord, Maze};
+use crate::rng;
+
+/// The Squares of one room.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Room {
+    id: i32,
+    cells: Vec<Coord>,
+    theme: Option<String>,
+}
+
+/// A doorway into a room: an open wall between one of the room's Squares and
+/// a Square outside it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Door {
+    /// The room Square the doorway is in.
+    pub coord: Coord,
+    /// The side of that Square the doorway is on.
+    pub dir: u32,
+    pub room_id: i32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub locked: bool,
+}
+
+impl Door {
+    //
+    // Internal - the order a Maze keeps its doors in: by row, column and
+    // direction.
+    //
+    pub(crate) fn order(&self) -> (u32, u32, u32) {
+        (self.coord.y, self.coord.x, self.dir)
+    }
+}
+
+/// Settings for Maze::merge_adjacent_rooms_with().
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RoomMergeConfig {
+    /// Whether a recorded Door between two rooms merges them too.  Off by
+    /// default, since connect_regions() knocks doors between neighboring
+    /// rooms on purpose and they're meant to stay separate rooms.
+    pub merge_through_doors: bool,
+}
+
+impl Room {
+    /// Returns the room's id, as stored in its Squares.
+    pub fn get_id(&self) -> i32 {
+        self.id
+    }
+
+    /// Returns the room's Squares in row-major order.
+    pub fn cells(&self) -> &[Coord] {
+        &self.cells
+    }
+
+    /// Returns the number of Squares in the room.
+    pub fn area(&self) -> u32 {
+        self.cells.len() as u32
+    }
+
+    /// Returns the room's theme, or None if the rooms haven't been themed.
+    pub fn theme(&self) -> Option<&str> {
+        self.theme.as_deref()
+    }
+
+    /// Returns whether coord is one of the room's Squares.
+    pub fn contains(&self, coord: Coord) -> bool {
+        self.cells.binary_search_by(|c| (c.y, c.x).cmp(&(coord.y, coord.x))).is_ok()
+    }
+}
+
+impl Maze {
+    /// Returns the number of rooms in the Maze.  Rooms are numbered from 1.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(20, 20);
+    /// maze.generate_seeded((3, 2, 4, 2, 4), 1).unwrap();
+    /// assert!(maze.get_num_rooms() <= 3);
+    /// ```
+    pub fn get_num_rooms(&self) -> u32 {
+        self.num_rooms
+    }
+
+    /// Returns the Squares of room room_id, in row-major order.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(20, 20);
+    /// maze.generate_seeded((3, 2, 4, 2, 4), 1).unwrap();
+    /// for c in maze.room_cells(1) {
+    ///     assert_eq!(1, maze.sq[(c.y * 20 + c.x) as usize].id);
+    /// }
+    /// ```
+    pub fn room_cells(&self, room_id: i32) -> impl Iterator<Item = Coord> + '_ {
+        self.sq.iter().enumerate()
+            .filter(move |&(_, sq)| sq.is_part_of_room() && sq.id == room_id)
+            .map(move |(i, _)| self.get_coord(i))
+    }
+
+    /// Returns room room_id, or None if no Square belongs to it.
+    pub fn room(&self, room_id: i32) -> Option<Room> {
+        let cells: Vec<Coord> = self.room_cells(room_id).collect();
+        if cells.is_empty() {
+            None
+        } else {
+            let theme = self.room_theme(room_id).map(|t| t.to_string());
+            Some(Room { id: room_id, cells, theme })
+        }
+    }
+
+    /// Returns the theme of room room_id, or None if it hasn't been given
+    /// one.
+    pub fn room_theme(&self, room_id: i32) -> Option<&str> {
+        if room_id < 1 {
+            return None;
+        }
+        // Rooms without a theme among ones with them have an empty one
+        self.room_themes.get(room_id as usize - 1).map(|t| t.as_str()).filter(|t| !t.is_empty())
+    }
+
+    /// Gives every room a theme picked at random from themes, a list of
+    /// (name, weight) pairs.  A theme with twice the weight of another is
+    /// picked about twice as often.  Any themes the rooms already had are
+    /// replaced.  The same seed always gives the same themes.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(20, 20);
+    /// maze.generate_seeded((3, 2, 4, 2, 4), 1).unwrap();
+    /// let themes = [("library".to_string(), 1.0), ("crypt".to_string(), 3.0)];
+    /// maze.theme_rooms(&themes, 7).unwrap();
+    /// for room in maze.rooms() {
+    ///     assert!(room.theme() == Some("library") || room.theme() == Some("crypt"));
+    /// }
+    /// ```
+    pub fn theme_rooms(&mut self, themes: &[(String, f32)], seed: u64) -> Result<(), MazeError> {
+        self.theme_rooms_with_rng(themes, &mut rng::from_seed(seed))
+    }
+
+    /// Gives every room a theme like theme_rooms(), using rng.
+    pub fn theme_rooms_with_rng<R: Rng>(&mut self, themes: &[(String, f32)], rng: &mut R) -> Result<(), MazeError> {
+        if themes.iter().any(|&(_, weight)| !weight.is_finite() || weight < 0.0) {
+            return Err(MazeError::InvalidArgument("theme weights can't be negative".to_string()));
+        }
+        let total: f32 = themes.iter().map(|&(_, weight)| weight).sum();
+        // Rounding can leave a pick just past the end, which goes to the
+        // last theme that can be picked at all
+        let last = match themes.iter().rposition(|&(_, weight)| weight > 0.0) {
+            Some(last) if total.is_finite() => last,
+            _ => return Err(MazeError::InvalidArgument("at least one theme needs a weight above zero".to_string())),
+        };
+
+        self.room_themes = (0..self.num_rooms).map(|_| {
+            let mut pick = rng.gen::<f32>() * total;
+            let chosen = themes.iter()
+                .position(|&(_, weight)| {
+                    if pick < weight {
+                        true
+                    } else {
+                        pick -= weight;
+                        false
+                    }
+                })
+                .unwrap_or(last);
+            themes[chosen].0.clone()
+        }).collect();
+        Ok(())
+    }
+
+    /// Returns the rooms with theme name, in order of id.
+    pub fn rooms_with_theme(&self, name: &str) -> Vec<Room> {
+        self.rooms().into_iter().filter(|room| room.theme() == Some(name)).collect()
+    }
+
+    /// Returns every room in the Maze, in order of id.
+    pub fn rooms(&self) -> Vec<Room> {
+        (1..=self.num_rooms as i32).filter_map(|id| self.room(id)).collect()
+    }
+
+    /// Renumbers the rooms 1 to n, ordered by the first Square of each room
+    /// in row-major order, so the room nearest the top left becomes room 1.
+    /// Every Square's id and every door is rewritten, and themes move with
+    /// their rooms.  Returns a map from each old id to its new one.
+    ///
+    /// Cropping or editing a Maze can leave gaps in the ids, and stitching
+    /// or stamping can leave two separate rooms with the same id.  A room
+    /// whose Squares don't all touch is split into one room for each piece,
+    /// each keeping the room's theme, and the map gives the new id of the
+    /// first piece.  Compacting afterwards gives the same ids however the
+    /// rooms were made.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(4, 4);
+    /// maze.carve(2, 0, DIR_EAST, 7, false).unwrap();
+    /// maze.carve(0, 3, DIR_EAST, 4, false).unwrap();
+    /// let mapping = maze.compact_room_ids();
+    /// assert_eq!(Some(&1), mapping.get(&7));
+    /// assert_eq!(Some(&2), mapping.get(&4));
+    /// assert_eq!(2, maze.get_num_rooms());
+    /// assert_eq!(1, maze.sq[3].id);
+    /// ```
+    pub fn compact_room_ids(&mut self) -> BTreeMap<i32, i32> {
+        // The new id of every room Square, and the old id of each new room
+        let mut pieces = vec![0; self.sq.len()];
+        let mut old_ids: Vec<i32> = Vec::new();
+        let mut mapping = BTreeMap::new();
+        for first in 0..self.sq.len() {
+            if !self.sq[first].is_part_of_room() || pieces[first] != 0 {
+                continue;
+            }
+            let old = self.sq[first].id;
+            old_ids.push(old);
+            let new = old_ids.len() as i32;
+            mapping.entry(old).or_insert(new);
+            pieces[first] = new;
+            let mut stack = vec![first];
+            while let Some(i) = stack.pop() {
+                let c = self.get_coord(i);
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    if let Some(n) = self.neighbor(c.x, c.y, dir) {
+                        let j = self.get_offset(n.x, n.y);
+                        if pieces[j] == 0 && self.sq[j].is_part_of_room() && self.sq[j].id == old {
+                            pieces[j] = new;
+                            stack.push(j);
+                        }
+                    }
+                }
+            }
+        }
+        let unchanged = self.sq.iter().zip(pieces.iter()).all(|(sq, &new)| !sq.is_part_of_room() || sq.id == new);
+        if unchanged && self.num_rooms as usize == old_ids.len() {
+            return mapping;
+        }
+
+        let themes: Vec<String> = old_ids.iter().map(|&old| self.room_theme(old).unwrap_or("").to_string()).collect();
+        self.room_themes = if themes.iter().any(|t| !t.is_empty()) { themes } else { Vec::new() };
+
+        // Doors go to the piece they're in, and go with rooms that are gone
+        let doors = core::mem::take(&mut self.doors);
+        for mut door in doors {
+            let i = self.get_offset(door.coord.x, door.coord.y);
+            let new = if self.sq[i].is_part_of_room() && self.sq[i].id == door.room_id {
+                Some(pieces[i])
+            } else {
+                mapping.get(&door.room_id).cloned()
+            };
+            if let Some(new) = new {
+                door.room_id = new;
+                self.doors.push(door);
+            }
+        }
+        self.sort_doors();
+        for (sq, &new) in self.sq.iter_mut().zip(pieces.iter()) {
+            if sq.is_part_of_room() {
+                sq.id = new;
+            }
+        }
+        self.num_rooms = old_ids.len() as u32;
+        // Only the ids changed, so the corridor regions still fit
+        self.revision = analysis::next_revision();
+        mapping
+    }
+
+    /// Merges rooms that have been carved into each other, with
+    /// merge_adjacent_rooms_with() and the default settings: any open
+    /// passage between two rooms, other than a recorded Door, makes them
+    /// one room.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(4, 4);
+    /// maze.carve(0, 0, DIR_EAST, 1, false).unwrap();
+    /// maze.carve(2, 0, DIR_EAST, 2, false).unwrap();
+    /// maze.carve(1, 0, DIR_EAST, 1, true).unwrap();
+    /// assert!(maze.room(2).is_some());
+    /// let mapping = maze.merge_adjacent_rooms();
+    /// assert_eq!(Some(&1), mapping.get(&2));
+    /// assert_eq!(4, maze.rooms()[0].area());
+    /// ```
+    pub fn merge_adjacent_rooms(&mut self) -> BTreeMap<i32, i32> {
+        self.merge_adjacent_rooms_with(&RoomMergeConfig::default())
+    }
+
+    /// Finds open passages between Squares of different rooms and makes
+    /// each group of rooms joined that way into one room, which a player
+    /// would see as one space anyway.  Every Square's id is rewritten, the
+    /// doors between merged rooms go, and the other doors move to the
+    /// merged room.  A merged room keeps the theme of the room in it with
+    /// the lowest id.  The rooms are then renumbered as compact_room_ids()
+    /// does, so the ids run from 1 again.
+    ///
+    /// Returns a map from every old room id to its new one.  If nothing was
+    /// merged, the Maze is left alone and every id maps to itself.
+    pub fn merge_adjacent_rooms_with(&mut self, config: &RoomMergeConfig) -> BTreeMap<i32, i32> {
+        // Union-find over room ids, with the lowest id of each group as its
+        // root
+        let mut parent: BTreeMap<i32, i32> = self.sq.iter()
+            .filter(|sq| sq.is_part_of_room())
+            .map(|sq| (sq.id, sq.id))
+            .collect();
+        fn root(parent: &BTreeMap<i32, i32>, mut id: i32) -> i32 {
+            while parent[&id] != id {
+                id = parent[&id];
+            }
+            id
+        }
+
+        let mut merged = 0;
+        for a in 0..self.sq.len() {
+            if !self.sq[a].is_part_of_room() {
+                continue;
+            }
+            let c = self.get_coord(a);
+            for &side in Direction::ALL.iter() {
+                let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+                let n = match self.neighbor(c.x, c.y, dir) {
+                    Some(n) => n,
+                    None => continue,
+                };
+                let b = self.get_offset(n.x, n.y);
+                let open = !self.sq[a].is_wall_present(dir) || !self.sq[b].is_wall_present(back);
+                if !open || !self.sq[b].is_part_of_room() || self.sq[a].id == self.sq[b].id {
+                    continue;
+                }
+                let door = self.doors.iter()
+                    .any(|d| (d.coord, d.dir) == (c, dir) || (d.coord, d.dir) == (n, back));
+                if door && !config.merge_through_doors {
+                    continue;
+                }
+                let (ra, rb) = (root(&parent, self.sq[a].id), root(&parent, self.sq[b].id));
+                if ra != rb {
+                    parent.insert(ra.max(rb), ra.min(rb));
+                    merged += 1;
+                }
+            }
+        }
+        if merged == 0 {
+            return parent;
+        }
+
+        for sq in self.sq.iter_mut().filter(|sq| sq.is_part_of_room()) {
+            sq.id = root(&parent, sq.id);
+        }
+        let mut doors = core::mem::take(&mut self.doors);
+        for door in doors.iter_mut() {
+            door.room_id = parent.get(&door.room_id).map_or(door.room_id, |_| root(&parent, door.room_id));
+        }
+        // A doorway from a room into itself isn't a doorway any more
+        doors.retain(|d| {
+            self.neighbor(d.coord.x, d.coord.y, d.dir)
+                .is_none_or(|n| self.sq[self.get_offset(n.x, n.y)].id != d.room_id)
+        });
+        self.doors = doors;
+
+        let compacted = self.compact_room_ids();
+        gen_debug!("merge_adjacent_rooms merged={} rooms={}", merged, self.num_rooms);
+        parent.keys().map(|&id| (id, compacted[&root(&parent, id)])).collect()
+    }
+
+    /// Returns the Maze's doors, sorted by row, column and direction.
+    pub fn doors(&self) -> &[Door] {
+        &self.doors
+    }
+
+    /// Records a door.  The Square at its coord must be in room room_id,
+    /// and the wall on its side must be open onto a Square outside the
+    /// room.  Recording the same doorway again replaces the old record.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::room::Door;
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(4, 4);
+    /// maze.carve(1, 1, DIR_EAST, 1, false).unwrap();
+    /// maze.carve(1, 1, DIR_NORTH, 1, true).unwrap();
+    /// maze.sq[1].id = ID_MAZE_PATH;
+    /// let door = Door { coord: Coord::new(1, 1), dir: DIR_NORTH, room_id: 1, locked: true };
+    /// maze.add_door(door).unwrap();
+    /// assert_eq!(&[door], maze.doors());
+    /// assert!(maze.add_door(Door { dir: DIR_EAST, ..door }).is_err());
+    /// ```
+    pub fn add_door(&mut self, door: Door) -> Result<(), MazeError> {
+        let Coord { x, y } = door.coord;
+        if !self.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        let sq = &self.sq[self.get_offset(x, y)];
+        if !sq.is_part_of_room() || sq.id != door.room_id {
+            return Err(MazeError::InvalidArgument(format!("({}, {}) isn't in room {}", x, y, door.room_id)));
+        }
+        let outside = self.neighbor(x, y, door.dir)
+            .filter(|n| self.sq[self.get_offset(n.x, n.y)].id != door.room_id);
+        if outside.is_none() || sq.is_wall_present(door.dir) {
+            return Err(MazeError::InvalidArgument(format!("({}, {}) has no doorway on its {} side", x, y,
+                                                          crate::trace::dir_name(door.dir))));
+        }
+        self.doors.retain(|d| (d.coord, d.dir) != (door.coord, door.dir));
+        self.doors.push(door);
+        self.sort_doors();
+        Ok(())
+    }
+
+    /// Locks or unlocks the door on side dir of the Square at coord.  Fails
+    /// if there's no door recorded there.
+    pub fn set_door_locked(&mut self, coord: Coord, dir: u32, locked: bool) -> Result<(), MazeError> {
+        match self.doors.iter_mut().find(|d| d.coord == coord && d.dir == dir) {
+            Some(door) => {
+                door.locked = locked;
+                Ok(())
+            }
+            None => Err(MazeError::InvalidArgument(format!("there's no door on the {} side of ({}, {})",
+                                                           crate::trace::dir_name(dir), coord.x, coord.y))),
+        }
+    }
+
+    /// Picks a random Square in room room_id, or None if the room has no
+    /// Squares.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(20, 20);
+    /// maze.generate_seeded((3, 2, 4, 2, 4), 1).unwrap();
+    /// let spawn = maze.random_cell_in_room(1, &mut mazegame::rng::from_seed(2)).unwrap();
+    /// assert_eq!(1, maze.sq[(spawn.y * 20 + spawn.x) as usize].id);
+    /// ```
+    pub fn random_cell_in_room<R: Rng>(&self, room_id: i32, rng: &mut R) -> Option<Coord> {
+        let count = self.room_cells(room_id).count();
+        if count == 0 {
+            return None;
+        }
+        self.room_cells(room_id).nth(rng.gen_range(0, count))
+    }
+
+    //
+    // Internal - puts the doors back in row, column and direction order, so
+    // where a door comes in doors() never depends on the order they were
+    // added or loaded in.
+    //
+    pub(crate) fn sort_doors(&mut self) {
+        self.doors.sort_by_key(Door::order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH};
+    use crate::maze::transform::Transformable;
+    use crate::rng;
+
+    #[test]
+    fn test_dungeon_rooms() {
+        let mut maze = Maze::new(30, 30);
+        maze.generate_seeded((6, 2, 5, 2, 5), 421).unwrap();
+        let rooms = maze.rooms();
+        assert_eq!(maze.get_num_rooms() as usize, rooms.len());
+        assert!(!rooms.is_empty());
+
+        let mut total = 0;
+        for room in rooms.iter() {
+            for c in maze.room_cells(room.get_id()) {
+                assert_eq!(room.get_id(), maze.sq[maze.get_offset(c.x, c.y)].id);
+                assert!(room.contains(c));
+            }
+            // Generated rooms are rectangles, so the area fills the bounding box
+            let cells = room.cells();
+            let width = cells.iter().map(|c| c.x).max().unwrap() - cells.iter().map(|c| c.x).min().unwrap() + 1;
+            let height = cells.last().unwrap().y - cells[0].y + 1;
+            assert_eq!(width * height, room.area());
+            total += room.area();
+        }
+        assert_eq!(maze.sq.iter().filter(|sq| sq.is_part_of_room()).count() as u32, total);
+        assert_eq!(None, maze.room(maze.get_num_rooms() as i32 + 1));
+    }
+
+    #[test]
+    fn test_odd_shaped_room() {
+        // An L made of two rectangles with the same id
+        let mut maze = Maze::new(6, 6);
+        for &(x, y, dir) in [(1, 1, DIR_SOUTH), (1, 2, DIR_SOUTH), (1, 3, DIR_EAST), (2, 3, DIR_EAST)].iter() {
+            maze.carve(x, y, dir, 1, false).unwrap();
+        }
+        maze.num_rooms = 1;
+        let room = maze.room(1).unwrap();
+        assert_eq!(5, room.area());
+        assert!(room.contains(Coord::new(3, 3)));
+        assert!(!room.contains(Coord::new(2, 1)));
+
+        let mut rng = rng::from_seed(5);
+        for _ in 0..20 {
+            assert!(room.contains(maze.random_cell_in_room(1, &mut rng).unwrap()));
+        }
+        assert_eq!(None, maze.random_cell_in_room(2, &mut rng));
+    }
+
+    #[test]
+    fn test_theme_rooms() {
+        let mut maze = Maze::new(60, 60);
+        maze.generate_seeded((200, 2, 3, 2, 3), 436).unwrap();
+        assert!(maze.get_num_rooms() >= 100);
+        assert!(maze.rooms().iter().all(|room| room.theme().is_none()));
+
+        let themes = [("library".to_string(), 1.0), ("armory".to_string(), 0.0), ("crypt".to_string(), 3.0)];
+        maze.theme_rooms(&themes, 436).unwrap();
+        let libraries = maze.rooms_with_theme("library");
+        let crypts = maze.rooms_with_theme("crypt");
+        // Every room gets exactly one theme, and never one with no weight
+        assert!(maze.rooms_with_theme("armory").is_empty());
+        assert_eq!(maze.get_num_rooms() as usize, libraries.len() + crypts.len());
+        assert!(libraries.iter().all(|room| maze.room_theme(room.get_id()) == Some("library")));
+        // About a quarter are libraries
+        let share = libraries.len() as f32 / maze.get_num_rooms() as f32;
+        assert!(share > 0.15 && share < 0.35, "{}", share);
+
+        // The same seed gives the same themes, whatever was there before
+        let first: Vec<Room> = maze.rooms();
+        maze.theme_rooms(&themes, 1).unwrap();
+        assert_ne!(first, maze.rooms());
+        maze.theme_rooms(&themes, 436).unwrap();
+        assert_eq!(first, maze.rooms());
+
+        maze.reset();
+        assert_eq!(None, maze.room_theme(1));
+    }
+
+    #[test]
+    fn test_bad_theme_weights() {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_seeded((3, 2, 4, 2, 4), 1).unwrap();
+        assert!(maze.theme_rooms(&[], 1).is_err());
+        assert!(maze.theme_rooms(&[("a".to_string(), 0.0)], 1).is_err());
+        assert!(maze.theme_rooms(&[("a".to_string(), 1.0), ("b".to_string(), -1.0)], 1).is_err());
+        assert!(maze.theme_rooms(&[("a".to_string(), f32::NAN)], 1).is_err());
+        assert_eq!(None, maze.room_theme(1));
+        assert_eq!(None, maze.room_theme(0));
+    }
+
+    #[test]
+    fn test_compact_room_ids() {
+        // Rooms placed by hand with gaps and out of order
+        let mut maze = Maze::new(8, 8);
+        maze.carve(5, 1, DIR_EAST, 9, false).unwrap();
+        maze.carve(1, 1, DIR_SOUTH, 3, false).unwrap();
+        maze.carve(2, 6, DIR_EAST, 12, false).unwrap();
+        maze.carve(6, 6, DIR_SOUTH, 3, false).unwrap();
+        maze.num_rooms = 12;
+
+        let mapping = maze.compact_room_ids();
+        let expected: BTreeMap<i32, i32> = [(3, 1), (9, 2), (12, 3)].iter().cloned().collect();
+        assert_eq!(expected, mapping);
+        assert_eq!(4, maze.get_num_rooms());
+
+        let rooms = maze.rooms();
+        assert_eq!(vec![1, 2, 3, 4], rooms.iter().map(|r| r.get_id()).collect::<Vec<_>>());
+        // The two pieces of room 3 are told apart
+        assert_eq!(vec![Coord::new(1, 1), Coord::new(1, 2)], rooms[0].cells());
+        assert_eq!(vec![Coord::new(5, 1), Coord::new(6, 1)], rooms[1].cells());
+        assert_eq!(vec![Coord::new(2, 6), Coord::new(3, 6)], rooms[2].cells());
+        assert_eq!(vec![Coord::new(6, 6), Coord::new(6, 7)], rooms[3].cells());
+        for room in rooms.iter() {
+            for &c in room.cells() {
+                assert_eq!(room.get_id(), maze.sq[maze.get_offset(c.x, c.y)].id);
+            }
+        }
+        let total = maze.sq.iter().filter(|sq| sq.is_part_of_room()).count();
+        assert_eq!(8, total);
+
+        // Compacting again changes nothing
+        let revision = maze.revision();
+        let again = maze.compact_room_ids();
+        assert!(again.iter().all(|(old, new)| old == new));
+        assert_eq!(revision, maze.revision());
+    }
+
+    #[test]
+    fn test_compact_splits_rooms() {
+        // Two pieces of room 5, each with a door, and room 2 without a theme
+        let mut maze = Maze::new(6, 6);
+        maze.carve(0, 0, DIR_EAST, 5, false).unwrap();
+        maze.carve(3, 3, DIR_SOUTH, 2, false).unwrap();
+        maze.carve(4, 5, DIR_EAST, 5, false).unwrap();
+        maze.num_rooms = 5;
+        maze.room_themes = ["", "", "", "", "vault"].iter().map(|t| t.to_string()).collect();
+        maze.doors = vec![Door { coord: Coord::new(1, 0), dir: DIR_SOUTH, room_id: 5, locked: false },
+                          Door { coord: Coord::new(4, 5), dir: DIR_NORTH, room_id: 5, locked: true }];
+
+        maze.compact_room_ids();
+        assert_eq!(3, maze.get_num_rooms());
+        assert_eq!(vec![Coord::new(0, 0), Coord::new(1, 0)], maze.room(1).unwrap().cells());
+        assert_eq!(vec![Coord::new(4, 5), Coord::new(5, 5)], maze.room(3).unwrap().cells());
+        assert_eq!(vec![Some("vault"), None, Some("vault")], (1..=3).map(|id| maze.room_theme(id)).collect::<Vec<_>>());
+        assert_eq!(vec![1, 3], maze.doors().iter().map(|d| d.room_id).collect::<Vec<_>>());
+
+        // Stitching a Maze to itself gives the copy's rooms their own ids
+        // and themes
+        let wide = maze.stitch_horizontal(&maze).unwrap();
+        assert_eq!(6, wide.get_num_rooms());
+        let themes: Vec<Option<&str>> = (1..=6).map(|id| wide.room_theme(id)).collect();
+        assert_eq!(vec![Some("vault"), Some("vault"), None, None, Some("vault"), Some("vault")], themes);
+        assert_eq!(vec![1, 2, 5, 6], wide.doors().iter().map(|d| d.room_id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_compact_keeps_themes() {
+        let mut maze = Maze::new(30, 30);
+        maze.generate_seeded((6, 2, 5, 2, 5), 453).unwrap();
+        let themes = [("library".to_string(), 1.0), ("crypt".to_string(), 1.0)];
+        maze.theme_rooms(&themes, 453).unwrap();
+        let before: Vec<(Vec<Coord>, Option<String>)> = maze.rooms().iter()
+            .map(|r| (r.cells().to_vec(), r.theme().map(|t| t.to_string())))
+            .collect();
+
+        let mapping = maze.compact_room_ids();
+        assert_eq!(before.len(), mapping.len());
+        for (old, (cells, theme)) in before.iter().enumerate() {
+            let room = maze.room(mapping[&(old as i32 + 1)]).unwrap();
+            assert_eq!(&cells[..], room.cells());
+            assert_eq!(theme.as_deref(), room.theme());
+        }
+        // New ids follow the first Square of each room
+        let firsts: Vec<Coord> = maze.rooms().iter().map(|r| r.cells()[0]).collect();
+        assert!(firsts.windows(2).all(|w| (w[0].y, w[0].x) < (w[1].y, w[1].x)));
+    }
+
+    #[test]
+    fn test_merge_adjacent_rooms() {
+        // Three rooms side by side, with doors between the last two
+        let mut maze = Maze::new(6, 8);
+        for &(x, id) in [(1, 1), (3, 2), (5, 3)].iter() {
+            maze.carve(x, 2, DIR_EAST, id, false).unwrap();
+            maze.carve(x, 2, DIR_SOUTH, id, false).unwrap();
+            maze.carve(x + 1, 2, DIR_SOUTH, id, false).unwrap();
+            maze.carve(x, 3, DIR_EAST, id, false).unwrap();
+        }
+        maze.num_rooms = 3;
+        maze.theme_rooms(&[("crypt".to_string(), 1.0)], 1).unwrap();
+        maze.carve(4, 2, DIR_EAST, 2, true).unwrap();
+        maze.add_door(Door { coord: Coord::new(4, 2), dir: DIR_EAST, room_id: 2, locked: true }).unwrap();
+        maze.carve(5, 3, constants::DIR_WEST, 3, true).unwrap();
+        maze.add_door(Door { coord: Coord::new(5, 3), dir: constants::DIR_WEST, room_id: 3, locked: false }).unwrap();
+        // Nothing joins room 1 to the others, and doors don't merge by default
+        let unchanged = maze.clone();
+        let mapping = maze.merge_adjacent_rooms();
+        assert!(mapping.iter().all(|(old, new)| old == new));
+        assert_eq!(unchanged, maze);
+
+        // Carving through the wall between rooms 1 and 2 makes one room of them
+        maze.carve(2, 2, DIR_EAST, 1, true).unwrap();
+        let mapping = maze.merge_adjacent_rooms();
+        let expected: BTreeMap<i32, i32> = [(1, 1), (2, 1), (3, 2)].iter().cloned().collect();
+        assert_eq!(expected, mapping);
+        assert_eq!(2, maze.get_num_rooms());
+        let rooms = maze.rooms();
+        assert_eq!(2, rooms.len());
+        assert_eq!(8, rooms[0].area());
+        assert_eq!(4, rooms[1].area());
+        assert_eq!(Some("crypt"), rooms[0].theme());
+        let door_rooms: Vec<i32> = maze.doors().iter().map(|d| d.room_id).collect();
+        assert_eq!(vec![1, 2], door_rooms);
+        assert!(maze.doors()[0].locked);
+        assert!(maze.validate().is_ok());
+
+        // Merging through the doors makes one room of everything
+        let mapping = maze.merge_adjacent_rooms_with(&RoomMergeConfig { merge_through_doors: true });
+        assert_eq!(Some(&1), mapping.get(&2));
+        assert_eq!(1, maze.get_num_rooms());
+        assert_eq!(12, maze.rooms()[0].area());
+        assert!(maze.doors().is_empty());
+    }
+}
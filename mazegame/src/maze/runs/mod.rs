@@ -0,0 +1,81 @@
+//! Perfect mazes without long straight corridors.  The generator keeps
+//! track of how many cells in a row it has carved in the same direction,
+//! and once that reaches the limit it turns instead of going straight on.
+//! A cell with nowhere else to go waits until the rest of the maze is
+//! carved, and only goes straight on if its neighbor still can't be reached
+//! any other way.
+use alloc::string::String;
+use rand::Rng;
+
+use crate::maze::generator;
+use crate::maze::info::GenerationAlgorithm;
+use crate::maze::Maze;
+use crate::rng;
+
+impl Maze {
+    /// Generates a perfect maze from a seed, turning after at most
+    /// max_run_length Squares carved in the same direction.  A
+    /// max_run_length of 0 doesn't limit runs, and gives the same maze as
+    /// generate_perfect_seeded().
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(16, 16);
+    /// maze.generate_perfect_max_run_seeded(3, 42).unwrap();
+    /// assert!(maze.stats().longest_straight <= 4);
+    /// ```
+    pub fn generate_perfect_max_run_seeded(&mut self, max_run_length: u32, seed: u64) -> Result<(), String> {
+        self.generate_perfect_max_run_with_rng(max_run_length, &mut rng::from_seed(seed))?;
+        self.record_generation(seed, GenerationAlgorithm::MaxRun { max_run_length }, None);
+        Ok(())
+    }
+
+    /// Generates a perfect maze with runs of at most max_run_length Squares
+    /// using the supplied random number generator.
+    pub fn generate_perfect_max_run_with_rng<R: Rng>(&mut self, max_run_length: u32, rng: &mut R)
+                                                     -> Result<(), String> {
+        let start = self.get_start();
+        self.measure_generation(|maze| {
+            let stats = generator::growing_tree_max_run(maze, start, rng, max_run_length)?;
+            gen_debug!("growing_tree x={} y={} carved={} backtracks={} max_run={}",
+                       start.x, start.y, stats.carved, stats.backtracks, max_run_length);
+            Ok(stats)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::test_mazes::assert_perfect;
+
+    #[test]
+    fn test_no_long_corridors() {
+        let mut maze = Maze::new(60, 60);
+        maze.generate_perfect_max_run_seeded(3, 451).unwrap();
+        assert_perfect(&maze);
+        // Three cells carved in a row make a corridor four Squares long
+        assert!(maze.stats().longest_straight <= 4, "{}", maze.stats().longest_straight);
+
+        // Without the limit, the same seed has much longer ones
+        let mut plain = Maze::new(60, 60);
+        plain.generate_perfect_seeded(451).unwrap();
+        assert!(plain.stats().longest_straight > 6);
+        assert_perfect(&plain);
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let mut a = Maze::new(20, 20);
+        let mut b = Maze::new(20, 20);
+        a.generate_perfect_max_run_seeded(0, 7).unwrap();
+        b.generate_perfect_seeded(7).unwrap();
+        assert_eq!(a, b);
+
+        // A limit of one turns at every Square
+        let mut zigzag = Maze::new(20, 20);
+        zigzag.generate_perfect_max_run_seeded(1, 7).unwrap();
+        assert_perfect(&zigzag);
+        assert_eq!(2, zigzag.stats().longest_straight);
+    }
+}
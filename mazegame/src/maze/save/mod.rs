@@ -0,0 +1,2038 @@
+//! Saving and loading Mazes in text, JSON and binary formats.
+//!
+//! The text format is a small line-based header followed by one line per row
+//! of Squares.  Each Square is written as `<open mask>:<id>`, where the open
+//! mask is `Square::open_mask()` in hex, plus `Square::under` shifted up four
+//! bits for weave crossings.  Squares with one-way or secret passages add a
+//! third `:<passage mask>` field, `Square::one_way` plus `Square::secret`
+//! shifted up four bits, in hex:
+//!
+//! ```text
+//! mazegame 1
+//! size 3 2
+//! rooms 0
+//! entrance 0 0 0
+//! exit 2 1 1
+//! cells
+//! 5:-1 c:-1 a:-1
+//! 4:-1 c:-1 b:-1
+//! ```
+//!
+//! Mazes with corridor regions from partition_corridors() add a
+//! `corridor_regions` line after the cells, followed by one line per row of
+//! each Square's region, or `-` for a Square in none.  Mazes with
+//! elevations add an `elevations` line after that, followed by one line per
+//! row of each Square's height as a signed decimal number.
+//!
+//! Mazes that wrap around add a `topology <x> <y>` line after `rooms`, with
+//! each axis written as `bounded` or `torus`.  A start Square chosen with
+//! set_start() adds a `start <x> <y>` line before the entrance.  Mazes with
+//! several exits have an `exit` line for each, in order.  Each recorded door
+//! adds a `door <x> <y> <dir> <room id> <locked>` line after the exits, with
+//! locked written as 0 or 1.  Each room with a theme adds a `theme <room id>
+//! <name>` line after the doors, the name taking up the rest of the line.
+//!
+//! to_text_canonical() writes a canonical form of the text format, meant for
+//! keeping levels in version control.  The `rooms` line is left out when
+//! there are none, the doors are sorted by row, column and direction, and
+//! every cell is written at a fixed width as `<open mask>:<id>:<passage
+//! mask>`, with both masks as two hex digits and the id signed and padded
+//! to four digits, so the columns line up and changing a wall changes only
+//! the rows it's in.  A `checksum` line after the header holds the 64-bit
+//! FNV-1a hash of everything after that line, as 16 hex digits:
+//!
+//! ```text
+//! mazegame 1
+//! checksum be7ef0f9f1c52f12
+//! size 2 1
+//! cells
+//! 04:-0001:00 08:-0001:00
+//! ```
+//!
+//! to_json_canonical() does the same for JSON: an object with sorted keys,
+//! a `format` key holding the text header, `size` as [cols, rows], the
+//! cells (and any corridor regions and elevations) as one string per row
+//! like the text form, sorted doors and a `checksum`, the hash of the
+//! pretty-printed object without it.  Sections that would be empty are
+//! left out.
+//! from_text() and from_json() read both forms, and check the checksum
+//! when there is one.
+//!
+//! The binary format is little-endian: the magic bytes `MZGM`, a version byte,
+//! cols, rows and room count as u32s, a flags byte (bit 0 set when an entrance
+//! follows, bit 1 when an exit follows, bits 2 and 3 when the x and y axes
+//! wrap around, bit 4 when more exits follow, bit 5 when doors follow, bit 6
+//! when a start Square follows, bit 7 when elevations follow), each opening as x and y u32s plus a
+//! direction byte, with any exits after the first as a u32 count and then
+//! their openings, any doors as a u32 count and then x and y u32s, a
+//! direction byte, the room id as an i32 and a locked byte for each, and any
+//! start Square as x and y u32s, then each Square in row-major order as a
+//! mask byte (in the same format as the text one), an i32 id and a passage
+//! mask byte (also as in the text format), then the room themes as a u32
+//! count followed by each theme as a u32 length and its UTF-8 bytes, and
+//! then any elevations as an i8 for each Square in row-major order, and
+//! then the corridor regions as a u32 count, either 0 or the number of
+//! Squares, followed by a u32 for each Square in row-major order holding
+//! its region plus one, or 0 for none.  Last comes the CRC-32 of everything before it, as a u32.  from_bytes()
+//! checks it, and checks every count and length against the bytes left
+//! before allocating anything, so a damaged file fails with
+//! MazeError::Corrupted instead of loading wrong.
+//!
+//! Files written by older versions are brought up to date by migrate(),
+//! which from_bytes() calls first.  Each version's changes are undone by
+//! one migration function, run in turn:
+//!
+//! ```text
+//! version 1  no one-way mask bytes
+//! version 2  one-way mask bytes, but no room themes
+//! version 3  no secret passages
+//! version 4  no CRC-32
+//! version 5  a single exit
+//! version 6  no doors
+//! version 7  no start Square
+//! version 8  no elevations
+//! version 9  no corridor regions
+//! version 10 the current format
+//! ```
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "serde")]
+use serde_json::Value as JsonValue;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::fingerprint::{crc32, fnv1a};
+use crate::maze::room::Door;
+use crate::maze::{Coord, Maze, Opening, Square, Topology};
+
+const TEXT_HEADER: &str = "mazegame 1";
+pub(crate) const BINARY_MAGIC: &[u8] = b"MZGM";
+pub(crate) const BINARY_VERSION: u8 = 10;
+
+// Where the version byte, and the cols and rows after it, sit in a file
+const VERSION_AT: usize = 4;
+const SIZE_AT: usize = 5;
+
+/// The bytes each Square takes in a binary maze file.
+pub(crate) const CELL_BYTES: usize = 6;
+
+/// A JSON object, as canonical JSON is built and read.
+#[cfg(feature = "serde")]
+type JsonMap = serde_json::Map<String, JsonValue>;
+
+/// Turns a file in one binary format version into the next version up.
+type Migration = fn(&[u8]) -> Result<Vec<u8>, MazeError>;
+
+// The migration from each old version, in order: the first entry upgrades
+// version 1 files to version 2
+const MIGRATIONS: [Migration; 9] = [migrate_v1_to_v2, migrate_v2_to_v3, migrate_v3_to_v4, migrate_v4_to_v5,
+                                    migrate_v5_to_v6, migrate_v6_to_v7, migrate_v7_to_v8, migrate_v8_to_v9,
+                                    migrate_v9_to_v10];
+
+/// The on-disk formats a Maze can be stored in.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Binary,
+}
+
+#[cfg(feature = "std")]
+impl Format {
+    /// Picks a format based on a file's extension: `.txt`/`.maze` for text,
+    /// `.json` for JSON and `.bin`/`.mzb` for binary.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::save::Format;
+    ///
+    /// assert_eq!(Ok(Format::Json), Format::from_path("level1.json"));
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Format, MazeError> {
+        let ext = path.as_ref().extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        match ext.as_deref() {
+            Some("txt") | Some("maze") => Ok(Format::Text),
+            Some("json") => Ok(Format::Json),
+            Some("bin") | Some("mzb") => Ok(Format::Binary),
+            _ => Err(MazeError::InvalidArgument(
+                format!("can't tell the maze format of '{}' from its extension", path.as_ref().display()))),
+        }
+    }
+}
+
+impl Maze {
+    /// Loads a Maze from a file, picking the format from the file's extension.
+    #[cfg(feature = "std")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Maze, MazeError> {
+        let format = Format::from_path(&path)?;
+        let mut data = Vec::new();
+        File::open(&path)?.read_to_end(&mut data)?;
+        match format {
+            Format::Text => {
+                let text = String::from_utf8(data)
+                    .map_err(|_| MazeError::Parse("text maze isn't valid UTF-8".to_string()))?;
+                Maze::from_text(&text)
+            }
+            Format::Json => {
+                let text = String::from_utf8(data)
+                    .map_err(|_| MazeError::Parse("JSON maze isn't valid UTF-8".to_string()))?;
+                Maze::from_json(&text)
+            }
+            Format::Binary => Maze::from_bytes(&data),
+        }
+    }
+
+    /// Saves a Maze to a file, picking the format from the file's extension.
+    #[cfg(feature = "std")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), MazeError> {
+        let data = match Format::from_path(&path)? {
+            Format::Text => self.to_text().into_bytes(),
+            Format::Json => self.to_json()?.into_bytes(),
+            Format::Binary => self.to_bytes(),
+        };
+        File::create(&path)?.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Saves a Maze like save(), writing text and JSON in their canonical
+    /// forms.
+    #[cfg(feature = "std")]
+    pub fn save_canonical<P: AsRef<Path>>(&self, path: P) -> Result<(), MazeError> {
+        let data = match Format::from_path(&path)? {
+            Format::Text => self.to_text_canonical().into_bytes(),
+            Format::Json => self.to_json_canonical()?.into_bytes(),
+            Format::Binary => self.to_bytes(),
+        };
+        File::create(&path)?.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Writes the Maze in the text format.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::Maze::new(1, 2);
+    /// assert_eq!("mazegame 1\nsize 2 1\nrooms 0\ncells\n0:0 0:0\n", maze.to_text());
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        out.push_str(TEXT_HEADER);
+        out.push('\n');
+        out.push_str(&format!("size {} {}\n", self.cols, self.rows));
+        out.push_str(&format!("rooms {}\n", self.num_rooms));
+        if self.get_topology() != (Topology::Bounded, Topology::Bounded) {
+            out.push_str(&format!("topology {} {}\n", topology_name(self.x_topology),
+                                  topology_name(self.y_topology)));
+        }
+        if let Some(c) = self.start {
+            out.push_str(&format!("start {} {}\n", c.x, c.y));
+        }
+        if let Some(o) = self.entrance {
+            out.push_str(&format!("entrance {} {} {}\n", o.coord.x, o.coord.y, o.dir));
+        }
+        for o in self.exits.iter() {
+            out.push_str(&format!("exit {} {} {}\n", o.coord.x, o.coord.y, o.dir));
+        }
+        for d in self.doors.iter() {
+            out.push_str(&format!("door {} {} {} {} {}\n", d.coord.x, d.coord.y, d.dir, d.room_id, d.locked as u8));
+        }
+        self.push_theme_lines(&mut out);
+        out.push_str("cells\n");
+        for y in 0..self.rows {
+            let row: Vec<String> = (0..self.cols)
+                .map(|x| {
+                    let sq = &self.sq[self.get_offset(x, y)];
+                    if passage_mask(sq) != 0 {
+                        format!("{:x}:{}:{:x}", cell_mask(sq), sq.id, passage_mask(sq))
+                    } else {
+                        format!("{:x}:{}", cell_mask(sq), sq.id)
+                    }
+                })
+                .collect();
+            out.push_str(&row.join(" "));
+            out.push('\n');
+        }
+        self.push_region_rows(&mut out);
+        self.push_elevation_rows(&mut out);
+        out
+    }
+
+    /// Reads a Maze written in the text format.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::Maze::new(4, 3);
+    /// let copy = mazegame::Maze::from_text(&maze.to_text()).unwrap();
+    /// assert_eq!(maze, copy);
+    /// ```
+    pub fn from_text(text: &str) -> Result<Maze, MazeError> {
+        let mut lines = text.lines().enumerate().filter(|&(_, l)| !l.trim().is_empty());
+
+        match lines.next() {
+            Some((_, l)) if l.trim() == TEXT_HEADER => {}
+            _ => return Err(MazeError::Parse(format!("missing '{}' header", TEXT_HEADER))),
+        }
+
+        let mut size = None;
+        let mut rooms = 0;
+        let mut topology = (Topology::Bounded, Topology::Bounded);
+        let mut start = None;
+        let mut entrance = None;
+        let mut exits = Vec::new();
+        let mut doors = Vec::new();
+        let mut themes = Vec::new();
+        let mut checksum = None;
+        for (num, line) in lines.by_ref() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[0] {
+                "size" => {
+                    let v = parse_fields(&fields, 2, num)?;
+                    size = Some((v[0], v[1]));
+                }
+                "rooms" => rooms = parse_fields(&fields, 1, num)?[0],
+                "topology" => topology = parse_topology(&fields, num)?,
+                "start" => {
+                    let v = parse_fields(&fields, 2, num)?;
+                    start = Some(Coord::new(v[0], v[1]));
+                }
+                "entrance" => entrance = Some(parse_opening(&fields, num)?),
+                "exit" => exits.push(parse_opening(&fields, num)?),
+                "door" => doors.push(parse_door(&fields, num)?),
+                "theme" => themes.push(parse_theme(line, num)?),
+                "checksum" => checksum = Some(parse_checksum(&fields, num)?),
+                "cells" => break,
+                other => {
+                    return Err(MazeError::Parse(format!("line {}: unknown key '{}'", num + 1, other)));
+                }
+            }
+        }
+
+        let (cols, rows) = size.ok_or_else(|| MazeError::Parse("missing 'size' line".to_string()))?;
+
+        // Check the shape of the cell data before allocating the Maze for it
+        let mut cell_lines: Vec<(usize, &str)> = lines.collect();
+        let elevation_lines = cell_lines.iter().position(|&(_, l)| l.trim() == "elevations")
+            .map(|at| cell_lines.split_off(at)[1..].to_vec());
+        let region_lines = cell_lines.iter().position(|&(_, l)| l.trim() == "corridor_regions")
+            .map(|at| cell_lines.split_off(at)[1..].to_vec());
+        if cell_lines.len() != rows as usize {
+            return Err(MazeError::Parse(format!("expected {} rows of cells, found {}", rows, cell_lines.len())));
+        }
+        let mut parsed = Vec::with_capacity(cell_lines.len());
+        for &(num, line) in &cell_lines {
+            let cells: Vec<&str> = line.split_whitespace().collect();
+            if cells.len() != cols as usize {
+                return Err(MazeError::Parse(
+                    format!("line {}: expected {} cells, found {}", num + 1, cols, cells.len())));
+            }
+            for cell in cells {
+                let sq = parse_cell(cell)
+                    .ok_or_else(|| MazeError::Parse(format!("line {}: bad cell '{}'", num + 1, cell)))?;
+                parsed.push(sq);
+            }
+        }
+        let mut regions = Vec::new();
+        if let Some(region_lines) = region_lines {
+            if region_lines.len() != rows as usize {
+                return Err(MazeError::Parse(format!("expected {} rows of corridor regions, found {}", rows,
+                                                    region_lines.len())));
+            }
+            for &(num, line) in &region_lines {
+                let row: Vec<&str> = line.split_whitespace().collect();
+                if row.len() != cols as usize {
+                    return Err(MazeError::Parse(
+                        format!("line {}: expected {} corridor regions, found {}", num + 1, cols, row.len())));
+                }
+                for r in row {
+                    regions.push(parse_region(r)
+                        .ok_or_else(|| MazeError::Parse(format!("line {}: bad region '{}'", num + 1, r)))?);
+                }
+            }
+        }
+        let mut elevations = Vec::new();
+        if let Some(elevation_lines) = elevation_lines {
+            if elevation_lines.len() != rows as usize {
+                return Err(MazeError::Parse(format!("expected {} rows of elevations, found {}", rows,
+                                                    elevation_lines.len())));
+            }
+            for &(num, line) in &elevation_lines {
+                let row: Vec<&str> = line.split_whitespace().collect();
+                if row.len() != cols as usize {
+                    return Err(MazeError::Parse(
+                        format!("line {}: expected {} elevations, found {}", num + 1, cols, row.len())));
+                }
+                for e in row {
+                    elevations.push(e.parse()
+                        .map_err(|_| MazeError::Parse(format!("line {}: bad elevation '{}'", num + 1, e)))?);
+                }
+            }
+        }
+
+        let mut maze = Maze::new(rows, cols);
+        maze.num_rooms = rooms;
+        maze.set_topology(topology.0, topology.1);
+        maze.start = start;
+        maze.entrance = entrance;
+        maze.exits = exits;
+        maze.doors = doors;
+        maze.sort_doors();
+        if !themes.is_empty() {
+            maze.room_themes = vec![String::new(); rooms as usize];
+            for (room_id, theme) in themes {
+                let slot = maze.room_themes.get_mut(room_id as usize - 1)
+                    .ok_or_else(|| MazeError::Parse(format!("a theme for room {} of {}", room_id, rooms)))?;
+                *slot = theme;
+            }
+        }
+        maze.sq = parsed;
+        maze.corridor_regions = regions;
+        maze.elevations = elevations;
+        maze.check_loaded()?;
+        if checksum.is_some_and(|c| c != fnv1a(maze.canonical_text_body().as_bytes())) {
+            return Err(MazeError::Parse("checksum doesn't match the maze".to_string()));
+        }
+        Ok(maze)
+    }
+
+    /// Writes the Maze in the canonical form of the text format, described
+    /// in the module docs.  from_text() reads it back.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(1, 2);
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// let text = maze.to_text_canonical();
+    /// assert_eq!("mazegame 1\nchecksum be7ef0f9f1c52f12\nsize 2 1\ncells\n04:-0001:00 08:-0001:00\n", text);
+    /// assert_eq!(text, Maze::from_text(&text).unwrap().to_text_canonical());
+    /// ```
+    pub fn to_text_canonical(&self) -> String {
+        let body = self.canonical_text_body();
+        format!("{}\nchecksum {:016x}\n{}", TEXT_HEADER, fnv1a(body.as_bytes()), body)
+    }
+
+    //
+    // Internal - the canonical text form after the checksum line.
+    //
+    fn canonical_text_body(&self) -> String {
+        let mut out = format!("size {} {}\n", self.cols, self.rows);
+        if self.num_rooms > 0 {
+            out.push_str(&format!("rooms {}\n", self.num_rooms));
+        }
+        if self.get_topology() != (Topology::Bounded, Topology::Bounded) {
+            out.push_str(&format!("topology {} {}\n", topology_name(self.x_topology),
+                                  topology_name(self.y_topology)));
+        }
+        if let Some(c) = self.start {
+            out.push_str(&format!("start {} {}\n", c.x, c.y));
+        }
+        if let Some(o) = self.entrance {
+            out.push_str(&format!("entrance {} {} {}\n", o.coord.x, o.coord.y, o.dir));
+        }
+        for o in self.exits.iter() {
+            out.push_str(&format!("exit {} {} {}\n", o.coord.x, o.coord.y, o.dir));
+        }
+        for d in self.sorted_doors() {
+            out.push_str(&format!("door {} {} {} {} {}\n", d.coord.x, d.coord.y, d.dir, d.room_id, d.locked as u8));
+        }
+        self.push_theme_lines(&mut out);
+        out.push_str("cells\n");
+        for row in self.canonical_rows() {
+            out.push_str(&row);
+            out.push('\n');
+        }
+        self.push_region_rows(&mut out);
+        self.push_elevation_rows(&mut out);
+        out
+    }
+
+    //
+    // Internal - each row of Squares as fixed-width canonical cells.
+    //
+    fn canonical_rows(&self) -> Vec<String> {
+        (0..self.rows)
+            .map(|y| {
+                let row: Vec<String> = (0..self.cols)
+                    .map(|x| {
+                        let sq = &self.sq[self.get_offset(x, y)];
+                        format!("{:02x}:{:+05}:{:02x}", cell_mask(sq), sq.id, passage_mask(sq))
+                    })
+                    .collect();
+                row.join(" ")
+            })
+            .collect()
+    }
+
+    //
+    // Internal - appends a 'theme' line for each room with a theme.
+    //
+    fn push_theme_lines(&self, out: &mut String) {
+        for (i, theme) in self.room_themes.iter().enumerate().filter(|(_, t)| !t.is_empty()) {
+            out.push_str(&format!("theme {} {}\n", i + 1, theme));
+        }
+    }
+
+    //
+    // Internal - each row of corridor regions, as numbers or '-' separated
+    // by spaces.
+    //
+    fn region_rows(&self) -> Vec<String> {
+        self.corridor_regions.chunks(self.cols.max(1) as usize)
+            .map(|row| row.iter().map(|r| r.map_or("-".to_string(), |r| r.to_string()))
+                          .collect::<Vec<String>>().join(" "))
+            .collect()
+    }
+
+    //
+    // Internal - appends the text format's corridor regions section, if the
+    // Maze has been split into them.
+    //
+    fn push_region_rows(&self, out: &mut String) {
+        if !self.corridor_regions.is_empty() {
+            out.push_str("corridor_regions\n");
+            for row in self.region_rows() {
+                out.push_str(&row);
+                out.push('\n');
+            }
+        }
+    }
+
+    //
+    // Internal - each row of elevations, as numbers separated by spaces.
+    //
+    fn elevation_rows(&self) -> Vec<String> {
+        self.elevations.chunks(self.cols.max(1) as usize)
+            .map(|row| row.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(" "))
+            .collect()
+    }
+
+    //
+    // Internal - appends the text format's elevations section, if the Maze
+    // has elevations.
+    //
+    fn push_elevation_rows(&self, out: &mut String) {
+        if !self.elevations.is_empty() {
+            out.push_str("elevations\n");
+            for row in self.elevation_rows() {
+                out.push_str(&row);
+                out.push('\n');
+            }
+        }
+    }
+
+    //
+    // Internal - the doors sorted by row, column and direction.
+    //
+    fn sorted_doors(&self) -> Vec<Door> {
+        let mut doors = self.doors.clone();
+        doors.sort_by_key(Door::order);
+        doors
+    }
+
+    /// Writes the Maze as JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, MazeError> {
+        ::serde_json::to_string(self).map_err(|e| MazeError::Parse(e.to_string()))
+    }
+
+    /// Writes the Maze as JSON.  Always fails, since JSON support wasn't compiled in.
+    #[cfg(not(feature = "serde"))]
+    pub fn to_json(&self) -> Result<String, MazeError> {
+        Err(MazeError::InvalidArgument("JSON support requires the 'serde' feature".to_string()))
+    }
+
+    /// Reads a Maze written as JSON.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(5, 5);
+    /// maze.generate_perfect().unwrap();
+    /// let copy = mazegame::Maze::from_json(&maze.to_json().unwrap()).unwrap();
+    /// assert_eq!(maze, copy);
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<Maze, MazeError> {
+        let value: JsonValue = ::serde_json::from_str(text).map_err(|e| MazeError::Parse(e.to_string()))?;
+        let maze = match value {
+            JsonValue::Object(map) if map.contains_key("format") => Maze::from_json_canonical(map)?,
+            value => ::serde_json::from_value(value).map_err(|e| MazeError::Parse(e.to_string()))?,
+        };
+        maze.check_loaded()?;
+        Ok(maze)
+    }
+
+    /// Writes the Maze as canonical JSON, described in the module docs.
+    /// from_json() reads it back.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(6, 6);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let json = maze.to_json_canonical().unwrap();
+    /// let copy = mazegame::Maze::from_json(&json).unwrap();
+    /// assert_eq!(maze, copy);
+    /// assert_eq!(json, copy.to_json_canonical().unwrap());
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn to_json_canonical(&self) -> Result<String, MazeError> {
+        let mut map = self.canonical_json_map()?;
+        let checksum = fnv1a(to_json_pretty(&map)?.as_bytes());
+        map.insert("checksum".to_string(), JsonValue::from(format!("{:016x}", checksum)));
+        Ok(to_json_pretty(&map)? + "\n")
+    }
+
+    /// Writes the Maze as canonical JSON.  Always fails, since JSON support
+    /// wasn't compiled in.
+    #[cfg(not(feature = "serde"))]
+    pub fn to_json_canonical(&self) -> Result<String, MazeError> {
+        Err(MazeError::InvalidArgument("JSON support requires the 'serde' feature".to_string()))
+    }
+
+    //
+    // Internal - the canonical JSON object, without its checksum.
+    //
+    #[cfg(feature = "serde")]
+    fn canonical_json_map(&self) -> Result<JsonMap, MazeError> {
+        let mut map = JsonMap::new();
+        map.insert("format".to_string(), JsonValue::from(TEXT_HEADER));
+        map.insert("size".to_string(), JsonValue::from(vec![self.cols, self.rows]));
+        map.insert("cells".to_string(), JsonValue::from(self.canonical_rows()));
+        if self.num_rooms > 0 {
+            map.insert("rooms".to_string(), JsonValue::from(self.num_rooms));
+        }
+        if self.get_topology() != (Topology::Bounded, Topology::Bounded) {
+            map.insert("topology".to_string(),
+                       JsonValue::from(vec![topology_name(self.x_topology), topology_name(self.y_topology)]));
+        }
+        if let Some(c) = self.start {
+            map.insert("start".to_string(), to_json_value(&c)?);
+        }
+        if let Some(o) = self.entrance {
+            map.insert("entrance".to_string(), to_json_value(&o)?);
+        }
+        if !self.exits.is_empty() {
+            map.insert("exits".to_string(), to_json_value(&self.exits)?);
+        }
+        if !self.doors.is_empty() {
+            map.insert("doors".to_string(), to_json_value(&self.sorted_doors())?);
+        }
+        if !self.room_themes.is_empty() {
+            map.insert("room_themes".to_string(), to_json_value(&self.room_themes)?);
+        }
+        if !self.corridor_regions.is_empty() {
+            map.insert("corridor_regions".to_string(), JsonValue::from(self.region_rows()));
+        }
+        if !self.elevations.is_empty() {
+            map.insert("elevations".to_string(), JsonValue::from(self.elevation_rows()));
+        }
+        Ok(map)
+    }
+
+    //
+    // Internal - reads a canonical JSON object, checking its checksum if it
+    // has one.
+    //
+    #[cfg(feature = "serde")]
+    fn from_json_canonical(mut map: JsonMap) -> Result<Maze, MazeError> {
+        if map.get("format").and_then(|f| f.as_str()) != Some(TEXT_HEADER) {
+            return Err(MazeError::Parse(format!("canonical JSON must have a 'format' of '{}'", TEXT_HEADER)));
+        }
+        let checksum = match map.remove("checksum") {
+            Some(c) => Some(c.as_str().and_then(|c| u64::from_str_radix(c, 16).ok())
+                .ok_or_else(|| MazeError::Parse(format!("bad checksum {}", c)))?),
+            None => None,
+        };
+        let size: Vec<u32> = json_field(&map, "size")?.ok_or_else(|| MazeError::Parse("missing 'size'".to_string()))?;
+        let (cols, rows) = match size[..] {
+            [cols, rows] => (cols, rows),
+            _ => return Err(MazeError::Parse("'size' must be [cols, rows]".to_string())),
+        };
+
+        let cells: Vec<String> = json_field(&map, "cells")?.unwrap_or_default();
+        if cells.len() != rows as usize {
+            return Err(MazeError::Parse(format!("expected {} rows of cells, found {}", rows, cells.len())));
+        }
+        let mut parsed = Vec::with_capacity(cells.len());
+        for (y, row) in cells.iter().enumerate() {
+            let row: Vec<&str> = row.split_whitespace().collect();
+            if row.len() != cols as usize {
+                return Err(MazeError::Parse(format!("row {}: expected {} cells, found {}", y, cols, row.len())));
+            }
+            for cell in row {
+                parsed.push(parse_cell(cell)
+                    .ok_or_else(|| MazeError::Parse(format!("row {}: bad cell '{}'", y, cell)))?);
+            }
+        }
+
+        let mut regions = Vec::new();
+        let region_rows: Vec<String> = json_field(&map, "corridor_regions")?.unwrap_or_default();
+        for (y, row) in region_rows.iter().enumerate() {
+            for r in row.split_whitespace() {
+                regions.push(parse_region(r)
+                    .ok_or_else(|| MazeError::Parse(format!("row {}: bad region '{}'", y, r)))?);
+            }
+        }
+        if !regions.is_empty() && regions.len() != parsed.len() {
+            return Err(MazeError::Parse(format!("expected {} corridor regions, found {}", parsed.len(),
+                                                regions.len())));
+        }
+
+        let mut elevations = Vec::new();
+        let elevation_rows: Vec<String> = json_field(&map, "elevations")?.unwrap_or_default();
+        for (y, row) in elevation_rows.iter().enumerate() {
+            for e in row.split_whitespace() {
+                elevations.push(e.parse().map_err(|_| MazeError::Parse(format!("row {}: bad elevation '{}'", y, e)))?);
+            }
+        }
+        if !elevations.is_empty() && elevations.len() != parsed.len() {
+            return Err(MazeError::Parse(format!("expected {} elevations, found {}", parsed.len(), elevations.len())));
+        }
+
+        let topology: Vec<String> = json_field(&map, "topology")?.unwrap_or_default();
+        let mut maze = Maze::new(rows, cols);
+        if !topology.is_empty() {
+            let bad = || MazeError::Parse(format!("bad topology {:?}", topology));
+            match topology[..] {
+                [ref x, ref y] => maze.set_topology(topology_from_name(x).ok_or_else(bad)?,
+                                                    topology_from_name(y).ok_or_else(bad)?),
+                _ => return Err(bad()),
+            }
+        }
+        maze.num_rooms = json_field(&map, "rooms")?.unwrap_or(0);
+        maze.start = json_field(&map, "start")?;
+        maze.entrance = json_field(&map, "entrance")?;
+        maze.exits = json_field(&map, "exits")?.unwrap_or_default();
+        maze.doors = json_field(&map, "doors")?.unwrap_or_default();
+        maze.sort_doors();
+        maze.room_themes = json_field(&map, "room_themes")?.unwrap_or_default();
+        maze.corridor_regions = regions;
+        maze.elevations = elevations;
+        maze.sq = parsed;
+        maze.check_loaded()?;
+        if let Some(checksum) = checksum {
+            if checksum != fnv1a(to_json_pretty(&maze.canonical_json_map()?)?.as_bytes()) {
+                return Err(MazeError::Parse("checksum doesn't match the maze".to_string()));
+            }
+        }
+        Ok(maze)
+    }
+
+    /// Reads a Maze written as JSON.  Always fails, since JSON support wasn't compiled in.
+    #[cfg(not(feature = "serde"))]
+    pub fn from_json(_text: &str) -> Result<Maze, MazeError> {
+        Err(MazeError::InvalidArgument("JSON support requires the 'serde' feature".to_string()))
+    }
+
+    /// Writes the Maze in the binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(26 + self.sq.len() * 6);
+        out.extend_from_slice(BINARY_MAGIC);
+        out.push(BINARY_VERSION);
+        out.extend_from_slice(&self.cols.to_le_bytes());
+        out.extend_from_slice(&self.rows.to_le_bytes());
+        out.extend_from_slice(&self.num_rooms.to_le_bytes());
+
+        let mut flags = 0;
+        if self.entrance.is_some() {
+            flags |= 1;
+        }
+        if !self.exits.is_empty() {
+            flags |= 2;
+        }
+        if self.x_topology == Topology::Torus {
+            flags |= 4;
+        }
+        if self.y_topology == Topology::Torus {
+            flags |= 8;
+        }
+        if self.exits.len() > 1 {
+            flags |= 16;
+        }
+        if !self.doors.is_empty() {
+            flags |= 32;
+        }
+        if self.start.is_some() {
+            flags |= 64;
+        }
+        if !self.elevations.is_empty() {
+            flags |= 128;
+        }
+        out.push(flags);
+        let write_opening = |out: &mut Vec<u8>, o: &Opening| {
+            out.extend_from_slice(&o.coord.x.to_le_bytes());
+            out.extend_from_slice(&o.coord.y.to_le_bytes());
+            out.push(o.dir as u8);
+        };
+        for o in self.entrance.iter().chain(self.exits.first()) {
+            write_opening(&mut out, o);
+        }
+        if self.exits.len() > 1 {
+            out.extend_from_slice(&(self.exits.len() as u32 - 1).to_le_bytes());
+            for o in self.exits[1..].iter() {
+                write_opening(&mut out, o);
+            }
+        }
+        if !self.doors.is_empty() {
+            out.extend_from_slice(&(self.doors.len() as u32).to_le_bytes());
+            for d in self.doors.iter() {
+                write_opening(&mut out, &Opening { coord: d.coord, dir: d.dir });
+                out.extend_from_slice(&d.room_id.to_le_bytes());
+                out.push(d.locked as u8);
+            }
+        }
+        if let Some(c) = self.start {
+            out.extend_from_slice(&c.x.to_le_bytes());
+            out.extend_from_slice(&c.y.to_le_bytes());
+        }
+
+        for sq in &self.sq {
+            out.push(cell_mask(sq));
+            out.extend_from_slice(&sq.id.to_le_bytes());
+            out.push(passage_mask(sq));
+        }
+        out.extend_from_slice(&(self.room_themes.len() as u32).to_le_bytes());
+        for theme in self.room_themes.iter() {
+            out.extend_from_slice(&(theme.len() as u32).to_le_bytes());
+            out.extend_from_slice(theme.as_bytes());
+        }
+        out.extend(self.elevations.iter().map(|&e| e as u8));
+        out.extend_from_slice(&(self.corridor_regions.len() as u32).to_le_bytes());
+        for r in self.corridor_regions.iter() {
+            out.extend_from_slice(&r.map_or(0, |r| r + 1).to_le_bytes());
+        }
+        let crc = crc32(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// Reads a Maze written in the binary format.  Fails with
+    /// MazeError::Corrupted if the data doesn't match its CRC-32, or holds
+    /// a count or length that runs past its end.  For files from older
+    /// versions, the offset is into the data as migrate() brings it up to
+    /// date.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(5, 5);
+    /// maze.generate_perfect().unwrap();
+    /// let copy = mazegame::Maze::from_bytes(&maze.to_bytes()).unwrap();
+    /// assert_eq!(maze, copy);
+    /// ```
+    pub fn from_bytes(data: &[u8]) -> Result<Maze, MazeError> {
+        let data = migrate(data)?;
+        let body = check_crc(&data)?;
+        let header = read_header(body)?;
+        let mut maze = Maze::new(header.rows, header.cols);
+        maze.num_rooms = header.num_rooms;
+        maze.entrance = header.entrance;
+        maze.exits = header.exits;
+        maze.doors = header.doors;
+        maze.start = header.start;
+        maze.set_topology(header.x_topology, header.y_topology);
+        let cells_end = header.cells_at + maze.sq.len() * CELL_BYTES;
+        for (sq, bytes) in maze.sq.iter_mut().zip(body[header.cells_at..cells_end].chunks(CELL_BYTES)) {
+            *sq = read_cell(bytes);
+        }
+
+        let mut reader = ByteReader { data: body, pos: cells_end };
+        // Each theme takes at least its four byte length
+        let at = reader.pos;
+        let themes = reader.count("room theme count", 4)?;
+        if themes > maze.num_rooms as usize {
+            return Err(MazeError::Corrupted { what: format!("{} room themes for {} rooms", themes, maze.num_rooms),
+                                              offset: at });
+        }
+        for _ in 0..themes {
+            let len = reader.count("room theme length", 1)?;
+            let theme = core::str::from_utf8(reader.take(len)?)
+                .map_err(|_| MazeError::Parse("room theme isn't valid UTF-8".to_string()))?;
+            maze.room_themes.push(theme.to_string());
+        }
+        if header.elevations {
+            maze.elevations = reader.take(maze.sq.len())?.iter().map(|&e| e as i8).collect();
+        }
+        let at = reader.pos;
+        let regions = reader.count("corridor region count", 4)?;
+        if regions != 0 && regions != maze.sq.len() {
+            return Err(MazeError::Corrupted { what: format!("{} corridor regions for {} Squares", regions,
+                                                            maze.sq.len()),
+                                              offset: at });
+        }
+        for _ in 0..regions {
+            maze.corridor_regions.push(reader.u32()?.checked_sub(1));
+        }
+        if reader.remaining() != 0 {
+            return Err(MazeError::Corrupted { what: format!("{} unexpected bytes at the end",
+                                                            reader.remaining()),
+                                              offset: reader.pos });
+        }
+        maze.check_loaded()?;
+        Ok(maze)
+    }
+
+    //
+    // Internal - sanity checks a freshly loaded Maze so the rest of the code
+    // can rely on its invariants.
+    //
+    fn check_loaded(&self) -> Result<(), MazeError> {
+        if self.sq.len() != (self.rows as usize) * (self.cols as usize) {
+            return Err(MazeError::Parse(format!("expected {} squares for a {}x{} maze, found {}",
+                                                self.rows * self.cols, self.cols, self.rows, self.sq.len())));
+        }
+        if let Some(c) = self.start.filter(|c| !self.in_bounds(c.x, c.y)) {
+            return Err(MazeError::OutOfBounds(c.x, c.y));
+        }
+        for o in self.entrance.iter().chain(self.exits.iter()) {
+            if !self.in_bounds(o.coord.x, o.coord.y) {
+                return Err(MazeError::OutOfBounds(o.coord.x, o.coord.y));
+            }
+            if o.dir >= constants::NUM_DIRECTIONS {
+                return Err(MazeError::Parse(format!("illegal direction {}", o.dir)));
+            }
+        }
+        for d in self.doors.iter() {
+            if !self.in_bounds(d.coord.x, d.coord.y) {
+                return Err(MazeError::OutOfBounds(d.coord.x, d.coord.y));
+            }
+            if d.dir >= constants::NUM_DIRECTIONS {
+                return Err(MazeError::Parse(format!("illegal direction {}", d.dir)));
+            }
+            let sq = &self.sq[self.get_offset(d.coord.x, d.coord.y)];
+            if d.room_id < 1 || d.room_id as u32 > self.num_rooms || !sq.is_part_of_room() || sq.id != d.room_id {
+                return Err(MazeError::Parse(format!("the door at ({}, {}) isn't in room {}",
+                                                    d.coord.x, d.coord.y, d.room_id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Brings binary maze data written by any earlier version of the format up
+/// to the current one, by running each migration from its version onward.
+/// Current data comes back unchanged.  Fails if data isn't a binary maze
+/// file, or was written by a newer version than this one understands.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::save;
+///
+/// let mut maze = mazegame::Maze::new(3, 3);
+/// maze.generate_perfect_seeded(472).unwrap();
+/// let bytes = maze.to_bytes();
+/// assert_eq!(bytes, save::migrate(&bytes).unwrap());
+///
+/// let mut future = bytes.clone();
+/// future[4] += 1;
+/// assert!(save::migrate(&future).is_err());
+/// ```
+pub fn migrate(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    if data.len() <= VERSION_AT || &data[..VERSION_AT] != BINARY_MAGIC {
+        return Err(MazeError::Parse("not a binary maze file".to_string()));
+    }
+    let version = data[VERSION_AT];
+    if version > BINARY_VERSION {
+        return Err(MazeError::Parse(format!("binary maze version {} is newer than this build understands ({})",
+                                            version, BINARY_VERSION)));
+    }
+    if version == 0 {
+        return Err(MazeError::Parse("unsupported binary maze version 0".to_string()));
+    }
+    let mut data = data.to_vec();
+    for migration in MIGRATIONS[version as usize - 1..].iter() {
+        data = migration(&data)?;
+    }
+    Ok(data)
+}
+
+/// The part of a current binary maze file before its Squares.
+pub(crate) struct BinaryHeader {
+    pub cols: u32,
+    pub rows: u32,
+    pub num_rooms: u32,
+    pub x_topology: Topology,
+    pub y_topology: Topology,
+    pub entrance: Option<Opening>,
+    pub exits: Vec<Opening>,
+    pub doors: Vec<Door>,
+    pub start: Option<Coord>,
+    /// Whether elevations follow the room themes.
+    pub elevations: bool,
+    /// Where the Squares start, CELL_BYTES each in row-major order.
+    pub cells_at: usize,
+}
+
+/// Reads the header of current binary maze data, without its CRC-32, and
+/// checks that every Square follows it.
+pub(crate) fn read_header(body: &[u8]) -> Result<BinaryHeader, MazeError> {
+    let mut reader = ByteReader { data: body, pos: 0 };
+    reader.take(SIZE_AT)?;
+    let cols = reader.u32()?;
+    let rows = reader.u32()?;
+    let num_rooms = reader.u32()?;
+    let flags = reader.u8()?;
+    let entrance = if flags & 1 != 0 { Some(reader.opening()?) } else { None };
+    let mut exits = Vec::new();
+    if flags & 2 != 0 {
+        exits.push(reader.opening()?);
+    }
+    if flags & 16 != 0 {
+        // Each opening takes nine bytes, which bounds the count
+        for _ in 0..reader.count("exit count", 9)? {
+            exits.push(reader.opening()?);
+        }
+    }
+    let mut doors = Vec::new();
+    if flags & 32 != 0 {
+        // Each door takes fourteen bytes
+        for _ in 0..reader.count("door count", 14)? {
+            let Opening { coord, dir } = reader.opening()?;
+            let room_id = reader.i32()?;
+            let locked = match reader.u8()? {
+                0 => false,
+                1 => true,
+                other => return Err(MazeError::Corrupted { what: format!("locked flag {}", other),
+                                                           offset: reader.pos - 1 }),
+            };
+            doors.push(Door { coord, dir, room_id, locked });
+        }
+        doors.sort_by_key(Door::order);
+    }
+    let start = if flags & 64 != 0 { Some(Coord::new(reader.u32()?, reader.u32()?)) } else { None };
+
+    // Make sure the data really holds every Square before anything is
+    // allocated for them
+    let cell_bytes = cell_count(cols, rows).ok().and_then(|cells| cells.checked_mul(CELL_BYTES));
+    if cell_bytes.is_none_or(|bytes| bytes > reader.remaining()) {
+        return Err(MazeError::Corrupted { what: format!("size {}x{}", cols, rows), offset: SIZE_AT });
+    }
+    let wraps = |bit: u8| if flags & bit != 0 { Topology::Torus } else { Topology::Bounded };
+    Ok(BinaryHeader { cols, rows, num_rooms, x_topology: wraps(4), y_topology: wraps(8), entrance, exits, doors,
+                      start, elevations: flags & 128 != 0, cells_at: reader.pos })
+}
+
+/// Decodes one Square from the CELL_BYTES it takes in a binary maze file.
+pub(crate) fn read_cell(bytes: &[u8]) -> Square {
+    let mut sq = Square::new();
+    sq.set_open_mask(bytes[0]);
+    sq.under = bytes[0] >> 4;
+    sq.id = i32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+    sq.one_way = bytes[5] & 0xf;
+    sq.secret = bytes[5] >> 4;
+    sq
+}
+
+//
+// Internal - version 1 to 2: adds a one-way mask byte, with no one-way
+// passages, after each Square's id.  The Squares are the last five bytes
+// per Square of the file.
+//
+fn migrate_v1_to_v2(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    let mut reader = ByteReader { data, pos: SIZE_AT };
+    let cells = cell_count(reader.u32()?, reader.u32()?)?;
+    let header = cells.checked_mul(5).and_then(|bytes| data.len().checked_sub(bytes))
+        .filter(|&header| header >= reader.pos)
+        .ok_or_else(|| MazeError::Corrupted { what: "version 1 cell data".to_string(), offset: data.len() })?;
+    let mut out = Vec::with_capacity(data.len() + cells);
+    out.extend_from_slice(&data[..header]);
+    out[VERSION_AT] = 2;
+    for cell in data[header..].chunks(5) {
+        out.extend_from_slice(cell);
+        out.push(0);
+    }
+    Ok(out)
+}
+
+//
+// Internal - version 2 to 3: adds an empty list of room themes at the end.
+//
+fn migrate_v2_to_v3(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    let mut out = data.to_vec();
+    out[VERSION_AT] = 3;
+    out.extend_from_slice(&0u32.to_le_bytes());
+    Ok(out)
+}
+
+//
+// Internal - version 3 to 4: the high bits of each passage mask, always
+// clear in version 3, now mark secret passages.  Only the version changes,
+// so that older builds turn the new files away instead of losing secrets.
+//
+fn migrate_v3_to_v4(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    let mut out = data.to_vec();
+    out[VERSION_AT] = 4;
+    Ok(out)
+}
+
+//
+// Internal - version 4 to 5: adds the CRC-32 of the file to its end.
+//
+fn migrate_v4_to_v5(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    let mut out = data.to_vec();
+    out[VERSION_AT] = 5;
+    let crc = crc32(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    Ok(out)
+}
+
+//
+// Internal - version 5 to 6: bit 4 of the flags, always clear in version 5,
+// now says more exits follow.  Only the version changes, so that older
+// builds turn the new files away instead of misreading them.
+//
+fn migrate_v5_to_v6(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    restamp(data, 6)
+}
+
+//
+// Internal - version 6 to 7: bit 5 of the flags, always clear in version 6,
+// now says doors follow.  Only the version changes.
+//
+fn migrate_v6_to_v7(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    restamp(data, 7)
+}
+
+//
+// Internal - version 7 to 8: bit 6 of the flags, always clear in version 7,
+// now says a start Square follows.  Only the version changes.
+//
+fn migrate_v7_to_v8(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    restamp(data, 8)
+}
+
+//
+// Internal - version 8 to 9: bit 7 of the flags, always clear in version 8,
+// now says elevations follow the room themes.  Only the version changes.
+//
+fn migrate_v8_to_v9(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    restamp(data, 9)
+}
+
+//
+// Internal - version 9 to 10: adds an empty list of corridor regions after
+// the elevations.
+//
+fn migrate_v9_to_v10(data: &[u8]) -> Result<Vec<u8>, MazeError> {
+    let mut out = check_crc(data)?.to_vec();
+    out[VERSION_AT] = 10;
+    out.extend_from_slice(&0u32.to_le_bytes());
+    let crc = crc32(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    Ok(out)
+}
+
+//
+// Internal - gives binary maze data with a CRC-32 a new version byte, and
+// the CRC-32 to match.  The old CRC-32 is checked first, so a damaged file
+// isn't passed off as a good one.
+//
+fn restamp(data: &[u8], version: u8) -> Result<Vec<u8>, MazeError> {
+    let mut out = check_crc(data)?.to_vec();
+    out[VERSION_AT] = version;
+    let crc = crc32(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    Ok(out)
+}
+
+//
+// Internal - checks the CRC-32 at the end of current binary maze data,
+// returning the bytes it covers.
+//
+pub(crate) fn check_crc(data: &[u8]) -> Result<&[u8], MazeError> {
+    let at = match data.len().checked_sub(4) {
+        Some(at) if at > SIZE_AT => at,
+        _ => return Err(MazeError::Corrupted { what: "data cut short".to_string(), offset: data.len() }),
+    };
+    let stored = u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+    if crc32(&data[..at]) != stored {
+        return Err(MazeError::Corrupted { what: "CRC-32 mismatch".to_string(), offset: at });
+    }
+    Ok(&data[..at])
+}
+
+//
+// Internal - the number of Squares in a cols x rows maze, if it can be
+// counted.
+//
+fn cell_count(cols: u32, rows: u32) -> Result<usize, MazeError> {
+    (cols as usize).checked_mul(rows as usize)
+        .ok_or_else(|| MazeError::Parse("maze dimensions are too large".to_string()))
+}
+
+//
+// Internal - parses `count` numeric values following the key of a header line.
+//
+fn parse_fields(fields: &[&str], count: usize, num: usize) -> Result<Vec<u32>, MazeError> {
+    if fields.len() != count + 1 {
+        return Err(MazeError::Parse(format!("line {}: '{}' takes {} values", num + 1, fields[0], count)));
+    }
+    fields[1..].iter()
+        .map(|f| f.parse().map_err(|_| MazeError::Parse(format!("line {}: bad number '{}'", num + 1, f))))
+        .collect()
+}
+
+//
+// Internal - parses an 'entrance' or 'exit' header line.
+//
+fn parse_opening(fields: &[&str], num: usize) -> Result<Opening, MazeError> {
+    let v = parse_fields(fields, 3, num)?;
+    Ok(Opening { coord: Coord::new(v[0], v[1]), dir: v[2] })
+}
+
+//
+// Internal - parses a 'door' header line.
+//
+fn parse_door(fields: &[&str], num: usize) -> Result<Door, MazeError> {
+    let bad = |f: &str| MazeError::Parse(format!("line {}: bad number '{}'", num + 1, f));
+    if fields.len() != 6 {
+        return Err(MazeError::Parse(format!("line {}: 'door' takes 5 values", num + 1)));
+    }
+    let v = parse_fields(&fields[..4], 3, num)?;
+    let room_id = fields[4].parse().map_err(|_| bad(fields[4]))?;
+    let locked = match fields[5] {
+        "0" => false,
+        "1" => true,
+        other => return Err(bad(other)),
+    };
+    Ok(Door { coord: Coord::new(v[0], v[1]), dir: v[2], room_id, locked })
+}
+
+//
+// Internal - parses a 'theme' header line into the room id and the theme,
+// which is the rest of the line.
+//
+fn parse_theme(line: &str, num: usize) -> Result<(u32, String), MazeError> {
+    let rest = line.trim().trim_start_matches("theme").trim_start();
+    match rest.split_once(char::is_whitespace) {
+        Some((id, theme)) => {
+            let room_id = id.parse().ok().filter(|&id| id > 0)
+                .ok_or_else(|| MazeError::Parse(format!("line {}: bad room id '{}'", num + 1, id)))?;
+            Ok((room_id, theme.trim().to_string()))
+        }
+        None => Err(MazeError::Parse(format!("line {}: 'theme' takes a room id and a name", num + 1))),
+    }
+}
+
+//
+// Internal - parses one corridor region, a number or '-' for none.
+//
+fn parse_region(region: &str) -> Option<Option<u32>> {
+    match region {
+        "-" => Some(None),
+        r => r.parse().ok().map(Some),
+    }
+}
+
+//
+// Internal - parses a 'checksum' header line.
+//
+fn parse_checksum(fields: &[&str], num: usize) -> Result<u64, MazeError> {
+    match fields {
+        [_, c] => u64::from_str_radix(c, 16)
+            .map_err(|_| MazeError::Parse(format!("line {}: bad checksum '{}'", num + 1, c))),
+        _ => Err(MazeError::Parse(format!("line {}: 'checksum' takes 1 value", num + 1))),
+    }
+}
+
+//
+// Internal - a value as JSON.
+//
+#[cfg(feature = "serde")]
+fn to_json_value<T: ::serde::Serialize>(value: &T) -> Result<JsonValue, MazeError> {
+    ::serde_json::to_value(value).map_err(|e| MazeError::Parse(e.to_string()))
+}
+
+//
+// Internal - a JSON object written out, one value to a line.
+//
+#[cfg(feature = "serde")]
+fn to_json_pretty(map: &JsonMap) -> Result<String, MazeError> {
+    ::serde_json::to_string_pretty(map).map_err(|e| MazeError::Parse(e.to_string()))
+}
+
+//
+// Internal - reads the field key of a JSON object, or None if it's missing.
+//
+#[cfg(feature = "serde")]
+fn json_field<T: ::serde::de::DeserializeOwned>(map: &JsonMap, key: &str) -> Result<Option<T>, MazeError> {
+    map.get(key)
+        .map(|v| ::serde_json::from_value(v.clone()).map_err(|e| MazeError::Parse(format!("'{}': {}", key, e))))
+        .transpose()
+}
+
+//
+// Internal - parses a 'topology' header line.
+//
+fn parse_topology(fields: &[&str], num: usize) -> Result<(Topology, Topology), MazeError> {
+    if fields.len() != 3 {
+        return Err(MazeError::Parse(format!("line {}: 'topology' takes 2 values", num + 1)));
+    }
+    let parse = |f: &str| topology_from_name(f)
+        .ok_or_else(|| MazeError::Parse(format!("line {}: bad topology '{}'", num + 1, f)));
+    Ok((parse(fields[1])?, parse(fields[2])?))
+}
+
+//
+// Internal - the Topology named name in the text format.
+//
+fn topology_from_name(name: &str) -> Option<Topology> {
+    match name {
+        "bounded" => Some(Topology::Bounded),
+        "torus" => Some(Topology::Torus),
+        _ => None,
+    }
+}
+
+//
+// Internal - the name of a Topology in the text format.
+//
+fn topology_name(topology: Topology) -> &'static str {
+    match topology {
+        Topology::Bounded => "bounded",
+        Topology::Torus => "torus",
+    }
+}
+
+//
+// Internal - parses a single `<mask>:<id>` cell.
+//
+fn parse_cell(cell: &str) -> Option<Square> {
+    let mut parts = cell.splitn(3, ':');
+    let mask = u8::from_str_radix(parts.next()?, 16).ok()?;
+    let id = parts.next()?.parse().ok()?;
+    let passages = match parts.next() {
+        Some(m) => u8::from_str_radix(m, 16).ok()?,
+        None => 0,
+    };
+    if mask > 0x3f {
+        return None;
+    }
+    let mut sq = Square::new();
+    sq.set_open_mask(mask);
+    sq.under = mask >> 4;
+    sq.id = id;
+    sq.one_way = passages & 0xf;
+    sq.secret = passages >> 4;
+    Some(sq)
+}
+
+//
+// Internal - a Square's open walls and weave crossings, as stored in a file.
+//
+fn cell_mask(sq: &Square) -> u8 {
+    sq.open_mask() | (sq.under << 4)
+}
+
+//
+// Internal - a Square's one-way and secret passages, as stored in a file.
+//
+fn passage_mask(sq: &Square) -> u8 {
+    (sq.one_way & 0xf) | (sq.secret << 4)
+}
+
+//
+// Internal - how exits appear in JSON: a list, or a single exit (or null)
+// in files saved before mazes could have more than one.
+//
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonExits {
+    Many(Vec<Opening>),
+    One(Option<Opening>),
+}
+
+//
+// Internal - reads a Maze's exits from JSON in either form.
+//
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_exits<'de, D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<Opening>, D::Error> {
+    use serde::Deserialize;
+    Ok(match JsonExits::deserialize(deserializer)? {
+        JsonExits::Many(exits) => exits,
+        JsonExits::One(exit) => exit.into_iter().collect(),
+    })
+}
+
+//
+// Internal - a cursor over binary maze data that fails cleanly on truncation.
+//
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], MazeError> {
+        if self.remaining() < count {
+            return Err(MazeError::Corrupted { what: "data cut short".to_string(), offset: self.pos });
+        }
+        let bytes = &self.data[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, MazeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, MazeError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, MazeError> {
+        let b = self.take(4)?;
+        Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    // A u32 count of things taking at least each bytes apiece, which has to
+    // fit in what's left
+    fn count(&mut self, what: &str, each: usize) -> Result<usize, MazeError> {
+        let at = self.pos;
+        let count = self.u32()? as usize;
+        if count > self.remaining() / each {
+            return Err(MazeError::Corrupted { what: format!("{} {}", what, count), offset: at });
+        }
+        Ok(count)
+    }
+
+    fn opening(&mut self) -> Result<Opening, MazeError> {
+        let x = self.u32()?;
+        let y = self.u32()?;
+        let dir = self.u8()? as u32;
+        Ok(Opening { coord: Coord::new(x, y), dir })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn sample_maze() -> Maze {
+        let mut maze = Maze::new(6, 8);
+        maze.generate_seeded((2, 2, 2, 2, 2), 11).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        maze.set_exit(7, 5, constants::DIR_SOUTH).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let maze = sample_maze();
+        let copy = Maze::from_text(&maze.to_text()).unwrap();
+        assert_eq!(maze, copy);
+    }
+
+    #[test]
+    fn test_text_parse() {
+        let text = "mazegame 1\nsize 3 2\nrooms 0\nentrance 0 0 0\nexit 2 1 1\ncells\n\
+                    5:-1 c:-1 a:-1\n4:-1 c:-1 b:-1\n";
+        let maze = Maze::from_text(text).unwrap();
+        assert_eq!(3, maze.get_cols());
+        assert_eq!(2, maze.get_rows());
+        assert_eq!(Some(Opening { coord: Coord::new(0, 0), dir: constants::DIR_NORTH }), maze.get_entrance());
+        assert_eq!([false, true, false, true], maze.sq[0].wall_present);
+        assert_eq!(-1, maze.sq[5].id);
+        assert_eq!(text, maze.to_text());
+    }
+
+    #[test]
+    fn test_text_errors() {
+        assert!(Maze::from_text("").is_err());
+        assert!(Maze::from_text("mazegame 1\ncells\n").is_err());
+        assert!(Maze::from_text("mazegame 1\nsize 2 1\nbogus 3\ncells\n0:0 0:0\n").is_err());
+        // Too few cells in a row, too few rows, bad cells
+        assert!(Maze::from_text("mazegame 1\nsize 2 1\ncells\n0:0\n").is_err());
+        assert!(Maze::from_text("mazegame 1\nsize 2 2\ncells\n0:0 0:0\n").is_err());
+        assert!(Maze::from_text("mazegame 1\nsize 2 1\ncells\n0:0 g:0\n").is_err());
+        assert!(Maze::from_text("mazegame 1\nsize 2 1\ncells\n0:0 0:x\n").is_err());
+        // Entrance outside of the maze
+        assert!(Maze::from_text("mazegame 1\nsize 2 1\nentrance 5 5 0\ncells\n0:0 0:0\n").is_err());
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let maze = sample_maze();
+        let copy = Maze::from_bytes(&maze.to_bytes()).unwrap();
+        assert_eq!(maze, copy);
+    }
+
+    #[test]
+    fn test_topology_round_trip() {
+        let mut maze = Maze::new(4, 5);
+        maze.set_topology(Topology::Torus, Topology::Bounded);
+        maze.generate_perfect_seeded(9).unwrap();
+        assert!(maze.to_text().contains("\ntopology torus bounded\n"));
+        assert_eq!(maze, Maze::from_text(&maze.to_text()).unwrap());
+        assert_eq!(maze, Maze::from_bytes(&maze.to_bytes()).unwrap());
+        assert!(Maze::from_text("mazegame 1\nsize 2 1\ntopology torus\ncells\n0:0 0:0\n").is_err());
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let bytes = sample_maze().to_bytes();
+        assert!(Maze::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Maze::from_bytes(&bytes[..10]).is_err());
+        assert!(Maze::from_bytes(b"JUNKJUNKJUNK").is_err());
+
+        // A huge size with no data behind it shouldn't try to allocate it
+        let mut bad = bytes[..5].to_vec();
+        bad.extend_from_slice(&u32::MAX.to_le_bytes());
+        bad.extend_from_slice(&u32::MAX.to_le_bytes());
+        bad.extend_from_slice(&[0, 0, 0, 0, 0]);
+        reseal(&mut bad, false);
+        assert_eq!(Err(MazeError::Corrupted { what: format!("size {}x{}", u32::MAX, u32::MAX), offset: SIZE_AT }),
+                   Maze::from_bytes(&bad));
+    }
+
+    // Replaces the CRC-32 at the end of bytes with the right one for what's
+    // before it, so the checks behind it can be reached.  Adds one if
+    // there isn't one to replace.
+    fn reseal(bytes: &mut Vec<u8>, replace: bool) {
+        if replace {
+            bytes.truncate(bytes.len() - 4);
+        }
+        let crc = crc32(bytes);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+    }
+
+    #[test]
+    fn test_crc() {
+        let bytes = sample_maze().to_bytes();
+        let at = bytes.len() - 4;
+        assert_eq!(crc32(&bytes[..at]).to_le_bytes(), bytes[at..]);
+
+        // Any one byte changed, anywhere after the header, is caught
+        for i in SIZE_AT..bytes.len() {
+            let mut bad = bytes.clone();
+            bad[i] ^= 0x10;
+            assert_eq!(Err(MazeError::Corrupted { what: "CRC-32 mismatch".to_string(), offset: at }),
+                       Maze::from_bytes(&bad), "byte {}", i);
+        }
+        // As is a file cut short anywhere
+        for cut in SIZE_AT..bytes.len() {
+            assert!(matches!(Maze::from_bytes(&bytes[..cut]), Err(MazeError::Corrupted { .. })), "cut at {}", cut);
+        }
+    }
+
+    #[test]
+    fn test_corrupted_files() {
+        // Flipped bytes must give an error, never a panic or a Maze bigger
+        // than the data could hold.  Resealing gets past the CRC-32, and
+        // files from before it have none.
+        let mut fixtures = vec![sample_maze().to_bytes(), door_maze().to_bytes(), fixture_v1_maze().to_bytes()];
+        let mut themed = sample_maze();
+        themed.theme_rooms(&[("crypt".to_string(), 1.0)], 480).unwrap();
+        fixtures.push(themed.to_bytes());
+        #[cfg(feature = "std")]
+        for name in ["save_v1.mzb", "save_v2.mzb"].iter() {
+            fixtures.push(std::fs::read(crate::testing::fixture_dir().join(name)).unwrap());
+        }
+
+        let mut rng = crate::rng::from_seed(480);
+        for fixture in fixtures.iter() {
+            let current = fixture[VERSION_AT] == BINARY_VERSION;
+            for _ in 0..300 {
+                let mut bad = fixture.clone();
+                for _ in 0..rng.gen_range(1, 4) {
+                    let i = rng.gen_range(SIZE_AT, bad.len());
+                    bad[i] = rng.gen();
+                }
+                if current && rng.gen() {
+                    reseal(&mut bad, true);
+                }
+                match Maze::from_bytes(&bad) {
+                    Ok(maze) => assert!(maze.sq.len() * 6 <= bad.len()),
+                    Err(MazeError::Corrupted { offset, .. }) => {
+                        assert!(offset <= migrate(&bad).map_or(bad.len(), |data| data.len()))
+                    }
+                    Err(_) => (),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_several_exits() {
+        let mut maze = sample_maze();
+        maze.add_exit(7, 0, constants::DIR_EAST).unwrap();
+        maze.add_exit(3, 0, constants::DIR_NORTH).unwrap();
+        assert!(maze.to_text().contains("\nexit 7 5 1\nexit 7 0 2\nexit 3 0 0\n"));
+        assert_eq!(maze, Maze::from_text(&maze.to_text()).unwrap());
+        let bytes = maze.to_bytes();
+        assert_eq!(maze, Maze::from_bytes(&bytes).unwrap());
+        #[cfg(feature = "serde")]
+        assert_eq!(maze, Maze::from_json(&maze.to_json().unwrap()).unwrap());
+
+        // Files with one exit are written as before
+        assert_eq!(sample_maze().to_bytes().len() + 4 + 2 * 9, bytes.len());
+        // A count of more exits than the file holds is caught before reading
+        let count_at = 4 + 1 + 12 + 1 + 2 * 9;
+        let mut bad = bytes.clone();
+        bad[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Maze::from_bytes(&bad).is_err());
+        reseal(&mut bad, true);
+        assert_eq!(Err(MazeError::Corrupted { what: format!("exit count {}", u32::MAX), offset: count_at }),
+                   Maze::from_bytes(&bad));
+    }
+
+    #[test]
+    fn test_doors_round_trip() {
+        let mut maze = sample_maze();
+        maze.connect_regions_with_rng(&mut crate::rng::from_seed(470));
+        assert!(!maze.doors().is_empty());
+        let door = maze.doors()[0];
+        maze.set_door_locked(door.coord, door.dir, true).unwrap();
+        let line = format!("\ndoor {} {} {} {} 1\n", door.coord.x, door.coord.y, door.dir, door.room_id);
+        assert!(maze.to_text().contains(&line), "{}", maze.to_text());
+
+        let text = Maze::from_text(&maze.to_text()).unwrap();
+        assert_eq!(maze.doors(), text.doors());
+        assert_eq!(maze, text);
+        let bytes = maze.to_bytes();
+        assert_eq!(maze, Maze::from_bytes(&bytes).unwrap());
+        #[cfg(feature = "serde")]
+        assert_eq!(maze, Maze::from_json(&maze.to_json().unwrap()).unwrap());
+
+        // Bad doors
+        let bad = maze.to_text().replace(&line, "\ndoor 1 1 0 1 2\n");
+        assert!(Maze::from_text(&bad).is_err());
+        let bad = maze.to_text().replace(&line, "\ndoor 99 1 0 1 0\n");
+        assert_eq!(Err(MazeError::OutOfBounds(99, 1)), Maze::from_text(&bad));
+        let mut bad = bytes.clone();
+        let locked_at = bytes.len() - 12 - maze.sq.len() * 6 - (maze.doors().len() - 1) * 14 - 1;
+        assert_eq!(1, bad[locked_at]);
+        bad[locked_at] = 7;
+        reseal(&mut bad, true);
+        assert_eq!(Err(MazeError::Corrupted { what: "locked flag 7".to_string(), offset: locked_at }),
+                   Maze::from_bytes(&bad));
+
+        // Doors have to be in the room they name, and it has to exist
+        for &room in [0, door.room_id + 1, 99].iter() {
+            let wrong = format!("\ndoor {} {} {} {} 1\n", door.coord.x, door.coord.y, door.dir, room);
+            assert!(matches!(Maze::from_text(&maze.to_text().replace(&line, &wrong)), Err(MazeError::Parse(_))));
+            let mut bad = bytes.clone();
+            bad[locked_at - 4..locked_at].copy_from_slice(&room.to_le_bytes());
+            reseal(&mut bad, true);
+            assert!(matches!(Maze::from_bytes(&bad), Err(MazeError::Parse(_))));
+        }
+    }
+
+    #[test]
+    fn test_room_themes_round_trip() {
+        let mut maze = sample_maze();
+        let themes = vec![("crypt".to_string(), 1.0), ("d\u{e9}p\u{f4}t".to_string(), 1.0)];
+        maze.theme_rooms(&themes, 472).unwrap();
+        let copy = Maze::from_bytes(&maze.to_bytes()).unwrap();
+        assert_eq!(maze, copy);
+        for room_id in 1..=maze.num_rooms as i32 {
+            assert!(copy.room_theme(room_id).is_some());
+            assert_eq!(maze.room_theme(room_id), copy.room_theme(room_id));
+        }
+
+        // More themes than rooms
+        let mut bad = maze.to_bytes();
+        let count_at = bad.len() - 12 - maze.room_themes.iter().map(|t| 4 + t.len()).sum::<usize>();
+        bad[count_at] += 1;
+        reseal(&mut bad, true);
+        assert!(matches!(Maze::from_bytes(&bad), Err(MazeError::Corrupted { offset, .. }) if offset == count_at));
+    }
+
+    #[test]
+    fn test_themes_and_regions_round_trip() {
+        let mut maze = door_maze();
+        maze.theme_rooms(&[("old vault".to_string(), 1.0)], 436).unwrap();
+        maze.partition_corridors(3, 436).unwrap();
+        assert!(maze.to_text().contains("\ntheme 1 old vault\n"), "{}", maze.to_text());
+
+        // Every format keeps both, and equality notices when they're lost
+        let mut plain = maze.clone();
+        plain.room_themes.clear();
+        plain.corridor_regions.clear();
+        assert_ne!(maze, plain);
+        for copy in [Maze::from_text(&maze.to_text()).unwrap(), Maze::from_text(&maze.to_text_canonical()).unwrap(),
+                     Maze::from_bytes(&maze.to_bytes()).unwrap()].iter() {
+            assert_eq!(&maze, copy);
+            assert_eq!(Some("old vault"), copy.room_theme(1));
+            assert_eq!(maze.corridor_regions(), copy.corridor_regions());
+        }
+        #[cfg(feature = "serde")]
+        assert_eq!(maze, Maze::from_json(&maze.to_json().unwrap()).unwrap());
+
+        // Themes for rooms that don't exist, and regions that don't fit
+        let text = maze.to_text();
+        assert!(Maze::from_text(&text.replace("\ntheme 1 ", "\ntheme 9 ")).is_err());
+        assert!(Maze::from_text(&text.replace("\ntheme 1 old vault\n", "\ntheme 1\n")).is_err());
+        let regions_at = text.find("corridor_regions\n").unwrap();
+        assert!(Maze::from_text(&format!("{}x\n", &text[..regions_at + 17])).is_err());
+        let mut bad = maze.to_bytes();
+        let count_at = bad.len() - 4 - maze.sq.len() * 4 - 4;
+        bad[count_at] -= 1;
+        reseal(&mut bad, true);
+        assert!(matches!(Maze::from_bytes(&bad), Err(MazeError::Corrupted { offset, .. }) if offset == count_at));
+    }
+
+    #[test]
+    fn test_secrets_round_trip() {
+        let mut maze = fixture_v2_maze();
+        maze.carve_secret(0, 0, constants::DIR_EAST).unwrap();
+        maze.carve_secret(2, 3, constants::DIR_NORTH).unwrap();
+        assert!(maze.to_text().contains("\n5:-1:40 c:-1:80 "), "{}", maze.to_text());
+        assert_eq!(maze, Maze::from_text(&maze.to_text()).unwrap());
+        assert_eq!(maze, Maze::from_bytes(&maze.to_bytes()).unwrap());
+        #[cfg(feature = "serde")]
+        assert_eq!(maze, Maze::from_json(&maze.to_json().unwrap()).unwrap());
+        assert_eq!(2, Maze::from_bytes(&maze.to_bytes()).unwrap().secret_passages().len());
+    }
+
+    // The maze in tests/fixtures/save_v1.mzb, which predates one-way
+    // passages and room themes
+    fn fixture_v1_maze() -> Maze {
+        let mut maze = Maze::new(5, 7);
+        maze.generate_seeded((1, 2, 3, 2, 3), 472).unwrap();
+        maze.connect_regions_with_rng(&mut crate::rng::from_seed(472));
+        maze.doors.clear();
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        maze.set_exit(6, 4, constants::DIR_EAST).unwrap();
+        maze
+    }
+
+    // The maze in tests/fixtures/save_v2.mzb, which has a one-way passage
+    // but predates room themes
+    fn fixture_v2_maze() -> Maze {
+        let mut maze = Maze::new(4, 4);
+        maze.generate_perfect_seeded(472).unwrap();
+        maze.carve_one_way(1, 1, constants::DIR_EAST).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        maze.set_exit(3, 3, constants::DIR_SOUTH).unwrap();
+        maze
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_migrate_fixtures() {
+        let read = |name: &str| std::fs::read(crate::testing::fixture_dir().join(name)).unwrap();
+        for (name, version, expected) in [("save_v1.mzb", 1, fixture_v1_maze()), ("save_v2.mzb", 2, fixture_v2_maze())] {
+            let old = read(name);
+            assert_eq!(version, old[VERSION_AT]);
+            let migrated = migrate(&old).unwrap();
+            assert_eq!(BINARY_VERSION, migrated[VERSION_AT]);
+            assert_eq!(expected.to_bytes(), migrated);
+            let maze = Maze::from_bytes(&old).unwrap();
+            assert_eq!(expected, maze);
+            assert_eq!(None, maze.room_theme(1));
+        }
+    }
+
+    #[test]
+    fn test_migrate() {
+        // Each migration on its own, on a maze version 1 can hold
+        let maze = fixture_v1_maze();
+        let current = maze.to_bytes();
+        assert_eq!(current, migrate(&current).unwrap());
+        let mut v9 = current[..current.len() - 8].to_vec();
+        v9[VERSION_AT] = 9;
+        reseal(&mut v9, false);
+        assert_eq!(current, migrate_v9_to_v10(&v9).unwrap());
+        let mut v8 = v9[..v9.len() - 4].to_vec();
+        v8[VERSION_AT] = 8;
+        reseal(&mut v8, false);
+        assert_eq!(v9, migrate_v8_to_v9(&v8).unwrap());
+        let mut v7 = v8[..v8.len() - 4].to_vec();
+        v7[VERSION_AT] = 7;
+        reseal(&mut v7, false);
+        assert_eq!(v8, migrate_v7_to_v8(&v7).unwrap());
+        let mut v6 = v7[..v7.len() - 4].to_vec();
+        v6[VERSION_AT] = 6;
+        reseal(&mut v6, false);
+        assert_eq!(v7, migrate_v6_to_v7(&v6).unwrap());
+        let mut v5 = v6[..v6.len() - 4].to_vec();
+        v5[VERSION_AT] = 5;
+        reseal(&mut v5, false);
+        assert_eq!(v6, migrate_v5_to_v6(&v5).unwrap());
+        let mut v4 = v5[..v5.len() - 4].to_vec();
+        v4[VERSION_AT] = 4;
+        assert_eq!(v5, migrate_v4_to_v5(&v4).unwrap());
+        let mut v3 = v4.clone();
+        v3[VERSION_AT] = 3;
+        assert_eq!(v4, migrate_v3_to_v4(&v3).unwrap());
+        let mut v2 = v3[..v3.len() - 4].to_vec();
+        v2[VERSION_AT] = 2;
+        assert_eq!(v3, migrate_v2_to_v3(&v2).unwrap());
+        let mut v1: Vec<u8> = v2[..v2.len() - maze.sq.len() * 6].to_vec();
+        v1[VERSION_AT] = 1;
+        for cell in v2[v1.len()..].chunks(6) {
+            v1.extend_from_slice(&cell[..5]);
+        }
+        assert_eq!(v2, migrate_v1_to_v2(&v1).unwrap());
+
+        // A damaged file isn't given a good CRC-32 on the way up
+        let mut bad = v5.clone();
+        bad[SIZE_AT] ^= 1;
+        assert!(matches!(migrate_v5_to_v6(&bad), Err(MazeError::Corrupted { .. })));
+
+        // Version 1 files that are cut short
+        assert!(Maze::from_bytes(&v1[..v1.len() - 1]).is_err());
+        assert!(migrate(&v1[..12]).is_err());
+        assert!(migrate(&v1[..6]).is_err());
+    }
+
+    #[test]
+    fn test_migrate_rejects_unknown_versions() {
+        let mut bytes = sample_maze().to_bytes();
+        for &version in [BINARY_VERSION + 1, 0xff].iter() {
+            bytes[VERSION_AT] = version;
+            assert_eq!(Err(MazeError::Parse(format!("binary maze version {} is newer than this build understands ({})",
+                                                    version, BINARY_VERSION))),
+                       migrate(&bytes));
+            assert!(Maze::from_bytes(&bytes).is_err());
+        }
+        bytes[VERSION_AT] = 0;
+        assert!(migrate(&bytes).is_err());
+        assert!(migrate(b"JUNK").is_err());
+        assert!(migrate(b"").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_single_exit() {
+        // Mazes saved before there could be several exits had just one
+        let maze = sample_maze();
+        let json = maze.to_json().unwrap().replace("\"exits\":[", "\"exit\":").replace("}],\"x_topology", "},\"x_topology");
+        assert!(json.contains("\"exit\":{"), "{}", json);
+        assert_eq!(maze, Maze::from_json(&json).unwrap());
+        let json = json.replace(&json[json.find("\"exit\":").unwrap()..json.find(",\"x_topology").unwrap()], "\"exit\":null");
+        assert_eq!(None, Maze::from_json(&json).unwrap().get_exit());
+    }
+
+    #[test]
+    fn test_start_round_trip() {
+        let mut maze = sample_maze();
+        maze.set_start(5, 4).unwrap();
+        assert!(maze.to_text().contains("\nstart 5 4\nentrance"));
+        assert_eq!(Coord::new(5, 4), Maze::from_text(&maze.to_text()).unwrap().get_start());
+        assert_eq!(Coord::new(5, 4), Maze::from_text(&maze.to_text_canonical()).unwrap().get_start());
+        assert_eq!(Coord::new(5, 4), Maze::from_bytes(&maze.to_bytes()).unwrap().get_start());
+        assert!(!Maze::from_bytes(&sample_maze().to_bytes()).unwrap().has_start());
+        let text = maze.to_text().replace("start 5 4", "start 8 0");
+        assert_eq!(Err(MazeError::OutOfBounds(8, 0)), Maze::from_text(&text));
+        #[cfg(feature = "serde")]
+        {
+            assert_eq!(Coord::new(5, 4), Maze::from_json(&maze.to_json().unwrap()).unwrap().get_start());
+            assert_eq!(Coord::new(5, 4), Maze::from_json(&maze.to_json_canonical().unwrap()).unwrap().get_start());
+            assert!(!sample_maze().to_json().unwrap().contains("\"start\""));
+        }
+    }
+
+    #[test]
+    fn test_elevations_round_trip() {
+        let mut maze = sample_maze();
+        maze.set_elevation(5, 4, -3).unwrap();
+        maze.set_elevation(0, 0, 127).unwrap();
+        assert!(maze.to_text().contains("\nelevations\n127 0 0 0 0 0 0 0\n"));
+        for copy in [Maze::from_text(&maze.to_text()), Maze::from_text(&maze.to_text_canonical()),
+                     Maze::from_bytes(&maze.to_bytes())] {
+            let copy = copy.unwrap();
+            assert_eq!(maze.elevations(), copy.elevations());
+            assert_eq!(maze, copy);
+        }
+        assert_ne!(maze, sample_maze());
+        assert!(Maze::from_bytes(&sample_maze().to_bytes()).unwrap().elevations().is_empty());
+        // A level Maze is equal to one that was never given elevations
+        let mut level = sample_maze();
+        level.set_elevation(1, 1, 0).unwrap();
+        assert_eq!(sample_maze(), level);
+
+        // Cut short, or not numbers
+        let text = maze.to_text();
+        let short: Vec<&str> = text.lines().take(text.lines().count() - 1).collect();
+        assert!(Maze::from_text(&short.join("\n")).is_err());
+        assert!(Maze::from_text(&text.replace("-3", "x")).is_err());
+        assert!(Maze::from_text(&text.replace("127", "128")).is_err());
+        let bytes = maze.to_bytes();
+        let mut cut = bytes[..bytes.len() - 5].to_vec();
+        reseal(&mut cut, false);
+        assert!(matches!(Maze::from_bytes(&cut), Err(MazeError::Corrupted { .. })));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let maze = sample_maze();
+        let copy = Maze::from_json(&maze.to_json().unwrap()).unwrap();
+        assert_eq!(maze, copy);
+        assert!(Maze::from_json("{\"rows\": 2}").is_err());
+    }
+
+    // The numbers of the lines that differ between two texts with the same
+    // number of lines
+    fn changed_lines(a: &str, b: &str) -> Vec<usize> {
+        assert_eq!(a.lines().count(), b.lines().count());
+        a.lines().zip(b.lines()).enumerate().filter(|(_, (a, b))| a != b).map(|(i, _)| i).collect()
+    }
+
+    // Carves the first east wall found outside the rooms, returning its row
+    fn carve_one(maze: &mut Maze) -> u32 {
+        let i = (0..maze.sq.len())
+            .find(|&i| {
+                let c = maze.get_coord(i);
+                c.x + 1 < maze.get_cols() && !maze.sq[i].is_part_of_room() && !maze.sq[i + 1].is_part_of_room() &&
+                    maze.sq[i].is_wall_present(constants::DIR_EAST)
+            })
+            .unwrap();
+        let c = maze.get_coord(i);
+        maze.carve(c.x, c.y, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        c.y
+    }
+
+    // A maze with rooms and several doors
+    fn door_maze() -> Maze {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_seeded((5, 3, 4, 3, 4), 478).unwrap();
+        maze.connect_regions_with_rng(&mut crate::rng::from_seed(478));
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        assert!(maze.doors.len() > 2);
+        maze
+    }
+
+    #[test]
+    fn test_canonical_door_order() {
+        // The same doors added in two orders save the same way
+        let maze = door_maze();
+        let readded = |doors: Vec<Door>| {
+            let mut copy = maze.clone();
+            copy.doors.clear();
+            for door in doors {
+                copy.add_door(door).unwrap();
+            }
+            copy
+        };
+        let forward = readded(maze.doors().to_vec());
+        let backward = readded(maze.doors().iter().rev().cloned().collect());
+        assert_eq!(maze.doors(), forward.doors());
+        assert_eq!(forward.doors(), backward.doors());
+        assert_eq!(forward.to_text_canonical(), backward.to_text_canonical());
+        #[cfg(feature = "serde")]
+        assert_eq!(forward.to_json_canonical().unwrap(), backward.to_json_canonical().unwrap());
+
+        // Doors recorded out of order in a file are put in order on loading
+        let text = maze.to_text();
+        let (doors, rest): (Vec<&str>, Vec<&str>) = text.lines().partition(|l| l.starts_with("door "));
+        let at = rest.iter().position(|&l| l == "cells").unwrap();
+        let mut lines = rest.clone();
+        lines.splice(at..at, doors.iter().rev().cloned());
+        assert_eq!(maze.doors(), Maze::from_text(&lines.join("\n")).unwrap().doors());
+    }
+
+    #[test]
+    fn test_canonical_text() {
+        let maze = door_maze();
+        let text = maze.to_text_canonical();
+        let copy = Maze::from_text(&text).unwrap();
+        assert_eq!(text, copy.to_text_canonical());
+        assert_eq!(maze.sq, copy.sq);
+        assert_eq!(maze.doors(), copy.doors());
+        // Legacy files give the same canonical form, and the legacy form
+        // can be written from a canonical file
+        assert_eq!(text, Maze::from_text(&maze.to_text()).unwrap().to_text_canonical());
+        assert_eq!(maze.sq, Maze::from_text(&copy.to_text()).unwrap().sq);
+        assert!(!Maze::new(2, 2).to_text_canonical().contains("rooms"));
+
+        let mut carved = copy.clone();
+        let row = carve_one(&mut carved);
+        let after = carved.to_text_canonical();
+        let cells_at = text.lines().position(|l| l == "cells").unwrap() + 1;
+        assert_eq!(vec![1, cells_at + row as usize], changed_lines(&text, &after));
+
+        // The checksum is checked when it's there
+        let tampered = after.replacen(":-0001:", ":+0000:", 1);
+        assert_eq!(Err(MazeError::Parse("checksum doesn't match the maze".to_string())), Maze::from_text(&tampered));
+        let unchecked: Vec<&str> = tampered.lines().filter(|l| !l.starts_with("checksum")).collect();
+        assert!(Maze::from_text(&unchecked.join("\n")).is_ok());
+        assert!(Maze::from_text(&after.replacen("checksum ", "checksum x", 1)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_canonical_json() {
+        let mut maze = door_maze();
+        maze.theme_rooms(&[("vault".to_string(), 1.0)], 478).unwrap();
+        maze.set_topology(Topology::Torus, Topology::Bounded);
+        maze.partition_corridors(3, 478).unwrap();
+        maze.set_elevation(1, 0, -3).unwrap();
+        let json = maze.to_json_canonical().unwrap();
+        let copy = Maze::from_json(&json).unwrap();
+        assert_eq!(json, copy.to_json_canonical().unwrap());
+        assert_eq!(maze.sq, copy.sq);
+        assert_eq!(maze.doors(), copy.doors());
+        assert_eq!(maze.room_theme(1), copy.room_theme(1));
+        assert!(!maze.corridor_regions().is_empty());
+        assert_eq!(maze.corridor_regions(), copy.corridor_regions());
+        assert_eq!(maze.elevations(), copy.elevations());
+        assert_eq!(maze.get_topology(), copy.get_topology());
+        assert_eq!(json, Maze::from_json(&maze.to_json().unwrap()).unwrap().to_json_canonical().unwrap());
+
+        // Carving forgets the corridor regions, so they're left out of both
+        let mut carved = copy.clone();
+        carved.corridor_regions.clear();
+        let before = carved.to_json_canonical().unwrap();
+        let row = carve_one(&mut carved);
+        let after = carved.to_json_canonical().unwrap();
+        let changed = changed_lines(&before, &after);
+        assert_eq!(2, changed.len(), "{:?}", changed);
+        let lines: Vec<&str> = after.lines().collect();
+        assert!(lines[changed[0] - row as usize - 1].ends_with("\"cells\": ["), "{}", after);
+        assert!(lines[changed[1]].trim_start().starts_with("\"checksum\""));
+
+        // Nothing optional is written for a plain maze
+        let plain = Maze::new(2, 3).to_json_canonical().unwrap();
+        for key in ["rooms", "topology", "entrance", "exits", "doors", "room_themes", "corridor_regions",
+                    "elevations"].iter() {
+            assert!(!plain.contains(&format!("\"{}\"", key)), "{}", plain);
+        }
+        assert!(Maze::from_json(&after.replacen(":-0001:", ":+0000:", 1)).is_err());
+        assert!(Maze::from_json(&plain.replace("mazegame 1", "mazegame 2")).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Ok(Format::Text), Format::from_path("a/b/level.txt"));
+        assert_eq!(Ok(Format::Json), Format::from_path("level.JSON"));
+        assert_eq!(Ok(Format::Binary), Format::from_path("level.mzb"));
+        assert!(Format::from_path("level").is_err());
+        assert!(Format::from_path("level.png").is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_save_and_load() {
+        let maze = sample_maze();
+        let mut exts = vec!["txt", "bin"];
+        if cfg!(feature = "serde") {
+            exts.push("json");
+        }
+        for ext in exts {
+            let path = std::env::temp_dir().join(format!("mazegame_save_test_{}.{}", std::process::id(), ext));
+            maze.save(&path).unwrap();
+            let copy = Maze::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(maze, copy);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Secret passages, like the secret doors of classic dungeon crawlers.  A
+//! secret passage is open for moving and solving, but is drawn as a wall
+//! until the game finds it and records it in a Discoveries set.
+//! `Square::secret` marks the passage on both of its sides.
+//!
+//! Solvers take secret passages like any other; solve_with_secrets() can
+//! leave them out, so hints don't give them away.
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze, Opening};
+
+/// The secret passages a player has found.  Each is held from both sides,
+/// so it can be looked up from either Square.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Discoveries {
+    found: BTreeSet<(Coord, u32)>,
+}
+
+impl Discoveries {
+    /// Creates an empty set of discoveries.
+    pub fn new() -> Discoveries {
+        Discoveries { found: BTreeSet::new() }
+    }
+
+    /// Returns whether the secret passage leaving coord in direction dir has
+    /// been found.
+    pub fn contains(&self, coord: Coord, dir: u32) -> bool {
+        self.found.contains(&(coord, dir))
+    }
+
+    /// Returns how many secret passages have been found.
+    pub fn len(&self) -> usize {
+        self.found.len() / 2
+    }
+
+    /// Returns whether no secret passages have been found.
+    pub fn is_empty(&self) -> bool {
+        self.found.is_empty()
+    }
+}
+
+impl Maze {
+    /// Opens a passage from (x, y) in direction dir and makes it secret.
+    /// The passage can be taken both ways.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(1, 2);
+    /// maze.carve_secret(0, 0, DIR_EAST).unwrap();
+    /// assert_eq!(vec![Coord::new(0, 0)], maze.open_neighbors(1, 0));
+    /// assert_eq!(Maze::new(1, 2).render_ascii(), maze.render_ascii());
+    /// ```
+    pub fn carve_secret(&mut self, x: u32, y: u32, dir: u32) -> Result<(), String> {
+        let (dest, back) = match (self.neighbor(x, y, dir), Direction::from_u32(dir)) {
+            (Some(c), Some(side)) => (c, u32::from(side.opposite())),
+            _ => return Err(format!("Can't carve secret {} from ({}, {})", crate::trace::dir_name(dir), x, y)),
+        };
+        let id = self.sq[self.get_offset(x, y)].id;
+        self.carve(x, y, dir, if id == constants::ID_UNCARVED { constants::ID_MAZE_PATH } else { id }, true)?;
+        let offset = self.get_offset(dest.x, dest.y);
+        if self.sq[offset].id == constants::ID_UNCARVED {
+            self.sq[offset].id = constants::ID_MAZE_PATH;
+        }
+        self.sq[offset].set_secret(back, true);
+        let offset = self.get_offset(x, y);
+        self.sq[offset].set_secret(dir, true);
+
+        gen_trace!("carve_secret x={} y={} dir={}", x, y, crate::trace::dir_name(dir));
+        Ok(())
+    }
+
+    /// Returns every secret passage once, as the Square it leads out of and
+    /// its direction, in row-major order of whichever end comes first.
+    pub fn secret_passages(&self) -> Vec<Opening> {
+        let mut passages = Vec::new();
+        for (i, sq) in self.sq.iter().enumerate() {
+            let coord = self.get_coord(i);
+            for &side in Direction::ALL.iter().filter(|&&side| sq.is_secret(side.into())) {
+                let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+                let first = self.neighbor(coord.x, coord.y, dir)
+                    .is_none_or(|n| (i, dir) <= (self.get_offset(n.x, n.y), back));
+                if first {
+                    passages.push(Opening { coord, dir });
+                }
+            }
+        }
+        passages
+    }
+
+    /// Records the secret passage leaving (x, y) in direction dir as found.
+    /// Returns whether it was new.  Fails if there's no secret passage
+    /// there.
+    pub fn discover_secret(&self, discoveries: &mut Discoveries, x: u32, y: u32, dir: u32) -> Result<bool, MazeError> {
+        if !self.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+        let (n, back) = match (self.neighbor(x, y, dir), Direction::from_u32(dir)) {
+            (Some(n), Some(side)) if self.sq[self.get_offset(x, y)].is_secret(dir) => (n, side.opposite()),
+            _ => return Err(MazeError::InvalidArgument(format!("there's no secret passage {} of ({}, {})",
+                                                               crate::trace::dir_name(dir), x, y))),
+        };
+        discoveries.found.insert((n, back.into()));
+        Ok(discoveries.found.insert((Coord::new(x, y), dir)))
+    }
+
+    /// Renders the maze like render_ascii(), drawing the secret passages in
+    /// discoveries as open.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::secret::Discoveries;
+    ///
+    /// let mut maze = Maze::new(1, 2);
+    /// maze.carve_secret(0, 0, DIR_EAST).unwrap();
+    /// let mut found = Discoveries::new();
+    /// assert_eq!(maze.render_ascii(), maze.render_ascii_with_discoveries(&found));
+    /// maze.discover_secret(&mut found, 1, 0, DIR_WEST).unwrap();
+    /// assert_eq!("XXXXX\nX   X\nXXXXX\n", maze.render_ascii_with_discoveries(&found));
+    /// ```
+    pub fn render_ascii_with_discoveries(&self, discoveries: &Discoveries) -> String {
+        self.render_ascii_marked(&[], '.', discoveries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A perfect maze with a secret passage across the wall that saves the
+    // most steps, and the two Squares either side of it
+    fn shortcut_maze() -> (Maze, Coord, Coord) {
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_seeded(473).unwrap();
+        let mut best = None;
+        for y in 0..8 {
+            for x in 0..8 {
+                for &dir in [constants::DIR_EAST, constants::DIR_SOUTH].iter() {
+                    let n = match maze.neighbor(x, y, dir) {
+                        Some(n) if maze.sq[maze.get_offset(x, y)].is_wall_present(dir) => n,
+                        _ => continue,
+                    };
+                    let len = maze.solve(Coord::new(x, y), n).unwrap().len();
+                    if best.is_none_or(|(best_len, _, _)| len > best_len) {
+                        best = Some((len, Coord::new(x, y), dir));
+                    }
+                }
+            }
+        }
+        let (_, a, dir) = best.unwrap();
+        maze.carve_secret(a.x, a.y, dir).unwrap();
+        let b = maze.neighbor(a.x, a.y, dir).unwrap();
+        (maze, a, b)
+    }
+
+    #[test]
+    fn test_solving() {
+        let (maze, a, b) = shortcut_maze();
+        assert_eq!(vec![a, b], maze.solve(a, b).unwrap());
+        assert_eq!(vec![a, b], maze.solve_with_secrets(a, b, true).unwrap());
+        let hint = maze.solve_with_secrets(a, b, false).unwrap();
+        assert!(hint.len() > 10, "{:?}", hint);
+        assert_eq!((a, b), (hint[0], hint[hint.len() - 1]));
+        assert!(maze.validate().is_ok());
+        assert!(maze.walls_consistent());
+    }
+
+    #[test]
+    fn test_rendering() {
+        let (maze, a, b) = shortcut_maze();
+        let mut plain = maze.clone();
+        let side = Direction::ALL.iter().cloned().find(|&d| maze.neighbor(a.x, a.y, d.into()) == Some(b)).unwrap();
+        let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+        let (i, j) = (maze.get_offset(a.x, a.y), maze.get_offset(b.x, b.y));
+        plain.sq[i].build_wall(dir);
+        plain.sq[j].build_wall(back);
+        assert_eq!(plain.render_ascii(), maze.render_ascii());
+
+        let mut found = Discoveries::new();
+        assert_eq!(plain.render_ascii(), maze.render_ascii_with_discoveries(&found));
+        assert!(maze.discover_secret(&mut found, b.x, b.y, back).unwrap());
+        assert!(!maze.discover_secret(&mut found, a.x, a.y, dir).unwrap());
+        assert_eq!(1, found.len());
+        let shown = maze.render_ascii_with_discoveries(&found);
+        assert_ne!(plain.render_ascii(), shown);
+        let mut open = maze.clone();
+        open.sq.iter_mut().for_each(|sq| sq.secret = 0);
+        assert_eq!(open.render_ascii(), shown);
+
+        assert!(maze.discover_secret(&mut found, 0, 0, constants::DIR_NORTH).is_err());
+        assert_eq!(Err(MazeError::OutOfBounds(8, 0)), maze.discover_secret(&mut found, 8, 0, dir));
+    }
+
+    #[test]
+    fn test_passages() {
+        let mut maze = Maze::new(3, 3);
+        assert!(maze.carve_secret(0, 0, constants::DIR_NORTH).is_err());
+        maze.carve_secret(1, 1, constants::DIR_WEST).unwrap();
+        maze.carve_secret(2, 2, constants::DIR_NORTH).unwrap();
+        assert_eq!(vec![Opening { coord: Coord::new(0, 1), dir: constants::DIR_EAST },
+                        Opening { coord: Coord::new(2, 1), dir: constants::DIR_SOUTH }],
+                   maze.secret_passages());
+        assert!(maze.validate().is_ok());
+
+        // Carving over a secret passage makes it an ordinary one, and
+        // building a wall across it removes it
+        maze.carve(0, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.sq[5].build_wall(constants::DIR_SOUTH);
+        maze.sq[8].build_wall(constants::DIR_NORTH);
+        assert!(maze.secret_passages().is_empty());
+        assert!(maze.validate().is_ok());
+
+        // Secrets on one side only, or on a wall, aren't consistent
+        maze.carve_secret(0, 0, constants::DIR_SOUTH).unwrap();
+        maze.sq[0].set_secret(constants::DIR_SOUTH, false);
+        assert!(maze.validate().is_err());
+        maze.sq[0].set_secret(constants::DIR_SOUTH, true);
+        assert!(maze.validate().is_ok());
+        maze.sq[0].set_secret(constants::DIR_EAST, true);
+        assert!(maze.validate().is_err());
+    }
+}
@@ -0,0 +1,330 @@
+//! Reshaping a generated maze so its solution goes through rooms, or keeps
+//! out of them.  Walls are changed one at a time, greedily: each round tries
+//! closing every wall on the current solution that isn't the only way
+//! across, and opening walls between carved Squares, and keeps whichever
+//! change brings the solution closest to what's wanted.  Closed walls are
+//! always on a loop, so every carved Square stays reachable.
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::morph::MorphStep;
+use crate::maze::{Coord, Maze};
+
+/// What the solution should do about rooms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathPreference {
+    /// Pass through at least min_rooms different rooms.
+    ThroughRooms { min_rooms: u32 },
+    /// Not enter any room, other than one the entrance or exit is in.
+    AroundRooms,
+}
+
+/// How to shape a maze's solution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathShaping {
+    pub preference: PathPreference,
+    /// The most walls that can be opened or closed.
+    pub max_changes: u32,
+}
+
+/// What shape_path() did.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapingReport {
+    /// The walls changed, in the order they were changed.
+    pub changes: Vec<MorphStep>,
+    /// Whether the solution now follows the preference.
+    pub satisfied: bool,
+    /// The shortest path from the entrance to the exit afterward.
+    pub solution: Vec<Coord>,
+}
+
+// How far from its goal a maze is, compared as (primary, secondary).
+// Higher is better.
+type Score = (i64, i64);
+
+// The secondary score when nothing could get closer
+const FAR: i64 = i64::MAX / 4;
+
+impl Maze {
+    /// Changes up to shaping.max_changes walls so the shortest path from
+    /// `from` to `to` follows shaping.preference.  Stops early once it
+    /// does, or when no single wall change helps.  Only walls between
+    /// carved Squares are opened, and a wall is only closed if there's
+    /// another way around it.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::shaping::{PathPreference, PathShaping};
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(16, 16);
+    /// maze.generate_seeded((4, 3, 4, 3, 4), 3).unwrap();
+    /// maze.connect_regions();
+    /// let shaping = PathShaping { preference: PathPreference::ThroughRooms { min_rooms: 1 }, max_changes: 10 };
+    /// let report = maze.shape_path(Coord::new(0, 0), Coord::new(15, 15), &shaping).unwrap();
+    /// assert!(report.changes.len() <= 10);
+    /// assert_eq!(report.solution, maze.solve(Coord::new(0, 0), Coord::new(15, 15)).unwrap());
+    /// ```
+    pub fn shape_path(&mut self, from: Coord, to: Coord, shaping: &PathShaping) -> Result<ShapingReport, MazeError> {
+        for c in [from, to].iter() {
+            if !self.in_bounds(c.x, c.y) {
+                return Err(MazeError::OutOfBounds(c.x, c.y));
+            }
+        }
+        let preference = shaping.preference;
+        let mut score = self.path_score(from, to, preference).ok_or(MazeError::Unreachable(from, to))?;
+        let mut changes = Vec::new();
+
+        while !self.path_satisfies(from, to, preference) && (changes.len() as u32) < shaping.max_changes {
+            let mut best: Option<(Score, MorphStep)> = None;
+            for step in self.shaping_candidates(from, to, preference) {
+                self.apply_morph_step(&step)?;
+                let (x, y) = (step.coord.x, step.coord.y);
+                // Closing a wall must leave a way around it
+                let keeps_loop = step.open || self.neighbor(x, y, step.dir)
+                    .is_some_and(|n| self.solve(step.coord, n).is_some());
+                let candidate = if keeps_loop { self.path_score(from, to, preference) } else { None };
+                self.apply_morph_step(&MorphStep { open: !step.open, ..step })?;
+                if let Some(candidate) = candidate {
+                    if candidate > score && best.is_none_or(|(b, _)| candidate > b) {
+                        best = Some((candidate, step));
+                    }
+                }
+            }
+            match best {
+                Some((better, step)) => {
+                    self.apply_morph_step(&step)?;
+                    changes.push(step);
+                    score = better;
+                }
+                None => break,
+            }
+        }
+
+        let solution = self.solve(from, to).ok_or(MazeError::Unreachable(from, to))?;
+        let satisfied = self.path_satisfies(from, to, preference);
+        Ok(ShapingReport { changes, satisfied, solution })
+    }
+
+    //
+    // Internal - whether the shortest path from `from` to `to` follows
+    // preference.
+    //
+    fn path_satisfies(&self, from: Coord, to: Coord, preference: PathPreference) -> bool {
+        let path = match self.solve(from, to) {
+            Some(path) => path,
+            None => return false,
+        };
+        match preference {
+            PathPreference::ThroughRooms { min_rooms } => self.rooms_on(&path).len() as u32 >= min_rooms,
+            PathPreference::AroundRooms => self.room_squares_between(&path) == 0,
+        }
+    }
+
+    //
+    // Internal - scores the maze for preference, or None if `to` can't be
+    // reached.  The secondary score is how many steps longer the best
+    // path that would help is than the solution, negated, so changes that
+    // bring it closer count as progress.
+    //
+    fn path_score(&self, from: Coord, to: Coord, preference: PathPreference) -> Option<Score> {
+        let path = self.solve(from, to)?;
+        let length = path.len() as i64 - 1;
+        match preference {
+            PathPreference::ThroughRooms { min_rooms } => {
+                let visited = self.rooms_on(&path);
+                let (d_from, d_to) = (self.distances(from), self.distances(to));
+                // The cheapest detour through a room the solution misses
+                let mut detour = FAR;
+                for (i, sq) in self.sq.iter().enumerate() {
+                    if !sq.is_part_of_room() || visited.contains(&sq.id) {
+                        continue;
+                    }
+                    if let (Some(a), Some(b)) = (d_from[i], d_to[i]) {
+                        detour = detour.min(a as i64 + b as i64 - length);
+                    }
+                }
+                Some(((visited.len() as i64).min(min_rooms as i64), -detour))
+            }
+            PathPreference::AroundRooms => {
+                let avoiding = self.room_free_distance(from, to).map_or(FAR, |d| d as i64 - length);
+                Some((-(self.room_squares_between(&path) as i64), -avoiding))
+            }
+        }
+    }
+
+    //
+    // Internal - the wall changes worth trying: closing each wall on the
+    // solution, and opening walls between carved Squares that could make a
+    // better path.
+    //
+    fn shaping_candidates(&self, from: Coord, to: Coord, preference: PathPreference) -> Vec<MorphStep> {
+        let mut steps = Vec::new();
+        let path = match self.solve(from, to) {
+            Some(path) => path,
+            None => return steps,
+        };
+        for w in path.windows(2) {
+            if let Some(dir) = (0..constants::NUM_DIRECTIONS).find(|&d| self.neighbor(w[0].x, w[0].y, d) == Some(w[1])) {
+                steps.push(MorphStep { coord: w[0], dir, open: false });
+            }
+        }
+
+        let visited = self.rooms_on(&path);
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let sq = &self.sq[self.get_offset(x, y)];
+                if !sq.is_carved() {
+                    continue;
+                }
+                // Each wall once, from its west or north side
+                for &dir in [constants::DIR_EAST, constants::DIR_SOUTH].iter() {
+                    let other = match self.neighbor(x, y, dir) {
+                        Some(n) => &self.sq[self.get_offset(n.x, n.y)],
+                        None => continue,
+                    };
+                    if !sq.is_wall_present(dir) || !other.is_carved() {
+                        continue;
+                    }
+                    let useful = match preference {
+                        PathPreference::ThroughRooms { .. } => [sq, other].iter()
+                            .any(|s| s.is_part_of_room() && !visited.contains(&s.id)),
+                        PathPreference::AroundRooms => !sq.is_part_of_room() && !other.is_part_of_room(),
+                    };
+                    if useful {
+                        steps.push(MorphStep { coord: Coord::new(x, y), dir, open: true });
+                    }
+                }
+            }
+        }
+        steps
+    }
+
+    //
+    // Internal - the ids of the rooms path goes through.
+    //
+    fn rooms_on(&self, path: &[Coord]) -> BTreeSet<i32> {
+        path.iter()
+            .map(|c| &self.sq[self.get_offset(c.x, c.y)])
+            .filter(|sq| sq.is_part_of_room())
+            .map(|sq| sq.id)
+            .collect()
+    }
+
+    //
+    // Internal - the room Squares on path, not counting its ends.
+    //
+    fn room_squares_between(&self, path: &[Coord]) -> u32 {
+        if path.len() < 3 {
+            return 0;
+        }
+        path[1..path.len() - 1].iter()
+            .filter(|c| self.sq[self.get_offset(c.x, c.y)].is_part_of_room())
+            .count() as u32
+    }
+
+    //
+    // Internal - the number of steps from `from` to `to` without passing
+    // through a room Square in between, or None if there's no such way.
+    //
+    fn room_free_distance(&self, from: Coord, to: Coord) -> Option<u32> {
+        let mut dist = vec![None; self.sq.len()];
+        let mut queue = VecDeque::new();
+        dist[self.get_offset(from.x, from.y)] = Some(0);
+        queue.push_back((from, 0));
+        while let Some((cur, d)) = queue.pop_front() {
+            if cur == to {
+                return Some(d);
+            }
+            for next in self.open_neighbors(cur.x, cur.y) {
+                let offset = self.get_offset(next.x, next.y);
+                if dist[offset].is_none() && (next == to || !self.sq[offset].is_part_of_room()) {
+                    dist[offset] = Some(d + 1);
+                    queue.push_back((next, d + 1));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+
+    fn dungeon(seed: u64) -> Maze {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_seeded((6, 3, 4, 3, 4), seed).unwrap();
+        maze.connect_regions_with_rng(&mut rng::from_seed(seed));
+        maze
+    }
+
+    fn all_connected(maze: &Maze) -> bool {
+        let start = maze.sq.iter().position(|sq| sq.is_carved()).unwrap();
+        let dist = maze.distances(maze.coord_of(start).unwrap());
+        maze.sq.iter().zip(dist.iter()).all(|(sq, d)| !sq.is_carved() || d.is_some())
+    }
+
+    #[test]
+    fn test_through_rooms() {
+        let (from, to) = (Coord::new(0, 0), Coord::new(19, 19));
+        let original = dungeon(449);
+        let before = original.rooms_on(&original.solve(from, to).unwrap()).len();
+
+        let mut maze = original.clone();
+        let min_rooms = before as u32 + 2;
+        let shaping = PathShaping { preference: PathPreference::ThroughRooms { min_rooms }, max_changes: 15 };
+        let report = maze.shape_path(from, to, &shaping).unwrap();
+        assert!(report.satisfied);
+        assert!(maze.rooms_on(&report.solution).len() as u32 >= min_rooms);
+        assert!(!report.changes.is_empty() && report.changes.len() <= 15);
+        // Each change is one wall, seen from both sides
+        assert!(original.diff(&maze).unwrap().changed_walls() as usize <= report.changes.len() * 2);
+        assert!(all_connected(&maze));
+        assert!(maze.walls_consistent());
+    }
+
+    // A dungeon whose solution has been pulled through two rooms
+    fn roomy_dungeon() -> Maze {
+        let mut maze = dungeon(448);
+        let shaping = PathShaping { preference: PathPreference::ThroughRooms { min_rooms: 2 }, max_changes: 15 };
+        assert!(maze.shape_path(Coord::new(0, 0), Coord::new(19, 19), &shaping).unwrap().satisfied);
+        maze
+    }
+
+    #[test]
+    fn test_around_rooms() {
+        let (from, to) = (Coord::new(0, 0), Coord::new(19, 19));
+        let original = roomy_dungeon();
+        assert!(original.room_squares_between(&original.solve(from, to).unwrap()) > 0);
+
+        let mut maze = original.clone();
+        let shaping = PathShaping { preference: PathPreference::AroundRooms, max_changes: 10 };
+        let report = maze.shape_path(from, to, &shaping).unwrap();
+        assert!(report.satisfied);
+        assert_eq!(0, maze.room_squares_between(&report.solution));
+        assert!(!report.changes.is_empty() && report.changes.len() <= 10);
+        assert!(original.diff(&maze).unwrap().changed_walls() as usize <= report.changes.len() * 2);
+        assert!(all_connected(&maze));
+    }
+
+    #[test]
+    fn test_change_limit() {
+        let (from, to) = (Coord::new(0, 0), Coord::new(19, 19));
+        let original = roomy_dungeon();
+        let mut maze = original.clone();
+        let shaping = PathShaping { preference: PathPreference::AroundRooms, max_changes: 0 };
+        let report = maze.shape_path(from, to, &shaping).unwrap();
+        assert!(!report.satisfied);
+        assert!(report.changes.is_empty());
+        assert_eq!(original, maze);
+
+        assert_eq!(Err(MazeError::OutOfBounds(20, 0)), maze.shape_path(Coord::new(20, 0), to, &shaping));
+        let mut walled = Maze::new(3, 3);
+        assert_eq!(Err(MazeError::Unreachable(from, Coord::new(2, 2))),
+                   walled.shape_path(from, Coord::new(2, 2), &shaping));
+    }
+}
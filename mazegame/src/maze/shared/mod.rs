@@ -0,0 +1,243 @@
+//! Sharing a finished Maze between threads, enabled with the `std` feature.
+//!
+//! Every query on a Maze takes `&self` and nothing inside it changes behind
+//! a shared reference, so a Maze can be read from any number of threads at
+//! once.  into_shared() wraps one in an Arc<MazeShared>, which hands out
+//! only `&Maze`, so worker threads can path find while the main thread
+//! draws.  Changing a shared maze goes through a MazeEditor, which edits a
+//! copy and swaps the new version in; threads still holding the old Arc
+//! carry on with it until they ask for the current one.
+use std::ops::Deref;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::vec::Vec;
+
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+// Fails to compile if a Maze or MazeShared ever stops being safe to share
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Maze>();
+    assert_send_sync::<MazeShared>();
+    assert_send_sync::<MazeEditor>();
+};
+
+/// A Maze that can only be read, for sharing between threads.  It derefs
+/// to the Maze, so every query and the solver work on it as usual.
+#[derive(Debug, PartialEq)]
+pub struct MazeShared {
+    maze: Maze,
+}
+
+impl Deref for MazeShared {
+    type Target = Maze;
+
+    fn deref(&self) -> &Maze {
+        &self.maze
+    }
+}
+
+impl MazeShared {
+    /// Solves every (from, to) pair in queries like Maze::solve(), spread
+    /// over as many threads as the machine has cores.  The paths come back
+    /// in the order of the queries.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(12, 12);
+    /// maze.generate_perfect_seeded(497).unwrap();
+    /// let queries = [(Coord::new(0, 0), Coord::new(11, 11)), (Coord::new(3, 4), Coord::new(12, 0))];
+    /// let expected: Vec<_> = queries.iter().map(|&(from, to)| maze.solve(from, to)).collect();
+    /// assert_eq!(expected, maze.into_shared().solve_par(&queries));
+    /// ```
+    pub fn solve_par(&self, queries: &[(Coord, Coord)]) -> Vec<Option<Vec<Coord>>> {
+        let workers = thread::available_parallelism().map_or(1, |n| n.get());
+        self.solve_with_threads(queries, workers)
+    }
+
+    /// Solves queries like solve_par(), on at most workers threads.
+    pub fn solve_with_threads(&self, queries: &[(Coord, Coord)], workers: usize) -> Vec<Option<Vec<Coord>>> {
+        if queries.is_empty() {
+            return Vec::new();
+        }
+        let chunk = queries.len().div_ceil(workers.max(1));
+        thread::scope(|scope| {
+            let handles: Vec<_> = queries.chunks(chunk)
+                .map(|part| scope.spawn(move || {
+                    part.iter().map(|&(from, to)| self.maze.solve(from, to)).collect::<Vec<_>>()
+                }))
+                .collect();
+            handles.into_iter().flat_map(|h| h.join().expect("a solver thread panicked")).collect()
+        })
+    }
+}
+
+impl Maze {
+    /// Gives up the Maze for reading from many threads at once.
+    pub fn into_shared(self) -> Arc<MazeShared> {
+        Arc::new(MazeShared { maze: self })
+    }
+}
+
+/// Keeps the current version of a shared Maze and makes changes to it.
+/// Each change is made to a copy, which then replaces the current version
+/// in one step and is sent to every subscriber, so readers never see a
+/// half-made change.
+#[derive(Debug)]
+pub struct MazeEditor {
+    current: Mutex<Arc<MazeShared>>,
+    // Held for the whole of an edit, so two edits can't both start from the
+    // same version and lose one another's changes
+    editing: Mutex<()>,
+    subscribers: Mutex<Vec<Sender<Arc<MazeShared>>>>,
+}
+
+impl MazeEditor {
+    /// Starts sharing maze.
+    pub fn new(maze: Maze) -> MazeEditor {
+        MazeEditor {
+            current: Mutex::new(maze.into_shared()),
+            editing: Mutex::new(()),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the current version of the Maze.
+    pub fn current(&self) -> Arc<MazeShared> {
+        lock(&self.current).clone()
+    }
+
+    /// Returns a channel that receives every new version of the Maze as
+    /// it's swapped in.  Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<Arc<MazeShared>> {
+        let (sender, receiver) = mpsc::channel();
+        lock(&self.subscribers).push(sender);
+        receiver
+    }
+
+    /// Runs change on a copy of the current Maze and, if it succeeds, makes
+    /// the copy current and tells the subscribers.  If it fails, nothing is
+    /// swapped in.  Returns what change returned.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::shared::MazeEditor;
+    ///
+    /// let editor = MazeEditor::new(Maze::new(4, 4));
+    /// let updates = editor.subscribe();
+    /// let before = editor.current();
+    /// editor.edit(|maze| maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).map_err(MazeError::InvalidArgument))
+    ///       .unwrap();
+    /// assert!(before.open_neighbors(0, 0).is_empty());
+    /// assert_eq!(1, updates.recv().unwrap().open_neighbors(0, 0).len());
+    /// assert_eq!(1, editor.current().open_neighbors(0, 0).len());
+    /// ```
+    pub fn edit<T, F>(&self, change: F) -> Result<T, MazeError>
+        where F: FnOnce(&mut Maze) -> Result<T, MazeError> {
+        let _editing = lock(&self.editing);
+        let mut maze = self.current().maze.clone();
+        let result = change(&mut maze)?;
+        let shared = maze.into_shared();
+        *lock(&self.current) = shared.clone();
+        lock(&self.subscribers).retain(|s| s.send(shared.clone()).is_ok());
+        Ok(result)
+    }
+}
+
+//
+// Internal - locks a mutex, carrying on if another thread panicked while
+// holding it, since everything behind these locks is replaced whole.
+//
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, ID_MAZE_PATH};
+
+    fn queries(maze: &Maze) -> Vec<(Coord, Coord)> {
+        (0..200u32).map(|i| {
+            let from = maze.get_coord((i as usize * 37) % maze.sq.len());
+            let to = maze.get_coord((i as usize * 101 + 13) % maze.sq.len());
+            (from, to)
+        }).collect()
+    }
+
+    #[test]
+    fn test_eight_threads() {
+        let mut maze = Maze::new(30, 30);
+        maze.generate_perfect_seeded(497).unwrap();
+        let queries = queries(&maze);
+        let expected: Vec<_> = queries.iter().map(|&(from, to)| maze.solve(from, to)).collect();
+        assert!(expected.iter().all(|p| p.is_some()));
+
+        let shared = maze.into_shared();
+        assert_eq!(expected, shared.solve_with_threads(&queries, 8));
+        assert_eq!(expected, shared.solve_par(&queries));
+        assert_eq!(expected, shared.solve_with_threads(&queries, 1000));
+        assert!(shared.solve_par(&[]).is_empty());
+
+        // Eight threads each holding the Arc and solving everything at once
+        let handles: Vec<_> = (0..8).map(|_| {
+            let shared = Arc::clone(&shared);
+            let queries = queries.clone();
+            thread::spawn(move || queries.iter().map(|&(from, to)| shared.solve(from, to)).collect::<Vec<_>>())
+        }).collect();
+        for handle in handles {
+            assert_eq!(expected, handle.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_editor() {
+        let mut maze = Maze::new(6, 6);
+        maze.generate_perfect_seeded(497).unwrap();
+        let editor = MazeEditor::new(maze.clone());
+        let updates = editor.subscribe();
+        let dropped = editor.subscribe();
+        drop(dropped);
+
+        // A failed edit changes nothing and tells nobody
+        let first = editor.current();
+        assert!(editor.edit(|maze| maze.carve(5, 0, DIR_EAST, ID_MAZE_PATH, false)
+                                       .map_err(MazeError::InvalidArgument)).is_err());
+        assert!(Arc::ptr_eq(&first, &editor.current()));
+        assert!(updates.try_recv().is_err());
+
+        // Readers holding the old version keep it while edits are made on
+        // other threads
+        let reader = {
+            let old = editor.current();
+            thread::spawn(move || old.solve(Coord::new(0, 0), Coord::new(5, 5)))
+        };
+        let editor = Arc::new(editor);
+        let writers: Vec<_> = (0..4u32).map(|y| {
+            let editor = Arc::clone(&editor);
+            thread::spawn(move || {
+                editor.edit(|maze| {
+                    maze.sq[(y * 6) as usize].build_wall(DIR_EAST);
+                    maze.sq[(y * 6 + 1) as usize].build_wall(crate::constants::DIR_WEST);
+                    Ok(y)
+                }).unwrap()
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(maze.solve(Coord::new(0, 0), Coord::new(5, 5)), reader.join().unwrap());
+        assert_eq!(4, updates.try_iter().count());
+        // None of the four edits was lost
+        let now = editor.current();
+        for y in 0..4 {
+            assert!(now.sq[(y * 6) as usize].is_wall_present(DIR_EAST));
+        }
+        assert_eq!(*first, MazeShared { maze });
+    }
+}
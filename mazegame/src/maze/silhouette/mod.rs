@@ -0,0 +1,248 @@
+//! Negative-space mazes, where the Squares left solid draw a picture.  The
+//! Squares inside a mask are never carved, so a heart or a logo shows up
+//! as a solid shape when the maze is drawn, and a single perfect maze
+//! fills everything around it.  The shape can pinch the grid into narrow
+//! channels, which the maze winds through; any pocket the shape walls off
+//! completely can't be reached, so it's left solid as part of the picture.
+use alloc::collections::VecDeque;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants::NUM_DIRECTIONS;
+use crate::error::MazeError;
+use crate::maze::mask::Mask;
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// Settings for Maze::generate_with_silhouette().
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SilhouetteConfig {
+    pub seed: u64,
+    /// How many steps the entrance and exit must be from the nearest
+    /// Square of the shape.  1 only keeps them off the shape itself; the
+    /// default of 2 keeps them from touching it too.
+    pub clearance: u32,
+}
+
+impl Default for SilhouetteConfig {
+    fn default() -> SilhouetteConfig {
+        SilhouetteConfig { seed: 0, clearance: 2 }
+    }
+}
+
+impl Maze {
+    /// Replaces the Maze with a perfect maze around the shape in mask,
+    /// which must be the same size as the Maze.  Squares inside the mask
+    /// stay solid, and the entrance and exit go on the border away from
+    /// the shape, as far apart along the maze as they can be.  Returns the
+    /// number of Squares outside the shape that it walled off and that were
+    /// left solid too.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::mask::Mask;
+    /// use mazegame::maze::silhouette::SilhouetteConfig;
+    ///
+    /// let heart = Mask::from_ascii(".........\n\
+    ///                               ..##.##..\n\
+    ///                               .#######.\n\
+    ///                               ..#####..\n\
+    ///                               ...###...\n\
+    ///                               ....#....\n\
+    ///                               .........\n").unwrap();
+    /// let mut maze = Maze::new(7, 9);
+    /// assert_eq!(0, maze.generate_with_silhouette(&heart, SilhouetteConfig::default()).unwrap());
+    /// assert!(!maze.sq[3 * 9 + 4].is_carved());
+    /// let (entrance, exit) = (maze.get_entrance().unwrap(), maze.get_exit().unwrap());
+    /// assert!(maze.solve(entrance.coord, exit.coord).is_some());
+    /// ```
+    pub fn generate_with_silhouette(&mut self, mask: &Mask, config: SilhouetteConfig) -> Result<u32, MazeError> {
+        self.generate_with_silhouette_rng(mask, config.clearance, &mut rng::from_seed(config.seed))
+    }
+
+    /// Generates a maze around the shape in mask like
+    /// generate_with_silhouette(), using the supplied random number
+    /// generator.
+    pub fn generate_with_silhouette_rng<R: Rng>(&mut self, mask: &Mask, clearance: u32, rng: &mut R)
+                                                -> Result<u32, MazeError> {
+        if (mask.get_cols(), mask.get_rows()) != (self.cols, self.rows) {
+            return Err(MazeError::InvalidArgument(format!("A {}x{} mask doesn't fit a {}x{} maze",
+                mask.get_cols(), mask.get_rows(), self.cols, self.rows)));
+        }
+        let solid = |c: Coord| mask.is_inside(c.x, c.y);
+        let region = self.largest_open_region(&solid);
+        if region.len() < 2 {
+            return Err(MazeError::ConstraintUnsatisfiable("the shape leaves no room for a maze".to_string()));
+        }
+        let mut carve = Mask::new(self.rows, self.cols);
+        for i in 0..self.sq.len() {
+            let c = self.get_coord(i);
+            carve.set_inside(c.x, c.y, false)?;
+        }
+        for &c in region.iter() {
+            carve.set_inside(c.x, c.y, true)?;
+        }
+
+        self.reset();
+        self.generate_masked_with_rng(&carve, rng).map_err(MazeError::InvalidArgument)?;
+
+        let from_shape = self.steps_from(&solid);
+        let doors: Vec<(Coord, u32)> = region.iter()
+            .filter(|c| from_shape[self.get_offset(c.x, c.y)].is_none_or(|d| d >= clearance))
+            .filter_map(|&c| (0..NUM_DIRECTIONS).find(|&d| self.neighbor(c.x, c.y, d).is_none()).map(|d| (c, d)))
+            .collect();
+        if doors.len() < 2 {
+            return Err(MazeError::ConstraintUnsatisfiable(format!(
+                "fewer than two border Squares are {} steps from the shape", clearance)));
+        }
+        let (entrance, in_dir) = doors[rng.gen_range(0, doors.len())];
+        let distances = self.distances(entrance);
+        let &(exit, out_dir) = doors.iter().filter(|&&(c, _)| c != entrance)
+            .max_by_key(|(c, _)| distances[self.get_offset(c.x, c.y)])
+            .expect("there are at least two doors");
+        self.set_entrance(entrance.x, entrance.y, in_dir)?;
+        self.set_exit(exit.x, exit.y, out_dir)?;
+        let filled = (self.sq.len() - region.len()) as u32 - mask.count();
+        gen_debug!("silhouette region={} filled={} entrance={},{} exit={},{}", region.len(), filled,
+                   entrance.x, entrance.y, exit.x, exit.y);
+        Ok(filled)
+    }
+
+    //
+    // Internal - returns the largest patch of Squares that aren't solid,
+    // joined by neighbor() so wrapped edges count.  Ties go to the patch
+    // found first in row-major order.
+    //
+    fn largest_open_region<F: Fn(Coord) -> bool>(&self, solid: &F) -> Vec<Coord> {
+        let mut seen = vec![false; self.sq.len()];
+        let mut largest = Vec::new();
+        for start in 0..self.sq.len() {
+            let c = self.get_coord(start);
+            if seen[start] || solid(c) {
+                continue;
+            }
+            seen[start] = true;
+            let mut region = Vec::new();
+            let mut stack = vec![c];
+            while let Some(c) = stack.pop() {
+                region.push(c);
+                for dir in 0..NUM_DIRECTIONS {
+                    if let Some(n) = self.neighbor(c.x, c.y, dir) {
+                        let offset = self.get_offset(n.x, n.y);
+                        if !seen[offset] && !solid(n) {
+                            seen[offset] = true;
+                            stack.push(n);
+                        }
+                    }
+                }
+            }
+            if region.len() > largest.len() {
+                largest = region;
+            }
+        }
+        largest
+    }
+
+    //
+    // Internal - returns how many steps across the grid, ignoring walls,
+    // each Square is from the nearest solid one.  None everywhere if
+    // nothing is solid.
+    //
+    fn steps_from<F: Fn(Coord) -> bool>(&self, solid: &F) -> Vec<Option<u32>> {
+        let mut steps = vec![None; self.sq.len()];
+        let mut queue = VecDeque::new();
+        for (i, step) in steps.iter_mut().enumerate() {
+            let c = self.get_coord(i);
+            if solid(c) {
+                *step = Some(0);
+                queue.push_back((c, 0));
+            }
+        }
+        while let Some((c, d)) = queue.pop_front() {
+            for dir in 0..NUM_DIRECTIONS {
+                if let Some(n) = self.neighbor(c.x, c.y, dir) {
+                    let offset = self.get_offset(n.x, n.y);
+                    if steps[offset].is_none() {
+                        steps[offset] = Some(d + 1);
+                        queue.push_back((n, d + 1));
+                    }
+                }
+            }
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLUS: &str = ".........\n\
+                        .........\n\
+                        ....#....\n\
+                        ....#....\n\
+                        ..#####..\n\
+                        ....#....\n\
+                        ....#....\n\
+                        .........\n\
+                        .........\n";
+
+    #[test]
+    fn test_plus() {
+        let mask = Mask::from_ascii(PLUS).unwrap();
+        for seed in 0..10 {
+            let mut maze = Maze::new(9, 9);
+            let config = SilhouetteConfig { seed, ..SilhouetteConfig::default() };
+            assert_eq!(0, maze.generate_with_silhouette(&mask, config).unwrap());
+            assert!(maze.walls_consistent());
+
+            // Nothing inside the shape is carved, and everything outside
+            // it is one perfect maze
+            let outside: Vec<Coord> = (0..maze.sq.len()).map(|i| maze.get_coord(i))
+                                                         .filter(|c| !mask.is_inside(c.x, c.y)).collect();
+            for c in (0..maze.sq.len()).map(|i| maze.get_coord(i)).filter(|c| mask.is_inside(c.x, c.y)) {
+                assert!(!maze.sq[maze.get_offset(c.x, c.y)].is_carved(), "({}, {}) is carved", c.x, c.y);
+                assert!(maze.open_neighbors(c.x, c.y).is_empty());
+            }
+            let distances = maze.distances(outside[0]);
+            assert!(outside.iter().all(|c| distances[maze.get_offset(c.x, c.y)].is_some()));
+            let passages: usize = outside.iter().map(|c| maze.open_neighbors(c.x, c.y).len()).sum();
+            assert_eq!(outside.len() - 1, passages / 2);
+
+            // The entrance and exit keep clear of the shape, and the maze
+            // solves between them
+            let (entrance, exit) = (maze.get_entrance().unwrap().coord, maze.get_exit().unwrap().coord);
+            assert_ne!(entrance, exit);
+            for c in [entrance, exit].iter() {
+                assert!(!(0..maze.sq.len()).map(|i| maze.get_coord(i)).any(|s| mask.is_inside(s.x, s.y)
+                        && s.x.abs_diff(c.x) + s.y.abs_diff(c.y) < 2));
+            }
+            assert!(maze.solve(entrance, exit).is_some());
+        }
+    }
+
+    #[test]
+    fn test_walled_off_pocket() {
+        // The box walls off two Squares and squeezes the rest into a
+        // channel two Squares wide down the right
+        let mask = Mask::from_ascii("####..\n\
+                                     #..#..\n\
+                                     ####..\n").unwrap();
+        let mut maze = Maze::new(3, 6);
+        assert_eq!(2, maze.generate_with_silhouette(&mask, SilhouetteConfig { seed: 498, clearance: 2 }).unwrap());
+        assert!(!maze.sq[maze.get_offset(1, 1)].is_carved());
+        assert!(!maze.sq[maze.get_offset(2, 1)].is_carved());
+        let (entrance, exit) = (maze.get_entrance().unwrap().coord, maze.get_exit().unwrap().coord);
+        assert_eq!((5, 5), (entrance.x, exit.x));
+        assert!(maze.solve(entrance, exit).is_some());
+
+        // Nothing on the border is three steps from the shape
+        assert!(matches!(maze.generate_with_silhouette(&mask, SilhouetteConfig { seed: 498, clearance: 3 }),
+                         Err(MazeError::ConstraintUnsatisfiable(_))));
+        assert!(Maze::new(4, 6).generate_with_silhouette(&mask, SilhouetteConfig::default()).is_err());
+        let all = Mask::new(3, 6);
+        assert!(Maze::new(3, 6).generate_with_silhouette(&all, SilhouetteConfig::default()).is_err());
+    }
+}
@@ -0,0 +1,394 @@
+//! Path finding through a generated Maze.
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+impl Maze {
+    /// Returns the Squares that can be reached in a single step from (x, y) -
+    /// that is, the in-bounds neighbors that aren't separated from it by a wall.
+    /// Neighbors across the edge of a Torus axis are included, and a passage
+    /// under a weave crossing leads to the Square on its far side.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// assert_eq!(vec![Coord::new(1, 0)], maze.open_neighbors(0, 0));
+    /// ```
+    pub fn open_neighbors(&self, x: u32, y: u32) -> Vec<Coord> {
+        self.passable_neighbors(x, y, true)
+    }
+
+    //
+    // Internal - open_neighbors(), leaving out those through secret
+    // passages unless secrets is set.
+    //
+    fn passable_neighbors(&self, x: u32, y: u32, secrets: bool) -> Vec<Coord> {
+        let mut neighbors = Vec::new();
+        if !self.in_bounds(x, y) {
+            return neighbors;
+        }
+
+        neighbors.extend((0..constants::NUM_DIRECTIONS).filter_map(|dir| self.passable_step(x, y, dir, secrets)));
+        neighbors
+    }
+
+    //
+    // Internal - the Square one step from (x, y) in direction dir leads to,
+    // or None if the way is shut.  Secret passages are only open when
+    // secrets is set.
+    //
+    pub(crate) fn passable_step(&self, x: u32, y: u32, dir: u32, secrets: bool) -> Option<Coord> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        let sq = &self.sq[self.get_offset(x, y)];
+        // Openings along a tunnel under this Square belong to the tunnel
+        if sq.is_wall_present(dir) || sq.has_under_passage(dir) || (!secrets && sq.is_secret(dir)) {
+            return None;
+        }
+        self.step_through(x, y, dir)
+    }
+
+    /// Finds the shortest path between two Squares using a breadth-first search.
+    /// The returned path includes both endpoints.  Returns None if either point
+    /// is outside of the maze or if there's no path between them.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.generate_perfect().unwrap();
+    /// let path = maze.solve(Coord::new(0, 0), Coord::new(9, 9)).unwrap();
+    /// assert_eq!(Coord::new(0, 0), path[0]);
+    /// assert_eq!(Coord::new(9, 9), path[path.len() - 1]);
+    /// ```
+    pub fn solve(&self, from: Coord, to: Coord) -> Option<Vec<Coord>> {
+        self.solve_with_secrets(from, to, true)
+    }
+
+    /// Finds the shortest path between two Squares like solve(), taking
+    /// secret passages only if use_secrets is set.  Hints shown to a player
+    /// can leave it unset, so they don't give secrets away.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::from_compact("22\n59").unwrap();
+    /// maze.carve_secret(0, 0, DIR_EAST).unwrap();
+    /// let (from, to) = (Coord::new(0, 0), Coord::new(1, 0));
+    /// assert_eq!(2, maze.solve_with_secrets(from, to, true).unwrap().len());
+    /// assert_eq!(4, maze.solve_with_secrets(from, to, false).unwrap().len());
+    /// ```
+    pub fn solve_with_secrets(&self, from: Coord, to: Coord, use_secrets: bool) -> Option<Vec<Coord>> {
+        if !self.in_bounds(from.x, from.y) || !self.in_bounds(to.x, to.y) {
+            return None;
+        }
+
+        let mut came_from: Vec<Option<Coord>> = vec![None; self.sq.len()];
+        let mut seen = vec![false; self.sq.len()];
+        let mut queue = VecDeque::new();
+
+        seen[self.get_offset(from.x, from.y)] = true;
+        queue.push_back(from);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                let mut path = vec![cur];
+                let mut step = cur;
+                while let Some(prev) = came_from[self.get_offset(step.x, step.y)] {
+                    path.push(prev);
+                    step = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.passable_neighbors(cur.x, cur.y, use_secrets) {
+                let offset = self.get_offset(next.x, next.y);
+                if !seen[offset] {
+                    seen[offset] = true;
+                    came_from[offset] = Some(cur);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Finds the shortest path from from to whichever of targets is nearest,
+    /// with one breadth-first search however many targets there are.
+    /// Returns the index of that target in targets and the path, both ends
+    /// included.  Of targets the same distance away, the one listed first
+    /// wins.  Targets outside the Maze are skipped; returns None if none of
+    /// them can be reached.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// // A corridor five Squares long
+    /// let maze = Maze::from_compact("4ccc8").unwrap();
+    /// let exits = [Coord::new(0, 0), Coord::new(4, 0)];
+    /// let (nearest, path) = maze.solve_to_nearest(Coord::new(3, 0), &exits).unwrap();
+    /// assert_eq!(1, nearest);
+    /// assert_eq!(vec![Coord::new(3, 0), Coord::new(4, 0)], path);
+    /// ```
+    pub fn solve_to_nearest(&self, from: Coord, targets: &[Coord]) -> Option<(usize, Vec<Coord>)> {
+        if !self.in_bounds(from.x, from.y) {
+            return None;
+        }
+        // The first target listed for each Square
+        let mut target_at: Vec<Option<usize>> = vec![None; self.sq.len()];
+        for (i, t) in targets.iter().enumerate().rev() {
+            if self.in_bounds(t.x, t.y) {
+                target_at[self.get_offset(t.x, t.y)] = Some(i);
+            }
+        }
+
+        let mut came_from: Vec<Option<Coord>> = vec![None; self.sq.len()];
+        let mut seen = vec![false; self.sq.len()];
+        let mut queue = VecDeque::new();
+        // The (distance, index, Square) of the best target found so far
+        let mut found: Option<(u32, usize, Coord)> = None;
+
+        seen[self.get_offset(from.x, from.y)] = true;
+        queue.push_back((from, 0));
+        while let Some((cur, d)) = queue.pop_front() {
+            // Once every Square as near as the first target found has been
+            // looked at, nothing nearer is left
+            if found.is_some_and(|(best, _, _)| d > best) {
+                break;
+            }
+            if let Some(i) = target_at[self.get_offset(cur.x, cur.y)] {
+                if found.is_none_or(|(_, best, _)| i < best) {
+                    found = Some((d, i, cur));
+                }
+            }
+            for next in self.open_neighbors(cur.x, cur.y) {
+                let offset = self.get_offset(next.x, next.y);
+                if !seen[offset] {
+                    seen[offset] = true;
+                    came_from[offset] = Some(cur);
+                    queue.push_back((next, d + 1));
+                }
+            }
+        }
+
+        let (_, index, target) = found?;
+        let mut path = vec![target];
+        let mut step = target;
+        while let Some(prev) = came_from[self.get_offset(step.x, step.y)] {
+            path.push(prev);
+            step = prev;
+        }
+        path.reverse();
+        Some((index, path))
+    }
+
+    /// Checks that moves is a walk through the Maze that a player could
+    /// make: every Square in bounds, and each one a single step from the
+    /// one before it, as open_neighbors() sees it.  A path from solve()
+    /// always passes.  An empty walk passes too.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let maze = Maze::from_compact("4ccc8").unwrap();
+    /// assert!(maze.validate_moves(&[Coord::new(0, 0), Coord::new(1, 0), Coord::new(2, 0)]).is_ok());
+    /// // Skipping a Square isn't a move
+    /// assert!(maze.validate_moves(&[Coord::new(0, 0), Coord::new(2, 0)]).is_err());
+    /// ```
+    pub fn validate_moves(&self, moves: &[Coord]) -> Result<(), MazeError> {
+        if let Some(c) = moves.iter().find(|c| !self.in_bounds(c.x, c.y)) {
+            return Err(MazeError::OutOfBounds(c.x, c.y));
+        }
+        for (i, pair) in moves.windows(2).enumerate() {
+            let (from, to) = (pair[0], pair[1]);
+            if !self.open_neighbors(from.x, from.y).contains(&to) {
+                return Err(MazeError::InvalidArgument(
+                    format!("move {} from ({}, {}) to ({}, {}) isn't a single open step",
+                            i + 1, from.x, from.y, to.x, to.y)));
+            }
+        }
+        Ok(())
+    }
+
+    //
+    // Internal - the number of steps from the Square at from to every other
+    // Square, or None for Squares that can't be reached.
+    //
+    pub(crate) fn distances(&self, from: Coord) -> Vec<Option<u32>> {
+        let mut dist = vec![None; self.sq.len()];
+        if !self.in_bounds(from.x, from.y) {
+            return dist;
+        }
+        let mut queue = VecDeque::new();
+        dist[self.get_offset(from.x, from.y)] = Some(0);
+        queue.push_back((from, 0));
+        while let Some((cur, d)) = queue.pop_front() {
+            for next in self.open_neighbors(cur.x, cur.y) {
+                let offset = self.get_offset(next.x, next.y);
+                if dist[offset].is_none() {
+                    dist[offset] = Some(d + 1);
+                    queue.push_back((next, d + 1));
+                }
+            }
+        }
+        dist
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Topology;
+
+    #[test]
+    fn test_open_neighbors() {
+        let mut maze = Maze::new(3, 3);
+        assert_eq!(0, maze.open_neighbors(1, 1).len());
+
+        maze.carve(1, 1, constants::DIR_NORTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        assert_eq!(vec![Coord::new(1, 0), Coord::new(0, 1)], maze.open_neighbors(1, 1));
+        assert_eq!(vec![Coord::new(1, 1)], maze.open_neighbors(1, 0));
+
+        // Border openings don't lead anywhere
+        maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        assert_eq!(0, maze.open_neighbors(0, 0).len());
+
+        // Out of bounds squares have no neighbors
+        assert_eq!(0, maze.open_neighbors(3, 3).len());
+    }
+
+    #[test]
+    fn test_solve_corridor() {
+        // A single corridor snaking through a 3x2 maze:
+        // (0,0) -> (1,0) -> (2,0) -> (2,1) -> (1,1) -> (0,1)
+        let maze = Maze::from_compact("4ca\n4c9").unwrap();
+
+        let path = maze.solve(Coord::new(0, 0), Coord::new(0, 1)).unwrap();
+        assert_eq!(vec![Coord::new(0, 0), Coord::new(1, 0), Coord::new(2, 0),
+                        Coord::new(2, 1), Coord::new(1, 1), Coord::new(0, 1)], path);
+
+        // Solving from a square to itself is a path of length 1
+        assert_eq!(Some(vec![Coord::new(1, 1)]), maze.solve(Coord::new(1, 1), Coord::new(1, 1)));
+    }
+
+    #[test]
+    fn test_solve_shortest() {
+        // A 2x2 loop - the solver should take the one step route rather than
+        // going around.
+        let maze = Maze::from_compact("6a\n59").unwrap();
+        assert_eq!(2, maze.solve(Coord::new(0, 0), Coord::new(0, 1)).unwrap().len());
+    }
+
+    #[test]
+    fn test_solve_torus() {
+        // A corridor along a single row, joined across the seam
+        let mut maze = Maze::new(1, 8);
+        maze.set_topology(Topology::Torus, Topology::Bounded);
+        for x in 0..8 {
+            maze.carve(x, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        }
+
+        // Going around the seam is much shorter than walking the row
+        assert_eq!(Some(vec![Coord::new(1, 0), Coord::new(0, 0), Coord::new(7, 0), Coord::new(6, 0)]),
+                   maze.solve(Coord::new(1, 0), Coord::new(6, 0)));
+
+        // Without wrapping, the same walls only allow the long way
+        maze.set_topology(Topology::Bounded, Topology::Bounded);
+        assert_eq!(6, maze.solve(Coord::new(1, 0), Coord::new(6, 0)).unwrap().len());
+    }
+
+    #[test]
+    fn test_solve_unreachable() {
+        let mut maze = Maze::new(3, 3);
+        maze.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        assert_eq!(None, maze.solve(Coord::new(0, 0), Coord::new(2, 2)));
+        assert_eq!(None, maze.solve(Coord::new(0, 0), Coord::new(5, 5)));
+    }
+
+    #[test]
+    fn test_solve_to_nearest() {
+        // Three exits at different distances from the middle of the maze
+        let mut maze = Maze::new(9, 9);
+        maze.generate_perfect_seeded(452).unwrap();
+        maze.set_exit(0, 0, constants::DIR_NORTH).unwrap();
+        maze.add_exit(8, 4, constants::DIR_EAST).unwrap();
+        maze.add_exit(3, 8, constants::DIR_SOUTH).unwrap();
+        let exits: Vec<Coord> = maze.exits().iter().map(|o| o.coord).collect();
+        let from = Coord::new(4, 4);
+        let lengths: Vec<usize> = exits.iter().map(|&e| maze.solve(from, e).unwrap().len()).collect();
+        assert!(lengths[0] != lengths[1] && lengths[1] != lengths[2] && lengths[0] != lengths[2], "{:?}", lengths);
+        let nearest = (0..3).min_by_key(|&i| lengths[i]).unwrap();
+
+        let (index, path) = maze.solve_to_nearest(from, &exits).unwrap();
+        assert_eq!(nearest, index);
+        assert_eq!(lengths[nearest], path.len());
+        assert_eq!((from, exits[nearest]), (path[0], path[path.len() - 1]));
+        // The order targets are listed in doesn't matter without a tie
+        let reversed: Vec<Coord> = exits.iter().rev().cloned().collect();
+        assert_eq!(2 - nearest, maze.solve_to_nearest(from, &reversed).unwrap().0);
+
+        // Standing on a target
+        assert_eq!(Some((1, vec![exits[1]])), maze.solve_to_nearest(exits[1], &exits));
+    }
+
+    #[test]
+    fn test_solve_to_nearest_tie() {
+        // A corridor with targets two steps away on each side; the first
+        // listed wins either way round
+        let maze = Maze::from_compact("4ccc8").unwrap();
+        let (left, right) = (Coord::new(0, 0), Coord::new(4, 0));
+        let from = Coord::new(2, 0);
+        assert_eq!(Some((0, vec![from, Coord::new(1, 0), left])), maze.solve_to_nearest(from, &[left, right]));
+        assert_eq!(Some((0, vec![from, Coord::new(3, 0), right])), maze.solve_to_nearest(from, &[right, left]));
+        // The same Square listed twice is the earlier entry
+        assert_eq!(1, maze.solve_to_nearest(from, &[Coord::new(9, 9), right, right]).unwrap().0);
+
+        // Unreachable and out of bounds targets are skipped
+        let mut walled = Maze::new(3, 3);
+        walled.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        assert_eq!(None, walled.solve_to_nearest(Coord::new(0, 0), &[Coord::new(2, 2), Coord::new(7, 0)]));
+        assert_eq!(Some((1, vec![Coord::new(0, 0), Coord::new(1, 0)])),
+                   walled.solve_to_nearest(Coord::new(0, 0), &[Coord::new(2, 2), Coord::new(1, 0)]));
+        assert_eq!(None, walled.solve_to_nearest(Coord::new(0, 0), &[]));
+        assert_eq!(None, walled.solve_to_nearest(Coord::new(5, 5), &[Coord::new(0, 0)]));
+    }
+
+    #[test]
+    fn test_solve_perfect_maze() {
+        // Every square of a perfect maze is reachable from every other square
+        let mut maze = Maze::new(15, 12);
+        maze.generate_perfect_seeded(1).unwrap();
+        for y in 0..maze.get_rows() {
+            for x in 0..maze.get_cols() {
+                assert!(maze.solve(Coord::new(0, 0), Coord::new(x, y)).is_some());
+            }
+        }
+    }
+    #[test]
+    fn test_validate_moves() {
+        let mut maze = Maze::new(1, 3);
+        maze.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_one_way(1, 0, constants::DIR_EAST).unwrap();
+        let (a, b, c) = (Coord::new(0, 0), Coord::new(1, 0), Coord::new(2, 0));
+        assert_eq!(Ok(()), maze.validate_moves(&[a, b, c]));
+        assert_eq!(Ok(()), maze.validate_moves(&[b]));
+        assert_eq!(Ok(()), maze.validate_moves(&[]));
+        // Back against the one-way passage, standing still, and off the edge
+        assert!(maze.validate_moves(&[c, b]).is_err());
+        assert!(maze.validate_moves(&[a, a]).is_err());
+        assert_eq!(Err(MazeError::OutOfBounds(3, 0)), maze.validate_moves(&[c, Coord::new(3, 0)]));
+        let path = maze.solve(a, c).unwrap();
+        assert_eq!(Ok(()), maze.validate_moves(&path));
+    }
+}
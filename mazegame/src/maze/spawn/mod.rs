@@ -0,0 +1,243 @@
+//! Picking spawn points for enemies and items that keep clear of the
+//! entrance, the solution and each other.  Every distance here is counted
+//! in steps through the maze, not in a straight line.
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// Which kind of Square a spawn point can be on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpawnArea {
+    /// Any carved Square.
+    #[default]
+    Anywhere,
+    /// Only Squares that are part of a room.
+    RoomsOnly,
+    /// Only carved Squares outside of rooms.
+    CorridorsOnly,
+    /// Only the anchor Squares of rooms, like their corners and the spots
+    /// along their walls; see Room::anchors().
+    RoomAnchors,
+}
+
+/// The rules spawn points have to follow.  The defaults allow any carved
+/// Square.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpawnConstraints {
+    /// Spawn points must be at least this many steps from the player's
+    /// start: the Square from set_start(), or else the entrance.  Ignored
+    /// if the Maze has neither.
+    pub min_entrance_distance: u32,
+    /// Spawn points must be at least this many steps from each other.
+    pub min_separation: u32,
+    /// Squares to keep off, like the solution to the maze.
+    pub avoid_path: Vec<Coord>,
+    /// Spawn points must also be more than this many steps from every
+    /// Square of avoid_path.
+    pub path_buffer: u32,
+    /// Which kind of Square spawn points can be on.
+    pub area: SpawnArea,
+    /// Favor Squares far from the entrance, picking each one with a weight
+    /// of its distance in steps.  Otherwise every allowed Square is as
+    /// likely.
+    pub weight_by_distance: bool,
+}
+
+impl Maze {
+    /// Picks up to count spawn points that follow constraints.  Points are
+    /// picked one at a time, each ruling out the Squares too close to it,
+    /// so a crowded maze may run out of room.  Returns the points in the
+    /// order they were picked, and whether all count of them were found.
+    /// The same seed always gives the same points.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::spawn::SpawnConstraints;
+    ///
+    /// let mut maze = mazegame::Maze::new(12, 12);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let constraints = SpawnConstraints { min_separation: 5, ..Default::default() };
+    /// let (points, all_found) = maze.spawn_points(4, &constraints, 7);
+    /// assert!(all_found);
+    /// assert_eq!(4, points.len());
+    /// ```
+    pub fn spawn_points(&self, count: u32, constraints: &SpawnConstraints, seed: u64) -> (Vec<Coord>, bool) {
+        self.spawn_points_with_rng(count, constraints, &mut rng::from_seed(seed))
+    }
+
+    /// Picks spawn points like spawn_points(), using rng.
+    pub fn spawn_points_with_rng<R: Rng>(&self, count: u32, constraints: &SpawnConstraints, rng: &mut R)
+                                         -> (Vec<Coord>, bool) {
+        let from_entrance = self.player_start().map(|c| self.distances(c));
+        let from_path = self.path_distances(&constraints.avoid_path);
+
+        let mut anchored = Vec::new();
+        if constraints.area == SpawnArea::RoomAnchors {
+            anchored = vec![false; self.sq.len()];
+            for room in self.rooms() {
+                for c in room.anchors(self).all() {
+                    anchored[self.get_offset(c.x, c.y)] = true;
+                }
+            }
+        }
+
+        // (offset, weight) of every Square that's allowed on its own
+        let mut candidates: Vec<(usize, u32)> = Vec::new();
+        for (i, sq) in self.sq.iter().enumerate() {
+            let allowed_area = match constraints.area {
+                SpawnArea::Anywhere => sq.is_carved(),
+                SpawnArea::RoomsOnly => sq.is_carved() && sq.is_part_of_room(),
+                SpawnArea::CorridorsOnly => sq.is_carved() && !sq.is_part_of_room(),
+                SpawnArea::RoomAnchors => sq.is_carved() && anchored[i],
+            };
+            if !allowed_area || from_path[i].is_some_and(|d| d <= constraints.path_buffer) {
+                continue;
+            }
+            let entrance_distance = from_entrance.as_ref().map(|dist| dist[i]);
+            if let Some(d) = entrance_distance {
+                if d.is_none_or(|d| d < constraints.min_entrance_distance) {
+                    continue;
+                }
+            }
+            let weight = match (constraints.weight_by_distance, entrance_distance) {
+                (true, Some(Some(d))) => d.max(1),
+                _ => 1,
+            };
+            candidates.push((i, weight));
+        }
+
+        let mut points = Vec::new();
+        while (points.len() as u32) < count && !candidates.is_empty() {
+            let total: u64 = candidates.iter().map(|&(_, w)| w as u64).sum();
+            let mut pick = rng.gen_range(0, total);
+            let chosen = candidates.iter()
+                .position(|&(_, w)| {
+                    if pick < w as u64 {
+                        true
+                    } else {
+                        pick -= w as u64;
+                        false
+                    }
+                })
+                .unwrap_or(candidates.len() - 1);
+            let offset = candidates.swap_remove(chosen).0;
+            let point = self.get_coord(offset);
+            points.push(point);
+
+            if constraints.min_separation > 0 {
+                let near = self.distances(point);
+                candidates.retain(|&(i, _)| near[i].is_none_or(|d| d >= constraints.min_separation));
+            }
+        }
+        let all_found = points.len() as u32 == count;
+        (points, all_found)
+    }
+
+    //
+    // Internal - the number of steps from the nearest Square of path to
+    // every Square, or None for Squares it can't reach.
+    //
+    fn path_distances(&self, path: &[Coord]) -> Vec<Option<u32>> {
+        let mut dist = vec![None; self.sq.len()];
+        let mut queue = VecDeque::new();
+        for c in path.iter().filter(|c| self.in_bounds(c.x, c.y)) {
+            let offset = self.get_offset(c.x, c.y);
+            if dist[offset].is_none() {
+                dist[offset] = Some(0);
+                queue.push_back((*c, 0));
+            }
+        }
+        while let Some((cur, d)) = queue.pop_front() {
+            for next in self.open_neighbors(cur.x, cur.y) {
+                let offset = self.get_offset(next.x, next.y);
+                if dist[offset].is_none() {
+                    dist[offset] = Some(d + 1);
+                    queue.push_back((next, d + 1));
+                }
+            }
+        }
+        dist
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::test_mazes::dungeon;
+
+    #[test]
+    fn test_constraints_hold() {
+        let maze = dungeon(6, 440);
+        let solution = maze.solve(Coord::new(0, 0), Coord::new(29, 29)).unwrap();
+        let entrance = maze.distances(Coord::new(0, 0));
+        let near_path = maze.path_distances(&solution);
+
+        for &area in [SpawnArea::Anywhere, SpawnArea::RoomsOnly, SpawnArea::CorridorsOnly].iter() {
+            let constraints = SpawnConstraints {
+                min_entrance_distance: 15,
+                min_separation: 6,
+                avoid_path: solution.clone(),
+                path_buffer: 2,
+                area,
+                weight_by_distance: area == SpawnArea::Anywhere,
+            };
+            // The rooms are small, so there's only space for a few there
+            let count = if area == SpawnArea::RoomsOnly { 4 } else { 8 };
+            let (points, all_found) = maze.spawn_points(count, &constraints, 440);
+            assert!(all_found, "{:?}", area);
+            assert_eq!(count as usize, points.len());
+            for (i, p) in points.iter().enumerate() {
+                let offset = maze.get_offset(p.x, p.y);
+                let sq = &maze.sq[offset];
+                assert!(sq.is_carved());
+                match area {
+                    SpawnArea::RoomsOnly => assert!(sq.is_part_of_room()),
+                    SpawnArea::CorridorsOnly => assert!(!sq.is_part_of_room()),
+                    SpawnArea::Anywhere | SpawnArea::RoomAnchors => {}
+                }
+                assert!(entrance[offset].unwrap() >= 15);
+                assert!(!solution.contains(p));
+                assert!(near_path[offset].unwrap() > 2);
+                let dist = maze.distances(*p);
+                for q in points[i + 1..].iter() {
+                    assert!(dist[maze.get_offset(q.x, q.y)].unwrap() >= 6);
+                }
+            }
+            assert_eq!((points, true), maze.spawn_points(count, &constraints, 440));
+        }
+    }
+
+    #[test]
+    fn test_room_anchors() {
+        let maze = dungeon(6, 440);
+        let constraints = SpawnConstraints { area: SpawnArea::RoomAnchors, ..Default::default() };
+        let (points, all_found) = maze.spawn_points(10, &constraints, 463);
+        assert!(all_found);
+        for p in points {
+            let id = maze.sq[maze.get_offset(p.x, p.y)].id;
+            assert!(maze.room_anchors(id).unwrap().all().contains(&p));
+        }
+    }
+
+    #[test]
+    fn test_too_crowded() {
+        let maze = dungeon(6, 440);
+        let constraints = SpawnConstraints { min_separation: 40, ..Default::default() };
+        let (points, all_found) = maze.spawn_points(100, &constraints, 1);
+        assert!(!all_found);
+        assert!(!points.is_empty() && points.len() < 100);
+        // Whatever was found still follows the rules
+        for (i, p) in points.iter().enumerate() {
+            let dist = maze.distances(*p);
+            assert!(points[i + 1..].iter().all(|q| dist[maze.get_offset(q.x, q.y)].unwrap() >= 40));
+        }
+
+        assert_eq!((vec![], false), Maze::new(5, 5).spawn_points(1, &SpawnConstraints::default(), 1));
+        assert_eq!((vec![], true), maze.spawn_points(0, &constraints, 1));
+        assert_ne!(maze.spawn_points(5, &SpawnConstraints::default(), 1),
+                   maze.spawn_points(5, &SpawnConstraints::default(), 2));
+    }
+}
@@ -0,0 +1,800 @@
+//! Building mazes from a JSON description, enabled with the `serde` feature.
+//!
+//! A spec is a JSON object with these keys:
+//!
+//! ```text
+//! {
+//!   "width": 40,                  required, 2 to 1000
+//!   "height": 30,                 required, 2 to 1000
+//!   "seed": 123,                  optional; a random seed is used if missing
+//!   "algorithm": "growing_tree",  optional; currently the only algorithm
+//!   "rooms": {                    optional; no rooms if missing
+//!     "count": 6, "min_w": 3, "max_w": 6, "min_h": 3, "max_h": 5
+//!   },
+//!   "braid": 0.3,                 optional, 0.0 to 1.0; the chance of
+//!                                 removing each dead end (default 0.0)
+//!   "allow_weave": true,          optional; let passages cross over one
+//!                                 another (default false, not with rooms)
+//!   "corridor_width": 2,          optional; passages are this many Squares
+//!                                 across (default 1).  The width and height
+//!                                 must be multiples of it, and room sizes
+//!                                 are counted in corridor widths
+//!   "entrance": "auto",           optional, "auto" (top left and bottom
+//!                                 right corners) or "none" (the default)
+//!   "min_solution_length": 60     optional; the shortest path from the
+//!                                 entrance to the exit must cover at least
+//!                                 this many Squares (default 0).  Needs an
+//!                                 "auto" entrance
+//!   "max_attempts": 10            optional; how many mazes are generated
+//!                                 looking for one that meets
+//!                                 min_solution_length (default 10)
+//! }
+//! ```
+//!
+//! Unknown keys are rejected, so typos don't silently fall back to defaults.
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use serde_json::{Map, Value};
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze, Opening};
+use crate::rng;
+
+/// The largest width or height a spec may ask for.
+pub const MAX_SPEC_DIMENSION: u32 = 1000;
+
+/// The default max_attempts: how many mazes are generated looking for one
+/// with a long enough solution before giving up.
+pub const MAX_SOLUTION_ATTEMPTS: u32 = 10;
+
+/// The generation algorithms a spec can select.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    GrowingTree,
+}
+
+/// Where a spec's maze gets its entrance and exit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntranceSpec {
+    /// No entrance or exit.
+    None,
+    /// An entrance in the top left corner and an exit in the bottom right.
+    Auto,
+}
+
+/// The rooms section of a spec.  Sizes are in Squares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoomSpec {
+    pub count: u32,
+    pub min_w: u32,
+    pub max_w: u32,
+    pub min_h: u32,
+    pub max_h: u32,
+}
+
+/// A validated maze spec.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MazeSpec {
+    pub width: u32,
+    pub height: u32,
+    pub seed: Option<u64>,
+    pub algorithm: Algorithm,
+    pub rooms: Option<RoomSpec>,
+    pub braid: f64,
+    pub allow_weave: bool,
+    pub corridor_width: u32,
+    pub entrance: EntranceSpec,
+    pub min_solution_length: u32,
+    pub max_attempts: u32,
+}
+
+/// A requirement of a spec that a generated maze can fall short of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecConstraint {
+    /// Every one of rooms.count rooms has to be placed.  Rooms that would
+    /// overlap are skipped, so a crowded maze can end up with fewer.
+    Rooms,
+    /// The solution has to cover at least min_solution_length Squares.
+    MinSolutionLength,
+}
+
+impl fmt::Display for SpecConstraint {
+    /// Writes the spec field that sets the constraint.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpecConstraint::Rooms => write!(f, "rooms.count"),
+            SpecConstraint::MinSolutionLength => write!(f, "min_solution_length"),
+        }
+    }
+}
+
+/// Why build_outcome() gave up, gathered over every attempt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenerationReport {
+    /// The constraint to loosen first: the one that failed in the most
+    /// attempts.  Ties go to the one the best attempt fell furthest short of.
+    pub constraint: SpecConstraint,
+    /// How many mazes were generated.
+    pub attempts: u32,
+    /// How many attempts each constraint failed, in the order of
+    /// SpecConstraint.  Constraints the spec doesn't set never fail.
+    pub failures: Vec<(SpecConstraint, u32)>,
+    /// The most rooms any attempt placed, and how many were asked for.
+    pub best_rooms: (u32, u32),
+    /// The longest solution any attempt could get by moving its exit, and
+    /// the min_solution_length asked for.
+    pub best_solution_length: (u32, u32),
+    /// The seed of each attempt, in order.  Building the spec with one of
+    /// them and a max_attempts of 1 gives that attempt's maze.
+    pub seeds: Vec<u64>,
+}
+
+impl fmt::Display for GenerationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "couldn't meet {}", self.constraint)?;
+        for &(constraint, count) in &self.failures {
+            write!(f, "; {} failed {} of {} attempts", constraint, count, self.attempts)?;
+            match constraint {
+                SpecConstraint::Rooms => write!(f, " (at best {} of {} rooms)", self.best_rooms.0, self.best_rooms.1)?,
+                SpecConstraint::MinSolutionLength => write!(f, " (at best {} of {} Squares)",
+                                                            self.best_solution_length.0, self.best_solution_length.1)?,
+            }
+        }
+        if let (Some(first), Some(last)) = (self.seeds.first(), self.seeds.last()) {
+            write!(f, "; seeds {} to {}", first, last)?;
+        }
+        Ok(())
+    }
+}
+
+/// What build_outcome() made of a spec.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenerationOutcome {
+    /// A maze that meets every constraint, with the seed that builds it and
+    /// the number of attempts it took.
+    Built { maze: Box<Maze>, seed: u64, attempts: u32 },
+    /// No attempt met every constraint.
+    Failed(GenerationReport),
+}
+
+impl GenerationOutcome {
+    /// Returns the built maze, or a ConstraintUnsatisfiable error holding the
+    /// report.
+    pub fn into_result(self) -> Result<Maze, MazeError> {
+        match self {
+            GenerationOutcome::Built { maze, .. } => Ok(*maze),
+            GenerationOutcome::Failed(report) => Err(MazeError::ConstraintUnsatisfiable(report.to_string())),
+        }
+    }
+}
+
+impl MazeSpec {
+    /// Creates a spec for a maze of the given size, with every optional
+    /// field at its default.
+    pub fn new(width: u32, height: u32) -> MazeSpec {
+        MazeSpec {
+            width,
+            height,
+            seed: None,
+            algorithm: Algorithm::GrowingTree,
+            rooms: None,
+            braid: 0.0,
+            allow_weave: false,
+            corridor_width: 1,
+            entrance: EntranceSpec::None,
+            min_solution_length: 0,
+            max_attempts: MAX_SOLUTION_ATTEMPTS,
+        }
+    }
+
+    /// Parses and validates a JSON spec.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let spec = MazeSpec::from_json(r#"{"width": 12, "height": 8, "braid": 0.5}"#).unwrap();
+    /// assert_eq!((12, 8), (spec.width, spec.height));
+    /// assert!(MazeSpec::from_json(r#"{"width": 12}"#).is_err());
+    /// ```
+    pub fn from_json(json: &str) -> Result<MazeSpec, MazeError> {
+        let value: Value = ::serde_json::from_str(json).map_err(|e| MazeError::Parse(e.to_string()))?;
+        let obj = value.as_object().ok_or_else(|| MazeError::Parse("a spec must be a JSON object".to_string()))?;
+        check_keys(obj, "", &["width", "height", "seed", "algorithm", "rooms", "braid", "allow_weave",
+                              "corridor_width", "entrance", "min_solution_length", "max_attempts"])?;
+
+        let mut spec = MazeSpec::new(required_u32(obj, "", "width")?, required_u32(obj, "", "height")?);
+        if let Some(seed) = obj.get("seed") {
+            spec.seed = Some(seed.as_u64().ok_or_else(|| spec_error("seed", "must be a non-negative integer"))?);
+        }
+        if let Some(algorithm) = obj.get("algorithm") {
+            spec.algorithm = match algorithm.as_str() {
+                Some("growing_tree") => Algorithm::GrowingTree,
+                _ => return Err(spec_error("algorithm", "must be \"growing_tree\"")),
+            };
+        }
+        if let Some(rooms) = obj.get("rooms") {
+            let rooms = rooms.as_object().ok_or_else(|| spec_error("rooms", "must be an object"))?;
+            check_keys(rooms, "rooms.", &["count", "min_w", "max_w", "min_h", "max_h"])?;
+            spec.rooms = Some(RoomSpec {
+                count: required_u32(rooms, "rooms.", "count")?,
+                min_w: required_u32(rooms, "rooms.", "min_w")?,
+                max_w: required_u32(rooms, "rooms.", "max_w")?,
+                min_h: required_u32(rooms, "rooms.", "min_h")?,
+                max_h: required_u32(rooms, "rooms.", "max_h")?,
+            });
+        }
+        if let Some(braid) = obj.get("braid") {
+            spec.braid = braid.as_f64().ok_or_else(|| spec_error("braid", "must be a number"))?;
+        }
+        if let Some(weave) = obj.get("allow_weave") {
+            spec.allow_weave = weave.as_bool().ok_or_else(|| spec_error("allow_weave", "must be true or false"))?;
+        }
+        if obj.contains_key("corridor_width") {
+            spec.corridor_width = required_u32(obj, "", "corridor_width")?;
+        }
+        if let Some(entrance) = obj.get("entrance") {
+            spec.entrance = match entrance.as_str() {
+                Some("auto") => EntranceSpec::Auto,
+                Some("none") => EntranceSpec::None,
+                _ => return Err(spec_error("entrance", "must be \"auto\" or \"none\"")),
+            };
+        }
+        if obj.contains_key("min_solution_length") {
+            spec.min_solution_length = required_u32(obj, "", "min_solution_length")?;
+        }
+        if obj.contains_key("max_attempts") {
+            spec.max_attempts = required_u32(obj, "", "max_attempts")?;
+        }
+
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    /// Checks that every value in the spec is in range.
+    pub fn validate(&self) -> Result<(), MazeError> {
+        for &(field, value) in &[("width", self.width), ("height", self.height)] {
+            if !(2..=MAX_SPEC_DIMENSION).contains(&value) {
+                return Err(spec_error(field, &format!("must be between 2 and {}", MAX_SPEC_DIMENSION)));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.braid) {
+            return Err(spec_error("braid", "must be between 0.0 and 1.0"));
+        }
+        if self.allow_weave && self.rooms.is_some() {
+            return Err(spec_error("allow_weave", "can't be combined with rooms"));
+        }
+        if self.corridor_width == 0 {
+            return Err(spec_error("corridor_width", "must be at least 1"));
+        }
+        if !self.width.is_multiple_of(self.corridor_width) || !self.height.is_multiple_of(self.corridor_width) ||
+           self.width / self.corridor_width < 2 || self.height / self.corridor_width < 2 {
+            return Err(spec_error("corridor_width", "must divide the width and height into at least 2 corridors"));
+        }
+        if self.allow_weave && self.corridor_width > 1 {
+            return Err(spec_error("allow_weave", "can't be combined with a corridor_width above 1"));
+        }
+        if self.min_solution_length > 0 && self.entrance != EntranceSpec::Auto {
+            return Err(spec_error("min_solution_length", "needs an \"auto\" entrance"));
+        }
+        if self.max_attempts == 0 {
+            return Err(spec_error("max_attempts", "must be at least 1"));
+        }
+        let (width, height) = self.coarse_size();
+        if let Some(rooms) = self.rooms {
+            // Rooms are kept at least one Square away from the edge of the maze
+            for &(min_field, min, max_field, max, limit) in
+                &[("rooms.min_w", rooms.min_w, "rooms.max_w", rooms.max_w, width),
+                  ("rooms.min_h", rooms.min_h, "rooms.max_h", rooms.max_h, height)] {
+                if min == 0 {
+                    return Err(spec_error(min_field, "must be at least 1"));
+                }
+                if max < min {
+                    return Err(spec_error(max_field, &format!("must be at least {}", min_field)));
+                }
+                if max + 2 > limit {
+                    return Err(spec_error(max_field, "rooms must fit inside the maze with a Square to spare on each side"));
+                }
+            }
+            if rooms.count > width * height {
+                return Err(spec_error("rooms.count", "can't be more than the number of Squares in the maze"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the maze the spec describes.  A spec with a seed always builds
+    /// the same maze.
+    ///
+    /// This is build_outcome() with the report turned into an error: if no
+    /// attempt meets every constraint, the error is ConstraintUnsatisfiable
+    /// holding the report.
+    pub fn build(&self) -> Result<Maze, MazeError> {
+        self.build_outcome()?.into_result()
+    }
+
+    /// Builds the maze the spec describes using the supplied random number
+    /// generator.  The spec's seed is ignored.
+    ///
+    /// If the solution is shorter than min_solution_length, the exit is
+    /// moved to the farthest Square on the outer wall.  If even that isn't
+    /// far enough, a new maze is generated, up to max_attempts times.
+    pub fn build_with_rng<R: Rng>(&self, rng: &mut R) -> Result<Maze, MazeError> {
+        self.validate()?;
+        if self.min_solution_length > self.width * self.height {
+            return Err(MazeError::ConstraintUnsatisfiable(
+                format!("a {}x{} maze has no solution of {} Squares", self.width, self.height, self.min_solution_length)));
+        }
+        for attempt in 1..=self.max_attempts {
+            let mut maze = self.generate(rng)?;
+            if self.entrance == EntranceSpec::Auto {
+                maze.set_entrance(0, 0, constants::DIR_NORTH)?;
+                maze.set_exit(self.width - 1, self.height - 1, constants::DIR_SOUTH)?;
+                if self.min_solution_length > 0 &&
+                   self.lengthen_solution(&mut maze, attempt)? < self.min_solution_length {
+                    continue;
+                }
+            }
+            return Ok(maze);
+        }
+        Err(MazeError::ConstraintUnsatisfiable(format!("no solution of {} Squares in {} attempts",
+                                                       self.min_solution_length, self.max_attempts)))
+    }
+
+    /// Builds the maze the spec describes, or reports why it couldn't.
+    ///
+    /// Unlike build_with_rng(), every attempt starts over from its own seed,
+    /// counting up from the spec's seed (or a random one), and a maze that
+    /// couldn't fit all of its rooms counts as a failure too.  After max_attempts
+    /// failures the result is a report of which constraints failed, how
+    /// close the attempts came and the seeds they used, rather than only the
+    /// last error.  An error is only returned for a spec that doesn't
+    /// validate.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::spec::{GenerationOutcome, SpecConstraint};
+    /// use mazegame::prelude::*;
+    ///
+    /// let json = r#"{"width": 6, "height": 6, "seed": 3, "entrance": "auto",
+    ///                "min_solution_length": 36, "max_attempts": 4}"#;
+    /// match MazeSpec::from_json(json).unwrap().build_outcome().unwrap() {
+    ///     GenerationOutcome::Failed(report) => {
+    ///         assert_eq!(SpecConstraint::MinSolutionLength, report.constraint);
+    ///         assert_eq!(vec![3, 4, 5, 6], report.seeds);
+    ///     }
+    ///     GenerationOutcome::Built { .. } => panic!("a 6x6 maze can't have a solution through every Square"),
+    /// }
+    /// ```
+    pub fn build_outcome(&self) -> Result<GenerationOutcome, MazeError> {
+        self.validate()?;
+        let base = match self.seed {
+            Some(seed) => seed,
+            None => random_seed()?,
+        };
+        let rooms_wanted = self.rooms.map_or(0, |r| r.count);
+        let mut report = GenerationReport {
+            constraint: SpecConstraint::MinSolutionLength,
+            attempts: 0,
+            failures: Vec::new(),
+            best_rooms: (0, rooms_wanted),
+            best_solution_length: (0, self.min_solution_length),
+            seeds: Vec::new(),
+        };
+        let (mut rooms_failed, mut solution_failed) = (0, 0);
+
+        for attempt in 1..=self.max_attempts {
+            let seed = base.wrapping_add(attempt as u64 - 1);
+            report.attempts = attempt;
+            report.seeds.push(seed);
+            let mut maze = self.generate(&mut rng::from_seed(seed))?;
+
+            let rooms_ok = maze.get_num_rooms() >= rooms_wanted;
+            report.best_rooms.0 = report.best_rooms.0.max(maze.get_num_rooms());
+            if !rooms_ok {
+                rooms_failed += 1;
+            }
+            let mut solution_ok = true;
+            if self.entrance == EntranceSpec::Auto {
+                maze.set_entrance(0, 0, constants::DIR_NORTH)?;
+                maze.set_exit(self.width - 1, self.height - 1, constants::DIR_SOUTH)?;
+                if self.min_solution_length > 0 {
+                    let length = self.lengthen_solution(&mut maze, attempt)?;
+                    report.best_solution_length.0 = report.best_solution_length.0.max(length);
+                    solution_ok = length >= self.min_solution_length;
+                    if !solution_ok {
+                        solution_failed += 1;
+                    }
+                }
+            }
+            if rooms_ok && solution_ok {
+                return Ok(GenerationOutcome::Built { maze: Box::new(maze), seed, attempts: attempt });
+            }
+        }
+
+        if rooms_failed > 0 {
+            report.failures.push((SpecConstraint::Rooms, rooms_failed));
+        }
+        if solution_failed > 0 {
+            report.failures.push((SpecConstraint::MinSolutionLength, solution_failed));
+        }
+        // How much of the way the best attempt got, to break ties
+        let reached = |constraint: SpecConstraint| match constraint {
+            SpecConstraint::Rooms => report.best_rooms.0 as f64 / report.best_rooms.1 as f64,
+            SpecConstraint::MinSolutionLength =>
+                report.best_solution_length.0 as f64 / report.best_solution_length.1 as f64,
+        };
+        report.constraint = report.failures.iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(reached(b.0).total_cmp(&reached(a.0))))
+            .map(|&(constraint, _)| constraint)
+            .unwrap_or(SpecConstraint::MinSolutionLength);
+        gen_warn!("spec_failed constraint={} attempts={}", report.constraint, report.attempts);
+        Ok(GenerationOutcome::Failed(report))
+    }
+
+    //
+    // Internal - generates the maze, without an entrance or exit.
+    //
+    fn generate<R: Rng>(&self, rng: &mut R) -> Result<Maze, MazeError> {
+        let (width, height) = self.coarse_size();
+        let mut maze = Maze::new(height, width);
+        match self.algorithm {
+            Algorithm::GrowingTree => {
+                let result = match self.rooms {
+                    Some(r) => maze.generate_with_rng((r.count, r.min_w, r.max_w, r.min_h, r.max_h), rng),
+                    None if self.allow_weave => maze.generate_perfect_woven_with_rng(rng),
+                    None => maze.generate_perfect_with_rng(rng),
+                };
+                result.map_err(MazeError::InvalidArgument)?;
+            }
+        }
+        if self.braid > 0.0 {
+            maze.braid_with_rng(self.braid, rng);
+        }
+        if self.corridor_width > 1 {
+            maze = maze.expand_passages(self.corridor_width)?;
+        }
+        Ok(maze)
+    }
+
+    //
+    // Internal - makes sure the solution is at least min_solution_length
+    // Squares long, moving the exit to the farthest Square on the outer wall
+    // if it has to.  Returns the length of the solution, or if no exit is
+    // far enough, the longest one it could have had.
+    //
+    fn lengthen_solution(&self, maze: &mut Maze, attempt: u32) -> Result<u32, MazeError> {
+        let (entrance, exit) = match (maze.entrance, maze.get_exit()) {
+            (Some(entrance), Some(exit)) => (entrance, exit),
+            _ => return Ok(0),
+        };
+        let dist = maze.distances(entrance.coord);
+        let length = |o: &Opening| dist[maze.get_offset(o.coord.x, o.coord.y)].map(|d| d + 1);
+        let current = length(&exit).unwrap_or(0);
+        if current >= self.min_solution_length {
+            gen_debug!("solution attempt={} length={} exit_moved=false", attempt, current);
+            return Ok(current);
+        }
+
+        let mut farthest: Option<(u32, Opening)> = None;
+        for y in 0..maze.rows {
+            for x in 0..maze.cols {
+                for dir in 0..constants::NUM_DIRECTIONS {
+                    let opening = Opening { coord: Coord::new(x, y), dir };
+                    if maze.neighbor(x, y, dir).is_some() || opening == entrance {
+                        continue;
+                    }
+                    if let Some(len) = length(&opening) {
+                        if farthest.is_none_or(|(best, _)| len > best) {
+                            farthest = Some((len, opening));
+                        }
+                    }
+                }
+            }
+        }
+        match farthest {
+            Some((len, opening)) if len >= self.min_solution_length => {
+                let offset = maze.get_offset(exit.coord.x, exit.coord.y);
+                maze.sq[offset].build_wall(exit.dir);
+                maze.set_exit(opening.coord.x, opening.coord.y, opening.dir)?;
+                gen_debug!("solution attempt={} length={} exit_moved=true", attempt, len);
+                Ok(len)
+            }
+            _ => {
+                let longest = farthest.map_or(0, |(len, _)| len).max(current);
+                gen_debug!("solution_too_short attempt={} longest={}", attempt, longest);
+                Ok(longest)
+            }
+        }
+    }
+
+    //
+    // Internal - the size of the maze before its passages are widened.
+    //
+    fn coarse_size(&self) -> (u32, u32) {
+        (self.width / self.corridor_width, self.height / self.corridor_width)
+    }
+}
+
+impl Maze {
+    /// Generates a maze from a JSON spec.  See the `maze::spec` module for
+    /// the format.
+    ///
+    /// # Example
+    /// ```
+    /// let json = r#"{"width": 20, "height": 10, "seed": 5, "entrance": "auto"}"#;
+    /// let maze = mazegame::Maze::from_spec(json).unwrap();
+    /// assert_eq!(20, maze.get_cols());
+    /// assert!(maze.get_entrance().is_some());
+    /// ```
+    pub fn from_spec(json: &str) -> Result<Maze, MazeError> {
+        MazeSpec::from_json(json)?.build()
+    }
+}
+
+//
+// Internal - builds the error for a bad field.
+//
+fn spec_error(field: &str, msg: &str) -> MazeError {
+    MazeError::Spec(field.to_string(), msg.to_string())
+}
+
+//
+// Internal - rejects any key in obj that isn't in allowed.  prefix is
+// prepended to key names in errors.
+//
+fn check_keys(obj: &Map<String, Value>, prefix: &str, allowed: &[&str]) -> Result<(), MazeError> {
+    match obj.keys().find(|k| !allowed.contains(&k.as_str())) {
+        Some(key) => Err(spec_error(&format!("{}{}", prefix, key), "unknown field")),
+        None => Ok(()),
+    }
+}
+
+//
+// Internal - fetches a required field that has to fit in a u32.
+//
+fn required_u32(obj: &Map<String, Value>, prefix: &str, key: &str) -> Result<u32, MazeError> {
+    let field = format!("{}{}", prefix, key);
+    let value = obj.get(key).ok_or_else(|| spec_error(&field, "is required"))?;
+    match value.as_u64() {
+        Some(v) if v <= u32::MAX as u64 => Ok(v as u32),
+        _ => Err(spec_error(&field, "must be a non-negative integer")),
+    }
+}
+
+//
+// Internal - the seed used when a spec has none.
+//
+#[cfg(feature = "std")]
+fn random_seed() -> Result<u64, MazeError> {
+    Ok(thread_rng().gen())
+}
+
+#[cfg(not(feature = "std"))]
+fn random_seed() -> Result<u64, MazeError> {
+    Err(spec_error("seed", "is required without the std feature"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_of(json: &str) -> String {
+        match MazeSpec::from_json(json) {
+            Err(MazeError::Spec(field, _)) => field,
+            other => panic!("expected a spec error for {}, got {:?}", json, other),
+        }
+    }
+
+    #[test]
+    fn test_full_spec() {
+        let json = r#"{"width":40,"height":30,"seed":123,"algorithm":"growing_tree",
+                       "rooms":{"count":6,"min_w":3,"max_w":6,"min_h":3,"max_h":5},
+                       "braid":0.3,"entrance":"auto"}"#;
+        let spec = MazeSpec::from_json(json).unwrap();
+        assert_eq!(Some(RoomSpec { count: 6, min_w: 3, max_w: 6, min_h: 3, max_h: 5 }), spec.rooms);
+        assert_eq!(0.3, spec.braid);
+
+        let maze = Maze::from_spec(json).unwrap();
+        assert_eq!((40, 30), (maze.get_cols(), maze.get_rows()));
+        assert!(maze.sq.iter().any(|sq| sq.is_part_of_room()));
+        assert_eq!(0, maze.get_entrance().unwrap().coord.x);
+        assert_eq!(39, maze.get_exit().unwrap().coord.x);
+
+        // The seed makes it repeatable
+        assert_eq!(maze, Maze::from_spec(json).unwrap());
+    }
+
+    #[test]
+    fn test_minimal_spec() {
+        let spec = MazeSpec::from_json(r#"{"width": 6, "height": 4}"#).unwrap();
+        assert_eq!(MazeSpec::new(6, 4), spec);
+
+        let maze = spec.build().unwrap();
+        assert_eq!((6, 4), (maze.get_cols(), maze.get_rows()));
+        assert_eq!(None, maze.get_entrance());
+        assert!(maze.sq.iter().all(|sq| sq.is_carved()));
+    }
+
+    #[test]
+    fn test_allow_weave() {
+        let maze = Maze::from_spec(r#"{"width": 12, "height": 12, "seed": 4, "allow_weave": true}"#).unwrap();
+        assert!(maze.sq.iter().any(|sq| sq.under != 0));
+        assert_eq!("allow_weave", field_of(r#"{"width": 12, "height": 12, "allow_weave": true,
+                                               "rooms": {"count": 1, "min_w": 2, "max_w": 3, "min_h": 2, "max_h": 3}}"#));
+    }
+
+    #[test]
+    fn test_corridor_width() {
+        let json = r#"{"width": 24, "height": 16, "seed": 8, "corridor_width": 2, "entrance": "auto",
+                       "rooms": {"count": 2, "min_w": 2, "max_w": 3, "min_h": 2, "max_h": 3}}"#;
+        let maze = Maze::from_spec(json).unwrap();
+        assert_eq!((24, 16), (maze.get_cols(), maze.get_rows()));
+        assert!(maze.walls_consistent());
+        assert!(maze.sq.iter().any(|sq| sq.is_part_of_room()));
+        assert_eq!(23, maze.get_exit().unwrap().coord.x);
+        assert!(maze.solve(maze.get_entrance().unwrap().coord, maze.get_exit().unwrap().coord).is_some());
+
+        assert_eq!("corridor_width", field_of(r#"{"width": 10, "height": 10, "corridor_width": 0}"#));
+        assert_eq!("corridor_width", field_of(r#"{"width": 10, "height": 9, "corridor_width": 2}"#));
+        assert_eq!("corridor_width", field_of(r#"{"width": 10, "height": 10, "corridor_width": 10}"#));
+        assert_eq!("allow_weave", field_of(r#"{"width": 10, "height": 10, "corridor_width": 2, "allow_weave": true}"#));
+    }
+
+    #[test]
+    fn test_min_solution_length() {
+        let solution = |maze: &Maze| {
+            maze.solve(maze.get_entrance().unwrap().coord, maze.get_exit().unwrap().coord).unwrap().len() as u32
+        };
+        let mut spec = MazeSpec::new(16, 16);
+        spec.seed = Some(422);
+        spec.entrance = EntranceSpec::Auto;
+        spec.braid = 1.0;
+        let short = solution(&spec.build().unwrap());
+
+        spec.min_solution_length = short + 20;
+        let maze = spec.build().unwrap();
+        assert!(solution(&maze) >= short + 20);
+        assert!(maze.walls_consistent());
+        assert_eq!(maze, spec.build().unwrap());
+
+        let json = r#"{"width": 10, "height": 10, "seed": 1, "entrance": "auto", "min_solution_length": 30}"#;
+        let maze = Maze::from_spec(json).unwrap();
+        assert!(solution(&maze) >= 30);
+    }
+
+    #[test]
+    fn test_min_solution_length_impossible() {
+        let mut spec = MazeSpec::new(6, 6);
+        spec.seed = Some(1);
+        spec.entrance = EntranceSpec::Auto;
+        spec.min_solution_length = 37;
+        assert!(matches!(spec.build(), Err(MazeError::ConstraintUnsatisfiable(_))));
+
+        // Fits in the maze, but braiding leaves too many shortcuts
+        spec.braid = 1.0;
+        spec.min_solution_length = 30;
+        assert!(matches!(spec.build(), Err(MazeError::ConstraintUnsatisfiable(_))));
+
+        assert_eq!("min_solution_length", field_of(r#"{"width": 10, "height": 10, "min_solution_length": 20}"#));
+    }
+
+    #[test]
+    fn test_build_outcome() {
+        let mut spec = MazeSpec::new(16, 16);
+        spec.seed = Some(422);
+        spec.entrance = EntranceSpec::Auto;
+        spec.min_solution_length = 40;
+        match spec.build_outcome().unwrap() {
+            GenerationOutcome::Built { maze, seed, attempts } => {
+                let mut again = spec.clone();
+                again.seed = Some(seed);
+                again.max_attempts = 1;
+                assert_eq!(*maze, again.build().unwrap());
+                assert_eq!(seed, 422 + attempts as u64 - 1);
+                // build() retries the same way
+                assert_eq!(*maze, spec.build().unwrap());
+            }
+            GenerationOutcome::Failed(report) => panic!("unexpected failure: {}", report),
+        }
+
+        spec.max_attempts = 0;
+        assert_eq!("max_attempts", match spec.build_outcome() {
+            Err(MazeError::Spec(field, _)) => field,
+            other => panic!("expected a spec error, got {:?}", other),
+        });
+        assert_eq!("max_attempts", field_of(r#"{"width": 10, "height": 10, "max_attempts": -1}"#));
+    }
+
+    #[test]
+    fn test_build_outcome_unsatisfiable() {
+        // A tiny maze with far too many rooms and a solution longer than the
+        // maze
+        let json = r#"{"width": 6, "height": 6, "seed": 20, "entrance": "auto", "min_solution_length": 60,
+                       "max_attempts": 7, "rooms": {"count": 30, "min_w": 2, "max_w": 3, "min_h": 2, "max_h": 3}}"#;
+        let spec = MazeSpec::from_json(json).unwrap();
+        assert!(matches!(spec.build(), Err(MazeError::ConstraintUnsatisfiable(_))));
+        let report = match spec.build_outcome().unwrap() {
+            GenerationOutcome::Failed(report) => report,
+            GenerationOutcome::Built { .. } => panic!("the spec can't be met"),
+        };
+        assert_eq!(7, report.attempts);
+        assert_eq!((20..27).collect::<Vec<u64>>(), report.seeds);
+        assert_eq!(vec![(SpecConstraint::Rooms, 7), (SpecConstraint::MinSolutionLength, 7)], report.failures);
+        assert!(report.best_rooms.0 >= 1 && report.best_rooms.0 < 30);
+        assert!(report.best_solution_length.0 > 0 && report.best_solution_length.0 <= 36);
+        // Both fail every time, but the rooms are the furthest off
+        assert_eq!(SpecConstraint::Rooms, report.constraint);
+        let text = report.to_string();
+        assert!(text.starts_with("couldn't meet rooms.count; rooms.count failed 7 of 7 attempts"), "{}", text);
+        assert!(text.ends_with("seeds 20 to 26"), "{}", text);
+        assert!(matches!(GenerationOutcome::Failed(report).into_result(),
+                         Err(MazeError::ConstraintUnsatisfiable(_))));
+
+        // With room to spare for the rooms, only the solution length binds
+        let mut spec = MazeSpec::new(6, 6);
+        spec.seed = Some(20);
+        spec.entrance = EntranceSpec::Auto;
+        spec.min_solution_length = 60;
+        spec.max_attempts = 3;
+        spec.rooms = Some(RoomSpec { count: 1, min_w: 2, max_w: 2, min_h: 2, max_h: 2 });
+        match spec.build_outcome().unwrap() {
+            GenerationOutcome::Failed(report) => {
+                assert_eq!(SpecConstraint::MinSolutionLength, report.constraint);
+                assert_eq!(vec![(SpecConstraint::MinSolutionLength, 3)], report.failures);
+                assert_eq!((1, 1), report.best_rooms);
+                assert_eq!(3, report.attempts);
+            }
+            GenerationOutcome::Built { .. } => panic!("the spec can't be met"),
+        }
+    }
+
+    #[test]
+    fn test_braid_removes_dead_ends() {
+        let dead_ends = |braid: f64| {
+            let mut spec = MazeSpec::new(20, 20);
+            spec.seed = Some(9);
+            spec.braid = braid;
+            let maze = spec.build().unwrap();
+            (0..20).flat_map(|y| (0..20).map(move |x| (x, y)))
+                   .filter(|&(x, y)| maze.open_neighbors(x, y).len() == 1)
+                   .count()
+        };
+        assert!(dead_ends(0.0) > 0);
+        assert!(dead_ends(0.5) < dead_ends(0.0));
+        assert_eq!(0, dead_ends(1.0));
+    }
+
+    #[test]
+    fn test_malformed_specs() {
+        assert_eq!("height", field_of(r#"{"width": 10}"#));
+        assert_eq!("width", field_of(r#"{"width": 1, "height": 10}"#));
+        assert_eq!("height", field_of(r#"{"width": 10, "height": 5000}"#));
+        assert_eq!("width", field_of(r#"{"width": "ten", "height": 10}"#));
+        assert_eq!("colour", field_of(r#"{"width": 10, "height": 10, "colour": "red"}"#));
+        assert_eq!("seed", field_of(r#"{"width": 10, "height": 10, "seed": -4}"#));
+        assert_eq!("algorithm", field_of(r#"{"width": 10, "height": 10, "algorithm": "prim"}"#));
+        assert_eq!("braid", field_of(r#"{"width": 10, "height": 10, "braid": 1.5}"#));
+        assert_eq!("entrance", field_of(r#"{"width": 10, "height": 10, "entrance": "left"}"#));
+        assert_eq!("rooms", field_of(r#"{"width": 10, "height": 10, "rooms": 3}"#));
+        assert_eq!("allow_weave", field_of(r#"{"width": 10, "height": 10, "allow_weave": 1}"#));
+        assert_eq!("rooms.min_h", field_of(r#"{"width": 10, "height": 10,
+                                              "rooms": {"count": 1, "min_w": 2, "max_w": 3, "max_h": 3}}"#));
+        assert_eq!("rooms.max_w", field_of(r#"{"width": 10, "height": 10,
+                                              "rooms": {"count": 1, "min_w": 4, "max_w": 3, "min_h": 2, "max_h": 3}}"#));
+        assert_eq!("rooms.max_h", field_of(r#"{"width": 10, "height": 10,
+                                              "rooms": {"count": 1, "min_w": 2, "max_w": 3, "min_h": 2, "max_h": 9}}"#));
+        assert_eq!("rooms.depth", field_of(r#"{"width": 10, "height": 10,
+                                              "rooms": {"count": 1, "min_w": 2, "max_w": 3, "min_h": 2, "max_h": 3, "depth": 1}}"#));
+
+        // Anything that isn't a JSON object is a parse error
+        assert!(matches!(MazeSpec::from_json("[1, 2]"), Err(MazeError::Parse(_))));
+        assert!(matches!(MazeSpec::from_json("{width"), Err(MazeError::Parse(_))));
+    }
+}
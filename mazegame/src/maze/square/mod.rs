@@ -1,11 +1,34 @@
-pub use constants;
+// Re-exported so the older `mazegame::maze::square::constants` path keeps
+// working
+pub use crate::constants;
 
 /// A struct representing an individual 'square', or distinct location, in the maze.
 /// It contains 4 walls, any number of which may be carved.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Square {
     pub wall_present: [bool; constants::NUM_DIRECTIONS as usize],
     pub id: i32,
+    /// Passages tunneling under the Square in a weave maze: bit 0 is set for
+    /// one running north-south, bit 1 for one running east-west.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub under: u8,
+    /// Open walls that can only be passed through outward: bit n is set when
+    /// the opening in direction n is one-way.  The Square on the other side
+    /// keeps its wall.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub one_way: u8,
+    /// Open walls that are secret passages: bit n is set when the opening
+    /// in direction n is one.  The Square on the other side has the same
+    /// passage marked.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub secret: u8,
+}
+
+impl Default for Square {
+    fn default() -> Square {
+        Square::new()
+    }
 }
 
 impl Square {
@@ -14,6 +37,9 @@ impl Square {
         Square {
             wall_present: [true, true, true, true],
             id: constants::ID_UNCARVED,
+            under: 0,
+            one_way: 0,
+            secret: 0,
         }
     }
     
@@ -21,72 +47,66 @@ impl Square {
     /// 
     /// # Example:
     /// ```
-    /// use mazegame::constants;
-    /// use mazegame::maze::square;
+    /// use mazegame::prelude::*;
     /// 
-    /// let mut sq = square::Square::new();
-    /// sq.break_wall(constants::DIR_NORTH);
+    /// let mut sq = Square::new();
+    /// sq.break_wall(DIR_NORTH);
     /// ```
     pub fn break_wall(&mut self, dir: u32) {
         self.set_wall_state(dir, false);
     }
 
-    /// Creates a wall in the specified direction.
+    /// Creates a wall in the specified direction.  A secret passage that
+    /// way is gone along with the opening.
     /// 
     /// # Example:
     /// ```
-    /// use mazegame::constants;
-    /// use mazegame::maze::square;
+    /// use mazegame::prelude::*;
     /// 
-    /// let mut sq = square::Square::new();
-    /// sq.build_wall(constants::DIR_NORTH);
+    /// let mut sq = Square::new();
+    /// sq.build_wall(DIR_NORTH);
     /// ```
     pub fn build_wall(&mut self, dir: u32) {
         self.set_wall_state(dir, true);
+        self.set_secret(dir, false);
     }
 
     pub fn is_part_of_room(&self) -> bool {
-        if self.id == constants::ID_MAZE_PATH || self.id == constants::ID_UNCARVED {
-            false
-        } else {
-            true
-        }
+        !(self.id == constants::ID_MAZE_PATH || self.id == constants::ID_UNCARVED)
     }
 
     /// Returns whether the Square is 'carved' - that is, at least one wall has been removed.
     /// 
     /// # Example:
     /// ```
-    /// use mazegame::constants;
-    /// use mazegame::maze::square;
+    /// use mazegame::prelude::*;
     /// 
-    /// let mut sq = square::Square::new();
-    /// sq.break_wall(constants::DIR_NORTH);
+    /// let mut sq = Square::new();
+    /// sq.break_wall(DIR_NORTH);
     /// let result = sq.is_carved();
     /// assert_eq!(true, result);
     /// ```
     pub fn is_carved(&self) -> bool {
-        if self.is_wall_present(constants::DIR_NORTH) == false { return true; }
-        if self.is_wall_present(constants::DIR_SOUTH) == false { return true; }
-        if self.is_wall_present(constants::DIR_EAST) == false { return true; }
-        if self.is_wall_present(constants::DIR_WEST) == false { return true; }
-        return false;
+        if !self.is_wall_present(constants::DIR_NORTH) { return true; }
+        if !self.is_wall_present(constants::DIR_SOUTH) { return true; }
+        if !self.is_wall_present(constants::DIR_EAST) { return true; }
+        if !self.is_wall_present(constants::DIR_WEST) { return true; }
+        false
     }
 
     /// Returns the state of the wall in a given direction (either present or absent).
     /// 
     /// # Example:
     /// ```
-    /// use mazegame::constants;
-    /// use mazegame::maze::square;
+    /// use mazegame::prelude::*;
     /// 
-    /// let mut sq = square::Square::new();
+    /// let mut sq = Square::new();
     /// 
-    /// let result = sq.is_wall_present(constants::DIR_NORTH);
+    /// let result = sq.is_wall_present(DIR_NORTH);
     /// assert_eq!(true, result);
     /// 
-    /// sq.break_wall(constants::DIR_NORTH);
-    /// let result = sq.is_wall_present(constants::DIR_NORTH);
+    /// sq.break_wall(DIR_NORTH);
+    /// let result = sq.is_wall_present(DIR_NORTH);
     /// assert_eq!(false, result);
     /// ```
     pub fn is_wall_present(&self, dir: u32) -> bool {
@@ -107,6 +127,134 @@ impl Square {
         }
     }
 
+    /// Returns a bitmask of the Square's open walls.  Bit n is set when the
+    /// wall in direction n (see the constants module) has been removed.
+    /// 
+    /// # Example:
+    /// ```
+    /// use mazegame::prelude::*;
+    /// 
+    /// let mut sq = Square::new();
+    /// sq.break_wall(DIR_EAST);
+    /// assert_eq!(1 << DIR_EAST, sq.open_mask());
+    /// ```
+    pub fn open_mask(&self) -> u8 {
+        let mut mask = 0;
+        for dir in 0..constants::NUM_DIRECTIONS {
+            if !self.is_wall_present(dir) {
+                mask |= 1 << dir;
+            }
+        }
+        mask
+    }
+
+    /// Sets the state of all four walls from a bitmask in the format returned
+    /// by open_mask().  Bits above the four direction bits are ignored.
+    /// 
+    /// # Example:
+    /// ```
+    /// use mazegame::prelude::*;
+    /// 
+    /// let mut sq = Square::new();
+    /// sq.set_open_mask(1 << DIR_SOUTH);
+    /// assert_eq!(false, sq.is_wall_present(DIR_SOUTH));
+    /// assert_eq!(true, sq.is_wall_present(DIR_NORTH));
+    /// ```
+    pub fn set_open_mask(&mut self, mask: u8) {
+        for dir in 0..constants::NUM_DIRECTIONS {
+            self.set_wall_state(dir, mask & (1 << dir) == 0);
+        }
+    }
+
+    /// Returns whether a passage runs under the Square along the axis of
+    /// direction dir.
+    ///
+    /// # Example:
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut sq = Square::new();
+    /// sq.add_under_passage(DIR_EAST);
+    /// assert_eq!(true, sq.has_under_passage(DIR_WEST));
+    /// assert_eq!(false, sq.has_under_passage(DIR_NORTH));
+    /// ```
+    pub fn has_under_passage(&self, dir: u32) -> bool {
+        self.under & under_bit(dir) != 0
+    }
+
+    /// Records a passage running under the Square along the axis of
+    /// direction dir.
+    pub fn add_under_passage(&mut self, dir: u32) {
+        self.under |= under_bit(dir);
+    }
+
+    /// Removes the passage running under the Square along the axis of
+    /// direction dir, if there is one.
+    pub fn remove_under_passage(&mut self, dir: u32) {
+        self.under &= !under_bit(dir);
+    }
+
+    /// Returns whether the opening in direction dir is a one-way passage out
+    /// of the Square.
+    ///
+    /// # Example:
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut sq = Square::new();
+    /// sq.set_one_way(DIR_SOUTH, true);
+    /// assert_eq!(true, sq.is_one_way(DIR_SOUTH));
+    /// assert_eq!(false, sq.is_one_way(DIR_NORTH));
+    /// ```
+    pub fn is_one_way(&self, dir: u32) -> bool {
+        dir < constants::NUM_DIRECTIONS && self.one_way & (1 << dir) != 0
+    }
+
+    /// Marks or unmarks the opening in direction dir as a one-way passage.
+    pub fn set_one_way(&mut self, dir: u32, one_way: bool) {
+        if dir < constants::NUM_DIRECTIONS {
+            if one_way {
+                self.one_way |= 1 << dir;
+            } else {
+                self.one_way &= !(1 << dir);
+            }
+        }
+    }
+
+    /// Returns whether the opening in direction dir is a secret passage.
+    ///
+    /// # Example:
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut sq = Square::new();
+    /// sq.break_wall(DIR_WEST);
+    /// sq.set_secret(DIR_WEST, true);
+    /// assert_eq!(true, sq.is_secret(DIR_WEST));
+    /// sq.build_wall(DIR_WEST);
+    /// assert_eq!(false, sq.is_secret(DIR_WEST));
+    /// ```
+    pub fn is_secret(&self, dir: u32) -> bool {
+        dir < constants::NUM_DIRECTIONS && self.secret & (1 << dir) != 0
+    }
+
+    /// Returns whether a renderer draws a wall in direction dir: one is
+    /// there, or the opening is a secret passage.
+    pub fn is_wall_drawn(&self, dir: u32) -> bool {
+        self.is_wall_present(dir) || self.is_secret(dir)
+    }
+
+    /// Marks or unmarks the opening in direction dir as a secret passage.
+    pub fn set_secret(&mut self, dir: u32, secret: bool) {
+        if dir < constants::NUM_DIRECTIONS {
+            if secret {
+                self.secret |= 1 << dir;
+            } else {
+                self.secret &= !(1 << dir);
+            }
+        }
+    }
+
     //
     // Internal - set the state of a particular wall in the square (present or absent).
     //
@@ -131,7 +279,19 @@ impl Square {
     }
 }
 
+//
+// Internal - the bit of Square::under for the axis of direction dir.
+//
+fn under_bit(dir: u32) -> u8 {
+    match dir {
+        constants::DIR_NORTH | constants::DIR_SOUTH => 1,
+        constants::DIR_EAST | constants::DIR_WEST => 2,
+        _ => 0,
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -179,4 +339,20 @@ mod tests {
         square.break_wall(17);
         assert_eq!([true, true, true, true], square.wall_present);
     }
+
+    #[test]
+    fn test_open_mask() {
+        let mut square = Square::new();
+        assert_eq!(0, square.open_mask());
+        square.break_wall(constants::DIR_NORTH);
+        square.break_wall(constants::DIR_WEST);
+        assert_eq!(0b1001, square.open_mask());
+
+        // Round trip every possible mask
+        for mask in 0..16 {
+            let mut square = Square::new();
+            square.set_open_mask(mask);
+            assert_eq!(mask, square.open_mask());
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,100 @@
+//! Numbers describing the last growing tree generation of a Maze, for tuning
+//! the generators.  They're kept on the Maze rather than returned, so the
+//! generators keep their signatures.
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use crate::maze::generator::GrowthStats;
+use crate::maze::Maze;
+
+/// What the last generation of a Maze did.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GenerationStats {
+    /// Squares carved when generation finished, including rooms.
+    pub cells_carved: u32,
+    /// Calls to carve() made while generating, including ones for rooms.
+    pub carve_calls: u32,
+    /// Times the growing tree hit a dead end and popped its stack.
+    pub backtracks: u32,
+    /// The deepest the growing tree's stack got.
+    pub max_stack_depth: u32,
+    /// How long generation took.  Always zero without the std feature.
+    pub duration: Duration,
+}
+
+impl Maze {
+    /// Returns the stats of the Maze's last growing tree generation, or
+    /// None if it hasn't been generated that way.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// assert_eq!(None, maze.last_generation_stats());
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// assert_eq!(100, maze.last_generation_stats().unwrap().cells_carved);
+    /// ```
+    pub fn last_generation_stats(&self) -> Option<&GenerationStats> {
+        self.last_stats.as_ref()
+    }
+
+    //
+    // Internal - runs generate, keeping its stats if it succeeds.
+    //
+    pub(crate) fn measure_generation<E, F>(&mut self, generate: F) -> Result<(), E>
+        where F: FnOnce(&mut Maze) -> Result<GrowthStats, E> {
+        self.last_stats = None;
+        let carve_count = self.carve_count;
+        #[cfg(feature = "std")]
+        let start = Instant::now();
+
+        let growth = generate(self)?;
+
+        #[cfg(feature = "std")]
+        let duration = start.elapsed();
+        #[cfg(not(feature = "std"))]
+        let duration = Duration::default();
+        self.last_stats = Some(GenerationStats {
+            cells_carved: self.sq.iter().filter(|sq| sq.is_carved() || sq.is_part_of_room()).count() as u32,
+            carve_calls: self.carve_count.wrapping_sub(carve_count),
+            backtracks: growth.backtracks,
+            max_stack_depth: growth.max_depth,
+            duration,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perfect() {
+        let mut maze = Maze::new(12, 17);
+        maze.generate_perfect_seeded(428).unwrap();
+        let stats = *maze.last_generation_stats().unwrap();
+        assert_eq!(12 * 17, stats.cells_carved);
+        // A perfect maze is a spanning tree, with one carve per edge
+        assert_eq!(12 * 17 - 1, stats.carve_calls);
+        assert!(stats.backtracks > 0);
+        assert!(stats.max_stack_depth > 1 && stats.max_stack_depth < 12 * 17);
+
+        // Rooms are carved too
+        maze.reset();
+        assert_eq!(None, maze.last_generation_stats());
+        maze.generate_seeded((4, 2, 4, 2, 4), 428).unwrap();
+        let rooms = *maze.last_generation_stats().unwrap();
+        assert_eq!(12 * 17, rooms.cells_carved);
+        assert!(rooms.carve_calls > 0);
+        assert!(rooms.backtracks > 0);
+    }
+
+    #[test]
+    fn test_failed() {
+        // A single Square has nowhere to grow
+        let mut maze = Maze::new(1, 1);
+        assert!(maze.generate_perfect_seeded(1).is_err());
+        assert_eq!(None, maze.last_generation_stats());
+    }
+}
@@ -0,0 +1,228 @@
+//! Symmetric mazes.  The generator picks walls from a fundamental domain (a
+//! half or a quarter of the grid) and knocks down each one together with its
+//! images under the symmetry, so the walls always stay symmetric.
+//!
+//! A symmetric perfect maze usually doesn't exist: under a half turn, a grid
+//! with an even number of Squares has no Square or wall at its center for a
+//! symmetric tree to pass through.  Once no more walls can be opened without
+//! making a loop, the generator stitches the separate pieces together with
+//! more symmetric passages, which adds a few loops.
+use alloc::string::String;
+use alloc::vec::Vec;
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use crate::constants;
+use crate::maze::info::GenerationAlgorithm;
+use crate::maze::transform::{half_turn, mirror_dir, quarter_turn};
+use crate::maze::{find_region, Maze};
+use crate::rng;
+
+/// The symmetries a maze can be generated with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    /// The east half mirrors the west half.
+    MirrorX,
+    /// The south half mirrors the north half.
+    MirrorY,
+    /// The maze looks the same turned upside down.
+    Rotate180,
+    /// The maze looks the same after a quarter turn.  Only square mazes can
+    /// have this symmetry.
+    Rotate90,
+}
+
+// A wall, as the Square on its north or west side and DIR_SOUTH or DIR_EAST.
+type Wall = (u32, u32, u32);
+
+impl Maze {
+    /// Generates a maze with the given symmetry.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::symmetric::Symmetry;
+    ///
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// maze.generate_symmetric(Symmetry::Rotate90).unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn generate_symmetric(&mut self, symmetry: Symmetry) -> Result<(), String> {
+        self.generate_symmetric_seeded(symmetry, thread_rng().gen())
+    }
+
+    /// Generates a symmetric maze from a seed.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::symmetric::Symmetry;
+    ///
+    /// let mut maze = Maze::new(6, 9);
+    /// maze.generate_symmetric_seeded(Symmetry::MirrorX, 4).unwrap();
+    /// assert_eq!(maze.sq[0].is_wall_present(DIR_SOUTH),
+    ///            maze.sq[8].is_wall_present(DIR_SOUTH));
+    /// ```
+    pub fn generate_symmetric_seeded(&mut self, symmetry: Symmetry, seed: u64) -> Result<(), String> {
+        self.generate_symmetric_with_rng(symmetry, &mut rng::from_seed(seed))?;
+        self.record_generation(seed, GenerationAlgorithm::Symmetric(symmetry), None);
+        Ok(())
+    }
+
+    /// Generates a symmetric maze using the supplied random number
+    /// generator.  Meant for a freshly created Maze.
+    pub fn generate_symmetric_with_rng<R: Rng>(&mut self, symmetry: Symmetry, rng: &mut R) -> Result<(), String> {
+        if symmetry == Symmetry::Rotate90 && self.rows != self.cols {
+            return Err(format!("A {}x{} maze can't have quarter turn symmetry", self.cols, self.rows));
+        }
+
+        // One wall from each orbit, in random order
+        let mut walls = Vec::new();
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                for &dir in [constants::DIR_SOUTH, constants::DIR_EAST].iter() {
+                    let wall = (x, y, dir);
+                    if self.neighbor(x, y, dir).is_some() && self.wall_orbit(symmetry, wall)[0] == wall {
+                        walls.push(wall);
+                    }
+                }
+            }
+        }
+        rng.shuffle(&mut walls);
+
+        let mut region: Vec<usize> = (0..self.sq.len()).collect();
+        let mut carved = 0;
+        let mut stitched = 0;
+        // First only open walls that don't make loops, then stitch together
+        // whatever pieces are left
+        for &stitching in [false, true].iter() {
+            for &wall in walls.iter() {
+                let orbit = self.wall_orbit(symmetry, wall);
+                let mut joins = Vec::new();
+                let mut loops = false;
+                for &(x, y, dir) in orbit.iter() {
+                    let n = self.neighbor(x, y, dir).unwrap();
+                    let (a, b) = (self.get_offset(x, y), self.get_offset(n.x, n.y));
+                    let (a, b) = (find_region(&mut region, a), find_region(&mut region, b));
+                    if a == b || joins.iter().any(|&(p, q)| (p, q) == (a, b) || (p, q) == (b, a)) {
+                        loops = true;
+                    } else {
+                        joins.push((a, b));
+                    }
+                }
+                if joins.is_empty() || (loops && !stitching) {
+                    continue;
+                }
+                for &(x, y, dir) in orbit.iter() {
+                    self.carve(x, y, dir, constants::ID_MAZE_PATH, false)?;
+                    let n = self.neighbor(x, y, dir).unwrap();
+                    let (a, b) = (self.get_offset(x, y), self.get_offset(n.x, n.y));
+                    let (a, b) = (find_region(&mut region, a), find_region(&mut region, b));
+                    region[a] = b;
+                }
+                if stitching {
+                    stitched += 1;
+                } else {
+                    carved += 1;
+                }
+            }
+        }
+        gen_debug!("symmetric symmetry={:?} carved={} stitched={}", symmetry, carved, stitched);
+        Ok(())
+    }
+
+    //
+    // Internal - returns a wall and its images under the symmetry, smallest
+    // first and without duplicates.
+    //
+    fn wall_orbit(&self, symmetry: Symmetry, wall: Wall) -> Vec<Wall> {
+        let turns = match symmetry {
+            Symmetry::Rotate90 => 3,
+            _ => 1,
+        };
+        let mut orbit = vec![wall];
+        let (mut x, mut y, mut dir) = wall;
+        for _ in 0..turns {
+            let (tx, ty, tdir) = match symmetry {
+                Symmetry::MirrorX => (self.cols - 1 - x, y, mirror_dir(dir, constants::DIR_EAST)),
+                Symmetry::MirrorY => (x, self.rows - 1 - y, mirror_dir(dir, constants::DIR_NORTH)),
+                Symmetry::Rotate180 => (self.cols - 1 - x, self.rows - 1 - y, half_turn(dir)),
+                Symmetry::Rotate90 => (self.cols - 1 - y, x, quarter_turn(dir)),
+            };
+            x = tx;
+            y = ty;
+            dir = tdir;
+            orbit.push(self.normalize_wall((x, y, dir)));
+        }
+        orbit.sort();
+        orbit.dedup();
+        orbit
+    }
+
+    //
+    // Internal - names a wall by the Square on its north or west side.
+    //
+    fn normalize_wall(&self, (x, y, dir): Wall) -> Wall {
+        match dir {
+            constants::DIR_NORTH | constants::DIR_WEST => {
+                let n = self.neighbor(x, y, dir).unwrap();
+                (n.x, n.y, half_turn(dir))
+            }
+            _ => (x, y, dir),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::test_mazes::connected;
+
+    // Whether every wall matches its image under the symmetry
+    fn is_symmetric(maze: &Maze, symmetry: Symmetry) -> bool {
+        let (cols, rows) = (maze.get_cols(), maze.get_rows());
+        (0..rows).all(|y| (0..cols).all(|x| (0..constants::NUM_DIRECTIONS).all(|dir| {
+            let (tx, ty, tdir) = match symmetry {
+                Symmetry::MirrorX => (cols - 1 - x, y, mirror_dir(dir, constants::DIR_EAST)),
+                Symmetry::MirrorY => (x, rows - 1 - y, mirror_dir(dir, constants::DIR_NORTH)),
+                Symmetry::Rotate180 => (cols - 1 - x, rows - 1 - y, half_turn(dir)),
+                Symmetry::Rotate90 => (cols - 1 - y, x, quarter_turn(dir)),
+            };
+            maze.sq[maze.get_offset(x, y)].is_wall_present(dir) ==
+                maze.sq[maze.get_offset(tx, ty)].is_wall_present(tdir)
+        })))
+    }
+
+    #[test]
+    fn test_symmetries() {
+        let cases = [(Symmetry::MirrorX, 8, 10), (Symmetry::MirrorX, 7, 9),
+                     (Symmetry::MirrorY, 10, 8), (Symmetry::MirrorY, 9, 5),
+                     (Symmetry::Rotate180, 8, 12), (Symmetry::Rotate180, 9, 7),
+                     (Symmetry::Rotate90, 10, 10), (Symmetry::Rotate90, 9, 9)];
+        for &(symmetry, rows, cols) in cases.iter() {
+            for seed in 0..4 {
+                let mut maze = Maze::new(rows, cols);
+                maze.generate_symmetric_seeded(symmetry, seed).unwrap();
+                assert!(is_symmetric(&maze, symmetry), "{:?} {}x{} seed {}", symmetry, cols, rows, seed);
+                assert!(connected(&maze), "{:?} {}x{} seed {}", symmetry, cols, rows, seed);
+                assert!(maze.walls_consistent());
+            }
+        }
+    }
+
+    #[test]
+    fn test_mostly_perfect() {
+        // Stitching only adds a handful of loops
+        let mut maze = Maze::new(16, 16);
+        maze.generate_symmetric_seeded(Symmetry::Rotate90, 416).unwrap();
+        let passages: usize = (0..16).flat_map(|y| (0..16).map(move |x| (x, y)))
+                                     .map(|(x, y)| maze.open_neighbors(x, y).len()).sum::<usize>() / 2;
+        assert!((16 * 16 - 1..16 * 16 + 8).contains(&passages), "{} passages", passages);
+    }
+
+    #[test]
+    fn test_quarter_turn_needs_square() {
+        let mut maze = Maze::new(8, 10);
+        assert!(maze.generate_symmetric_seeded(Symmetry::Rotate90, 1).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+//! Mazes and checks shared by this crate's own tests.
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::maze::{find_region, Coord, Maze};
+use crate::rng;
+
+/// A 30x30 dungeon of up to `rooms` rooms joined to its passages, with the
+/// entrance at the top left and the exit at the bottom right.
+pub(crate) fn dungeon(rooms: u32, seed: u64) -> Maze {
+    let mut maze = Maze::new(30, 30);
+    maze.generate_seeded((rooms, 3, 5, 3, 5), seed).unwrap();
+    // Generated rooms aren't joined to the passages until this
+    maze.connect_regions_with_rng(&mut rng::from_seed(seed));
+    maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+    maze.set_exit(29, 29, constants::DIR_SOUTH).unwrap();
+    maze
+}
+
+/// The number of independent loops among the carved Squares: passages,
+/// less Squares, plus connected parts.
+pub(crate) fn cycles(maze: &Maze) -> i64 {
+    let mut region: Vec<usize> = (0..maze.sq.len()).collect();
+    let mut passages = 0;
+    for i in 0..maze.sq.len() {
+        let c = maze.get_coord(i);
+        for n in maze.open_neighbors(c.x, c.y) {
+            let j = maze.get_offset(n.x, n.y);
+            if i < j {
+                passages += 1;
+            }
+            let (ri, rj) = (find_region(&mut region, i), find_region(&mut region, j));
+            region[ri] = rj;
+        }
+    }
+    let carved: Vec<usize> = (0..maze.sq.len()).filter(|&i| maze.sq[i].id != constants::ID_UNCARVED).collect();
+    let parts = carved.iter().filter(|&&i| find_region(&mut region, i) == i).count() as i64;
+    passages - carved.len() as i64 + parts
+}
+
+/// Whether every Square can be reached from (0, 0).
+pub(crate) fn connected(maze: &Maze) -> bool {
+    maze.distances(Coord::new(0, 0)).iter().all(|d| d.is_some())
+}
+
+/// Panics unless maze is perfect: connected, with one passage fewer than
+/// it has Squares.
+pub(crate) fn assert_perfect(maze: &Maze) {
+    let passages: usize = (0..maze.sq.len()).map(|i| maze.get_coord(i))
+                                            .map(|c| maze.open_neighbors(c.x, c.y).len()).sum();
+    assert_eq!(maze.sq.len() - 1, passages / 2);
+    assert!(connected(maze));
+}
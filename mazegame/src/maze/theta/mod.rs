@@ -0,0 +1,478 @@
+//! Circular ("theta") mazes made of concentric rings.  Ring 0 is a single
+//! cell in the center; each ring after it is split into enough cells to keep
+//! them roughly square, so a cell can have more than one neighbor outward.
+//! Directions are `constants::THETA_DIR_*`; a cell's outward neighbors are
+//! `THETA_DIR_OUTWARD`, `THETA_DIR_OUTWARD + 1` and so on, counting clockwise.
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::generator::{self, Carvable};
+use crate::rng;
+
+/// A (ring, index) position in a ThetaMaze.  Cells in a ring are numbered
+/// clockwise from the top.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThetaCoord {
+    pub ring: u32,
+    pub index: u32,
+}
+
+impl ThetaCoord {
+    /// Creates a new ThetaCoord.
+    pub fn new(ring: u32, index: u32) -> ThetaCoord {
+        ThetaCoord { ring, index }
+    }
+}
+
+/// A single cell in a ThetaMaze.  Each cell owns its inward wall and the
+/// wall on its clockwise side; its other walls belong to its neighbors.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThetaCell {
+    pub inward_wall: bool,
+    pub clockwise_wall: bool,
+    pub id: i32,
+}
+
+impl ThetaCell {
+    /// Creates a new ThetaCell with its walls standing.
+    pub fn new() -> ThetaCell {
+        ThetaCell {
+            inward_wall: true,
+            clockwise_wall: true,
+            id: constants::ID_UNCARVED,
+        }
+    }
+}
+
+impl Default for ThetaCell {
+    fn default() -> ThetaCell {
+        ThetaCell::new()
+    }
+}
+
+/// A circular maze of `rings` concentric rings.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThetaMaze {
+    // The number of cells in each ring, and where each ring starts in cells
+    ring_sizes: Vec<u32>,
+    ring_starts: Vec<u32>,
+    cells: Vec<ThetaCell>,
+    exit: Option<u32>,
+}
+
+impl ThetaMaze {
+    /// Creates a new, uncarved theta maze.  Each ring is as many times
+    /// bigger than the one inside it as keeps its cells about as wide as they
+    /// are deep.
+    ///
+    /// # Example
+    /// ```
+    /// let maze = mazegame::maze::theta::ThetaMaze::new(4);
+    /// assert_eq!(vec![1, 6, 12, 24], (0..4).map(|r| maze.ring_size(r)).collect::<Vec<u32>>());
+    /// ```
+    pub fn new(rings: u32) -> ThetaMaze {
+        let mut ring_sizes = if rings == 0 { Vec::new() } else { vec![1] };
+        for ring in 1..rings {
+            let prev = ring_sizes[ring as usize - 1];
+            // The ring's circumference over the inner ring's cell count,
+            // rounded, with 2 * pi taken as 710 / 113
+            let ratio = ((2 * 710 * ring + 113 * prev) / (2 * 113 * prev)).max(1);
+            ring_sizes.push(prev * ratio);
+        }
+        let ring_starts = ring_sizes.iter().scan(0, |start, &size| {
+            let this = *start;
+            *start += size;
+            Some(this)
+        }).collect();
+        let count = ring_sizes.iter().sum::<u32>() as usize;
+        ThetaMaze {
+            ring_sizes,
+            ring_starts,
+            cells: vec![ThetaCell::new(); count],
+            exit: None,
+        }
+    }
+
+    /// Returns the number of rings, including the center.
+    pub fn get_rings(&self) -> u32 {
+        self.ring_sizes.len() as u32
+    }
+
+    /// Returns the number of cells in a ring, or 0 if there's no such ring.
+    pub fn ring_size(&self, ring: u32) -> u32 {
+        self.ring_sizes.get(ring as usize).cloned().unwrap_or(0)
+    }
+
+    /// Returns the number of cells in the maze.
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns every cell position in the maze, ring by ring.
+    pub fn coords(&self) -> Vec<ThetaCoord> {
+        (0..self.get_rings()).flat_map(|r| (0..self.ring_size(r)).map(move |i| ThetaCoord::new(r, i))).collect()
+    }
+
+    /// Returns whether `c` is one of the maze's cells.
+    pub fn contains(&self, c: ThetaCoord) -> bool {
+        c.index < self.ring_size(c.ring)
+    }
+
+    /// Returns the cell at `c`, or None if it's outside of the maze.
+    pub fn get(&self, c: ThetaCoord) -> Option<&ThetaCell> {
+        if self.contains(c) {
+            Some(&self.cells[self.get_offset(c)])
+        } else {
+            None
+        }
+    }
+
+    // Internal: Calculates the offset of a cell in the cell vector.
+    fn get_offset(&self, c: ThetaCoord) -> usize {
+        (self.ring_starts[c.ring as usize] + c.index) as usize
+    }
+
+    /// Returns the index of the outer ring cell whose outer wall is open, if
+    /// there is one.  The center cell is the entrance.
+    pub fn get_exit(&self) -> Option<u32> {
+        self.exit
+    }
+
+    /// Opens the outer wall of cell `index` in the outer ring.
+    pub fn set_exit(&mut self, index: u32) -> Result<(), MazeError> {
+        let rings = self.get_rings();
+        if rings == 0 || index >= self.ring_size(rings - 1) {
+            return Err(MazeError::InvalidArgument(format!("no outer ring cell {}", index)));
+        }
+        self.exit = Some(index);
+        Ok(())
+    }
+
+    /// Returns every direction that leads from `c` to another cell.
+    pub fn directions(&self, c: ThetaCoord) -> Vec<u32> {
+        let mut dirs = Vec::new();
+        if !self.contains(c) {
+            return dirs;
+        }
+        if c.ring > 0 {
+            dirs.push(constants::THETA_DIR_INWARD);
+            if self.ring_size(c.ring) > 1 {
+                dirs.push(constants::THETA_DIR_CLOCKWISE);
+                dirs.push(constants::THETA_DIR_COUNTER_CLOCKWISE);
+            }
+        }
+        dirs.extend((0..self.outward_ratio(c.ring)).map(|k| constants::THETA_DIR_OUTWARD + k));
+        dirs
+    }
+
+    /// Returns the cell next to `c` in direction dir, or None if there's no
+    /// cell there.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::constants;
+    /// use mazegame::maze::theta::{ThetaCoord, ThetaMaze};
+    ///
+    /// let maze = ThetaMaze::new(3);
+    /// let c = ThetaCoord::new(1, 5);
+    /// assert_eq!(Some(ThetaCoord::new(1, 0)), maze.neighbor(c, constants::THETA_DIR_CLOCKWISE));
+    /// assert_eq!(Some(ThetaCoord::new(2, 11)), maze.neighbor(c, constants::THETA_DIR_OUTWARD + 1));
+    /// assert_eq!(Some(ThetaCoord::new(0, 0)), maze.neighbor(c, constants::THETA_DIR_INWARD));
+    /// ```
+    pub fn neighbor(&self, c: ThetaCoord, dir: u32) -> Option<ThetaCoord> {
+        if !self.directions(c).contains(&dir) {
+            return None;
+        }
+        let size = self.ring_size(c.ring);
+        match dir {
+            constants::THETA_DIR_INWARD => {
+                Some(ThetaCoord::new(c.ring - 1, c.index / self.outward_ratio(c.ring - 1)))
+            }
+            constants::THETA_DIR_CLOCKWISE => Some(ThetaCoord::new(c.ring, (c.index + 1) % size)),
+            constants::THETA_DIR_COUNTER_CLOCKWISE => Some(ThetaCoord::new(c.ring, (c.index + size - 1) % size)),
+            _ => {
+                let ratio = self.outward_ratio(c.ring);
+                Some(ThetaCoord::new(c.ring + 1, c.index * ratio + dir - constants::THETA_DIR_OUTWARD))
+            }
+        }
+    }
+
+    /// Returns whether there's a wall on side dir of `c`.  Directions that
+    /// don't lead to another cell are always walled.
+    pub fn is_wall_present(&self, c: ThetaCoord, dir: u32) -> bool {
+        let (owner, own_dir) = match self.wall_owner(c, dir) {
+            Some(w) => w,
+            None => return true,
+        };
+        let cell = &self.cells[self.get_offset(owner)];
+        if own_dir == constants::THETA_DIR_INWARD { cell.inward_wall } else { cell.clockwise_wall }
+    }
+
+    /// Knocks down the wall on side dir of `c`.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::constants;
+    /// use mazegame::maze::theta::{ThetaCoord, ThetaMaze};
+    ///
+    /// let mut maze = ThetaMaze::new(3);
+    /// maze.carve(ThetaCoord::new(0, 0), constants::THETA_DIR_OUTWARD + 2).unwrap();
+    /// assert!(!maze.is_wall_present(ThetaCoord::new(1, 2), constants::THETA_DIR_INWARD));
+    /// assert!(maze.carve(ThetaCoord::new(0, 0), constants::THETA_DIR_INWARD).is_err());
+    /// ```
+    pub fn carve(&mut self, c: ThetaCoord, dir: u32) -> Result<(), MazeError> {
+        let (owner, own_dir) = self.wall_owner(c, dir).ok_or_else(|| {
+            MazeError::InvalidArgument(format!("no theta cell in direction {} of ({}, {})", dir, c.ring, c.index))
+        })?;
+        let other = self.neighbor(c, dir).unwrap();
+
+        let offset = self.get_offset(owner);
+        if own_dir == constants::THETA_DIR_INWARD {
+            self.cells[offset].inward_wall = false;
+        } else {
+            self.cells[offset].clockwise_wall = false;
+        }
+        for cell in &[c, other] {
+            let offset = self.get_offset(*cell);
+            self.cells[offset].id = constants::ID_MAZE_PATH;
+        }
+        Ok(())
+    }
+
+    /// Generates a perfect theta maze from a seed, with the exit in a random
+    /// cell of the outer ring.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::maze::theta::ThetaMaze::new(5);
+    /// maze.generate_perfect_seeded(8).unwrap();
+    /// assert!(maze.get_exit().is_some());
+    /// ```
+    pub fn generate_perfect_seeded(&mut self, seed: u64) -> Result<(), String> {
+        self.generate_perfect_with_rng(&mut rng::from_seed(seed))
+    }
+
+    /// Generates a perfect theta maze using the supplied random number
+    /// generator, growing out from the center.
+    pub fn generate_perfect_with_rng<R: Rng>(&mut self, rng: &mut R) -> Result<(), String> {
+        let stats = generator::growing_tree(self, ThetaCoord::new(0, 0), rng)?;
+        gen_debug!("theta_growing_tree ring=0 index=0 carved={} backtracks={}", stats.carved, stats.backtracks);
+        let outer = self.ring_size(self.get_rings() - 1);
+        self.set_exit(rng.gen_range(0, outer)).map_err(|e| e.to_string())
+    }
+
+    /// Returns the cells that can be reached in a single step from `c`.
+    pub fn open_neighbors(&self, c: ThetaCoord) -> Vec<ThetaCoord> {
+        self.directions(c).into_iter()
+            .filter(|&dir| !self.is_wall_present(c, dir))
+            .filter_map(|dir| self.neighbor(c, dir))
+            .collect()
+    }
+
+    /// Finds the shortest path between two cells using a breadth-first
+    /// search.  The path includes both endpoints.  Returns None if either
+    /// cell is outside of the maze or if there's no path between them.
+    pub fn solve(&self, from: ThetaCoord, to: ThetaCoord) -> Option<Vec<ThetaCoord>> {
+        if !self.contains(from) || !self.contains(to) {
+            return None;
+        }
+
+        let mut came_from: Vec<Option<ThetaCoord>> = vec![None; self.cells.len()];
+        let mut seen = vec![false; self.cells.len()];
+        let mut queue = VecDeque::new();
+
+        seen[self.get_offset(from)] = true;
+        queue.push_back(from);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                let mut path = vec![cur];
+                let mut step = cur;
+                while let Some(prev) = came_from[self.get_offset(step)] {
+                    path.push(prev);
+                    step = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.open_neighbors(cur) {
+                let offset = self.get_offset(next);
+                if !seen[offset] {
+                    seen[offset] = true;
+                    came_from[offset] = Some(cur);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Renders the maze as an SVG image, with each ring `ring_size` pixels
+    /// deep.  Inward walls are drawn as arcs and clockwise walls as spokes.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::maze::theta::ThetaMaze::new(4);
+    /// maze.generate_perfect_seeded(2).unwrap();
+    /// let svg = maze.render_svg(20);
+    /// assert!(svg.starts_with("<svg") && svg.contains("<path"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn render_svg(&self, ring_size: u32) -> String {
+        use std::f64::consts::PI;
+
+        let rings = self.get_rings();
+        let center = (rings * ring_size + ring_size / 2) as f64;
+        let size = center as u32 * 2;
+        let mut out = String::new();
+
+        out.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                               viewBox=\"0 0 {} {}\">\n", size, size, size, size));
+        out.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", size, size));
+        out.push_str("<g stroke=\"black\" stroke-width=\"2\" stroke-linecap=\"round\" fill=\"none\">\n");
+
+        // Angles are measured clockwise from the top, as the cells are numbered
+        let point = |radius: f64, angle: f64| (center + radius * angle.sin(), center - radius * angle.cos());
+        let arc = |out: &mut String, radius: f64, from: f64, to: f64| {
+            let (x1, y1) = point(radius, from);
+            let (x2, y2) = point(radius, to);
+            out.push_str(&format!("<path d=\"M {:.2} {:.2} A {:.2} {:.2} 0 0 1 {:.2} {:.2}\"/>\n",
+                                  x1, y1, radius, radius, x2, y2));
+        };
+
+        for c in self.coords().into_iter().filter(|c| c.ring > 0) {
+            let count = self.ring_size(c.ring) as f64;
+            let inner = (c.ring * ring_size) as f64;
+            let outer = inner + ring_size as f64;
+            let start = 2.0 * PI * c.index as f64 / count;
+            let end = 2.0 * PI * (c.index + 1) as f64 / count;
+
+            if self.is_wall_present(c, constants::THETA_DIR_INWARD) {
+                arc(&mut out, inner, start, end);
+            }
+            if count > 1.0 && self.is_wall_present(c, constants::THETA_DIR_CLOCKWISE) {
+                let (x1, y1) = point(inner, end);
+                let (x2, y2) = point(outer, end);
+                out.push_str(&format!("<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\"/>\n",
+                                      x1, y1, x2, y2));
+            }
+            if c.ring == rings - 1 && self.exit != Some(c.index) {
+                arc(&mut out, outer, start, end);
+            }
+        }
+
+        out.push_str("</g>\n</svg>\n");
+        out
+    }
+
+    //
+    // Internal - the number of cells in the next ring out for each cell of
+    // `ring`, or 0 for the outer ring.
+    //
+    fn outward_ratio(&self, ring: u32) -> u32 {
+        match self.ring_sizes.get(ring as usize + 1) {
+            Some(&next) => next / self.ring_sizes[ring as usize],
+            None => 0,
+        }
+    }
+
+    //
+    // Internal - finds the cell that stores the wall on side dir of `c`,
+    // and whether it's that cell's inward or clockwise wall.
+    //
+    fn wall_owner(&self, c: ThetaCoord, dir: u32) -> Option<(ThetaCoord, u32)> {
+        let other = self.neighbor(c, dir)?;
+        match dir {
+            constants::THETA_DIR_INWARD | constants::THETA_DIR_CLOCKWISE => Some((c, dir)),
+            constants::THETA_DIR_COUNTER_CLOCKWISE => Some((other, constants::THETA_DIR_CLOCKWISE)),
+            _ => Some((other, constants::THETA_DIR_INWARD)),
+        }
+    }
+}
+
+impl Carvable for ThetaMaze {
+    type Cell = ThetaCoord;
+
+    fn uncarved_directions(&self, c: ThetaCoord) -> Vec<u32> {
+        self.directions(c).into_iter().filter(|&dir| {
+            self.neighbor(c, dir).is_some_and(|n| self.cells[self.get_offset(n)].id == constants::ID_UNCARVED)
+        }).collect()
+    }
+
+    fn carve_toward(&mut self, c: ThetaCoord, dir: u32) -> Result<ThetaCoord, String> {
+        self.carve(c, dir).map_err(|e| e.to_string())?;
+        self.neighbor(c, dir).ok_or_else(|| format!("no theta cell in direction {}", dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_sizes() {
+        let maze = ThetaMaze::new(8);
+        assert_eq!(vec![1, 6, 12, 24, 24, 24, 48, 48], (0..8).map(|r| maze.ring_size(r)).collect::<Vec<u32>>());
+        assert_eq!(187, maze.cell_count());
+        assert_eq!(0, ThetaMaze::new(0).cell_count());
+    }
+
+    #[test]
+    fn test_adjacency_symmetry() {
+        let maze = ThetaMaze::new(7);
+        // Ring 2 splits in two going out to ring 3
+        let c = ThetaCoord::new(2, 7);
+        assert_eq!(Some(ThetaCoord::new(3, 14)), maze.neighbor(c, constants::THETA_DIR_OUTWARD));
+        assert_eq!(Some(ThetaCoord::new(3, 15)), maze.neighbor(c, constants::THETA_DIR_OUTWARD + 1));
+        assert_eq!(None, maze.neighbor(c, constants::THETA_DIR_OUTWARD + 2));
+
+        // Every step has a step back, and both sides share the same wall
+        let mut maze = maze;
+        maze.generate_perfect_seeded(5).unwrap();
+        for c in maze.coords() {
+            for dir in maze.directions(c) {
+                let other = maze.neighbor(c, dir).unwrap();
+                let back: Vec<u32> = maze.directions(other).into_iter()
+                                         .filter(|&d| maze.neighbor(other, d) == Some(c)).collect();
+                assert!(!back.is_empty(), "no way back from ({}, {})", other.ring, other.index);
+                if maze.ring_size(c.ring) > 2 {
+                    assert_eq!(1, back.len());
+                    assert_eq!(maze.is_wall_present(c, dir), maze.is_wall_present(other, back[0]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_full_connectivity() {
+        let mut maze = ThetaMaze::new(6);
+        maze.generate_perfect_seeded(412).unwrap();
+        let center = ThetaCoord::new(0, 0);
+        for c in maze.coords() {
+            assert!(maze.solve(center, c).is_some());
+        }
+
+        // A perfect maze has exactly one fewer passage than it has cells
+        let passages: usize = maze.coords().iter().map(|&c| maze.open_neighbors(c).len()).sum();
+        assert_eq!(maze.cell_count() - 1, passages / 2);
+    }
+
+    #[test]
+    fn test_solve_center_to_rim() {
+        let mut maze = ThetaMaze::new(6);
+        maze.generate_perfect_seeded(7).unwrap();
+        let exit = ThetaCoord::new(5, maze.get_exit().unwrap());
+        let path = maze.solve(ThetaCoord::new(0, 0), exit).unwrap();
+        assert_eq!(ThetaCoord::new(0, 0), path[0]);
+        assert_eq!(exit, path[path.len() - 1]);
+        assert!(path.windows(2).all(|step| maze.open_neighbors(step[0]).contains(&step[1])));
+        // Every ring has to be crossed on the way out
+        assert!(path.len() >= 6);
+    }
+}
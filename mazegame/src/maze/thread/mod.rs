@@ -0,0 +1,271 @@
+//! Ariadne's thread: the walkable route from the Square a thread is tied
+//! to, usually the entrance, to wherever the player is now.  Unlike a
+//! trail of everywhere the player has been, the thread only ever holds the
+//! way back.
+//!
+//! The route always follows the tree of shortest paths out of the anchor,
+//! so it's the same path Maze::solve() would give from the anchor to the
+//! player.  Working that tree out is the only search, and it's only redone
+//! when the Maze's revision changes; moving one Square away along the tree
+//! adds a Square to the thread and stepping back along it winds one up.
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::maze::secret::Discoveries;
+use crate::maze::{Coord, Maze};
+
+/// The route from an anchor Square to the player, kept up to date as the
+/// player moves.
+#[derive(Clone, Debug)]
+pub struct Thread {
+    anchor: Coord,
+    // The (revision, cols, rows) the tree belongs to
+    seen: Option<(u64, u32, u32)>,
+    // Each Square's parent in the tree of shortest paths from the anchor,
+    // indexed like `sq`.  The anchor and unreachable Squares have none.
+    parent: Vec<Option<Coord>>,
+    route: Vec<Coord>,
+    // How many times the tree has been rebuilt, for tests
+    pub(crate) searches: u32,
+}
+
+impl Thread {
+    /// Ties a thread to anchor.  Its route is empty until the first
+    /// follow().
+    pub fn new(anchor: Coord) -> Thread {
+        Thread { anchor, seen: None, parent: Vec::new(), route: Vec::new(), searches: 0 }
+    }
+
+    /// Returns the Square the thread is tied to.
+    pub fn anchor(&self) -> Coord {
+        self.anchor
+    }
+
+    /// Returns the route from the anchor to the player as of the last
+    /// follow(), both ends included.  It's empty if the player couldn't be
+    /// reached from the anchor.
+    pub fn route(&self) -> &[Coord] {
+        &self.route
+    }
+
+    /// Updates the route for the player being at player in maze.  Moves
+    /// along the thread are handled in place; the Squares are only searched
+    /// again if the maze has changed since the last call.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::thread::Thread;
+    /// use mazegame::prelude::*;
+    ///
+    /// // A corridor four Squares long
+    /// let maze = Maze::from_compact("4cc8").unwrap();
+    /// let mut thread = Thread::new(Coord::new(0, 0));
+    /// thread.follow(&maze, Coord::new(2, 0));
+    /// assert_eq!(3, thread.route().len());
+    /// thread.follow(&maze, Coord::new(3, 0));
+    /// thread.follow(&maze, Coord::new(2, 0));
+    /// thread.follow(&maze, Coord::new(1, 0));
+    /// assert_eq!(&[Coord::new(0, 0), Coord::new(1, 0)], thread.route());
+    /// ```
+    pub fn follow(&mut self, maze: &Maze, player: Coord) {
+        let key = Some((maze.revision(), maze.cols, maze.rows));
+        if self.seen != key {
+            self.seen = key;
+            self.build_tree(maze);
+            self.rewind(maze, player);
+            return;
+        }
+
+        let len = self.route.len();
+        if len > 0 && self.route[len - 1] == player {
+            return;
+        }
+        // Stepping back along the thread
+        if len > 1 && self.route[len - 2] == player {
+            self.route.pop();
+            return;
+        }
+        // Stepping one Square further out
+        if len > 0 && maze.in_bounds(player.x, player.y) &&
+           self.parent[maze.get_offset(player.x, player.y)] == Some(self.route[len - 1]) {
+            self.route.push(player);
+            return;
+        }
+        // Crossing to another branch, or jumping
+        self.rewind(maze, player);
+    }
+
+    //
+    // Internal - works out the tree of shortest paths from the anchor, in
+    // the same order Maze::solve() searches.
+    //
+    fn build_tree(&mut self, maze: &Maze) {
+        self.searches += 1;
+        self.parent = vec![None; maze.sq.len()];
+        if !maze.in_bounds(self.anchor.x, self.anchor.y) {
+            return;
+        }
+        let mut seen = vec![false; maze.sq.len()];
+        let mut queue = VecDeque::new();
+        seen[maze.get_offset(self.anchor.x, self.anchor.y)] = true;
+        queue.push_back(self.anchor);
+        while let Some(cur) = queue.pop_front() {
+            for next in maze.open_neighbors(cur.x, cur.y) {
+                let offset = maze.get_offset(next.x, next.y);
+                if !seen[offset] {
+                    seen[offset] = true;
+                    self.parent[offset] = Some(cur);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    //
+    // Internal - rebuilds the route by walking the tree from player back to
+    // the anchor.
+    //
+    fn rewind(&mut self, maze: &Maze, player: Coord) {
+        self.route.clear();
+        if !maze.in_bounds(player.x, player.y) {
+            return;
+        }
+        let mut at = player;
+        self.route.push(at);
+        while let Some(p) = self.parent[maze.get_offset(at.x, at.y)] {
+            self.route.push(p);
+            at = p;
+        }
+        if at != self.anchor {
+            self.route.clear();
+            return;
+        }
+        self.route.reverse();
+    }
+}
+
+impl Maze {
+    /// Renders the maze like render_ascii_with_path(), with the thread's
+    /// route drawn faintly as ','.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::thread::Thread;
+    /// use mazegame::prelude::*;
+    ///
+    /// let maze = Maze::from_compact("4cc8").unwrap();
+    /// let mut thread = Thread::new(Coord::new(0, 0));
+    /// thread.follow(&maze, Coord::new(2, 0));
+    /// assert_eq!("XXXXXXXXX\nX,,,,,  X\nXXXXXXXXX\n", maze.render_ascii_with_thread(&thread));
+    /// ```
+    pub fn render_ascii_with_thread(&self, thread: &Thread) -> String {
+        self.render_ascii_marked(thread.route(), ',', &Discoveries::new())
+    }
+
+    /// Renders the maze like render_svg(), with the thread's route drawn
+    /// over it as a faint line through the middle of each Square.
+    pub fn render_svg_with_thread(&self, cell_size: u32, thread: &Thread) -> String {
+        let mut out = self.render_svg(cell_size);
+        if thread.route().len() < 2 {
+            return out;
+        }
+        // Drop the closing tag, add the line and put it back
+        out.truncate(out.len() - "</svg>\n".len());
+        // The margin, plus half a Square
+        let center = cell_size / 2 + cell_size / 2;
+        let points: Vec<String> = thread.route().iter()
+            .map(|c| format!("{},{}", center + c.x * cell_size, center + c.y * cell_size))
+            .collect();
+        out.push_str(&format!("<polyline points=\"{}\" fill=\"none\" stroke=\"steelblue\" stroke-width=\"2\" \
+                               stroke-opacity=\"0.35\"/>\n</svg>\n", points.join(" ")));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+    use rand::Rng;
+    use crate::rng;
+
+    // Checks the thread against a fresh search from the anchor
+    fn assert_fresh(maze: &Maze, thread: &Thread, player: Coord) {
+        let expected = maze.solve(thread.anchor(), player).unwrap_or_default();
+        assert_eq!(expected, thread.route(), "player at {:?}", player);
+    }
+
+    #[test]
+    fn test_scripted_walk() {
+        // Braided, so there are loops to walk around
+        let mut maze = Maze::new(14, 14);
+        maze.generate_perfect_seeded(444).unwrap();
+        maze.braid_with_rng(0.6, &mut rng::from_seed(444));
+        let mut thread = Thread::new(Coord::new(0, 0));
+        let mut player = Coord::new(0, 0);
+        thread.follow(&maze, player);
+        assert_eq!(&[player], thread.route());
+
+        // A random walk doubles back on itself and goes round loops
+        let mut rng = rng::from_seed(1);
+        let mut backtracks = 0;
+        for _ in 0..2000 {
+            let next = maze.open_neighbors(player.x, player.y);
+            let step = next[rng.gen_range(0, next.len())];
+            if thread.route().len() > 1 && thread.route()[thread.route().len() - 2] == step {
+                backtracks += 1;
+            }
+            player = step;
+            thread.follow(&maze, player);
+            assert_fresh(&maze, &thread, player);
+        }
+        assert!(backtracks > 100);
+        // Moving around never searched again
+        assert_eq!(1, thread.searches);
+    }
+
+    #[test]
+    fn test_wall_changes() {
+        let mut maze = Maze::new(8, 8);
+        maze.generate_perfect_seeded(2).unwrap();
+        let mut thread = Thread::new(Coord::new(0, 0));
+        let player = Coord::new(7, 7);
+        thread.follow(&maze, player);
+        assert_fresh(&maze, &thread, player);
+
+        // Opening a shortcut reroutes the thread
+        let before = thread.route().len();
+        for y in 0..8 {
+            for x in 0..7 {
+                maze.carve(x, y, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+            }
+        }
+        thread.follow(&maze, player);
+        assert_eq!(2, thread.searches);
+        assert_fresh(&maze, &thread, player);
+        assert!(thread.route().len() < before);
+
+        // Cutting the player off leaves no thread
+        let mut walled = Maze::new(3, 3);
+        walled.carve(0, 0, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        let mut thread = Thread::new(Coord::new(0, 0));
+        thread.follow(&walled, Coord::new(2, 2));
+        assert!(thread.route().is_empty());
+        thread.follow(&walled, Coord::new(1, 0));
+        assert_eq!(2, thread.route().len());
+        thread.follow(&walled, Coord::new(5, 5));
+        assert!(thread.route().is_empty());
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let maze = Maze::from_compact("4cc8").unwrap();
+        let mut thread = Thread::new(Coord::new(0, 0));
+        assert_eq!(maze.render_svg(10), maze.render_svg_with_thread(10, &thread));
+        thread.follow(&maze, Coord::new(3, 0));
+        let svg = maze.render_svg_with_thread(10, &thread);
+        assert!(svg.contains("points=\"10,10 20,10 30,10 40,10\""));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
@@ -0,0 +1,702 @@
+//! Rotating, mirroring, cropping and stitching mazes.  The transforms are
+//! provided by the Transformable trait, which MazeData and a Maze paired
+//! with its MazeData implement too, so `(maze, data).rotate_cw()` moves a
+//! Maze and the data attached to its Squares together.
+//!
+//! Corridor regions from Maze::partition_corridors() and elevations move
+//! with their Squares.  Cropping and stitching renumber rooms from 1 and
+//! regions from 0, in the order they're found.  Stitching a Maze with
+//! elevations to one without puts the other's Squares at 0.
+//!
+//! A start Square chosen with Maze::set_start() moves with the Square it's
+//! on.  A crop that cuts it out clears it, so the cropped Maze starts from
+//! the default of (0, 0) again; Maze::crop_with_report() says when that
+//! happens.  Stitching keeps the first Maze's start, or the second's if the
+//! first didn't have one.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::room::Door;
+use crate::maze::square::Square;
+use crate::maze::{Coord, Maze, Opening};
+
+/// Something laid out on a maze's grid that can be rotated, mirrored,
+/// cropped and stitched.  Each transform returns a new value.
+pub trait Transformable: Sized {
+    /// Turns a quarter turn clockwise.  The width and height swap.
+    fn rotate_cw(&self) -> Self;
+
+    /// Flips east to west.
+    fn mirror_x(&self) -> Self;
+
+    /// Flips north to south.
+    fn mirror_y(&self) -> Self;
+
+    /// Cuts out the cols x rows block with its top left corner at (x, y).
+    fn crop(&self, x: u32, y: u32, cols: u32, rows: u32) -> Result<Self, MazeError>;
+
+    /// Joins other onto the east side.  Both must have the same number of
+    /// rows.
+    fn stitch_horizontal(&self, other: &Self) -> Result<Self, MazeError>;
+
+    /// Joins other onto the south side.  Both must have the same number of
+    /// columns.
+    fn stitch_vertical(&self, other: &Self) -> Result<Self, MazeError>;
+}
+
+/// What Maze::crop_with_report() dropped because it was cut out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CropReport {
+    /// The start Square from set_start() was cut out and cleared.
+    pub start_cleared: bool,
+    /// The entrance was cut out or is no longer on the outer wall.
+    pub entrance_dropped: bool,
+    /// How many exits were cut out or are no longer on the outer wall.
+    pub exits_dropped: usize,
+    /// How many doors were cut out or lead across the cut.
+    pub doors_dropped: usize,
+}
+
+impl Transformable for Maze {
+    /// Turns the Maze a quarter turn clockwise.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(2, 3);
+    /// maze.carve(0, 0, DIR_EAST, ID_MAZE_PATH, false).unwrap();
+    /// let turned = maze.rotate_cw();
+    /// assert_eq!((2, 3), (turned.get_cols(), turned.get_rows()));
+    /// // The top left Square is now the top right, and its east wall faces south
+    /// assert!(!turned.sq[1].is_wall_present(DIR_SOUTH));
+    /// ```
+    fn rotate_cw(&self) -> Maze {
+        let mut maze = self.remap(self.cols, self.rows, rotate_cells(&self.sq, self.cols, self.rows), quarter_turn);
+        maze.x_topology = self.y_topology;
+        maze.y_topology = self.x_topology;
+        for sq in maze.sq.iter_mut() {
+            // Tunnels change axis
+            sq.under = (sq.under & 1) << 1 | (sq.under & 2) >> 1;
+        }
+        if !self.corridor_regions.is_empty() {
+            maze.corridor_regions = rotate_cells(&self.corridor_regions, self.cols, self.rows);
+        }
+        if !self.elevations.is_empty() {
+            maze.elevations = rotate_cells(&self.elevations, self.cols, self.rows);
+        }
+        let rows = self.rows;
+        maze.start = self.start.map(|c| Coord::new(rows - 1 - c.y, c.x));
+        maze.entrance = self.entrance.map(|o| turn_opening(o, rows));
+        maze.exits = self.exits.iter().map(|&o| turn_opening(o, rows)).collect();
+        maze.doors = self.doors.iter().map(|&d| move_door(d, |o| turn_opening(o, rows))).collect();
+        maze.sort_doors();
+        maze
+    }
+
+    fn mirror_x(&self) -> Maze {
+        let mut maze = self.remap(self.rows, self.cols, mirror_x_cells(&self.sq, self.cols),
+                                  |dir| mirror_dir(dir, constants::DIR_EAST));
+        maze.corridor_regions = mirror_x_cells(&self.corridor_regions, self.cols);
+        maze.elevations = mirror_x_cells(&self.elevations, self.cols);
+        let cols = self.cols;
+        let flip = |o: Opening| Opening {
+            coord: Coord::new(cols - 1 - o.coord.x, o.coord.y),
+            dir: mirror_dir(o.dir, constants::DIR_EAST),
+        };
+        maze.start = self.start.map(|c| Coord::new(cols - 1 - c.x, c.y));
+        maze.entrance = self.entrance.map(flip);
+        maze.exits = self.exits.iter().cloned().map(flip).collect();
+        maze.doors = self.doors.iter().map(|&d| move_door(d, flip)).collect();
+        maze.sort_doors();
+        maze
+    }
+
+    fn mirror_y(&self) -> Maze {
+        let mut maze = self.remap(self.rows, self.cols, mirror_y_cells(&self.sq, self.cols),
+                                  |dir| mirror_dir(dir, constants::DIR_NORTH));
+        maze.corridor_regions = mirror_y_cells(&self.corridor_regions, self.cols);
+        maze.elevations = mirror_y_cells(&self.elevations, self.cols);
+        let rows = self.rows;
+        let flip = |o: Opening| Opening {
+            coord: Coord::new(o.coord.x, rows - 1 - o.coord.y),
+            dir: mirror_dir(o.dir, constants::DIR_NORTH),
+        };
+        maze.start = self.start.map(|c| Coord::new(c.x, rows - 1 - c.y));
+        maze.entrance = self.entrance.map(flip);
+        maze.exits = self.exits.iter().cloned().map(flip).collect();
+        maze.doors = self.doors.iter().map(|&d| move_door(d, flip)).collect();
+        maze.sort_doors();
+        maze
+    }
+
+    /// Cuts a block out of the Maze.  Walls along the cut are closed, the
+    /// entrance and exits are only kept if they're still on the outer wall,
+    /// doors are only kept if they don't lead across the cut, the start
+    /// Square is cleared if it's cut out, and the rooms left are renumbered
+    /// from 1 in the order they're found.  crop_with_report() also says
+    /// what was dropped.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let corner = maze.crop(5, 5, 5, 5).unwrap();
+    /// assert_eq!(25, corner.sq.len());
+    /// assert!(corner.walls_consistent());
+    /// ```
+    fn crop(&self, x: u32, y: u32, cols: u32, rows: u32) -> Result<Maze, MazeError> {
+        self.crop_with_report(x, y, cols, rows).map(|(maze, _)| maze)
+    }
+
+    /// Joins another Maze onto the east side of this one.  The other Maze's
+    /// rooms are renumbered to follow this one's, and the walls along the
+    /// seam are left as they were, so the halves aren't connected until a
+    /// wall between them is carved.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut left = Maze::new(4, 4);
+    /// left.generate_perfect_seeded(1).unwrap();
+    /// let wide = left.stitch_horizontal(&left.mirror_x()).unwrap();
+    /// assert_eq!(8, wide.get_cols());
+    /// assert!(left.stitch_horizontal(&Maze::new(5, 4)).is_err());
+    /// ```
+    fn stitch_horizontal(&self, other: &Maze) -> Result<Maze, MazeError> {
+        let sq = stitch_cells(&self.sq, self.cols, self.rows, &other.sq, other.cols, other.rows, true)?;
+        self.stitched(other, sq, self.rows, self.cols + other.cols, Coord::new(self.cols, 0))
+    }
+
+    fn stitch_vertical(&self, other: &Maze) -> Result<Maze, MazeError> {
+        let sq = stitch_cells(&self.sq, self.cols, self.rows, &other.sq, other.cols, other.rows, false)?;
+        self.stitched(other, sq, self.rows + other.rows, self.cols, Coord::new(0, self.rows))
+    }
+}
+
+impl Maze {
+    /// Cuts a block out of the Maze like crop(), and reports what was
+    /// dropped along the way.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.set_start(8, 8).unwrap();
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let (corner, report) = maze.crop_with_report(0, 0, 5, 5).unwrap();
+    /// assert!(report.start_cleared);
+    /// assert!(!corner.has_start());
+    /// ```
+    pub fn crop_with_report(&self, x: u32, y: u32, cols: u32, rows: u32) -> Result<(Maze, CropReport), MazeError> {
+        let sq = crop_cells(&self.sq, self.cols, self.rows, x, y, cols, rows)?;
+        let mut maze = Maze::new(rows, cols);
+        maze.sq = sq;
+        // Rooms cut out entirely leave gaps in the ids, closed up below
+        maze.num_rooms = self.num_rooms;
+        maze.room_themes = self.room_themes.clone();
+        if !self.corridor_regions.is_empty() {
+            maze.corridor_regions = crop_cells(&self.corridor_regions, self.cols, self.rows, x, y, cols, rows)?;
+        }
+        if !self.elevations.is_empty() {
+            maze.elevations = crop_cells(&self.elevations, self.cols, self.rows, x, y, cols, rows)?;
+        }
+
+        let keep = |o: Opening| {
+            let (ox, oy) = (o.coord.x.wrapping_sub(x), o.coord.y.wrapping_sub(y));
+            if maze.in_bounds(ox, oy) && maze.neighbor(ox, oy, o.dir).is_none() {
+                Some(Opening { coord: Coord::new(ox, oy), dir: o.dir })
+            } else {
+                None
+            }
+        };
+        let (entrance, exits): (_, Vec<Opening>) =
+            (self.entrance.and_then(keep), self.exits.iter().filter_map(|&o| keep(o)).collect());
+        // Doors are kept if both sides of them are
+        let doors: Vec<Door> = self.doors.iter()
+            .map(|&d| Door { coord: Coord::new(d.coord.x.wrapping_sub(x), d.coord.y.wrapping_sub(y)), ..d })
+            .filter(|d| maze.in_bounds(d.coord.x, d.coord.y) && maze.neighbor(d.coord.x, d.coord.y, d.dir).is_some())
+            .collect();
+        let start = self.start
+            .map(|c| Coord::new(c.x.wrapping_sub(x), c.y.wrapping_sub(y)))
+            .filter(|c| maze.in_bounds(c.x, c.y));
+        let report = CropReport {
+            start_cleared: self.start.is_some() && start.is_none(),
+            entrance_dropped: self.entrance.is_some() && entrance.is_none(),
+            exits_dropped: self.exits.len() - exits.len(),
+            doors_dropped: self.doors.len() - doors.len(),
+        };
+        maze.start = start;
+        maze.entrance = entrance;
+        maze.exits = exits;
+        maze.doors = doors;
+        maze.seal_edges();
+        maze.compact_room_ids();
+        compact_regions(&mut maze.corridor_regions);
+        Ok((maze, report))
+    }
+
+    //
+    // Internal - builds a rows x cols Maze from rearranged Squares, turning
+    // each Square's walls with turn.  Rooms and topology are carried over.
+    //
+    fn remap<F: Fn(u32) -> u32>(&self, rows: u32, cols: u32, sq: Vec<Square>, turn: F) -> Maze {
+        let mut maze = Maze::new(rows, cols);
+        maze.num_rooms = self.num_rooms;
+        maze.room_themes = self.room_themes.clone();
+        maze.x_topology = self.x_topology;
+        maze.y_topology = self.y_topology;
+        maze.sq = sq.into_iter().map(|old| {
+            let mut sq = old.clone();
+            for dir in 0..constants::NUM_DIRECTIONS {
+                let to = turn(dir);
+                if old.is_wall_present(dir) {
+                    sq.build_wall(to);
+                } else {
+                    sq.break_wall(to);
+                }
+                sq.set_one_way(to, old.is_one_way(dir));
+                sq.set_secret(to, old.is_secret(dir));
+            }
+            sq
+        }).collect();
+        maze
+    }
+
+    //
+    // Internal - finishes stitching other onto this Maze, with other's top
+    // left corner at offset in the combined Squares.
+    //
+    fn stitched(&self, other: &Maze, sq: Vec<Square>, rows: u32, cols: u32, offset: Coord) -> Result<Maze, MazeError> {
+        let mut maze = Maze::new(rows, cols);
+        maze.sq = sq;
+        maze.num_rooms = self.num_rooms + other.num_rooms;
+        if !self.room_themes.is_empty() || !other.room_themes.is_empty() {
+            let themes = |m: &Maze| (1..=m.num_rooms as i32).map(|id| m.room_theme(id).unwrap_or("").to_string())
+                .collect::<Vec<String>>();
+            maze.room_themes = themes(self);
+            maze.room_themes.extend(themes(other));
+        }
+        for y in 0..other.rows {
+            for x in 0..other.cols {
+                let i = maze.get_offset(x + offset.x, y + offset.y);
+                if maze.sq[i].is_part_of_room() {
+                    maze.sq[i].id += self.num_rooms as i32;
+                }
+            }
+        }
+
+        // Openings on the seam are inside the new Maze
+        let on_border = |o: &Opening| maze.neighbor(o.coord.x, o.coord.y, o.dir).is_none();
+        let moved = |o: Opening| Opening { coord: Coord::new(o.coord.x + offset.x, o.coord.y + offset.y), dir: o.dir };
+        let entrance = self.entrance.filter(&on_border).or_else(|| other.entrance.map(moved).filter(&on_border));
+        let mut exits: Vec<Opening> = self.exits.iter().cloned().filter(&on_border).collect();
+        if exits.is_empty() {
+            exits = other.exits.iter().map(|&o| moved(o)).filter(&on_border).collect();
+        }
+        maze.start = self.start.or_else(|| other.start.map(|c| Coord::new(c.x + offset.x, c.y + offset.y)));
+        maze.entrance = entrance;
+        maze.exits = exits;
+        maze.doors = self.doors.clone();
+        maze.doors.extend(other.doors.iter().map(|&d| Door {
+            coord: Coord::new(d.coord.x + offset.x, d.coord.y + offset.y),
+            room_id: d.room_id + self.num_rooms as i32,
+            ..d
+        }));
+        maze.sort_doors();
+        // Regions are only kept if both halves have them
+        if !self.corridor_regions.is_empty() && !other.corridor_regions.is_empty() {
+            let after = self.corridor_regions.iter().flatten().max().map_or(0, |&r| r + 1);
+            let moved: Vec<Option<u32>> = other.corridor_regions.iter().map(|r| r.map(|r| r + after)).collect();
+            maze.corridor_regions = stitch_cells(&self.corridor_regions, self.cols, self.rows,
+                                                 &moved, other.cols, other.rows, offset.x > 0)?;
+            compact_regions(&mut maze.corridor_regions);
+        }
+        if !self.elevations.is_empty() || !other.elevations.is_empty() {
+            let heights = |m: &Maze| if m.elevations.is_empty() { vec![0; m.sq.len()] } else { m.elevations.clone() };
+            maze.elevations = stitch_cells(&heights(self), self.cols, self.rows,
+                                           &heights(other), other.cols, other.rows, offset.x > 0)?;
+        }
+        maze.seal_edges();
+        maze.compact_room_ids();
+        Ok(maze)
+    }
+
+    //
+    // Internal - closes walls left open on one side after the Maze was cut
+    // or joined: openings through the outer wall other than the entrance and
+    // exits, and passages (including wrapped ones) whose other side is gone.
+    // A tunnel that led off the edge is filled in, leaving the Square it
+    // started from a dead end.
+    //
+    fn seal_edges(&mut self) {
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                for &side in Direction::ALL.iter() {
+                    let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+                    let offset = self.get_offset(x, y);
+                    if self.neighbor(x, y, dir).is_none() && self.sq[offset].has_under_passage(dir) {
+                        self.sq[offset].remove_under_passage(dir);
+                        self.sq[offset].build_wall(back);
+                        if let Some(n) = self.neighbor(x, y, back) {
+                            let n = self.get_offset(n.x, n.y);
+                            self.sq[n].build_wall(dir);
+                        }
+                    }
+                }
+            }
+        }
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                for &side in Direction::ALL.iter() {
+                    let (dir, back) = (u32::from(side), u32::from(side.opposite()));
+                    let offset = self.get_offset(x, y);
+                    let open = match self.neighbor(x, y, dir) {
+                        None => {
+                            let opening = Opening { coord: Coord::new(x, y), dir };
+                            Some(opening) == self.entrance || self.exits.contains(&opening)
+                        }
+                        Some(n) => {
+                            !self.sq[self.get_offset(n.x, n.y)].is_wall_present(back) ||
+                                self.sq[offset].is_one_way(dir)
+                        }
+                    };
+                    if !open {
+                        self.sq[offset].build_wall(dir);
+                        self.sq[offset].set_one_way(dir, false);
+                    }
+                }
+            }
+        }
+    }
+}
+
+//
+// Internal - the Squares of a cols x rows grid turned a quarter turn
+// clockwise.  The result is rows wide.
+//
+pub(crate) fn rotate_cells<T: Clone>(cells: &[T], cols: u32, rows: u32) -> Vec<T> {
+    let mut out = Vec::with_capacity(cells.len());
+    for y in 0..cols {
+        for x in 0..rows {
+            out.push(cells[((rows - 1 - x) * cols + y) as usize].clone());
+        }
+    }
+    out
+}
+
+//
+// Internal - the Squares of a grid cols wide, flipped east to west.
+//
+pub(crate) fn mirror_x_cells<T: Clone>(cells: &[T], cols: u32) -> Vec<T> {
+    cells.chunks(cols.max(1) as usize).flat_map(|row| row.iter().rev().cloned()).collect()
+}
+
+//
+// Internal - the Squares of a grid cols wide, flipped north to south.
+//
+pub(crate) fn mirror_y_cells<T: Clone>(cells: &[T], cols: u32) -> Vec<T> {
+    cells.chunks(cols.max(1) as usize).rev().flat_map(|row| row.iter().cloned()).collect()
+}
+
+//
+// Internal - the Squares of a block of a cols x rows grid.
+//
+pub(crate) fn crop_cells<T: Clone>(cells: &[T], cols: u32, rows: u32, x: u32, y: u32, width: u32, height: u32)
+                                   -> Result<Vec<T>, MazeError> {
+    if width == 0 || height == 0 {
+        return Err(MazeError::InvalidArgument(format!("can't crop to {}x{}", width, height)));
+    }
+    match (x.checked_add(width), y.checked_add(height)) {
+        (Some(end_x), Some(end_y)) if end_x <= cols && end_y <= rows => {}
+        _ => return Err(MazeError::OutOfBounds(x.saturating_add(width) - 1, y.saturating_add(height) - 1)),
+    }
+    let mut out = Vec::with_capacity((width * height) as usize);
+    for cy in y..y + height {
+        for cx in x..x + width {
+            out.push(cells[(cy * cols + cx) as usize].clone());
+        }
+    }
+    Ok(out)
+}
+
+//
+// Internal - two grids side by side (horizontal) or one above the other.
+//
+pub(crate) fn stitch_cells<T: Clone>(a: &[T], a_cols: u32, a_rows: u32, b: &[T], b_cols: u32, b_rows: u32,
+                                     horizontal: bool) -> Result<Vec<T>, MazeError> {
+    if horizontal {
+        if a_rows != b_rows {
+            return Err(MazeError::InvalidArgument(format!("can't stitch {} rows beside {}", b_rows, a_rows)));
+        }
+        let rows_a = a.chunks(a_cols.max(1) as usize);
+        let rows_b = b.chunks(b_cols.max(1) as usize);
+        Ok(rows_a.zip(rows_b).flat_map(|(ra, rb)| ra.iter().chain(rb.iter()).cloned()).collect())
+    } else {
+        if a_cols != b_cols {
+            return Err(MazeError::InvalidArgument(format!("can't stitch {} columns below {}", b_cols, a_cols)));
+        }
+        Ok(a.iter().chain(b.iter()).cloned().collect())
+    }
+}
+
+//
+// Internal - renumbers corridor regions from 0 in the order they're found,
+// closing up the gaps left by regions that were cut out.
+//
+fn compact_regions(regions: &mut [Option<u32>]) {
+    let mut order: Vec<u32> = Vec::new();
+    for region in regions.iter_mut().flatten() {
+        *region = match order.iter().position(|&r| r == *region) {
+            Some(i) => i as u32,
+            None => {
+                order.push(*region);
+                order.len() as u32 - 1
+            }
+        };
+    }
+}
+
+//
+// Internal - where an opening ends up when a Maze with rows rows is turned
+// a quarter turn clockwise.
+//
+fn turn_opening(o: Opening, rows: u32) -> Opening {
+    Opening { coord: Coord::new(rows - 1 - o.coord.y, o.coord.x), dir: quarter_turn(o.dir) }
+}
+
+//
+// Internal - moves a door the way place moves an opening.
+//
+fn move_door<F: Fn(Opening) -> Opening>(door: Door, place: F) -> Door {
+    let Opening { coord, dir } = place(Opening { coord: door.coord, dir: door.dir });
+    Door { coord, dir, ..door }
+}
+
+//
+// Internal - flips dir around if it runs along the same axis as axis_dir.
+// Anything that isn't a flat direction is left alone.
+//
+pub(crate) fn mirror_dir(dir: u32, axis_dir: u32) -> u32 {
+    match (Direction::from_u32(dir), Direction::from_u32(axis_dir)) {
+        (Some(side), Some(axis)) if side == axis || side == axis.opposite() => side.opposite().into(),
+        _ => dir,
+    }
+}
+
+//
+// Internal - turns dir a quarter turn clockwise, leaving anything that
+// isn't a flat direction alone.
+//
+pub(crate) fn quarter_turn(dir: u32) -> u32 {
+    Direction::from_u32(dir).map_or(dir, |side| side.rotate_cw().into())
+}
+
+//
+// Internal - turns dir around, leaving anything that isn't a flat direction
+// alone.
+//
+pub(crate) fn half_turn(dir: u32) -> u32 {
+    Direction::from_u32(dir).map_or(dir, |side| side.opposite().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated(rows: u32, cols: u32, seed: u64) -> Maze {
+        let mut maze = Maze::new(rows, cols);
+        maze.generate_seeded((2, 2, 3, 2, 3), seed).unwrap();
+        maze.set_entrance(0, 0, constants::DIR_WEST).unwrap();
+        maze.set_exit(cols - 1, rows - 1, constants::DIR_SOUTH).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_rotate_and_mirror() {
+        let maze = generated(9, 12, 419);
+        let turned = maze.rotate_cw();
+        assert_eq!((9, 12), (turned.get_cols(), turned.get_rows()));
+        assert!(turned.walls_consistent());
+        assert_eq!(Some(Opening { coord: Coord::new(8, 0), dir: constants::DIR_NORTH }), turned.get_entrance());
+
+        // Four quarter turns, or two flips, put everything back
+        assert_eq!(maze, turned.rotate_cw().rotate_cw().rotate_cw());
+        assert_eq!(maze, maze.mirror_x().mirror_x());
+        assert_eq!(maze, maze.mirror_y().mirror_y());
+        // A half turn is both flips
+        assert_eq!(turned.rotate_cw(), maze.mirror_x().mirror_y());
+
+        let mirrored = maze.mirror_x();
+        assert!(mirrored.walls_consistent());
+        assert_eq!(Coord::new(11, 0), mirrored.get_entrance().unwrap().coord);
+        let path = maze.solve(Coord::new(0, 0), Coord::new(11, 8)).unwrap();
+        assert_eq!(path.len(), mirrored.solve(Coord::new(11, 0), Coord::new(0, 8)).unwrap().len());
+    }
+
+    #[test]
+    fn test_rotate_weave_and_one_way() {
+        let mut maze = Maze::new(3, 4);
+        maze.carve(1, 0, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_under(0, 1, constants::DIR_EAST).unwrap();
+        maze.carve_one_way(2, 1, constants::DIR_EAST).unwrap();
+        let turned = maze.rotate_cw();
+        // The tunnel now runs north-south under (1, 1)
+        assert!(turned.sq[turned.get_offset(1, 1)].has_under_passage(constants::DIR_NORTH));
+        assert!(turned.sq[turned.get_offset(1, 2)].is_one_way(constants::DIR_SOUTH));
+        assert_eq!(Ok(()), turned.validate());
+        assert_eq!(Some(vec![Coord::new(1, 0), Coord::new(1, 2), Coord::new(1, 3)]),
+                   turned.solve(Coord::new(1, 0), Coord::new(1, 3)));
+    }
+
+    #[test]
+    fn test_crop() {
+        let maze = generated(10, 10, 7);
+        let corner = maze.crop(0, 0, 6, 4).unwrap();
+        assert_eq!((6, 4), (corner.get_cols(), corner.get_rows()));
+        assert!(corner.walls_consistent());
+        assert_eq!(maze.get_entrance(), corner.get_entrance());
+        assert_eq!(None, corner.get_exit());
+        for y in 0..4 {
+            for x in 0..6 {
+                assert_eq!(maze.sq[maze.get_offset(x, y)].id, corner.sq[corner.get_offset(x, y)].id);
+            }
+        }
+
+        let middle = maze.crop(3, 2, 5, 5).unwrap();
+        assert!(middle.walls_consistent());
+        assert_eq!(None, middle.get_entrance());
+
+        assert!(maze.crop(8, 0, 3, 3).is_err());
+        assert!(maze.crop(0, 0, 0, 3).is_err());
+        assert!(maze.crop(u32::MAX, 0, 2, 2).is_err());
+    }
+
+    #[test]
+    fn test_crop_cuts_tunnels() {
+        let mut maze = Maze::new(3, 3);
+        maze.carve(0, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_under(1, 0, constants::DIR_SOUTH).unwrap();
+        let top = maze.crop(0, 0, 3, 2).unwrap();
+        assert_eq!(0, top.sq[top.get_offset(1, 1)].under);
+        assert!(top.walls_consistent());
+        assert!(top.open_neighbors(1, 0).is_empty());
+        assert_eq!(2, top.open_neighbors(1, 1).len());
+    }
+
+    #[test]
+    fn test_stitch() {
+        let mut left = generated(6, 5, 1);
+        left.set_exit(4, 2, constants::DIR_EAST).unwrap();
+        let right = generated(6, 7, 2);
+        let both = left.stitch_horizontal(&right).unwrap();
+        assert_eq!((12, 6), (both.get_cols(), both.get_rows()));
+        assert!(both.walls_consistent());
+        // The left exit was on the seam, so the right one is used
+        assert_eq!(left.get_entrance(), both.get_entrance());
+        assert_eq!(Coord::new(11, 5), both.get_exit().unwrap().coord);
+
+        // Rooms are renumbered rather than merged
+        let left_rooms: Vec<i32> = left.sq.iter().filter(|sq| sq.is_part_of_room()).map(|sq| sq.id).collect();
+        assert!(both.sq.iter().enumerate().filter(|&(i, _)| i % 12 >= 5)
+                    .all(|(_, sq)| !sq.is_part_of_room() || !left_rooms.contains(&sq.id)));
+
+        let stacked = left.stitch_vertical(&left.mirror_y()).unwrap();
+        assert_eq!((5, 12), (stacked.get_cols(), stacked.get_rows()));
+        assert!(stacked.walls_consistent());
+        assert!(left.stitch_vertical(&right).is_err());
+        assert!(left.stitch_horizontal(&right.rotate_cw()).is_err());
+    }
+
+    #[test]
+    fn test_doors() {
+        // A 2x2 room in the middle of a 5x4 maze with a door on its north side
+        let mut maze = Maze::new(4, 5);
+        for &(x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)].iter() {
+            let offset = maze.get_offset(x, y);
+            maze.sq[offset].id = 1;
+        }
+        maze.num_rooms = 1;
+        maze.carve(1, 1, constants::DIR_NORTH, 1, true).unwrap();
+        let door = Door { coord: Coord::new(1, 1), dir: constants::DIR_NORTH, room_id: 1, locked: true };
+        maze.add_door(door).unwrap();
+
+        // A quarter turn moves the door to the room's east side
+        let turned = maze.rotate_cw();
+        assert_eq!(&[Door { coord: Coord::new(2, 1), dir: constants::DIR_EAST, ..door }], turned.doors());
+        assert!(!turned.sq[turned.get_offset(2, 1)].is_wall_present(constants::DIR_EAST));
+        assert_eq!(maze, turned.rotate_cw().rotate_cw().rotate_cw());
+        assert_eq!(Coord::new(3, 1), maze.mirror_x().doors()[0].coord);
+        assert_eq!(Door { coord: Coord::new(1, 2), dir: constants::DIR_SOUTH, ..door }, maze.mirror_y().doors()[0]);
+        assert!(maze.mirror_y().validate().is_ok());
+
+        // Cropping keeps the door only if it still leads somewhere
+        assert_eq!(&[Door { coord: Coord::new(0, 1), ..door }], maze.crop(1, 0, 3, 3).unwrap().doors());
+        assert!(maze.crop(0, 1, 5, 3).unwrap().doors().is_empty());
+        assert!(maze.crop(1, 1, 2, 2).unwrap().doors().is_empty());
+
+        // Stitched doors move with their half and follow its room ids
+        let both = maze.stitch_horizontal(&maze).unwrap();
+        assert_eq!(2, both.get_num_rooms());
+        assert_eq!(vec![door, Door { coord: Coord::new(6, 1), room_id: 2, ..door }], both.doors().to_vec());
+        assert!(both.validate().is_ok());
+    }
+
+    #[test]
+    fn test_start() {
+        let mut maze = generated(6, 8, 3);
+        maze.set_start(2, 1).unwrap();
+        assert_eq!(Coord::new(4, 2), maze.rotate_cw().get_start());
+        assert_eq!(Coord::new(5, 1), maze.mirror_x().get_start());
+        assert_eq!(Coord::new(2, 4), maze.mirror_y().get_start());
+        assert_eq!(maze, maze.rotate_cw().rotate_cw().rotate_cw().rotate_cw());
+
+        let (kept, report) = maze.crop_with_report(1, 0, 4, 4).unwrap();
+        assert_eq!(Coord::new(1, 1), kept.get_start());
+        assert!(!report.start_cleared);
+        assert_eq!(1, report.exits_dropped);
+        assert!(report.entrance_dropped);
+
+        let (cut, report) = maze.crop_with_report(3, 2, 5, 4).unwrap();
+        assert!(report.start_cleared);
+        assert!(!cut.has_start());
+        assert_eq!(cut, maze.crop(3, 2, 5, 4).unwrap());
+
+        // Stitching keeps the first start, or moves the second one over
+        let plain = generated(6, 8, 4);
+        assert_eq!(Coord::new(2, 1), maze.stitch_horizontal(&plain).unwrap().get_start());
+        assert_eq!(Coord::new(10, 1), plain.stitch_horizontal(&maze).unwrap().get_start());
+        assert!(!plain.stitch_vertical(&plain).unwrap().has_start());
+    }
+
+    #[test]
+    fn test_elevations() {
+        let mut maze = generated(6, 8, 5);
+        maze.set_elevation(2, 1, 4).unwrap();
+        assert_eq!(Some(4), maze.rotate_cw().get_elevation(4, 2));
+        assert_eq!(Some(4), maze.mirror_x().get_elevation(5, 1));
+        assert_eq!(Some(4), maze.mirror_y().get_elevation(2, 4));
+        assert_eq!(maze, maze.rotate_cw().rotate_cw().rotate_cw().rotate_cw());
+
+        let (kept, _) = maze.crop_with_report(1, 0, 4, 4).unwrap();
+        assert_eq!(Some(4), kept.get_elevation(1, 1));
+        assert_eq!(16, kept.elevations().len());
+        assert!(maze.crop(3, 2, 5, 4).unwrap().elevations().iter().all(|&e| e == 0));
+
+        // The half without elevations is level
+        let plain = generated(6, 8, 4);
+        let both = plain.stitch_horizontal(&maze).unwrap();
+        assert_eq!(Some(4), both.get_elevation(10, 1));
+        assert_eq!(Some(0), both.get_elevation(2, 1));
+        assert_eq!(96, both.elevations().len());
+        assert!(plain.stitch_vertical(&plain).unwrap().elevations().is_empty());
+    }
+}
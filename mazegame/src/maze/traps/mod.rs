@@ -0,0 +1,221 @@
+//! Traps placed on a Maze's corridors, kept as a separate layer so the same
+//! maze can be trapped differently, and a solver that steers around them.
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use rand::Rng;
+
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// Which Squares of a Maze are trapped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Traps {
+    cols: u32,
+    rows: u32,
+    trapped: Vec<bool>,
+}
+
+impl Traps {
+    /// Creates a layer with no traps for a Maze the size of maze.
+    pub fn new(maze: &Maze) -> Traps {
+        Traps { cols: maze.cols, rows: maze.rows, trapped: vec![false; maze.sq.len()] }
+    }
+
+    /// Puts up to count traps on random corridor Squares of maze, that is
+    /// carved Squares outside of rooms, skipping every Square in avoid.
+    /// Fewer traps are placed if there aren't enough Squares for them.  The
+    /// same seed always gives the same traps.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::traps::Traps;
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// let solution = maze.solve(Coord::new(0, 0), Coord::new(9, 9)).unwrap();
+    /// let traps = Traps::place(&maze, 10, 2, &solution);
+    /// assert_eq!(10, traps.count());
+    /// assert!(solution.iter().all(|&c| !traps.is_trapped(c)));
+    /// ```
+    pub fn place(maze: &Maze, count: u32, seed: u64, avoid: &[Coord]) -> Traps {
+        Traps::place_with_rng(maze, count, avoid, &mut rng::from_seed(seed))
+    }
+
+    /// Places traps like place(), using rng.
+    pub fn place_with_rng<R: Rng>(maze: &Maze, count: u32, avoid: &[Coord], rng: &mut R) -> Traps {
+        let mut traps = Traps::new(maze);
+        let mut allowed = vec![true; maze.sq.len()];
+        for c in avoid.iter().filter(|c| maze.in_bounds(c.x, c.y)) {
+            allowed[maze.get_offset(c.x, c.y)] = false;
+        }
+        let mut candidates: Vec<usize> = maze.sq.iter().enumerate()
+            .filter(|&(i, sq)| allowed[i] && sq.is_carved() && !sq.is_part_of_room())
+            .map(|(i, _)| i)
+            .collect();
+        rng.shuffle(&mut candidates);
+        for &i in candidates.iter().take(count as usize) {
+            traps.trapped[i] = true;
+        }
+        traps
+    }
+
+    /// Returns whether there's a trap at coord.  Squares outside the Maze
+    /// aren't trapped.
+    pub fn is_trapped(&self, coord: Coord) -> bool {
+        coord.x < self.cols && coord.y < self.rows && self.trapped[(coord.y * self.cols + coord.x) as usize]
+    }
+
+    /// Adds or removes the trap at coord.  Does nothing outside the Maze.
+    pub fn set_trapped(&mut self, coord: Coord, trapped: bool) {
+        if coord.x < self.cols && coord.y < self.rows {
+            self.trapped[(coord.y * self.cols + coord.x) as usize] = trapped;
+        }
+    }
+
+    /// Returns the number of traps.
+    pub fn count(&self) -> u32 {
+        self.trapped.iter().filter(|&&t| t).count() as u32
+    }
+
+    /// Returns the trapped Squares in row-major order.
+    pub fn coords(&self) -> Vec<Coord> {
+        (0..self.trapped.len() as u32)
+            .filter(|&i| self.trapped[i as usize])
+            .map(|i| Coord::new(i % self.cols, i / self.cols))
+            .collect()
+    }
+
+    /// Counts the trapped Squares on path.
+    pub fn crossings(&self, path: &[Coord]) -> u32 {
+        path.iter().filter(|&&c| self.is_trapped(c)).count() as u32
+    }
+}
+
+impl Maze {
+    /// Finds the path from `from` to `to` that crosses the fewest traps,
+    /// and of those the shortest.  If any path avoids the traps entirely,
+    /// this is the shortest one that does.  Returns None if `to` can't be
+    /// reached at all.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::traps::Traps;
+    /// use mazegame::prelude::*;
+    ///
+    /// // A loop of six Squares with a trap on the short way round
+    /// let maze = Maze::from_compact("6ca\n5c9").unwrap();
+    /// let mut traps = Traps::new(&maze);
+    /// traps.set_trapped(Coord::new(1, 0), true);
+    /// let path = maze.safe_path(Coord::new(0, 0), Coord::new(2, 0), &traps).unwrap();
+    /// assert_eq!(0, traps.crossings(&path));
+    /// assert_eq!(5, path.len());
+    /// ```
+    pub fn safe_path(&self, from: Coord, to: Coord, traps: &Traps) -> Option<Vec<Coord>> {
+        if !self.in_bounds(from.x, from.y) || !self.in_bounds(to.x, to.y) {
+            return None;
+        }
+        // Dijkstra's, with a cost of (traps crossed, steps taken)
+        let mut best: Vec<Option<(u32, u32)>> = vec![None; self.sq.len()];
+        let mut prev: Vec<Option<Coord>> = vec![None; self.sq.len()];
+        let mut heap = BinaryHeap::new();
+        let start = (traps.is_trapped(from) as u32, 0);
+        best[self.get_offset(from.x, from.y)] = Some(start);
+        heap.push(Reverse((start, from)));
+
+        while let Some(Reverse((cost, cur))) = heap.pop() {
+            if cur == to {
+                let mut path = vec![to];
+                let mut at = to;
+                while let Some(p) = prev[self.get_offset(at.x, at.y)] {
+                    path.push(p);
+                    at = p;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if best[self.get_offset(cur.x, cur.y)].is_some_and(|b| b < cost) {
+                continue;
+            }
+            for next in self.open_neighbors(cur.x, cur.y) {
+                let offset = self.get_offset(next.x, next.y);
+                let next_cost = (cost.0 + traps.is_trapped(next) as u32, cost.1 + 1);
+                if best[offset].is_none_or(|b| next_cost < b) {
+                    best[offset] = Some(next_cost);
+                    prev[offset] = Some(cur);
+                    heap.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    #[test]
+    fn test_place() {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_seeded((4, 3, 4, 3, 4), 442).unwrap();
+        maze.connect_regions_with_rng(&mut rng::from_seed(442));
+        let solution = maze.solve(Coord::new(0, 0), Coord::new(19, 19)).unwrap();
+
+        let traps = Traps::place(&maze, 40, 442, &solution);
+        assert_eq!(40, traps.count());
+        assert_eq!(0, traps.crossings(&solution));
+        for c in traps.coords() {
+            let sq = &maze.sq[maze.get_offset(c.x, c.y)];
+            assert!(sq.is_carved() && !sq.is_part_of_room());
+        }
+        assert_eq!(traps, Traps::place(&maze, 40, 442, &solution));
+        assert_ne!(traps, Traps::place(&maze, 40, 443, &solution));
+
+        // Asking for more than fit places what it can
+        let corridors = maze.sq.iter().filter(|sq| sq.is_carved() && !sq.is_part_of_room()).count() as u32;
+        assert_eq!(corridors, Traps::place(&maze, 10_000, 1, &[]).count());
+        assert!(!traps.is_trapped(Coord::new(20, 0)));
+    }
+
+    #[test]
+    fn test_safe_path() {
+        let mut maze = Maze::new(12, 12);
+        maze.generate_perfect_seeded(442).unwrap();
+        maze.braid_with_rng(1.0, &mut rng::from_seed(1));
+        let (from, to) = (Coord::new(0, 0), Coord::new(11, 11));
+        let shortest = maze.solve(from, to).unwrap();
+
+        // Trapping the middle of the shortest path forces a detour
+        let mut traps = Traps::new(&maze);
+        traps.set_trapped(shortest[shortest.len() / 2], true);
+        let safe = maze.safe_path(from, to, &traps).unwrap();
+        assert_eq!(0, traps.crossings(&safe));
+        assert!(safe.len() >= shortest.len());
+        assert_eq!((from, to), (safe[0], *safe.last().unwrap()));
+        for w in safe.windows(2) {
+            assert!(maze.open_neighbors(w[0].x, w[0].y).contains(&w[1]));
+        }
+
+        // With no traps it's just the shortest path
+        assert_eq!(shortest.len(), maze.safe_path(from, to, &Traps::new(&maze)).unwrap().len());
+    }
+
+    #[test]
+    fn test_unavoidable_traps() {
+        // A corridor with a dead end off it: both traps on the corridor
+        // have to be crossed, but the one in the dead end doesn't
+        let mut maze = Maze::from_compact("4ccca\n00001").unwrap();
+        maze.carve(4, 1, constants::DIR_WEST, constants::ID_MAZE_PATH, false).unwrap();
+        let mut traps = Traps::new(&maze);
+        for &(x, y) in [(1, 0), (3, 0), (3, 1)].iter() {
+            traps.set_trapped(Coord::new(x, y), true);
+        }
+        let path = maze.safe_path(Coord::new(0, 0), Coord::new(4, 1), &traps).unwrap();
+        assert_eq!(2, traps.crossings(&path));
+        assert_eq!(6, path.len());
+        assert_eq!(None, maze.safe_path(Coord::new(0, 0), Coord::new(0, 1), &traps));
+    }
+}
@@ -0,0 +1,215 @@
+//! The spanning tree of a perfect maze, recorded while it's generated.  Each
+//! Square knows the Square it was carved from, which makes questions about
+//! paths and regions cheap to answer without searching the maze.
+use alloc::string::String;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::maze::generator;
+use crate::maze::info::GenerationAlgorithm;
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+/// The parent of every Square in a generated maze.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpanningTree {
+    /// The Square each Square was carved from, in row-major order.  The root
+    /// and any Squares the generator didn't reach, like rooms, have none.
+    pub parent: Vec<Option<Coord>>,
+    /// The Square the generator started from.
+    pub root: Coord,
+    cols: u32,
+}
+
+impl SpanningTree {
+    /// Returns the Squares from coord up to the root, both included, or None
+    /// if coord isn't part of the tree.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(6, 6);
+    /// let tree = maze.generate_spanning_tree_seeded(1).unwrap();
+    /// let path = tree.path_to_root(Coord::new(5, 5)).unwrap();
+    /// assert_eq!(Some(&tree.root), path.last());
+    /// assert_eq!(Some(path), maze.solve(Coord::new(5, 5), tree.root));
+    /// ```
+    pub fn path_to_root(&self, coord: Coord) -> Option<Vec<Coord>> {
+        if !self.contains(coord) {
+            return None;
+        }
+        let mut path = vec![coord];
+        let mut cur = coord;
+        while let Some(p) = self.parent[self.offset(cur)] {
+            path.push(p);
+            cur = p;
+        }
+        Some(path)
+    }
+
+    /// Returns the number of Squares whose path to the root passes through
+    /// coord, including coord itself.  That's every Square cut off from the
+    /// root if coord were walled up.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(6, 6);
+    /// let tree = maze.generate_spanning_tree_seeded(1).unwrap();
+    /// assert_eq!(36, tree.subtree_size(tree.root));
+    /// ```
+    pub fn subtree_size(&self, coord: Coord) -> u32 {
+        if !self.contains(coord) {
+            return 0;
+        }
+        // Add each Square to its ancestors, deepest Squares first
+        let mut depth: Vec<Option<u32>> = vec![None; self.parent.len()];
+        let mut order: Vec<usize> = Vec::new();
+        for i in 0..self.parent.len() {
+            if self.depth(i, &mut depth).is_some() {
+                order.push(i);
+            }
+        }
+        order.sort_by(|&a, &b| depth[b].cmp(&depth[a]));
+        let mut size = vec![1u32; self.parent.len()];
+        for i in order {
+            if let Some(p) = self.parent[i] {
+                let p = self.offset(p);
+                size[p] += size[i];
+            }
+        }
+        size[self.offset(coord)]
+    }
+
+    //
+    // Internal - whether coord is the root or has a parent.
+    //
+    fn contains(&self, coord: Coord) -> bool {
+        coord.x < self.cols && self.offset(coord) < self.parent.len() &&
+            (coord == self.root || self.parent[self.offset(coord)].is_some())
+    }
+
+    //
+    // Internal - the depth of the Square at offset i, or None if it isn't in
+    // the tree.  Depths already worked out are kept in known.
+    //
+    fn depth(&self, i: usize, known: &mut [Option<u32>]) -> Option<u32> {
+        // Climb to the nearest Square with a known depth, then fill in the
+        // Squares on the way back down
+        let mut chain = Vec::new();
+        let mut cur = i;
+        let mut d = loop {
+            if let Some(d) = known[cur] {
+                break d;
+            }
+            match self.parent[cur] {
+                Some(p) => {
+                    chain.push(cur);
+                    cur = self.offset(p);
+                }
+                None if cur == self.offset(self.root) => break 0,
+                None => return None,
+            }
+        };
+        known[cur] = Some(d);
+        for &c in chain.iter().rev() {
+            d += 1;
+            known[c] = Some(d);
+        }
+        Some(d)
+    }
+
+    //
+    // Internal - the index of coord in parent.
+    //
+    fn offset(&self, coord: Coord) -> usize {
+        (coord.y * self.cols + coord.x) as usize
+    }
+}
+
+impl Maze {
+    /// Generates a perfect maze from a seed and returns its spanning tree.
+    /// The maze is the same one generate_perfect_seeded() makes.
+    pub fn generate_spanning_tree_seeded(&mut self, seed: u64) -> Result<SpanningTree, String> {
+        let tree = self.generate_spanning_tree_with_rng(&mut rng::from_seed(seed))?;
+        self.record_generation(seed, GenerationAlgorithm::GrowingTree, None);
+        Ok(tree)
+    }
+
+    /// Generates a perfect maze using the supplied random number generator,
+    /// and returns its spanning tree.
+    pub fn generate_spanning_tree_with_rng<R: Rng>(&mut self, rng: &mut R) -> Result<SpanningTree, String> {
+        let root = self.get_start();
+        let (stats, edges) = generator::growing_tree_with_edges(self, root, rng)?;
+        gen_debug!("growing_tree x={} y={} carved={} backtracks={}", root.x, root.y, stats.carved, stats.backtracks);
+
+        let mut parent = vec![None; self.sq.len()];
+        for (from, to) in edges {
+            parent[self.get_offset(to.x, to.y)] = Some(from);
+        }
+        Ok(SpanningTree { parent, root, cols: self.cols })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    #[test]
+    fn test_parents_reach_root() {
+        let mut maze = Maze::new(15, 20);
+        let tree = maze.generate_spanning_tree_seeded(417).unwrap();
+        for y in 0..15 {
+            for x in 0..20 {
+                let path = tree.path_to_root(Coord::new(x, y)).unwrap();
+                assert_eq!(Some(&tree.root), path.last());
+                // Every step to a parent goes through an open passage
+                for pair in path.windows(2) {
+                    assert!(maze.open_neighbors(pair[0].x, pair[0].y).contains(&pair[1]));
+                }
+            }
+        }
+        assert_eq!(None, tree.parent[0]);
+        assert_eq!(None, tree.path_to_root(Coord::new(20, 0)));
+
+        let mut plain = Maze::new(15, 20);
+        plain.generate_perfect_seeded(417).unwrap();
+        assert_eq!(plain, maze);
+    }
+
+    #[test]
+    fn test_subtree_size() {
+        let mut maze = Maze::new(10, 10);
+        let tree = maze.generate_spanning_tree_seeded(3).unwrap();
+        assert_eq!(100, tree.subtree_size(tree.root));
+
+        // A dead end is a subtree of its own
+        let dead_end = (0..100).map(|i| Coord::new(i % 10, i / 10))
+                               .find(|&c| c != tree.root && maze.open_neighbors(c.x, c.y).len() == 1).unwrap();
+        assert_eq!(1, tree.subtree_size(dead_end));
+
+        // Walling a Square off from its parent cuts off its whole subtree
+        let cut = (0..100).map(|i| Coord::new(i % 10, i / 10))
+                          .max_by_key(|&c| if c == tree.root { 0 } else { tree.subtree_size(c) }).unwrap();
+        let parent = tree.parent[maze.get_offset(cut.x, cut.y)].unwrap();
+        let dir = constants::Direction::ALL.iter().cloned()
+            .find(|&d| maze.neighbor(cut.x, cut.y, d.into()) == Some(parent)).unwrap();
+        let (offset, parent_offset) = (maze.get_offset(cut.x, cut.y), maze.get_offset(parent.x, parent.y));
+        maze.sq[offset].build_wall(dir.into());
+        maze.sq[parent_offset].build_wall(dir.opposite().into());
+        let reachable = (0..100).filter(|&i| maze.solve(tree.root, Coord::new(i % 10, i / 10)).is_some()).count();
+        assert_eq!(100 - tree.subtree_size(cut), reachable as u32);
+    }
+
+    #[test]
+    fn test_carved_squares_outside_tree() {
+        // Squares carved before generation starts are left out, like rooms
+        let mut maze = Maze::new(8, 8);
+        maze.carve(5, 5, constants::DIR_EAST, 1, false).unwrap();
+        let tree = maze.generate_spanning_tree_seeded(2).unwrap();
+        assert_eq!(None, tree.path_to_root(Coord::new(5, 5)));
+        assert_eq!(0, tree.subtree_size(Coord::new(6, 5)));
+        assert_eq!(62, tree.subtree_size(tree.root));
+    }
+}
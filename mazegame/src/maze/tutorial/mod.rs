@@ -0,0 +1,456 @@
+//! The guided first level.  build() gives a small hand-made maze and the
+//! triggers that explain it; a Tutorial walks a player through it, showing
+//! a trigger's message the first time the player steps into its area.
+//!
+//! The triggers run on a TriggerEngine, which knows nothing about the
+//! tutorial: any level can use one.  A trigger fires once, on the first
+//! step into its area after the trigger it waits for, if any, has fired.
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::room::Door;
+use crate::maze::{Coord, Maze};
+use crate::messages::MessageCatalog;
+
+// The tutorial maze, in the compact form: a dead end down the left side,
+// another off the first corridor, and a room in the top right to pass
+// through on the way to the exit
+const TUTORIAL_MAZE: &str = "eecceea\n35c85f9\n36cccda\n35a6a49\n58595ca";
+
+// The Squares of the tutorial's room
+const TUTORIAL_ROOM: (Coord, Coord) = (Coord { x: 4, y: 0 }, Coord { x: 6, y: 1 });
+
+/// An area of a level that shows a message when the player steps into it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TutorialTrigger {
+    /// Names the trigger, for others to wait on.
+    pub name: String,
+    /// Opposite corners of the area, both inside it.
+    pub from: Coord,
+    pub to: Coord,
+    /// The key of the message to show, in a MessageCatalog.
+    pub message: String,
+    /// The trigger that has to fire before this one can.
+    pub after: Option<String>,
+}
+
+impl TutorialTrigger {
+    /// Creates a trigger for the block of Squares between from and to.
+    pub fn new(name: &str, from: Coord, to: Coord, message: &str) -> TutorialTrigger {
+        TutorialTrigger { name: name.to_string(), from, to, message: message.to_string(), after: None }
+    }
+
+    /// Makes the trigger wait until the one called name has fired.
+    pub fn after(self, name: &str) -> TutorialTrigger {
+        TutorialTrigger { after: Some(name.to_string()), ..self }
+    }
+
+    /// Returns whether coord is in the trigger's area.
+    pub fn contains(&self, coord: Coord) -> bool {
+        let between = |v: u32, a: u32, b: u32| a.min(b) <= v && v <= a.max(b);
+        between(coord.x, self.from.x, self.to.x) && between(coord.y, self.from.y, self.to.y)
+    }
+}
+
+/// Fires triggers as the player moves, each only once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TriggerEngine {
+    triggers: Vec<TutorialTrigger>,
+    fired: Vec<bool>,
+}
+
+impl TriggerEngine {
+    /// Creates an engine with none of triggers fired yet.  Fails if two
+    /// triggers share a name, or if one waits on a trigger that isn't
+    /// there or on a chain of triggers that leads back to itself, since it
+    /// could never fire.
+    pub fn new(triggers: Vec<TutorialTrigger>) -> Result<TriggerEngine, MazeError> {
+        let index = |name: &str| triggers.iter().position(|t| t.name == name);
+        for (i, trigger) in triggers.iter().enumerate() {
+            if index(&trigger.name) != Some(i) {
+                return Err(MazeError::InvalidArgument(format!("there are two triggers called '{}'", trigger.name)));
+            }
+            // Following the chain of waits from any trigger has to end
+            // within as many steps as there are triggers
+            let mut waits = trigger.after.as_deref();
+            for _ in 0..=triggers.len() {
+                let name = match waits {
+                    Some(name) => name,
+                    None => break,
+                };
+                let before = index(name).ok_or_else(|| {
+                    MazeError::InvalidArgument(format!("trigger '{}' waits on '{}', which isn't there",
+                                                       trigger.name, name))
+                })?;
+                waits = triggers[before].after.as_deref();
+            }
+            if waits.is_some() {
+                return Err(MazeError::InvalidArgument(format!("trigger '{}' waits on itself", trigger.name)));
+            }
+        }
+        let fired = vec![false; triggers.len()];
+        Ok(TriggerEngine { triggers, fired })
+    }
+
+    /// Returns the triggers, in the order they were given.
+    pub fn triggers(&self) -> &[TutorialTrigger] {
+        &self.triggers
+    }
+
+    /// Returns whether the trigger called name has fired.
+    pub fn has_fired(&self, name: &str) -> bool {
+        self.triggers.iter().zip(self.fired.iter()).any(|(t, &fired)| fired && t.name == name)
+    }
+
+    /// Records the player stepping onto coord, and returns the triggers it
+    /// fires, each after any it waits on.  Triggers that fire together
+    /// come out in the order they were given, except where one waits on a
+    /// later one.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::tutorial::{TriggerEngine, TutorialTrigger};
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut engine = TriggerEngine::new(vec![
+    ///     TutorialTrigger::new("key", Coord::new(0, 0), Coord::new(0, 0), "found_key"),
+    ///     TutorialTrigger::new("door", Coord::new(5, 0), Coord::new(5, 3), "open_door").after("key"),
+    /// ]).unwrap();
+    /// assert!(engine.enter(Coord::new(5, 1)).is_empty());
+    /// assert_eq!("key", engine.enter(Coord::new(0, 0))[0].name);
+    /// assert_eq!("door", engine.enter(Coord::new(5, 2))[0].name);
+    /// assert!(engine.enter(Coord::new(5, 2)).is_empty());
+    /// ```
+    pub fn enter(&mut self, coord: Coord) -> Vec<TutorialTrigger> {
+        let mut fired = Vec::new();
+        loop {
+            let ready = (0..self.triggers.len()).find(|&i| {
+                let trigger = &self.triggers[i];
+                !self.fired[i] && trigger.contains(coord) &&
+                    trigger.after.as_deref().is_none_or(|name| self.has_fired(name))
+            });
+            match ready {
+                Some(i) => {
+                    self.fired[i] = true;
+                    fired.push(self.triggers[i].clone());
+                }
+                None => return fired,
+            }
+        }
+    }
+
+    /// Forgets which triggers have fired, to play the level again.
+    pub fn reset(&mut self) {
+        self.fired.iter_mut().for_each(|fired| *fired = false);
+    }
+}
+
+/// Returns the tutorial maze and its triggers.  The player starts at the
+/// entrance in the top left; the exit is in the bottom right.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::tutorial;
+///
+/// let (maze, triggers) = tutorial::build();
+/// assert!(maze.validate().is_ok());
+/// assert_eq!("welcome", triggers[0].name);
+/// ```
+pub fn build() -> (Maze, Vec<TutorialTrigger>) {
+    let mut maze = Maze::from_compact(TUTORIAL_MAZE).unwrap();
+    let (from, to) = TUTORIAL_ROOM;
+    for y in from.y..=to.y {
+        for x in from.x..=to.x {
+            let offset = maze.get_offset(x, y);
+            maze.sq[offset].id = 1;
+        }
+    }
+    maze.num_rooms = 1;
+    maze.add_door(Door { coord: Coord::new(4, 0), dir: constants::DIR_WEST, room_id: 1, locked: false }).unwrap();
+    maze.add_door(Door { coord: Coord::new(5, 1), dir: constants::DIR_SOUTH, room_id: 1, locked: false }).unwrap();
+
+    let triggers = vec![
+        TutorialTrigger::new("welcome", Coord::new(0, 0), Coord::new(0, 0), "tutorial.welcome"),
+        TutorialTrigger::new("dead_end", Coord::new(0, 4), Coord::new(1, 4), "tutorial.dead_end"),
+        TutorialTrigger::new("backtracked", Coord::new(0, 0), Coord::new(0, 1), "tutorial.backtracked").after("dead_end"),
+        TutorialTrigger::new("room", from, to, "tutorial.room"),
+        TutorialTrigger::new("exit_near", Coord::new(4, 4), Coord::new(6, 4), "tutorial.exit_near").after("room"),
+    ];
+    (maze, triggers)
+}
+
+/// Something that happened when the player moved in a Tutorial.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TutorialEvent {
+    /// The player moved onto a Square.
+    Moved(Coord),
+    /// A trigger fired, and its message is waiting to be shown.
+    Message { trigger: String, text: String },
+    /// The player is on the exit.
+    ReachedExit,
+}
+
+/// A player working through the tutorial.  Messages from triggers queue up
+/// and are shown one at a time until dismissed; the player can keep moving
+/// while one is showing.
+#[derive(Clone, Debug)]
+pub struct Tutorial {
+    maze: Maze,
+    engine: TriggerEngine,
+    messages: MessageCatalog,
+    player: Coord,
+    shown: VecDeque<String>,
+}
+
+impl Tutorial {
+    /// Starts the tutorial with its messages in English.
+    pub fn new() -> Tutorial {
+        Tutorial::with_messages(MessageCatalog::english())
+    }
+
+    /// Starts the tutorial with its messages from messages.  The player
+    /// stands on the entrance, with whatever it triggers already showing.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::maze::tutorial::Tutorial;
+    ///
+    /// let mut tutorial = Tutorial::new();
+    /// assert!(tutorial.message().unwrap().starts_with("Welcome"));
+    /// assert!(tutorial.dismiss_message());
+    /// assert_eq!(None, tutorial.message());
+    /// ```
+    pub fn with_messages(messages: MessageCatalog) -> Tutorial {
+        let (maze, triggers) = build();
+        let player = maze.player_start().unwrap_or(Coord::new(0, 0));
+        let mut tutorial = Tutorial {
+            maze,
+            engine: TriggerEngine::new(triggers).unwrap(),
+            messages,
+            player,
+            shown: VecDeque::new(),
+        };
+        tutorial.fire(player);
+        tutorial
+    }
+
+    /// Returns the tutorial maze.
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    /// Returns the Square the player is on.
+    pub fn player(&self) -> Coord {
+        self.player
+    }
+
+    /// Returns the triggers and which have fired.
+    pub fn engine(&self) -> &TriggerEngine {
+        &self.engine
+    }
+
+    /// Moves the player one Square in direction dir, and returns what
+    /// happened.  Fails if there's a wall in the way.
+    pub fn move_player(&mut self, dir: u32) -> Result<Vec<TutorialEvent>, MazeError> {
+        let Coord { x, y } = self.player;
+        let next = match self.maze.passable_step(x, y, dir, true) {
+            Some(next) => next,
+            None => return Err(MazeError::InvalidArgument(format!("can't move {} from ({}, {})",
+                                                               crate::trace::dir_name(dir), x, y))),
+        };
+        self.player = next;
+        let mut events = vec![TutorialEvent::Moved(next)];
+        events.extend(self.fire(next));
+        if self.maze.is_exit(next) {
+            events.push(TutorialEvent::ReachedExit);
+        }
+        Ok(events)
+    }
+
+    /// Returns the message being shown, if there is one.
+    pub fn message(&self) -> Option<&str> {
+        self.shown.front().map(|text| text.as_str())
+    }
+
+    /// Dismisses the message being shown, bringing up the next one waiting.
+    /// Returns whether there was one to dismiss.
+    pub fn dismiss_message(&mut self) -> bool {
+        self.shown.pop_front().is_some()
+    }
+
+    /// Draws the maze with the player as an '@', and the message being
+    /// shown in a box underneath.
+    pub fn render_ascii(&self) -> String {
+        let rendered = self.maze.render_ascii();
+        let mut lines: Vec<Vec<char>> = rendered.lines().map(|l| l.chars().collect()).collect();
+        lines[(self.player.y * 2 + 1) as usize][(self.player.x * 2 + 1) as usize] = '@';
+        let mut out = String::new();
+        for line in lines {
+            out.extend(line);
+            out.push('\n');
+        }
+        if let Some(text) = self.message() {
+            out.push_str(&message_box(&[text, self.messages.get("tutorial.dismiss")]));
+        }
+        out
+    }
+
+    //
+    // Internal - fires the triggers at coord, queueing their messages.
+    //
+    fn fire(&mut self, coord: Coord) -> Vec<TutorialEvent> {
+        let mut events = Vec::new();
+        for trigger in self.engine.enter(coord) {
+            let text = self.messages.get(&trigger.message).to_string();
+            self.shown.push_back(text.clone());
+            events.push(TutorialEvent::Message { trigger: trigger.name, text });
+        }
+        events
+    }
+}
+
+impl Default for Tutorial {
+    fn default() -> Tutorial {
+        Tutorial::new()
+    }
+}
+
+/// Draws lines of text in a box, for showing a message under a maze.
+///
+/// # Example
+/// ```
+/// let drawn = mazegame::maze::tutorial::message_box(&["Hello", "[Enter]"]);
+/// assert_eq!("+---------+\n| Hello   |\n| [Enter] |\n+---------+\n", drawn);
+/// ```
+pub fn message_box(lines: &[&str]) -> String {
+    let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    let edge = format!("+{}+\n", "-".repeat(width + 2));
+    let mut out = edge.clone();
+    for line in lines {
+        out.push_str(&format!("| {}{} |\n", line, " ".repeat(width - line.chars().count())));
+    }
+    out.push_str(&edge);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DIR_EAST, DIR_NORTH, DIR_SOUTH, DIR_WEST};
+
+    // Walks the player through moves, returning the triggers that fired
+    fn walk(tutorial: &mut Tutorial, moves: &[u32]) -> Vec<String> {
+        let mut fired = Vec::new();
+        for &dir in moves.iter() {
+            for event in tutorial.move_player(dir).unwrap() {
+                if let TutorialEvent::Message { trigger, .. } = event {
+                    fired.push(trigger);
+                }
+            }
+        }
+        fired
+    }
+
+    #[test]
+    fn test_maze() {
+        let (maze, triggers) = build();
+        assert!(maze.validate().is_ok());
+        assert_eq!(Some(Coord::new(0, 0)), maze.get_entrance().map(|o| o.coord));
+        assert!(maze.is_exit(Coord::new(6, 4)));
+        assert!(maze.solve(Coord::new(0, 0), Coord::new(6, 4)).unwrap().contains(&Coord::new(5, 1)));
+        assert!(TriggerEngine::new(triggers.clone()).is_ok());
+        let english = MessageCatalog::english();
+        for trigger in triggers.iter() {
+            assert_ne!(trigger.message, english.get(&trigger.message), "{}", trigger.message);
+        }
+    }
+
+    #[test]
+    fn test_scripted_walks() {
+        // Straight to the exit, through the room
+        let mut tutorial = Tutorial::new();
+        assert!(tutorial.engine().has_fired("welcome"));
+        let fired = walk(&mut tutorial, &[DIR_EAST, DIR_EAST, DIR_EAST, DIR_EAST, DIR_EAST, DIR_SOUTH, DIR_SOUTH]);
+        assert_eq!(vec!["room"], fired);
+        let fired = walk(&mut tutorial, &[DIR_WEST, DIR_WEST, DIR_WEST, DIR_WEST, DIR_SOUTH, DIR_EAST,
+                                          DIR_SOUTH, DIR_EAST, DIR_NORTH, DIR_EAST, DIR_SOUTH, DIR_EAST]);
+        assert_eq!(vec!["exit_near"], fired);
+        let events = tutorial.move_player(DIR_EAST).unwrap();
+        assert_eq!(Some(&TutorialEvent::ReachedExit), events.last());
+        assert!(!tutorial.engine().has_fired("dead_end"));
+        assert!(!tutorial.engine().has_fired("backtracked"));
+
+        // Into the dead end and back: the hint about walls only comes on
+        // the way back, and only once
+        let mut tutorial = Tutorial::new();
+        assert_eq!(Vec::<String>::new(), walk(&mut tutorial, &[DIR_SOUTH, DIR_NORTH]));
+        assert_eq!(vec!["dead_end"], walk(&mut tutorial, &[DIR_SOUTH, DIR_SOUTH, DIR_SOUTH, DIR_SOUTH, DIR_EAST]));
+        assert_eq!(vec!["backtracked"], walk(&mut tutorial, &[DIR_WEST, DIR_NORTH, DIR_NORTH, DIR_NORTH]));
+        assert!(walk(&mut tutorial, &[DIR_NORTH, DIR_SOUTH, DIR_SOUTH, DIR_SOUTH, DIR_SOUTH]).is_empty());
+
+        // Messages wait to be dismissed in the order they fired
+        assert!(tutorial.message().unwrap().starts_with("Welcome"));
+        assert!(tutorial.dismiss_message());
+        assert!(tutorial.message().unwrap().contains("dead end"));
+        assert!(tutorial.render_ascii().contains("| This is a dead end"));
+        assert!(tutorial.dismiss_message());
+        assert!(tutorial.dismiss_message());
+        assert!(!tutorial.dismiss_message());
+        assert_eq!(None, tutorial.message());
+        assert!(!tutorial.render_ascii().contains('|'));
+
+        // Walls stop the player
+        assert!(tutorial.move_player(DIR_EAST).is_ok());
+        assert!(tutorial.move_player(DIR_EAST).is_err());
+        assert_eq!(Coord::new(1, 4), tutorial.player());
+    }
+
+    #[test]
+    fn test_crossings() {
+        // A corridor down the middle column, crossed by a tunnel along the
+        // middle row: the player can't turn off the corridor into it
+        let mut tutorial = Tutorial::new();
+        let mut maze = Maze::new(3, 3);
+        maze.carve(1, 0, DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_under(0, 1, DIR_EAST).unwrap();
+        tutorial.maze = maze;
+        tutorial.player = Coord::new(1, 1);
+        assert!(tutorial.move_player(DIR_EAST).is_err());
+        assert!(tutorial.move_player(DIR_WEST).is_err());
+        assert_eq!(Coord::new(1, 1), tutorial.player());
+
+        // The tunnel leads under the crossing to the far side
+        tutorial.player = Coord::new(0, 1);
+        assert!(tutorial.move_player(DIR_EAST).is_ok());
+        assert_eq!(Coord::new(2, 1), tutorial.player());
+    }
+
+    #[test]
+    fn test_engine() {
+        let at = |x, y| Coord::new(x, y);
+        let mut engine = TriggerEngine::new(vec![
+            TutorialTrigger::new("c", at(0, 0), at(3, 3), "c").after("b"),
+            TutorialTrigger::new("a", at(2, 2), at(0, 0), "a"),
+            TutorialTrigger::new("b", at(1, 1), at(1, 1), "b").after("a"),
+        ]).unwrap();
+        assert!(engine.enter(at(3, 3)).is_empty());
+        // A chain that's ready all at once fires in order
+        let names: Vec<String> = engine.enter(at(1, 1)).into_iter().map(|t| t.name).collect();
+        assert_eq!(vec!["a", "b", "c"], names);
+        assert!(engine.enter(at(1, 1)).is_empty());
+        engine.reset();
+        assert!(!engine.has_fired("a"));
+        assert_eq!(1, engine.enter(at(2, 0)).len());
+
+        let one = |name: &str, after: &str| TutorialTrigger::new(name, at(0, 0), at(0, 0), name).after(after);
+        assert!(TriggerEngine::new(vec![one("a", "b")]).is_err());
+        assert!(TriggerEngine::new(vec![one("a", "a")]).is_err());
+        assert!(TriggerEngine::new(vec![one("a", "b"), one("b", "c"), one("c", "a")]).is_err());
+        let plain = TutorialTrigger::new("a", at(0, 0), at(0, 0), "a");
+        assert!(TriggerEngine::new(vec![plain.clone(), plain]).is_err());
+    }
+}
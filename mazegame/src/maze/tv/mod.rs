@@ -0,0 +1,195 @@
+//! "Maze TV": a spectator mode that streams a maze being made and solved
+//! as plain text frames.  Each frame moves the cursor home and redraws the
+//! whole maze, so the stream plays back on any ANSI terminal - over ssh,
+//! or from a file with `cat` - and needs nothing but a writer.
+//!
+//! The show has three parts.  First the maze is carved one passage at a
+//! time, replaying the order the generator carved them in.  Then the
+//! breadth-first search from the entrance spreads out a layer at a time,
+//! drawn over the maze, until it reaches the exit.  Last comes the solution.
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::generator;
+use crate::maze::{Coord, Maze};
+use crate::pipeline::RunConfig;
+use crate::rng;
+
+/// Moves the cursor to the top left corner; written before every frame.
+pub const CURSOR_HOME: &str = "\x1b[H";
+/// Clears the screen; written once, before the first frame.
+pub const CLEAR_SCREEN: &str = "\x1b[2J";
+/// Drawn on the Squares the search reaches in the layer being shown.
+pub const FRONTIER_MARK: char = '*';
+/// Drawn on the Squares the search reached in earlier layers.
+pub const SEARCHED_MARK: char = ':';
+
+/// Streams frames of a perfect maze from config being carved and solved to
+/// w, waiting frame_delay after each one.  The entrance and exit go as far
+/// apart as they can on the outer wall.  Returns how many frames were
+/// written: one per passage carved (each showing the maze before it's
+/// carved, so the first is solid wall), one per layer of the search, and
+/// one for the solution.
+///
+/// Mazes with rooms aren't supported, since rooms are carved all at once
+/// before generation starts.
+///
+/// # Example
+/// ```
+/// use mazegame::maze::tv;
+/// use mazegame::pipeline::RunConfig;
+/// use std::time::Duration;
+///
+/// let mut out = Vec::new();
+/// let frames = tv::stream(&RunConfig::new(4, 3, 1), &mut out, Duration::from_millis(0)).unwrap();
+/// let text = String::from_utf8(out).unwrap();
+/// assert_eq!(frames as usize, text.matches(tv::CURSOR_HOME).count());
+/// ```
+pub fn stream<W: Write>(config: &RunConfig, w: &mut W, frame_delay: Duration) -> Result<u32, MazeError> {
+    if config.rooms.0 > 0 {
+        return Err(MazeError::InvalidArgument("maze TV can't show mazes with rooms".to_string()));
+    }
+    if config.width == 0 || config.height == 0 {
+        return Err(MazeError::InvalidArgument(format!("a {}x{} maze has no Squares", config.width, config.height)));
+    }
+
+    // Generate once to learn the order passages are carved in
+    let mut finished = Maze::new(config.height, config.width);
+    let (_, edges) = generator::growing_tree_with_edges(&mut finished, Coord::new(0, 0),
+                                                        &mut rng::from_seed(config.seed))
+        .map_err(MazeError::InvalidArgument)?;
+
+    let mut frames = 0;
+    let mut show = |w: &mut W, frame: &str| -> Result<(), MazeError> {
+        if frames == 0 {
+            w.write_all(CLEAR_SCREEN.as_bytes())?;
+        }
+        w.write_all(CURSOR_HOME.as_bytes())?;
+        w.write_all(frame.as_bytes())?;
+        w.flush()?;
+        frames += 1;
+        if frame_delay > Duration::from_secs(0) {
+            thread::sleep(frame_delay);
+        }
+        Ok(())
+    };
+
+    // Carving
+    let mut maze = Maze::new(config.height, config.width);
+    for &(from, to) in edges.iter() {
+        show(w, &maze.render_ascii())?;
+        let dir = (0..constants::NUM_DIRECTIONS)
+            .find(|&dir| maze.neighbor(from.x, from.y, dir) == Some(to))
+            .ok_or_else(|| MazeError::InvalidArgument(format!("({}, {}) and ({}, {}) aren't neighbors",
+                                                              from.x, from.y, to.x, to.y)))?;
+        maze.carve(from.x, from.y, dir, constants::ID_MAZE_PATH, false).map_err(MazeError::InvalidArgument)?;
+    }
+
+    // Searching
+    maze.place_entrance_exit_longest()?;
+    let (entrance, exit) = match (maze.get_entrance(), maze.get_exit()) {
+        (Some(entrance), Some(exit)) => (entrance.coord, exit.coord),
+        _ => return Err(MazeError::InvalidArgument("the maze has no entrance or exit".to_string())),
+    };
+    let dist = maze.distances(entrance);
+    let goal = dist[maze.get_offset(exit.x, exit.y)].ok_or(MazeError::Unreachable(entrance, exit))?;
+    let drawn = maze.render_ascii();
+    for layer in 0..=goal {
+        let marks: Vec<(usize, char)> = dist.iter().enumerate()
+            .filter_map(|(i, d)| match *d {
+                Some(d) if d == layer => Some((i, FRONTIER_MARK)),
+                Some(d) if d < layer => Some((i, SEARCHED_MARK)),
+                _ => None,
+            })
+            .collect();
+        show(w, &overlay(&maze, &drawn, &marks))?;
+    }
+
+    // The solution
+    let path = maze.solve(entrance, exit).ok_or(MazeError::Unreachable(entrance, exit))?;
+    show(w, &maze.render_ascii_with_path(&path))?;
+    Ok(frames)
+}
+
+//
+// Internal - draws a mark in the middle of Squares of a render of maze,
+// each given by its offset in `sq`.
+//
+fn overlay(maze: &Maze, drawn: &str, marks: &[(usize, char)]) -> String {
+    let mut lines: Vec<Vec<char>> = drawn.lines().map(|l| l.chars().collect()).collect();
+    for &(i, mark) in marks.iter() {
+        let Coord { x, y } = maze.get_coord(i);
+        lines[(y * 2 + 1) as usize][(x * 2 + 1) as usize] = mark;
+    }
+    let mut out = String::new();
+    for line in lines {
+        out.extend(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(config: &RunConfig) -> (u32, Vec<String>) {
+        let mut out = Vec::new();
+        let count = stream(config, &mut out, Duration::from_millis(0)).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with(CLEAR_SCREEN));
+        let frames = text[CLEAR_SCREEN.len()..].split(CURSOR_HOME).skip(1).map(|f| f.to_string()).collect();
+        (count, frames)
+    }
+
+    #[test]
+    fn test_frame_sequence() {
+        let config = RunConfig::new(9, 6, 466);
+        let (count, frames) = frames(&config);
+        assert_eq!(count as usize, frames.len());
+
+        let solid = Maze::new(6, 9).render_ascii();
+        assert_eq!(solid, frames[0]);
+
+        let mut maze = Maze::new(6, 9);
+        maze.generate_perfect_seeded(466).unwrap();
+        maze.place_entrance_exit_longest().unwrap();
+        let path = maze.solve(maze.get_entrance().unwrap().coord, maze.get_exit().unwrap().coord).unwrap();
+        assert_eq!(maze.render_ascii_with_path(&path), *frames.last().unwrap());
+        assert!(frames.last().unwrap().contains('.'));
+
+        // A passage per Square but the first, a layer per Square on the
+        // solution, and the solution itself
+        let carves = 9 * 6 - 1;
+        assert_eq!(carves + path.len() + 1, frames.len());
+        // The search starts from the entrance alone and ends at the exit
+        assert_eq!(1, frames[carves].matches(FRONTIER_MARK).count());
+        let last_layer = &frames[frames.len() - 2];
+        let exit = maze.get_exit().unwrap().coord;
+        let line: Vec<char> = last_layer.lines().nth((exit.y * 2 + 1) as usize).unwrap().chars().collect();
+        assert_eq!(FRONTIER_MARK, line[(exit.x * 2 + 1) as usize]);
+    }
+
+    #[test]
+    fn test_carving_frames() {
+        // Each carving frame opens exactly one more wall than the last
+        let (_, frames) = frames(&RunConfig::new(5, 5, 3));
+        let walls = |frame: &str| frame.matches('X').count();
+        for pair in frames[..24].windows(2) {
+            assert_eq!(walls(&pair[0]) - 1, walls(&pair[1]));
+        }
+        assert!(frames.iter().all(|f| f.lines().count() == 11));
+    }
+
+    #[test]
+    fn test_bad_configs() {
+        let mut out = Vec::new();
+        let rooms = RunConfig { rooms: (2, 2, 3, 2, 3), ..RunConfig::new(10, 10, 1) };
+        assert!(stream(&rooms, &mut out, Duration::from_millis(0)).is_err());
+        assert!(stream(&RunConfig::new(0, 3, 1), &mut out, Duration::from_millis(0)).is_err());
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+//! Variations on a maze, for practice runs at a level that are similar but
+//! not identical.  Rectangles of plain corridor are torn up and carved
+//! again, joining only Squares that were connected before, so the rooms,
+//! doors, entrance, exits and what can reach what all stay as they were.
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::square::Square;
+use crate::maze::{find_region, Coord, Maze};
+use crate::rng;
+
+impl Maze {
+    /// Re-carves the passages between the plain corridor Squares in the
+    /// width x height block with its top left corner at (x, y).  Passages
+    /// leading out of the block, and Squares in rooms, uncarved or with
+    /// one-way, secret or weave passages, are left alone.  The new passages
+    /// only join Squares that were connected before, and never make a loop
+    /// that the old ones didn't, so a perfect maze stays perfect.
+    ///
+    /// Fails if the block is empty or doesn't fit in the Maze.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(12, 12);
+    /// maze.generate_perfect_seeded(481).unwrap();
+    /// let before = maze.clone();
+    /// maze.regenerate_region(2, 2, 8, 8, &mut mazegame::rng::from_seed(1)).unwrap();
+    /// assert_ne!(before, maze);
+    /// assert!(maze.solve(Coord::new(0, 0), Coord::new(11, 11)).is_some());
+    /// ```
+    pub fn regenerate_region<R: Rng>(&mut self, x: u32, y: u32, width: u32, height: u32,
+                                     rng: &mut R) -> Result<(), MazeError> {
+        if width == 0 || height == 0 {
+            return Err(MazeError::InvalidArgument("a region needs a width and height of at least 1".to_string()));
+        }
+        let (right, bottom) = (x.saturating_add(width - 1), y.saturating_add(height - 1));
+        if !self.in_bounds(right, bottom) {
+            return Err(MazeError::OutOfBounds(right, bottom));
+        }
+
+        // Each passage that can be redrawn, as the Square it's carved from
+        // and the direction to carve in
+        let before = self.connected_regions();
+        let mut passages = Vec::new();
+        for cy in y..=bottom {
+            for cx in x..=right {
+                let a = self.get_offset(cx, cy);
+                let sides = [(cx + 1, cy, Direction::East), (cx, cy + 1, Direction::South)];
+                for &(nx, ny, dir) in sides.iter().filter(|&&(nx, ny, _)| nx <= right && ny <= bottom) {
+                    let b = self.get_offset(nx, ny);
+                    if is_plain_corridor(&self.sq[a]) && is_plain_corridor(&self.sq[b]) && before[a] == before[b] {
+                        passages.push((Coord::new(cx, cy), dir, a, b));
+                    }
+                }
+            }
+        }
+        for &(_, dir, a, b) in passages.iter() {
+            self.sq[a].build_wall(dir.into());
+            self.sq[b].build_wall(dir.opposite().into());
+        }
+        self.mark_changed();
+
+        let mut region = self.connected_regions();
+        rng.shuffle(&mut passages);
+        let mut carved = 0;
+        for &(c, dir, a, b) in passages.iter() {
+            let (ra, rb) = (find_region(&mut region, a), find_region(&mut region, b));
+            if ra != rb {
+                self.carve(c.x, c.y, dir.into(), constants::ID_MAZE_PATH, false).unwrap();
+                region[ra] = rb;
+                carved += 1;
+            }
+        }
+        gen_debug!("regenerate_region x={} y={} width={} height={} walls={} carved={}",
+                   x, y, width, height, passages.len(), carved);
+        Ok(())
+    }
+
+    /// Returns a copy of the maze with some of its corridors re-carved by
+    /// regenerate_region(), in random blocks that between them cover about
+    /// strength (from 0.0 to 1.0) of the plain corridor Squares.  Strength
+    /// 0.0 gives an identical copy, and 1.0 re-carves every corridor, which
+    /// is as good as a fresh maze around the same rooms.  The entrance,
+    /// exits, rooms, doors and connectivity are kept, and the same seed and
+    /// strength always give the same variant.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(20, 20);
+    /// maze.generate_seeded((4, 2, 4, 2, 4), 481).unwrap();
+    /// assert_eq!(maze, maze.variant(0.0, 1));
+    /// let practice = maze.variant(0.3, 1);
+    /// assert_ne!(maze, practice);
+    /// assert_eq!(practice, maze.variant(0.3, 1));
+    /// ```
+    pub fn variant(&self, strength: f32, seed: u64) -> Maze {
+        let mut maze = self.clone();
+        let plain: Vec<bool> = self.sq.iter().map(is_plain_corridor).collect();
+        let total = plain.iter().filter(|&&p| p).count();
+        // Rounded to the nearest Square; NaN counts as no change
+        let target = (strength.clamp(0.0, 1.0) * total as f32 + 0.5) as usize;
+        if target == 0 {
+            return maze;
+        }
+        let mut rng = rng::from_seed(seed);
+        if target >= total {
+            maze.regenerate_region(0, 0, self.cols, self.rows, &mut rng).unwrap();
+            return maze;
+        }
+
+        // Blocks of up to a third of the shorter side, so a variant changes
+        // in patches rather than everywhere a little
+        let side = (self.cols.min(self.rows) / 3).max(2);
+        let mut covered = vec![false; self.sq.len()];
+        let mut count = 0;
+        while count < target {
+            let width = rng.gen_range(1, side.min(self.cols) + 1);
+            let height = rng.gen_range(1, side.min(self.rows) + 1);
+            let x = rng.gen_range(0, self.cols - width + 1);
+            let y = rng.gen_range(0, self.rows - height + 1);
+            maze.regenerate_region(x, y, width, height, &mut rng).unwrap();
+            for cy in y..y + height {
+                for cx in x..x + width {
+                    let i = self.get_offset(cx, cy);
+                    if plain[i] && !covered[i] {
+                        covered[i] = true;
+                        count += 1;
+                    }
+                }
+            }
+        }
+        gen_debug!("variant strength={} seed={} squares={} of {}", strength, seed, count, total);
+        maze
+    }
+
+    //
+    // Internal - the connected part of the maze each Square belongs to, as
+    // a flattened region table.
+    //
+    pub(crate) fn connected_regions(&self) -> Vec<usize> {
+        let mut region: Vec<usize> = (0..self.sq.len()).collect();
+        for i in 0..self.sq.len() {
+            let c = self.get_coord(i);
+            for n in self.open_neighbors(c.x, c.y) {
+                let j = self.get_offset(n.x, n.y);
+                let (ri, rj) = (find_region(&mut region, i), find_region(&mut region, j));
+                region[ri] = rj;
+            }
+        }
+        for i in 0..region.len() {
+            region[i] = find_region(&mut region, i);
+        }
+        region
+    }
+}
+
+//
+// Internal - whether a Square is an ordinary corridor, whose passages can
+// be torn up and carved again.
+//
+fn is_plain_corridor(sq: &Square) -> bool {
+    sq.id == constants::ID_MAZE_PATH && sq.one_way == 0 && sq.secret == 0 && sq.under == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::test_mazes::{cycles, dungeon};
+
+    fn changed(a: &Maze, b: &Maze) -> f64 {
+        let count = a.sq.iter().zip(b.sq.iter()).filter(|(x, y)| x.open_mask() != y.open_mask()).count();
+        count as f64 / a.sq.len() as f64
+    }
+
+    #[test]
+    fn test_regenerate_region() {
+        let mut maze = Maze::new(10, 10);
+        maze.generate_perfect_seeded(481).unwrap();
+        let before = maze.clone();
+        maze.regenerate_region(3, 2, 5, 6, &mut rng::from_seed(2)).unwrap();
+        assert_eq!(0, cycles(&maze));
+        assert_eq!(1, maze.connected_regions().iter().enumerate().filter(|&(i, &r)| i == r).count());
+        for i in 0..maze.sq.len() {
+            let c = maze.get_coord(i);
+            if c.x < 3 || c.x > 7 || c.y < 2 || c.y > 7 {
+                assert_eq!(before.sq[i], maze.sq[i], "({}, {})", c.x, c.y);
+            }
+        }
+        assert!(maze.walls_consistent());
+
+        assert!(maze.regenerate_region(0, 0, 0, 1, &mut rng::from_seed(1)).is_err());
+        assert_eq!(Err(MazeError::OutOfBounds(10, 9)), maze.regenerate_region(5, 5, 6, 5, &mut rng::from_seed(1)));
+    }
+
+    #[test]
+    fn test_keeps_level() {
+        let maze = dungeon(5, 481);
+        let before = maze.connected_regions();
+        for &strength in [0.2, 0.6, 1.0].iter() {
+            let practice = maze.variant(strength, 7);
+            assert_eq!(maze.get_entrance(), practice.get_entrance());
+            assert_eq!(maze.exits(), practice.exits());
+            assert_eq!(maze.doors(), practice.doors());
+            for (old, new) in maze.sq.iter().zip(practice.sq.iter()) {
+                assert_eq!(old.id, new.id);
+                if old.is_part_of_room() {
+                    assert_eq!(old, new);
+                }
+            }
+            // The same Squares are connected to each other as before
+            let after = practice.connected_regions();
+            for i in 0..before.len() {
+                assert_eq!(after[i], after[before[i]]);
+                assert_eq!(before[i], before[after[i]]);
+            }
+            assert!(practice.solve(Coord::new(0, 0), Coord::new(29, 29)).is_some());
+            assert!(practice.walls_consistent());
+        }
+    }
+
+    #[test]
+    fn test_strength() {
+        let mut maze = Maze::new(30, 30);
+        maze.generate_perfect_seeded(481).unwrap();
+        assert_eq!(maze, maze.variant(0.0, 3));
+        assert_eq!(maze, maze.variant(f32::NAN, 3));
+        assert_eq!(maze.variant(0.5, 3), maze.variant(0.5, 3));
+        assert_ne!(maze.variant(0.5, 3), maze.variant(0.5, 4));
+
+        // More strength changes more, and a full variant changes about as
+        // much as a fresh maze does
+        let mut fresh = Maze::new(30, 30);
+        fresh.generate_perfect_seeded(482).unwrap();
+        let fractions: Vec<f64> = [0.1, 0.3, 0.6, 1.0].iter().map(|&s| changed(&maze, &maze.variant(s, 3))).collect();
+        assert!(fractions.windows(2).all(|w| w[0] < w[1]), "{:?}", fractions);
+        assert!(fractions[0] < 0.15, "{:?}", fractions);
+        assert!(fractions[3] > changed(&maze, &fresh) * 0.8, "{:?}", fractions);
+        for &s in [0.1f32, 0.6, 1.0].iter() {
+            assert_eq!(0, cycles(&maze.variant(s, 5)));
+        }
+    }
+}
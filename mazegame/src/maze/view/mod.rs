@@ -0,0 +1,320 @@
+//! Read-only access to mazes too big to load.  A MazeView reads Squares
+//! straight out of a byte slice in the binary save format, one at a time
+//! as they're asked for, so the slice can come from a memory-mapped file
+//! and the Squares never have to fit in memory at once.  Every field in
+//! the format is little-endian, so the bytes read the same on any machine.
+use alloc::collections::VecDeque;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::constants::{self, Direction};
+use crate::error::MazeError;
+use crate::maze::room::Door;
+use crate::maze::save::{self, CELL_BYTES};
+use crate::maze::square::Square;
+use crate::maze::{step_axis, Coord, Maze, Opening, Topology};
+
+// In a solve, marks a Square that hasn't been reached
+const UNSEEN: u8 = 0xff;
+
+/// A Maze in the binary save format, read in place.
+///
+/// # Example
+/// ```
+/// use mazegame::prelude::*;
+/// use mazegame::maze::view::MazeView;
+///
+/// let mut maze = Maze::new(8, 8);
+/// maze.generate_perfect_seeded(482).unwrap();
+/// let frozen = maze.freeze();
+/// let view = MazeView::new(&frozen).unwrap();
+/// assert_eq!(maze.sq[9], view.get_square(1, 1).unwrap());
+/// assert_eq!(maze.solve(Coord::new(0, 0), Coord::new(7, 7)), view.solve(Coord::new(0, 0), Coord::new(7, 7)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MazeView<'a> {
+    data: &'a [u8],
+    cols: u32,
+    rows: u32,
+    num_rooms: u32,
+    x_topology: Topology,
+    y_topology: Topology,
+    entrance: Option<Opening>,
+    exits: Vec<Opening>,
+    doors: Vec<Door>,
+    cells_at: usize,
+}
+
+impl Maze {
+    /// Returns the Maze in the layout MazeView reads, which is the current
+    /// binary save format: the same bytes as to_bytes().
+    pub fn freeze(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+}
+
+impl<'a> MazeView<'a> {
+    /// Creates a view over data, which must be in the current binary save
+    /// format, like the bytes from Maze::freeze() or a file written by
+    /// to_bytes().  Only the header is read, so this takes the same time
+    /// however big the maze is; call verify() to check the CRC-32 as well.
+    /// Files from older versions need bringing up to date with
+    /// save::migrate() first.
+    pub fn new(data: &'a [u8]) -> Result<MazeView<'a>, MazeError> {
+        if data.len() < 5 || &data[..4] != save::BINARY_MAGIC {
+            return Err(MazeError::Parse("not a binary maze file".to_string()));
+        }
+        if data[4] != save::BINARY_VERSION {
+            return Err(MazeError::Parse(format!("binary maze version {} needs migrating to {} first",
+                                                data[4], save::BINARY_VERSION)));
+        }
+        let body = match data.len().checked_sub(4) {
+            Some(len) => &data[..len],
+            None => return Err(MazeError::Corrupted { what: "data cut short".to_string(), offset: data.len() }),
+        };
+        let header = save::read_header(body)?;
+        let view = MazeView {
+            data,
+            cols: header.cols,
+            rows: header.rows,
+            num_rooms: header.num_rooms,
+            x_topology: header.x_topology,
+            y_topology: header.y_topology,
+            entrance: header.entrance,
+            exits: header.exits,
+            doors: header.doors,
+            cells_at: header.cells_at,
+        };
+        for o in view.entrance.iter().chain(view.exits.iter()) {
+            if !view.in_bounds(o.coord.x, o.coord.y) {
+                return Err(MazeError::OutOfBounds(o.coord.x, o.coord.y));
+            }
+        }
+        Ok(view)
+    }
+
+    /// Checks the data against its CRC-32, reading all of it.
+    pub fn verify(&self) -> Result<(), MazeError> {
+        save::check_crc(self.data).map(|_| ())
+    }
+
+    pub fn get_rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn get_cols(&self) -> u32 {
+        self.cols
+    }
+
+    /// Returns the number of rooms the Maze was saved with.
+    pub fn num_rooms(&self) -> u32 {
+        self.num_rooms
+    }
+
+    /// Returns whether (x, y) is inside the maze.
+    pub fn in_bounds(&self, x: u32, y: u32) -> bool {
+        x < self.cols && y < self.rows
+    }
+
+    /// Returns how each axis treats its edges.
+    pub fn get_topology(&self) -> (Topology, Topology) {
+        (self.x_topology, self.y_topology)
+    }
+
+    /// Returns the recorded entrance, if there is one.
+    pub fn get_entrance(&self) -> Option<Opening> {
+        self.entrance
+    }
+
+    /// Returns the recorded exits, in order.
+    pub fn exits(&self) -> &[Opening] {
+        &self.exits
+    }
+
+    /// Returns the recorded doors into rooms, sorted by row, column and
+    /// direction like Maze::doors().
+    pub fn doors(&self) -> &[Door] {
+        &self.doors
+    }
+
+    /// Decodes the Square at (x, y), or returns None if it's outside the
+    /// maze.
+    pub fn get_square(&self, x: u32, y: u32) -> Option<Square> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        let at = self.cells_at + (y as usize * self.cols as usize + x as usize) * CELL_BYTES;
+        Some(save::read_cell(&self.data[at..at + CELL_BYTES]))
+    }
+
+    /// Returns whether the Square at (x, y) has a wall in direction dir.
+    /// Squares outside the maze are all wall.
+    pub fn is_wall_present(&self, x: u32, y: u32, dir: u32) -> bool {
+        self.get_square(x, y).is_none_or(|sq| sq.is_wall_present(dir))
+    }
+
+    /// Returns the Square next to (x, y) in direction dir, like
+    /// Maze::neighbor().
+    pub fn neighbor(&self, x: u32, y: u32, dir: u32) -> Option<Coord> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        let wrap_x = self.x_topology == Topology::Torus && self.cols > 1;
+        let wrap_y = self.y_topology == Topology::Torus && self.rows > 1;
+        let (dx, dy) = constants::Direction::from_u32(dir)?.delta();
+        Some(Coord::new(step_axis(x, dx, self.cols, wrap_x)?, step_axis(y, dy, self.rows, wrap_y)?))
+    }
+
+    /// Returns the Squares that can be reached in one step from (x, y),
+    /// like Maze::open_neighbors().
+    pub fn open_neighbors(&self, x: u32, y: u32) -> Vec<Coord> {
+        let sq = match self.get_square(x, y) {
+            Some(sq) => sq,
+            None => return Vec::new(),
+        };
+        (0..constants::NUM_DIRECTIONS)
+            .filter(|&dir| !sq.is_wall_present(dir) && !sq.has_under_passage(dir))
+            .filter_map(|dir| self.step_through(x, y, dir))
+            .collect()
+    }
+
+    /// Finds the shortest path between two Squares, like Maze::solve(), and
+    /// gives the same path.  Keeps one byte per Square while it searches.
+    pub fn solve(&self, from: Coord, to: Coord) -> Option<Vec<Coord>> {
+        if !self.in_bounds(from.x, from.y) || !self.in_bounds(to.x, to.y) {
+            return None;
+        }
+        let offset = |c: Coord| c.y as usize * self.cols as usize + c.x as usize;
+
+        // The direction each Square was first reached in
+        let mut came = vec![UNSEEN; self.rows as usize * self.cols as usize];
+        let mut queue = VecDeque::new();
+        came[offset(from)] = constants::NUM_DIRECTIONS as u8;
+        queue.push_back(from);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                let mut path = vec![cur];
+                let mut step = cur;
+                while let Some(side) = Direction::from_u32(came[offset(step)] as u32) {
+                    step = self.step_through(step.x, step.y, side.opposite().into())?;
+                    path.push(step);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let sq = self.get_square(cur.x, cur.y)?;
+            for dir in 0..constants::NUM_DIRECTIONS {
+                if sq.is_wall_present(dir) || sq.has_under_passage(dir) {
+                    continue;
+                }
+                if let Some(next) = self.step_through(cur.x, cur.y, dir) {
+                    if came[offset(next)] == UNSEEN {
+                        came[offset(next)] = dir as u8;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    //
+    // Internal - the Square a step from (x, y) toward dir lands on, passing
+    // under any weave crossings on the way, like Maze::step_through().
+    //
+    fn step_through(&self, x: u32, y: u32, dir: u32) -> Option<Coord> {
+        let mut next = self.neighbor(x, y, dir);
+        let cells = self.rows as usize * self.cols as usize;
+        for _ in 0..cells {
+            match next {
+                Some(c) if self.get_square(c.x, c.y).is_some_and(|sq| sq.has_under_passage(dir)) => {
+                    next = self.neighbor(c.x, c.y, dir);
+                }
+                _ => return next,
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same(maze: &Maze, view: &MazeView) {
+        assert_eq!((maze.get_cols(), maze.get_rows()), (view.get_cols(), view.get_rows()));
+        assert_eq!(maze.get_topology(), view.get_topology());
+        assert_eq!(maze.get_entrance(), view.get_entrance());
+        assert_eq!(maze.exits(), view.exits());
+        assert_eq!(maze.doors(), view.doors());
+        for y in 0..maze.get_rows() + 1 {
+            for x in 0..maze.get_cols() + 1 {
+                let sq = maze.index_of(x, y).map(|i| maze.sq[i].clone());
+                assert_eq!(sq, view.get_square(x, y), "({}, {})", x, y);
+                for dir in 0..constants::NUM_DIRECTIONS + 1 {
+                    assert_eq!(maze.neighbor(x, y, dir), view.neighbor(x, y, dir));
+                    assert_eq!(sq.as_ref().is_none_or(|sq| sq.is_wall_present(dir)), view.is_wall_present(x, y, dir));
+                }
+                assert_eq!(maze.open_neighbors(x, y), view.open_neighbors(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+
+    fn assert_same_solves(maze: &Maze, view: &MazeView, pairs: &[(Coord, Coord)]) {
+        for &(from, to) in pairs.iter() {
+            let path = maze.solve(from, to);
+            assert_eq!(path, view.solve(from, to), "{:?} to {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn test_rooms_and_passages() {
+        let mut maze = Maze::new(20, 24);
+        maze.generate_seeded((5, 2, 4, 2, 4), 482).unwrap();
+        maze.connect_regions_with_rng(&mut crate::rng::from_seed(482));
+        maze.set_entrance(0, 0, constants::DIR_NORTH).unwrap();
+        maze.set_exit(23, 19, constants::DIR_SOUTH).unwrap();
+        maze.carve_one_way(3, 4, constants::DIR_EAST).unwrap();
+        maze.carve_secret(10, 10, constants::DIR_SOUTH).unwrap();
+        let frozen = maze.freeze();
+        let view = MazeView::new(&frozen).unwrap();
+        assert!(view.verify().is_ok());
+        assert_eq!(maze.num_rooms, view.num_rooms());
+        assert_same(&maze, &view);
+        assert_same_solves(&maze, &view, &[(Coord::new(0, 0), Coord::new(23, 19)), (Coord::new(4, 4), Coord::new(3, 4)),
+                                           (Coord::new(5, 18), Coord::new(12, 1)), (Coord::new(0, 0), Coord::new(24, 0))]);
+    }
+
+    #[test]
+    fn test_woven_torus() {
+        let mut maze = Maze::new(15, 15);
+        maze.set_topology(Topology::Torus, Topology::Torus);
+        maze.generate_perfect_woven_seeded(482).unwrap();
+        assert!(maze.sq.iter().any(|sq| sq.under != 0));
+        let frozen = maze.freeze();
+        let view = MazeView::new(&frozen).unwrap();
+        assert_same(&maze, &view);
+        let pairs: Vec<(Coord, Coord)> = (0..15).map(|i| (Coord::new(i, 0), Coord::new(14 - i, 14))).collect();
+        assert_same_solves(&maze, &view, &pairs);
+    }
+
+    #[test]
+    fn test_bad_data() {
+        let mut maze = Maze::new(6, 6);
+        maze.generate_perfect_seeded(482).unwrap();
+        let frozen = maze.freeze();
+        assert!(MazeView::new(&frozen[..frozen.len() - 10]).is_err());
+        assert!(MazeView::new(b"MZGM").is_err());
+        assert!(MazeView::new(b"JUNKJUNKJUNK").is_err());
+        let mut old = frozen.clone();
+        old[4] = 4;
+        assert!(MazeView::new(&old).is_err());
+
+        // The CRC-32 is only read when asked for
+        let mut flipped = frozen.clone();
+        let last = flipped.len() - 5;
+        flipped[last] ^= 1;
+        let view = MazeView::new(&flipped).unwrap();
+        assert!(matches!(view.verify(), Err(MazeError::Corrupted { .. })));
+    }
+}
@@ -0,0 +1,233 @@
+//! Weave mazes, where passages can cross over one another.  A passage that
+//! tunnels under a Square knocks down the Square's walls along its axis and
+//! sets the matching bit of `Square::under`; the Square's own corridor runs
+//! across it on the other axis.  Moving along the tunnel skips straight from
+//! one end to the other, so there's no way to turn at a crossing.
+use alloc::string::String;
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::constants::{self, Direction};
+use crate::maze::generator::{self, Carvable};
+use crate::maze::info::GenerationAlgorithm;
+use crate::maze::{Coord, Maze};
+use crate::rng;
+
+impl Maze {
+    /// Tunnels from (x, y) in direction dir, under the next Square and into
+    /// the one beyond it.  The Square being tunneled under can't already
+    /// have a passage along that axis.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    ///
+    /// let mut maze = Maze::new(1, 3);
+    /// maze.carve_under(0, 0, DIR_EAST).unwrap();
+    /// assert_eq!(vec![Coord::new(2, 0)], maze.open_neighbors(0, 0));
+    /// assert!(maze.sq[1].has_under_passage(DIR_EAST));
+    /// ```
+    pub fn carve_under(&mut self, x: u32, y: u32, dir: u32) -> Result<(), String> {
+        let ((over, far), back) = match (self.tunnel_ends(x, y, dir), Direction::from_u32(dir)) {
+            (Some(ends), Some(side)) => (ends, u32::from(side.opposite())),
+            _ => return Err(format!("Can't tunnel {} from ({}, {})", crate::trace::dir_name(dir), x, y)),
+        };
+
+        let offset = self.get_offset(x, y);
+        self.sq[offset].break_wall(dir);
+        self.sq[offset].id = constants::ID_MAZE_PATH;
+        let offset = self.get_offset(over.x, over.y);
+        self.sq[offset].break_wall(dir);
+        self.sq[offset].break_wall(back);
+        self.sq[offset].add_under_passage(dir);
+        let offset = self.get_offset(far.x, far.y);
+        self.sq[offset].break_wall(back);
+        self.sq[offset].id = constants::ID_MAZE_PATH;
+        self.mark_changed();
+
+        gen_trace!("carve_under x={} y={} dir={}", x, y, crate::trace::dir_name(dir));
+        Ok(())
+    }
+
+    /// Generates a perfect weave maze from a seed.
+    ///
+    /// # Example
+    /// ```
+    /// let mut maze = mazegame::Maze::new(10, 10);
+    /// maze.generate_perfect_woven_seeded(5).unwrap();
+    /// assert!(maze.sq.iter().any(|sq| sq.under != 0));
+    /// ```
+    pub fn generate_perfect_woven_seeded(&mut self, seed: u64) -> Result<(), String> {
+        self.generate_perfect_woven_with_rng(&mut rng::from_seed(seed))?;
+        self.record_generation(seed, GenerationAlgorithm::Woven, None);
+        Ok(())
+    }
+
+    /// Generates a perfect maze using the supplied random number generator,
+    /// letting the carver tunnel under straight corridors it runs into
+    /// instead of turning away from them.
+    pub fn generate_perfect_woven_with_rng<R: Rng>(&mut self, rng: &mut R) -> Result<(), String> {
+        let start = self.get_start();
+        let stats = generator::growing_tree(&mut Weaver(self), start, rng)?;
+        gen_debug!("growing_tree x={} y={} carved={} backtracks={} weave=true", start.x, start.y, stats.carved,
+                   stats.backtracks);
+        Ok(())
+    }
+
+    //
+    // Internal - follows a step from (x, y) in direction dir through any
+    // tunnels, returning the Square where it comes back up.
+    //
+    pub(crate) fn step_through(&self, x: u32, y: u32, dir: u32) -> Option<Coord> {
+        let mut next = self.neighbor(x, y, dir);
+        // A torus row made entirely of crossings would otherwise never end
+        for _ in 0..self.sq.len() {
+            match next {
+                Some(c) if self.sq[self.get_offset(c.x, c.y)].has_under_passage(dir) => {
+                    next = self.neighbor(c.x, c.y, dir);
+                }
+                _ => return next,
+            }
+        }
+        None
+    }
+
+    //
+    // Internal - returns the Square a tunnel from (x, y) in direction dir
+    // would pass under and the Square it would come up in, if it can be dug.
+    //
+    fn tunnel_ends(&self, x: u32, y: u32, dir: u32) -> Option<(Coord, Coord)> {
+        let back = u32::from(Direction::from_u32(dir)?.opposite());
+        let over = self.neighbor(x, y, dir)?;
+        let far = self.neighbor(over.x, over.y, dir)?;
+        let sq = &self.sq[self.get_offset(over.x, over.y)];
+        if far == Coord::new(x, y) || sq.under != 0 || sq.is_part_of_room() ||
+           !sq.is_wall_present(dir) || !sq.is_wall_present(back) {
+            return None;
+        }
+        Some((over, far))
+    }
+}
+
+//
+// Internal - a Maze being carved with weaving allowed.  Tunnels are offered
+// under straight corridors that cross the carver's path.
+//
+struct Weaver<'a>(&'a mut Maze);
+
+impl<'a> Weaver<'a> {
+    //
+    // Internal - whether the generator may tunnel from cell in direction dir:
+    // the next Square must be a straight corridor across the tunnel, and the
+    // Square beyond it must still be uncarved.
+    //
+    fn can_weave(&self, cell: Coord, dir: u32) -> bool {
+        let maze = &*self.0;
+        match maze.tunnel_ends(cell.x, cell.y, dir) {
+            Some((over, far)) => {
+                let side = if dir == constants::DIR_NORTH || dir == constants::DIR_SOUTH {
+                    Direction::East
+                } else {
+                    Direction::North
+                };
+                let sq = &maze.sq[maze.get_offset(over.x, over.y)];
+                !sq.is_wall_present(side.into()) && !sq.is_wall_present(side.opposite().into()) &&
+                    !maze.sq[maze.get_offset(far.x, far.y)].is_carved()
+            }
+            None => false,
+        }
+    }
+}
+
+impl<'a> Carvable for Weaver<'a> {
+    type Cell = Coord;
+
+    fn uncarved_directions(&self, cell: Coord) -> Vec<u32> {
+        let mut directions = self.0.uncarved_directions(cell);
+        directions.extend((0..constants::NUM_DIRECTIONS).filter(|&dir| self.can_weave(cell, dir)));
+        directions
+    }
+
+    fn carve_toward(&mut self, cell: Coord, dir: u32) -> Result<Coord, String> {
+        if self.can_weave(cell, dir) {
+            self.0.carve_under(cell.x, cell.y, dir)?;
+            self.0.step_through(cell.x, cell.y, dir).ok_or_else(|| format!("Lost tunnel from ({}, {})", cell.x, cell.y))
+        } else {
+            self.0.carve_toward(cell, dir)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::assert_matches_fixture;
+
+    // A 3x3 maze with a corridor down the middle column, crossed by a tunnel
+    // along the middle row.
+    fn crossing() -> Maze {
+        let mut maze = Maze::new(3, 3);
+        maze.carve(1, 0, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, constants::DIR_SOUTH, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_under(0, 1, constants::DIR_EAST).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_crossing_walls() {
+        let maze = crossing();
+        assert_eq!([false, false, false, false], maze.sq[4].wall_present);
+        assert!(maze.sq[4].has_under_passage(constants::DIR_EAST));
+        assert!(!maze.sq[4].has_under_passage(constants::DIR_NORTH));
+        assert!(maze.walls_consistent());
+
+        // The crossing already has an east-west passage, and can't take another
+        let mut maze = maze;
+        assert!(maze.carve_under(0, 1, constants::DIR_EAST).is_err());
+        assert!(maze.carve_under(1, 0, constants::DIR_SOUTH).is_err());
+    }
+
+    #[test]
+    fn test_solver_cannot_turn() {
+        let maze = crossing();
+        assert_eq!(vec![Coord::new(1, 0), Coord::new(1, 2)], maze.open_neighbors(1, 1));
+        assert_eq!(vec![Coord::new(2, 1)], maze.open_neighbors(0, 1));
+        assert_eq!(Some(vec![Coord::new(0, 1), Coord::new(2, 1)]), maze.solve(Coord::new(0, 1), Coord::new(2, 1)));
+        assert_eq!(None, maze.solve(Coord::new(0, 1), Coord::new(1, 0)));
+        assert_eq!(None, maze.solve(Coord::new(0, 1), Coord::new(1, 1)));
+    }
+
+    #[test]
+    fn test_render_crossings() {
+        let maze = crossing();
+        assert_matches_fixture("weave_crossing_ns.txt", &maze.render_ascii());
+
+        // Turned on its side, the east-west corridor is on top
+        let mut maze = Maze::new(3, 3);
+        maze.carve(0, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve(1, 1, constants::DIR_EAST, constants::ID_MAZE_PATH, false).unwrap();
+        maze.carve_under(1, 0, constants::DIR_SOUTH).unwrap();
+        assert_matches_fixture("weave_crossing_ew.txt", &maze.render_ascii());
+    }
+
+    #[test]
+    fn test_generate_woven() {
+        let mut maze = Maze::new(12, 12);
+        maze.generate_perfect_woven_seeded(411).unwrap();
+        assert!(maze.sq.iter().any(|sq| sq.under != 0));
+        assert!(maze.walls_consistent());
+
+        // Still a perfect maze: every Square is reachable, with one fewer
+        // passage than Squares
+        let passages: usize = (0..12).flat_map(|y| (0..12).map(move |x| (x, y)))
+                                     .map(|(x, y)| maze.open_neighbors(x, y).len()).sum();
+        assert_eq!(12 * 12 - 1, passages / 2);
+
+        // The crossings are saved along with the walls
+        assert_eq!(maze, Maze::from_text(&maze.to_text()).unwrap());
+        assert_eq!(maze, Maze::from_bytes(&maze.to_bytes()).unwrap());
+        for (x, y) in (0..12).flat_map(|y| (0..12).map(move |x| (x, y))) {
+            assert!(maze.solve(Coord::new(0, 0), Coord::new(x, y)).is_some());
+        }
+    }
+}
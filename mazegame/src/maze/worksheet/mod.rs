@@ -0,0 +1,453 @@
+//! Printable worksheets: a puzzle page with the entrance and exit marked,
+//! and an answer page with the solution drawn over the same maze.  Both
+//! pages are drawn from the same shapes at the same size, so printing the
+//! answer on tracing paper lines it up with the puzzle exactly.
+//!
+//! Pages come out as SVG, or as PNG when the `image` feature is on.  The
+//! SVG title is real text; the PNG title is drawn with a small built in
+//! capitals-only font, which is why lowercase letters come out as capitals.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::constants;
+use crate::error::MazeError;
+use crate::maze::{Coord, Maze};
+
+/// The colour of the walls and title.
+pub const WALL_COLOR: [u8; 3] = [0, 0, 0];
+/// The colour of the page.
+pub const PAPER_COLOR: [u8; 3] = [255, 255, 255];
+/// The colour of the entrance marker.
+pub const ENTRANCE_COLOR: [u8; 3] = [0, 160, 0];
+/// The colour of the exit marker.
+pub const EXIT_COLOR: [u8; 3] = [200, 0, 0];
+/// The colour of the solution on the answer page.  Nothing on the puzzle
+/// page uses it.
+pub const PATH_COLOR: [u8; 3] = [40, 90, 230];
+
+/// The most pixels render_worksheet() will draw on a page, so a huge cell
+/// size can't make it allocate gigabytes.  A 200x200 maze with 64 pixel
+/// Squares fits.
+pub const MAX_WORKSHEET_PIXELS: u32 = 1 << 28;
+
+/// The file format worksheet pages are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WorksheetFormat {
+    #[default]
+    Svg,
+    #[cfg(feature = "image")]
+    Png,
+}
+
+/// How render_worksheet() lays out the pages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorksheetConfig {
+    /// Text across the top of both pages.  An empty title leaves no room
+    /// for one.
+    pub title: String,
+    /// How many pixels across each Square is.
+    pub cell_size: u32,
+    /// How many pixels thick the walls are.  The solution is drawn at the
+    /// same thickness.
+    pub line_thickness: u32,
+    pub format: WorksheetFormat,
+}
+
+impl Default for WorksheetConfig {
+    fn default() -> WorksheetConfig {
+        WorksheetConfig { title: String::new(), cell_size: 20, line_thickness: 2, format: WorksheetFormat::Svg }
+    }
+}
+
+/// The two pages of a worksheet, as the bytes of image files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Worksheet {
+    pub puzzle: Vec<u8>,
+    pub answer: Vec<u8>,
+    /// The size of both pages, in pixels.
+    pub width: u32,
+    pub height: u32,
+}
+
+//
+// Internal - a filled rectangle, in pixels.
+//
+#[derive(Clone, Copy)]
+struct Block {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    color: [u8; 3],
+}
+
+//
+// Internal - everything drawn on a page.  The answer page is the puzzle
+// page with the path blocks on top.
+//
+struct Page {
+    width: u32,
+    height: u32,
+    scale: u32,
+    title: String,
+    blocks: Vec<Block>,
+    path: Vec<Block>,
+}
+
+impl Maze {
+    /// Draws the Maze as a puzzle page and an answer page, with the
+    /// solution from the entrance to the exit on the answer page.  Fails if
+    /// the Maze is missing either or they aren't connected, or if the
+    /// walls wouldn't fit inside the Squares.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::prelude::*;
+    /// use mazegame::maze::worksheet::WorksheetConfig;
+    ///
+    /// let mut maze = Maze::new(10, 10);
+    /// maze.generate_perfect_seeded(1).unwrap();
+    /// maze.place_entrance_exit_longest().unwrap();
+    /// let config = WorksheetConfig { title: "Maze 1".to_string(), ..Default::default() };
+    /// let sheet = maze.render_worksheet(&config).unwrap();
+    /// assert!(sheet.puzzle.starts_with(b"<svg"));
+    /// assert!(sheet.answer.len() > sheet.puzzle.len());
+    /// ```
+    pub fn render_worksheet(&self, opts: &WorksheetConfig) -> Result<Worksheet, MazeError> {
+        let page = self.worksheet_page(opts)?;
+        let (puzzle, answer) = match opts.format {
+            WorksheetFormat::Svg => (page.to_svg(false), page.to_svg(true)),
+            #[cfg(feature = "image")]
+            WorksheetFormat::Png => (page.to_png(false)?, page.to_png(true)?),
+        };
+        Ok(Worksheet { puzzle, answer, width: page.width, height: page.height })
+    }
+
+    //
+    // Internal - lays out the walls, markers and solution of a worksheet.
+    //
+    fn worksheet_page(&self, opts: &WorksheetConfig) -> Result<Page, MazeError> {
+        let (cell, thickness) = (opts.cell_size, opts.line_thickness);
+        if thickness == 0 || thickness > cell / 2 {
+            return Err(MazeError::InvalidArgument(
+                format!("lines {} pixels thick don't fit in {} pixel Squares", thickness, cell)));
+        }
+        let (entrance, exit) = match (self.entrance, self.get_exit()) {
+            (Some(entrance), Some(exit)) => (entrance.coord, exit.coord),
+            _ => return Err(MazeError::InvalidArgument("a worksheet needs an entrance and an exit".to_string())),
+        };
+        let path = self.solve(entrance, exit).ok_or(MazeError::Unreachable(entrance, exit))?;
+
+        // A Square's worth of margin all round, and a band for the title
+        let scale = (cell / 8).max(1);
+        let band = if opts.title.is_empty() { 0 } else { 11 * scale };
+        let (left, top) = (cell, cell + band);
+        let size = |squares: u32, extra: u32| {
+            squares.checked_add(2).and_then(|n| n.checked_mul(cell)).and_then(|pixels| pixels.checked_add(extra))
+        };
+        let (width, height) = match (size(self.cols, 0), size(self.rows, band)) {
+            (Some(width), Some(height)) if width.checked_mul(height).is_some_and(|n| n <= MAX_WORKSHEET_PIXELS) => {
+                (width, height)
+            }
+            _ => return Err(MazeError::InvalidArgument(
+                format!("a worksheet with {} pixel Squares is too big to draw", cell))),
+        };
+
+        let mut blocks = Vec::new();
+        let half = thickness / 2;
+        // The wall from grid point (x1, y1) to (x2, y2), with square ends
+        let wall = |x1: u32, y1: u32, x2: u32, y2: u32| Block {
+            x: left + x1 * cell - half,
+            y: top + y1 * cell - half,
+            w: (x2 - x1) * cell + thickness,
+            h: (y2 - y1) * cell + thickness,
+            color: WALL_COLOR,
+        };
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let sq = &self.sq[self.get_offset(x, y)];
+                // As in render_svg(), each Square draws its north and west
+                // walls, and the last row and column the outer ones
+                if sq.is_wall_drawn(constants::DIR_NORTH) {
+                    blocks.push(wall(x, y, x + 1, y));
+                }
+                if sq.is_wall_drawn(constants::DIR_WEST) {
+                    blocks.push(wall(x, y, x, y + 1));
+                }
+                if y == self.rows - 1 && sq.is_wall_drawn(constants::DIR_SOUTH) {
+                    blocks.push(wall(x, y + 1, x + 1, y + 1));
+                }
+                if x == self.cols - 1 && sq.is_wall_drawn(constants::DIR_EAST) {
+                    blocks.push(wall(x + 1, y, x + 1, y + 1));
+                }
+            }
+        }
+        let inset = cell / 4;
+        let marker = |c: Coord, color: [u8; 3]| Block {
+            x: left + c.x * cell + inset,
+            y: top + c.y * cell + inset,
+            w: cell - inset * 2,
+            h: cell - inset * 2,
+            color,
+        };
+        blocks.push(marker(entrance, ENTRANCE_COLOR));
+        blocks.push(marker(exit, EXIT_COLOR));
+
+        // The solution joins the middles of the Squares it passes through
+        let middle = |c: Coord| (left + c.x * cell + cell / 2 - half, top + c.y * cell + cell / 2 - half);
+        let path = path.windows(2)
+            .map(|step| {
+                let ((x1, y1), (x2, y2)) = (middle(step[0]), middle(step[1]));
+                let (x, y) = (x1.min(x2), y1.min(y2));
+                Block { x, y, w: x1.max(x2) - x + thickness, h: y1.max(y2) - y + thickness, color: PATH_COLOR }
+            })
+            .collect();
+        Ok(Page { width, height, scale, title: opts.title.clone(), blocks, path })
+    }
+}
+
+impl Page {
+    //
+    // Internal - writes the page as SVG, with the solution if answer is set.
+    //
+    fn to_svg(&self, answer: bool) -> Vec<u8> {
+        let fill = |color: [u8; 3]| format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]);
+        let rect = |out: &mut String, b: &Block| {
+            out.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                                  b.x, b.y, b.w, b.h, fill(b.color)));
+        };
+        let mut out = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                               viewBox=\"0 0 {} {}\">\n", self.width, self.height, self.width, self.height);
+        out.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n", self.width, self.height,
+                              fill(PAPER_COLOR)));
+        if !self.title.is_empty() {
+            out.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-family=\"sans-serif\" \
+                                   font-size=\"{}\" fill=\"{}\">{}</text>\n", self.width / 2, 9 * self.scale,
+                                  7 * self.scale, fill(WALL_COLOR), escape_xml(&self.title)));
+        }
+        for b in self.blocks.iter() {
+            rect(&mut out, b);
+        }
+        if answer {
+            for b in self.path.iter() {
+                rect(&mut out, b);
+            }
+        }
+        out.push_str("</svg>\n");
+        out.into_bytes()
+    }
+
+    //
+    // Internal - writes the page as a PNG, with the solution if answer is
+    // set.
+    //
+    #[cfg(feature = "image")]
+    fn to_png(&self, answer: bool) -> Result<Vec<u8>, MazeError> {
+        use ::image::codecs::png::PngEncoder;
+        use ::image::{ColorType, ImageEncoder};
+
+        let mut pixels = PAPER_COLOR.repeat(self.width as usize * self.height as usize);
+        let mut fill = |b: &Block| {
+            for y in b.y..(b.y + b.h).min(self.height) {
+                for x in b.x..(b.x + b.w).min(self.width) {
+                    let i = (y as usize * self.width as usize + x as usize) * 3;
+                    pixels[i..i + 3].copy_from_slice(&b.color);
+                }
+            }
+        };
+
+        // The title is centred in its band, a font pixel per scale pixels
+        let title: Vec<[u8; 7]> = self.title.chars().map(glyph).collect();
+        let advance = 6 * self.scale;
+        let text_width = (title.len() as u32 * advance).saturating_sub(self.scale);
+        let x0 = (self.width.saturating_sub(text_width)) / 2;
+        for (i, rows) in title.iter().enumerate() {
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..5 {
+                    if bits & (0x10 >> col) != 0 {
+                        fill(&Block { x: x0 + i as u32 * advance + col * self.scale,
+                                      y: (2 + row as u32) * self.scale, w: self.scale, h: self.scale,
+                                      color: WALL_COLOR });
+                    }
+                }
+            }
+        }
+        for b in self.blocks.iter() {
+            fill(b);
+        }
+        if answer {
+            for b in self.path.iter() {
+                fill(b);
+            }
+        }
+
+        let mut out = Vec::new();
+        PngEncoder::new(&mut out).write_image(&pixels, self.width, self.height, ColorType::Rgb8)
+            .map_err(|e| MazeError::InvalidArgument(e.to_string()))?;
+        Ok(out)
+    }
+}
+
+//
+// Internal - escapes text for use inside an SVG element.
+//
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+//
+// Internal - the 5x7 font the PNG title is drawn with.  Each row is five
+// bits, the leftmost column in bit 4.
+//
+#[cfg(feature = "image")]
+const FONT: &[(char, [u8; 7])] = &[
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    ('A', [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('B', [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e]),
+    ('C', [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e]),
+    ('D', [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c]),
+    ('E', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f]),
+    ('F', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10]),
+    ('G', [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f]),
+    ('H', [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('I', [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f]),
+    ('M', [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('P', [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10]),
+    ('Q', [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d]),
+    ('R', [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11]),
+    ('S', [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e]),
+    ('T', [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a]),
+    ('X', [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04]),
+    ('Z', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f]),
+    ('-', [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08]),
+    (':', [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('?', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('\'', [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+];
+
+//
+// Internal - the rows of c in FONT, blank for characters it doesn't have.
+//
+#[cfg(feature = "image")]
+fn glyph(c: char) -> [u8; 7] {
+    let c = c.to_ascii_uppercase();
+    FONT.iter().find(|&&(f, _)| f == c).map_or([0; 7], |&(_, rows)| rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Maze {
+        let mut maze = Maze::new(12, 9);
+        maze.generate_perfect_seeded(492).unwrap();
+        maze.place_entrance_exit_longest().unwrap();
+        maze
+    }
+
+    // The width and height attributes of an SVG page
+    fn svg_size(svg: &str) -> (u32, u32) {
+        let attr = |name: &str| {
+            let start = svg.find(&format!("{}=\"", name)).unwrap() + name.len() + 2;
+            svg[start..].split('"').next().unwrap().parse().unwrap()
+        };
+        (attr("width"), attr("height"))
+    }
+
+    #[test]
+    fn test_svg() {
+        let maze = sample();
+        let config = WorksheetConfig { title: "Tom & Jerry's <maze>".to_string(), ..Default::default() };
+        let sheet = maze.render_worksheet(&config).unwrap();
+        let (puzzle, answer) = (String::from_utf8(sheet.puzzle).unwrap(), String::from_utf8(sheet.answer).unwrap());
+        assert_eq!((sheet.width, sheet.height), svg_size(&puzzle));
+        assert_eq!(svg_size(&puzzle), svg_size(&answer));
+        assert_eq!((9 * 20 + 40, 12 * 20 + 40 + 22), (sheet.width, sheet.height));
+        assert!(puzzle.contains(">Tom &amp; Jerry's &lt;maze&gt;</text>"));
+
+        // The answer is the puzzle with the path's rectangles added
+        let path_fill = "fill=\"#285ae6\"";
+        assert!(!puzzle.contains(path_fill));
+        let path = maze.solve(maze.get_entrance().unwrap().coord, maze.get_exit().unwrap().coord).unwrap();
+        assert_eq!(path.len() - 1, answer.matches(path_fill).count());
+        let stripped: String = answer.lines()
+            .filter(|l| !l.contains(path_fill))
+            .map(|l| l.to_string() + "\n")
+            .collect();
+        assert_eq!(puzzle, stripped);
+    }
+
+    #[test]
+    fn test_bad_worksheets() {
+        let mut maze = sample();
+        assert!(maze.render_worksheet(&WorksheetConfig { line_thickness: 0, ..Default::default() }).is_err());
+        assert!(maze.render_worksheet(&WorksheetConfig { cell_size: 3, ..Default::default() }).is_err());
+        assert!(maze.render_worksheet(&WorksheetConfig { line_thickness: 3_000_000_000, ..Default::default() })
+                    .is_err());
+        assert!(maze.render_worksheet(&WorksheetConfig { cell_size: 100_000, ..Default::default() }).is_err());
+        assert!(maze.render_worksheet(&WorksheetConfig { cell_size: u32::MAX, ..Default::default() }).is_err());
+        maze.exits.clear();
+        assert!(maze.render_worksheet(&WorksheetConfig::default()).is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_png() {
+        let maze = sample();
+        let config = WorksheetConfig { title: "Worksheet 7".to_string(), cell_size: 16, line_thickness: 3,
+                                       format: WorksheetFormat::Png };
+        let sheet = maze.render_worksheet(&config).unwrap();
+        let puzzle = ::image::load_from_memory(&sheet.puzzle).unwrap().to_rgb8();
+        let answer = ::image::load_from_memory(&sheet.answer).unwrap().to_rgb8();
+        assert_eq!((sheet.width, sheet.height), puzzle.dimensions());
+        assert_eq!(puzzle.dimensions(), answer.dimensions());
+
+        // The SVG pages are the same size
+        let svg = maze.render_worksheet(&WorksheetConfig { format: WorksheetFormat::Svg, ..config }).unwrap();
+        assert_eq!((svg.width, svg.height), (sheet.width, sheet.height));
+
+        // Only the path's pixels differ, and the puzzle has none of them
+        let mut changed = 0;
+        for (p, a) in puzzle.pixels().zip(answer.pixels()) {
+            assert_ne!(PATH_COLOR, p.0);
+            if p != a {
+                assert_eq!(PATH_COLOR, a.0);
+                changed += 1;
+            }
+        }
+        assert!(changed > 0);
+        // The title was drawn
+        assert!(puzzle.pixels().take((sheet.width * 11 * 2) as usize).any(|p| p.0 == WALL_COLOR));
+    }
+}
@@ -0,0 +1,303 @@
+//! The text shown to players and command line users, looked up by stable
+//! keys so it can be translated.  English is compiled in; a catalog loaded
+//! from a file only has to override the messages it translates, and any
+//! key it leaves out falls back to English.
+//!
+//! Catalog files have one `key = value` pair per line.  Blank lines and
+//! lines starting with `#` are skipped, and `\n` and `\\` in a value stand
+//! for a newline and a backslash:
+//!
+//! ```text
+//! # Pirate
+//! cli.path_length = Ye walked {0} squares
+//! error.general = mazegame be sinkin': {0}
+//! ```
+//!
+//! Messages take positional arguments, written `{0}`, `{1}` and so on.
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::error::MazeError;
+
+/// Every message, with its English text.
+pub const ENGLISH: &[(&str, &str)] = &[
+    ("usage.rooms", "usage: mazegame <width> <height> <num_rooms> <room_min_w> <room_min_h> <room_max_w> <room_max_h>"),
+    ("usage.perfect", "       mazegame <width> <height>"),
+    ("usage.spec", "       mazegame --spec <file> [--output <file>]"),
+    ("usage.solve", "       mazegame solve <file> [--from x,y --to x,y] [--format ascii|json]"),
+    ("usage.sweep", "       mazegame sweep --braid|--rooms|--size <values> [--spec <file>] [--samples n] [--seed n] [--csv]"),
+    ("usage.verify", "       mazegame verify --seed <n> [--size WxH] [--rooms <n>]"),
+    ("usage.window", "       mazegame --window WxH --cell <px> --seed <n> [--hud <px>]"),
+    ("usage.bench", "       mazegame bench [--sizes WxH,...] [--reps <n>] [--csv]"),
+    ("usage.worksheet", "       mazegame worksheet --seed <n> --out <dir> [--size WxH] [--title <text>] \
+                         [--cell-size <n>] [--line <n>]"),
+    ("usage.capabilities", "       mazegame --capabilities"),
+    ("usage.frontend", "       the first two can start with --frontend sdl|terminal to pick where to play"),
+    ("usage.lang", "       any of these can start with --lang <file> to load translated messages"),
+    ("error.general", "mazegame: {0}"),
+    ("error.solve", "mazegame solve: {0}"),
+    ("error.sweep", "mazegame sweep: {0}"),
+    ("error.verify", "mazegame verify: {0}"),
+    ("error.bench", "mazegame bench: {0}"),
+    ("error.worksheet", "mazegame worksheet: {0}"),
+    ("error.not_a_number", "'{0}' isn't a number"),
+    ("error.too_large", "'{0}' is too large"),
+    ("cli.path_length", "Path length: {0}"),
+    ("cli.saved", "Saved a {0}x{1} maze to {2}"),
+    ("cli.unknown_format", "unknown output format '{0}'"),
+    ("cli.unknown_option", "unknown option '{0}'"),
+    ("cli.unexpected_argument", "unexpected argument '{0}'"),
+    ("cli.no_maze_file", "no maze file given"),
+    ("cli.no_spec_file", "no spec file given"),
+    ("cli.no_entrance", "the maze has no entrance; use --from x,y"),
+    ("cli.no_exit", "the maze has no exit; use --to x,y"),
+    ("cli.needs_value", "{0} needs a value"),
+    ("cli.bad_coord", "'{0}' isn't a coordinate of the form x,y"),
+    ("cli.bad_values", "'{0}' isn't a list of values or a from:to:step range"),
+    ("cli.bad_size", "'{0}' isn't a size of the form WxH"),
+    ("cli.no_seed", "no seed given; use --seed n"),
+    ("cli.no_window", "no window size given; use --window WxH"),
+    ("cli.no_output_dir", "no output directory given; use --out <dir>"),
+    ("cli.wrote", "Wrote {0}"),
+    ("cli.sweep_no_setting", "nothing to sweep; use --braid, --rooms or --size"),
+    ("cli.sweep_two_settings", "only one setting can be swept at a time"),
+    ("sweep.samples", "samples"),
+    ("sweep.solution_length", "solution length"),
+    ("sweep.dead_ends", "dead ends"),
+    ("sweep.junctions", "junctions"),
+    ("sweep.carved_squares", "carved squares"),
+    ("verify.maze", "Seed {0}: a {1}x{2} maze with {3} rooms"),
+    ("verify.maze_one_room", "Seed {0}: a {1}x{2} maze with 1 room"),
+    ("verify.openings", "Entrance {0}, exit {1}"),
+    ("verify.solution", "Solution: {0} Squares"),
+    ("verify.stats", "Dead ends: {0}, junctions: {1}, carved Squares: {2}"),
+    ("verify.saved", "Saved size: {0} bytes"),
+    ("verify.passed", "Passed: {0}"),
+    ("window.maze", "Seed {0}: {1}x{2} Squares of {3}px fill {4}x{5} pixels"),
+    ("capabilities.feature", "{0}: {1}"),
+    ("capabilities.yes", "yes"),
+    ("capabilities.no", "no"),
+    ("capabilities.frontend", "Front end: {0}"),
+    ("capabilities.no_frontend", "none"),
+    ("bench.operation", "operation"),
+    ("bench.size", "size"),
+    ("bench.min", "min"),
+    ("bench.median", "median"),
+    ("bench.max", "max"),
+    ("tutorial.welcome", "Welcome!  Find your way to the exit at the bottom right."),
+    ("tutorial.dead_end", "This is a dead end \u{2014} backtrack and try another way."),
+    ("tutorial.backtracked", "Lost?  Keeping one hand on a wall always gets you out."),
+    ("tutorial.room", "Rooms may contain items."),
+    ("tutorial.exit_near", "The exit is just ahead."),
+    ("tutorial.dismiss", "[Enter] continue"),
+];
+
+/// A set of messages, each falling back to English if it isn't overridden.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MessageCatalog {
+    overrides: BTreeMap<String, String>,
+}
+
+impl MessageCatalog {
+    /// Creates a catalog with only the English messages.
+    pub fn english() -> MessageCatalog {
+        MessageCatalog::default()
+    }
+
+    /// Reads a catalog from key = value text, in the format described in the
+    /// module docs.  Keys that aren't in ENGLISH are kept, so a catalog can
+    /// be written ahead of the messages it translates.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::messages::MessageCatalog;
+    ///
+    /// let catalog = MessageCatalog::parse("# German\ncli.path_length = Weglänge: {0}\n").unwrap();
+    /// assert_eq!("Weglänge: 12", catalog.format("cli.path_length", &["12"]));
+    /// assert_eq!("no maze file given", catalog.get("cli.no_maze_file"));
+    /// assert!(MessageCatalog::parse("cli.path_length").is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<MessageCatalog, MazeError> {
+        let mut overrides = BTreeMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => return Err(MazeError::Parse(format!("line {}: expected key = value", number + 1))),
+            };
+            if key.is_empty() {
+                return Err(MazeError::Parse(format!("line {}: missing key", number + 1)));
+            }
+            overrides.insert(key.to_string(), unescape(value));
+        }
+        Ok(MessageCatalog { overrides })
+    }
+
+    /// Loads a catalog from the file at path.
+    #[cfg(feature = "std")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<MessageCatalog, MazeError> {
+        MessageCatalog::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Returns the message for key, in English if this catalog doesn't
+    /// have it.  A key with no English text either is returned as is, so
+    /// a missing message shows up instead of vanishing.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        match self.overrides.get(key) {
+            Some(message) => message,
+            None => english(key).unwrap_or(key),
+        }
+    }
+
+    /// Returns the message for key with `{0}`, `{1}` and so on replaced by
+    /// the matching entries of args.  Placeholders without an argument are
+    /// left in.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::messages::MessageCatalog;
+    ///
+    /// let catalog = MessageCatalog::english();
+    /// assert_eq!("Saved a 8x6 maze to out.txt", catalog.format("cli.saved", &["8", "6", "out.txt"]));
+    /// ```
+    pub fn format(&self, key: &str, args: &[&str]) -> String {
+        substitute(self.get(key), args)
+    }
+
+    /// Returns the number of messages this catalog overrides.
+    pub fn len(&self) -> usize {
+        self.overrides.len()
+    }
+
+    /// Returns whether this catalog has only the English messages.
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+}
+
+//
+// Internal - the English text for key.
+//
+fn english(key: &str) -> Option<&'static str> {
+    ENGLISH.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v)
+}
+
+//
+// Internal - replaces each `{n}` in message with args[n].
+//
+fn substitute(message: &str, args: &[&str]) -> String {
+    let mut out = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let arg = after.find('}')
+            .and_then(|close| after[..close].parse::<usize>().ok().map(|n| (n, close)))
+            .and_then(|(n, close)| args.get(n).map(|a| (a, close)));
+        match arg {
+            Some((a, close)) => {
+                out.push_str(a);
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+//
+// Internal - turns `\n` and `\\` in a catalog value into what they stand for.
+//
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const TEST_CATALOG: &str = "\
+        # A test translation\n\
+        \n\
+        error.general = mazegame: ERROR: {0}\n\
+        cli.saved=Wrote {2} ({0} by {1})\n\
+        usage.perfect = line one\\nline two\n\
+        extra.key = kept\n";
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_load_catalog() {
+        let path = ::std::env::temp_dir().join(format!("mazegame_messages_{}.lang", ::std::process::id()));
+        ::std::fs::write(&path, TEST_CATALOG).unwrap();
+        let catalog = MessageCatalog::load(&path).unwrap();
+        ::std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(4, catalog.len());
+        assert_eq!("line one\nline two", catalog.get("usage.perfect"));
+        assert_eq!("kept", catalog.get("extra.key"));
+        assert!(MessageCatalog::load("missing.lang").is_err());
+        assert_eq!(Err(MazeError::Parse("line 2: expected key = value".to_string())),
+                   MessageCatalog::parse("a = b\nnot a pair\n"));
+        assert!(MessageCatalog::parse(" = value").is_err());
+    }
+
+    #[test]
+    fn test_parameterized_error() {
+        let catalog = MessageCatalog::parse(TEST_CATALOG).unwrap();
+        let err = MazeError::OutOfBounds(9, 9).to_string();
+        assert_eq!("mazegame: ERROR: (9, 9) is outside of the maze", catalog.format("error.general", &[&err]));
+        // Arguments can appear in any order, and more than once
+        assert_eq!("Wrote out.bin (8 by 6)", catalog.format("cli.saved", &["8", "6", "out.bin"]));
+        assert_eq!("a-b-a", substitute("{0}-{1}-{0}", &["a", "b"]));
+        // Placeholders that can't be filled are left alone
+        assert_eq!("{1} {x} {", substitute("{1} {x} {", &["a"]));
+    }
+
+    #[test]
+    fn test_fallback() {
+        let catalog = MessageCatalog::parse(TEST_CATALOG).unwrap();
+        assert_eq!("Path length: 6", catalog.format("cli.path_length", &["6"]));
+        assert_eq!("no maze file given", catalog.get("cli.no_maze_file"));
+        assert_eq!("no.such.key", catalog.get("no.such.key"));
+
+        // English has every key, once
+        let english = MessageCatalog::english();
+        assert!(english.is_empty());
+        let mut keys: Vec<&str> = ENGLISH.iter().map(|&(k, _)| k).collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(ENGLISH.len(), keys.len());
+        for &(key, text) in ENGLISH.iter() {
+            assert_eq!(text, english.get(key));
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! The whole chain a maze goes through before it's played - generate, place
+//! the entrance and exit, solve, render - run end to end on a seeded config,
+//! with checks that each stage agrees with the others.  A change to one
+//! stage that breaks an assumption another relies on shows up as a failed
+//! check, rather than as a strange maze much later.
+use alloc::fmt;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::MazeError;
+use crate::maze::analysis::MazeStats;
+use crate::maze::{Maze, Opening};
+use crate::rng;
+
+/// The smallest Square from_window() will draw, in pixels.  Asking for
+/// smaller ones gets this size instead.
+pub const MIN_CELL_PX: u32 = 4;
+
+/// The most Squares from_window() will fit along either side of a window,
+/// so a huge window size can't ask for a maze too big to build.  A 4K
+/// screen of the smallest Squares fits.
+pub const MAX_WINDOW_SQUARES: u32 = 1000;
+
+/// The settings for one run of the pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunConfig {
+    pub width: u32,
+    pub height: u32,
+    pub seed: u64,
+    /// The rooms to add, as (count, min width, max width, min height, max
+    /// height) like Maze::generate() takes.  A count of 0 makes a perfect
+    /// maze.
+    pub rooms: (u32, u32, u32, u32, u32),
+}
+
+impl RunConfig {
+    /// Creates a config for a perfect maze of the given size.
+    pub fn new(width: u32, height: u32, seed: u64) -> RunConfig {
+        RunConfig { width, height, seed, rooms: (0, 0, 0, 0, 0) }
+    }
+
+    /// Creates a config for the biggest perfect maze whose Squares, cell_px
+    /// pixels each way, fit in a window of width_px by height_px.  Pixels
+    /// left over after the last whole Square go unused.  The seed is 0; set
+    /// it afterwards.
+    ///
+    /// # Example
+    /// ```
+    /// use mazegame::pipeline::RunConfig;
+    ///
+    /// let config = RunConfig::from_window(1280, 720, 24).unwrap();
+    /// assert_eq!((53, 30), (config.width, config.height));
+    /// assert!(RunConfig::from_window(40, 40, 24).is_err());
+    /// ```
+    pub fn from_window(width_px: u32, height_px: u32, cell_px: u32) -> Result<RunConfig, MazeError> {
+        RunConfig::from_window_with_hud(width_px, height_px, cell_px, 0)
+    }
+
+    /// Creates a config like from_window(), keeping hud_px pixels at the top
+    /// of the window free for a heads-up display.  A cell_px under
+    /// MIN_CELL_PX is raised to it.  Fails if fewer than 2x2 Squares fit, or
+    /// more than MAX_WINDOW_SQUARES along either side.
+    pub fn from_window_with_hud(width_px: u32, height_px: u32, cell_px: u32, hud_px: u32)
+                                -> Result<RunConfig, MazeError> {
+        let cell_px = cell_px.max(MIN_CELL_PX);
+        let (width, height) = (width_px / cell_px, height_px.saturating_sub(hud_px) / cell_px);
+        if width < 2 || height < 2 {
+            return Err(MazeError::InvalidArgument(
+                format!("a {}x{} window with a {}px HUD only fits {}x{} Squares of {}px; a maze needs at least 2x2",
+                        width_px, height_px, hud_px, width, height, cell_px)));
+        }
+        if width > MAX_WINDOW_SQUARES || height > MAX_WINDOW_SQUARES {
+            return Err(MazeError::InvalidArgument(
+                format!("a {}x{} window fits {}x{} Squares of {}px; a maze can be at most {} Squares each way",
+                        width_px, height_px, width, height, cell_px, MAX_WINDOW_SQUARES)));
+        }
+        Ok(RunConfig::new(width, height, 0))
+    }
+
+    /// Returns the (width, height) in pixels the maze fills when its Squares
+    /// are cell_px across, with hud_px above it, so a window made for
+    /// from_window_with_hud() can be checked against it.
+    pub fn window_size(&self, cell_px: u32, hud_px: u32) -> (u32, u32) {
+        let cell_px = cell_px.max(MIN_CELL_PX);
+        (self.width * cell_px, self.height * cell_px + hud_px)
+    }
+}
+
+/// A stage of the pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Generate,
+    PlaceOpenings,
+    Solve,
+    Render,
+    Save,
+}
+
+impl Stage {
+    /// Returns the stage's name, as used in reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Generate => "generate",
+            Stage::PlaceOpenings => "place entrance and exit",
+            Stage::Solve => "solve",
+            Stage::Render => "render",
+            Stage::Save => "save",
+        }
+    }
+}
+
+/// A check that the stages agree with each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+    /// The gaps in the rendered border are exactly the entrance and exits.
+    BorderGaps,
+    /// The solution is a walk from the entrance to the exit that passes
+    /// Maze::validate_moves().
+    Moves,
+    /// Counting the dead ends in the Maze read back from the render gives
+    /// the same number as the stats.
+    DeadEnds,
+    /// Saving and loading the Maze, in both the text and binary formats,
+    /// gives the same Maze.
+    RoundTrip,
+}
+
+impl Check {
+    /// Every check, in the order verify() runs them.
+    pub const ALL: [Check; 4] = [Check::BorderGaps, Check::Moves, Check::DeadEnds, Check::RoundTrip];
+
+    /// Returns the check's name, as used in reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            Check::BorderGaps => "border gaps",
+            Check::Moves => "moves",
+            Check::DeadEnds => "dead ends",
+            Check::RoundTrip => "round trip",
+        }
+    }
+}
+
+/// Why a run of the pipeline failed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PipelineError {
+    /// A stage returned an error.
+    Stage(Stage, MazeError),
+    /// The stages ran, but disagree.  Holds the check that failed and what
+    /// was different.
+    Check(Check, String),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PipelineError::Stage(stage, ref err) => write!(f, "{} stage failed: {}", stage.name(), err),
+            PipelineError::Check(check, ref msg) => write!(f, "{} check failed: {}", check.name(), msg),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PipelineError {}
+
+/// What a successful run of the pipeline produced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipelineReport {
+    pub config: RunConfig,
+    pub entrance: Opening,
+    pub exit: Opening,
+    /// The Squares on the solution, counting both ends.
+    pub solution_length: usize,
+    pub stats: MazeStats,
+    /// The Maze as render_ascii() draws it.
+    pub render: String,
+    /// The size of the Maze in the binary format.
+    pub saved_bytes: usize,
+    /// The checks that passed; all of them, since any failure is an error.
+    pub checks: Vec<Check>,
+}
+
+/// Runs the pipeline on config and checks the stages agree.  The maze is
+/// generated from the seed (with its rooms joined to the corridors), the
+/// entrance and exit go as far apart as they can on the outer wall, and the
+/// path between them is solved and rendered.  The same config always gives
+/// the same report.
+///
+/// # Example
+/// ```
+/// use mazegame::pipeline::{self, Check, RunConfig};
+///
+/// let report = pipeline::verify(&RunConfig::new(12, 8, 464)).unwrap();
+/// assert_eq!(Check::ALL.to_vec(), report.checks);
+/// assert_eq!(17, report.render.lines().count());
+/// assert!(pipeline::verify(&RunConfig::new(1, 1, 464)).is_err());
+/// ```
+pub fn verify(config: &RunConfig) -> Result<PipelineReport, PipelineError> {
+    let maze = generate(config).map_err(|err| PipelineError::Stage(Stage::Generate, err))?;
+    run_stages(config, maze)
+}
+
+//
+// Internal - runs every stage after generation on maze, and the checks.
+//
+fn run_stages(config: &RunConfig, mut maze: Maze) -> Result<PipelineReport, PipelineError> {
+    let stage = |stage: Stage| move |err: MazeError| PipelineError::Stage(stage, err);
+    let mismatch = |check: Check, msg: String| Err(PipelineError::Check(check, msg));
+
+    maze.place_entrance_exit_longest().map_err(stage(Stage::PlaceOpenings))?;
+    let (entrance, exit) = match (maze.get_entrance(), maze.get_exit()) {
+        (Some(entrance), Some(exit)) => (entrance, exit),
+        _ => return Err(PipelineError::Stage(Stage::PlaceOpenings,
+                                             MazeError::InvalidArgument("no entrance or exit was placed".to_string()))),
+    };
+    let solution = maze.solve(entrance.coord, exit.coord)
+        .ok_or(MazeError::Unreachable(entrance.coord, exit.coord))
+        .map_err(stage(Stage::Solve))?;
+    let stats = maze.stats();
+    let render = maze.render_ascii();
+    let drawn = Maze::from_ascii(&render).map_err(stage(Stage::Render))?;
+
+    // Border gaps
+    let mut recorded: Vec<Opening> = maze.get_entrance().into_iter().chain(maze.exits().iter().cloned()).collect();
+    recorded.sort_by_key(|o| (o.coord.y, o.coord.x, o.dir));
+    if drawn.exits() != &recorded[..] {
+        return mismatch(Check::BorderGaps, format!("the render has gaps at {}, but the openings are {}",
+                                                   describe(drawn.exits()), describe(&recorded)));
+    }
+
+    // Moves
+    if let Err(err) = maze.validate_moves(&solution) {
+        return mismatch(Check::Moves, err.to_string());
+    }
+    if solution.first() != Some(&entrance.coord) || solution.last() != Some(&exit.coord) {
+        return mismatch(Check::Moves, "the solution doesn't run from the entrance to the exit".to_string());
+    }
+
+    // Dead ends
+    let recount = drawn.dead_ends().len() as u32;
+    if recount != stats.dead_ends {
+        return mismatch(Check::DeadEnds, format!("the stats count {} but the render has {}", stats.dead_ends, recount));
+    }
+
+    // Round trip
+    let bytes = maze.to_bytes();
+    let loaded = Maze::from_bytes(&bytes).map_err(stage(Stage::Save))?;
+    if loaded != maze {
+        return mismatch(Check::RoundTrip, "the binary format loaded a different maze".to_string());
+    }
+    let loaded = Maze::from_text(&maze.to_text()).map_err(stage(Stage::Save))?;
+    if loaded != maze {
+        return mismatch(Check::RoundTrip, "the text format loaded a different maze".to_string());
+    }
+
+    Ok(PipelineReport {
+        config: *config,
+        entrance,
+        exit,
+        solution_length: solution.len(),
+        stats,
+        render,
+        saved_bytes: bytes.len(),
+        checks: Check::ALL.to_vec(),
+    })
+}
+
+//
+// Internal - the generate stage: a perfect maze, or one with rooms that are
+// then joined up, all from the config's seed.
+//
+fn generate(config: &RunConfig) -> Result<Maze, MazeError> {
+    if config.width == 0 || config.height == 0 {
+        return Err(MazeError::InvalidArgument(format!("a {}x{} maze has no Squares", config.width, config.height)));
+    }
+    let (count, _, max_w, _, max_h) = config.rooms;
+    // Rooms are kept a Square away from the outer wall
+    if count > 0 && (config.width < max_w + 2 || config.height < max_h + 2) {
+        return Err(MazeError::InvalidArgument(format!("rooms up to {}x{} don't fit in a {}x{} maze",
+                                                      max_w, max_h, config.width, config.height)));
+    }
+    let mut rng = rng::from_seed(config.seed);
+    let mut maze = Maze::new(config.height, config.width);
+    if config.rooms.0 > 0 {
+        maze.generate_with_rng(config.rooms, &mut rng).map_err(MazeError::InvalidArgument)?;
+        maze.connect_regions_with_rng(&mut rng);
+    } else {
+        maze.generate_perfect_with_rng(&mut rng).map_err(MazeError::InvalidArgument)?;
+    }
+    Ok(maze)
+}
+
+//
+// Internal - lists openings as `(x, y) dir`, for error messages.
+//
+fn describe(openings: &[Opening]) -> String {
+    let list: Vec<String> = openings.iter()
+        .map(|o| format!("({}, {}) {}", o.coord.x, o.coord.y, crate::trace::dir_name(o.dir)))
+        .collect();
+    if list.is_empty() { "none".to_string() } else { list.join(", ") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    #[test]
+    fn test_seeds_and_sizes() {
+        for &(width, height) in [(2, 2), (5, 5), (12, 7), (20, 20), (3, 30)].iter() {
+            for seed in 0..8 {
+                let config = RunConfig::new(width, height, seed);
+                let report = verify(&config).unwrap_or_else(|e| panic!("{:?}: {}", config, e));
+                assert_eq!(Check::ALL.to_vec(), report.checks);
+                assert_eq!(report, verify(&config).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_one_wide() {
+        // A single row or column is one corridor: the solution runs its
+        // whole length and its ends are the only dead ends
+        for &(width, height) in [(1, 2), (1, 15), (15, 1), (2, 1)].iter() {
+            let report = verify(&RunConfig::new(width, height, 464)).unwrap();
+            assert_eq!((width * height) as usize, report.solution_length);
+            assert_eq!(2, report.stats.dead_ends);
+        }
+    }
+
+    #[test]
+    fn test_from_window() {
+        // Exact division
+        let config = RunConfig::from_window(1280, 720, 16).unwrap();
+        assert_eq!(RunConfig::new(80, 45, 0), config);
+        assert_eq!((1280, 720), config.window_size(16, 0));
+
+        // Leftover pixels round down to whole Squares
+        let config = RunConfig::from_window(1280, 720, 24).unwrap();
+        assert_eq!((53, 30), (config.width, config.height));
+        assert_eq!((1272, 720), config.window_size(24, 0));
+        let config = RunConfig::from_window(71, 47, 23).unwrap();
+        assert_eq!((3, 2), (config.width, config.height));
+
+        // The HUD comes off the height
+        let config = RunConfig::from_window_with_hud(1280, 720, 24, 48).unwrap();
+        assert_eq!((53, 28), (config.width, config.height));
+        assert_eq!((1272, 720), config.window_size(24, 48));
+        assert!(RunConfig::from_window_with_hud(100, 100, 10, 1000).is_err());
+
+        // Tiny cells are raised to the minimum
+        let config = RunConfig::from_window(40, 20, 1).unwrap();
+        assert_eq!((10, 5), (config.width, config.height));
+
+        // Under 2x2 is too small
+        assert!(RunConfig::from_window(47, 720, 24).is_err());
+        assert!(RunConfig::from_window_with_hud(48, 100, 24, 60).is_err());
+        assert!(RunConfig::from_window(48, 48, 24).is_ok());
+
+        // So is a window too big to fill with a maze
+        assert!(RunConfig::from_window(4000, 4000, 4).is_ok());
+        assert!(RunConfig::from_window(4004, 40, 4).is_err());
+        assert!(RunConfig::from_window(100_000, 100_000, 4).is_err());
+        match RunConfig::from_window(0, 0, 0) {
+            Err(MazeError::InvalidArgument(msg)) => assert!(msg.contains("0x0 Squares of 4px"), "{}", msg),
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dungeons() {
+        for seed in 0..10 {
+            let config = RunConfig { rooms: (8, 2, 5, 2, 5), ..RunConfig::new(30, 20, seed) };
+            let report = verify(&config).unwrap_or_else(|e| panic!("{:?}: {}", config, e));
+            assert!(report.solution_length >= 2);
+        }
+        // Rooms packed into a small maze
+        let config = RunConfig { rooms: (6, 2, 3, 2, 3), ..RunConfig::new(10, 10, 7) };
+        assert!(verify(&config).is_ok());
+    }
+
+    #[test]
+    fn test_failures() {
+        assert!(matches!(verify(&RunConfig::new(0, 5, 1)), Err(PipelineError::Stage(Stage::Generate, _))));
+        assert!(matches!(verify(&RunConfig::new(1, 1, 1)), Err(PipelineError::Stage(Stage::Generate, _))));
+        let config = RunConfig { rooms: (1, 2, 4, 2, 4), ..RunConfig::new(9, 5, 1) };
+        assert!(matches!(verify(&config), Err(PipelineError::Stage(Stage::Generate, _))));
+
+        // A gap in the outer wall that isn't an entrance or exit
+        let config = RunConfig::new(4, 4, 3);
+        let mut maze = generate(&config).unwrap();
+        let offset = maze.index_of(1, 0).unwrap();
+        maze.sq[offset].break_wall(constants::DIR_NORTH);
+        match run_stages(&config, maze) {
+            Err(PipelineError::Check(Check::BorderGaps, msg)) => assert!(msg.contains("(1, 0)"), "{}", msg),
+            other => panic!("{:?}", other),
+        }
+
+        // A wall broken on one side only draws as open, so the Maze read
+        // back from the render has a dead end the stats don't
+        let mut maze = Maze::from_compact("4ccc8").unwrap();
+        maze.sq[1].build_wall(constants::DIR_WEST);
+        match run_stages(&RunConfig::new(5, 1, 0), maze) {
+            Err(PipelineError::Check(Check::DeadEnds, _)) => {}
+            other => panic!("{:?}", other),
+        }
+        let err = PipelineError::Check(Check::Moves, "oops".to_string());
+        assert_eq!("moves check failed: oops", err.to_string());
+    }
+}
@@ -0,0 +1,47 @@
+//! The types most code that uses the crate needs, gathered in one place:
+//!
+//! ```
+//! use mazegame::prelude::*;
+//!
+//! let mut maze = Maze::new(10, 10);
+//! maze.generate_perfect_seeded(1).unwrap();
+//! let path = maze.solve(Coord::new(0, 0), Coord::new(9, 9)).unwrap();
+//! assert_eq!(Coord::new(9, 9), *path.last().unwrap());
+//! ```
+//!
+//! Generating and solving are methods on Maze, so importing Maze is enough
+//! to use them.  Transformable is here so its methods can be called without
+//! importing it separately.  The four `DIR_*` constants of a square maze and
+//! ID_MAZE_PATH are included; everything else in `constants`, like the hex
+//! and theta directions, is imported from there.
+pub use crate::constants::{Direction, DIR_EAST, DIR_NORTH, DIR_SOUTH, DIR_WEST, ID_MAZE_PATH};
+pub use crate::error::MazeError;
+pub use crate::maze::room::{Door, Room};
+#[cfg(feature = "serde")]
+pub use crate::maze::spec::{MazeSpec, RoomSpec};
+pub use crate::maze::transform::Transformable;
+pub use crate::maze::{Coord, Maze, Opening, Square, Surface, Topology};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_paths() {
+        // Each deep path still names the same item as the prelude.  The
+        // assignments only compile if the types match.
+        let square: Square = crate::maze::square::Square::new();
+        let _: crate::maze::Square = square;
+        let _: Maze = crate::Maze::new(2, 2);
+        let _: Coord = crate::maze::Coord::new(0, 0);
+        let _: MazeError = crate::error::MazeError::Cancelled;
+        let _: MazeError = crate::MazeError::Cancelled;
+        let _: Direction = crate::constants::Direction::North;
+        let _: Option<Room> = Maze::new(2, 2).room(1).map(|r: crate::maze::room::Room| r);
+        let _: fn(&Maze) -> Maze = crate::maze::transform::Transformable::rotate_cw;
+        let _: fn(&Maze) -> Maze = Transformable::rotate_cw;
+        assert_eq!(DIR_EAST, crate::maze::constants::DIR_EAST);
+        assert_eq!(DIR_NORTH, crate::maze::square::constants::DIR_NORTH);
+        assert_eq!(ID_MAZE_PATH, crate::constants::ID_MAZE_PATH);
+    }
+}
@@ -0,0 +1,213 @@
+//! Golden file comparisons for tests, enabled with the `test-fixtures`
+//! feature (and always available to this crate's own tests).
+//!
+//! assert_matches_fixture() compares text against a file under
+//! `tests/fixtures/` in the crate being tested.  When they differ, the new
+//! text is written next to the fixture with `.new` added to its name, and
+//! the panic message shows which lines changed.  Running the tests with
+//! `MAZEGAME_BLESS=1` set writes every fixture from what the tests produce
+//! instead, for when the output was meant to change:
+//!
+//! ```text
+//! MAZEGAME_BLESS=1 cargo test
+//! git diff tests/fixtures
+//! ```
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The environment variable that turns on bless mode.
+pub const BLESS_VAR: &str = "MAZEGAME_BLESS";
+
+/// What happened to a fixture that didn't fail its check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixtureOutcome {
+    /// The text matched the fixture.
+    Matched,
+    /// Bless mode wrote the text as the fixture.
+    Blessed,
+}
+
+/// Returns the directory fixtures are read from: `tests/fixtures` under the
+/// crate cargo is testing.
+pub fn fixture_dir() -> PathBuf {
+    let root = env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+    root.join("tests").join("fixtures")
+}
+
+/// Returns whether bless mode is on, that is whether BLESS_VAR is set to
+/// anything other than empty or `0`.
+pub fn blessing() -> bool {
+    env::var(BLESS_VAR).is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Panics unless actual is the same as the fixture called name, writing
+/// `<name>.new` and showing what changed if it isn't.  In bless mode the
+/// fixture is written from actual instead.
+pub fn assert_matches_fixture(name: &str, actual: &str) {
+    if let Err(message) = check_fixture(&fixture_dir(), name, actual, blessing()) {
+        panic!("{}", message);
+    }
+}
+
+/// Checks actual against the fixture called name in dir.  On a mismatch,
+/// or when there's no fixture yet, actual is written to `<name>.new` and
+/// the error describes the difference.  With bless set, actual is written
+/// to the fixture itself.  A leftover `.new` file is removed whenever the
+/// check passes.
+///
+/// # Example
+/// ```
+/// use mazegame::testing::{check_fixture, FixtureOutcome};
+///
+/// let dir = std::env::temp_dir().join(format!("mazegame_fixture_doc_{}", std::process::id()));
+/// assert_eq!(Ok(FixtureOutcome::Blessed), check_fixture(&dir, "maze.txt", "XXX\n", true));
+/// assert_eq!(Ok(FixtureOutcome::Matched), check_fixture(&dir, "maze.txt", "XXX\n", false));
+/// assert!(check_fixture(&dir, "maze.txt", "X X\n", false).is_err());
+/// std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn check_fixture(dir: &Path, name: &str, actual: &str, bless: bool) -> Result<FixtureOutcome, String> {
+    let path = dir.join(name);
+    let new_path = dir.join(format!("{}.new", name));
+    let io_error = |e: io::Error, p: &Path| format!("fixture {}: {}", p.display(), e);
+
+    if bless {
+        fs::create_dir_all(dir).map_err(|e| io_error(e, dir))?;
+        fs::write(&path, actual).map_err(|e| io_error(e, &path))?;
+        remove_if_present(&new_path).map_err(|e| io_error(e, &new_path))?;
+        return Ok(FixtureOutcome::Blessed);
+    }
+
+    let expected = match fs::read_to_string(&path) {
+        Ok(expected) => Some(expected),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(io_error(e, &path)),
+    };
+    if expected.as_deref() == Some(actual) {
+        remove_if_present(&new_path).map_err(|e| io_error(e, &new_path))?;
+        return Ok(FixtureOutcome::Matched);
+    }
+
+    fs::create_dir_all(dir).map_err(|e| io_error(e, dir))?;
+    fs::write(&new_path, actual).map_err(|e| io_error(e, &new_path))?;
+    Err(match expected {
+        None => format!("there's no fixture {}; wrote the output to {}\n\
+                         Rerun with {}=1 to keep it", path.display(), new_path.display(), BLESS_VAR),
+        Some(expected) => format!("output doesn't match fixture {}; wrote it to {}\n\
+                                   Rerun with {}=1 if the change is intended\n{}",
+                                  path.display(), new_path.display(), BLESS_VAR, line_diff(&expected, actual)),
+    })
+}
+
+//
+// Internal - lists the lines that differ between expected and actual, as
+// `-` for the fixture's line and `+` for the new one, with line numbers.
+//
+fn line_diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.split('\n').collect();
+    let new: Vec<&str> = actual.split('\n').collect();
+    let mut out = String::new();
+    for i in 0..old.len().max(new.len()) {
+        let (a, b) = (old.get(i), new.get(i));
+        if a == b {
+            continue;
+        }
+        out.push_str(&format!("line {}:\n", i + 1));
+        match a {
+            Some(line) => out.push_str(&format!("- {:?}\n", line)),
+            None => out.push_str("- (missing)\n"),
+        }
+        match b {
+            Some(line) => out.push_str(&format!("+ {:?}\n", line)),
+            None => out.push_str("+ (missing)\n"),
+        }
+    }
+    out
+}
+
+//
+// Internal - removes the file at path if there is one.
+//
+fn remove_if_present(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory for one test, removed when it's dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let dir = env::temp_dir().join(format!("mazegame_fixtures_{}_{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let dir = TempDir::new("compare");
+        let path = dir.0.join("maze.txt");
+        let new_path = dir.0.join("maze.txt.new");
+
+        // No fixture yet: the output is kept for review, not blessed
+        let err = check_fixture(&dir.0, "maze.txt", "XXX\nX X\n", false).unwrap_err();
+        assert!(err.contains("there's no fixture"));
+        assert!(!path.exists());
+        assert_eq!("XXX\nX X\n", fs::read_to_string(&new_path).unwrap());
+
+        fs::write(&path, "XXX\nX X\nXXX\n").unwrap();
+        let err = check_fixture(&dir.0, "maze.txt", "XXX\nX.X\nXXX\n", false).unwrap_err();
+        assert!(err.contains("line 2:\n- \"X X\"\n+ \"X.X\"\n"), "{}", err);
+        assert!(!err.contains("line 1:"));
+        assert_eq!("XXX\nX.X\nXXX\n", fs::read_to_string(&new_path).unwrap());
+        // The fixture itself is never touched outside of bless mode
+        assert_eq!("XXX\nX X\nXXX\n", fs::read_to_string(&path).unwrap());
+
+        // Matching cleans up the stale .new
+        assert_eq!(Ok(FixtureOutcome::Matched), check_fixture(&dir.0, "maze.txt", "XXX\nX X\nXXX\n", false));
+        assert!(!new_path.exists());
+    }
+
+    #[test]
+    fn test_bless() {
+        let dir = TempDir::new("bless");
+        let path = dir.0.join("out.svg");
+        assert!(check_fixture(&dir.0, "out.svg", "<svg/>\n", false).is_err());
+        assert_eq!(Ok(FixtureOutcome::Blessed), check_fixture(&dir.0, "out.svg", "<svg/>\n", true));
+        assert_eq!("<svg/>\n", fs::read_to_string(&path).unwrap());
+        assert!(!dir.0.join("out.svg.new").exists());
+
+        // Blessing again overwrites
+        assert_eq!(Ok(FixtureOutcome::Blessed), check_fixture(&dir.0, "out.svg", "<svg></svg>\n", true));
+        assert_eq!(Ok(FixtureOutcome::Matched), check_fixture(&dir.0, "out.svg", "<svg></svg>\n", false));
+    }
+
+    #[test]
+    fn test_line_diff() {
+        assert_eq!("", line_diff("a\nb\n", "a\nb\n"));
+        assert_eq!("line 3:\n- \"\"\n+ \"c\"\nline 4:\n- (missing)\n+ \"\"\n", line_diff("a\nb\n", "a\nb\nc\n"));
+        assert_eq!("line 1:\n- \"x\"\n+ \"y\"\n", line_diff("x", "y"));
+    }
+
+    #[test]
+    fn test_fixture_dir() {
+        assert!(fixture_dir().ends_with("tests/fixtures"));
+        assert!(fixture_dir().is_dir());
+    }
+}
@@ -0,0 +1,174 @@
+//! Generation tracing through the `log` facade, enabled with the `log`
+//! feature.  Nothing is printed unless the application installs a logger.
+//!
+//! Every event is logged with the `mazegame::gen` target, as `key=value`
+//! pairs so a log can be parsed and replayed:
+//!
+//! ```text
+//! TRACE carve x=3 y=4 dir=e id=-1          a wall was knocked down
+//! TRACE carve_under x=2 y=3 dir=s          a passage tunneled under (x, y+1)
+//! TRACE carve_one_way x=1 y=0 dir=e        a one-way passage was opened
+//! TRACE carve_secret x=0 y=2 dir=s         a secret passage was opened
+//! TRACE room id=2 x=5 y=1 w=3 h=4          a room was placed
+//! DEBUG room_rejected x=2 y=2 w=4 h=3 reason=overlap
+//! DEBUG growing_tree x=0 y=0 carved=99 backtracks=61
+//! DEBUG hex_growing_tree q=0 r=0 carved=61 backtracks=40
+//! DEBUG theta_growing_tree ring=0 index=0 carved=91 backtracks=55
+//! DEBUG rooms requested=6 placed=4 rejected=2
+//! DEBUG braid chance=0.3 removed=12
+//! DEBUG caves fill=0.45 passes=4 caverns=7 carved=212
+//! DEBUG connect_regions regions=9 joined=8
+//! DEBUG symmetric symmetry=MirrorX carved=51 stitched=2
+//! DEBUG masked regions=2 carved=58
+//! DEBUG stamp x=4 y=2 w=3 h=3 mode=Merge   a prefab was stamped in
+//! DEBUG solution attempt=1 length=64 exit_moved=true
+//! DEBUG solution_too_short attempt=1 longest=31
+//! DEBUG cancelled                          generation was stopped
+//! DEBUG stairs z=0 placed=2                stairs up from level 0
+//! WARN  rooms_exhausted requested=6 placed=4
+//! ```
+//!
+//! Directions are written as `n`, `s`, `e` and `w`.
+
+/// The log target used for every generation event.
+pub const TARGET: &str = "mazegame::gen";
+
+/// Returns the short name used for a direction in log events.
+pub fn dir_name(dir: u32) -> &'static str {
+    match dir {
+        crate::constants::DIR_NORTH => "n",
+        crate::constants::DIR_SOUTH => "s",
+        crate::constants::DIR_EAST => "e",
+        crate::constants::DIR_WEST => "w",
+        _ => "?",
+    }
+}
+
+// Logs a generation event at the given level.  Without the `log` feature the
+// arguments are still type checked, but nothing is formatted.
+macro_rules! gen_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        log!(target: $crate::trace::TARGET, ::log::Level::$level, $($arg)+);
+        #[cfg(not(feature = "log"))]
+        {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
+
+macro_rules! gen_trace {
+    ($($arg:tt)+) => { gen_event!(Trace, $($arg)+) };
+}
+
+macro_rules! gen_debug {
+    ($($arg:tt)+) => { gen_event!(Debug, $($arg)+) };
+}
+
+macro_rules! gen_warn {
+    ($($arg:tt)+) => { gen_event!(Warn, $($arg)+) };
+}
+
+#[cfg(all(test, feature = "log"))]
+mod tests {
+    use std::cell::RefCell;
+    use std::sync::Once;
+
+    use log::{self, Level, Log, Metadata, Record};
+
+    use crate::constants;
+    use crate::maze::Maze;
+
+    // Collects the current thread's generation events, so tests running in
+    // parallel don't see each other's.
+    struct CaptureLogger;
+
+    thread_local! {
+        static EVENTS: RefCell<Vec<(Level, String)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.target() == super::TARGET
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                EVENTS.with(|e| e.borrow_mut().push((record.level(), format!("{}", record.args()))));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger;
+    static INIT: Once = Once::new();
+
+    fn capture<F: FnOnce()>(f: F) -> Vec<(Level, String)> {
+        INIT.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        EVENTS.with(|e| e.borrow_mut().clear());
+        f();
+        EVENTS.with(|e| e.borrow_mut().drain(..).collect())
+    }
+
+    fn field(event: &str, key: &str) -> u32 {
+        let prefix = format!("{}=", key);
+        event.split(' ').find(|f| f.starts_with(&prefix)).unwrap()[prefix.len()..].parse().unwrap()
+    }
+
+    fn open_passages(maze: &Maze) -> usize {
+        // Count each wall from its west or north side only
+        maze.sq.chunks(maze.get_cols() as usize).enumerate().map(|(y, row)| {
+            row.iter().enumerate().filter(|&(x, sq)| (x as u32) < maze.get_cols() - 1 &&
+                                                     !sq.is_wall_present(constants::DIR_EAST)).count() +
+            row.iter().filter(|sq| (y as u32) < maze.get_rows() - 1 &&
+                                   !sq.is_wall_present(constants::DIR_SOUTH)).count()
+        }).sum()
+    }
+
+    #[test]
+    fn test_carve_events_match_passages() {
+        let mut maze = Maze::new(12, 15);
+        let events = capture(|| maze.generate_seeded((4, 2, 4, 2, 4), 31).unwrap());
+        let carves: Vec<&String> = events.iter().filter(|e| e.0 == Level::Trace && e.1.starts_with("carve "))
+                                         .map(|e| &e.1).collect();
+        assert_eq!(open_passages(&maze), carves.len());
+
+        // Replaying the carves onto an empty maze reproduces the walls
+        let mut replayed = Maze::new(12, 15);
+        for event in carves {
+            let dir = match event.split(' ').find(|f| f.starts_with("dir=")).unwrap() {
+                "dir=n" => constants::DIR_NORTH,
+                "dir=s" => constants::DIR_SOUTH,
+                "dir=e" => constants::DIR_EAST,
+                _ => constants::DIR_WEST,
+            };
+            replayed.carve(field(event, "x"), field(event, "y"), dir, constants::ID_MAZE_PATH, false).unwrap();
+        }
+        for (a, b) in maze.sq.iter().zip(replayed.sq.iter()) {
+            assert_eq!(a.wall_present, b.wall_present);
+        }
+    }
+
+    #[test]
+    fn test_summaries() {
+        let mut maze = Maze::new(10, 10);
+        let events = capture(|| maze.generate_perfect_seeded(5).unwrap());
+        let summary = events.iter().find(|e| e.1.starts_with("growing_tree ")).unwrap();
+        assert_eq!(Level::Debug, summary.0);
+        assert_eq!(100, field(&summary.1, "carved"));
+
+        // Asking for far more rooms than fit warns about it
+        let mut maze = Maze::new(8, 8);
+        let events = capture(|| maze.generate_seeded((40, 3, 4, 3, 4), 2).unwrap());
+        let placed = events.iter().filter(|e| e.1.starts_with("room ")).count() as u32;
+        let rejected = events.iter().filter(|e| e.1.starts_with("room_rejected ")).count() as u32;
+        assert_eq!(40, placed + rejected);
+        let warning = events.iter().find(|e| e.0 == Level::Warn).unwrap();
+        assert!(warning.1.starts_with("rooms_exhausted "));
+        assert_eq!(placed, field(&warning.1, "placed"));
+    }
+}
@@ -0,0 +1,143 @@
+//! WebAssembly bindings, enabled with the `wasm` feature.
+//!
+//! Mazes cross the JS boundary as the JSON produced by `Maze::to_json()`.
+//! Everything here uses the seeded generators and returns its results rather
+//! than printing them, so nothing depends on an OS random source or stdout.
+use wasm_bindgen::prelude::*;
+
+use crate::error::MazeError;
+use crate::maze::render::WallGrid;
+use crate::maze::{Coord, Maze};
+
+/// The size of a Square, in pixels, in mazes drawn by render_svg().
+const SVG_CELL_SIZE: u32 = 16;
+
+/// What generate() hands back to JS.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeneratedMaze {
+    pub cols: u32,
+    pub rows: u32,
+    /// The wall layout, ready to draw.
+    pub grid: WallGrid,
+    /// The maze itself, to pass back into solve() and render_svg().
+    pub maze: String,
+}
+
+/// Generates a perfect maze of the given size from a seed.
+pub fn generate_maze(width: u32, height: u32, seed: u32) -> Result<GeneratedMaze, MazeError> {
+    if width == 0 || height == 0 {
+        return Err(MazeError::InvalidArgument("mazes must be at least 1x1".to_string()));
+    }
+    let mut maze = Maze::new(height, width);
+    maze.generate_perfect_seeded(seed as u64).map_err(MazeError::InvalidArgument)?;
+    Ok(GeneratedMaze {
+        cols: width,
+        rows: height,
+        grid: maze.to_wall_grid(),
+        maze: maze.to_json()?,
+    })
+}
+
+/// Finds the shortest path between two Squares of a JSON maze.
+pub fn solve_maze(maze_json: &str, sx: u32, sy: u32, gx: u32, gy: u32) -> Result<Option<Vec<[u32; 2]>>, MazeError> {
+    let maze = Maze::from_json(maze_json)?;
+    for &(x, y) in &[(sx, sy), (gx, gy)] {
+        if !maze.in_bounds(x, y) {
+            return Err(MazeError::OutOfBounds(x, y));
+        }
+    }
+    Ok(maze.solve(Coord::new(sx, sy), Coord::new(gx, gy))
+           .map(|path| path.iter().map(|c| [c.x, c.y]).collect()))
+}
+
+/// Draws a JSON maze as an SVG image.
+pub fn render_maze_svg(maze_json: &str) -> Result<String, MazeError> {
+    Ok(Maze::from_json(maze_json)?.render_svg(SVG_CELL_SIZE))
+}
+
+/// Generates a perfect maze, returning `{ cols, rows, grid: { width, height, walls }, maze }`.
+#[wasm_bindgen]
+pub fn generate(width: u32, height: u32, seed: u32) -> Result<JsValue, JsValue> {
+    let generated = generate_maze(width, height, seed).map_err(to_js_error)?;
+    ::serde_wasm_bindgen::to_value(&generated).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Solves a maze returned by generate(), returning an array of `[x, y]`
+/// pairs, or null when the goal can't be reached.
+#[wasm_bindgen]
+pub fn solve(maze_json: &str, sx: u32, sy: u32, gx: u32, gy: u32) -> Result<JsValue, JsValue> {
+    match solve_maze(maze_json, sx, sy, gx, gy).map_err(to_js_error)? {
+        Some(path) => ::serde_wasm_bindgen::to_value(&path).map_err(|e| JsValue::from_str(&e.to_string())),
+        None => Ok(JsValue::NULL),
+    }
+}
+
+/// Draws a maze returned by generate() as an SVG document.
+#[wasm_bindgen]
+pub fn render_svg(maze_json: &str) -> Result<String, JsValue> {
+    render_maze_svg(maze_json).map_err(to_js_error)
+}
+
+//
+// Internal - converts a MazeError into an exception for JS.
+//
+fn to_js_error(err: MazeError) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_is_seeded() {
+        let a = generate_maze(12, 8, 5).unwrap();
+        assert_eq!(a, generate_maze(12, 8, 5).unwrap());
+        assert_ne!(a.maze, generate_maze(12, 8, 6).unwrap().maze);
+        assert_eq!((25, 17), (a.grid.width, a.grid.height));
+        assert!(generate_maze(0, 8, 5).is_err());
+    }
+
+    #[test]
+    fn test_generate_solve_round_trip() {
+        let generated = generate_maze(12, 8, 5).unwrap();
+        let path = solve_maze(&generated.maze, 0, 0, 11, 7).unwrap().unwrap();
+        assert_eq!([0, 0], path[0]);
+        assert_eq!([11, 7], path[path.len() - 1]);
+
+        // Every step of the path crosses an open wall in the exported grid
+        for step in path.windows(2) {
+            let wall_x = step[0][0] + step[1][0] + 1;
+            let wall_y = step[0][1] + step[1][1] + 1;
+            assert!(!generated.grid.is_wall(wall_x, wall_y));
+        }
+        assert!(solve_maze(&generated.maze, 0, 0, 12, 7).is_err());
+        assert!(solve_maze("not json", 0, 0, 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let generated = generate_maze(4, 4, 1).unwrap();
+        assert!(render_maze_svg(&generated.maze).unwrap().starts_with("<svg"));
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_js_generate_solve_round_trip() {
+        let value = generate(10, 10, 99).unwrap();
+        let generated: GeneratedMaze = ::serde_wasm_bindgen::from_value(value).unwrap();
+        assert_eq!(21 * 21, generated.grid.walls.len());
+
+        let path = solve(&generated.maze, 0, 0, 9, 9).unwrap();
+        let path: Vec<[u32; 2]> = ::serde_wasm_bindgen::from_value(path).unwrap();
+        assert_eq!([0, 0], path[0]);
+        assert_eq!([9, 9], path[path.len() - 1]);
+
+        assert!(render_svg(&generated.maze).unwrap().contains("<line"));
+    }
+}